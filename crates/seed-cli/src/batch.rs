@@ -0,0 +1,273 @@
+//! Пакетная генерация миров по манифесту (`seed-cli batch`).
+//!
+//! Манифест — JSON со списком миров; для каждого — конфиг, необязательные
+//! seed и размер и набор выходов «вид → путь». Относительные пути
+//! отсчитываются от каталога манифеста. Миры обрабатываются пулом потоков,
+//! итоговый отчёт — по каждому миру: что записано, сколько заняло, ошибка.
+//!
+//! ```json
+//! { "worlds": [ {
+//!     "name": "alpha", "config": "world-config.json", "seed": 42,
+//!     "width": 1024, "height": 1024, "heightmapFormat": "png16",
+//!     "outputs": { "heightmap": "alpha/height.png", "worldview": "alpha/view.png" }
+//! } ] }
+//! ```
+
+use crate::dem::{save_heightmap, HeightmapFormat};
+use crate::progress::PhaseTimings;
+use crate::relief::{save_hillshade, save_normal_map, NormalConvention, Sun};
+use crate::{
+    geojson, save_biome_map_to_png, save_mesh, save_political_map_to_png, save_worldview_to_png,
+    summary, MeshColors,
+};
+use clap::ValueEnum;
+use seed_config::WorldConfig;
+use seed_core::{simulate_history, History, TerrainMesh, World};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Виды выходов, которые понимает манифест
+pub const OUTPUT_KINDS: [&str; 9] = [
+    "heightmap",
+    "biomes",
+    "worldview",
+    "political",
+    "normal",
+    "hillshade",
+    "mesh",
+    "vectors",
+    "summary",
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub worlds: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    /// Имя в отчёте; по умолчанию — номер в манифесте
+    pub name: Option<String>,
+    pub config: String,
+    /// Переопределяет `worldSeed` и `geology.heightmap.baseSeed`
+    pub seed: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub heightmap_format: Option<String>,
+    pub outputs: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub worlds: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub seconds: f64,
+    pub results: Vec<WorldResult>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldResult {
+    pub name: String,
+    pub config: String,
+    pub seed: u64,
+    pub width: u32,
+    pub height: u32,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub seconds: f64,
+    /// Записанные файлы: вид → путь
+    pub outputs: BTreeMap<String, String>,
+}
+
+pub fn load_manifest(path: &Path) -> anyhow::Result<Manifest> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read manifest '{}': {e}", path.display()))?;
+    let manifest: Manifest = serde_json::from_str(&text)
+        .map_err(|e| anyhow::anyhow!("invalid manifest '{}': {e}", path.display()))?;
+    for (i, entry) in manifest.worlds.iter().enumerate() {
+        if let Some(kind) = entry
+            .outputs
+            .keys()
+            .find(|k| !OUTPUT_KINDS.contains(&k.as_str()))
+        {
+            anyhow::bail!(
+                "worlds[{i}].outputs: unknown output '{kind}', expected one of {}",
+                OUTPUT_KINDS.join(", ")
+            );
+        }
+        if let Some(format) = &entry.heightmap_format {
+            HeightmapFormat::from_str(format, true)
+                .map_err(|e| anyhow::anyhow!("worlds[{i}].heightmapFormat: {e}"))?;
+        }
+    }
+    Ok(manifest)
+}
+
+/// Генерирует все миры манифеста пулом из `jobs` потоков; порядок
+/// результатов — как в манифесте
+pub fn run_batch(
+    manifest: &Manifest,
+    base_dir: &Path,
+    jobs: usize,
+    default_size: (u32, u32),
+) -> BatchReport {
+    let started = Instant::now();
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<WorldResult>>> =
+        Mutex::new((0..manifest.worlds.len()).map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, manifest.worlds.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(entry) = manifest.worlds.get(i) else {
+                    break;
+                };
+                let result = run_entry(i, entry, base_dir, default_size);
+                println!(
+                    "[{}/{}] {}: {} ({:.1} s)",
+                    i + 1,
+                    manifest.worlds.len(),
+                    result.name,
+                    result.error.as_deref().unwrap_or("ok"),
+                    result.seconds
+                );
+                results.lock().expect("batch results lock")[i] = Some(result);
+            });
+        }
+    });
+
+    let results: Vec<WorldResult> = results
+        .into_inner()
+        .expect("batch results lock")
+        .into_iter()
+        .flatten()
+        .collect();
+    let succeeded = results.iter().filter(|r| r.ok).count();
+    BatchReport {
+        worlds: results.len(),
+        succeeded,
+        failed: results.len() - succeeded,
+        seconds: started.elapsed().as_secs_f64(),
+        results,
+    }
+}
+
+fn run_entry(
+    index: usize,
+    entry: &ManifestEntry,
+    base_dir: &Path,
+    default_size: (u32, u32),
+) -> WorldResult {
+    let started = Instant::now();
+    let width = entry.width.unwrap_or(default_size.0);
+    let height = entry.height.unwrap_or(default_size.1);
+    let mut result = WorldResult {
+        name: entry
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("world-{}", index + 1)),
+        config: entry.config.clone(),
+        seed: entry.seed.unwrap_or_default(),
+        width,
+        height,
+        ok: false,
+        error: None,
+        seconds: 0.0,
+        outputs: BTreeMap::new(),
+    };
+    match generate_entry(entry, base_dir, width, height, &mut result) {
+        Ok(()) => result.ok = true,
+        Err(e) => result.error = Some(e.to_string()),
+    }
+    result.seconds = started.elapsed().as_secs_f64();
+    result
+}
+
+fn generate_entry(
+    entry: &ManifestEntry,
+    base_dir: &Path,
+    width: u32,
+    height: u32,
+    result: &mut WorldResult,
+) -> anyhow::Result<()> {
+    let config_path = resolve(base_dir, &entry.config);
+    let mut cfg = WorldConfig::from_file(&config_path)?;
+    if let Some(seed) = entry.seed {
+        cfg.world_seed = seed;
+        cfg.geology.heightmap.base_seed = seed;
+    }
+    result.seed = cfg.world_seed;
+
+    let world = World::with_map_size(&cfg, width, height)
+        .map_err(|e| anyhow::anyhow!("failed to construct world: {e}"))?;
+    let (hm, bm) = (world.heightmap(), world.biomes());
+    // история нужна не всем выходам и дорога — считается по первому запросу
+    let history_cell: OnceCell<History> = OnceCell::new();
+    let history = || history_cell.get_or_init(|| simulate_history(&cfg, hm, bm, cfg.world_seed));
+
+    let format = entry
+        .heightmap_format
+        .as_deref()
+        .and_then(|f| HeightmapFormat::from_str(f, true).ok())
+        .unwrap_or(HeightmapFormat::Png);
+
+    for (kind, out) in &entry.outputs {
+        let path = resolve(base_dir, out);
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let path_str = path.to_string_lossy().into_owned();
+        let p = path_str.as_str();
+        match kind.as_str() {
+            "heightmap" => save_heightmap(hm, &cfg, format, false, p)?,
+            "biomes" => save_biome_map_to_png(bm, &cfg, p)?,
+            "worldview" => save_worldview_to_png(hm, bm, &cfg, p)?,
+            "political" => save_political_map_to_png(hm, history(), &cfg, p)?,
+            "normal" => save_normal_map(hm, &cfg, 1.0, NormalConvention::Opengl, p)?,
+            "hillshade" => {
+                let sun = Sun {
+                    azimuth_deg: 315.0,
+                    altitude_deg: 45.0,
+                };
+                save_hillshade(hm, &cfg, 1.0, sun, p)?
+            }
+            "mesh" => {
+                let mesh = TerrainMesh::from_heightmap(&cfg, hm, 1.0);
+                save_mesh(&cfg, hm, bm, &mesh, MeshColors::Biome, false, p)?
+            }
+            "vectors" => {
+                let collection = geojson::build_vectors(&cfg, hm, history(), 0.75);
+                std::fs::write(p, serde_json::to_string(&collection)?)?
+            }
+            "summary" => {
+                let summary =
+                    summary::build_summary(&cfg, &world, width, height, &PhaseTimings::quiet());
+                std::fs::write(p, serde_json::to_string_pretty(&summary)?)?
+            }
+            _ => unreachable!("output kinds are checked when the manifest is loaded"),
+        }
+        result.outputs.insert(kind.clone(), path_str);
+    }
+    Ok(())
+}
+
+fn resolve(base_dir: &Path, path: &str) -> PathBuf {
+    let p = Path::new(path);
+    if p.is_absolute() {
+        p.to_path_buf()
+    } else {
+        base_dir.join(p)
+    }
+}
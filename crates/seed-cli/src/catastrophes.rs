@@ -0,0 +1,119 @@
+//! Лента катастроф (`seed-cli catastrophes`): события за N лет и, если они
+//! применены к рельефу, сводка изменений карты — в JSON для отладки и
+//! сравнения миров.
+
+use seed_config::WorldConfig;
+use seed_core::{BiomeMap, Catastrophe, Heightmap};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatastropheLog {
+    pub world_seed: u64,
+    pub years: f64,
+    pub applied: bool,
+    /// Число событий по типам
+    pub counts: BTreeMap<String, usize>,
+    pub events: Vec<CatastropheRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effects: Option<TerrainEffects>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatastropheRecord {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Год от начала симуляции
+    pub year: f64,
+    pub lat: f64,
+    pub lon: f64,
+    pub magnitude: f64,
+    pub radius_km: f64,
+    pub duration_hours: f64,
+    pub major: bool,
+}
+
+/// Что катастрофы сделали с картой
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerrainEffects {
+    pub cells_changed: usize,
+    pub max_raise_m: f64,
+    pub max_lower_m: f64,
+    pub land_cells_before: usize,
+    pub land_cells_after: usize,
+    pub biome_cells_changed: usize,
+}
+
+/// Лента в порядке времени; `effects` — только если события применены
+pub fn build_log(
+    cfg: &WorldConfig,
+    years: f64,
+    events: &[Catastrophe],
+    effects: Option<TerrainEffects>,
+) -> CatastropheLog {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for cat in events {
+        *counts
+            .entry(cat.catastrophe_type.id().to_string())
+            .or_default() += 1;
+    }
+    CatastropheLog {
+        world_seed: cfg.world_seed,
+        years,
+        applied: effects.is_some(),
+        counts,
+        events: events
+            .iter()
+            .map(|cat| CatastropheRecord {
+                id: cat.id.clone(),
+                kind: cat.catastrophe_type.id(),
+                year: cat.timestamp,
+                lat: cat.position.0,
+                lon: cat.position.1,
+                magnitude: cat.magnitude,
+                radius_km: cat.radius_km,
+                duration_hours: cat.duration_hours,
+                major: cat.is_major(),
+            })
+            .collect(),
+        effects,
+    }
+}
+
+/// Разница карт до и после катастроф
+pub fn compare(
+    cfg: &WorldConfig,
+    (hm_before, bm_before): (&Heightmap, &BiomeMap),
+    (hm_after, bm_after): (&Heightmap, &BiomeMap),
+) -> TerrainEffects {
+    let sea_level = cfg.sea_level as f32;
+    let mut effects = TerrainEffects {
+        cells_changed: 0,
+        max_raise_m: 0.0,
+        max_lower_m: 0.0,
+        land_cells_before: hm_before.values.iter().filter(|&&v| v > sea_level).count(),
+        land_cells_after: hm_after.values.iter().filter(|&&v| v > sea_level).count(),
+        biome_cells_changed: bm_before
+            .indices
+            .iter()
+            .zip(&bm_after.indices)
+            .filter(|(a, b)| a != b)
+            .count(),
+    };
+    for y in 0..hm_before.height {
+        for x in 0..hm_before.width {
+            let delta = hm_after.elevation_m(cfg.sea_level, x, y)
+                - hm_before.elevation_m(cfg.sea_level, x, y);
+            if delta != 0.0 {
+                effects.cells_changed += 1;
+            }
+            effects.max_raise_m = effects.max_raise_m.max(delta);
+            effects.max_lower_m = effects.max_lower_m.max(-delta);
+        }
+    }
+    effects
+}
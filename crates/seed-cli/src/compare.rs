@@ -0,0 +1,246 @@
+//! Сравнение двух миров (`seed-cli compare`): оба генерируются в одном
+//! разрешении, отчёт — один HTML-файл без внешних ресурсов: карты рядом,
+//! доля суши, покрытие биомов с разницей и гистограммы высот.
+
+use crate::export::base64;
+use seed_config::WorldConfig;
+use seed_core::{BiomeMap, Heightmap};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// Столбцов в гистограмме высот
+const HISTOGRAM_BINS: usize = 24;
+const COLOR_A: &str = "#3b73b9";
+const COLOR_B: &str = "#e08a2c";
+
+/// Один из сравниваемых миров
+pub struct Side<'a> {
+    /// Откуда конфиг (для заголовка)
+    pub label: &'a str,
+    pub cfg: &'a WorldConfig,
+    pub hm: &'a Heightmap,
+    pub bm: &'a BiomeMap,
+    /// Совмещённая карта мира
+    pub render: &'a image::RgbImage,
+}
+
+struct Stats {
+    land_fraction: f64,
+    /// Доля клеток по id биома; `water` — клетки без биома
+    coverage: BTreeMap<String, f64>,
+    /// Высоты клеток над уровнем моря, м
+    elevations: Vec<f64>,
+}
+
+fn stats(side: &Side) -> Stats {
+    let (hm, bm, cfg) = (side.hm, side.bm, side.cfg);
+    let cells = hm.values.len().max(1) as f64;
+    let elevations: Vec<f64> = (0..hm.height)
+        .flat_map(|y| (0..hm.width).map(move |x| (x, y)))
+        .map(|(x, y)| hm.elevation_m(cfg.sea_level, x, y))
+        .collect();
+    let mut coverage: BTreeMap<String, f64> = BTreeMap::new();
+    for idx in &bm.indices {
+        let id = match idx {
+            Some(i) if (*i as usize) < cfg.biomes.len() => cfg.biomes[*i as usize].id.as_str(),
+            _ => "water",
+        };
+        *coverage.entry(id.to_string()).or_default() += 1.0 / cells;
+    }
+    Stats {
+        land_fraction: elevations.iter().filter(|&&e| e > 0.0).count() as f64 / cells,
+        coverage,
+        elevations,
+    }
+}
+
+/// HTML-отчёт целиком
+pub fn build_report(a: &Side, b: &Side) -> anyhow::Result<String> {
+    let (sa, sb) = (stats(a), stats(b));
+    let mut html = String::new();
+    html.push_str(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>World comparison</title>\n\
+         <style>body{font-family:sans-serif;margin:24px;color:#222}\
+         .maps{display:flex;gap:16px}.maps figure{margin:0}\
+         .maps img{width:100%;max-width:512px;image-rendering:pixelated;border:1px solid #999}\
+         table{border-collapse:collapse}td,th{padding:4px 10px;border-bottom:1px solid #ddd;text-align:right}\
+         td:first-child,th:first-child{text-align:left}.up{color:#2a7a2a}.down{color:#b03030}</style>\n\
+         </head><body>\n<h1>World comparison</h1>\n",
+    );
+
+    html.push_str("<div class=\"maps\">\n");
+    for (name, side) in [("A", a), ("B", b)] {
+        let _ = writeln!(
+            html,
+            "<figure><img src=\"data:image/png;base64,{}\"><figcaption><b>{name}</b>: {} \
+             (seed {}, {}&times;{})</figcaption></figure>",
+            base64(&png_bytes(side.render)?),
+            escape(side.label),
+            side.cfg.world_seed,
+            side.hm.width,
+            side.hm.height
+        );
+    }
+    html.push_str("</div>\n");
+
+    html.push_str(
+        "<h2>Summary</h2>\n<table><tr><th></th><th>A</th><th>B</th><th>B &minus; A</th></tr>\n",
+    );
+    summary_row(
+        &mut html,
+        "Land, %",
+        sa.land_fraction * 100.0,
+        sb.land_fraction * 100.0,
+    );
+    summary_row(
+        &mut html,
+        "Mean elevation, m",
+        mean(&sa.elevations),
+        mean(&sb.elevations),
+    );
+    summary_row(
+        &mut html,
+        "Highest point, m",
+        max(&sa.elevations),
+        max(&sb.elevations),
+    );
+    summary_row(
+        &mut html,
+        "Deepest point, m",
+        min(&sa.elevations),
+        min(&sb.elevations),
+    );
+    html.push_str("</table>\n");
+
+    html.push_str(
+        "<h2>Biome coverage</h2>\n<table><tr><th>Biome</th><th>A, %</th><th>B, %</th><th>&Delta;, pp</th></tr>\n",
+    );
+    let ids: std::collections::BTreeSet<&String> =
+        sa.coverage.keys().chain(sb.coverage.keys()).collect();
+    for id in ids {
+        let name = display_name(a.cfg, id)
+            .or_else(|| display_name(b.cfg, id))
+            .unwrap_or(if id == "water" { "Water" } else { id });
+        let pa = sa.coverage.get(id).copied().unwrap_or(0.0) * 100.0;
+        let pb = sb.coverage.get(id).copied().unwrap_or(0.0) * 100.0;
+        summary_row(&mut html, name, pa, pb);
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Elevation histogram</h2>\n");
+    html.push_str(&histogram_svg(&sa.elevations, &sb.elevations));
+    let _ = writeln!(
+        html,
+        "<p><span style=\"color:{COLOR_A}\">&#9632; A</span> \
+         <span style=\"color:{COLOR_B}\">&#9632; B</span> &mdash; share of cells per elevation band</p>"
+    );
+    html.push_str("</body></html>\n");
+    Ok(html)
+}
+
+fn summary_row(html: &mut String, name: &str, a: f64, b: f64) {
+    let delta = b - a;
+    let class = if delta > 0.05 {
+        "up"
+    } else if delta < -0.05 {
+        "down"
+    } else {
+        ""
+    };
+    let _ = writeln!(
+        html,
+        "<tr><td>{}</td><td>{a:.1}</td><td>{b:.1}</td><td class=\"{class}\">{delta:+.1}</td></tr>",
+        escape(name)
+    );
+}
+
+/// Гистограммы обоих миров на общей шкале высот, столбцы A и B рядом
+fn histogram_svg(a: &[f64], b: &[f64]) -> String {
+    const W: f64 = 720.0;
+    const H: f64 = 220.0;
+    const AXIS: f64 = 24.0;
+    let lo = min(a).min(min(b));
+    let hi = max(a).max(max(b));
+    let span = (hi - lo).max(1.0);
+    let bins = |values: &[f64]| {
+        let mut counts = vec![0.0; HISTOGRAM_BINS];
+        for &v in values {
+            let i = (((v - lo) / span) * HISTOGRAM_BINS as f64) as usize;
+            counts[i.min(HISTOGRAM_BINS - 1)] += 1.0 / values.len().max(1) as f64;
+        }
+        counts
+    };
+    let (ha, hb) = (bins(a), bins(b));
+    let top = ha
+        .iter()
+        .chain(&hb)
+        .fold(0.0f64, |m, &v| m.max(v))
+        .max(1e-9);
+
+    let bar_w = W / HISTOGRAM_BINS as f64;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{W}\" height=\"{}\" font-size=\"11\">\n",
+        H + AXIS
+    );
+    for i in 0..HISTOGRAM_BINS {
+        let x = i as f64 * bar_w;
+        for (k, (share, color)) in [(ha[i], COLOR_A), (hb[i], COLOR_B)].into_iter().enumerate() {
+            let h = share / top * H;
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{h:.1}\" fill=\"{color}\"/>",
+                x + 1.0 + k as f64 * (bar_w - 2.0) / 2.0,
+                H - h,
+                (bar_w - 2.0) / 2.0
+            );
+        }
+        if i % 4 == 0 {
+            let _ = writeln!(
+                svg,
+                "<text x=\"{x:.1}\" y=\"{:.1}\">{:.0}</text>",
+                H + 14.0,
+                lo + span * i as f64 / HISTOGRAM_BINS as f64
+            );
+        }
+    }
+    let _ = writeln!(
+        svg,
+        "<line x1=\"0\" y1=\"{H}\" x2=\"{W}\" y2=\"{H}\" stroke=\"#555\"/>\n</svg>"
+    );
+    svg
+}
+
+fn display_name<'a>(cfg: &'a WorldConfig, id: &str) -> Option<&'a str> {
+    cfg.biomes
+        .iter()
+        .find(|b| b.id == id)
+        .map(|b| b.display_name.as_str())
+}
+
+fn png_bytes(img: &image::RgbImage) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    img.write_to(
+        &mut std::io::Cursor::new(&mut bytes),
+        image::ImageFormat::Png,
+    )?;
+    Ok(bytes)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn mean(v: &[f64]) -> f64 {
+    v.iter().sum::<f64>() / v.len().max(1) as f64
+}
+
+fn max(v: &[f64]) -> f64 {
+    v.iter().copied().fold(f64::MIN, f64::max)
+}
+
+fn min(v: &[f64]) -> f64 {
+    v.iter().copied().fold(f64::MAX, f64::min)
+}
@@ -0,0 +1,256 @@
+//! Выгрузка heightmap как цифровой модели рельефа (DEM): 16-битный PNG,
+//! GeoTIFF с float32-высотами в метрах или сырой float32, а также профили
+//! под движки (Unity Terrain RAW, Unreal Landscape PNG). Рядом с файлом
+//! пишется `<out>.json` с размером клетки, формулой перевода значений в метры
+//! и, для движков, рекомендуемыми настройками импорта.
+
+use clap::ValueEnum;
+use image::{ImageBuffer, Luma};
+use seed_config::WorldConfig;
+use seed_core::Heightmap;
+use serde::Serialize;
+use serde_json::json;
+use std::io::{BufWriter, Write};
+use tiff::encoder::{colortype, TiffEncoder};
+use tiff::tags::Tag;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeightmapFormat {
+    /// 8-битный grayscale PNG, нормализованные высоты (превью)
+    Png,
+    /// 16-битный grayscale PNG, диапазон высот мира растянут на 0..65535
+    Png16,
+    /// GeoTIFF, float32, метры над уровнем моря
+    Geotiff,
+    /// Сырые float32 little-endian, метры над уровнем моря, построчно с севера
+    Raw32,
+    /// Unity Terrain: 16-битный RAW little-endian, строки с юга на север
+    UnityRaw,
+    /// Unreal Landscape: 16-битный PNG, середина шкалы (32768) — ноль актора
+    UnrealPng,
+}
+
+/// Описание растра: как из значения пикселя получить высоту в метрах
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DemMetadata<'a> {
+    format: &'a str,
+    width: u32,
+    height: u32,
+    /// Размер клетки на местности, метры
+    cell_size_m: f64,
+    /// Высота в метрах = значение × elevationScale + elevationOffset
+    elevation_scale: f64,
+    elevation_offset: f64,
+    min_elevation_m: f64,
+    max_elevation_m: f64,
+    /// Уровень моря, в метрах той же шкалы
+    sea_level_m: f64,
+    /// Рекомендуемые настройки импорта в движок
+    #[serde(skip_serializing_if = "Option::is_none")]
+    import_settings: Option<serde_json::Value>,
+}
+
+/// `pow2_plus_one` — передискретизировать в квадрат 2ⁿ+1 (разрешения,
+/// которые движки принимают без обрезки и растяжения)
+pub fn save_heightmap(
+    hm: &Heightmap,
+    cfg: &WorldConfig,
+    format: HeightmapFormat,
+    pow2_plus_one: bool,
+    path: &str,
+) -> anyhow::Result<()> {
+    if format == HeightmapFormat::Png {
+        let img: ImageBuffer<Luma<u8>, Vec<u8>> =
+            ImageBuffer::from_fn(hm.width, hm.height, |x, y| {
+                Luma([(hm.get(x, y).clamp(0.0, 1.0) * 255.0) as u8])
+            });
+        img.save(path)?;
+        // превью без сопроводительного файла, как и раньше
+        return Ok(());
+    }
+
+    // размеры региона на местности, метры
+    let extent_x = cfg.scale.region_size_km * 1000.0;
+    let extent_y = extent_x * hm.height as f64 / hm.width.max(1) as f64;
+
+    let mut width = hm.width;
+    let mut height = hm.height;
+    let mut elevations: Vec<f64> = (0..hm.height)
+        .flat_map(|y| (0..hm.width).map(move |x| hm.elevation_m(cfg.sea_level, x, y)))
+        .collect();
+    if pow2_plus_one {
+        let size = hm
+            .width
+            .max(hm.height)
+            .saturating_sub(1)
+            .next_power_of_two()
+            + 1;
+        elevations = resample(&elevations, width, height, size, size);
+        width = size;
+        height = size;
+    }
+    let (min, max) = elevations
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), &e| (lo.min(e), hi.max(e)));
+    let span = (max - min).max(1e-6);
+    let cell_size_m = extent_x / width.max(1) as f64;
+    let to_u16 = |e: f64| ((e - min) / span * 65535.0).round() as u16;
+
+    let (name, scale, offset, import_settings) = match format {
+        HeightmapFormat::Png => unreachable!("handled above"),
+        HeightmapFormat::Png16 => {
+            save_png16(path, width, height, elevations.iter().map(|&e| to_u16(e)))?;
+            ("png16", span / 65535.0, min, None)
+        }
+        HeightmapFormat::Geotiff => {
+            let data: Vec<f32> = elevations.iter().map(|&e| e as f32).collect();
+            write_geotiff(path, width, height, cell_size_m, &data)?;
+            ("geotiff", 1.0, 0.0, None)
+        }
+        HeightmapFormat::Raw32 => {
+            let mut out = BufWriter::new(std::fs::File::create(path)?);
+            for &e in &elevations {
+                out.write_all(&(e as f32).to_le_bytes())?;
+            }
+            out.flush()?;
+            ("raw32", 1.0, 0.0, None)
+        }
+        HeightmapFormat::UnityRaw => {
+            // Unity кладёт первую строку RAW на южный край террейна
+            let mut out = BufWriter::new(std::fs::File::create(path)?);
+            for row in elevations.chunks_exact(width as usize).rev() {
+                for &e in row {
+                    out.write_all(&to_u16(e).to_le_bytes())?;
+                }
+            }
+            out.flush()?;
+            if width != height {
+                eprintln!("warning: Unity terrain heightmaps must be square; use --pow2-plus-one");
+            }
+            let settings = json!({
+                "engine": "unity",
+                "depth": 16,
+                "byteOrder": "Windows",
+                "resolution": width,
+                "flipVertically": false,
+                "terrainSize": { "x": extent_x, "y": span, "z": extent_y },
+                "terrainPositionY": min,
+            });
+            ("unity-raw", span / 65535.0, min, Some(settings))
+        }
+        HeightmapFormat::UnrealPng => {
+            save_png16(path, width, height, elevations.iter().map(|&e| to_u16(e)))?;
+            // при масштабе Z = 100 полная шкала 0..65535 — это 512 м
+            let cm = 100.0;
+            let settings = json!({
+                "engine": "unreal",
+                "resolution": { "x": width, "y": height },
+                "recommendedResolution": unreal_resolution(width.max(height)),
+                "scale": {
+                    "x": extent_x / (width - 1).max(1) as f64 * cm,
+                    "y": extent_y / (height - 1).max(1) as f64 * cm,
+                    "z": span / 512.0 * cm,
+                },
+                "locationZ": (min + span / 2.0) * cm,
+            });
+            ("unreal-png", span / 65535.0, min, Some(settings))
+        }
+    };
+
+    let meta = DemMetadata {
+        format: name,
+        width,
+        height,
+        cell_size_m,
+        elevation_scale: scale,
+        elevation_offset: offset,
+        min_elevation_m: min,
+        max_elevation_m: max,
+        sea_level_m: 0.0,
+        import_settings,
+    };
+    std::fs::write(format!("{path}.json"), serde_json::to_string_pretty(&meta)?)?;
+    Ok(())
+}
+
+fn save_png16(
+    path: &str,
+    width: u32,
+    height: u32,
+    values: impl Iterator<Item = u16>,
+) -> anyhow::Result<()> {
+    let img: ImageBuffer<Luma<u16>, Vec<u16>> =
+        ImageBuffer::from_raw(width, height, values.collect())
+            .expect("buffer matches heightmap size");
+    img.save(path)?;
+    Ok(())
+}
+
+/// Билинейная передискретизация растра `w`×`h` в `nw`×`nh` с совпадающими углами
+fn resample(src: &[f64], w: u32, h: u32, nw: u32, nh: u32) -> Vec<f64> {
+    let (w, h) = (w as usize, h as usize);
+    let at = |x: usize, y: usize| src[y.min(h - 1) * w + x.min(w - 1)];
+    let step = |n: u32, len: usize| (len - 1) as f64 / (n - 1).max(1) as f64;
+    let (sx, sy) = (step(nw, w), step(nh, h));
+    let mut out = Vec::with_capacity((nw * nh) as usize);
+    for j in 0..nh {
+        let fy = j as f64 * sy;
+        let (y0, ty) = (fy.floor() as usize, fy.fract());
+        for i in 0..nw {
+            let fx = i as f64 * sx;
+            let (x0, tx) = (fx.floor() as usize, fx.fract());
+            let top = at(x0, y0) * (1.0 - tx) + at(x0 + 1, y0) * tx;
+            let bottom = at(x0, y0 + 1) * (1.0 - tx) + at(x0 + 1, y0 + 1) * tx;
+            out.push(top * (1.0 - ty) + bottom * ty);
+        }
+    }
+    out
+}
+
+/// Ближайшее сверху разрешение ландшафта из таблицы рекомендуемых
+/// размеров Unreal (целое число компонентов, без обрезки по краям)
+fn unreal_resolution(size: u32) -> u32 {
+    const SIZES: [u32; 7] = [127, 253, 505, 1009, 2017, 4033, 8129];
+    SIZES
+        .into_iter()
+        .find(|&s| s >= size)
+        .unwrap_or(SIZES[SIZES.len() - 1])
+}
+
+/// Одноканальный float32 TIFF с геопривязкой в локальной метрической
+/// системе: начало — юго-западный угол региона, ось Y — на север
+fn write_geotiff(
+    path: &str,
+    width: u32,
+    height: u32,
+    cell_size_m: f64,
+    data: &[f32],
+) -> anyhow::Result<()> {
+    // GeoKey: (id, location, count, value); location 0 — значение прямо в записи
+    const GEO_KEYS: [u16; 24] = [
+        1, 1, 0, 5, // версия каталога и число ключей
+        1024, 0, 1, 1, // GTModelTypeGeoKey = ModelTypeProjected
+        1025, 0, 1, 1, // GTRasterTypeGeoKey = RasterPixelIsArea
+        3072, 0, 1, 32767, // ProjectedCSTypeGeoKey = user-defined
+        3076, 0, 1, 9001, // ProjLinearUnitsGeoKey = Linear_Meter
+        4099, 0, 1, 9001, // VerticalUnitsGeoKey = Linear_Meter
+    ];
+
+    let file = BufWriter::new(std::fs::File::create(path)?);
+    let mut tiff = TiffEncoder::new(file)?;
+    let mut image = tiff.new_image::<colortype::Gray32Float>(width, height)?;
+    let dir = image.encoder();
+    dir.write_tag(
+        Tag::ModelPixelScaleTag,
+        &[cell_size_m, cell_size_m, 0.0][..],
+    )?;
+    // левый верхний пиксель ↔ северо-западный угол
+    dir.write_tag(
+        Tag::ModelTiepointTag,
+        &[0.0, 0.0, 0.0, 0.0, height as f64 * cell_size_m, 0.0][..],
+    )?;
+    dir.write_tag(Tag::GeoKeyDirectoryTag, &GEO_KEYS[..])?;
+    image.write_data(data)?;
+    Ok(())
+}
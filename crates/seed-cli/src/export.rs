@@ -0,0 +1,264 @@
+//! Запись сетки рельефа в OBJ и glTF 2.0 (`.gltf` со встроенным буфером
+//! или бинарный `.glb`) и навигационной сетки в OBJ или JSON.
+
+use image::{ImageFormat, RgbImage};
+use seed_core::{NavMesh, TerrainMesh};
+use serde_json::json;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::Path;
+
+/// Сетка и всё, что к ней прилагается
+pub struct MeshExport<'a> {
+    pub mesh: &'a TerrainMesh,
+    pub normals: Vec<[f32; 3]>,
+    /// Цвета вершин, 0..1
+    pub colors: Option<Vec<[f32; 3]>>,
+    /// Текстура, натягиваемая по UV
+    pub texture: Option<RgbImage>,
+}
+
+/// Формат по расширению файла: `.obj`, `.gltf` или `.glb`
+pub fn write_mesh(export: &MeshExport, path: &str) -> anyhow::Result<()> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match ext.as_deref() {
+        Some("obj") => write_obj(export, path),
+        Some("gltf") => write_gltf(export, path, false),
+        Some("glb") => write_gltf(export, path, true),
+        _ => anyhow::bail!("unsupported mesh format '{path}': expected .obj, .gltf or .glb"),
+    }
+}
+
+/// Навигационная сетка: `.obj` — полигоны, `.json` — полигоны с соседями
+pub fn write_navmesh(navmesh: &NavMesh, path: &str) -> anyhow::Result<()> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match ext.as_deref() {
+        Some("obj") => std::fs::write(path, navmesh.to_obj())?,
+        Some("json") => std::fs::write(path, serde_json::to_string(navmesh)?)?,
+        _ => anyhow::bail!("unsupported navmesh format '{path}': expected .obj or .json"),
+    }
+    Ok(())
+}
+
+/// OBJ с цветами вершин (`v x y z r g b`); текстура — рядом, через `.mtl`
+fn write_obj(export: &MeshExport, path: &str) -> anyhow::Result<()> {
+    let mesh = export.mesh;
+    let mut out = BufWriter::new(std::fs::File::create(path)?);
+    writeln!(out, "# SEED terrain mesh")?;
+
+    if let Some(texture) = &export.texture {
+        let base = Path::new(path).with_extension("");
+        let stem = base
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("terrain");
+        texture.save(base.with_extension("png"))?;
+        std::fs::write(
+            base.with_extension("mtl"),
+            format!("newmtl terrain\nKd 1 1 1\nmap_Kd {stem}.png\n"),
+        )?;
+        writeln!(out, "mtllib {stem}.mtl")?;
+    }
+
+    for (i, p) in mesh.positions.iter().enumerate() {
+        match &export.colors {
+            Some(c) => writeln!(
+                out,
+                "v {} {} {} {:.4} {:.4} {:.4}",
+                p[0], p[1], p[2], c[i][0], c[i][1], c[i][2]
+            )?,
+            None => writeln!(out, "v {} {} {}", p[0], p[1], p[2])?,
+        }
+    }
+    // у OBJ начало UV внизу
+    for uv in &mesh.uvs {
+        writeln!(out, "vt {} {}", uv[0], 1.0 - uv[1])?;
+    }
+    for n in &export.normals {
+        writeln!(out, "vn {:.5} {:.5} {:.5}", n[0], n[1], n[2])?;
+    }
+    if export.texture.is_some() {
+        writeln!(out, "usemtl terrain")?;
+    }
+    for t in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [t[0] + 1, t[1] + 1, t[2] + 1];
+        writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn write_gltf(export: &MeshExport, path: &str, binary: bool) -> anyhow::Result<()> {
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+    let mesh = export.mesh;
+    let count = mesh.positions.len();
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+
+    // Кладёт блок в буфер (с выравниванием по 4 байта) и возвращает индекс bufferView
+    let mut add_view = |buffer: &mut Vec<u8>, bytes: &[u8], target: Option<u32>| {
+        while !buffer.len().is_multiple_of(4) {
+            buffer.push(0);
+        }
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": buffer.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(t) = target {
+            view["target"] = json!(t);
+        }
+        buffer.extend_from_slice(bytes);
+        views.push(view);
+        views.len() - 1
+    };
+    let floats = |v: &[[f32; 3]]| -> Vec<u8> {
+        v.iter()
+            .flat_map(|p| p.iter().flat_map(|x| x.to_le_bytes()))
+            .collect()
+    };
+
+    let (lo, hi) = mesh.bounds();
+    let view = add_view(&mut buffer, &floats(&mesh.positions), Some(ARRAY_BUFFER));
+    accessors.push(json!({
+        "bufferView": view, "componentType": FLOAT, "count": count, "type": "VEC3",
+        "min": lo, "max": hi,
+    }));
+    let mut attributes = json!({ "POSITION": accessors.len() - 1 });
+
+    let view = add_view(&mut buffer, &floats(&export.normals), Some(ARRAY_BUFFER));
+    accessors.push(json!({
+        "bufferView": view, "componentType": FLOAT, "count": count, "type": "VEC3",
+    }));
+    attributes["NORMAL"] = json!(accessors.len() - 1);
+
+    let uv_bytes: Vec<u8> = mesh
+        .uvs
+        .iter()
+        .flat_map(|uv| uv.iter().flat_map(|x| x.to_le_bytes()))
+        .collect();
+    let view = add_view(&mut buffer, &uv_bytes, Some(ARRAY_BUFFER));
+    accessors.push(json!({
+        "bufferView": view, "componentType": FLOAT, "count": count, "type": "VEC2",
+    }));
+    attributes["TEXCOORD_0"] = json!(accessors.len() - 1);
+
+    if let Some(colors) = &export.colors {
+        let view = add_view(&mut buffer, &floats(colors), Some(ARRAY_BUFFER));
+        accessors.push(json!({
+            "bufferView": view, "componentType": FLOAT, "count": count, "type": "VEC3",
+        }));
+        attributes["COLOR_0"] = json!(accessors.len() - 1);
+    }
+
+    let index_bytes: Vec<u8> = mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let view = add_view(&mut buffer, &index_bytes, Some(ELEMENT_ARRAY_BUFFER));
+    accessors.push(json!({
+        "bufferView": view, "componentType": UNSIGNED_INT, "count": mesh.indices.len(),
+        "type": "SCALAR",
+    }));
+    let indices = accessors.len() - 1;
+
+    let mut material = json!({
+        "name": "terrain",
+        "pbrMetallicRoughness": { "metallicFactor": 0.0, "roughnessFactor": 1.0 },
+    });
+    let mut extra = serde_json::Map::new();
+    if let Some(texture) = &export.texture {
+        let mut png = Vec::new();
+        texture.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        let view = add_view(&mut buffer, &png, None);
+        extra.insert(
+            "images".into(),
+            json!([{ "bufferView": view, "mimeType": "image/png" }]),
+        );
+        extra.insert(
+            "samplers".into(),
+            json!([{ "wrapS": 33071, "wrapT": 33071 }]),
+        );
+        extra.insert("textures".into(), json!([{ "source": 0, "sampler": 0 }]));
+        material["pbrMetallicRoughness"]["baseColorTexture"] = json!({ "index": 0 });
+    }
+    while !buffer.len().is_multiple_of(4) {
+        buffer.push(0);
+    }
+
+    let mut buffer_desc = json!({ "byteLength": buffer.len() });
+    if !binary {
+        buffer_desc["uri"] = json!(format!(
+            "data:application/octet-stream;base64,{}",
+            base64(&buffer)
+        ));
+    }
+    let mut doc = json!({
+        "asset": { "version": "2.0", "generator": "seed-cli" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "terrain" }],
+        "meshes": [{
+            "name": "terrain",
+            "primitives": [{ "attributes": attributes, "indices": indices, "material": 0 }],
+        }],
+        "materials": [material],
+        "buffers": [buffer_desc],
+        "bufferViews": views,
+        "accessors": accessors,
+    });
+    for (k, v) in extra {
+        doc[k] = v;
+    }
+
+    if !binary {
+        std::fs::write(path, serde_json::to_string(&doc)?)?;
+        return Ok(());
+    }
+
+    let mut json_bytes = serde_json::to_vec(&doc)?;
+    while !json_bytes.len().is_multiple_of(4) {
+        json_bytes.push(b' ');
+    }
+    let total = 12 + 8 + json_bytes.len() + 8 + buffer.len();
+    let mut out = BufWriter::new(std::fs::File::create(path)?);
+    out.write_all(b"glTF")?;
+    out.write_all(&2u32.to_le_bytes())?;
+    out.write_all(&(total as u32).to_le_bytes())?;
+    out.write_all(&(json_bytes.len() as u32).to_le_bytes())?;
+    out.write_all(b"JSON")?;
+    out.write_all(&json_bytes)?;
+    out.write_all(&(buffer.len() as u32).to_le_bytes())?;
+    out.write_all(b"BIN\0")?;
+    out.write_all(&buffer)?;
+    out.flush()?;
+    Ok(())
+}
+
+pub(crate) fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for k in 0..4 {
+            if k <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * k) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
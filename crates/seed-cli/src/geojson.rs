@@ -0,0 +1,143 @@
+//! Векторные слои мира в GeoJSON: реки, озёра, береговая линия, дороги и
+//! границы фракций. Система координат — та же локальная метрическая, что у
+//! GeoTIFF-экспорта heightmap (x — на восток, y — на север, начало —
+//! юго-западный угол региона), так что слои ложатся на DEM в QGIS без
+//! перепроецирования.
+
+use seed_config::WorldConfig;
+use seed_core::infrastructure::RoadQuality;
+use seed_core::territory::simplify_polyline;
+use seed_core::{
+    extract_borders, extract_coastlines, extract_lakes, extract_rivers, Heightmap, History,
+};
+use serde_json::{json, Value};
+
+/// Переводит точки из клеток карты в метры
+struct Frame {
+    cell_m: f64,
+    height: f64,
+}
+
+impl Frame {
+    fn point(&self, (x, y): (f32, f32)) -> [f64; 2] {
+        [
+            round_cm(x as f64 * self.cell_m),
+            round_cm((self.height - y as f64) * self.cell_m),
+        ]
+    }
+
+    fn line(&self, points: &[(f32, f32)]) -> Vec<[f64; 2]> {
+        points.iter().map(|&p| self.point(p)).collect()
+    }
+}
+
+/// Собирает FeatureCollection; `epsilon` — допуск упрощения линий в клетках
+pub fn build_vectors(cfg: &WorldConfig, hm: &Heightmap, history: &History, epsilon: f32) -> Value {
+    let sea_level = cfg.sea_level as f32;
+    let cell_m = cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64;
+    let frame = Frame {
+        cell_m,
+        height: hm.height as f64,
+    };
+    let mut features = Vec::new();
+
+    for river in extract_rivers(hm, sea_level, epsilon) {
+        features.push(feature(
+            json!({ "type": "LineString", "coordinates": frame.line(&river.points) }),
+            json!({ "layer": "river", "discharge": river.discharge }),
+        ));
+    }
+
+    for lake in extract_lakes(hm, sea_level, epsilon) {
+        let rings: Vec<Vec<[f64; 2]>> = lake
+            .rings
+            .iter()
+            .enumerate()
+            .map(|(i, ring)| oriented(frame.line(ring), i == 0))
+            .collect();
+        features.push(feature(
+            json!({ "type": "Polygon", "coordinates": rings }),
+            json!({
+                "layer": "lake",
+                "areaKm2": lake.cells as f64 * cell_m * cell_m / 1e6,
+            }),
+        ));
+    }
+
+    for coast in extract_coastlines(hm, sea_level, epsilon) {
+        features.push(feature(
+            json!({ "type": "LineString", "coordinates": frame.line(&coast) }),
+            json!({ "layer": "coastline" }),
+        ));
+    }
+
+    for road in history
+        .infrastructure
+        .roads
+        .iter()
+        .filter(|r| r.points.len() >= 2)
+    {
+        // дороги идут по центрам клеток
+        let points: Vec<(f32, f32)> = road
+            .points
+            .iter()
+            .map(|&(x, y)| (x as f32 + 0.5, y as f32 + 0.5))
+            .collect();
+        let points = simplify_polyline(&points, epsilon);
+        let quality = match road.quality {
+            RoadQuality::Track => "track",
+            RoadQuality::Paved => "paved",
+            RoadQuality::Highway => "highway",
+        };
+        features.push(feature(
+            json!({ "type": "LineString", "coordinates": frame.line(&points) }),
+            json!({ "layer": "road", "quality": quality, "era": road.era.id() }),
+        ));
+    }
+
+    let faction = |id: Option<u16>| {
+        id.and_then(|i| history.factions.get(i as usize))
+            .map(|f| json!({ "id": f.id, "name": f.name }))
+            .unwrap_or(Value::Null)
+    };
+    for border in extract_borders(&history.territory, epsilon) {
+        features.push(feature(
+            json!({ "type": "LineString", "coordinates": frame.line(&border.points) }),
+            json!({
+                "layer": "border",
+                "factionA": faction(border.faction_a),
+                "factionB": faction(border.faction_b),
+            }),
+        ));
+    }
+
+    let (w_m, h_m) = (
+        round_cm(hm.width as f64 * cell_m),
+        round_cm(hm.height as f64 * cell_m),
+    );
+    json!({
+        "type": "FeatureCollection",
+        "bbox": [0.0, 0.0, w_m, h_m],
+        "features": features,
+    })
+}
+
+fn feature(geometry: Value, properties: Value) -> Value {
+    json!({ "type": "Feature", "geometry": geometry, "properties": properties })
+}
+
+/// RFC 7946: внешний контур — против часовой стрелки, дыры — по часовой
+fn oriented(mut ring: Vec<[f64; 2]>, outer: bool) -> Vec<[f64; 2]> {
+    let area: f64 = ring
+        .windows(2)
+        .map(|p| p[0][0] * p[1][1] - p[1][0] * p[0][1])
+        .sum();
+    if (area > 0.0) != outer {
+        ring.reverse();
+    }
+    ring
+}
+
+fn round_cm(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
@@ -0,0 +1,118 @@
+//! Отпечатки сгенерированного мира (`seed-cli hash`): SHA-1 heightmap, карты
+//! биомов и набора объектов для конфига, seed и размера. Данные хешируются в
+//! фиксированной двоичной раскладке (числа — LE, f32 — как биты), а не через
+//! JSON, чтобы отпечаток не зависел от форматирования чисел в зависимостях.
+//! Игры на этом крейте могут хранить отпечатки в CI и ловить нечаянные
+//! изменения генерации при обновлении.
+
+use crate::objects::type_id;
+use seed_config::WorldConfig;
+use seed_core::{BiomeMap, Heightmap, ProceduralObject};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+/// Клетка без биома
+const NO_BIOME: u8 = u8::MAX;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldHashes {
+    pub seed_version: String,
+    pub world_seed: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Отпечаток входа — конфига целиком
+    pub config: String,
+    pub heightmap: String,
+    pub biomes: String,
+    pub objects: String,
+    pub object_count: usize,
+    /// Отпечаток трёх выходов вместе
+    pub world: String,
+}
+
+impl WorldHashes {
+    /// Какие из выходов отличаются от `expected`
+    pub fn mismatches(&self, expected: &WorldHashes) -> Vec<&'static str> {
+        [
+            ("heightmap", &self.heightmap, &expected.heightmap),
+            ("biomes", &self.biomes, &expected.biomes),
+            ("objects", &self.objects, &expected.objects),
+        ]
+        .into_iter()
+        .filter(|(_, a, b)| a != b)
+        .map(|(name, _, _)| name)
+        .collect()
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "config     {}\nheightmap  {}\nbiomes     {}\nobjects    {}  ({} objects)\nworld      {}\n",
+            self.config, self.heightmap, self.biomes, self.objects, self.object_count, self.world
+        )
+    }
+}
+
+pub fn compute(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    objects: &[ProceduralObject],
+) -> anyhow::Result<WorldHashes> {
+    let mut heights = sized(hm.width, hm.height);
+    for v in &hm.values {
+        heights.update(v.to_bits().to_le_bytes());
+    }
+
+    let mut biomes = sized(bm.width, bm.height);
+    let cells: Vec<u8> = bm.indices.iter().map(|i| i.unwrap_or(NO_BIOME)).collect();
+    biomes.update(&cells);
+
+    let mut placed = Sha1::new();
+    placed.update((objects.len() as u64).to_le_bytes());
+    for o in objects {
+        for v in [o.x, o.y, o.z, o.scale, o.rotation_y] {
+            placed.update(v.to_bits().to_le_bytes());
+        }
+        let kind = type_id(o.object_type);
+        placed.update((kind.len() as u32).to_le_bytes());
+        placed.update(kind.as_bytes());
+        placed.update([o.variant]);
+        placed.update(o.age_years.map_or(u64::MAX, u64::from).to_le_bytes());
+    }
+
+    let [heightmap, biomes, objects_hex] = [heights, biomes, placed].map(|h| hex(&h.finalize()));
+    let mut world = Sha1::new();
+    for part in [&heightmap, &biomes, &objects_hex] {
+        world.update(part.as_bytes());
+    }
+
+    // через Value: ключи отсортированы, порядок HashMap в конфиге не влияет
+    let canonical = serde_json::to_vec(&serde_json::to_value(cfg)?)?;
+    let config = hex(&Sha1::digest(canonical));
+
+    Ok(WorldHashes {
+        seed_version: cfg.seed_version.clone(),
+        world_seed: cfg.world_seed,
+        width: hm.width,
+        height: hm.height,
+        config,
+        heightmap,
+        biomes,
+        objects: objects_hex,
+        object_count: objects.len(),
+        world: hex(&world.finalize()),
+    })
+}
+
+/// Хешер, в который уже записан размер карты
+fn sized(width: u32, height: u32) -> Sha1 {
+    let mut h = Sha1::new();
+    h.update(width.to_le_bytes());
+    h.update(height.to_le_bytes());
+    h
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,423 @@
+//! Заготовка конфига (`seed-cli init`): пример мира из репозитория, подогнанный
+//! под несколько ключевых решений — размер планеты, тип климата, число
+//! биомов, катастрофы. С `--interactive` решения спрашиваются в терминале,
+//! флаги задают ответы по умолчанию. Готовый конфиг проверяется так же, как
+//! `seed-cli validate`.
+
+use clap::ValueEnum;
+use seed_config::{BiomeClimateRangeConfig, BiomeConfig, WorldConfig};
+use std::io::{BufRead, Write};
+
+/// Пример мира — основа для всех заготовок
+const TEMPLATE: &str = include_str!("../../../world-config.json");
+/// Радиус, для которого в примере заданы масса и гравитация
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanetSize {
+    /// Как Марс
+    Small,
+    /// Как Земля
+    Earth,
+    /// Суперземля
+    Large,
+}
+
+impl PlanetSize {
+    fn radius_km(self) -> f64 {
+        match self {
+            PlanetSize::Small => 3390.0,
+            PlanetSize::Earth => EARTH_RADIUS_KM,
+            PlanetSize::Large => 10000.0,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClimateArchetype {
+    Temperate,
+    Arid,
+    Cold,
+    Tropical,
+}
+
+impl ClimateArchetype {
+    /// Средняя температура, °C, средняя влажность и множитель осадков
+    fn atmosphere(self) -> (f64, f64, f64) {
+        match self {
+            ClimateArchetype::Temperate => (25.0, 0.5, 1.0),
+            ClimateArchetype::Arid => (32.0, 0.2, 0.4),
+            ClimateArchetype::Cold => (5.0, 0.55, 0.7),
+            ClimateArchetype::Tropical => (32.0, 0.8, 1.6),
+        }
+    }
+
+    /// Биомы по убыванию уместности для этого климата
+    fn biome_order(self) -> [&'static str; MAX_BIOMES] {
+        match self {
+            ClimateArchetype::Temperate => [
+                "temperate_forest",
+                "grassland",
+                "cold_mountains",
+                "taiga",
+                "wetland",
+                "tundra",
+                "hot_desert",
+                "savanna",
+                "tropical_rainforest",
+            ],
+            ClimateArchetype::Arid => [
+                "hot_desert",
+                "savanna",
+                "grassland",
+                "cold_mountains",
+                "temperate_forest",
+                "wetland",
+                "tundra",
+                "taiga",
+                "tropical_rainforest",
+            ],
+            ClimateArchetype::Cold => [
+                "tundra",
+                "taiga",
+                "cold_mountains",
+                "temperate_forest",
+                "grassland",
+                "wetland",
+                "hot_desert",
+                "savanna",
+                "tropical_rainforest",
+            ],
+            ClimateArchetype::Tropical => [
+                "tropical_rainforest",
+                "savanna",
+                "wetland",
+                "hot_desert",
+                "temperate_forest",
+                "grassland",
+                "cold_mountains",
+                "taiga",
+                "tundra",
+            ],
+        }
+    }
+}
+
+/// Сколько биомов можно выбрать
+pub const MAX_BIOMES: usize = 9;
+
+/// Биом, которого нет в примере
+struct BiomePreset {
+    id: &'static str,
+    name: &'static str,
+    temperature_c: [f64; 2],
+    humidity: [f64; 2],
+    elevation_m: [f64; 2],
+    precipitation_mm: [f64; 2],
+    base_material: &'static str,
+    overlay_material: &'static str,
+    vegetation_density: f32,
+    allow_settlements: bool,
+    fauna: &'static [&'static str],
+}
+
+const EXTRA_BIOMES: [BiomePreset; 5] = [
+    BiomePreset {
+        id: "grassland",
+        name: "Grassland",
+        temperature_c: [5.0, 28.0],
+        humidity: [0.25, 0.6],
+        elevation_m: [0.0, 1200.0],
+        precipitation_mm: [300.0, 900.0],
+        base_material: "soil",
+        overlay_material: "grass",
+        vegetation_density: 0.3,
+        allow_settlements: true,
+        fauna: &["grazers"],
+    },
+    BiomePreset {
+        id: "tropical_rainforest",
+        name: "Tropical Rainforest",
+        temperature_c: [22.0, 35.0],
+        humidity: [0.75, 1.0],
+        elevation_m: [0.0, 1000.0],
+        precipitation_mm: [1800.0, 4000.0],
+        base_material: "soil",
+        overlay_material: "moss",
+        vegetation_density: 0.95,
+        allow_settlements: false,
+        fauna: &["tropical_birds", "primates"],
+    },
+    BiomePreset {
+        id: "savanna",
+        name: "Savanna",
+        temperature_c: [20.0, 35.0],
+        humidity: [0.3, 0.6],
+        elevation_m: [0.0, 1500.0],
+        precipitation_mm: [500.0, 1300.0],
+        base_material: "soil",
+        overlay_material: "grass",
+        vegetation_density: 0.2,
+        allow_settlements: true,
+        fauna: &["grazers", "big_cats"],
+    },
+    BiomePreset {
+        id: "taiga",
+        name: "Taiga",
+        temperature_c: [-10.0, 10.0],
+        humidity: [0.4, 0.9],
+        elevation_m: [0.0, 1500.0],
+        precipitation_mm: [400.0, 1000.0],
+        base_material: "soil_cold",
+        overlay_material: "moss",
+        vegetation_density: 0.7,
+        allow_settlements: true,
+        fauna: &["taiga_mammals"],
+    },
+    BiomePreset {
+        id: "wetland",
+        name: "Wetland",
+        temperature_c: [0.0, 30.0],
+        humidity: [0.8, 1.0],
+        elevation_m: [-20.0, 300.0],
+        precipitation_mm: [800.0, 2500.0],
+        base_material: "soil",
+        overlay_material: "moss",
+        vegetation_density: 0.6,
+        allow_settlements: false,
+        fauna: &["waterfowl", "amphibians"],
+    },
+];
+
+/// Решения, из которых собирается конфиг
+#[derive(Debug, Clone)]
+pub struct InitChoices {
+    pub name: String,
+    pub seed: u64,
+    pub planet: PlanetSize,
+    pub climate: ClimateArchetype,
+    pub biomes: usize,
+    pub catastrophes: bool,
+}
+
+/// Конфиг по решениям: пример мира с заменёнными разделами
+pub fn build_config(choices: &InitChoices) -> anyhow::Result<WorldConfig> {
+    let mut cfg: WorldConfig = TEMPLATE.parse()?;
+
+    cfg.world_id = slug(&choices.name);
+    cfg.meta.name = choices.name.clone();
+    cfg.meta.description = format!(
+        "{:?} {:?}-size world with {} biomes.",
+        choices.climate, choices.planet, choices.biomes
+    );
+    cfg.meta.author = std::env::var("USER").unwrap_or_default();
+    cfg.meta.created_at = utc_now();
+    cfg.world_seed = choices.seed;
+    cfg.geology.heightmap.base_seed = choices.seed;
+
+    // масса и сила тяжести — при плотности как у примера
+    let radius = choices.planet.radius_km();
+    let ratio = radius / EARTH_RADIUS_KM;
+    cfg.scale.planet_radius_km = radius;
+    let active = cfg.cosmos.star_system.active_planet_id.clone();
+    for planet in &mut cfg.cosmos.star_system.planets {
+        if planet.id == active {
+            planet.radius_km = radius;
+            planet.mass_earths = ratio.powi(3);
+            planet.gravity_ms2 = (9.81 * ratio * 100.0).round() / 100.0;
+        }
+    }
+
+    let (temperature, humidity, precipitation) = choices.climate.atmosphere();
+    cfg.environment.atmosphere.base_temperature_c = temperature;
+    cfg.environment.atmosphere.humidity_global_mean = humidity;
+    cfg.environment.climate_model.precipitation_scale = precipitation;
+
+    let count = choices.biomes.clamp(1, MAX_BIOMES);
+    let template_biomes = std::mem::take(&mut cfg.biomes);
+    cfg.biomes = choices.climate.biome_order()[..count]
+        .iter()
+        .map(|id| {
+            template_biomes
+                .iter()
+                .find(|b| b.id == *id)
+                .cloned()
+                .or_else(|| extra_biome(id))
+                .expect("every archetype biome has a preset")
+        })
+        .collect();
+    // ссылки на выброшенные биомы переводятся на самый уместный оставшийся
+    let kept: Vec<String> = cfg.biomes.iter().map(|b| b.id.clone()).collect();
+    let retarget = |ids: &mut Vec<String>| {
+        ids.retain(|id| kept.contains(id));
+        if ids.is_empty() {
+            ids.push(kept[0].clone());
+        }
+    };
+    for species in &mut cfg.ecosystems.species_definitions {
+        retarget(&mut species.preferred_biomes);
+    }
+    for faction in &mut cfg.civilizations.faction_presets {
+        retarget(&mut faction.preferred_biomes);
+    }
+
+    cfg.catastrophes.global_controls.enabled = choices.catastrophes;
+    cfg.narrative_director.can_trigger_global_catastrophes &= choices.catastrophes;
+    Ok(cfg)
+}
+
+fn extra_biome(id: &str) -> Option<BiomeConfig> {
+    let p = EXTRA_BIOMES.iter().find(|p| p.id == id)?;
+    Some(BiomeConfig {
+        id: p.id.to_string(),
+        display_name: p.name.to_string(),
+        climate_range: BiomeClimateRangeConfig {
+            temperature_c: p.temperature_c,
+            humidity: p.humidity,
+            elevation_meters: p.elevation_m,
+        },
+        precipitation_range_mm_per_year: p.precipitation_mm,
+        base_material_id: Some(p.base_material.to_string()),
+        overlay_material_ids: Some(vec![p.overlay_material.to_string()]),
+        dominant_materials: vec![p.base_material.to_string(), p.overlay_material.to_string()],
+        vegetation_density: p.vegetation_density,
+        fauna_profiles: p.fauna.iter().map(|f| f.to_string()).collect(),
+        allow_settlements: p.allow_settlements,
+        movement_cost: None,
+    })
+}
+
+/// Спрашивает решения в терминале; `defaults` — ответ на пустую строку
+pub fn prompt_choices(defaults: InitChoices) -> anyhow::Result<InitChoices> {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut ask = |question: &str, default: String| -> anyhow::Result<String> {
+        print!("{question} [{default}]: ");
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            anyhow::bail!("input closed");
+        }
+        let answer = line.trim();
+        Ok(if answer.is_empty() {
+            default
+        } else {
+            answer.to_string()
+        })
+    };
+
+    let name = ask("World name", defaults.name)?;
+    let seed = loop {
+        match ask("Seed", defaults.seed.to_string())?.parse() {
+            Ok(seed) => break seed,
+            Err(_) => println!("  expected a whole number"),
+        }
+    };
+    let planet = choose(&mut ask, "Planet size", defaults.planet)?;
+    let climate = choose(&mut ask, "Climate", defaults.climate)?;
+    let biomes = loop {
+        let answer = ask(
+            &format!("Number of biomes (1-{MAX_BIOMES})"),
+            defaults.biomes.to_string(),
+        )?;
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=MAX_BIOMES).contains(&n) => break n,
+            _ => println!("  expected a number from 1 to {MAX_BIOMES}"),
+        }
+    };
+    let catastrophes = loop {
+        let default = if defaults.catastrophes { "y" } else { "n" };
+        match ask("Catastrophes (y/n)", default.to_string())?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "y" | "yes" => break true,
+            "n" | "no" => break false,
+            _ => println!("  expected y or n"),
+        }
+    };
+    Ok(InitChoices {
+        name,
+        seed,
+        planet,
+        climate,
+        biomes,
+        catastrophes,
+    })
+}
+
+/// Вариант из `ValueEnum` по имени; список вариантов — в вопросе
+fn choose<T: ValueEnum>(
+    ask: &mut impl FnMut(&str, String) -> anyhow::Result<String>,
+    question: &str,
+    default: T,
+) -> anyhow::Result<T> {
+    let names: Vec<String> = T::value_variants()
+        .iter()
+        .filter_map(|v| v.to_possible_value())
+        .map(|v| v.get_name().to_string())
+        .collect();
+    let default = default
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default();
+    loop {
+        let answer = ask(
+            &format!("{question} ({})", names.join("/")),
+            default.clone(),
+        )?;
+        match T::from_str(&answer, true) {
+            Ok(value) => return Ok(value),
+            Err(_) => println!("  expected one of: {}", names.join(", ")),
+        }
+    }
+}
+
+/// Seed по умолчанию — от текущего времени
+pub fn fresh_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64 % 1_000_000_000)
+}
+
+/// `My World!` → `my-world`
+fn slug(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.ends_with('-') && !out.is_empty() {
+            out.push('-');
+        }
+    }
+    let out = out.trim_end_matches('-');
+    if out.is_empty() {
+        "world".to_string()
+    } else {
+        out.to_string()
+    }
+}
+
+/// Текущее время UTC в ISO 8601 (как `createdAt` в примере)
+fn utc_now() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs()) as i64;
+    // дни от эпохи → дата по григорианскому календарю
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
@@ -0,0 +1,227 @@
+//! Легенда совмещённой карты (`--legend-out`): образцы цветов с подписями в
+//! той же палитре, что и `--worldview-out` выбранного `--style`. Подписи
+//! рисуются встроенным растровым шрифтом 5×7 (только ASCII, строчные
+//! выводятся заглавными, прочие символы — `?`).
+
+use crate::relief::{DEPTH_TINTS, LAND_TINTS};
+use crate::{build_biome_palette, WorldviewStyle, WATER_COLOR};
+use image::{Rgb, RgbImage};
+use seed_config::WorldConfig;
+
+/// Во сколько раз увеличен шрифт
+const SCALE: u32 = 2;
+const GLYPH_W: u32 = 5;
+const GLYPH_H: u32 = 7;
+/// Шаг символа, включая промежуток
+const ADVANCE: u32 = (GLYPH_W + 1) * SCALE;
+const PAD: u32 = 12;
+const SWATCH_W: u32 = 28;
+const SWATCH_H: u32 = 16;
+const ROW_H: u32 = 22;
+/// Промежуток перед заголовком раздела (кроме первого)
+const SECTION_GAP: u32 = 10;
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const INK: Rgb<u8> = Rgb([30, 30, 30]);
+
+pub struct LegendSection {
+    pub title: String,
+    pub entries: Vec<(String, [u8; 3])>,
+}
+
+/// Разделы легенды для стиля карты: биомы и вода или ступени высот и глубин
+pub fn legend_sections(cfg: &WorldConfig, style: WorldviewStyle) -> Vec<LegendSection> {
+    match style {
+        WorldviewStyle::Biome => {
+            let palette = build_biome_palette(cfg);
+            let biomes = cfg
+                .biomes
+                .iter()
+                .zip(palette)
+                .map(|(biome, color)| {
+                    let name = if biome.display_name.is_empty() {
+                        &biome.id
+                    } else {
+                        &biome.display_name
+                    };
+                    (name.clone(), color)
+                })
+                .collect();
+            vec![
+                LegendSection {
+                    title: "Biomes".to_string(),
+                    entries: biomes,
+                },
+                LegendSection {
+                    title: "Water".to_string(),
+                    entries: vec![("Sea and lakes".to_string(), WATER_COLOR)],
+                },
+            ]
+        }
+        WorldviewStyle::Hypsometric => {
+            let mut land = Vec::new();
+            let mut below = 0.0;
+            for &(top, color) in &LAND_TINTS {
+                let label = if top == f64::MAX {
+                    format!("> {below} m (snow)")
+                } else {
+                    format!("{below} - {top} m")
+                };
+                land.push((label, color));
+                below = top;
+            }
+            // сверху вниз, как на карте в горах
+            land.reverse();
+
+            let mut depth = Vec::new();
+            let mut above = 0.0;
+            for &(floor, color) in &DEPTH_TINTS {
+                let label = if floor == f64::MIN {
+                    format!("> {above} m")
+                } else {
+                    format!("{above} - {} m", -floor)
+                };
+                depth.push((label, color));
+                above = -floor;
+            }
+            vec![
+                LegendSection {
+                    title: "Elevation".to_string(),
+                    entries: land,
+                },
+                LegendSection {
+                    title: "Depth".to_string(),
+                    entries: depth,
+                },
+            ]
+        }
+    }
+}
+
+/// Картинка легенды: заголовки разделов и строки «образец — подпись»
+pub fn render_legend(sections: &[LegendSection]) -> RgbImage {
+    let label_w = sections
+        .iter()
+        .flat_map(|s| {
+            std::iter::once(s.title.chars().count())
+                .chain(s.entries.iter().map(|(label, _)| label.chars().count() + 4))
+        })
+        .max()
+        .unwrap_or(0) as u32;
+    let rows: u32 = sections.iter().map(|s| 1 + s.entries.len() as u32).sum();
+    let gaps = sections.len().saturating_sub(1) as u32;
+    let width = PAD * 2 + label_w * ADVANCE;
+    let height = PAD * 2 + rows * ROW_H + gaps * SECTION_GAP;
+
+    let mut img = RgbImage::from_pixel(width, height, BACKGROUND);
+    let text_dy = (ROW_H - GLYPH_H * SCALE) / 2;
+    let swatch_dy = (ROW_H - SWATCH_H) / 2;
+    let mut y = PAD;
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            y += SECTION_GAP;
+        }
+        draw_text(&mut img, PAD, y + text_dy, &section.title);
+        y += ROW_H;
+        for (label, color) in &section.entries {
+            draw_swatch(&mut img, PAD, y + swatch_dy, Rgb(*color));
+            draw_text(&mut img, PAD + SWATCH_W + ADVANCE, y + text_dy, label);
+            y += ROW_H;
+        }
+    }
+    img
+}
+
+/// Легенда для `--style` карты в PNG
+pub fn save_legend(cfg: &WorldConfig, style: WorldviewStyle, path: &str) -> anyhow::Result<()> {
+    render_legend(&legend_sections(cfg, style)).save(path)?;
+    Ok(())
+}
+
+/// Образец цвета в тонкой рамке (чтобы светлые ступени не сливались с фоном)
+fn draw_swatch(img: &mut RgbImage, x0: u32, y0: u32, color: Rgb<u8>) {
+    for y in y0..y0 + SWATCH_H {
+        for x in x0..x0 + SWATCH_W {
+            let edge = y == y0 || y == y0 + SWATCH_H - 1 || x == x0 || x == x0 + SWATCH_W - 1;
+            img.put_pixel(x, y, if edge { INK } else { color });
+        }
+    }
+}
+
+fn draw_text(img: &mut RgbImage, x0: u32, y0: u32, text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        let gx = x0 + i as u32 * ADVANCE;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_W {
+                if bits & (1 << (GLYPH_W - 1 - col)) == 0 {
+                    continue;
+                }
+                for dy in 0..SCALE {
+                    for dx in 0..SCALE {
+                        let (x, y) = (gx + col * SCALE + dx, y0 + row as u32 * SCALE + dy);
+                        if x < img.width() && y < img.height() {
+                            img.put_pixel(x, y, INK);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Строки символа сверху вниз, старший из пяти битов — левый столбец
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0; 7],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '\'' => [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '<' => [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02],
+        '>' => [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08],
+        '&' => [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
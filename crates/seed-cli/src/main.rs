@@ -1,138 +1,1752 @@
-use clap::Parser;
-use image::{GrayImage, ImageBuffer, Rgb, RgbImage};
-use seed_config::WorldConfig;
+mod batch;
+mod catastrophes;
+mod compare;
+mod dem;
+mod export;
+mod geojson;
+mod hash;
+mod init;
+mod legend;
+mod objects;
+mod progress;
+mod relief;
+mod summary;
+mod tui;
+mod watch;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use dem::{save_heightmap, HeightmapFormat};
+use image::{ImageBuffer, Rgb, RgbImage, RgbaImage};
+use objects::{ObjectCategory, ObjectsFormat};
+use progress::PhaseTimings;
+use relief::{
+    render_hypsometric, save_contours_svg, save_hillshade, save_normal_map, NormalConvention, Sun,
+};
+use seed_config::{Diagnostic, Severity, WorldConfig};
 use seed_core::{
-    generate_biome_map_from_config, generate_heightmap_from_config, BiomeMap, Heightmap, World,
+    apply_catastrophe_to_heightmap, build_chronicle, extract_borders, generate_biome_map_window,
+    generate_biome_map_with_progress, generate_catastrophes, generate_heightmap_window,
+    generate_heightmap_with_progress, generate_objects_for_chunk, generate_species_distribution,
+    simulate_history, Astronomy, BiomeMap, CoreError, FoodWeb, FoodWebIssue, Heightmap, History,
+    MapWindow, NameStyle, NavMeshOptions, QuestType, RouteKind, SpeciesDistribution, SurfaceMap,
+    TargetSelector, TerrainMesh, World,
 };
+use seed_save::{BundleReader, WorldBundle};
+use serde::Serialize;
 
 #[derive(Parser, Debug)]
 #[command(name = "seed-cli")]
 #[command(about = "SEED world tools", long_about = None)]
 struct Cli {
     /// Path to world config JSON
-    #[arg(short, long, default_value = "world-config.json")]
+    #[arg(short, long, default_value = "world-config.json", global = true)]
     config: String,
 
-    /// Если указан путь, будет сгенерирован heightmap и сохранён как PNG (grayscale)
+    /// Формат сводки мира: `text` — для человека, `json` — сводка и статистика
+    /// генерации (единственный вывод в stdout, если других действий нет)
+    #[arg(long, value_enum, default_value_t = SummaryFormat::Text, global = true)]
+    summary: SummaryFormat,
+
+    /// Число потоков для построчной генерации (по умолчанию — все ядра)
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    /// Без полос прогресса и разбивки времени по этапам в stderr
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Если указан путь, будет сгенерирован heightmap и сохранён в формате `--heightmap-format`
     #[arg(long)]
     heightmap_out: Option<String>,
 
+    /// Формат heightmap: `png` — 8-битное превью; остальные — DEM в метрах
+    /// или профиль движка с описанием масштаба в `<out>.json`
+    #[arg(long, value_enum, default_value_t = HeightmapFormat::Png)]
+    heightmap_format: HeightmapFormat,
+
+    /// Передискретизировать heightmap в квадрат 2ⁿ+1 (257, 513, 1025, ...),
+    /// как того требует Unity Terrain
+    #[arg(long)]
+    pow2_plus_one: bool,
+
+    /// Если указан путь, будет сохранена карта нормалей рельефа (tangent space, PNG)
+    #[arg(long)]
+    normal_out: Option<String>,
+
+    /// Направление зелёного канала карты нормалей
+    #[arg(long, value_enum, default_value_t = NormalConvention::Opengl)]
+    normal_convention: NormalConvention,
+
+    /// Если указан путь, будет сохранена отмывка рельефа (grayscale PNG)
+    #[arg(long)]
+    hillshade_out: Option<String>,
+
+    /// Азимут солнца для отмывки, градусы по часовой стрелке от севера
+    #[arg(long, default_value_t = 315.0)]
+    sun_azimuth: f64,
+
+    /// Высота солнца над горизонтом, градусы
+    #[arg(long, default_value_t = 45.0)]
+    sun_altitude: f64,
+
+    /// Вместо `--sun-azimuth`/`--sun-altitude` — солнце над центром карты в
+    /// этот час суток активной планеты (0..`dayLengthHours`); ночью отмывка
+    /// чёрная
+    #[arg(long)]
+    sun_hour: Option<f64>,
+
+    /// День года для `--sun-hour`, считая от весеннего равноденствия
+    #[arg(long, default_value_t = 0.0)]
+    sun_day: f64,
+
+    /// Вертикальное преувеличение для нормалей и отмывки
+    #[arg(long, default_value_t = 1.0)]
+    z_factor: f64,
+
+    /// Если указан путь, будут сохранены горизонтали рельефа (SVG)
+    #[arg(long)]
+    contours_out: Option<String>,
+
+    /// Шаг горизонталей: `100m`, `0.5km` или просто метры
+    #[arg(long, default_value = "100m", value_parser = parse_meters)]
+    interval: f64,
+
     /// Если указан путь, будет сгенерирована карта биомов и сохранена как PNG (color)
     #[arg(long)]
     biome_out: Option<String>,
 
+    /// Если указан путь, будет сохранена splat-карта материалов поверхности
+    /// (PNG RGBA: скала, песок, снег, почва)
+    #[arg(long)]
+    splat_out: Option<String>,
+
     /// Если указан путь, будет сгенерирована совмещённая карта (рельеф + биомы)
     #[arg(long)]
     worldview_out: Option<String>,
 
+    /// Оформление совмещённой карты
+    #[arg(long, value_enum, default_value_t = WorldviewStyle::Biome, global = true)]
+    style: WorldviewStyle,
+
+    /// Если указан путь, будет сохранена легенда совмещённой карты (PNG) —
+    /// образцы цветов выбранного `--style` с подписями
+    #[arg(long)]
+    legend_out: Option<String>,
+
+    /// Если указан путь, будет просимулирована история и сохранена политическая карта
+    /// (территории фракций, границы и города)
+    #[arg(long)]
+    political_out: Option<String>,
+
+    /// Если указан префикс пути, для каждого вида из `ecosystems` сохраняется
+    /// карта ареала `<prefix>_<species>.png` (плотность поверх рельефа)
+    #[arg(long)]
+    species_out: Option<String>,
+
     /// Ширина карт в пикселях
-    #[arg(long, default_value_t = 512)]
+    #[arg(long, default_value_t = 512, global = true)]
     width: u32,
 
     /// Высота карт в пикселях
-    #[arg(long, default_value_t = 512)]
+    #[arg(long, default_value_t = 512, global = true)]
     height: u32,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Просимулировать историю и выгрузить летопись
+    History {
+        /// Куда сохранить летопись: `.json` — структурированные события, иначе — текст.
+        /// Без флага летопись печатается в stdout
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// Проверить конфиг по смыслу; при ошибках код возврата 1
+    Validate {
+        /// Путь к конфигу (по умолчанию — `--config`)
+        path: Option<String>,
+
+        /// Формат отчёта
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+    },
+    /// Создать стартовый конфиг мира: по флагам или, с `--interactive`, по
+    /// ответам в терминале
+    Init {
+        /// Спросить решения в терминале (флаги — ответы по умолчанию)
+        #[arg(long)]
+        interactive: bool,
+
+        /// Куда записать конфиг
+        #[arg(long, default_value = "world-config.json")]
+        out: String,
+
+        /// Перезаписать существующий файл
+        #[arg(long)]
+        force: bool,
+
+        /// Название мира
+        #[arg(long, default_value = "New World")]
+        name: String,
+
+        /// Seed мира (по умолчанию — от текущего времени)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        #[arg(long, value_enum, default_value_t = init::PlanetSize::Earth)]
+        planet: init::PlanetSize,
+
+        #[arg(long, value_enum, default_value_t = init::ClimateArchetype::Temperate)]
+        climate: init::ClimateArchetype,
+
+        /// Число биомов, от самых уместных для климата
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u8).range(1..=init::MAX_BIOMES as i64))]
+        biomes: u8,
+
+        /// Выключить катастрофы
+        #[arg(long)]
+        no_catastrophes: bool,
+    },
+    /// Сгенерировать карты и экспорты для списка миров из манифеста
+    Batch {
+        /// JSON-манифест: `{ "worlds": [{ "config", "seed", "width", "height", "outputs" }] }`
+        #[arg(long)]
+        manifest: String,
+
+        /// Сколько миров генерировать параллельно (по умолчанию — по числу ядер)
+        #[arg(long)]
+        jobs: Option<usize>,
+
+        /// Куда сохранить сводный отчёт (JSON); без флага — только итог в stdout
+        #[arg(long)]
+        report: Option<String>,
+    },
+    /// Сгенерировать два мира в одном разрешении и сравнить их в HTML-отчёте.
+    /// Чтобы сравнить два seed одного конфига, укажите его дважды и `--seed-b`
+    Compare {
+        /// Первый конфиг (A)
+        a: String,
+
+        /// Второй конфиг (B)
+        b: String,
+
+        /// Заменить seed мира A
+        #[arg(long)]
+        seed_a: Option<u64>,
+
+        /// Заменить seed мира B
+        #[arg(long)]
+        seed_b: Option<u64>,
+
+        /// Куда сохранить отчёт (HTML)
+        #[arg(long)]
+        out: String,
+    },
+    /// Отпечатки (SHA-1) heightmap, биомов и объектов мира `--config` в размере
+    /// `--width`×`--height` — для CI, чтобы ловить изменения генерации
+    Hash {
+        /// Заменить seed мира
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Формат вывода
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+
+        /// Сверить с сохранённым `--format json`; при расхождении — код выхода 1
+        #[arg(long)]
+        check: Option<String>,
+    },
+    /// Пересобирать превью при каждом изменении конфига (до Ctrl+C)
+    Watch {
+        /// Ширина превью; высота — в пропорции `--width`×`--height`
+        #[arg(long, default_value_t = 256)]
+        preview_width: u32,
+
+        /// Сколько ждать тишины после сохранения, прежде чем пересобирать, мс
+        #[arg(long, default_value_t = 300)]
+        debounce_ms: u64,
+
+        /// Heightmap в формате `--heightmap-format`
+        #[arg(long)]
+        heightmap_out: Option<String>,
+
+        /// Отмывка рельефа
+        #[arg(long)]
+        hillshade_out: Option<String>,
+
+        /// Карта биомов (PNG)
+        #[arg(long)]
+        biome_out: Option<String>,
+
+        /// Совмещённая карта (оформление — `--style`)
+        #[arg(long)]
+        worldview_out: Option<String>,
+    },
+    /// Сгенерировать мир и сохранить его бандлом `.seedworld`
+    Save {
+        /// Путь к бандлу
+        #[arg(long)]
+        out: String,
+
+        /// Что не сохранять (можно несколько раз)
+        #[arg(long, value_enum)]
+        without: Vec<BundlePart>,
+
+        /// За сколько лет сохранить ленту катастроф
+        #[arg(long, default_value_t = 500.0)]
+        catastrophe_years: f64,
+    },
+    /// Открыть бандл `.seedworld` (без генерации) и выгрузить из него карты
+    Load {
+        /// Путь к бандлу
+        path: String,
+
+        /// Heightmap в формате `--heightmap-format`
+        #[arg(long)]
+        heightmap_out: Option<String>,
+
+        /// Карта биомов (PNG)
+        #[arg(long)]
+        biome_out: Option<String>,
+
+        /// Совмещённая карта (оформление — `--style`)
+        #[arg(long)]
+        worldview_out: Option<String>,
+
+        /// Политическая карта (нужна сохранённая история)
+        #[arg(long)]
+        political_out: Option<String>,
+    },
+    /// Сгенерировать ленту катастроф и, по желанию, применить её к рельефу
+    Catastrophes {
+        /// Сколько лет симулировать
+        #[arg(long, default_value_t = 500.0)]
+        years: f64,
+
+        /// Применить события к heightmap и пересчитать биомы
+        #[arg(long)]
+        apply: bool,
+
+        /// Куда сохранить ленту событий (JSON)
+        #[arg(long)]
+        timeline_out: Option<String>,
+
+        /// Совмещённая карта до катастроф (оформление — `--style`)
+        #[arg(long)]
+        before: Option<String>,
+
+        /// Совмещённая карта после катастроф
+        #[arg(long, requires = "apply")]
+        after: Option<String>,
+    },
+    /// Интерактивный просмотр карты в терминале: сдвиг, масштаб, слои и
+    /// инспектор клетки под курсором
+    Tui {
+        /// Символы вместо цветных полублоков (для терминалов без 24-битного цвета
+        /// и Unicode)
+        #[arg(long)]
+        ascii: bool,
+    },
+    /// Сгенерировать объекты (деревья, камни, дома, руины) и выгрузить их
+    /// расстановку с координатами в метрах
+    Objects {
+        /// Участок карты в клетках: `x,y,width,height` (по умолчанию — вся карта)
+        #[arg(long)]
+        chunk: Option<String>,
+
+        /// Какие категории выгрузить, через запятую (по умолчанию — все)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        types: Vec<ObjectCategory>,
+
+        #[arg(long, value_enum, default_value_t = ObjectsFormat::Json)]
+        format: ObjectsFormat,
+
+        /// Куда сохранить расстановку
+        #[arg(long)]
+        out: String,
+    },
+    /// Заново сгенерировать участок карты в большем разрешении. Крупный рельеф
+    /// и биомы совпадают с полной картой, шум досчитывается до `--width`
+    /// пикселей по ширине участка; высота — по его пропорциям
+    Render {
+        /// Участок полной карты в её клетках: `x0,y0,x1,y1` (углы включительно)
+        #[arg(long)]
+        region: String,
+
+        /// Ширина полной карты, в клетках которой задан участок
+        #[arg(long, default_value_t = 512)]
+        map_width: u32,
+
+        /// Высота полной карты, в клетках которой задан участок
+        #[arg(long, default_value_t = 512)]
+        map_height: u32,
+
+        /// Heightmap участка в формате `--heightmap-format`
+        #[arg(long)]
+        heightmap_out: Option<String>,
+
+        /// Карта биомов участка (PNG)
+        #[arg(long)]
+        biome_out: Option<String>,
+
+        /// Совмещённая карта участка (рельеф + биомы)
+        #[arg(long)]
+        worldview_out: Option<String>,
+
+        /// Отмывка рельефа участка (солнце — `--sun-azimuth`/`--sun-altitude`)
+        #[arg(long)]
+        hillshade_out: Option<String>,
+    },
+    /// Экспорт мира в форматы других программ
+    Export {
+        #[command(subcommand)]
+        target: ExportTarget,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ExportTarget {
+    /// Сетка рельефа: `.obj`, `.gltf` или `.glb` (по расширению `--out`)
+    Mesh {
+        #[arg(long)]
+        out: String,
+
+        /// Множитель высот (регион в сотни километров при честном масштабе почти плоский)
+        #[arg(long, default_value_t = 1.0)]
+        vertical_scale: f32,
+
+        /// Доля треугольников, которую оставить после упрощения (0..1)
+        #[arg(long)]
+        simplify: Option<f32>,
+
+        /// Цвета вершин
+        #[arg(long, value_enum, default_value_t = MeshColors::Biome)]
+        colors: MeshColors,
+
+        /// Запечь карту мира (биомы + освещение) в текстуру по UV
+        #[arg(long)]
+        texture: bool,
+    },
+    /// Векторные слои (реки, озёра, берег, дороги, границы) в GeoJSON
+    Vectors {
+        #[arg(long)]
+        out: String,
+
+        /// Допуск упрощения линий, в клетках карты (0 — без упрощения)
+        #[arg(long, default_value_t = 0.75)]
+        tolerance: f32,
+    },
+    /// Навигационная сетка: `.obj` или `.json` (по расширению `--out`)
+    Navmesh {
+        #[arg(long)]
+        out: String,
+
+        /// Круче не пройти, градусы
+        #[arg(long, default_value_t = 45.0)]
+        max_slope: f64,
+
+        /// Глубина брода, м (0 — вода непроходима)
+        #[arg(long, default_value_t = 0.0)]
+        max_water_depth: f64,
+
+        /// Радиус агента, м
+        #[arg(long, default_value_t = 0.5)]
+        agent_radius: f64,
+
+        /// Ячеек на сторону клетки карты (1..16)
+        #[arg(long, default_value_t = 4)]
+        subdivisions: u32,
+
+        /// Сторона самого большого полигона, ячейки
+        #[arg(long, default_value_t = 16)]
+        max_polygon_cells: u32,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum MeshColors {
+    /// Цвет биома, вода — синим
+    Biome,
+    /// Серый по высоте
+    Height,
+    None,
+}
+
+/// Необязательные части бандла
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum BundlePart {
+    Objects,
+    History,
+    Catastrophes,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum WorldviewStyle {
+    /// Цвета биомов с освещением рельефа
+    Biome,
+    /// Послойная окраска по высоте и глубине с отмывкой
+    Hypsometric,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SummaryFormat {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ReportFormat {
+    Text,
+    Json,
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    println!("Loading world config from: {}", cli.config);
+    if let Some(n) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(n)
+            .build_global()
+            .map_err(|e| anyhow::anyhow!("failed to set up {n} threads: {e}"))?;
+    }
+
+    // отчёт проверки должен оставаться чистым (JSON) — без сводки мира
+    if let Some(Command::Validate { path, format }) = &cli.command {
+        let path = path.as_deref().unwrap_or(&cli.config);
+        let ok = run_validate(path, *format)?;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    // конфига ещё нет — его и создаём
+    if let Some(Command::Init {
+        interactive,
+        out,
+        force,
+        name,
+        seed,
+        planet,
+        climate,
+        biomes,
+        no_catastrophes,
+    }) = &cli.command
+    {
+        let choices = init::InitChoices {
+            name: name.clone(),
+            seed: seed.unwrap_or_else(init::fresh_seed),
+            planet: *planet,
+            climate: *climate,
+            biomes: *biomes as usize,
+            catastrophes: !no_catastrophes,
+        };
+        return run_init(choices, *interactive, out, *force);
+    }
+
+    // у пакета свои конфиги — общий `--config` не загружается
+    if let Some(Command::Batch {
+        manifest,
+        jobs,
+        report,
+    }) = &cli.command
+    {
+        let ok = run_batch(&cli, manifest, *jobs, report.as_deref())?;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    // у сравнения два своих конфига
+    if let Some(Command::Compare {
+        a,
+        b,
+        seed_a,
+        seed_b,
+        out,
+    }) = &cli.command
+    {
+        return run_compare(&cli, (a, *seed_a), (b, *seed_b), out);
+    }
+
+    // в stdout — только отпечатки, без сводки мира
+    if let Some(Command::Hash {
+        seed,
+        format,
+        check,
+    }) = &cli.command
+    {
+        let ok = run_hash(&cli, *seed, *format, check.as_deref())?;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    // конфиг перечитывается на каждое изменение и может быть пока с ошибкой
+    if let Some(Command::Watch {
+        preview_width,
+        debounce_ms,
+        heightmap_out,
+        hillshade_out,
+        biome_out,
+        worldview_out,
+    }) = &cli.command
+    {
+        let width = (*preview_width).max(2);
+        let height = ((width as u64 * cli.height as u64) / cli.width.max(1) as u64).max(2) as u32;
+        let outputs = watch::WatchOutputs {
+            heightmap: heightmap_out.as_deref(),
+            hillshade: hillshade_out.as_deref(),
+            biomes: biome_out.as_deref(),
+            worldview: worldview_out.as_deref(),
+        };
+        let debounce = std::time::Duration::from_millis(*debounce_ms);
+        return watch::run_watch(&cli, &cli.config, (width, height), debounce, &outputs);
+    }
+
+    // конфиг лежит в самом бандле
+    if let Some(Command::Load {
+        path,
+        heightmap_out,
+        biome_out,
+        worldview_out,
+        political_out,
+    }) = &cli.command
+    {
+        return run_load(
+            &cli,
+            path,
+            [heightmap_out, biome_out, worldview_out, political_out].map(Option::as_deref),
+        );
+    }
+
+    if cli.summary == SummaryFormat::Text {
+        println!("Loading world config from: {}", cli.config);
+    }
     let cfg = WorldConfig::from_file(&cli.config)?;
-    let world =
-        World::from_config(&cfg).map_err(|e| anyhow::anyhow!("failed to construct world: {e}"))?;
+    let mut world = World::with_map_size(&cfg, cli.width, cli.height)
+        .map_err(|e| anyhow::anyhow!("failed to construct world: {e}"))?;
+
+    let timings = PhaseTimings::new(cli.quiet);
 
     // Сводка
-    print_world_summary(&cfg, &world);
+    match cli.summary {
+        SummaryFormat::Text => print_world_summary(&cfg, &world),
+        SummaryFormat::Json => {
+            let summary = summary::build_summary(&cfg, &world, cli.width, cli.height, &timings);
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+            if cli.command.is_none() && !wants_maps(&cli) && cli.legend_out.is_none() {
+                timings.print();
+                return Ok(());
+            }
+        }
+    }
 
+    let result = match &cli.command {
+        Some(Command::History { out }) => run_history(&world, &timings, out.as_deref()),
+        Some(Command::Validate { .. })
+        | Some(Command::Batch { .. })
+        | Some(Command::Compare { .. })
+        | Some(Command::Init { .. })
+        | Some(Command::Hash { .. })
+        | Some(Command::Load { .. })
+        | Some(Command::Watch { .. }) => {
+            unreachable!("handled before loading the config")
+        }
+        Some(Command::Save {
+            out,
+            without,
+            catastrophe_years,
+        }) => run_save(&cli, &cfg, &timings, out, without, *catastrophe_years),
+        Some(Command::Catastrophes {
+            years,
+            apply,
+            timeline_out,
+            before,
+            after,
+        }) => run_catastrophes(
+            &cli,
+            &cfg,
+            &timings,
+            *years,
+            *apply,
+            timeline_out.as_deref(),
+            (before.as_deref(), after.as_deref()),
+        ),
+        Some(Command::Tui { ascii }) => run_tui(&world, &timings, *ascii),
+        Some(Command::Objects {
+            chunk,
+            types,
+            format,
+            out,
+        }) => run_objects(&cli, &cfg, &timings, chunk.as_deref(), types, *format, out),
+        Some(Command::Render {
+            region,
+            map_width,
+            map_height,
+            heightmap_out,
+            biome_out,
+            worldview_out,
+            hillshade_out,
+        }) => run_render(
+            &cli,
+            &cfg,
+            &timings,
+            (region, *map_width, *map_height),
+            RenderOutputs {
+                heightmap: heightmap_out.as_deref(),
+                biomes: biome_out.as_deref(),
+                worldview: worldview_out.as_deref(),
+                hillshade: hillshade_out.as_deref(),
+            },
+        ),
+        Some(Command::Export {
+            target:
+                ExportTarget::Mesh {
+                    out,
+                    vertical_scale,
+                    simplify,
+                    colors,
+                    texture,
+                },
+        }) => run_export_mesh(
+            &cli,
+            &cfg,
+            &timings,
+            out,
+            *vertical_scale,
+            *simplify,
+            *colors,
+            *texture,
+        ),
+        Some(Command::Export {
+            target: ExportTarget::Vectors { out, tolerance },
+        }) => run_export_vectors(&cli, &cfg, &timings, out, *tolerance),
+        Some(Command::Export {
+            target:
+                ExportTarget::Navmesh {
+                    out,
+                    max_slope,
+                    max_water_depth,
+                    agent_radius,
+                    subdivisions,
+                    max_polygon_cells,
+                },
+        }) => {
+            let options = NavMeshOptions {
+                max_slope_deg: *max_slope,
+                max_water_depth_m: *max_water_depth,
+                agent_radius_m: *agent_radius,
+                subdivisions: *subdivisions,
+                max_polygon_cells: *max_polygon_cells,
+            };
+            run_export_navmesh(&mut world, &timings, out, &options)
+        }
+        None => run_maps(&cli, &world, &timings),
+    };
+    timings.print();
+    result
+}
+
+/// Запрошена ли хоть одна карта `--*-out`
+fn wants_maps(cli: &Cli) -> bool {
+    cli.heightmap_out.is_some()
+        || cli.normal_out.is_some()
+        || cli.hillshade_out.is_some()
+        || cli.contours_out.is_some()
+        || cli.biome_out.is_some()
+        || cli.worldview_out.is_some()
+        || cli.political_out.is_some()
+        || cli.species_out.is_some()
+        || cli.splat_out.is_some()
+}
+
+/// Режим по умолчанию: генерация карт по флагам `--*-out`
+fn run_maps(cli: &Cli, world: &World, timings: &PhaseTimings) -> anyhow::Result<()> {
+    let cfg = world.config();
     // Нужно ли генерировать heightmap?
-    let need_heightmap =
-        cli.heightmap_out.is_some() || cli.biome_out.is_some() || cli.worldview_out.is_some();
+    let need_heightmap = wants_maps(cli);
+
+    let mut heightmap: Option<&Heightmap> = None;
+    let mut biomemap: Option<&BiomeMap> = None;
 
-    let mut heightmap: Option<Heightmap> = None;
-    let mut biomemap: Option<BiomeMap> = None;
+    // Легенде нужен только конфиг
+    if let Some(out_path) = &cli.legend_out {
+        println!("Saving legend ({:?}) to: {}", cli.style, out_path);
+        legend::save_legend(cfg, cli.style, out_path)?;
+    }
 
     if need_heightmap {
         println!();
         println!("Generating heightmap {}x{} ...", cli.width, cli.height);
-        let hm = generate_heightmap_from_config(&cfg, cli.width, cli.height);
-        heightmap = Some(hm);
+        heightmap = Some(world.heightmap_with_progress(timings));
     }
 
     // heightmap -> PNG
-    if let (Some(out_path), Some(ref hm)) = (&cli.heightmap_out, &heightmap) {
-        println!("Saving heightmap (grayscale) to: {}", out_path);
-        save_heightmap_to_png(hm, out_path)?;
+    if let (Some(out_path), Some(hm)) = (&cli.heightmap_out, heightmap) {
+        println!(
+            "Saving heightmap ({:?}) to: {}",
+            cli.heightmap_format, out_path
+        );
+        save_heightmap(hm, cfg, cli.heightmap_format, cli.pow2_plus_one, out_path)?;
+    }
+
+    // Нормали и отмывка рельефа
+    if let (Some(out_path), Some(hm)) = (&cli.normal_out, heightmap) {
+        println!("Saving normal map to: {}", out_path);
+        save_normal_map(hm, cfg, cli.z_factor, cli.normal_convention, out_path)?;
+    }
+    if let (Some(out_path), Some(hm)) = (&cli.hillshade_out, heightmap) {
+        println!("Saving hillshade to: {}", out_path);
+        let sun = hillshade_sun(cli, cfg);
+        save_hillshade(hm, cfg, cli.z_factor, sun, out_path)?;
+    }
+    if let (Some(out_path), Some(hm)) = (&cli.contours_out, heightmap) {
+        let count = save_contours_svg(hm, cfg, cli.interval, out_path)?;
+        println!(
+            "Saved {} contour lines every {} m to: {}",
+            count, cli.interval, out_path
+        );
     }
 
     // Генерация и сохранение карты биомов
-    if cli.biome_out.is_some() || cli.worldview_out.is_some() {
-        if let Some(ref hm) = heightmap {
-            println!("Generating biome map ...");
-            let bm = generate_biome_map_from_config(&cfg, hm);
-            biomemap = Some(bm);
-        }
+    if (cli.biome_out.is_some()
+        || cli.worldview_out.is_some()
+        || cli.political_out.is_some()
+        || cli.species_out.is_some()
+        || cli.splat_out.is_some())
+        && heightmap.is_some()
+    {
+        println!("Generating biome map ...");
+        biomemap = Some(world.biomes_with_progress(timings));
     }
 
-    if let (Some(out_path), Some(ref bm)) = (&cli.biome_out, &biomemap) {
+    if let (Some(out_path), Some(bm)) = (&cli.biome_out, biomemap) {
         println!("Saving biome map (color) to: {}", out_path);
-        save_biome_map_to_png(bm, &cfg, out_path)?;
+        save_biome_map_to_png(bm, cfg, out_path)?;
+    }
+
+    // Материалы поверхности
+    if let (Some(out_path), Some(_)) = (&cli.splat_out, biomemap) {
+        println!("Saving surface splat map to: {}", out_path);
+        save_splat_map_to_png(world.surface(), out_path)?;
     }
 
     // Совмещённая карта: биомы + освещение рельефа
-    if let (Some(out_path), Some(ref hm), Some(ref bm)) =
-        (&cli.worldview_out, &heightmap, &biomemap)
-    {
-        println!("Saving worldview (biomes + shading) to: {}", out_path);
-        save_worldview_to_png(hm, bm, &cfg, out_path)?;
+    if let (Some(out_path), Some(hm), Some(bm)) = (&cli.worldview_out, heightmap, biomemap) {
+        println!("Saving worldview ({:?}) to: {}", cli.style, out_path);
+        render_styled_worldview(cli, hm, bm, cfg, 1.0).save(out_path)?;
+    }
+
+    // Политическая карта: история → территории → границы
+    if let (Some(out_path), Some(hm), Some(bm)) = (&cli.political_out, heightmap, biomemap) {
+        println!("Simulating history ...");
+        let history = timings.time("history", || simulate_history(cfg, hm, bm, cfg.world_seed));
+        println!(
+            "  {} factions, {} cities",
+            history.factions.len(),
+            history.cities.len()
+        );
+        println!("Saving political map to: {}", out_path);
+        save_political_map_to_png(hm, &history, cfg, out_path)?;
+    }
+
+    // Ареалы видов
+    if let (Some(prefix), Some(hm), Some(bm)) = (&cli.species_out, heightmap, biomemap) {
+        println!("Computing species distribution ...");
+        for dist in generate_species_distribution(cfg, bm, world.climate()) {
+            let path = format!("{prefix}_{}.png", dist.species_id);
+            println!(
+                "Saving {} range ({:.0}% of map) to: {}",
+                dist.species_id,
+                dist.range_share() * 100.0,
+                path
+            );
+            save_species_overlay_to_png(hm, &dist, cfg, &path)?;
+        }
     }
 
     println!("Done.");
     Ok(())
 }
 
-// ---------- Сохранение heightmap ----------
+/// `seed-cli history`: симуляция истории и экспорт летописи
+fn run_history(world: &World, timings: &PhaseTimings, out: Option<&str>) -> anyhow::Result<()> {
+    let cfg = world.config();
+    let (width, height) = world.map_size();
+    println!();
+    println!("Generating world {}x{} ...", width, height);
+    let hm = world.heightmap_with_progress(timings);
+    let bm = world.biomes_with_progress(timings);
 
-fn save_heightmap_to_png(hm: &Heightmap, path: &str) -> anyhow::Result<()> {
-    let mut img: GrayImage = GrayImage::new(hm.width, hm.height);
+    println!("Simulating history ...");
+    let history = timings.time("history", || simulate_history(cfg, hm, bm, cfg.world_seed));
+    let chronicle = build_chronicle(cfg, &history);
+    println!(
+        "  {} factions, {} cities, {} ruins, {} events",
+        history.factions.len(),
+        history.cities.len(),
+        history.ruins.len(),
+        chronicle.entries.len()
+    );
 
-    for y in 0..hm.height {
-        for x in 0..hm.width {
-            let v = hm.get(x, y); // 0.0..1.0
-            let v_u8 = (v.clamp(0.0, 1.0) * 255.0) as u8;
-            img.put_pixel(x, y, image::Luma([v_u8]));
+    match out {
+        Some(path) if path.to_ascii_lowercase().ends_with(".json") => {
+            println!("Saving chronicle (JSON) to: {}", path);
+            std::fs::write(path, serde_json::to_string_pretty(&chronicle)?)?;
+        }
+        Some(path) => {
+            println!("Saving chronicle (text) to: {}", path);
+            std::fs::write(path, chronicle.to_text())?;
+        }
+        None => {
+            println!();
+            print!("{}", chronicle.to_text());
         }
     }
 
-    img.save(path)?;
+    println!("Done.");
+    Ok(())
+}
+
+/// `seed-cli tui`: генерация мира и просмотр в терминале
+fn run_tui(world: &World, timings: &PhaseTimings, ascii: bool) -> anyhow::Result<()> {
+    let (width, height) = world.map_size();
+    println!();
+    println!("Generating world {}x{} ...", width, height);
+    tui::run_tui(
+        tui::TuiWorld {
+            cfg: world.config(),
+            hm: world.heightmap_with_progress(timings),
+            bm: world.biomes_with_progress(timings),
+        },
+        ascii,
+    )
+}
+
+/// `seed-cli objects`: объекты участка с фильтром по категориям
+fn run_objects(
+    cli: &Cli,
+    cfg: &WorldConfig,
+    timings: &PhaseTimings,
+    chunk: Option<&str>,
+    types: &[ObjectCategory],
+    format: ObjectsFormat,
+    out: &str,
+) -> anyhow::Result<()> {
+    let chunk = match chunk {
+        Some(s) => objects::parse_chunk(s, cli.width, cli.height)?,
+        None => objects::Chunk {
+            x: 0,
+            y: 0,
+            width: cli.width,
+            height: cli.height,
+        },
+    };
+
+    println!();
+    println!("Generating world {}x{} ...", cli.width, cli.height);
+    let hm = generate_heightmap_with_progress(cfg, cli.width, cli.height, timings);
+    let bm = generate_biome_map_with_progress(cfg, &hm, timings);
+    let placed = timings.time("objects", || {
+        generate_objects_for_chunk(
+            cfg,
+            &hm,
+            &bm,
+            chunk.x,
+            chunk.y,
+            chunk.width,
+            chunk.height,
+            cfg.world_seed,
+        )
+    });
+
+    let export = objects::build_export(cfg, &hm, chunk, &placed, types);
+    println!(
+        "Saving {} of {} objects ({:?}) to: {}",
+        export.objects.len(),
+        placed.len(),
+        format,
+        out
+    );
+    objects::write_export(&export, format, out)?;
+    Ok(())
+}
+
+/// `seed-cli save`: генерация мира и запись бандла
+fn run_save(
+    cli: &Cli,
+    cfg: &WorldConfig,
+    timings: &PhaseTimings,
+    out: &str,
+    without: &[BundlePart],
+    catastrophe_years: f64,
+) -> anyhow::Result<()> {
+    let wanted = |part: BundlePart| !without.contains(&part);
+
+    println!();
+    println!("Generating world {}x{} ...", cli.width, cli.height);
+    let heightmap = generate_heightmap_with_progress(cfg, cli.width, cli.height, timings);
+    let biomes = generate_biome_map_with_progress(cfg, &heightmap, timings);
+
+    let objects = wanted(BundlePart::Objects).then(|| {
+        timings.time("objects", || {
+            generate_objects_for_chunk(
+                cfg,
+                &heightmap,
+                &biomes,
+                0,
+                0,
+                cli.width,
+                cli.height,
+                cfg.world_seed,
+            )
+        })
+    });
+    let history = wanted(BundlePart::History).then(|| {
+        println!("Simulating history ...");
+        timings.time("history", || {
+            simulate_history(cfg, &heightmap, &biomes, cfg.world_seed)
+        })
+    });
+    let catastrophes = wanted(BundlePart::Catastrophes).then(|| {
+        let mut events = generate_catastrophes(cfg, catastrophe_years, cfg.world_seed);
+        events.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        events
+    });
+
+    let bundle = WorldBundle {
+        config: cfg.clone(),
+        heightmap,
+        biomes,
+        objects,
+        history,
+        catastrophes,
+    };
+    let bytes = timings.time("save", || bundle.to_bytes())?;
+    println!("Saving {} KiB bundle to: {}", bytes.len() / 1024, out);
+    std::fs::write(out, bytes)?;
+
+    println!("Done.");
+    Ok(())
+}
+
+/// `seed-cli load`: содержимое бандла и карты из него
+fn run_load(cli: &Cli, path: &str, outputs: [Option<&str>; 4]) -> anyhow::Result<()> {
+    let [heightmap_out, biome_out, worldview_out, political_out] = outputs;
+    println!("Loading world bundle from: {}", path);
+    let bytes = std::fs::read(path)?;
+    let index = BundleReader::new(&bytes)?.index;
+    println!(
+        "  {} ({}), seed {}, {}x{}",
+        index.world_name, index.world_id, index.world_seed, index.width, index.height
+    );
+    for section in &index.sections {
+        println!(
+            "  {:<13} {:>9} KiB ({} KiB packed)",
+            section.name,
+            section.raw_length / 1024,
+            section.length / 1024
+        );
+    }
+
+    let bundle = WorldBundle::from_bytes(&bytes)?;
+    let cfg = &bundle.config;
+    if let Some(history) = &bundle.history {
+        println!(
+            "  history: {} years, {} factions, {} cities",
+            history.years_simulated,
+            history.factions.len(),
+            history.cities.len()
+        );
+    }
+    if let Some(objects) = &bundle.objects {
+        println!("  objects: {}", objects.len());
+    }
+    if let Some(catastrophes) = &bundle.catastrophes {
+        println!("  catastrophes: {}", catastrophes.len());
+    }
+
+    if let Some(out_path) = heightmap_out {
+        println!(
+            "Saving heightmap ({:?}) to: {}",
+            cli.heightmap_format, out_path
+        );
+        save_heightmap(
+            &bundle.heightmap,
+            cfg,
+            cli.heightmap_format,
+            cli.pow2_plus_one,
+            out_path,
+        )?;
+    }
+    if let Some(out_path) = biome_out {
+        println!("Saving biome map (color) to: {}", out_path);
+        save_biome_map_to_png(&bundle.biomes, cfg, out_path)?;
+    }
+    if let Some(out_path) = worldview_out {
+        println!("Saving worldview ({:?}) to: {}", cli.style, out_path);
+        render_styled_worldview(cli, &bundle.heightmap, &bundle.biomes, cfg, 1.0).save(out_path)?;
+    }
+    if let Some(out_path) = political_out {
+        let history = bundle
+            .history
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("bundle '{path}' has no history section"))?;
+        println!("Saving political map to: {}", out_path);
+        save_political_map_to_png(&bundle.heightmap, history, cfg, out_path)?;
+    }
+
+    println!("Done.");
+    Ok(())
+}
+
+/// `seed-cli catastrophes`: лента событий, их след на карте и снимки до/после
+fn run_catastrophes(
+    cli: &Cli,
+    cfg: &WorldConfig,
+    timings: &PhaseTimings,
+    years: f64,
+    apply: bool,
+    timeline_out: Option<&str>,
+    (before_out, after_out): (Option<&str>, Option<&str>),
+) -> anyhow::Result<()> {
+    println!();
+    println!("Generating world {}x{} ...", cli.width, cli.height);
+    let hm = generate_heightmap_with_progress(cfg, cli.width, cli.height, timings);
+    let bm = generate_biome_map_with_progress(cfg, &hm, timings);
+
+    let mut events = generate_catastrophes(cfg, years, cfg.world_seed);
+    events.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    println!(
+        "  {} catastrophes in {} years ({} major)",
+        events.len(),
+        years,
+        events.iter().filter(|c| c.is_major()).count()
+    );
+
+    if let Some(out_path) = before_out {
+        println!("Saving worldview before to: {}", out_path);
+        render_styled_worldview(cli, &hm, &bm, cfg, 1.0).save(out_path)?;
+    }
+
+    let effects = if apply {
+        println!("Applying catastrophes ...");
+        let mut hm_after = hm.clone();
+        timings.time("catastrophes", || {
+            for cat in &events {
+                apply_catastrophe_to_heightmap(&mut hm_after, cat, cfg);
+            }
+        });
+        let bm_after = generate_biome_map_with_progress(cfg, &hm_after, timings);
+        let effects = catastrophes::compare(cfg, (&hm, &bm), (&hm_after, &bm_after));
+        println!(
+            "  {} cells changed (up to +{:.0} m / -{:.0} m), land {} -> {} cells",
+            effects.cells_changed,
+            effects.max_raise_m,
+            effects.max_lower_m,
+            effects.land_cells_before,
+            effects.land_cells_after
+        );
+        if let Some(out_path) = after_out {
+            println!("Saving worldview after to: {}", out_path);
+            render_styled_worldview(cli, &hm_after, &bm_after, cfg, 1.0).save(out_path)?;
+        }
+        Some(effects)
+    } else {
+        None
+    };
+
+    if let Some(out_path) = timeline_out {
+        println!("Saving timeline to: {}", out_path);
+        let log = catastrophes::build_log(cfg, years, &events, effects);
+        std::fs::write(out_path, serde_json::to_string_pretty(&log)?)?;
+    }
+
+    println!("Done.");
+    Ok(())
+}
+
+/// Куда сохранить карты участка в `seed-cli render`
+struct RenderOutputs<'a> {
+    heightmap: Option<&'a str>,
+    biomes: Option<&'a str>,
+    worldview: Option<&'a str>,
+    hillshade: Option<&'a str>,
+}
+
+/// `seed-cli render`: участок `region` карты `map_width`×`map_height` в разрешении `--width`
+fn run_render(
+    cli: &Cli,
+    cfg: &WorldConfig,
+    timings: &PhaseTimings,
+    (region, map_width, map_height): (&str, u32, u32),
+    outputs: RenderOutputs,
+) -> anyhow::Result<()> {
+    let [x0, y0, x1, y1] = parse_region(region, map_width, map_height)?;
+    let width = cli.width.max(2);
+    let height = ((width - 1) as f64 * (y1 - y0) as f64 / (x1 - x0) as f64).round() as u32 + 1;
+    let window = MapWindow::from_cells(map_width, map_height, x0, y0, x1, y1);
+
+    println!();
+    println!(
+        "Rendering region {x0},{y0}..{x1},{y1} of {map_width}x{map_height} at {width}x{height} ..."
+    );
+    let hm = generate_heightmap_window(cfg, map_width, map_height, window, width, height, timings);
+    let bm = generate_biome_map_window(cfg, &hm, window, timings);
+
+    // масштаб участка: шаг его пикселей в метрах вместо шага полной карты
+    let map_cell_m = cfg.scale.region_size_km * 1000.0 / map_width as f64;
+    let pixel_m = (x1 - x0) as f64 * map_cell_m / (width - 1) as f64;
+    let mut crop_cfg = cfg.clone();
+    crop_cfg.scale.region_size_km = pixel_m * width as f64 / 1000.0;
+    println!("  {:.1} m per pixel", pixel_m);
+
+    if let Some(out_path) = outputs.heightmap {
+        println!(
+            "Saving heightmap ({:?}) to: {}",
+            cli.heightmap_format, out_path
+        );
+        save_heightmap(&hm, &crop_cfg, cli.heightmap_format, false, out_path)?;
+    }
+    if let Some(out_path) = outputs.biomes {
+        println!("Saving biome map (color) to: {}", out_path);
+        save_biome_map_to_png(&bm, &crop_cfg, out_path)?;
+    }
+    if let Some(out_path) = outputs.worldview {
+        println!("Saving worldview ({:?}) to: {}", cli.style, out_path);
+        let zoom = (map_cell_m / pixel_m) as f32;
+        render_styled_worldview(cli, &hm, &bm, &crop_cfg, zoom).save(out_path)?;
+    }
+    if let Some(out_path) = outputs.hillshade {
+        println!("Saving hillshade to: {}", out_path);
+        let sun = hillshade_sun(cli, cfg);
+        save_hillshade(&hm, &crop_cfg, cli.z_factor, sun, out_path)?;
+    }
+
+    println!("Done.");
     Ok(())
 }
 
+/// Солнце для отмывки: `--sun-hour` или `--sun-azimuth`/`--sun-altitude`
+pub(crate) fn hillshade_sun(cli: &Cli, cfg: &WorldConfig) -> Sun {
+    match cli.sun_hour {
+        Some(hour) => {
+            let astro = Astronomy::from_config(cfg);
+            let sun = astro.sun(0.0, 0.0, cli.sun_day * astro.day_length_s() + hour * 3600.0);
+            Sun {
+                azimuth_deg: sun.azimuth_deg,
+                altitude_deg: sun.elevation_deg,
+            }
+        }
+        None => Sun {
+            azimuth_deg: cli.sun_azimuth,
+            altitude_deg: cli.sun_altitude,
+        },
+    }
+}
+
+/// Длина в метрах: `100m`, `0.5km` или число метров
+fn parse_meters(s: &str) -> Result<f64, String> {
+    let t = s.trim();
+    let (number, scale) = if let Some(km) = t.strip_suffix("km") {
+        (km, 1000.0)
+    } else {
+        (t.strip_suffix('m').unwrap_or(t), 1.0)
+    };
+    match number.trim().parse::<f64>() {
+        Ok(v) if v > 0.0 && v.is_finite() => Ok(v * scale),
+        _ => Err(format!(
+            "expected a positive length like 100m or 0.5km, got '{s}'"
+        )),
+    }
+}
+
+/// `x0,y0,x1,y1` в клетках карты `width`×`height`
+fn parse_region(s: &str, width: u32, height: u32) -> anyhow::Result<[u32; 4]> {
+    let parts: Vec<u32> = s
+        .split(',')
+        .map(|p| p.trim().parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| anyhow::anyhow!("invalid --region '{s}': {e}"))?;
+    let [x0, y0, x1, y1] = parts[..] else {
+        anyhow::bail!("invalid --region '{s}': expected x0,y0,x1,y1");
+    };
+    if x0 >= x1 || y0 >= y1 {
+        anyhow::bail!("invalid --region '{s}': expected x0 < x1 and y0 < y1");
+    }
+    if x1 >= width || y1 >= height {
+        anyhow::bail!("--region '{s}' is outside the {width}x{height} map");
+    }
+    Ok([x0, y0, x1, y1])
+}
+
+/// `seed-cli compare`: два мира в разрешении `--width`×`--height` и отчёт
+fn run_compare(
+    cli: &Cli,
+    (path_a, seed_a): (&str, Option<u64>),
+    (path_b, seed_b): (&str, Option<u64>),
+    out: &str,
+) -> anyhow::Result<()> {
+    let load = |path: &str, seed: Option<u64>| -> anyhow::Result<WorldConfig> {
+        let mut cfg = WorldConfig::from_file(path)?;
+        if let Some(seed) = seed {
+            cfg.world_seed = seed;
+            cfg.geology.heightmap.base_seed = seed;
+        }
+        Ok(cfg)
+    };
+    let cfgs = [load(path_a, seed_a)?, load(path_b, seed_b)?];
+    let timings = PhaseTimings::new(cli.quiet);
+
+    let mut worlds = Vec::new();
+    for (name, cfg) in ["A", "B"].iter().zip(&cfgs) {
+        println!(
+            "Generating world {} (seed {}) {}x{} ...",
+            name, cfg.world_seed, cli.width, cli.height
+        );
+        let hm = generate_heightmap_with_progress(cfg, cli.width, cli.height, &timings);
+        let bm = generate_biome_map_with_progress(cfg, &hm, &timings);
+        let render = render_styled_worldview(cli, &hm, &bm, cfg, 1.0);
+        worlds.push((hm, bm, render));
+    }
+
+    let side = |i: usize, label| compare::Side {
+        label,
+        cfg: &cfgs[i],
+        hm: &worlds[i].0,
+        bm: &worlds[i].1,
+        render: &worlds[i].2,
+    };
+    let report = compare::build_report(&side(0, path_a), &side(1, path_b))?;
+    println!("Saving comparison report to: {}", out);
+    std::fs::write(out, report)?;
+    timings.print();
+    Ok(())
+}
+
+/// `seed-cli hash`: `false`, если отпечатки не сошлись с `--check`
+fn run_hash(
+    cli: &Cli,
+    seed: Option<u64>,
+    format: ReportFormat,
+    check: Option<&str>,
+) -> anyhow::Result<bool> {
+    let mut cfg = WorldConfig::from_file(&cli.config)?;
+    if let Some(seed) = seed {
+        cfg.world_seed = seed;
+        cfg.geology.heightmap.base_seed = seed;
+    }
+    let timings = PhaseTimings::quiet();
+    let hm = generate_heightmap_with_progress(&cfg, cli.width, cli.height, &timings);
+    let bm = generate_biome_map_with_progress(&cfg, &hm, &timings);
+    let objects =
+        generate_objects_for_chunk(&cfg, &hm, &bm, 0, 0, cli.width, cli.height, cfg.world_seed);
+    let hashes = hash::compute(&cfg, &hm, &bm, &objects)?;
+
+    match format {
+        ReportFormat::Text => print!("{}", hashes.to_text()),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&hashes)?),
+    }
+
+    let Some(path) = check else {
+        return Ok(true);
+    };
+    let expected: hash::WorldHashes = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if (expected.world_seed, expected.width, expected.height)
+        != (hashes.world_seed, hashes.width, hashes.height)
+    {
+        eprintln!(
+            "error: {} was recorded for seed {} at {}x{}, not seed {} at {}x{}",
+            path,
+            expected.world_seed,
+            expected.width,
+            expected.height,
+            hashes.world_seed,
+            hashes.width,
+            hashes.height
+        );
+        return Ok(false);
+    }
+    let changed = hashes.mismatches(&expected);
+    if changed.is_empty() {
+        eprintln!("Hashes match {}", path);
+        Ok(true)
+    } else {
+        eprintln!("error: {} differ from {}", changed.join(", "), path);
+        Ok(false)
+    }
+}
+
+/// `seed-cli init`: заготовка конфига, проверенная как в `validate`
+fn run_init(
+    choices: init::InitChoices,
+    interactive: bool,
+    out: &str,
+    force: bool,
+) -> anyhow::Result<()> {
+    if !force && std::path::Path::new(out).exists() {
+        anyhow::bail!("{out} already exists (use --force to overwrite)");
+    }
+    let choices = if interactive {
+        init::prompt_choices(choices)?
+    } else {
+        choices
+    };
+
+    let cfg = init::build_config(&choices)?;
+    let mut diagnostics = cfg.validate();
+    diagnostics.extend(validate_with_core(&cfg));
+    for d in &diagnostics {
+        eprintln!("{d}");
+    }
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        anyhow::bail!("generated config did not pass validation");
+    }
+
+    std::fs::write(out, serde_json::to_string_pretty(&cfg)? + "\n")?;
+    println!(
+        "Wrote {} ({}, seed {}, {} biomes)",
+        out,
+        cfg.meta.name,
+        cfg.world_seed,
+        cfg.biomes.len()
+    );
+    println!("Next: seed-cli -c {} --worldview-out world.png", out);
+    Ok(())
+}
+
+/// `seed-cli batch`: миры из манифеста; `false`, если хоть один не удался
+fn run_batch(
+    cli: &Cli,
+    manifest_path: &str,
+    jobs: Option<usize>,
+    report_path: Option<&str>,
+) -> anyhow::Result<bool> {
+    let path = std::path::Path::new(manifest_path);
+    let manifest = batch::load_manifest(path)?;
+    let base_dir = path.parent().unwrap_or(std::path::Path::new("."));
+    let jobs = jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+    });
+    println!(
+        "Generating {} worlds with {} workers ...",
+        manifest.worlds.len(),
+        jobs
+    );
+
+    let report = batch::run_batch(&manifest, base_dir, jobs, (cli.width, cli.height));
+    println!(
+        "{} succeeded, {} failed in {:.1} s",
+        report.succeeded, report.failed, report.seconds
+    );
+    if let Some(out) = report_path {
+        println!("Saving report to: {}", out);
+        std::fs::write(out, serde_json::to_string_pretty(&report)?)?;
+    }
+    Ok(report.failed == 0)
+}
+
+/// `seed-cli export mesh`: триангуляция рельефа
+#[allow(clippy::too_many_arguments)]
+fn run_export_mesh(
+    cli: &Cli,
+    cfg: &WorldConfig,
+    timings: &PhaseTimings,
+    out: &str,
+    vertical_scale: f32,
+    simplify: Option<f32>,
+    colors: MeshColors,
+    texture: bool,
+) -> anyhow::Result<()> {
+    println!();
+    println!("Generating world {}x{} ...", cli.width, cli.height);
+    let hm = generate_heightmap_with_progress(cfg, cli.width, cli.height, timings);
+    let bm = generate_biome_map_with_progress(cfg, &hm, timings);
+
+    let mut mesh = TerrainMesh::from_heightmap(cfg, &hm, vertical_scale);
+    println!("  {} triangles", mesh.triangle_count());
+    if let Some(keep) = simplify {
+        println!("Simplifying to {:.0}% ...", keep * 100.0);
+        mesh.simplify(keep);
+        println!("  {} triangles", mesh.triangle_count());
+    }
+
+    println!("Saving mesh to: {}", out);
+    save_mesh(cfg, &hm, &bm, &mesh, colors, texture, out)?;
+
+    println!("Done.");
+    Ok(())
+}
+
+/// Сетка с цветами вершин и текстурой; формат — по расширению `out`
+fn save_mesh(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    mesh: &TerrainMesh,
+    colors: MeshColors,
+    texture: bool,
+    out: &str,
+) -> anyhow::Result<()> {
+    let palette = build_biome_palette(cfg);
+    let cell = |uv: [f32; 2]| {
+        (
+            (uv[0] * (hm.width - 1) as f32).round() as u32,
+            (uv[1] * (hm.height - 1) as f32).round() as u32,
+        )
+    };
+    let colors = match colors {
+        MeshColors::None => None,
+        MeshColors::Biome => Some(
+            mesh.uvs
+                .iter()
+                .map(|&uv| {
+                    let (x, y) = cell(uv);
+                    let c = match bm.get_index(x, y) {
+                        Some(i) if i < palette.len() => palette[i],
+                        _ => WATER_COLOR,
+                    };
+                    c.map(|v| v as f32 / 255.0)
+                })
+                .collect(),
+        ),
+        MeshColors::Height => Some(
+            mesh.uvs
+                .iter()
+                .map(|&uv| {
+                    let (x, y) = cell(uv);
+                    [hm.get(x, y); 3]
+                })
+                .collect(),
+        ),
+    };
+
+    let export = export::MeshExport {
+        mesh,
+        normals: mesh.normals(),
+        colors,
+        texture: texture.then(|| render_worldview(hm, bm, cfg, 1.0)),
+    };
+    export::write_mesh(&export, out)
+}
+
+/// `seed-cli export vectors`: гидрография, дороги и границы в GeoJSON
+fn run_export_vectors(
+    cli: &Cli,
+    cfg: &WorldConfig,
+    timings: &PhaseTimings,
+    out: &str,
+    tolerance: f32,
+) -> anyhow::Result<()> {
+    println!();
+    println!("Generating world {}x{} ...", cli.width, cli.height);
+    let hm = generate_heightmap_with_progress(cfg, cli.width, cli.height, timings);
+    let bm = generate_biome_map_with_progress(cfg, &hm, timings);
+
+    println!("Simulating history ...");
+    let history = timings.time("history", || {
+        simulate_history(cfg, &hm, &bm, cfg.world_seed)
+    });
+
+    let collection = geojson::build_vectors(cfg, &hm, &history, tolerance);
+    let count = collection["features"].as_array().map_or(0, Vec::len);
+    println!("Saving {} features to: {}", count, out);
+    std::fs::write(out, serde_json::to_string(&collection)?)?;
+
+    println!("Done.");
+    Ok(())
+}
+
+/// `seed-cli export navmesh`: навигационная сетка с препятствиями-объектами
+fn run_export_navmesh(
+    world: &mut World,
+    timings: &PhaseTimings,
+    out: &str,
+    options: &NavMeshOptions,
+) -> anyhow::Result<()> {
+    let (width, height) = world.map_size();
+    println!();
+    println!("Generating world {}x{} ...", width, height);
+    world.biomes_with_progress(timings);
+
+    println!("Building navmesh ...");
+    let navmesh = timings.time("navmesh", || world.navmesh(options));
+    println!(
+        "Saving navmesh ({} polygons, {:.1} km² walkable) to: {}",
+        navmesh.polygons.len(),
+        navmesh.area_m2() / 1e6,
+        out
+    );
+    export::write_navmesh(&navmesh, out)?;
+
+    println!("Done.");
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ValidationReport<'a> {
+    path: &'a str,
+    valid: bool,
+    errors: usize,
+    warnings: usize,
+    diagnostics: &'a [Diagnostic],
+}
+
+/// `seed-cli validate`: печатает найденные проблемы; `false`, если есть ошибки
+fn run_validate(path: &str, format: ReportFormat) -> anyhow::Result<bool> {
+    let diagnostics = match WorldConfig::from_file(path) {
+        Ok(cfg) => {
+            let mut d = cfg.validate();
+            d.extend(validate_with_core(&cfg));
+            d
+        }
+        Err(e) => vec![Diagnostic::error("$", e.to_string())],
+    };
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+
+    match format {
+        ReportFormat::Json => {
+            let report = ValidationReport {
+                path,
+                valid: errors == 0,
+                errors,
+                warnings,
+                diagnostics: &diagnostics,
+            };
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        ReportFormat::Text => {
+            for d in &diagnostics {
+                println!("{d}");
+            }
+            println!("{path}: {errors} error(s), {warnings} warning(s)");
+        }
+    }
+    Ok(errors == 0)
+}
+
+/// Проверки, которым нужна логика ядра: пищевая сеть, типы заданий,
+/// селекторы целей в шаблонах, стили имён
+fn validate_with_core(cfg: &WorldConfig) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+
+    let species = &cfg.ecosystems.species_definitions;
+    let species_path = |id: &str, field: &str| {
+        let i = species.iter().position(|s| s.id == id).unwrap_or(0);
+        format!("$.ecosystems.speciesDefinitions[{i}].{field}")
+    };
+    for issue in FoodWeb::from_config(&cfg.ecosystems).issues {
+        let d = match &issue {
+            FoodWebIssue::UnknownTrophicLevel { species, .. } => {
+                Diagnostic::warning(species_path(species, "trophicLevel"), issue.to_string())
+            }
+            FoodWebIssue::UnknownPrey { species, .. } | FoodWebIssue::SelfPrey { species } => {
+                Diagnostic::error(species_path(species, "prey"), issue.to_string())
+            }
+            FoodWebIssue::HerbivoreWithPrey { species } => {
+                Diagnostic::warning(species_path(species, "prey"), issue.to_string())
+            }
+            FoodWebIssue::NoPrey { species } => {
+                Diagnostic::warning(species_path(species, "trophicLevel"), issue.to_string())
+            }
+        };
+        out.push(d);
+    }
+
+    for (i, f) in cfg.civilizations.faction_presets.iter().enumerate() {
+        if let Some(style) = &f.name_style {
+            if NameStyle::by_id(style).is_none() {
+                out.push(Diagnostic::warning(
+                    format!("$.civilizations.factionPresets[{i}].nameStyle"),
+                    format!("unknown name style '{style}', picked by biome instead"),
+                ));
+            }
+        }
+    }
+
+    let nd = &cfg.narrative_director;
+    for (i, t) in nd.quest_generation.preferred_quest_types.iter().enumerate() {
+        if QuestType::from_id(t).is_none() {
+            out.push(Diagnostic::warning(
+                format!("$.narrativeDirector.questGeneration.preferredQuestTypes[{i}]"),
+                format!("unknown quest type '{t}' is ignored"),
+            ));
+        }
+    }
+    for (i, t) in nd.quest_templates.iter().flatten().enumerate() {
+        let path = format!("$.narrativeDirector.questTemplates[{i}]");
+        if QuestType::from_id(&t.quest_type).is_none() {
+            out.push(Diagnostic::error(
+                format!("{path}.questType"),
+                format!("unknown quest type '{}'", t.quest_type),
+            ));
+        }
+        for (j, o) in t.objectives.iter().enumerate() {
+            let target_path = format!("{path}.objectives[{j}].target");
+            match TargetSelector::parse(&o.target) {
+                Ok(TargetSelector::Same) if j == 0 => out.push(Diagnostic::error(
+                    target_path,
+                    "first objective cannot target 'same'",
+                )),
+                Ok(_) => {}
+                Err(CoreError::Config(msg)) => out.push(Diagnostic::error(target_path, msg)),
+            }
+        }
+    }
+
+    out
+}
+
+// ---------- Карта мира ----------
+
 fn save_worldview_to_png(
     hm: &Heightmap,
     bm: &BiomeMap,
     cfg: &WorldConfig,
     path: &str,
 ) -> anyhow::Result<()> {
+    render_worldview(hm, bm, cfg, 1.0).save(path)?;
+    Ok(())
+}
+
+/// Совмещённая карта в оформлении `--style`
+fn render_styled_worldview(
+    cli: &Cli,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    cfg: &WorldConfig,
+    zoom: f32,
+) -> RgbImage {
+    match cli.style {
+        WorldviewStyle::Biome => render_worldview(hm, bm, cfg, zoom),
+        WorldviewStyle::Hypsometric => {
+            let sun = hillshade_sun(cli, cfg);
+            render_hypsometric(hm, cfg, cli.z_factor, sun)
+        }
+    }
+}
+
+/// Цвет воды (пока без ocean-биома)
+const WATER_COLOR: [u8; 3] = [40, 80, 160];
+
+/// Биомы с освещением рельефа; `zoom` — во сколько раз пиксель мельче клетки
+/// полной карты (склоны на пиксель у увеличенного участка положе)
+fn render_worldview(hm: &Heightmap, bm: &BiomeMap, cfg: &WorldConfig, zoom: f32) -> RgbImage {
     let mut img: RgbImage = ImageBuffer::new(hm.width, hm.height);
 
     // Палитра биомов
     let palette = build_biome_palette(cfg);
 
-    // Цвет воды (пока без ocean-биома)
-    let water_color = [40u8, 80u8, 160u8];
-
     // Направление света (примерно северо-запад, сверху)
     let light_dir = normalize3(0.6, 0.6, 1.0);
 
     // Насколько сильно высота будет влиять на наклон нормали
-    let slope_scale = 40.0_f32;
+    let slope_scale = 40.0_f32 * zoom;
 
     for y in 0..hm.height {
         for x in 0..hm.width {
-            // Высота в центре
-            let hc = hm.get(x, y);
-
             // Соседи (с клэмпом по краю)
             let xl = x.saturating_sub(1);
             let xr = (x + 1).min(hm.width - 1);
@@ -145,8 +1759,8 @@ fn save_worldview_to_png(
             let hd = hm.get(x, yd);
 
             // Градиенты высоты
-            let dx = (hr - hl) as f32;
-            let dy = (hd - hu) as f32;
+            let dx = hr - hl;
+            let dy = hd - hu;
 
             // Нормаль поверхности (приблизительная)
             let nx = -dx * slope_scale;
@@ -167,7 +1781,7 @@ fn save_worldview_to_png(
             // Цвет биома или воды
             let base_color = match bm.get_index(x, y) {
                 Some(idx) if idx < palette.len() => palette[idx],
-                _ => water_color,
+                _ => WATER_COLOR,
             };
 
             let r = (base_color[0] as f32 * shade).round().clamp(0.0, 255.0) as u8;
@@ -178,10 +1792,167 @@ fn save_worldview_to_png(
         }
     }
 
+    img
+}
+
+// ---------- Ареалы видов ----------
+
+fn save_species_overlay_to_png(
+    hm: &Heightmap,
+    dist: &SpeciesDistribution,
+    cfg: &WorldConfig,
+    path: &str,
+) -> anyhow::Result<()> {
+    let mut img: RgbImage = ImageBuffer::new(hm.width, hm.height);
+    let sea_level = cfg.sea_level as f32;
+    // нормируем на максимум, чтобы редкие виды тоже было видно
+    let max_density = dist
+        .density
+        .iter()
+        .cloned()
+        .fold(0.0f32, f32::max)
+        .max(1e-6);
+
+    for y in 0..hm.height {
+        for x in 0..hm.width {
+            let hc = hm.get(x, y);
+            let color = if hc <= sea_level {
+                [40, 80, 160]
+            } else {
+                let g = 90.0 + hc * 100.0;
+                let t = (dist.density_at(x, y) / max_density).clamp(0.0, 1.0);
+                if dist.presence[(y * hm.width + x) as usize] {
+                    // от жёлтого (редко) к красному (плотно)
+                    let heat = [255.0, 230.0 - 200.0 * t, 40.0];
+                    let a = 0.35 + 0.5 * t;
+                    [
+                        (g * (1.0 - a) + heat[0] * a) as u8,
+                        (g * (1.0 - a) + heat[1] * a) as u8,
+                        (g * (1.0 - a) + heat[2] * a) as u8,
+                    ]
+                } else {
+                    [g as u8, g as u8, g as u8]
+                }
+            };
+            img.put_pixel(x, y, Rgb(color));
+        }
+    }
+
+    img.save(path)?;
+    Ok(())
+}
+
+// ---------- Политическая карта ----------
+
+fn save_political_map_to_png(
+    hm: &Heightmap,
+    history: &History,
+    cfg: &WorldConfig,
+    path: &str,
+) -> anyhow::Result<()> {
+    let mut img: RgbImage = ImageBuffer::new(hm.width, hm.height);
+    let sea_level = cfg.sea_level as f32;
+
+    let faction_colors: Vec<[u8; 3]> = history
+        .factions
+        .iter()
+        .map(|f| {
+            let h = simple_hash(&f.id);
+            [
+                90 + (h & 0x9F) as u8,
+                90 + ((h >> 8) & 0x9F) as u8,
+                90 + ((h >> 16) & 0x9F) as u8,
+            ]
+        })
+        .collect();
+
+    for y in 0..hm.height {
+        for x in 0..hm.width {
+            let hc = hm.get(x, y);
+            let color = if hc <= sea_level {
+                [40, 80, 160]
+            } else {
+                // серая подложка по высоте + цвет владельца
+                let g = (120.0 + hc * 100.0) as u8;
+                match history.territory.owner_at(x, y) {
+                    Some(fi) => {
+                        let c = faction_colors[fi as usize];
+                        [
+                            ((c[0] as u16 + g as u16) / 2) as u8,
+                            ((c[1] as u16 + g as u16) / 2) as u8,
+                            ((c[2] as u16 + g as u16) / 2) as u8,
+                        ]
+                    }
+                    None => [g, g, g],
+                }
+            };
+            img.put_pixel(x, y, Rgb(color));
+        }
+    }
+
+    // Границы: вершины полилиний лежат на углах клеток
+    for border in extract_borders(&history.territory, 0.75) {
+        for seg in border.points.windows(2) {
+            draw_line(&mut img, seg[0], seg[1], [20, 20, 20]);
+        }
+    }
+
+    // Торговые маршруты: дороги, реки и морские пути разными цветами
+    let routes = history.trade.rasterize(hm.width, hm.height);
+    for y in 0..hm.height {
+        for x in 0..hm.width {
+            let color = match routes[(y * hm.width + x) as usize] {
+                Some(RouteKind::Road) => [150, 100, 50],
+                Some(RouteKind::River) => [60, 170, 230],
+                Some(RouteKind::SeaLane) => [230, 230, 240],
+                None => continue,
+            };
+            img.put_pixel(x, y, Rgb(color));
+        }
+    }
+
+    // Руины разрушенных городов
+    for ruin in &history.ruins {
+        for (dx, dy) in [(-1, -1), (1, 1), (-1, 1), (1, -1), (0, 0)] {
+            let x = ruin.x as i32 + dx;
+            let y = ruin.y as i32 + dy;
+            if x >= 0 && y >= 0 && x < hm.width as i32 && y < hm.height as i32 {
+                img.put_pixel(x as u32, y as u32, Rgb([60, 40, 40]));
+            }
+        }
+    }
+
+    // Города: столицы крупнее
+    for city in &history.cities {
+        let r: i32 = if city.is_capital { 2 } else { 1 };
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let x = city.x as i32 + dx;
+                let y = city.y as i32 + dy;
+                if x >= 0 && y >= 0 && x < hm.width as i32 && y < hm.height as i32 {
+                    img.put_pixel(x as u32, y as u32, Rgb([200, 30, 30]));
+                }
+            }
+        }
+    }
+
     img.save(path)?;
     Ok(())
 }
 
+/// Простая растеризация отрезка (DDA)
+fn draw_line(img: &mut RgbImage, a: (f32, f32), b: (f32, f32), color: [u8; 3]) {
+    let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).ceil().max(1.0) as i32;
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let x = (a.0 + (b.0 - a.0) * t) as i32;
+        let y = (a.1 + (b.1 - a.1) * t) as i32;
+        if x >= 0 && y >= 0 && (x as u32) < img.width() && (y as u32) < img.height() {
+            img.put_pixel(x as u32, y as u32, Rgb(color));
+        }
+    }
+}
+
 /// Нормализация 3D-вектора
 fn normalize3(x: f32, y: f32, z: f32) -> (f32, f32, f32) {
     let len = (x * x + y * y + z * z).sqrt().max(1e-6);
@@ -194,7 +1965,7 @@ fn save_biome_map_to_png(bm: &BiomeMap, cfg: &WorldConfig, path: &str) -> anyhow
     let mut img: RgbImage = ImageBuffer::new(bm.width, bm.height);
 
     // Палитра цветов для биомов
-    let palette = build_biome_palette(&cfg);
+    let palette = build_biome_palette(cfg);
 
     for y in 0..bm.height {
         for x in 0..bm.width {
@@ -211,6 +1982,13 @@ fn save_biome_map_to_png(bm: &BiomeMap, cfg: &WorldConfig, path: &str) -> anyhow
     Ok(())
 }
 
+fn save_splat_map_to_png(surface: &SurfaceMap, path: &str) -> anyhow::Result<()> {
+    let img = RgbaImage::from_raw(surface.width, surface.height, surface.splat_map().concat())
+        .ok_or_else(|| anyhow::anyhow!("splat map does not match the map size"))?;
+    img.save(path)?;
+    Ok(())
+}
+
 // fn build_biome_palette(cfg: &WorldConfig) -> Vec<[u8; 3]> {
 //     let n = cfg.biomes.len().max(1);
 //     let mut palette = Vec::with_capacity(n);
@@ -233,13 +2011,19 @@ pub fn build_biome_palette(cfg: &WorldConfig) -> Vec<[u8; 3]> {
         .iter()
         .map(|b| match b.id.as_str() {
             // Тёплый лес
-            "temperate_forest" => [34, 139, 34],     // тёмно-зелёный
+            "temperate_forest" => [34, 139, 34], // тёмно-зелёный
             // Пустыня
-            "hot_desert" => [210, 180, 80],          // песочный
+            "hot_desert" => [210, 180, 80], // песочный
             // Холодные горы
-            "cold_mountains" => [160, 160, 170],     // серо-каменный
+            "cold_mountains" => [160, 160, 170], // серо-каменный
             // Тундра / холодная равнина
-            "tundra" => [150, 180, 160],             // холодно-зелёный
+            "tundra" => [150, 180, 160], // холодно-зелёный
+            // Биомы заготовок `seed-cli init`
+            "grassland" => [140, 190, 90],
+            "savanna" => [200, 190, 110],
+            "taiga" => [40, 100, 70],
+            "tropical_rainforest" => [20, 110, 40],
+            "wetland" => [90, 130, 110],
             // fallback — если добавишь новый биом, но не задашь цвет
             _ => {
                 // стабильный "псевдослучайный" цвет по hash id
@@ -265,27 +2049,6 @@ fn simple_hash(s: &str) -> u32 {
     h
 }
 
-/// Конвертация HSV -> RGB (0<=h<360, 0..1, 0..1)
-fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (u8, u8, u8) {
-    let c = v * s;
-    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
-    let m = v - c;
-
-    let (r1, g1, b1) = match h {
-        h if h < 60.0 => (c, x, 0.0),
-        h if h < 120.0 => (x, c, 0.0),
-        h if h < 180.0 => (0.0, c, x),
-        h if h < 240.0 => (0.0, x, c),
-        h if h < 300.0 => (x, 0.0, c),
-        _ => (c, 0.0, x),
-    };
-
-    let r = ((r1 + m) * 255.0).round() as u8;
-    let g = ((g1 + m) * 255.0).round() as u8;
-    let b = ((b1 + m) * 255.0).round() as u8;
-    (r, g, b)
-}
-
 // ---------- Сводка по миру (как ранее) ----------
 
 fn print_world_summary(cfg: &WorldConfig, world: &World) {
@@ -421,6 +2184,17 @@ fn print_world_summary(cfg: &WorldConfig, world: &World) {
             s.id, s.id, s.trophic_level, s.population_density_per_km2, s.migration_enabled
         );
     }
+    let web = FoodWeb::from_config(&cfg.ecosystems);
+    println!("Food web links:   {}", web.links.len());
+    for link in &web.links {
+        println!(
+            "  - {} eats {}",
+            web.species[link.predator], web.species[link.prey]
+        );
+    }
+    for issue in &web.issues {
+        println!("  warning: {issue}");
+    }
     println!();
 
     let cat = &cfg.catastrophes;
@@ -565,3 +2339,22 @@ fn print_world_summary(cfg: &WorldConfig, world: &World) {
         sim.network.state_sync_strategy
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn cli_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn quiet_is_global() {
+        assert!(!Cli::parse_from(["seed-cli"]).quiet);
+        assert!(Cli::parse_from(["seed-cli", "--quiet"]).quiet);
+        assert!(Cli::parse_from(["seed-cli", "history", "-q"]).quiet);
+    }
+}
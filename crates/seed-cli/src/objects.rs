@@ -0,0 +1,217 @@
+//! Выгрузка расстановки объектов (`seed-cli objects`) для левел-дизайна:
+//! участок карты, фильтр по категориям, JSON или CSV. Координаты — в метрах
+//! на местности, как у DEM и GeoJSON: x на восток, y на север от юго-западного
+//! угла карты, z — высота над уровнем моря; объект стоит в центре своей клетки.
+
+use clap::ValueEnum;
+use seed_config::WorldConfig;
+use seed_core::{Heightmap, ObjectType, ProceduralObject};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectsFormat {
+    Json,
+    Csv,
+}
+
+/// Категория для `--types`: несколько близких типов объектов
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectCategory {
+    Tree,
+    Rock,
+    Bush,
+    Grass,
+    Cactus,
+    House,
+    Ruin,
+}
+
+impl ObjectCategory {
+    pub fn of(kind: ObjectType) -> Self {
+        match kind {
+            ObjectType::TreeConifer | ObjectType::TreeDeciduous | ObjectType::TreePalm => {
+                ObjectCategory::Tree
+            }
+            ObjectType::RockSmall
+            | ObjectType::RockMedium
+            | ObjectType::RockLarge
+            | ObjectType::BoulderCluster => ObjectCategory::Rock,
+            ObjectType::Bush => ObjectCategory::Bush,
+            ObjectType::Grass => ObjectCategory::Grass,
+            ObjectType::Cactus => ObjectCategory::Cactus,
+            ObjectType::HouseWood | ObjectType::HouseStone | ObjectType::HouseMedieval => {
+                ObjectCategory::House
+            }
+            ObjectType::Ruins | ObjectType::CollapsedWall | ObjectType::OvergrownFoundation => {
+                ObjectCategory::Ruin
+            }
+        }
+    }
+
+    fn id(self) -> &'static str {
+        match self {
+            ObjectCategory::Tree => "tree",
+            ObjectCategory::Rock => "rock",
+            ObjectCategory::Bush => "bush",
+            ObjectCategory::Grass => "grass",
+            ObjectCategory::Cactus => "cactus",
+            ObjectCategory::House => "house",
+            ObjectCategory::Ruin => "ruin",
+        }
+    }
+}
+
+pub(crate) fn type_id(kind: ObjectType) -> &'static str {
+    match kind {
+        ObjectType::TreeConifer => "tree_conifer",
+        ObjectType::TreeDeciduous => "tree_deciduous",
+        ObjectType::TreePalm => "tree_palm",
+        ObjectType::RockSmall => "rock_small",
+        ObjectType::RockMedium => "rock_medium",
+        ObjectType::RockLarge => "rock_large",
+        ObjectType::BoulderCluster => "boulder_cluster",
+        ObjectType::Bush => "bush",
+        ObjectType::Grass => "grass",
+        ObjectType::Cactus => "cactus",
+        ObjectType::HouseWood => "house_wood",
+        ObjectType::HouseStone => "house_stone",
+        ObjectType::HouseMedieval => "house_medieval",
+        ObjectType::Ruins => "ruins",
+        ObjectType::CollapsedWall => "collapsed_wall",
+        ObjectType::OvergrownFoundation => "overgrown_foundation",
+    }
+}
+
+/// Участок карты в клетках: левый верхний угол и размер
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Chunk {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// `x,y,width,height` в клетках карты `map_w`×`map_h`
+pub fn parse_chunk(s: &str, map_w: u32, map_h: u32) -> anyhow::Result<Chunk> {
+    let parts: Vec<u32> = s
+        .split(',')
+        .map(|p| p.trim().parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| anyhow::anyhow!("invalid --chunk '{s}': {e}"))?;
+    let [x, y, width, height] = parts[..] else {
+        anyhow::bail!("invalid --chunk '{s}': expected x,y,width,height");
+    };
+    if width == 0 || height == 0 {
+        anyhow::bail!("invalid --chunk '{s}': width and height must be positive");
+    }
+    if x >= map_w || y >= map_h {
+        anyhow::bail!("--chunk '{s}' is outside the {map_w}x{map_h} map");
+    }
+    // край участка обрезается по карте, как в generate_objects_for_chunk
+    Ok(Chunk {
+        x,
+        y,
+        width: width.min(map_w - x),
+        height: height.min(map_h - y),
+    })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectsExport {
+    pub world_seed: u64,
+    pub chunk: Chunk,
+    pub cell_size_m: f64,
+    /// Число объектов по типам
+    pub counts: BTreeMap<&'static str, usize>,
+    pub objects: Vec<Placement>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Placement {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub category: &'static str,
+    pub x_m: f64,
+    pub y_m: f64,
+    pub z_m: f64,
+    pub scale: f32,
+    /// Поворот вокруг вертикали, радианы
+    pub rotation_y: f32,
+    pub variant: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_years: Option<u32>,
+}
+
+/// Объекты участка в метрах; `types` пустой — без фильтра
+pub fn build_export(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    chunk: Chunk,
+    objects: &[ProceduralObject],
+    types: &[ObjectCategory],
+) -> ObjectsExport {
+    let cell_m = cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64;
+    let mut counts = BTreeMap::new();
+    let placements: Vec<Placement> = objects
+        .iter()
+        .filter(|o| types.is_empty() || types.contains(&ObjectCategory::of(o.object_type)))
+        .map(|o| {
+            let kind = type_id(o.object_type);
+            *counts.entry(kind).or_default() += 1;
+            let (x, y) = (o.x as u32, o.y as u32);
+            Placement {
+                kind,
+                category: ObjectCategory::of(o.object_type).id(),
+                x_m: round_cm((x as f64 + 0.5) * cell_m),
+                y_m: round_cm((hm.height as f64 - y as f64 - 0.5) * cell_m),
+                z_m: round_cm(hm.elevation_m(cfg.sea_level, x, y)),
+                scale: o.scale,
+                rotation_y: o.rotation_y,
+                variant: o.variant,
+                age_years: o.age_years,
+            }
+        })
+        .collect();
+    ObjectsExport {
+        world_seed: cfg.world_seed,
+        chunk,
+        cell_size_m: cell_m,
+        counts,
+        objects: placements,
+    }
+}
+
+/// Пишет выгрузку в `path` в формате `format`
+pub fn write_export(
+    export: &ObjectsExport,
+    format: ObjectsFormat,
+    path: &str,
+) -> anyhow::Result<()> {
+    let text = match format {
+        ObjectsFormat::Json => serde_json::to_string_pretty(export)?,
+        ObjectsFormat::Csv => to_csv(&export.objects),
+    };
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+fn to_csv(placements: &[Placement]) -> String {
+    let mut out = String::from("type,category,x_m,y_m,z_m,scale,rotation_y,variant,age_years\n");
+    for p in placements {
+        let age = p.age_years.map(|a| a.to_string()).unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            p.kind, p.category, p.x_m, p.y_m, p.z_m, p.scale, p.rotation_y, p.variant, age
+        );
+    }
+    out
+}
+
+fn round_cm(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
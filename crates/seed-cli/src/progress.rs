@@ -0,0 +1,186 @@
+//! Полоса прогресса (indicatif) и разбивка времени по этапам генерации. Всё
+//! пишется в stderr, чтобы не мешать выводу в stdout (например, `--summary
+//! json`); полосы рисуются, только если stderr — терминал, и молчат при
+//! `--quiet` вместе со строкой `Timing:`.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use seed_core::{GenerationPhase, Progress};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Вид полосы одного этапа
+const BAR_TEMPLATE: &str = "{prefix:<8} [{bar:30}] {percent:>3}%";
+
+pub struct PhaseTimings {
+    /// Куда рисуются полосы; None — не рисуем (не терминал или `--quiet`)
+    bars: Option<MultiProgress>,
+    /// `--quiet`: без полос и без разбивки по этапам
+    quiet: bool,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Отрезки этапов в порядке начала: имя, начало, конец. Этап может
+    /// повториться (сводка и карты генерируют heightmap каждая сама)
+    phases: Vec<(&'static str, Instant, Instant)>,
+    /// Полоса текущего этапа
+    bar: Option<ProgressBar>,
+}
+
+impl PhaseTimings {
+    /// Полосы в stderr, если это терминал; `quiet` глушит полосы и `print`
+    pub fn new(quiet: bool) -> Self {
+        Self::with_target(quiet, ProgressDrawTarget::stderr())
+    }
+
+    /// Только замеры, без полосы и разбивки (например, для потоков `batch`)
+    pub fn quiet() -> Self {
+        Self::with_target(true, ProgressDrawTarget::hidden())
+    }
+
+    fn with_target(quiet: bool, target: ProgressDrawTarget) -> Self {
+        // indicatif считает stderr-цель скрытой, если stderr не терминал
+        let bars = (!quiet && !target.is_hidden()).then(|| MultiProgress::with_draw_target(target));
+        Self {
+            bars,
+            quiet,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Засекает этап, который ядро не отчитывает само (история, объекты, экспорт)
+    pub fn time<T>(&self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let out = f();
+        let mut state = self.state.lock().expect("timings lock");
+        state.phases.push((name, started, Instant::now()));
+        out
+    }
+
+    /// Разбивка по этапам в stderr
+    pub fn print(&self) {
+        if let Some(line) = self.summary() {
+            eprintln!("{line}");
+        }
+    }
+
+    /// Строка разбивки (повторы складываются) и общего времени; None при
+    /// `--quiet` или если этапов не было
+    fn summary(&self) -> Option<String> {
+        let state = self.state.lock().expect("timings lock");
+        if self.quiet || state.phases.is_empty() {
+            return None;
+        }
+        let mut totals: Vec<(&'static str, Duration)> = Vec::new();
+        for &(name, start, end) in &state.phases {
+            match totals.iter_mut().find(|(n, _)| *n == name) {
+                Some(entry) => entry.1 += end - start,
+                None => totals.push((name, end - start)),
+            }
+        }
+        let parts: Vec<String> = totals
+            .iter()
+            .map(|(name, d)| format!("{name} {}", secs(*d)))
+            .collect();
+        let total: Duration = totals.iter().map(|(_, d)| *d).sum();
+        Some(format!(
+            "Timing: {} (total {})",
+            parts.join(", "),
+            secs(total)
+        ))
+    }
+}
+
+impl Progress for PhaseTimings {
+    fn report(&self, phase: GenerationPhase, done: f32) {
+        let now = Instant::now();
+        let name = phase.id();
+        let mut state = self.state.lock().expect("timings lock");
+        let started = match state.phases.last_mut() {
+            Some(last) if last.0 == name => {
+                last.2 = now;
+                false
+            }
+            _ => {
+                state.phases.push((name, now, now));
+                true
+            }
+        };
+
+        let Some(bars) = &self.bars else {
+            return;
+        };
+        if started {
+            if let Some(prev) = state.bar.take() {
+                prev.finish();
+            }
+            let bar = ProgressBar::new(100)
+                .with_style(
+                    ProgressStyle::with_template(BAR_TEMPLATE)
+                        .expect("bar template")
+                        .progress_chars("#>-"),
+                )
+                .with_prefix(name);
+            state.bar = Some(bars.add(bar));
+        }
+        if let Some(bar) = &state.bar {
+            bar.set_position((done.clamp(0.0, 1.0) * 100.0) as u64);
+            if done >= 1.0 {
+                bar.finish();
+            }
+        }
+    }
+}
+
+fn secs(d: Duration) -> String {
+    format!("{:.2} s", d.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use indicatif::InMemoryTerm;
+
+    use super::*;
+
+    fn terminal() -> (InMemoryTerm, ProgressDrawTarget) {
+        let term = InMemoryTerm::new(10, 80);
+        let target = ProgressDrawTarget::term_like(Box::new(term.clone()));
+        (term, target)
+    }
+
+    fn generate(timings: &PhaseTimings) {
+        timings.report(GenerationPhase::Noise, 0.5);
+        timings.report(GenerationPhase::Noise, 1.0);
+        timings.time("history", || ());
+    }
+
+    #[test]
+    fn draws_a_bar_per_phase_on_a_terminal() {
+        let (term, target) = terminal();
+        let timings = PhaseTimings::with_target(false, target);
+        generate(&timings);
+        let screen = term.contents();
+        assert!(screen.contains("noise"), "{screen}");
+        assert!(screen.contains("100%"), "{screen}");
+        assert!(timings.summary().unwrap().starts_with("Timing: noise "));
+    }
+
+    #[test]
+    fn no_bar_when_stderr_is_not_a_terminal() {
+        let timings = PhaseTimings::with_target(false, ProgressDrawTarget::hidden());
+        assert!(timings.bars.is_none());
+        generate(&timings);
+        // разбивка по этапам остаётся
+        assert!(timings.summary().is_some());
+    }
+
+    #[test]
+    fn quiet_draws_nothing_and_skips_the_summary() {
+        let (term, target) = terminal();
+        let timings = PhaseTimings::with_target(true, target);
+        generate(&timings);
+        assert_eq!(term.contents().trim(), "");
+        assert_eq!(timings.summary(), None);
+    }
+}
@@ -0,0 +1,162 @@
+//! Текстуры рельефа: карта нормалей (tangent space) и отмывка рельефа
+//! (hillshade) по `seed_core::relief`, послойная окраска по высоте и
+//! горизонтали в SVG.
+
+use clap::ValueEnum;
+use image::{GrayImage, Rgb, RgbImage};
+use seed_config::WorldConfig;
+use seed_core::relief::{hillshade, normal_map, shade, surface_normal};
+use seed_core::{extract_contours, Heightmap};
+use std::fmt::Write as _;
+
+/// Глубины моря: нижняя граница ступени, м, и цвет (чем глубже, тем темнее)
+pub(crate) const DEPTH_TINTS: [(f64, [u8; 3]); 6] = [
+    (-50.0, [198, 236, 255]),
+    (-200.0, [161, 210, 247]),
+    (-500.0, [121, 178, 222]),
+    (-1000.0, [84, 145, 200]),
+    (-2000.0, [50, 110, 175]),
+    (f64::MIN, [30, 80, 150]),
+];
+
+/// Высоты суши: верхняя граница ступени, м, и цвет (от низин к снегам)
+pub(crate) const LAND_TINTS: [(f64, [u8; 3]); 9] = [
+    (100.0, [80, 150, 90]),
+    (200.0, [120, 175, 100]),
+    (500.0, [170, 200, 120]),
+    (1000.0, [225, 220, 150]),
+    (1500.0, [215, 185, 120]),
+    (2000.0, [190, 150, 100]),
+    (2500.0, [165, 120, 85]),
+    (3000.0, [180, 170, 165]),
+    (f64::MAX, [240, 240, 240]),
+];
+
+/// Каждая такая горизонталь — утолщённая (с подписью высоты в атрибуте)
+const INDEX_CONTOUR_EVERY: i64 = 5;
+
+/// Направление зелёного канала карты нормалей
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalConvention {
+    /// +Y вверх по текстуре (Unity, Blender, glTF)
+    Opengl,
+    /// +Y вниз по текстуре (Unreal)
+    Directx,
+}
+
+pub use seed_core::relief::Sun;
+
+pub fn save_normal_map(
+    hm: &Heightmap,
+    cfg: &WorldConfig,
+    z_factor: f64,
+    convention: NormalConvention,
+    path: &str,
+) -> anyhow::Result<()> {
+    let green_down = convention == NormalConvention::Directx;
+    let pixels = normal_map(hm, cfg, z_factor, green_down).concat();
+    let img = RgbImage::from_raw(hm.width, hm.height, pixels)
+        .ok_or_else(|| anyhow::anyhow!("normal map does not match the map size"))?;
+    img.save(path)?;
+    Ok(())
+}
+
+pub fn save_hillshade(
+    hm: &Heightmap,
+    cfg: &WorldConfig,
+    z_factor: f64,
+    sun: Sun,
+    path: &str,
+) -> anyhow::Result<()> {
+    let img = GrayImage::from_raw(hm.width, hm.height, hillshade(hm, cfg, z_factor, sun))
+        .ok_or_else(|| anyhow::anyhow!("hillshade does not match the map size"))?;
+    img.save(path)?;
+    Ok(())
+}
+
+/// Цвет ступени высоты (над уровнем моря) или глубины (ниже нуля), м
+pub(crate) fn hypsometric_tint(elevation: f64) -> [u8; 3] {
+    if elevation <= 0.0 {
+        let tint = DEPTH_TINTS.iter().find(|(floor, _)| elevation > *floor);
+        return tint.map_or(DEPTH_TINTS[5].1, |t| t.1);
+    }
+    LAND_TINTS
+        .iter()
+        .find(|(top, _)| elevation <= *top)
+        .map_or(LAND_TINTS[8].1, |t| t.1)
+}
+
+/// Послойная окраска: ступени высот суши и глубин моря; суша подсвечена
+/// отмывкой так, что ровное место сохраняет цвет ступени
+pub fn render_hypsometric(hm: &Heightmap, cfg: &WorldConfig, z_factor: f64, sun: Sun) -> RgbImage {
+    let light = sun.direction();
+    let flat = light.2.max(1e-3);
+    RgbImage::from_fn(hm.width, hm.height, |x, y| {
+        let elevation = hm.elevation_m(cfg.sea_level, x, y);
+        let tint = hypsometric_tint(elevation);
+        if elevation <= 0.0 {
+            return Rgb(tint);
+        }
+        let normal = surface_normal(hm, cfg, z_factor, x, y);
+        let k = (shade(normal, light) / flat).clamp(0.5, 1.3);
+        Rgb(tint.map(|c| (c as f64 * k).round().min(255.0) as u8))
+    })
+}
+
+/// Горизонтали через `interval_m` метров в SVG размером с карту (пиксель —
+/// клетка): суша — коричневым, изобаты — синим, берег — жирным синим
+pub fn save_contours_svg(
+    hm: &Heightmap,
+    cfg: &WorldConfig,
+    interval_m: f64,
+    path: &str,
+) -> anyhow::Result<usize> {
+    let lines = extract_contours(hm, cfg.sea_level, interval_m, 0.25);
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = hm.width,
+        h = hm.height
+    )?;
+    writeln!(
+        svg,
+        r#"<g fill="none" stroke-linejoin="round" stroke-linecap="round">"#
+    )?;
+    for line in &lines {
+        let step = (line.elevation_m / interval_m).round() as i64;
+        let (color, width) = if step == 0 {
+            ("#1f4e8c", 1.5)
+        } else if line.elevation_m < 0.0 {
+            (
+                "#6fa8dc",
+                if step % INDEX_CONTOUR_EVERY == 0 {
+                    0.9
+                } else {
+                    0.4
+                },
+            )
+        } else {
+            (
+                "#8b5a2b",
+                if step % INDEX_CONTOUR_EVERY == 0 {
+                    0.9
+                } else {
+                    0.4
+                },
+            )
+        };
+        let mut d = String::new();
+        for (i, (x, y)) in line.points.iter().enumerate() {
+            write!(d, "{}{:.1},{:.1}", if i == 0 { "M" } else { " L" }, x, y)?;
+        }
+        writeln!(
+            svg,
+            r#"<path d="{d}" stroke="{color}" stroke-width="{width}" data-elevation-m="{}"/>"#,
+            line.elevation_m
+        )?;
+    }
+    writeln!(svg, "</g>\n</svg>")?;
+    std::fs::write(path, svg)?;
+    Ok(lines.len())
+}
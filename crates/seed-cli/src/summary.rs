@@ -0,0 +1,250 @@
+//! Сводка мира в JSON (`--summary json`): то же, что текстовая сводка, плюс
+//! статистика генерации — доля суши, покрытие биомами, гистограмма высот и
+//! число объектов. Порядок ключей стабилен, чтобы отчёты можно было сравнивать.
+
+use crate::progress::PhaseTimings;
+use seed_config::WorldConfig;
+use seed_core::{
+    build_gazetteer, generate_biome_map_with_progress, generate_heightmap_with_progress,
+    generate_objects_for_chunk, simulate_history, FoodWeb, World,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Столько корзин в гистограмме высот
+const ELEVATION_BINS: usize = 16;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldSummary {
+    pub world_id: String,
+    pub name: String,
+    pub author: String,
+    pub created_at: String,
+    pub seed_version: String,
+    pub world_seed: u64,
+    pub scale: ScaleSummary,
+    pub active_planet: PlanetSummary,
+    pub config: ConfigCounts,
+    pub food_web_issues: Vec<String>,
+    pub generation: GenerationStats,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScaleSummary {
+    pub mode: String,
+    pub region_size_km: f64,
+    pub planet_radius_km: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanetSummary {
+    pub id: String,
+    pub name: String,
+    pub radius_km: f64,
+    pub gravity_ms2: f64,
+    pub day_length_hours: f64,
+    pub year_length_days: f64,
+}
+
+/// Сколько чего описано в конфиге
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigCounts {
+    pub stars: usize,
+    pub planets: usize,
+    pub materials: usize,
+    pub biomes: usize,
+    pub species: usize,
+    pub food_web_links: usize,
+    pub catastrophe_types: usize,
+    pub faction_presets: usize,
+    pub quest_templates: usize,
+}
+
+/// Статистика сгенерированной карты `width`×`height`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationStats {
+    pub width: u32,
+    pub height: u32,
+    pub land_fraction: f64,
+    pub elevation: ElevationStats,
+    /// Доля всех клеток карты под каждым биомом; `none` — вода и клетки без биома
+    pub biome_coverage: BTreeMap<String, f64>,
+    /// Процедурные объекты (деревья, камни, дома) по типам на всей карте
+    pub objects: BTreeMap<String, usize>,
+    /// Объекты газеттира по видам
+    pub features: BTreeMap<String, usize>,
+    pub history: HistoryCounts,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElevationStats {
+    pub min_m: f64,
+    pub max_m: f64,
+    pub mean_land_m: f64,
+    pub histogram: Vec<HistogramBin>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramBin {
+    pub from_m: f64,
+    pub to_m: f64,
+    pub cells: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryCounts {
+    pub years_simulated: u32,
+    pub factions: usize,
+    pub cities: usize,
+    pub ruins: usize,
+    pub wars: usize,
+    pub battles: usize,
+    pub trade_routes: usize,
+    pub roads: usize,
+    pub structures: usize,
+}
+
+pub fn build_summary(
+    cfg: &WorldConfig,
+    world: &World,
+    width: u32,
+    height: u32,
+    timings: &PhaseTimings,
+) -> WorldSummary {
+    let hm = generate_heightmap_with_progress(cfg, width, height, timings);
+    let bm = generate_biome_map_with_progress(cfg, &hm, timings);
+    let history = timings.time("history", || {
+        simulate_history(cfg, &hm, &bm, cfg.world_seed)
+    });
+    let web = FoodWeb::from_config(&cfg.ecosystems);
+    let sea_level = cfg.sea_level as f32;
+
+    let cells = (hm.width * hm.height) as usize;
+    let elevations: Vec<f64> = (0..hm.height)
+        .flat_map(|y| (0..hm.width).map(move |x| (x, y)))
+        .map(|(x, y)| hm.elevation_m(cfg.sea_level, x, y))
+        .collect();
+    let land = hm.values.iter().filter(|&&v| v > sea_level).count();
+
+    let (min_m, max_m) = elevations
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), &e| (lo.min(e), hi.max(e)));
+    let span = (max_m - min_m).max(1e-6);
+    let mut histogram: Vec<HistogramBin> = (0..ELEVATION_BINS)
+        .map(|i| HistogramBin {
+            from_m: min_m + span * i as f64 / ELEVATION_BINS as f64,
+            to_m: min_m + span * (i + 1) as f64 / ELEVATION_BINS as f64,
+            cells: 0,
+        })
+        .collect();
+    for &e in &elevations {
+        let bin = (((e - min_m) / span * ELEVATION_BINS as f64) as usize).min(ELEVATION_BINS - 1);
+        histogram[bin].cells += 1;
+    }
+    let land_sum: f64 = elevations.iter().filter(|&&e| e > 0.0).sum();
+
+    let mut biome_cells: BTreeMap<String, usize> = BTreeMap::new();
+    for idx in &bm.indices {
+        let id = idx
+            .and_then(|i| cfg.biomes.get(i as usize))
+            .map_or("none", |b| b.id.as_str());
+        *biome_cells.entry(id.to_string()).or_default() += 1;
+    }
+
+    let mut objects: BTreeMap<String, usize> = BTreeMap::new();
+    let placed = timings.time("objects", || {
+        generate_objects_for_chunk(cfg, &hm, &bm, 0, 0, width, height, cfg.world_seed)
+    });
+    for obj in placed {
+        *objects.entry(format!("{:?}", obj.object_type)).or_default() += 1;
+    }
+
+    let mut features: BTreeMap<String, usize> = BTreeMap::new();
+    for entry in build_gazetteer(cfg, &hm, &bm, cfg.world_seed) {
+        let kind = serde_json::to_value(entry.kind)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        *features.entry(kind).or_default() += 1;
+    }
+
+    let sys = &cfg.cosmos.star_system;
+    let p = &world.cosmos.active_planet;
+    WorldSummary {
+        world_id: cfg.world_id.clone(),
+        name: cfg.meta.name.clone(),
+        author: cfg.meta.author.clone(),
+        created_at: cfg.meta.created_at.clone(),
+        seed_version: cfg.seed_version.clone(),
+        world_seed: cfg.world_seed,
+        scale: ScaleSummary {
+            mode: cfg.scale.mode.clone(),
+            region_size_km: cfg.scale.region_size_km,
+            planet_radius_km: cfg.scale.planet_radius_km,
+        },
+        active_planet: PlanetSummary {
+            id: p.id.clone(),
+            name: p.name.clone(),
+            radius_km: p.radius_km,
+            gravity_ms2: p.gravity_ms2,
+            day_length_hours: p.day_length_hours,
+            year_length_days: p.year_length_days,
+        },
+        config: ConfigCounts {
+            stars: sys.stars.len(),
+            planets: sys.planets.len(),
+            materials: cfg.materials.len(),
+            biomes: cfg.biomes.len(),
+            species: cfg.ecosystems.species_definitions.len(),
+            food_web_links: web.links.len(),
+            catastrophe_types: cfg.catastrophes.event_types.len(),
+            faction_presets: cfg.civilizations.faction_presets.len(),
+            quest_templates: cfg
+                .narrative_director
+                .quest_templates
+                .as_ref()
+                .map_or(0, Vec::len),
+        },
+        food_web_issues: web.issues.iter().map(ToString::to_string).collect(),
+        generation: GenerationStats {
+            width: hm.width,
+            height: hm.height,
+            land_fraction: land as f64 / cells.max(1) as f64,
+            elevation: ElevationStats {
+                min_m,
+                max_m,
+                mean_land_m: if land > 0 {
+                    land_sum / land as f64
+                } else {
+                    0.0
+                },
+                histogram,
+            },
+            biome_coverage: biome_cells
+                .into_iter()
+                .map(|(id, n)| (id, n as f64 / cells.max(1) as f64))
+                .collect(),
+            objects,
+            features,
+            history: HistoryCounts {
+                years_simulated: history.years_simulated,
+                factions: history.factions.len(),
+                cities: history.cities.len(),
+                ruins: history.ruins.len(),
+                wars: history.wars.len(),
+                battles: history.battles.len(),
+                trade_routes: history.trade.routes.len(),
+                roads: history.infrastructure.roads.len(),
+                structures: history.infrastructure.structures.len(),
+            },
+        },
+    }
+}
@@ -0,0 +1,375 @@
+//! Просмотр мира в терминале (`seed-cli tui`): карта полублоками в 24-битном
+//! цвете (или символами с `--ascii`), сдвиг и масштаб с клавиатуры, слои
+//! биомов, рек и рельефа, а под курсором в центре экрана — высота, биом и
+//! климат клетки. Терминал переводится в сырой режим (только Unix) и
+//! восстанавливается при выходе.
+
+use crate::relief::hypsometric_tint;
+use crate::{build_biome_palette, WATER_COLOR};
+use seed_config::WorldConfig;
+use seed_core::{compute_climate_map, extract_rivers, BiomeMap, ClimateMap, Heightmap};
+use std::fmt::Write as _;
+
+const RIVER_COLOR: [u8; 3] = [60, 170, 230];
+const LAND_COLOR: [u8; 3] = [150, 150, 150];
+/// Строки под картой: инспектор и подсказка
+const STATUS_ROWS: u16 = 2;
+/// Градации рельефа для `--ascii`, от низин к вершинам
+const ASCII_RAMP: &[u8] = b".:-=+*#%@";
+/// Самое крупное приближение: клеток карты на пиксель экрана
+const MIN_SCALE: f64 = 0.125;
+
+/// Всё, что показывает просмотрщик
+pub struct TuiWorld<'a> {
+    pub cfg: &'a WorldConfig,
+    pub hm: &'a Heightmap,
+    pub bm: &'a BiomeMap,
+}
+
+struct Layers {
+    biomes: bool,
+    rivers: bool,
+    elevation: bool,
+}
+
+struct View {
+    /// Центр экрана в клетках карты (там же курсор)
+    cx: f64,
+    cy: f64,
+    /// Клеток карты на пиксель экрана (пиксель — полсимвола по высоте)
+    scale: f64,
+}
+
+struct Viewer<'a> {
+    world: TuiWorld<'a>,
+    climate: ClimateMap,
+    rivers: Vec<bool>,
+    palette: Vec<[u8; 3]>,
+    cell_m: f64,
+    layers: Layers,
+    view: View,
+    ascii: bool,
+}
+
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    ZoomIn,
+    ZoomOut,
+    Toggle(char),
+    Quit,
+    Other,
+}
+
+/// Открывает просмотр до `q`/Esc
+pub fn run_tui(world: TuiWorld, ascii: bool) -> anyhow::Result<()> {
+    use std::io::IsTerminal;
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        anyhow::bail!("tui needs an interactive terminal");
+    }
+
+    let climate = compute_climate_map(world.cfg, world.hm);
+    let mut rivers = vec![false; world.hm.values.len()];
+    for river in extract_rivers(world.hm, world.cfg.sea_level as f32, 0.0) {
+        for &(x, y) in &river.points {
+            rivers[y as usize * world.hm.width as usize + x as usize] = true;
+        }
+    }
+    let (w, h) = (world.hm.width, world.hm.height);
+    let mut viewer = Viewer {
+        palette: build_biome_palette(world.cfg),
+        cell_m: world.cfg.scale.region_size_km * 1000.0 / w.max(1) as f64,
+        climate,
+        rivers,
+        layers: Layers {
+            biomes: true,
+            rivers: true,
+            elevation: true,
+        },
+        view: View {
+            cx: w as f64 / 2.0,
+            cy: h as f64 / 2.0,
+            scale: 1.0,
+        },
+        ascii,
+        world,
+    };
+    // вся карта на экране
+    let (cols, rows) = term::size();
+    let (pw, ph) = viewer.pixels(cols, rows);
+    viewer.view.scale = (w as f64 / pw as f64)
+        .max(h as f64 / ph as f64)
+        .max(MIN_SCALE);
+
+    let _raw = term::RawMode::enter()?;
+    loop {
+        let (cols, rows) = term::size();
+        term::write(&viewer.frame(cols, rows))?;
+        match term::read_key()? {
+            Key::Quit => return Ok(()),
+            key => viewer.handle(key, cols, rows),
+        }
+    }
+}
+
+impl Viewer<'_> {
+    /// Размер картинки в пикселях для терминала `cols`×`rows`
+    fn pixels(&self, cols: u16, rows: u16) -> (u32, u32) {
+        let map_rows = rows.saturating_sub(STATUS_ROWS).max(1) as u32;
+        (cols.max(1) as u32, map_rows * 2)
+    }
+
+    fn handle(&mut self, key: Key, cols: u16, rows: u16) {
+        let (pw, ph) = self.pixels(cols, rows);
+        let (w, h) = (self.world.hm.width as f64, self.world.hm.height as f64);
+        let step_x = (pw as f64 * self.view.scale / 8.0).max(1.0);
+        let step_y = (ph as f64 * self.view.scale / 8.0).max(1.0);
+        let view = &mut self.view;
+        match key {
+            Key::Up => view.cy = (view.cy - step_y).max(0.0),
+            Key::Down => view.cy = (view.cy + step_y).min(h - 0.5),
+            Key::Left => view.cx = (view.cx - step_x).max(0.0),
+            Key::Right => view.cx = (view.cx + step_x).min(w - 0.5),
+            Key::ZoomIn => view.scale = (view.scale / 2.0).max(MIN_SCALE),
+            Key::ZoomOut => view.scale = (view.scale * 2.0).min(w.max(h)),
+            Key::Toggle('b') => self.layers.biomes = !self.layers.biomes,
+            Key::Toggle('r') => self.layers.rivers = !self.layers.rivers,
+            Key::Toggle('e') => self.layers.elevation = !self.layers.elevation,
+            Key::Toggle(_) | Key::Quit | Key::Other => {}
+        }
+    }
+
+    /// Клетка карты под пикселем экрана
+    fn cell_at(&self, px: u32, py: u32, (pw, ph): (u32, u32)) -> Option<(u32, u32)> {
+        let x = self.view.cx + (px as f64 - pw as f64 / 2.0) * self.view.scale;
+        let y = self.view.cy + (py as f64 - ph as f64 / 2.0) * self.view.scale;
+        let (w, h) = (self.world.hm.width as f64, self.world.hm.height as f64);
+        (x >= 0.0 && y >= 0.0 && x < w && y < h).then_some((x as u32, y as u32))
+    }
+
+    fn color(&self, x: u32, y: u32) -> [u8; 3] {
+        let hm = self.world.hm;
+        let i = (y * hm.width + x) as usize;
+        if self.layers.rivers && self.rivers[i] {
+            return RIVER_COLOR;
+        }
+        let elevation = hm.elevation_m(self.world.cfg.sea_level, x, y);
+        match (self.layers.biomes, self.layers.elevation) {
+            (true, elevation_on) => {
+                let base = match self.world.bm.get_index(x, y) {
+                    Some(idx) if idx < self.palette.len() => self.palette[idx],
+                    _ => WATER_COLOR,
+                };
+                if !elevation_on {
+                    return base;
+                }
+                // подсветка склонов с северо-запада
+                let nw = hm.elevation_m(
+                    self.world.cfg.sea_level,
+                    x.saturating_sub(1),
+                    y.saturating_sub(1),
+                );
+                let se = hm.elevation_m(
+                    self.world.cfg.sea_level,
+                    (x + 1).min(hm.width - 1),
+                    (y + 1).min(hm.height - 1),
+                );
+                let k = (1.0 + (nw - se) / (2.0 * self.cell_m) * 1.5).clamp(0.6, 1.3);
+                base.map(|c| (c as f64 * k).round().min(255.0) as u8)
+            }
+            (false, true) => hypsometric_tint(elevation),
+            (false, false) if elevation > 0.0 => LAND_COLOR,
+            (false, false) => WATER_COLOR,
+        }
+    }
+
+    fn glyph(&self, x: u32, y: u32) -> char {
+        let i = (y * self.world.hm.width + x) as usize;
+        if self.layers.rivers && self.rivers[i] {
+            return '=';
+        }
+        let elevation = self.world.hm.elevation_m(self.world.cfg.sea_level, x, y);
+        if elevation <= 0.0 {
+            return '~';
+        }
+        if !self.layers.elevation {
+            return '.';
+        }
+        let t = (elevation / seed_core::MAX_RELIEF_M).clamp(0.0, 1.0);
+        ASCII_RAMP[((t * ASCII_RAMP.len() as f64) as usize).min(ASCII_RAMP.len() - 1)] as char
+    }
+
+    /// Кадр целиком: карта, курсор, строка инспектора и подсказка
+    fn frame(&self, cols: u16, rows: u16) -> String {
+        let (pw, ph) = self.pixels(cols, rows);
+        let (ccol, crow) = (pw / 2, ph / 4);
+        let mut out = String::new();
+        for row in 0..ph / 2 {
+            let _ = write!(out, "\x1b[{};1H", row + 1);
+            for col in 0..pw {
+                let top = self.cell_at(col, row * 2, (pw, ph));
+                let bottom = self.cell_at(col, row * 2 + 1, (pw, ph));
+                let cursor = col == ccol && row == crow;
+                if self.ascii || cursor {
+                    let (ch, color) = match top {
+                        _ if cursor => ('+', [255, 255, 255]),
+                        Some((x, y)) => (self.glyph(x, y), self.color(x, y)),
+                        None => (' ', [0, 0, 0]),
+                    };
+                    let [r, g, b] = color;
+                    let _ = write!(out, "\x1b[49;38;2;{r};{g};{b}m{ch}");
+                    continue;
+                }
+                let [r, g, b] = top.map_or([0, 0, 0], |(x, y)| self.color(x, y));
+                let [br, bg, bb] = bottom.map_or([0, 0, 0], |(x, y)| self.color(x, y));
+                let _ = write!(out, "\x1b[38;2;{r};{g};{b};48;2;{br};{bg};{bb}m\u{2580}");
+            }
+        }
+        out.push_str("\x1b[0m");
+
+        let _ = write!(out, "\x1b[{};1H\x1b[2K", ph / 2 + 1);
+        out.push_str(&fit(&self.inspector(), pw as usize));
+        let _ = write!(out, "\x1b[{};1H\x1b[2K", ph / 2 + 2);
+        let on = |flag: bool| if flag { "on" } else { "off" };
+        let help = format!(
+            "arrows/hjkl pan  +/- zoom  b biomes:{}  r rivers:{}  e elevation:{}  q quit  ({:.3} cells/px)",
+            on(self.layers.biomes),
+            on(self.layers.rivers),
+            on(self.layers.elevation),
+            self.view.scale
+        );
+        out.push_str(&fit(&help, pw as usize));
+        out
+    }
+
+    /// Что лежит в клетке под курсором
+    fn inspector(&self) -> String {
+        let (x, y) = (self.view.cx as u32, self.view.cy as u32);
+        let cfg = self.world.cfg;
+        let elevation = self.world.hm.elevation_m(cfg.sea_level, x, y);
+        let biome = match self.world.bm.get_index(x, y) {
+            Some(idx) if idx < cfg.biomes.len() => cfg.biomes[idx].display_name.as_str(),
+            _ => "water",
+        };
+        let i = (y * self.climate.width + x) as usize;
+        let mut line = format!(
+            "cell {x},{y}  height {elevation:.0} m  biome {biome}  temp {:.1} C  humidity {:.2}  precip {:.0} mm/yr",
+            self.climate.temperature_c[i],
+            self.climate.humidity[i],
+            self.climate.precipitation_mm_per_year[i]
+        );
+        if self.rivers[i] {
+            line.push_str("  river");
+        }
+        line
+    }
+}
+
+/// Обрезает строку по ширине терминала
+fn fit(s: &str, width: usize) -> String {
+    s.chars().take(width).collect()
+}
+
+#[cfg(unix)]
+mod term {
+    use super::Key;
+    use std::io::Write;
+
+    /// Сырой режим и альтернативный экран; всё возвращается в `Drop`
+    pub struct RawMode {
+        saved: libc::termios,
+    }
+
+    impl RawMode {
+        pub fn enter() -> anyhow::Result<Self> {
+            // SAFETY: termios — простая структура, заполняется tcgetattr
+            let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            let mut raw = saved;
+            unsafe {
+                libc::cfmakeraw(&mut raw);
+                if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+            }
+            write("\x1b[?1049h\x1b[?25l\x1b[2J")?;
+            Ok(Self { saved })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            let _ = write("\x1b[0m\x1b[?25h\x1b[?1049l");
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+            }
+        }
+    }
+
+    /// Колонки и строки терминала (80×24, если размер не узнать)
+    pub fn size() -> (u16, u16) {
+        // SAFETY: winsize заполняется ioctl, при ошибке не используется
+        let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+        let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) } == 0;
+        if ok && ws.ws_col > 0 && ws.ws_row > 0 {
+            (ws.ws_col, ws.ws_row)
+        } else {
+            (80, 24)
+        }
+    }
+
+    pub fn write(s: &str) -> std::io::Result<()> {
+        let mut out = std::io::stdout().lock();
+        out.write_all(s.as_bytes())?;
+        out.flush()
+    }
+
+    /// Ждёт нажатия; стрелки приходят как `ESC [ A..D`
+    pub fn read_key() -> std::io::Result<Key> {
+        let mut buf = [0u8; 8];
+        let n = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+        if n < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(match &buf[..n as usize] {
+            [0x1b, b'[', b'A', ..] | [b'k'] | [b'w'] => Key::Up,
+            [0x1b, b'[', b'B', ..] | [b'j'] | [b's'] => Key::Down,
+            [0x1b, b'[', b'D', ..] | [b'h'] | [b'a'] => Key::Left,
+            [0x1b, b'[', b'C', ..] | [b'l'] | [b'd'] => Key::Right,
+            [b'+'] | [b'='] => Key::ZoomIn,
+            [b'-'] | [b'_'] => Key::ZoomOut,
+            [b'q'] | [0x1b] | [0x03] => Key::Quit,
+            [c @ (b'b' | b'r' | b'e')] => Key::Toggle(*c as char),
+            _ => Key::Other,
+        })
+    }
+}
+
+#[cfg(not(unix))]
+mod term {
+    use super::Key;
+
+    pub struct RawMode;
+
+    impl RawMode {
+        pub fn enter() -> anyhow::Result<Self> {
+            anyhow::bail!("tui is only supported on Unix terminals")
+        }
+    }
+
+    pub fn size() -> (u16, u16) {
+        (80, 24)
+    }
+
+    pub fn write(_: &str) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    pub fn read_key() -> std::io::Result<Key> {
+        Ok(Key::Quit)
+    }
+}
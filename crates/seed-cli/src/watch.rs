@@ -0,0 +1,156 @@
+//! Режим наблюдения (`seed-cli watch`): при каждом сохранении конфига карты
+//! пересобираются в уменьшенном разрешении. Файл опрашивается по времени
+//! изменения; серия быстрых сохранений даёт одну пересборку. Этапы, входы
+//! которых не поменялись, берутся из прошлого прохода — например, heightmap
+//! зависит только от `geology.heightmap` и размера карты.
+
+use crate::dem::save_heightmap;
+use crate::relief::save_hillshade;
+use crate::{hillshade_sun, render_styled_worldview, save_biome_map_to_png, Cli};
+use seed_config::WorldConfig;
+use seed_core::{
+    generate_biome_map_from_config, generate_heightmap_from_config, BiomeMap, Heightmap,
+};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Как часто смотреть на файл конфига
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Куда писать превью
+pub struct WatchOutputs<'a> {
+    pub heightmap: Option<&'a str>,
+    pub biomes: Option<&'a str>,
+    pub worldview: Option<&'a str>,
+    pub hillshade: Option<&'a str>,
+}
+
+/// Результат этапа и ключ входов, по которым он посчитан
+struct Stage<T> {
+    key: String,
+    value: T,
+}
+
+#[derive(Default)]
+struct Cache {
+    heightmap: Option<Stage<Heightmap>>,
+    biomes: Option<Stage<BiomeMap>>,
+}
+
+/// Следит за `config_path` до Ctrl+C; карты — `width`×`height`
+pub fn run_watch(
+    cli: &Cli,
+    config_path: &str,
+    (width, height): (u32, u32),
+    debounce: Duration,
+    outputs: &WatchOutputs,
+) -> anyhow::Result<()> {
+    let path = Path::new(config_path);
+    let mut cache = Cache::default();
+    let mut seen = modified(path);
+
+    println!(
+        "Watching {} ({}x{} preview, Ctrl+C to stop) ...",
+        config_path, width, height
+    );
+    rebuild(cli, path, (width, height), outputs, &mut cache);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let current = modified(path);
+        if current == seen || current.is_none() {
+            continue;
+        }
+        // ждём, пока файл перестанет меняться
+        let mut settled = current;
+        loop {
+            std::thread::sleep(debounce);
+            let again = modified(path);
+            if again == settled && again.is_some() {
+                break;
+            }
+            settled = again;
+        }
+        seen = settled;
+        println!();
+        println!("Config changed, regenerating ...");
+        rebuild(cli, path, (width, height), outputs, &mut cache);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Один проход; ошибки печатаются, а наблюдение продолжается
+fn rebuild(cli: &Cli, path: &Path, size: (u32, u32), outputs: &WatchOutputs, cache: &mut Cache) {
+    let started = Instant::now();
+    let result = WorldConfig::from_file(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|cfg| regenerate(cli, &cfg, size, outputs, cache));
+    match result {
+        Ok(()) => println!("Ready in {:.2} s.", started.elapsed().as_secs_f64()),
+        Err(e) => println!("error: {e:#}"),
+    }
+}
+
+fn regenerate(
+    cli: &Cli,
+    cfg: &WorldConfig,
+    (width, height): (u32, u32),
+    outputs: &WatchOutputs,
+    cache: &mut Cache,
+) -> anyhow::Result<()> {
+    // heightmap зависит только от своего раздела конфига и размера,
+    // биомы — от всего остального тоже (климат, уровень моря, seed)
+    let heightmap_key = format!(
+        "{width}x{height}:{}",
+        serde_json::to_string(&cfg.geology.heightmap)?
+    );
+    let biome_key = format!("{heightmap_key}:{}", serde_json::to_string(cfg)?);
+
+    if cache.heightmap.as_ref().map(|s| &s.key) == Some(&heightmap_key) {
+        println!("  heightmap: reused");
+    } else {
+        let t = Instant::now();
+        let value = generate_heightmap_from_config(cfg, width, height);
+        println!("  heightmap: {:.2} s", t.elapsed().as_secs_f64());
+        cache.heightmap = Some(Stage {
+            key: heightmap_key,
+            value,
+        });
+        cache.biomes = None;
+    }
+    let hm = &cache.heightmap.as_ref().expect("heightmap stage").value;
+
+    if cache.biomes.as_ref().map(|s| &s.key) == Some(&biome_key) {
+        println!("  biomes: reused");
+    } else {
+        let t = Instant::now();
+        let value = generate_biome_map_from_config(cfg, hm);
+        println!("  biomes: {:.2} s", t.elapsed().as_secs_f64());
+        cache.biomes = Some(Stage {
+            key: biome_key,
+            value,
+        });
+    }
+    let bm = &cache.biomes.as_ref().expect("biome stage").value;
+
+    if let Some(out) = outputs.heightmap {
+        save_heightmap(hm, cfg, cli.heightmap_format, false, out)?;
+        println!("  wrote {out}");
+    }
+    if let Some(out) = outputs.hillshade {
+        let sun = hillshade_sun(cli, cfg);
+        save_hillshade(hm, cfg, cli.z_factor, sun, out)?;
+        println!("  wrote {out}");
+    }
+    if let Some(out) = outputs.biomes {
+        save_biome_map_to_png(bm, cfg, out)?;
+        println!("  wrote {out}");
+    }
+    if let Some(out) = outputs.worldview {
+        render_styled_worldview(cli, hm, bm, cfg, 1.0).save(out)?;
+        println!("  wrote {out}");
+    }
+    Ok(())
+}
@@ -2,8 +2,13 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::str::FromStr;
 use thiserror::Error;
 
+mod validate;
+
+pub use validate::{Diagnostic, Severity};
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("I/O error while reading config: {0}")]
@@ -128,6 +133,8 @@ pub struct EnvironmentConfig {
 pub struct AtmosphereConfig {
     pub composition: HashMap<String, f64>, // N2, O2 и т.д.
     pub pressure_k_pa: f64,
+    /// Какой была бы температура у Земли по умолчанию; звезда, орбита, наклон
+    /// оси и атмосфера сдвигают её (`seed_core::planet`)
     pub base_temperature_c: f64,
     pub humidity_global_mean: f64,
     pub scattering_intensity: f32,
@@ -221,6 +228,9 @@ pub struct BiomeConfig {
     pub vegetation_density: f32,
     pub fauna_profiles: Vec<String>,
     pub allow_settlements: bool,
+    /// Во сколько раз шаг по биому дороже, чем по голой равнине, при поиске
+    /// пути; без него — 1 + `vegetation_density`
+    pub movement_cost: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -284,6 +294,8 @@ pub struct SpeciesConfig {
     pub preferred_biomes: Vec<String>,
     pub population_density_per_km2: f64,
     pub migration_enabled: bool,
+    /// Явный список добычи (id видов); по умолчанию — по трофическим уровням
+    pub prey: Option<Vec<String>>,
 }
 
 // ---------- Catastrophes ----------
@@ -341,6 +353,8 @@ pub struct FactionPresetConfig {
     pub preferred_biomes: Vec<String>,
     pub starting_population: i64,
     pub capital_location_hint: CapitalLocationHintConfig,
+    /// Стиль процедурных имён ("nordic", "latin", ...); по умолчанию — по биомам
+    pub name_style: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -373,6 +387,8 @@ pub struct NarrativeDirectorConfig {
     pub can_trigger_global_catastrophes: bool,
     pub quest_generation: QuestGenerationConfig,
     pub event_policies: EventPoliciesConfig,
+    /// Шаблоны заданий; директор подставляет в них цели из живого мира
+    pub quest_templates: Option<Vec<QuestTemplateConfig>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -392,6 +408,40 @@ pub struct EventPoliciesConfig {
     pub max_players_killed_by_system_event: f32,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestTemplateConfig {
+    pub id: String,
+    /// Один из `preferredQuestTypes`
+    pub quest_type: String,
+    /// Заголовок; `{target}` — цель первого шага, `{faction}` — фракция-заказчик
+    pub title: String,
+    /// Относительный вес среди шаблонов того же типа (по умолчанию 1)
+    pub weight: Option<f32>,
+    pub objectives: Vec<QuestObjectiveConfig>,
+    pub rewards: Option<Vec<QuestRewardConfig>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestObjectiveConfig {
+    /// Действие: "travel", "explore", "defend", "rescue", "gather", ...
+    pub action: String,
+    /// Селектор цели, например "nearest ruin in hostile territory"
+    pub target: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestRewardConfig {
+    pub item: String,
+    pub min: u32,
+    pub max: u32,
+    /// Вероятность выпадения (по умолчанию 1)
+    pub chance: Option<f32>,
+}
+
 // ---------- Simulation ----------
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -440,14 +490,18 @@ pub struct SimulationNetworkConfig {
 
 // ---------- Загрузка ----------
 
-impl WorldConfig {
-    pub fn from_str(s: &str) -> Result<Self> {
+impl FromStr for WorldConfig {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self> {
         let cfg = serde_json::from_str::<WorldConfig>(s)?;
         Ok(cfg)
     }
+}
 
+impl WorldConfig {
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let data = fs::read_to_string(path)?;
-        Self::from_str(&data)
+        data.parse()
     }
 }
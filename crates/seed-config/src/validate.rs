@@ -0,0 +1,341 @@
+//! Смысловая проверка конфига: ссылки между разделами, диапазоны,
+//! повторяющиеся id. Парсинг JSON проверяет только типы; здесь ловится то,
+//! что парсится, но не имеет смысла. Каждая находка указывает JSON-путь поля.
+
+use crate::WorldConfig;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Конфиг нельзя использовать
+    Error,
+    /// Конфиг работает, но, скорее всего, не так, как задумано
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// JSON-путь поля, например `$.biomes[2].climateRange.humidity`
+    pub path: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{level}: {}: {}", self.path, self.message)
+    }
+}
+
+impl WorldConfig {
+    /// Все смысловые ошибки и предупреждения конфига
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut out = Vec::new();
+
+        if !(0.0..=1.0).contains(&self.sea_level) {
+            out.push(Diagnostic::error("$.seaLevel", "must be within 0..1"));
+        }
+        if self.scale.region_size_km <= 0.0 {
+            out.push(Diagnostic::error(
+                "$.scale.regionSizeKm",
+                "must be positive",
+            ));
+        }
+        if self.scale.planet_radius_km <= 0.0 {
+            out.push(Diagnostic::error(
+                "$.scale.planetRadiusKm",
+                "must be positive",
+            ));
+        }
+
+        // Звёздная система
+        let sys = &self.cosmos.star_system;
+        let stars: HashSet<&str> = sys.stars.iter().map(|s| s.id.as_str()).collect();
+        duplicates(
+            sys.planets.iter().map(|p| p.id.as_str()),
+            "$.cosmos.starSystem.planets",
+            &mut out,
+        );
+        if !sys.planets.iter().any(|p| p.id == sys.active_planet_id) {
+            out.push(Diagnostic::error(
+                "$.cosmos.starSystem.activePlanetId",
+                format!("planet '{}' is not defined", sys.active_planet_id),
+            ));
+        }
+        for (i, p) in sys.planets.iter().enumerate() {
+            if !stars.contains(p.orbit.star_id.as_str()) {
+                out.push(Diagnostic::warning(
+                    format!("$.cosmos.starSystem.planets[{i}].orbit.starId"),
+                    format!("star '{}' is not defined", p.orbit.star_id),
+                ));
+            }
+        }
+
+        // Материалы и биомы
+        duplicates(
+            self.materials.iter().map(|m| m.id.as_str()),
+            "$.materials",
+            &mut out,
+        );
+        if self.biomes.is_empty() {
+            out.push(Diagnostic::error(
+                "$.biomes",
+                "at least one biome is required",
+            ));
+        }
+        duplicates(
+            self.biomes.iter().map(|b| b.id.as_str()),
+            "$.biomes",
+            &mut out,
+        );
+        let biomes: HashSet<&str> = self.biomes.iter().map(|b| b.id.as_str()).collect();
+        for (i, b) in self.biomes.iter().enumerate() {
+            let path = format!("$.biomes[{i}]");
+            let c = &b.climate_range;
+            range(
+                &c.temperature_c,
+                &format!("{path}.climateRange.temperatureC"),
+                &mut out,
+            );
+            range(
+                &c.humidity,
+                &format!("{path}.climateRange.humidity"),
+                &mut out,
+            );
+            range(
+                &c.elevation_meters,
+                &format!("{path}.climateRange.elevationMeters"),
+                &mut out,
+            );
+            range(
+                &b.precipitation_range_mm_per_year,
+                &format!("{path}.precipitationRangeMmPerYear"),
+                &mut out,
+            );
+            if !(0.0..=1.0).contains(&b.vegetation_density) {
+                out.push(Diagnostic::warning(
+                    format!("{path}.vegetationDensity"),
+                    "expected within 0..1",
+                ));
+            }
+            if b.movement_cost.is_some_and(|c| c <= 0.0) {
+                out.push(Diagnostic::warning(
+                    format!("{path}.movementCost"),
+                    "expected greater than 0",
+                ));
+            }
+        }
+
+        // Экосистемы
+        let eco = &self.ecosystems;
+        if eco.time_step_minutes == 0 {
+            out.push(Diagnostic::error(
+                "$.ecosystems.timeStepMinutes",
+                "must be positive",
+            ));
+        }
+        if !["local", "regional", "global"].contains(&eco.simulation_scale.as_str()) {
+            out.push(Diagnostic::warning(
+                "$.ecosystems.simulationScale",
+                format!(
+                    "unknown scale '{}', treated as regional",
+                    eco.simulation_scale
+                ),
+            ));
+        }
+        duplicates(
+            eco.species_definitions.iter().map(|s| s.id.as_str()),
+            "$.ecosystems.speciesDefinitions",
+            &mut out,
+        );
+        for (i, s) in eco.species_definitions.iter().enumerate() {
+            let path = format!("$.ecosystems.speciesDefinitions[{i}]");
+            if s.population_density_per_km2 < 0.0 {
+                out.push(Diagnostic::error(
+                    format!("{path}.populationDensityPerKm2"),
+                    "must not be negative",
+                ));
+            }
+            unknown_biomes(&s.preferred_biomes, &biomes, &path, &mut out);
+        }
+
+        // Катастрофы
+        for (i, e) in self.catastrophes.event_types.iter().enumerate() {
+            let path = format!("$.catastrophes.eventTypes[{i}]");
+            if e.base_frequency_per_year < 0.0 {
+                out.push(Diagnostic::error(
+                    format!("{path}.baseFrequencyPerYear"),
+                    "must not be negative",
+                ));
+            }
+            if let Some(r) = &e.affected_radius_km_range {
+                range(r, &format!("{path}.affectedRadiusKmRange"), &mut out);
+            }
+            if let Some(r) = &e.crater_radius_km_range {
+                range(r, &format!("{path}.craterRadiusKmRange"), &mut out);
+            }
+        }
+
+        // Цивилизации
+        let civ = &self.civilizations;
+        duplicates(
+            civ.faction_presets.iter().map(|f| f.id.as_str()),
+            "$.civilizations.factionPresets",
+            &mut out,
+        );
+        for (i, f) in civ.faction_presets.iter().enumerate() {
+            let path = format!("$.civilizations.factionPresets[{i}]");
+            if f.starting_population < 0 {
+                out.push(Diagnostic::error(
+                    format!("{path}.startingPopulation"),
+                    "must not be negative",
+                ));
+            }
+            let hint = &f.capital_location_hint;
+            if !(-90.0..=90.0).contains(&hint.lat_deg) {
+                out.push(Diagnostic::error(
+                    format!("{path}.capitalLocationHint.latDeg"),
+                    "must be within -90..90",
+                ));
+            }
+            if !(-180.0..=180.0).contains(&hint.lon_deg) {
+                out.push(Diagnostic::error(
+                    format!("{path}.capitalLocationHint.lonDeg"),
+                    "must be within -180..180",
+                ));
+            }
+            unknown_biomes(&f.preferred_biomes, &biomes, &path, &mut out);
+        }
+        let hist = &civ.history_simulation;
+        for (name, v) in [
+            ("warLikelihood", hist.war_likelihood),
+            ("tradeImportance", hist.trade_importance),
+            (
+                "catastropheImpactOnHistory",
+                hist.catastrophe_impact_on_history,
+            ),
+        ] {
+            unit(
+                v,
+                &format!("$.civilizations.historySimulation.{name}"),
+                &mut out,
+            );
+        }
+
+        // Директор
+        let nd = &self.narrative_director;
+        for (name, v) in [
+            ("aggressiveness", nd.aggressiveness),
+            ("playerDangerBias", nd.player_danger_bias),
+            ("worldStabilityBias", nd.world_stability_bias),
+        ] {
+            unit(v, &format!("$.narrativeDirector.{name}"), &mut out);
+        }
+        if !(0.0..=1.0).contains(&nd.event_policies.max_players_killed_by_system_event) {
+            out.push(Diagnostic::error(
+                "$.narrativeDirector.eventPolicies.maxPlayersKilledBySystemEvent",
+                "is a share of players and must be within 0..1",
+            ));
+        }
+        if let Some(templates) = &nd.quest_templates {
+            duplicates(
+                templates.iter().map(|t| t.id.as_str()),
+                "$.narrativeDirector.questTemplates",
+                &mut out,
+            );
+            for (i, t) in templates.iter().enumerate() {
+                let path = format!("$.narrativeDirector.questTemplates[{i}]");
+                if t.objectives.is_empty() {
+                    out.push(Diagnostic::error(
+                        format!("{path}.objectives"),
+                        "at least one objective is required",
+                    ));
+                }
+                for (j, r) in t.rewards.iter().flatten().enumerate() {
+                    if r.min > r.max {
+                        out.push(Diagnostic::error(
+                            format!("{path}.rewards[{j}]"),
+                            "min is greater than max",
+                        ));
+                    }
+                    if r.chance.is_some_and(|c| !(0.0..=1.0).contains(&c)) {
+                        out.push(Diagnostic::error(
+                            format!("{path}.rewards[{j}].chance"),
+                            "must be within 0..1",
+                        ));
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// Ошибка на каждый повтор id в списке `path`
+fn duplicates<'a>(ids: impl Iterator<Item = &'a str>, path: &str, out: &mut Vec<Diagnostic>) {
+    let mut seen = HashSet::new();
+    for (i, id) in ids.enumerate() {
+        if !seen.insert(id) {
+            out.push(Diagnostic::error(
+                format!("{path}[{i}].id"),
+                format!("duplicate id '{id}'"),
+            ));
+        }
+    }
+}
+
+fn range(r: &[f64; 2], path: &str, out: &mut Vec<Diagnostic>) {
+    if r[0] > r[1] {
+        out.push(Diagnostic::error(
+            path,
+            format!("range start {} is greater than end {}", r[0], r[1]),
+        ));
+    }
+}
+
+/// Коэффициенты 0..1 вне диапазона обрезаются симуляцией — это предупреждение
+fn unit(v: f32, path: &str, out: &mut Vec<Diagnostic>) {
+    if !(0.0..=1.0).contains(&v) {
+        out.push(Diagnostic::warning(
+            path,
+            format!("{v} is outside 0..1 and will be clamped"),
+        ));
+    }
+}
+
+fn unknown_biomes(ids: &[String], biomes: &HashSet<&str>, path: &str, out: &mut Vec<Diagnostic>) {
+    for (j, id) in ids.iter().enumerate() {
+        if !biomes.contains(id.as_str()) {
+            out.push(Diagnostic::warning(
+                format!("{path}.preferredBiomes[{j}]"),
+                format!("biome '{id}' is not defined"),
+            ));
+        }
+    }
+}
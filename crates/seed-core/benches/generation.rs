@@ -0,0 +1,142 @@
+//! Время этапов генерации на конфиге-образце `world-config.json`:
+//! рельеф целиком на 512/1024/2048, шум и каждый проход эрозии по
+//! отдельности, сток, биомы и объекты. Запуск — `cargo bench -p seed-core`,
+//! один этап — `cargo bench -p seed-core -- erosion/`; сравнить с прошлым
+//! прогоном criterion умеет сам (`--save-baseline` / `--baseline`).
+//!
+//! Бюджеты — с запасом в 2–4 раза к замерам на машине разработчика с
+//! `parallel` по умолчанию; на них равняются переделки вроде rayon или GPU,
+//! и выход за бюджет — регрессия:
+//!
+//! | этап                           | бюджет |
+//! |--------------------------------|--------|
+//! | `heightmap/512`                | 1 с    |
+//! | `heightmap/1024`               | 4 с    |
+//! | `heightmap/2048`               | 16 с   |
+//! | `erosion/*` на 512             | 250 мс |
+//! | `maps/flow_accumulation/512`   | 100 мс |
+//! | `maps/biomes/512`              | 1 с    |
+//! | `objects/chunk` (32×32)        | 1 мс   |
+//! | `objects/map/512`              | 200 мс |
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use seed_config::WorldConfig;
+use seed_core::{
+    compute_flow_accumulation, generate_biome_map_from_config, generate_heightmap_from_config,
+    generate_objects_for_chunk, HeightmapBuilder,
+};
+
+/// Сторона карты для всего, кроме `heightmap/*`
+const SIZE: u32 = 512;
+/// Проходы эрозии `HeightmapBuilder` по порядку
+const EROSION_STAGES: [&str; 5] = ["thermal", "flow", "lakes", "canyons", "smooth"];
+const OBJECT_CHUNK_CELLS: u32 = 32;
+
+fn sample_config() -> WorldConfig {
+    include_str!("../../../world-config.json")
+        .parse()
+        .expect("world-config.json parses")
+}
+
+fn heightmap(c: &mut Criterion) {
+    let cfg = sample_config();
+    let mut group = c.benchmark_group("heightmap");
+    group.sample_size(10);
+    for size in [512, 1024, 2048] {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| generate_heightmap_from_config(&cfg, size, size))
+        });
+    }
+    group.finish();
+}
+
+fn erosion(c: &mut Criterion) {
+    let cfg = sample_config();
+    let mut group = c.benchmark_group("erosion");
+    group.sample_size(10);
+
+    group.bench_function("noise", |b| {
+        b.iter_batched(
+            || HeightmapBuilder::new(&cfg, SIZE, SIZE),
+            |mut builder| builder.step(SIZE),
+            BatchSize::LargeInput,
+        )
+    });
+    // строитель перед каждым проходом: шум готов, прошлые проходы сделаны
+    let mut builder = HeightmapBuilder::new(&cfg, SIZE, SIZE);
+    builder.step(SIZE);
+    for stage in EROSION_STAGES {
+        group.bench_function(stage, |b| {
+            b.iter_batched(
+                || builder.clone(),
+                |mut builder| builder.step(1),
+                BatchSize::LargeInput,
+            )
+        });
+        builder.step(1);
+    }
+    group.finish();
+}
+
+fn flow_and_biomes(c: &mut Criterion) {
+    let cfg = sample_config();
+    let hm = generate_heightmap_from_config(&cfg, SIZE, SIZE);
+    let mut group = c.benchmark_group("maps");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("flow_accumulation", SIZE), |b| {
+        b.iter(|| compute_flow_accumulation(&hm, cfg.sea_level as f32))
+    });
+    group.bench_function(BenchmarkId::new("biomes", SIZE), |b| {
+        b.iter(|| generate_biome_map_from_config(&cfg, &hm))
+    });
+    group.finish();
+}
+
+fn objects(c: &mut Criterion) {
+    let cfg = sample_config();
+    let hm = generate_heightmap_from_config(&cfg, SIZE, SIZE);
+    let bm = generate_biome_map_from_config(&cfg, &hm);
+    let mut group = c.benchmark_group("objects");
+    // чанк посреди карты — там суша
+    let origin = SIZE / 2;
+    group.bench_function("chunk", |b| {
+        b.iter(|| {
+            generate_objects_for_chunk(
+                &cfg,
+                &hm,
+                &bm,
+                origin,
+                origin,
+                OBJECT_CHUNK_CELLS,
+                OBJECT_CHUNK_CELLS,
+                cfg.world_seed,
+            )
+        })
+    });
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("map", SIZE), |b| {
+        b.iter(|| {
+            let chunks = SIZE / OBJECT_CHUNK_CELLS;
+            (0..chunks * chunks)
+                .map(|i| {
+                    let (cx, cy) = (i % chunks, i / chunks);
+                    generate_objects_for_chunk(
+                        &cfg,
+                        &hm,
+                        &bm,
+                        cx * OBJECT_CHUNK_CELLS,
+                        cy * OBJECT_CHUNK_CELLS,
+                        OBJECT_CHUNK_CELLS,
+                        OBJECT_CHUNK_CELLS,
+                        cfg.world_seed,
+                    )
+                    .len()
+                })
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, heightmap, erosion, flow_and_biomes, objects);
+criterion_main!(benches);
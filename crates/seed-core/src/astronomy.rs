@@ -0,0 +1,255 @@
+//! Солнце и луны над любой точкой планеты в любой момент мирового времени —
+//! для смены дня и ночи на сервере, отмывки «в такой-то час» в seed-cli и
+//! неба в браузере. Время — игровые секунды с начала мира; год начинается
+//! с весеннего равноденствия, полдень на долготе 0 — середина суток.
+//! Длина суток и года, наклон оси, орбита и луны — из `cosmos.starSystem`
+//! активной планеты, чего нет — земное.
+//!
+//! Модель простая, но честная: склонение и прямое восхождение солнца — по
+//! его эклиптической долготе, расстояние до звезды — по эксцентриситету
+//! (перигелий в равноденствие), луны ходят по круговым орбитам в плоскости
+//! эклиптики с периодом по третьему закону Кеплера. В начале мира все
+//! луны новые.
+
+use std::f64::consts::TAU;
+
+use seed_config::{PlanetConfig, WorldConfig};
+
+// Если активной планеты в конфиге нет — земные сутки, год и наклон
+const DEFAULT_DAY_HOURS: f64 = 24.0;
+const DEFAULT_YEAR_DAYS: f64 = 365.25;
+const DEFAULT_TILT_DEG: f64 = 23.44;
+/// Поток от Солнца на 1 а. е., Вт/м²
+const SOLAR_CONSTANT_W_M2: f64 = 1361.0;
+/// Гравитационная постоянная, умноженная на массу Земли, м³/с²
+const EARTH_GM: f64 = 3.986e14;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunPosition {
+    /// Высота над горизонтом, градусы; отрицательная — ночь
+    pub elevation_deg: f64,
+    /// Азимут по часовой стрелке от севера, градусы
+    pub azimuth_deg: f64,
+    pub declination_deg: f64,
+    /// Часовой угол в точке: 0 — местный полдень
+    pub hour_angle_deg: f64,
+}
+
+impl SunPosition {
+    /// Единичный вектор на солнце: восток, вверх, север
+    pub fn direction(&self) -> [f64; 3] {
+        direction(self.elevation_deg, self.azimuth_deg)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoonPosition {
+    pub id: String,
+    pub name: String,
+    /// Доля синодического месяца: 0 — новолуние, 0.5 — полнолуние
+    pub phase: f64,
+    /// Освещённая доля диска, 0..1
+    pub illumination: f64,
+    pub elevation_deg: f64,
+    pub azimuth_deg: f64,
+}
+
+impl MoonPosition {
+    /// Единичный вектор на луну: восток, вверх, север
+    pub fn direction(&self) -> [f64; 3] {
+        direction(self.elevation_deg, self.azimuth_deg)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MoonOrbit {
+    id: String,
+    name: String,
+    /// Синодический месяц, с
+    synodic_s: f64,
+}
+
+/// Сутки, год, орбита и луны активной планеты
+#[derive(Debug, Clone)]
+pub struct Astronomy {
+    day_length_s: f64,
+    year_length_days: f64,
+    tilt: f64,
+    eccentricity: f64,
+    /// Поток от звезды на среднем расстоянии, Вт/м²
+    mean_flux_w_m2: f64,
+    moons: Vec<MoonOrbit>,
+}
+
+impl Astronomy {
+    pub fn from_config(cfg: &WorldConfig) -> Self {
+        let sys = &cfg.cosmos.star_system;
+        let planet = sys.planets.iter().find(|p| p.id == sys.active_planet_id);
+        let day_hours = planet
+            .map(|p| p.day_length_hours)
+            .filter(|h| *h > 0.0)
+            .unwrap_or(DEFAULT_DAY_HOURS);
+        let year_length_days = planet
+            .map(|p| p.year_length_days)
+            .filter(|d| *d > 0.0)
+            .unwrap_or(DEFAULT_YEAR_DAYS);
+        let day_length_s = day_hours * 3600.0;
+        let luminosity = planet
+            .and_then(|p| sys.stars.iter().find(|s| s.id == p.orbit.star_id))
+            .map_or(1.0, |s| s.luminosity.max(0.0));
+        let axis_au = planet
+            .map(|p| p.orbit.semi_major_axis_au)
+            .filter(|a| *a > 0.0)
+            .unwrap_or(1.0);
+        let year_s = year_length_days * day_length_s;
+        Astronomy {
+            day_length_s,
+            year_length_days,
+            tilt: planet
+                .map_or(DEFAULT_TILT_DEG, |p| p.axial_tilt_degrees)
+                .to_radians(),
+            eccentricity: planet.map_or(0.0, |p| p.orbit.eccentricity.clamp(0.0, 0.99)),
+            mean_flux_w_m2: SOLAR_CONSTANT_W_M2 * luminosity / (axis_au * axis_au),
+            moons: planet.map_or_else(Vec::new, |p| moon_orbits(p, year_s)),
+        }
+    }
+
+    pub fn day_length_s(&self) -> f64 {
+        self.day_length_s
+    }
+
+    pub fn year_length_days(&self) -> f64 {
+        self.year_length_days
+    }
+
+    pub fn year_length_s(&self) -> f64 {
+        self.year_length_days * self.day_length_s
+    }
+
+    /// Доля суток на долготе 0: 0 — полночь, 0.5 — полдень
+    pub fn time_of_day(&self, time_s: f64) -> f64 {
+        (time_s / self.day_length_s).rem_euclid(1.0)
+    }
+
+    /// Доля года: 0 — весеннее равноденствие
+    pub fn year_fraction(&self, time_s: f64) -> f64 {
+        (time_s / self.year_length_s()).rem_euclid(1.0)
+    }
+
+    /// Солнце над точкой (`lat_deg` — к северу, `lon_deg` — к востоку)
+    pub fn sun(&self, lat_deg: f64, lon_deg: f64, time_s: f64) -> SunPosition {
+        let (declination, _) = self.equatorial(self.sun_longitude(time_s));
+        let hour_angle = self.solar_hour_angle(lon_deg, time_s);
+        let (elevation, azimuth) = horizontal(lat_deg.to_radians(), declination, hour_angle);
+        SunPosition {
+            elevation_deg: elevation.to_degrees(),
+            azimuth_deg: azimuth.to_degrees(),
+            declination_deg: declination.to_degrees(),
+            hour_angle_deg: wrap_angle(hour_angle).to_degrees(),
+        }
+    }
+
+    /// Поток от звезды на горизонтальную площадку на верхней границе
+    /// атмосферы, Вт/м²; ночью — 0
+    pub fn insolation(&self, lat_deg: f64, lon_deg: f64, time_s: f64) -> f64 {
+        let sun = self.sun(lat_deg, lon_deg, time_s);
+        // перигелий в равноденствие: расстояние по средней аномалии
+        let anomaly = TAU * self.year_fraction(time_s);
+        let distance = 1.0 - self.eccentricity * anomaly.cos();
+        self.mean_flux_w_m2 / (distance * distance) * sun.elevation_deg.to_radians().sin().max(0.0)
+    }
+
+    /// Луны планеты над точкой
+    pub fn moons(&self, lat_deg: f64, lon_deg: f64, time_s: f64) -> Vec<MoonPosition> {
+        let sun_longitude = self.sun_longitude(time_s);
+        let (_, sun_ra) = self.equatorial(sun_longitude);
+        let sun_hour_angle = self.solar_hour_angle(lon_deg, time_s);
+        self.moons
+            .iter()
+            .map(|m| {
+                let phase = (time_s / m.synodic_s).rem_euclid(1.0);
+                // луна отстаёт от солнца по небу на свою элонгацию
+                let (declination, ra) = self.equatorial(sun_longitude + TAU * phase);
+                let hour_angle = sun_hour_angle + sun_ra - ra;
+                let (elevation, azimuth) =
+                    horizontal(lat_deg.to_radians(), declination, hour_angle);
+                MoonPosition {
+                    id: m.id.clone(),
+                    name: m.name.clone(),
+                    phase,
+                    illumination: (1.0 - (TAU * phase).cos()) / 2.0,
+                    elevation_deg: elevation.to_degrees(),
+                    azimuth_deg: azimuth.to_degrees(),
+                }
+            })
+            .collect()
+    }
+
+    /// Эклиптическая долгота солнца, рад: 0 — весеннее равноденствие
+    fn sun_longitude(&self, time_s: f64) -> f64 {
+        TAU * self.year_fraction(time_s)
+    }
+
+    /// Часовой угол солнца на долготе `lon_deg`, рад
+    fn solar_hour_angle(&self, lon_deg: f64, time_s: f64) -> f64 {
+        TAU * (self.time_of_day(time_s) - 0.5) + lon_deg.to_radians()
+    }
+
+    /// Склонение и прямое восхождение точки эклиптики с долготой `longitude`
+    fn equatorial(&self, longitude: f64) -> (f64, f64) {
+        let declination = (self.tilt.sin() * longitude.sin()).asin();
+        let right_ascension = (self.tilt.cos() * longitude.sin()).atan2(longitude.cos());
+        (declination, right_ascension)
+    }
+}
+
+fn moon_orbits(planet: &PlanetConfig, year_s: f64) -> Vec<MoonOrbit> {
+    let gm = EARTH_GM
+        * if planet.mass_earths > 0.0 {
+            planet.mass_earths
+        } else {
+            1.0
+        };
+    planet
+        .moons
+        .iter()
+        .filter(|m| m.orbit_distance_km > 0.0)
+        .map(|m| {
+            let r = m.orbit_distance_km * 1000.0;
+            let sidereal_s = TAU * (r * r * r / gm).sqrt();
+            // за месяц планета уходит по орбите, и до той же фазы луне надо
+            // догнать солнце
+            let synodic_s = if sidereal_s < year_s {
+                1.0 / (1.0 / sidereal_s - 1.0 / year_s)
+            } else {
+                sidereal_s
+            };
+            MoonOrbit {
+                id: m.id.clone(),
+                name: m.name.clone(),
+                synodic_s,
+            }
+        })
+        .collect()
+}
+
+/// Высота над горизонтом и азимут (от севера по часовой стрелке), рад
+fn horizontal(lat: f64, declination: f64, hour_angle: f64) -> (f64, f64) {
+    let east = -declination.cos() * hour_angle.sin();
+    let north = declination.sin() * lat.cos() - declination.cos() * lat.sin() * hour_angle.cos();
+    let up = declination.sin() * lat.sin() + declination.cos() * lat.cos() * hour_angle.cos();
+    (
+        up.clamp(-1.0, 1.0).asin(),
+        east.atan2(north).rem_euclid(TAU),
+    )
+}
+
+fn direction(elevation_deg: f64, azimuth_deg: f64) -> [f64; 3] {
+    let (el, az) = (elevation_deg.to_radians(), azimuth_deg.to_radians());
+    [az.sin() * el.cos(), el.sin(), az.cos() * el.cos()]
+}
+
+/// Угол в -π..π
+fn wrap_angle(a: f64) -> f64 {
+    (a + TAU / 2.0).rem_euclid(TAU) - TAU / 2.0
+}
@@ -1,4 +1,7 @@
-use crate::terrain::Heightmap;
+use crate::planet::PlanetClimate;
+use crate::progress::{map_rows, GenerationPhase, NoProgress, Progress};
+use crate::seed_tree::SeedTree;
+use crate::terrain::{Heightmap, MapWindow, MAX_RELIEF_M};
 use noise::{NoiseFn, Perlin};
 use seed_config::{BiomeConfig, WorldConfig};
 
@@ -65,7 +68,7 @@ fn smooth_biome_map(src: &BiomeMap, iterations: u32) -> BiomeMap {
                         }
 
                         if let Some(bi) = current.get_index(nx as u32, ny as u32) {
-                            counts[bi as usize] += 1;
+                            counts[bi] += 1;
                             has_any = true;
                         }
                     }
@@ -79,8 +82,7 @@ fn smooth_biome_map(src: &BiomeMap, iterations: u32) -> BiomeMap {
                 let mut best_bi: Option<usize> = None;
                 let mut best_count: u16 = 0;
 
-                for bi in 0..256usize {
-                    let c = counts[bi];
+                for (bi, &c) in counts.iter().enumerate() {
                     if c > best_count {
                         best_count = c;
                         best_bi = Some(bi);
@@ -112,58 +114,157 @@ struct BiomeSample {
 
 /// Основная функция: генерирует карту биомов по heightmap и конфигу мира
 pub fn generate_biome_map_from_config(cfg: &WorldConfig, hm: &Heightmap) -> BiomeMap {
-    let width = hm.width;
-    let height = hm.height;
-    let mut bm = BiomeMap::new(width, height);
+    generate_biome_map_with_progress(cfg, hm, &NoProgress)
+}
 
-    let biomes: &[BiomeConfig] = &cfg.biomes;
-    if biomes.is_empty() {
+/// То же, что `generate_biome_map_from_config`, с отчётом о прогрессе
+pub fn generate_biome_map_with_progress(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    progress: &dyn Progress,
+) -> BiomeMap {
+    generate_biome_map_window(cfg, hm, MapWindow::FULL, progress)
+}
+
+/// Биомы для `hm`, покрывающего окно `window` полной карты (см.
+/// `generate_heightmap_window`): широта и шум границ берутся по положению на
+/// полной карте, так что биомы участка совпадают с её биомами
+pub fn generate_biome_map_window(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    window: MapWindow,
+    progress: &dyn Progress,
+) -> BiomeMap {
+    let mut bm = BiomeMap::new(hm.width, hm.height);
+    let Some(rows) = BiomeRows::new(cfg, hm, window) else {
         return bm;
+    };
+    bm.indices = map_rows(hm.height, GenerationPhase::Biomes, progress, |y| {
+        rows.row(hm, y)
+    });
+
+    // сглаживаем границы, чтобы убрать иголки
+    smooth_biome_map(&bm, 2)
+}
+
+/// Карта биомов по кускам строк (см. `HeightmapBuilder`); та же, что у
+/// `generate_biome_map_from_config`
+pub struct BiomeMapBuilder {
+    map: BiomeMap,
+    rows_done: u32,
+}
+
+impl BiomeMapBuilder {
+    pub fn new(hm: &Heightmap) -> Self {
+        Self {
+            map: BiomeMap::new(hm.width, hm.height),
+            rows_done: 0,
+        }
     }
 
-    // sea_level в координатах heightmap (0..1)
-    let sea_level_norm = cfg.sea_level as f64;
-    let max_relief_m = 3500.0_f64;
-    let sea_level_m = cfg.environment.climate_model.sea_level_meters as f64;
+    /// Следующие `rows` строк биомов для `hm`; true — все строки готовы
+    pub fn step(&mut self, cfg: &WorldConfig, hm: &Heightmap, rows: u32) -> bool {
+        let end = self.rows_done.saturating_add(rows.max(1)).min(hm.height);
+        if let Some(biome_rows) = BiomeRows::new(cfg, hm, MapWindow::FULL) {
+            for y in self.rows_done..end {
+                let start = (y * hm.width) as usize;
+                let row = biome_rows.row(hm, y);
+                self.map.indices[start..start + row.len()].copy_from_slice(&row);
+            }
+        }
+        self.rows_done = end;
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.rows_done >= self.map.height
+    }
 
-    // helper для поиска индекса биома по id
-    fn find_biome_index(biomes: &[BiomeConfig], id: &str) -> Option<usize> {
-        biomes.iter().position(|b| b.id == id)
+    /// Доля готовых строк
+    pub fn progress(&self) -> f32 {
+        self.rows_done as f32 / self.map.height.max(1) as f32
     }
 
-    let forest_idx = find_biome_index(biomes, "temperate_forest");
-    let desert_idx = find_biome_index(biomes, "hot_desert");
-    let tundra_idx = find_biome_index(biomes, "tundra");
-    let mountains_idx = find_biome_index(biomes, "cold_mountains");
+    /// Готовая карта; None, пока `step` не вернул true
+    pub fn finish(self, cfg: &WorldConfig) -> Option<BiomeMap> {
+        if !self.is_done() {
+            return None;
+        }
+        if cfg.biomes.is_empty() {
+            return Some(self.map);
+        }
+        // сглаживаем границы, чтобы убрать иголки
+        Some(smooth_biome_map(&self.map, 2))
+    }
+}
 
+fn find_biome_index(biomes: &[BiomeConfig], id: &str) -> Option<usize> {
+    biomes.iter().position(|b| b.id == id)
+}
+
+/// Выбор биомов по строкам карты, покрывающей окно `window` полной карты
+struct BiomeRows<'a> {
+    cfg: &'a WorldConfig,
+    window: MapWindow,
+    // sea_level в координатах heightmap (0..1)
+    sea_level_norm: f64,
+    sea_level_m: f64,
+    forest_idx: Option<usize>,
+    desert_idx: Option<usize>,
+    tundra_idx: Option<usize>,
+    mountains_idx: Option<usize>,
     // лёгкий шум для разнообразия (можно тонко подкручивать)
-    let base_seed = cfg.world_seed as u32;
-    let biome_noise = Perlin::new(base_seed.wrapping_add(4242));
+    noise: Perlin,
+    planet: PlanetClimate,
+    w1: f64,
+    h1: f64,
+}
 
-    let w1 = (width.saturating_sub(1).max(1)) as f64;
-    let h1 = (height.saturating_sub(1).max(1)) as f64;
+impl<'a> BiomeRows<'a> {
+    /// None — в конфиге нет биомов
+    fn new(cfg: &'a WorldConfig, hm: &Heightmap, window: MapWindow) -> Option<Self> {
+        let biomes: &[BiomeConfig] = &cfg.biomes;
+        if biomes.is_empty() {
+            return None;
+        }
+        Some(Self {
+            cfg,
+            window,
+            sea_level_norm: cfg.sea_level,
+            sea_level_m: cfg.environment.climate_model.sea_level_meters,
+            forest_idx: find_biome_index(biomes, "temperate_forest"),
+            desert_idx: find_biome_index(biomes, "hot_desert"),
+            tundra_idx: find_biome_index(biomes, "tundra"),
+            mountains_idx: find_biome_index(biomes, "cold_mountains"),
+            noise: SeedTree::from_config(cfg).derive("biomes").perlin(),
+            planet: PlanetClimate::from_config(cfg),
+            w1: (hm.width.saturating_sub(1).max(1)) as f64,
+            h1: (hm.height.saturating_sub(1).max(1)) as f64,
+        })
+    }
 
-    for y in 0..height {
-        let fy = y as f64 / h1;
+    fn row(&self, hm: &Heightmap, y: u32) -> Vec<Option<u8>> {
+        let fy = self.window.fy(y, self.h1);
         let lat = fy * 2.0 - 1.0;
         let lat_abs = lat.abs();
         let heat = 1.0 - lat_abs; // 1 — жарко, 0 — холодно
 
-        for x in 0..width {
+        let mut row = Vec::with_capacity(hm.width as usize);
+        for x in 0..hm.width {
             let h01 = hm.get(x, y) as f64;
 
             // вода
-            if h01 <= sea_level_norm + 0.002 {
-                bm.set_index(x, y, None);
+            if h01 <= self.sea_level_norm + 0.002 {
+                row.push(None);
                 continue;
             }
 
             // относительная высота над уровнем моря
-            let rel = ((h01 - sea_level_norm) / (1.0 - sea_level_norm)).clamp(0.0, 1.0);
-            let elevation_m = rel * max_relief_m;
+            let rel = ((h01 - self.sea_level_norm) / (1.0 - self.sea_level_norm)).clamp(0.0, 1.0);
+            let elevation_m = rel * MAX_RELIEF_M;
 
             // климат из JSON-модели
-            let climate = sample_climate(cfg, lat, elevation_m);
+            let climate = sample_climate_with(self.cfg, &self.planet, lat, elevation_m);
 
             let sample = BiomeSample {
                 _latitude: lat,
@@ -174,18 +275,18 @@ pub fn generate_biome_map_from_config(cfg: &WorldConfig, hm: &Heightmap) -> Biom
             };
 
             // базовый выбор по climateRange/precipitationRange
-            let mut idx = choose_biome(biomes, &sample, sea_level_m);
+            let mut idx = choose_biome(&self.cfg.biomes, &sample, self.sea_level_m);
 
             // немного шума, чтобы границы не были идеально ровными
-            let fx = x as f64 / w1;
-            let n_raw = biome_noise.get([fx * 1.3, fy * 1.3]); // -1..1
+            let fx = self.window.fx(x, self.w1);
+            let n_raw = self.noise.get([fx * 1.3, fy * 1.3]); // -1..1
             let n01 = (n_raw * 0.5 + 0.5).clamp(0.0, 1.0); // 0..1
 
             // --- Fallback-логика, если choose_biome вернул None
             //     или тебе хочется более "игрового" паттерна
             if idx.is_none() {
                 // 1) Горы: если есть mountains-биом и мы высоко
-                if let Some(mi) = mountains_idx {
+                if let Some(mi) = self.mountains_idx {
                     let mountain_base = ((elevation_m - 1400.0) / 1200.0).clamp(0.0, 1.0);
                     if mountain_base > 0.7 {
                         idx = Some(mi);
@@ -195,7 +296,7 @@ pub fn generate_biome_map_from_config(cfg: &WorldConfig, hm: &Heightmap) -> Biom
                 if idx.is_none() {
                     if heat > 0.45 {
                         // тёплый пояс — лес + пустыня
-                        if let Some(di) = desert_idx {
+                        if let Some(di) = self.desert_idx {
                             let elevation_factor = 1.0 - (elevation_m / 1400.0).clamp(0.0, 1.0);
                             let base = 0.25 + 0.35 * (heat - 0.45) / 0.55; // ~0.25..0.6
                             let desert_threshold = base * elevation_factor;
@@ -204,22 +305,22 @@ pub fn generate_biome_map_from_config(cfg: &WorldConfig, hm: &Heightmap) -> Biom
                             }
                         }
                         if idx.is_none() {
-                            idx = forest_idx;
+                            idx = self.forest_idx;
                         }
                     } else if heat < 0.3 {
                         // холодный пояс — тундра/лес
-                        if let Some(ti) = tundra_idx {
+                        if let Some(ti) = self.tundra_idx {
                             let tundra_thresh = 0.55;
                             if n01 < tundra_thresh && elevation_m < 1600.0 {
                                 idx = Some(ti);
                             }
                         }
                         if idx.is_none() {
-                            idx = forest_idx;
+                            idx = self.forest_idx;
                         }
                     } else {
                         // умеренный пояс
-                        if let Some(di) = desert_idx {
+                        if let Some(di) = self.desert_idx {
                             let elevation_factor = 1.0 - (elevation_m / 900.0).clamp(0.0, 1.0);
                             let desert_threshold = 0.25 * elevation_factor;
                             if n01 < desert_threshold {
@@ -227,46 +328,108 @@ pub fn generate_biome_map_from_config(cfg: &WorldConfig, hm: &Heightmap) -> Biom
                             }
                         }
                         if idx.is_none() {
-                            if let Some(ti) = tundra_idx {
+                            if let Some(ti) = self.tundra_idx {
                                 if n01 > 0.86 && elevation_m < 1500.0 {
                                     idx = Some(ti);
                                 }
                             }
                         }
                         if idx.is_none() {
-                            idx = forest_idx;
+                            idx = self.forest_idx;
                         }
                     }
                 }
             }
 
-            bm.set_index(x, y, idx);
+            row.push(idx.map(|v| v as u8));
         }
+        row
     }
+}
 
-    // сглаживаем границы, чтобы убрать иголки
-    smooth_biome_map(&bm, 2)
+/// Климат по клеткам карты (те же широта и высота, что и при выборе биомов)
+#[derive(Debug, Clone)]
+pub struct ClimateMap {
+    pub width: u32,
+    pub height: u32,
+    pub temperature_c: Vec<f32>,
+    /// 0..1
+    pub humidity: Vec<f32>,
+    pub precipitation_mm_per_year: Vec<f32>,
+}
+
+impl ClimateMap {
+    pub fn temperature_at(&self, x: u32, y: u32) -> f32 {
+        self.temperature_c[(y * self.width + x) as usize]
+    }
+
+    pub fn humidity_at(&self, x: u32, y: u32) -> f32 {
+        self.humidity[(y * self.width + x) as usize]
+    }
+}
+
+/// Считает климат для каждой клетки heightmap; море — на высоте 0 м
+pub fn compute_climate_map(cfg: &WorldConfig, hm: &Heightmap) -> ClimateMap {
+    let (width, height) = (hm.width, hm.height);
+    let len = (width * height) as usize;
+    let mut map = ClimateMap {
+        width,
+        height,
+        temperature_c: Vec::with_capacity(len),
+        humidity: Vec::with_capacity(len),
+        precipitation_mm_per_year: Vec::with_capacity(len),
+    };
+    let sea_level_norm = cfg.sea_level;
+    let h1 = (height.saturating_sub(1).max(1)) as f64;
+    let planet = PlanetClimate::from_config(cfg);
+
+    for y in 0..height {
+        let lat = (y as f64 / h1) * 2.0 - 1.0;
+        for x in 0..width {
+            let h01 = hm.get(x, y) as f64;
+            let rel = ((h01 - sea_level_norm) / (1.0 - sea_level_norm)).clamp(0.0, 1.0);
+            let c = sample_climate_with(cfg, &planet, lat, rel * MAX_RELIEF_M);
+            map.temperature_c.push(c.temperature_c as f32);
+            map.humidity.push(c.humidity as f32);
+            map.precipitation_mm_per_year
+                .push(c.precipitation_mm_per_year as f32);
+        }
+    }
+    map
 }
 
 pub fn sample_climate(cfg: &WorldConfig, lat_norm: f64, elevation_m: f64) -> ClimateSample {
+    sample_climate_with(cfg, &PlanetClimate::from_config(cfg), lat_norm, elevation_m)
+}
+
+/// `sample_climate` с заранее посчитанным тепловым режимом планеты — для
+/// циклов по всей карте
+pub fn sample_climate_with(
+    cfg: &WorldConfig,
+    planet: &PlanetClimate,
+    lat_norm: f64,
+    elevation_m: f64,
+) -> ClimateSample {
     let atm = &cfg.environment.atmosphere;
     let clim = &cfg.environment.climate_model;
 
     // --- Температура ---
 
-    // Базовая температура из JSON (где-то в районе "среднего по планете")
-    let base_t = atm.base_temperature_c as f64;
+    // Базовая температура из JSON (где-то в районе "среднего по планете"),
+    // сдвинутая звездой, орбитой и атмосферой
+    let base_t = atm.base_temperature_c + planet.mean_shift_c;
     let lat_abs = lat_norm.abs(); // 0 – экватор, 1 – полюс
 
     // Широтный профиль:
-    // экватор теплее, полюса холоднее: ~ +8°C к base на экваторе, ~ -25°C на полюсе
-    let equator_boost = 8.0_f64;
-    let pole_drop = 25.0_f64;
+    // у опорной планеты экватор теплее, полюса холоднее: ~ +8°C к base на
+    // экваторе, ~ -25°C на полюсе; у других размах масштабируется
+    let equator_boost = 8.0_f64 * planet.latitude_contrast;
+    let pole_drop = 25.0_f64 * planet.latitude_contrast;
     // линейная мешанина: чем ближе к экватору, тем больше equator_boost, чем ближе к полюсу — тем больше pole_drop
     let t_lat = base_t + equator_boost * (1.0 - lat_abs) - pole_drop * lat_abs;
 
     // Градиент по высоте (из climateModel.temperatureLapseRateCPerKm)
-    let lapse_rate = clim.temperature_lapse_rate_c_per_km as f64; // °C/км
+    let lapse_rate = clim.temperature_lapse_rate_c_per_km; // °C/км
     let t_alt = -lapse_rate * (elevation_m / 1000.0);
 
     let mut temperature_c = t_lat + t_alt;
@@ -278,7 +441,7 @@ pub fn sample_climate(cfg: &WorldConfig, lat_norm: f64, elevation_m: f64) -> Cli
     // --- Влажность 0..1 ---
 
     // Стартуем от глобального среднего
-    let mut humidity = atm.humidity_global_mean as f64;
+    let mut humidity = atm.humidity_global_mean;
 
     match clim.model_type.as_str() {
         // Модель "полос": влажный экватор, сухие субтропики (~±30°),
@@ -313,7 +476,7 @@ pub fn sample_climate(cfg: &WorldConfig, lat_norm: f64, elevation_m: f64) -> Cli
     // --- Осадки (мм/год) ---
 
     // Базовый уровень осадков под масштаб JSON
-    let base_prec = 1000.0_f64 * clim.precipitation_scale as f64;
+    let base_prec = 1000.0_f64 * clim.precipitation_scale;
 
     // Влажность → осадки: при humidity=0→~0.3 base, при humidity=1→~1.0 base
     let mut precipitation_mm_per_year = base_prec * (0.3 + 0.7 * humidity);
@@ -332,6 +495,32 @@ pub fn sample_climate(cfg: &WorldConfig, lat_norm: f64, elevation_m: f64) -> Cli
     }
 }
 
+/// Преобладающий ветер на широте `lat_deg` (единичный вектор в координатах карты:
+/// +x — на восток, +y — к росту широты).
+pub fn prevailing_wind(cfg: &WorldConfig, lat_deg: f64) -> (f64, f64) {
+    let lat_abs = lat_deg.abs();
+    // направление "к экватору" по оси y
+    let to_equator = if lat_deg >= 0.0 { -1.0 } else { 1.0 };
+
+    let (wx, wy): (f64, f64) = match cfg.environment.climate_model.wind_global_pattern.as_str() {
+        // три ячейки циркуляции: пассаты, западные ветры, полярные восточные
+        "hadley_cells" => {
+            if lat_abs < 30.0 {
+                (-1.0, 0.4 * to_equator)
+            } else if lat_abs < 60.0 {
+                (1.0, -0.3 * to_equator)
+            } else {
+                (-1.0, 0.3 * to_equator)
+            }
+        }
+        // без модели циркуляции — просто западный перенос
+        _ => (1.0, 0.0),
+    };
+
+    let len = (wx * wx + wy * wy).sqrt().max(1e-9);
+    (wx / len, wy / len)
+}
+
 /// Выбор подходящего биома для одной точки
 fn choose_biome(biomes: &[BiomeConfig], sample: &BiomeSample, sea_level_m: f64) -> Option<usize> {
     // Море – без биома (рисуем просто воду).
@@ -0,0 +1,175 @@
+//! Мировое время: игровые секунды с начала мира и их перевод в сутки, годы
+//! и сезоны активной планеты (длины — как у `Astronomy`), в минуты для
+//! директора и в годы для экосистемы. Реальное время переводится в игровое
+//! со скоростью `simulation.time.timeScale`, ограниченной пределами из
+//! того же раздела. Год начинается с весеннего равноденствия; сезон — для
+//! северного полушария.
+
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::astronomy::Astronomy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+/// Где идёт симуляция — от этого зависит, насколько можно ускорить время
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulationContext {
+    /// С игроками: не быстрее `maxTimeScaleInHub`
+    Hub,
+    /// Без игроков: не быстрее `maxTimeScaleInBackgroundSim`
+    Background,
+}
+
+/// Сутки и год планеты и скорость времени из конфига
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calendar {
+    pub day_length_s: f64,
+    pub year_length_days: f64,
+    time_scale: f64,
+    allow_acceleration: bool,
+    max_scale_hub: f64,
+    max_scale_background: f64,
+}
+
+impl Calendar {
+    pub fn from_config(cfg: &WorldConfig) -> Self {
+        let astro = Astronomy::from_config(cfg);
+        let time = &cfg.simulation.time;
+        Calendar {
+            day_length_s: astro.day_length_s(),
+            year_length_days: astro.year_length_days(),
+            time_scale: (time.time_scale as f64).max(0.0),
+            allow_acceleration: time.allow_time_acceleration,
+            max_scale_hub: (time.max_time_scale_in_hub as f64).max(0.0),
+            max_scale_background: (time.max_time_scale_in_background_sim as f64).max(0.0),
+        }
+    }
+
+    /// Игровых секунд за реальную: `timeScale`, но без ускорения, если оно
+    /// запрещено, и не больше предела для `context`
+    pub fn time_scale(&self, context: SimulationContext) -> f64 {
+        let cap = match context {
+            _ if !self.allow_acceleration => 1.0,
+            SimulationContext::Hub => self.max_scale_hub,
+            SimulationContext::Background => self.max_scale_background,
+        };
+        self.time_scale.min(cap)
+    }
+
+    /// Игровые секунды за `real_s` реальных
+    pub fn world_seconds(&self, real_s: f64, context: SimulationContext) -> f64 {
+        real_s * self.time_scale(context)
+    }
+
+    pub fn year_length_s(&self) -> f64 {
+        self.year_length_days * self.day_length_s
+    }
+
+    /// Игровые секунды в сутках планеты
+    pub fn days(&self, seconds: f64) -> f64 {
+        seconds / self.day_length_s
+    }
+
+    /// Игровые секунды в годах планеты
+    pub fn years(&self, seconds: f64) -> f64 {
+        seconds / self.year_length_s()
+    }
+
+    pub fn seconds_from_days(&self, days: f64) -> f64 {
+        days * self.day_length_s
+    }
+
+    pub fn seconds_from_years(&self, years: f64) -> f64 {
+        years * self.year_length_s()
+    }
+
+    /// Минут в `days` сутках планеты — для сроков директора
+    pub fn minutes_in_days(&self, days: f64) -> u64 {
+        (self.seconds_from_days(days) / 60.0).round() as u64
+    }
+
+    /// Минут в году планеты — для шага экосистемы
+    pub fn minutes_per_year(&self) -> f64 {
+        self.year_length_s() / 60.0
+    }
+
+    /// Дата в момент `seconds` от начала мира
+    pub fn date(&self, seconds: f64) -> WorldDate {
+        let days = self.days(seconds).max(0.0);
+        let year_fraction = self.years(seconds).rem_euclid(1.0);
+        WorldDate {
+            year: self.years(seconds).max(0.0) as u64,
+            day: days as u64,
+            day_of_year: (year_fraction * self.year_length_days) as u64,
+            time_of_day: days.fract(),
+            year_fraction,
+            season: match (year_fraction * 4.0) as u32 {
+                0 => Season::Spring,
+                1 => Season::Summer,
+                2 => Season::Autumn,
+                _ => Season::Winter,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldDate {
+    /// Полных лет с начала мира
+    pub year: u64,
+    /// Полных суток с начала мира
+    pub day: u64,
+    /// Номер суток в году, с 0
+    pub day_of_year: u64,
+    /// Доля суток на долготе 0: 0 — полночь, 0.5 — полдень
+    pub time_of_day: f64,
+    /// Доля года: 0 — весеннее равноденствие
+    pub year_fraction: f64,
+    pub season: Season,
+}
+
+/// Часы мира; сохраняются между перезапусками
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorldClock {
+    /// Игровых секунд с начала мира
+    pub elapsed_s: f64,
+}
+
+impl WorldClock {
+    /// Часы на `hour` часу первых суток
+    pub fn at_hour(calendar: &Calendar, hour: f64) -> Self {
+        WorldClock {
+            elapsed_s: calendar.day_length_s * hour / 24.0,
+        }
+    }
+
+    /// Двигает часы на `real_s` реальных секунд; возвращает, на сколько
+    /// игровых секунд они ушли
+    pub fn advance(&mut self, calendar: &Calendar, real_s: f64, context: SimulationContext) -> f64 {
+        let dt = calendar.world_seconds(real_s, context);
+        self.elapsed_s += dt;
+        dt
+    }
+
+    pub fn date(&self, calendar: &Calendar) -> WorldDate {
+        calendar.date(self.elapsed_s)
+    }
+
+    /// Игровых лет с начала мира
+    pub fn years(&self, calendar: &Calendar) -> f64 {
+        calendar.years(self.elapsed_s)
+    }
+
+    /// Игровых минут с начала мира — время директора
+    pub fn minutes(&self) -> u64 {
+        (self.elapsed_s.max(0.0) / 60.0) as u64
+    }
+}
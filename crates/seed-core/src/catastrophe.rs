@@ -1,20 +1,84 @@
-use noise::{NoiseFn, Perlin};
-use seed_config::{CatastrophesConfig, WorldConfig};
+use crate::geo::MapGeo;
+use crate::seed_tree::SeedTree;
 use crate::terrain::Heightmap;
-use std::f64::consts::PI;
+use crate::volcano::{erupt, VolcanicDeposits, VolcanoSimulator};
+use noise::NoiseFn;
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Catastrophe {
     pub id: String,
     pub catastrophe_type: CatastropheType,
-    pub position: (f64, f64),  // lat, lon
+    pub position: (f64, f64), // lat, lon
     pub magnitude: f64,
     pub radius_km: f64,
     pub timestamp: f64,
     pub duration_hours: f64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl Catastrophe {
+    /// Событие `kind` силы `magnitude` в точке (lat, lon) — например, по
+    /// щелчку на карте. Радиус и длительность — как у `generate_catastrophes`
+    /// для той же магнитуды
+    pub fn at(
+        cfg: &WorldConfig,
+        kind: CatastropheType,
+        position: (f64, f64),
+        magnitude: f64,
+    ) -> Self {
+        let event_type = cfg
+            .catastrophes
+            .event_types
+            .iter()
+            .find(|e| e.id == kind.id());
+        // доля магнитуды в её диапазоне — на неё же ложится радиус
+        let lerp = |range: [f64; 2], t: f64| range[0] + t.clamp(0.0, 1.0) * (range[1] - range[0]);
+        let radius_km = match kind {
+            CatastropheType::Earthquake => {
+                let max_mag = event_type.and_then(|e| e.max_magnitude).unwrap_or(9.0);
+                match event_type.and_then(|e| e.affected_radius_km_range) {
+                    Some(range) => lerp(range, (magnitude - 5.0) / (max_mag - 5.0)),
+                    None => magnitude * 20.0,
+                }
+            }
+            CatastropheType::VolcanicEruption => 50.0 + magnitude * 10.0,
+            CatastropheType::MeteorImpact => {
+                match event_type.and_then(|e| e.crater_radius_km_range) {
+                    Some(range) => lerp(range, magnitude / 100.0),
+                    None => magnitude * 0.5,
+                }
+            }
+            _ => 10.0,
+        };
+        Catastrophe {
+            id: format!("{}_{:.4}_{:.4}", kind.id(), position.0, position.1),
+            catastrophe_type: kind,
+            position,
+            magnitude,
+            radius_km,
+            timestamp: 0.0,
+            duration_hours: match kind {
+                CatastropheType::Earthquake => 0.05,
+                CatastropheType::VolcanicEruption => 24.0 * magnitude,
+                CatastropheType::MeteorImpact => 0.01,
+                _ => 1.0,
+            },
+        }
+    }
+
+    /// Крупное событие, заметное в масштабе истории цивилизаций
+    pub fn is_major(&self) -> bool {
+        match self.catastrophe_type {
+            CatastropheType::Earthquake => self.magnitude >= 8.0,
+            CatastropheType::VolcanicEruption => self.magnitude >= 5.0,
+            CatastropheType::MeteorImpact | CatastropheType::Tsunami => true,
+            CatastropheType::Hurricane | CatastropheType::Tornado => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CatastropheType {
     Earthquake,
     VolcanicEruption,
@@ -24,6 +88,43 @@ pub enum CatastropheType {
     Hurricane,
 }
 
+impl CatastropheType {
+    /// id типа, как в `catastrophes.eventTypes` конфига
+    pub fn id(self) -> &'static str {
+        match self {
+            CatastropheType::Earthquake => "earthquake",
+            CatastropheType::VolcanicEruption => "volcanic_eruption",
+            CatastropheType::MeteorImpact => "meteor_impact",
+            CatastropheType::Tsunami => "tsunami",
+            CatastropheType::Tornado => "tornado",
+            CatastropheType::Hurricane => "hurricane",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        [
+            CatastropheType::Earthquake,
+            CatastropheType::VolcanicEruption,
+            CatastropheType::MeteorImpact,
+            CatastropheType::Tsunami,
+            CatastropheType::Tornado,
+            CatastropheType::Hurricane,
+        ]
+        .into_iter()
+        .find(|kind| kind.id() == id)
+    }
+
+    /// Меняет рельеф (`apply_catastrophe_to_heightmap`)
+    pub fn shapes_terrain(self) -> bool {
+        matches!(
+            self,
+            CatastropheType::Earthquake
+                | CatastropheType::VolcanicEruption
+                | CatastropheType::MeteorImpact
+        )
+    }
+}
+
 /// Генерирует список катастроф для симуляции мира
 pub fn generate_catastrophes(
     cfg: &WorldConfig,
@@ -31,27 +132,27 @@ pub fn generate_catastrophes(
     seed: u64,
 ) -> Vec<Catastrophe> {
     let mut catastrophes = Vec::new();
-    
+
     if !cfg.catastrophes.global_controls.enabled {
         return catastrophes;
     }
-    
-    let noise = Perlin::new(seed as u32);
-    
+
+    let noise = SeedTree::new(seed).derive("catastrophes").perlin();
+
     for event_type in &cfg.catastrophes.event_types {
         let frequency = event_type.base_frequency_per_year;
         let expected_count = (frequency * simulation_years) as usize;
-        
+
         for i in 0..expected_count {
             let time_offset = (i as f64 / expected_count as f64) * simulation_years;
-            
+
             // Случайная позиция с учётом шума
             let noise_x = noise.get([time_offset * 0.1, i as f64 * 0.5]);
             let noise_y = noise.get([time_offset * 0.2, i as f64 * 0.7 + 100.0]);
-            
-            let lat = noise_x * 180.0 - 90.0;  // -90 .. 90
+
+            let lat = noise_x * 180.0 - 90.0; // -90 .. 90
             let lon = noise_y * 360.0 - 180.0; // -180 .. 180
-            
+
             let magnitude_noise = noise.get([lat * 0.01, lon * 0.01]);
             let magnitude = match event_type.id.as_str() {
                 "earthquake" => {
@@ -66,7 +167,7 @@ pub fn generate_catastrophes(
                 }
                 _ => 1.0,
             };
-            
+
             let radius_km = match event_type.id.as_str() {
                 "earthquake" => {
                     if let Some(range) = &event_type.affected_radius_km_range {
@@ -85,14 +186,14 @@ pub fn generate_catastrophes(
                 }
                 _ => 10.0,
             };
-            
+
             let cat_type = match event_type.id.as_str() {
                 "earthquake" => CatastropheType::Earthquake,
                 "volcanic_eruption" => CatastropheType::VolcanicEruption,
                 "meteor_impact" => CatastropheType::MeteorImpact,
                 _ => continue,
             };
-            
+
             catastrophes.push(Catastrophe {
                 id: format!("{}_{}", event_type.id, i),
                 catastrophe_type: cat_type,
@@ -109,143 +210,164 @@ pub fn generate_catastrophes(
             });
         }
     }
-    
+
     catastrophes
 }
 
+/// Расписание катастроф мира, по возрастанию времени. Время события —
+/// годы часов мира; прошедшие до `now_years` считаются уже случившимися
+#[derive(Debug, Clone, Default)]
+pub struct CatastropheSchedule {
+    events: Vec<Catastrophe>,
+    next: usize,
+}
+
+impl CatastropheSchedule {
+    /// `generate_catastrophes` на `horizon_years` вперёд
+    pub fn new(cfg: &WorldConfig, horizon_years: f64, seed: u64, now_years: f64) -> Self {
+        let mut events = generate_catastrophes(cfg, horizon_years, seed);
+        events.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        let next = events.partition_point(|c| c.timestamp <= now_years);
+        CatastropheSchedule { events, next }
+    }
+
+    /// Ближайшее ещё не наступившее событие
+    pub fn peek(&self) -> Option<&Catastrophe> {
+        self.events.get(self.next)
+    }
+
+    /// Снимает ближайшее событие с расписания
+    pub fn pop(&mut self) -> Option<Catastrophe> {
+        let cat = self.events.get(self.next).cloned()?;
+        self.next += 1;
+        Some(cat)
+    }
+
+    /// Снимает все события до `until_years` включительно
+    pub fn due(&mut self, until_years: f64) -> Vec<Catastrophe> {
+        let end = self.next
+            + self
+                .upcoming()
+                .partition_point(|c| c.timestamp <= until_years);
+        let due = self.events[self.next..end].to_vec();
+        self.next = end;
+        due
+    }
+
+    /// Ещё не наступившие события
+    pub fn upcoming(&self) -> &[Catastrophe] {
+        &self.events[self.next..]
+    }
+}
+
 /// Применяет катастрофу к карте высот
-pub fn apply_catastrophe_to_heightmap(
-    hm: &mut Heightmap,
-    cat: &Catastrophe,
-    cfg: &WorldConfig,
-) {
-    let w = hm.width as usize;
-    let h = hm.height as usize;
-    
-    // Конвертируем lat/lon в координаты карты
-    let (lat, lon) = cat.position;
-    let norm_lat = (lat + 90.0) / 180.0;  // 0..1
-    let norm_lon = (lon + 180.0) / 360.0; // 0..1
-    
-    let center_x = (norm_lon * w as f64) as usize;
-    let center_y = (norm_lat * h as f64) as f64;
-    
-    // Определяем радиус влияния в пикселях
-    let world_scale = cfg.scale.region_size_km;
-    let pixel_per_km = w as f64 / world_scale;
-    let radius_pixels = (cat.radius_km * pixel_per_km) as usize;
-    
+pub fn apply_catastrophe_to_heightmap(hm: &mut Heightmap, cat: &Catastrophe, cfg: &WorldConfig) {
+    let geo = MapGeo::from_config(cfg, hm.width, hm.height);
+    let center = cat.position.into();
+
     match cat.catastrophe_type {
         CatastropheType::Earthquake => {
-            apply_earthquake(hm, center_x, center_y as usize, radius_pixels, cat.magnitude);
+            let cells = geo.cells_within(center, cat.radius_km);
+            apply_earthquake(hm, &cells, cat.radius_km, cat.magnitude);
         }
         CatastropheType::VolcanicEruption => {
-            apply_volcanic_eruption(hm, center_x, center_y as usize, radius_pixels, cat.magnitude);
+            // Разовое извержение того же вулкана, что и в VolcanoSimulator:
+            // постройка + лава вниз по склону + пепел по ветру
+            let (cx, cy) = geo.cell_at(center);
+            let mut deposits = VolcanicDeposits::new(hm.width, hm.height);
+            erupt(
+                cfg,
+                hm,
+                &mut deposits,
+                cx,
+                cy,
+                cat.magnitude,
+                cat_seed(cfg, cat),
+            );
         }
         CatastropheType::MeteorImpact => {
-            apply_meteor_impact(hm, center_x, center_y as usize, radius_pixels, cat.magnitude);
+            let cells = geo.cells_within(center, cat.radius_km);
+            apply_meteor_impact(hm, &cells, cat.radius_km, cat.magnitude);
         }
         _ => {}
     }
 }
 
-/// Землетрясение: случайные вертикальные смещения
-fn apply_earthquake(hm: &mut Heightmap, cx: usize, cy: usize, radius: usize, magnitude: f64) {
-    let w = hm.width as usize;
-    let h = hm.height as usize;
-    
-    let intensity = (magnitude - 5.0) / 4.0; // 0..1 для магнитуды 5..9
-    let max_displacement = intensity * 0.05; // максимум 5% от диапазона высот
-    
-    for dy in -(radius as isize)..=(radius as isize) {
-        for dx in -(radius as isize)..=(radius as isize) {
-            let x = cx as isize + dx;
-            let y = cy as isize + dy;
-            
-            if x < 0 || y < 0 || x >= w as isize || y >= h as isize {
-                continue;
-            }
-            
-            let dist = ((dx * dx + dy * dy) as f64).sqrt();
-            if dist > radius as f64 {
-                continue;
-            }
-            
-            let falloff = (1.0 - dist / radius as f64).max(0.0);
-            let displacement = (((x + y) as f64 * 0.5).sin() * max_displacement * falloff) as f32;
-            
-            let idx = y as usize * w + x as usize;
-            hm.values[idx] = (hm.values[idx] + displacement).clamp(0.0, 1.0);
-        }
+/// Как `apply_catastrophe_to_heightmap`, но извержение проходит через
+/// вулканы мира (`VolcanoSimulator::trigger`): извергается ближайший вулкан,
+/// отложения копятся в симуляторе
+pub fn apply_catastrophe_with_volcanoes(
+    hm: &mut Heightmap,
+    cat: &Catastrophe,
+    cfg: &WorldConfig,
+    volcanoes: &mut VolcanoSimulator,
+) {
+    if cat.catastrophe_type != CatastropheType::VolcanicEruption {
+        apply_catastrophe_to_heightmap(hm, cat, cfg);
+        return;
     }
+    let (x, y) = MapGeo::from_config(cfg, hm.width, hm.height).cell_at(cat.position.into());
+    volcanoes.trigger(hm, x, y, cat.magnitude, cat_seed(cfg, cat));
 }
 
-/// Извержение вулкана: конус пепла и лавы
-fn apply_volcanic_eruption(hm: &mut Heightmap, cx: usize, cy: usize, radius: usize, magnitude: f64) {
+/// Детерминированный seed для случайных деталей конкретного события
+fn cat_seed(cfg: &WorldConfig, cat: &Catastrophe) -> u64 {
+    SeedTree::from_config(cfg)
+        .derive("catastrophes")
+        .derive(&cat.id)
+        .derive_index(cat.position.0.to_bits())
+        .derive_index(cat.position.1.to_bits())
+        .seed()
+}
+
+/// Землетрясение: случайные вертикальные смещения в клетках охвата
+/// (`MapGeo::cells_within`, расстояние в км)
+fn apply_earthquake(
+    hm: &mut Heightmap,
+    cells: &[((u32, u32), f64)],
+    radius_km: f64,
+    magnitude: f64,
+) {
     let w = hm.width as usize;
-    let h = hm.height as usize;
-    
-    let cone_height = (magnitude / 10.0) * 0.15; // до 15% высоты карты
-    
-    for dy in -(radius as isize)..=(radius as isize) {
-        for dx in -(radius as isize)..=(radius as isize) {
-            let x = cx as isize + dx;
-            let y = cy as isize + dy;
-            
-            if x < 0 || y < 0 || x >= w as isize || y >= h as isize {
-                continue;
-            }
-            
-            let dist = ((dx * dx + dy * dy) as f64).sqrt();
-            if dist > radius as f64 {
-                continue;
-            }
-            
-            // Конический профиль
-            let height_add = cone_height * (1.0 - (dist / radius as f64).powf(1.5));
-            
-            let idx = y as usize * w + x as usize;
-            hm.values[idx] = (hm.values[idx] + height_add as f32).min(1.0);
-        }
+
+    let intensity = (magnitude - 5.0) / 4.0; // 0..1 для магнитуды 5..9
+    let max_displacement = intensity * 0.05; // максимум 5% от диапазона высот
+
+    for &((x, y), dist) in cells {
+        let falloff = (1.0 - dist / radius_km.max(1e-9)).max(0.0);
+        let displacement = (((x + y) as f64 * 0.5).sin() * max_displacement * falloff) as f32;
+
+        let idx = y as usize * w + x as usize;
+        hm.values[idx] = (hm.values[idx] + displacement).clamp(0.0, 1.0);
     }
 }
 
-/// Падение метеорита: круглый кратер
-fn apply_meteor_impact(hm: &mut Heightmap, cx: usize, cy: usize, radius: usize, magnitude: f64) {
+/// Падение метеорита: круглый по поверхности кратер
+fn apply_meteor_impact(
+    hm: &mut Heightmap,
+    cells: &[((u32, u32), f64)],
+    radius_km: f64,
+    magnitude: f64,
+) {
     let w = hm.width as usize;
-    let h = hm.height as usize;
-    
+
     let crater_depth = (magnitude / 100.0) * 0.2; // до 20% глубины
-    
-    for dy in -(radius as isize)..=(radius as isize) {
-        for dx in -(radius as isize)..=(radius as isize) {
-            let x = cx as isize + dx;
-            let y = cy as isize + dy;
-            
-            if x < 0 || y < 0 || x >= w as isize || y >= h as isize {
-                continue;
-            }
-            
-            let dist = ((dx * dx + dy * dy) as f64).sqrt();
-            if dist > radius as f64 {
-                continue;
-            }
-            
-            let norm_dist = dist / radius as f64;
-            
-            // Параболический профиль кратера
-            let depth_factor = if norm_dist < 0.7 {
-                // Внутри кратера - углубление
-                -(1.0 - (norm_dist / 0.7).powf(2.0))
-            } else {
-                // Вал вокруг кратера
-                ((norm_dist - 0.7) / 0.3) * 0.3
-            };
-            
-            let height_change = crater_depth * depth_factor;
-            
-            let idx = y as usize * w + x as usize;
-            hm.values[idx] = (hm.values[idx] + height_change as f32).clamp(0.0, 1.0);
-        }
+
+    for &((x, y), dist) in cells {
+        let norm_dist = (dist / radius_km.max(1e-9)).min(1.0);
+
+        // Параболический профиль кратера
+        let depth_factor = if norm_dist < 0.7 {
+            // Внутри кратера - углубление
+            -(1.0 - (norm_dist / 0.7).powf(2.0))
+        } else {
+            // Вал вокруг кратера
+            ((norm_dist - 0.7) / 0.3) * 0.3
+        };
+
+        let height_change = crater_depth * depth_factor;
+
+        let idx = y as usize * w + x as usize;
+        hm.values[idx] = (hm.values[idx] + height_change as f32).clamp(0.0, 1.0);
     }
 }
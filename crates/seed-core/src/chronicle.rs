@@ -0,0 +1,322 @@
+//! Летопись: история мира в виде структурированных записей (для JSON)
+//! и связного текста ("In year 312, the Ashen Compact sacked Harrowmere
+//! after the great flood.").
+
+use crate::catastrophe::CatastropheType;
+use crate::diplomacy::Treaty;
+use crate::geo::pixel_to_latlon;
+use crate::history::{History, HistoryEventKind};
+use seed_config::WorldConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Сколько лет катастрофа остаётся "недавней" для пояснения событий
+const CATASTROPHE_MEMORY_YEARS: u32 = 20;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chronicle {
+    pub world_name: String,
+    pub years_simulated: u32,
+    pub factions: Vec<ChronicleFaction>,
+    pub entries: Vec<ChronicleEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChronicleFaction {
+    pub id: String,
+    pub name: String,
+    pub capital: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChronicleEntry {
+    pub year: u32,
+    /// Тип события в snake_case: "city_founded", "war_declared", ...
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// id фракций-участников; первая — инициатор
+    pub actors: Vec<String>,
+    pub location: Option<ChronicleLocation>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChronicleLocation {
+    /// id города, если событие связано с городом
+    pub city_id: Option<String>,
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub lat_deg: f64,
+    pub lon_deg: f64,
+}
+
+impl Chronicle {
+    /// Летопись как текст: по предложению на строку
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "The Chronicle of {} ({} years)\n\n",
+            self.world_name, self.years_simulated
+        );
+        for e in &self.entries {
+            out.push_str(&e.text);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Собирает летопись из результатов `simulate_history`
+pub fn build_chronicle(cfg: &WorldConfig, history: &History) -> Chronicle {
+    let (w, h) = (history.territory.width, history.territory.height);
+
+    // Города, в том числе разрушенные: id -> (имя, x, y)
+    let mut places: HashMap<&str, (&str, u32, u32)> = HashMap::new();
+    for c in &history.cities {
+        places.insert(&c.id, (&c.name, c.x, c.y));
+    }
+    for r in &history.ruins {
+        places.insert(&r.id, (&r.name, r.x, r.y));
+    }
+    let faction_names: HashMap<&str, &str> = history
+        .factions
+        .iter()
+        .map(|f| (f.id.as_str(), f.name.as_str()))
+        .collect();
+
+    let faction = |id: &str| display_faction(faction_names.get(id).copied().unwrap_or(id));
+    let city_name = |id: &str| places.get(id).map(|p| p.0).unwrap_or(id).to_string();
+    let city_location = |id: &str| {
+        places.get(id).map(|&(name, x, y)| {
+            let (lat, lon) = pixel_to_latlon(x, y, w, h);
+            ChronicleLocation {
+                city_id: Some(id.to_string()),
+                name: name.to_string(),
+                x,
+                y,
+                lat_deg: lat,
+                lon_deg: lon,
+            }
+        })
+    };
+
+    // Последняя катастрофа, задевшая город: city_id -> (год, тип)
+    let mut recent_disaster: HashMap<&str, (u32, CatastropheType)> = HashMap::new();
+
+    let mut entries = Vec::with_capacity(history.events.len());
+    for e in &history.events {
+        let actor = faction(&e.faction_id);
+        let mut actors = vec![e.faction_id.clone()];
+
+        let (event_type, location, sentence) = match &e.kind {
+            HistoryEventKind::CityFounded { city_id } => {
+                // в год 0 основываются только столицы
+                let text = if e.year == 0 {
+                    format!("{actor} established its seat at {}", city_name(city_id))
+                } else {
+                    format!("{actor} founded {}", city_name(city_id))
+                };
+                ("city_founded", city_location(city_id), text)
+            }
+            HistoryEventKind::WarDeclared { enemy_id } => {
+                actors.push(enemy_id.clone());
+                (
+                    "war_declared",
+                    None,
+                    format!("{actor} declared war on {}", faction(enemy_id)),
+                )
+            }
+            HistoryEventKind::BattleFought {
+                city_id,
+                enemy_id,
+                won,
+            } => {
+                actors.push(enemy_id.clone());
+                let text = if *won {
+                    format!(
+                        "{actor} defeated {} at {}",
+                        faction(enemy_id),
+                        city_name(city_id)
+                    )
+                } else {
+                    format!(
+                        "{} repelled {actor} at {}",
+                        faction(enemy_id),
+                        city_name(city_id)
+                    )
+                };
+                ("battle_fought", city_location(city_id), text)
+            }
+            HistoryEventKind::CityCaptured {
+                city_id,
+                from_faction_id,
+            } => {
+                actors.push(from_faction_id.clone());
+                (
+                    "city_captured",
+                    city_location(city_id),
+                    format!(
+                        "{actor} captured {} from {}",
+                        city_name(city_id),
+                        faction(from_faction_id)
+                    ),
+                )
+            }
+            HistoryEventKind::CityRazed { city_id } => {
+                let mut text = format!("{actor} sacked {}", city_name(city_id));
+                if let Some(&(year, kind)) = recent_disaster.get(city_id.as_str()) {
+                    if e.year - year <= CATASTROPHE_MEMORY_YEARS {
+                        text.push_str(&format!(" after the {}", catastrophe_noun(kind)));
+                    }
+                }
+                ("city_razed", city_location(city_id), text)
+            }
+            HistoryEventKind::CityAbandoned { city_id } => {
+                let mut text = format!("{} was abandoned by its people", city_name(city_id));
+                if let Some(&(year, kind)) = recent_disaster.get(city_id.as_str()) {
+                    if e.year - year <= CATASTROPHE_MEMORY_YEARS {
+                        text.push_str(&format!(" after the {}", catastrophe_noun(kind)));
+                    }
+                }
+                ("city_abandoned", city_location(city_id), text)
+            }
+            HistoryEventKind::PeaceMade { enemy_id } => {
+                actors.push(enemy_id.clone());
+                (
+                    "peace_made",
+                    None,
+                    format!("{actor} made peace with {}", faction(enemy_id)),
+                )
+            }
+            HistoryEventKind::FactionDestroyed => (
+                "faction_destroyed",
+                None,
+                format!("{actor} fell, having lost its last city"),
+            ),
+            HistoryEventKind::TreatySigned { other_id, treaty } => {
+                actors.push(other_id.clone());
+                let text = match treaty {
+                    Treaty::TradePact => {
+                        format!("{actor} and {} signed a trade pact", faction(other_id))
+                    }
+                    Treaty::Alliance => {
+                        format!("{actor} and {} swore an alliance", faction(other_id))
+                    }
+                };
+                ("treaty_signed", None, text)
+            }
+            HistoryEventKind::TreatyBroken { other_id, treaty } => {
+                actors.push(other_id.clone());
+                let what = match treaty {
+                    Treaty::TradePact => "trade pact",
+                    Treaty::Alliance => "alliance",
+                };
+                (
+                    "treaty_broken",
+                    None,
+                    format!(
+                        "the {what} between {actor} and {} lapsed",
+                        faction(other_id)
+                    ),
+                )
+            }
+            HistoryEventKind::ReligionFounded {
+                religion_id,
+                city_id,
+            } => {
+                let faith = history
+                    .culture
+                    .religions
+                    .iter()
+                    .find(|r| &r.id == religion_id)
+                    .map(|r| r.name.as_str())
+                    .unwrap_or(religion_id);
+                (
+                    "religion_founded",
+                    city_location(city_id),
+                    format!("the {faith} faith arose in {}", city_name(city_id)),
+                )
+            }
+            HistoryEventKind::EraAdvanced { era } => (
+                "era_advanced",
+                None,
+                format!("{actor} entered the {}", era.display_name()),
+            ),
+            HistoryEventKind::CatastropheStruck {
+                catastrophe_type,
+                x,
+                y,
+                city_ids,
+            } => {
+                for id in city_ids {
+                    recent_disaster.insert(id, (e.year, *catastrophe_type));
+                }
+                let near = city_ids.first().map(|id| city_name(id));
+                let (lat, lon) = pixel_to_latlon(*x, *y, w, h);
+                let location = ChronicleLocation {
+                    city_id: city_ids.first().cloned(),
+                    name: near.clone().unwrap_or_default(),
+                    x: *x,
+                    y: *y,
+                    lat_deg: lat,
+                    lon_deg: lon,
+                };
+                let text = match near {
+                    Some(name) => format!(
+                        "the {} struck near {name}",
+                        catastrophe_noun(*catastrophe_type)
+                    ),
+                    None => format!("the {} struck", catastrophe_noun(*catastrophe_type)),
+                };
+                ("catastrophe", Some(location), text)
+            }
+        };
+
+        entries.push(ChronicleEntry {
+            year: e.year,
+            event_type: event_type.to_string(),
+            actors,
+            location,
+            text: format!("In year {}, {}.", e.year, sentence),
+        });
+    }
+
+    Chronicle {
+        world_name: cfg.meta.name.clone(),
+        years_simulated: history.years_simulated,
+        factions: history
+            .factions
+            .iter()
+            .map(|f| ChronicleFaction {
+                id: f.id.clone(),
+                name: f.name.clone(),
+                capital: f.capital_id.clone(),
+            })
+            .collect(),
+        entries,
+    }
+}
+
+/// "Northern Kingdom" -> "the Northern Kingdom"; однословные имена — без артикля
+fn display_faction(name: &str) -> String {
+    if name.contains(' ') && !name.starts_with("The ") {
+        format!("the {name}")
+    } else {
+        name.to_string()
+    }
+}
+
+fn catastrophe_noun(kind: CatastropheType) -> &'static str {
+    match kind {
+        CatastropheType::Earthquake => "great earthquake",
+        CatastropheType::VolcanicEruption => "eruption",
+        CatastropheType::MeteorImpact => "falling star",
+        CatastropheType::Tsunami => "great flood",
+        CatastropheType::Tornado => "whirlwind",
+        CatastropheType::Hurricane => "great storm",
+    }
+}
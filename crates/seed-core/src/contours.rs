@@ -0,0 +1,111 @@
+//! Горизонтали рельефа (marching squares по центрам клеток). Координаты — в
+//! клетках карты, как у гидрографии: (0, 0) — северо-западный угол, значения
+//! высоты — в центрах клеток (x + 0.5, y + 0.5).
+
+use crate::terrain::Heightmap;
+use crate::territory::{chain_segments, simplify_polyline, LatticeSegment};
+
+#[derive(Debug, Clone)]
+pub struct ContourLine {
+    /// Высота горизонтали над уровнем моря, м (0 — берег, ниже — изобаты)
+    pub elevation_m: f64,
+    /// Замкнутые горизонтали повторяют первую точку в конце
+    pub points: Vec<(f32, f32)>,
+}
+
+/// Горизонтали через каждые `interval_m` метров, включая изобаты и берег;
+/// `epsilon` — допуск упрощения в клетках
+pub fn extract_contours(
+    hm: &Heightmap,
+    sea_level: f64,
+    interval_m: f64,
+    epsilon: f32,
+) -> Vec<ContourLine> {
+    let (w, h) = (hm.width, hm.height);
+    if w < 2 || h < 2 || interval_m <= 0.0 {
+        return Vec::new();
+    }
+    let z: Vec<f64> = (0..h)
+        .flat_map(|y| (0..w).map(move |x| (x, y)))
+        .map(|(x, y)| hm.elevation_m(sea_level, x, y))
+        .collect();
+    let (lo, hi) = z
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+
+    let mut lines = Vec::new();
+    for k in (lo / interval_m).ceil() as i64..=(hi / interval_m).floor() as i64 {
+        let level = k as f64 * interval_m;
+        for chain in chain_segments(&level_segments(&z, w, h, level)) {
+            let points: Vec<(f32, f32)> = chain
+                .iter()
+                .map(|&key| edge_point(&z, w, key, level))
+                .collect();
+            lines.push(ContourLine {
+                elevation_m: level,
+                points: simplify_polyline(&points, epsilon),
+            });
+        }
+    }
+    lines
+}
+
+/// Отрезки горизонтали `level` по всем квадратам из четырёх соседних центров.
+/// Точки — рёбра решётки: (2x + 1, 2y) — ребро от (x, y) на восток,
+/// (2x, 2y + 1) — от (x, y) на юг.
+fn level_segments(z: &[f64], w: u32, h: u32, level: f64) -> Vec<LatticeSegment> {
+    let above = |x: u32, y: u32| z[(y * w + x) as usize] >= level;
+    let mut segs = Vec::new();
+    for y in 0..h - 1 {
+        for x in 0..w - 1 {
+            let (xi, yi) = (x as i32, y as i32);
+            let corners = [
+                above(x, y),
+                above(x + 1, y),
+                above(x + 1, y + 1),
+                above(x, y + 1),
+            ];
+            // рёбра по кругу: верх, право, низ, лево; ребро i — между углами i и i+1
+            let edges = [
+                (2 * xi + 1, 2 * yi),
+                (2 * xi + 2, 2 * yi + 1),
+                (2 * xi + 1, 2 * yi + 2),
+                (2 * xi, 2 * yi + 1),
+            ];
+            let crossed: Vec<usize> = (0..4)
+                .filter(|&i| corners[i] != corners[(i + 1) % 4])
+                .collect();
+            match crossed.len() {
+                2 => segs.push((edges[crossed[0]], edges[crossed[1]])),
+                4 => {
+                    // седло: решает среднее по четырём углам
+                    let i = (y * w + x) as usize;
+                    let w = w as usize;
+                    let center = (z[i] + z[i + 1] + z[i + w] + z[i + w + 1]) / 4.0;
+                    if (center >= level) == corners[0] {
+                        segs.push((edges[0], edges[1]));
+                        segs.push((edges[2], edges[3]));
+                    } else {
+                        segs.push((edges[3], edges[0]));
+                        segs.push((edges[1], edges[2]));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    segs
+}
+
+/// Точка пересечения горизонтали с ребром `key` (линейная интерполяция)
+fn edge_point(z: &[f64], w: u32, (ex, ey): (i32, i32), level: f64) -> (f32, f32) {
+    let (x, y) = ((ex / 2) as u32, (ey / 2) as u32);
+    let (nx, ny) = if ex % 2 == 1 { (x + 1, y) } else { (x, y + 1) };
+    let za = z[(y * w + x) as usize];
+    let zb = z[(ny * w + nx) as usize];
+    let t = ((level - za) / (zb - za)).clamp(0.0, 1.0) as f32;
+    (
+        x as f32 + 0.5 + (nx - x) as f32 * t,
+        y as f32 + 0.5 + (ny - y) as f32 * t,
+    )
+}
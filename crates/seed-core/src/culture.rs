@@ -0,0 +1,426 @@
+//! Культуры и религии. Культура рождается в столице каждой фракции,
+//! религии — в крупных городах; и те и другие распространяются вдоль
+//! торговых путей и между соседними городами одной страны. Преобладающая
+//! культура города определяет стиль имён его новых поселений и архитектуру.
+
+use crate::history::{History, HistoryEvent, HistoryEventKind};
+use crate::names::{NameGenerator, NameStyle};
+use crate::objects::ObjectType;
+use crate::population::catchment_owners;
+use crate::rng::SplitMix64;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Не больше стольких религий за всю историю
+const MAX_RELIGIONS: usize = 6;
+
+/// Вероятность рождения новой религии за шаг в городе-кандидате
+const RELIGION_BIRTH_CHANCE: f64 = 0.01;
+
+/// Минимальное население города, где может родиться религия
+const RELIGION_BIRTH_POPULATION: u64 = 30_000;
+
+/// Доля влияния, которую город за шаг перенимает у соседей
+const DIFFUSION_RATE: f32 = 0.15;
+
+/// Доля веры, ниже которой город считается без устойчивой религии
+const FAITH_THRESHOLD: f32 = 0.3;
+
+/// Архитектурный стиль построек
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Architecture {
+    Timber,
+    Stone,
+    Marble,
+    Adobe,
+    Longhouse,
+    Thatch,
+}
+
+impl Architecture {
+    /// Архитектура по стилю имён культуры
+    pub fn for_name_style(style_id: &str) -> Self {
+        match style_id {
+            "sylvan" => Architecture::Timber,
+            "latin" => Architecture::Marble,
+            "desert" => Architecture::Adobe,
+            "nordic" => Architecture::Longhouse,
+            "tribal" => Architecture::Thatch,
+            _ => Architecture::Stone,
+        }
+    }
+
+    /// Тип дома и вариант модели для этой архитектуры
+    pub fn house(self) -> (ObjectType, u8) {
+        match self {
+            Architecture::Timber => (ObjectType::HouseWood, 0),
+            Architecture::Longhouse => (ObjectType::HouseWood, 1),
+            Architecture::Thatch => (ObjectType::HouseWood, 2),
+            Architecture::Stone => (ObjectType::HouseStone, 0),
+            Architecture::Adobe => (ObjectType::HouseStone, 1),
+            Architecture::Marble => (ObjectType::HouseMedieval, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Culture {
+    pub id: String,
+    pub name: String,
+    /// Фракция, в столице которой родилась культура
+    pub origin_faction_id: String,
+    pub name_style: String,
+    pub architecture: Architecture,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Religion {
+    pub id: String,
+    pub name: String,
+    pub origin_city_id: String,
+    pub founded_year: u32,
+}
+
+/// Культурная принадлежность города на конкретный год
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CityAffiliation {
+    pub city_id: String,
+    pub x: u32,
+    pub y: u32,
+    /// Индекс в `CultureLayer::cultures`
+    pub culture: usize,
+    /// Индекс в `CultureLayer::religions`
+    pub religion: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CultureSnapshot {
+    pub year: u32,
+    pub cities: Vec<CityAffiliation>,
+}
+
+/// Культурный слой истории
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CultureLayer {
+    pub width: u32,
+    pub height: u32,
+    pub cultures: Vec<Culture>,
+    pub religions: Vec<Religion>,
+    /// Срезы принадлежности городов по годам
+    pub snapshots: Vec<CultureSnapshot>,
+    /// Радиус влияния города на карте принадлежности, клеток
+    pub reach: u32,
+    /// city_id -> доли культур
+    influence: BTreeMap<String, Vec<f32>>,
+    /// city_id -> доли религий (сумма ≤ 1, остаток — местные культы)
+    faith: BTreeMap<String, Vec<f32>>,
+    /// Нужны только во время симуляции — в сохранение не попадают
+    #[serde(skip)]
+    namers: Vec<NameGenerator>,
+}
+
+impl CultureLayer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            cultures: Vec::new(),
+            religions: Vec::new(),
+            snapshots: Vec::new(),
+            reach: (width.max(height) / 12).max(3),
+            influence: BTreeMap::new(),
+            faith: BTreeMap::new(),
+            namers: Vec::new(),
+        }
+    }
+
+    /// Преобладающая культура города
+    pub fn culture_of(&self, city_id: &str) -> Option<usize> {
+        argmax(self.influence.get(city_id)?)
+    }
+
+    /// Религия города, если вера в ней достаточно крепка
+    pub fn religion_of(&self, city_id: &str) -> Option<usize> {
+        let faith = self.faith.get(city_id)?;
+        let best = argmax(faith)?;
+        (faith[best] >= FAITH_THRESHOLD).then_some(best)
+    }
+
+    /// Последний срез не позже `year`
+    pub fn affiliation_at(&self, year: u32) -> Option<&CultureSnapshot> {
+        let i = self.snapshots.partition_point(|s| s.year <= year);
+        i.checked_sub(1).map(|i| &self.snapshots[i])
+    }
+
+    /// Карта культурной принадлежности на год `year`: индекс культуры
+    /// ближайшего города в радиусе `reach`
+    pub fn culture_map_at(&self, year: u32) -> Option<Vec<Option<u16>>> {
+        self.region_map(year, |c| Some(c.culture))
+    }
+
+    /// Карта религий на год `year`
+    pub fn religion_map_at(&self, year: u32) -> Option<Vec<Option<u16>>> {
+        self.region_map(year, |c| c.religion)
+    }
+
+    /// Архитектура в клетке (x, y) на год `year`
+    pub fn architecture_at(&self, year: u32, x: u32, y: u32) -> Option<Architecture> {
+        let snap = self.affiliation_at(year)?;
+        let reach = self.reach as f64;
+        snap.cities
+            .iter()
+            .map(|c| ((c.x as f64 - x as f64).hypot(c.y as f64 - y as f64), c))
+            .filter(|(d, _)| *d <= reach)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, c)| self.cultures[c.culture].architecture)
+    }
+
+    fn region_map(
+        &self,
+        year: u32,
+        value: impl Fn(&CityAffiliation) -> Option<usize>,
+    ) -> Option<Vec<Option<u16>>> {
+        let snap = self.affiliation_at(year)?;
+        let positions: Vec<(u32, u32)> = snap.cities.iter().map(|c| (c.x, c.y)).collect();
+        let owner = catchment_owners(self.width, self.height, &positions, self.reach);
+        Some(
+            owner
+                .into_iter()
+                .map(|o| o.and_then(|ci| value(&snap.cities[ci])).map(|v| v as u16))
+                .collect(),
+        )
+    }
+
+    /// Новая культура фракции; возвращает её индекс
+    pub(crate) fn found_culture(
+        &mut self,
+        faction_id: &str,
+        style: &'static NameStyle,
+        seed: u64,
+    ) -> usize {
+        let mut namer = NameGenerator::new(style, seed);
+        let idx = self.cultures.len();
+        self.cultures.push(Culture {
+            id: format!("culture_{idx}"),
+            name: namer.next_name(),
+            origin_faction_id: faction_id.to_string(),
+            name_style: style.id.to_string(),
+            architecture: Architecture::for_name_style(style.id),
+        });
+        self.namers.push(namer);
+        for v in self.influence.values_mut() {
+            v.push(0.0);
+        }
+        idx
+    }
+
+    /// Следующее имя в стиле культуры
+    pub(crate) fn next_name(&mut self, culture: usize) -> String {
+        self.namers[culture].next_name()
+    }
+
+    /// Город целиком принадлежит культуре `culture`
+    pub(crate) fn settle(&mut self, city_id: &str, culture: usize) {
+        let mut v = vec![0.0; self.cultures.len()];
+        v[culture] = 1.0;
+        self.influence.insert(city_id.to_string(), v);
+        self.faith
+            .insert(city_id.to_string(), vec![0.0; self.religions.len()]);
+    }
+
+    /// Новый город перенимает культуру и веру города-основателя
+    pub(crate) fn inherit(&mut self, parent_id: &str, city_id: &str) {
+        if let Some(v) = self.influence.get(parent_id).cloned() {
+            self.influence.insert(city_id.to_string(), v);
+        }
+        if let Some(v) = self.faith.get(parent_id).cloned() {
+            self.faith.insert(city_id.to_string(), v);
+        }
+    }
+
+    /// Разрушенный город выпадает из слоя
+    pub(crate) fn forget(&mut self, city_id: &str) {
+        self.influence.remove(city_id);
+        self.faith.remove(city_id);
+    }
+}
+
+fn argmax(v: &[f32]) -> Option<usize> {
+    v.iter()
+        .enumerate()
+        .filter(|(_, x)| **x > 0.0)
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(i, _)| i)
+}
+
+/// Один шаг: рождение религий, диффузия культур и вер по торговым путям и
+/// между ближайшими городами одной фракции, давление государственной культуры.
+pub(crate) fn culture_step(history: &mut History, year: u32, rng: &mut SplitMix64) {
+    found_religions(history, year, rng);
+
+    let cities = &history.cities;
+    let layer = &history.culture;
+    let n = cities.len();
+
+    // Связи между городами: торговые пути (по объёму) и ближайший сосед своей фракции
+    let mut links: Vec<Vec<(usize, f32)>> = vec![Vec::new(); n];
+    let max_volume = history
+        .trade
+        .routes
+        .iter()
+        .map(|r| r.volume)
+        .fold(0.0, f64::max);
+    for r in &history.trade.routes {
+        if r.from_city < n && r.to_city < n && max_volume > 0.0 {
+            let w = (r.volume / max_volume) as f32;
+            links[r.from_city].push((r.to_city, w));
+            links[r.to_city].push((r.from_city, w));
+        }
+    }
+    for (i, a) in cities.iter().enumerate() {
+        let nearest = cities
+            .iter()
+            .enumerate()
+            .filter(|(j, b)| *j != i && b.faction_id == a.faction_id)
+            .min_by_key(|(_, b)| {
+                let dx = a.x as i64 - b.x as i64;
+                let dy = a.y as i64 - b.y as i64;
+                dx * dx + dy * dy
+            });
+        if let Some((j, _)) = nearest {
+            links[i].push((j, 0.5));
+        }
+    }
+
+    // Государственная культура фракции — культура её столицы
+    let state_culture: BTreeMap<&str, usize> = history
+        .factions
+        .iter()
+        .filter_map(|f| {
+            let capital = f.capital_id.as_deref()?;
+            Some((f.id.as_str(), layer.culture_of(capital)?))
+        })
+        .collect();
+
+    let mut next_influence = BTreeMap::new();
+    let mut next_faith = BTreeMap::new();
+    for (i, city) in cities.iter().enumerate() {
+        let (Some(own_inf), Some(own_faith)) =
+            (layer.influence.get(&city.id), layer.faith.get(&city.id))
+        else {
+            continue;
+        };
+
+        let mut inf = diffuse(own_inf, &links[i], |j| layer.influence.get(&cities[j].id));
+        if let Some(&sc) = state_culture.get(city.faction_id.as_str()) {
+            inf[sc] += 0.02;
+        }
+        let total: f32 = inf.iter().sum();
+        if total > 0.0 {
+            inf.iter_mut().for_each(|v| *v /= total);
+        }
+
+        let mut faith = diffuse(own_faith, &links[i], |j| layer.faith.get(&cities[j].id));
+        let total: f32 = faith.iter().sum();
+        if total > 1.0 {
+            faith.iter_mut().for_each(|v| *v /= total);
+        }
+
+        next_influence.insert(city.id.clone(), inf);
+        next_faith.insert(city.id.clone(), faith);
+    }
+
+    history.culture.influence = next_influence;
+    history.culture.faith = next_faith;
+
+    let snapshot = CultureSnapshot {
+        year,
+        cities: history
+            .cities
+            .iter()
+            .filter_map(|c| {
+                Some(CityAffiliation {
+                    city_id: c.id.clone(),
+                    x: c.x,
+                    y: c.y,
+                    culture: history.culture.culture_of(&c.id)?,
+                    religion: history.culture.religion_of(&c.id),
+                })
+            })
+            .collect(),
+    };
+    history.culture.snapshots.push(snapshot);
+}
+
+/// Смешивает долю соседей в собственный вектор влияния
+fn diffuse<'a>(
+    own: &[f32],
+    links: &[(usize, f32)],
+    neighbour: impl Fn(usize) -> Option<&'a Vec<f32>>,
+) -> Vec<f32> {
+    let mut mix = vec![0.0f32; own.len()];
+    let mut weight = 0.0f32;
+    for &(j, w) in links {
+        if let Some(v) = neighbour(j) {
+            for (m, x) in mix.iter_mut().zip(v) {
+                *m += x * w;
+            }
+            weight += w;
+        }
+    }
+    if weight <= 0.0 {
+        return own.to_vec();
+    }
+    own.iter()
+        .zip(&mix)
+        .map(|(o, m)| o * (1.0 - DIFFUSION_RATE) + m / weight * DIFFUSION_RATE)
+        .collect()
+}
+
+/// В крупных городах без устойчивой веры изредка рождаются новые религии
+fn found_religions(history: &mut History, year: u32, rng: &mut SplitMix64) {
+    if history.culture.religions.len() >= MAX_RELIGIONS {
+        return;
+    }
+    let candidates: Vec<usize> = history
+        .cities
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            c.population >= RELIGION_BIRTH_POPULATION
+                && history.culture.religion_of(&c.id).is_none()
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    for ci in candidates {
+        if history.culture.religions.len() >= MAX_RELIGIONS || !rng.chance(RELIGION_BIRTH_CHANCE) {
+            continue;
+        }
+        let city = &history.cities[ci];
+        let culture = history.culture.culture_of(&city.id).unwrap_or(0);
+        let layer = &mut history.culture;
+        let name = layer.next_name(culture);
+        let idx = layer.religions.len();
+        layer.religions.push(Religion {
+            id: format!("religion_{idx}"),
+            name,
+            origin_city_id: city.id.clone(),
+            founded_year: year,
+        });
+        for v in layer.faith.values_mut() {
+            v.push(0.0);
+        }
+        if let Some(f) = layer.faith.get_mut(&city.id) {
+            f[idx] = 1.0;
+        }
+        history.events.push(HistoryEvent {
+            year,
+            faction_id: city.faction_id.clone(),
+            kind: HistoryEventKind::ReligionFounded {
+                religion_id: format!("religion_{idx}"),
+                city_id: city.id.clone(),
+            },
+        });
+    }
+}
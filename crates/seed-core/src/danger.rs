@@ -0,0 +1,246 @@
+//! Карты опасности и напряжённости для директора и оверлеев клиента.
+//! Опасность региона складывается из плотности хищников, риска катастроф
+//! (по истории), враждебности соседствующих фракций и свежих событий;
+//! напряжённость — только из враждебности и событий.
+
+use crate::diplomacy::{RelationMatrix, Stance};
+use crate::ecosystem::Ecosystem;
+use crate::events::WorldEvent;
+use crate::history::{History, HistoryEventKind};
+use serde::Serialize;
+
+/// Сторона региона карты опасности, клеток
+pub const DANGER_REGION_CELLS: u32 = 8;
+/// На сколько регионов вокруг себя катастрофа истории поднимает риск
+const CATASTROPHE_SPREAD: i32 = 3;
+
+/// Вклад слоёв в опасность: хищники, катастрофы, враждебность, события
+const DANGER_WEIGHTS: [f32; 4] = [0.25, 0.25, 0.3, 0.2];
+/// Вклад враждебности и событий в напряжённость
+const TENSION_WEIGHTS: [f32; 2] = [0.6, 0.4];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DangerMap {
+    pub width: u32,
+    pub height: u32,
+    pub region_cells: u32,
+    pub regions_x: u32,
+    pub regions_y: u32,
+    /// Слои по регионам, каждый 0..1
+    pub predators: Vec<f32>,
+    pub catastrophe_risk: Vec<f32>,
+    pub hostility: Vec<f32>,
+    pub recent_events: Vec<f32>,
+    /// Итоговая опасность, 0..1
+    pub danger: Vec<f32>,
+    /// Напряжённость, 0..1
+    pub tension: Vec<f32>,
+}
+
+impl DangerMap {
+    pub fn region_at(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let rc = self.region_cells;
+        Some(((y / rc) * self.regions_x + x / rc) as usize)
+    }
+
+    pub fn danger_at(&self, x: u32, y: u32) -> f32 {
+        self.region_at(x, y).map_or(0.0, |r| self.danger[r])
+    }
+
+    pub fn tension_at(&self, x: u32, y: u32) -> f32 {
+        self.region_at(x, y).map_or(0.0, |r| self.tension[r])
+    }
+
+    /// Опасность по клеткам карты (для оверлея)
+    pub fn danger_raster(&self) -> Vec<f32> {
+        self.expand(&self.danger)
+    }
+
+    /// Напряжённость по клеткам карты (для оверлея)
+    pub fn tension_raster(&self) -> Vec<f32> {
+        self.expand(&self.tension)
+    }
+
+    fn expand(&self, layer: &[f32]) -> Vec<f32> {
+        (0..self.width * self.height)
+            .map(|i| {
+                let r = self.region_at(i % self.width, i / self.width).unwrap_or(0);
+                layer[r]
+            })
+            .collect()
+    }
+}
+
+/// Считает карту опасности по итогам истории, текущим отношениям фракций,
+/// экосистеме (если она есть) и свежим событиям шины.
+pub fn compute_danger_map(
+    history: &History,
+    relations: &RelationMatrix,
+    ecosystem: Option<&Ecosystem>,
+    events: &[WorldEvent],
+) -> DangerMap {
+    let (w, h) = (history.territory.width, history.territory.height);
+    let rc = DANGER_REGION_CELLS;
+    let (rx, ry) = (w.div_ceil(rc).max(1), h.div_ceil(rc).max(1));
+    let regions = (rx * ry) as usize;
+    let region_of = |x: u32, y: u32| ((y / rc) * rx + x / rc) as usize;
+
+    // Хищники: средняя плотность охотников в регионе
+    let mut predators = vec![0.0f32; regions];
+    if let Some(eco) = ecosystem.filter(|e| e.width == w && e.height == h) {
+        let mut counts = vec![0u32; regions];
+        for (si, s) in eco.species.iter().enumerate() {
+            let hunts = s.role.hunts() as f32;
+            if hunts == 0.0 {
+                continue;
+            }
+            for (i, d) in eco.population_raster(si).into_iter().enumerate() {
+                let r = region_of(i as u32 % w, i as u32 / w);
+                predators[r] += hunts * d;
+                counts[r] += 1;
+            }
+        }
+        for (p, &n) in predators.iter_mut().zip(&counts) {
+            *p /= n.max(1) as f32;
+        }
+    }
+    normalize(&mut predators);
+
+    // Риск катастроф: где били раньше, ударит и снова
+    let mut catastrophe_risk = vec![0.0f32; regions];
+    for e in &history.events {
+        let HistoryEventKind::CatastropheStruck { x, y, .. } = e.kind else {
+            continue;
+        };
+        let (cx, cy) = ((x / rc) as i32, (y / rc) as i32);
+        for dy in -CATASTROPHE_SPREAD..=CATASTROPHE_SPREAD {
+            for dx in -CATASTROPHE_SPREAD..=CATASTROPHE_SPREAD {
+                let (nx, ny) = (cx + dx, cy + dy);
+                if nx < 0 || ny < 0 || nx >= rx as i32 || ny >= ry as i32 {
+                    continue;
+                }
+                let d = ((dx * dx + dy * dy) as f32).sqrt();
+                let weight = 1.0 - d / (CATASTROPHE_SPREAD + 1) as f32;
+                if weight > 0.0 {
+                    catastrophe_risk[(ny as u32 * rx + nx as u32) as usize] += weight;
+                }
+            }
+        }
+    }
+    normalize(&mut catastrophe_risk);
+
+    // Враждебность: худшие отношения среди фракций в регионе и по соседству
+    let mut owners: Vec<Vec<u16>> = vec![Vec::new(); regions];
+    for (i, o) in history.territory.owner.iter().enumerate() {
+        if let Some(o) = *o {
+            let r = region_of(i as u32 % w, i as u32 / w);
+            if !owners[r].contains(&o) {
+                owners[r].push(o);
+            }
+        }
+    }
+    let pair_hostility = |a: u16, b: u16| {
+        let (Some(fa), Some(fb)) = (
+            history.factions.get(a as usize),
+            history.factions.get(b as usize),
+        ) else {
+            return 0.0;
+        };
+        match relations.get_by_id(&fa.id, &fb.id) {
+            Some(r) if r.stance == Stance::AtWar => 1.0,
+            Some(r) => (-r.score / 100.0).clamp(0.0, 1.0),
+            None => 0.0,
+        }
+    };
+    let mut hostility = vec![0.0f32; regions];
+    for y in 0..ry {
+        for x in 0..rx {
+            let mut near: Vec<u16> = Vec::new();
+            for ny in y.saturating_sub(1)..(y + 2).min(ry) {
+                for nx in x.saturating_sub(1)..(x + 2).min(rx) {
+                    for &o in &owners[(ny * rx + nx) as usize] {
+                        if !near.contains(&o) {
+                            near.push(o);
+                        }
+                    }
+                }
+            }
+            let mut worst = 0.0f32;
+            for (i, &a) in near.iter().enumerate() {
+                for &b in &near[i + 1..] {
+                    worst = worst.max(pair_hostility(a, b));
+                }
+            }
+            hostility[(y * rx + x) as usize] = worst;
+        }
+    }
+
+    // Свежие события: сила спадает от центра к краю охвата
+    let mut recent_events = vec![0.0f32; regions];
+    for y in 0..ry {
+        for x in 0..rx {
+            let cx = (x * rc) as f32 + rc as f32 / 2.0;
+            let cy = (y * rc) as f32 + rc as f32 / 2.0;
+            let strongest = events
+                .iter()
+                .map(|e| {
+                    let d = (cx - e.x as f32).hypot(cy - e.y as f32);
+                    let reach = e.radius + rc as f32 / 2.0;
+                    e.intensity * (1.0 - d / reach).max(0.0)
+                })
+                .fold(0.0f32, f32::max);
+            recent_events[(y * rx + x) as usize] = strongest.min(1.0);
+        }
+    }
+
+    let danger = (0..regions)
+        .map(|r| {
+            let layers = [
+                predators[r],
+                catastrophe_risk[r],
+                hostility[r],
+                recent_events[r],
+            ];
+            layers
+                .iter()
+                .zip(DANGER_WEIGHTS)
+                .map(|(v, k)| v * k)
+                .sum::<f32>()
+                .clamp(0.0, 1.0)
+        })
+        .collect();
+    let tension = (0..regions)
+        .map(|r| {
+            (hostility[r] * TENSION_WEIGHTS[0] + recent_events[r] * TENSION_WEIGHTS[1])
+                .clamp(0.0, 1.0)
+        })
+        .collect();
+
+    DangerMap {
+        width: w,
+        height: h,
+        region_cells: rc,
+        regions_x: rx,
+        regions_y: ry,
+        predators,
+        catastrophe_risk,
+        hostility,
+        recent_events,
+        danger,
+        tension,
+    }
+}
+
+/// Делит слой на его максимум
+fn normalize(layer: &mut [f32]) {
+    let max = layer.iter().copied().fold(0.0f32, f32::max);
+    if max > 0.0 {
+        for v in layer.iter_mut() {
+            *v /= max;
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! Следы и вмятины на поверхности (`interaction.footprints` и
+//! `interaction.surfaceDeformation` конфига) — данные, общие для сервера и
+//! клиентов. Координаты — метры: x — на восток, z — на юг от
+//! северо-западного угла карты, глубина — вниз от рельефа.
+//!
+//! Слой делится на чанки по `chunk_size_m`. У чанка — события для
+//! синхронизации (вмятины `Stamp` и следы-декали `Decal`, как их
+//! рассылает сервер) и решётка глубин с шагом
+//! `deformationResolutionMeters`: узлы заводятся плитками по мере того, как
+//! их задевают, так что нетронутая земля места не занимает. Вмятина и след
+//! продавливают решётку параболоидом, глубина в узле не больше
+//! `maxDeformationDepthMeters`.
+//!
+//! Глубина следа зависит от твёрдости материала под ним (на скале почти
+//! ноль), время жизни — `fadeTimeSeconds`, умноженное на
+//! `footprintPersistence` материала. Выцветшие и вытесненные следы
+//! уходят из решётки, вмятины остаются.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use seed_config::{InteractionConfig, MaterialConfig, WorldConfig};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub type ChunkKey = (u32, u32);
+
+/// Вид следа ноги; остальные виды — из `trackTypes`
+pub const FOOTPRINT: &str = "footprint";
+pub const MAX_STAMP_RADIUS_M: f32 = 3.0;
+pub const MAX_STAMPS_PER_CHUNK: usize = 4096;
+/// Глубина следа ноги на самой мягкой поверхности, м
+const FOOTPRINT_DEPTH_M: f32 = 0.08;
+const FOOTPRINT_RADIUS_M: f32 = 0.15;
+/// Колея и волок шире следа ноги
+const TRACK_RADIUS_M: f32 = 0.3;
+/// Твёрдость поверхности, материала которой нет в конфиге, — как у суглинка
+const DEFAULT_HARDNESS: f32 = 0.3;
+const MIN_RESOLUTION_M: f32 = 0.01;
+/// Сторона плитки решётки, узлы
+const TILE: i64 = 64;
+
+/// Вмятина: глубина в центре, к краю спадает до нуля
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stamp {
+    pub x: f32,
+    pub z: f32,
+    pub radius: f32,
+    pub depth: f32,
+}
+
+impl Stamp {
+    pub fn depth_at(&self, x: f32, z: f32) -> f32 {
+        dent_depth(self.x, self.z, self.radius, self.depth, x, z)
+    }
+}
+
+/// След-декаль: `footprint` или вид из `trackTypes`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decal {
+    pub id: u64,
+    pub kind: String,
+    pub x: f32,
+    pub z: f32,
+    pub heading: f32,
+    pub created_ms: i64,
+    /// Глубина в центре, м
+    #[serde(default)]
+    pub depth: f32,
+    /// Когда след выцветет; `None` — не выцветает
+    #[serde(default)]
+    pub expires_ms: Option<i64>,
+}
+
+impl Decal {
+    pub fn radius(&self) -> f32 {
+        if self.kind == FOOTPRINT {
+            FOOTPRINT_RADIUS_M
+        } else {
+            TRACK_RADIUS_M
+        }
+    }
+
+    /// Насколько след ещё виден в момент `now_ms`: 1 — свежий, 0 — выцвел
+    pub fn opacity(&self, now_ms: i64) -> f32 {
+        match self.expires_ms {
+            Some(expires) => {
+                let lifetime = (expires - self.created_ms).max(1);
+                ((expires - now_ms) as f64 / lifetime as f64).clamp(0.0, 1.0) as f32
+            }
+            None => 1.0,
+        }
+    }
+}
+
+/// Слой чанка целиком — для клиента, который его загрузил
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeformChunkState {
+    pub stamps: Vec<Stamp>,
+    pub decals: Vec<Decal>,
+}
+
+/// Изменения чанка с прошлой рассылки
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeformDelta {
+    pub stamps: Vec<Stamp>,
+    pub decals: Vec<Decal>,
+    pub removed_decals: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum DeformError {
+    #[error("this kind of trace is disabled in the config")]
+    UnsupportedKind,
+    #[error("the surface does not keep this kind of trace")]
+    UnsupportedSurface,
+    #[error("invalid position or size")]
+    InvalidEvent,
+    #[error("too many stamps in the chunk")]
+    ChunkFull,
+    #[error("the surface is already pressed to the max depth")]
+    MaxDepthReached,
+}
+
+impl DeformError {
+    /// Код ошибки для API: `unsupported_kind`, `unsupported_surface`,
+    /// `invalid_event`, `chunk_full`, `max_depth_reached`
+    pub fn code(self) -> &'static str {
+        match self {
+            DeformError::UnsupportedKind => "unsupported_kind",
+            DeformError::UnsupportedSurface => "unsupported_surface",
+            DeformError::InvalidEvent => "invalid_event",
+            DeformError::ChunkFull => "chunk_full",
+            DeformError::MaxDepthReached => "max_depth_reached",
+        }
+    }
+}
+
+/// Глубины узлов чанка плитками `TILE`×`TILE`; узел (i, j) — точка
+/// (i·шаг, j·шаг)
+#[derive(Debug, Clone, Default)]
+pub struct DeformationGrid {
+    tiles: HashMap<(i64, i64), Vec<f32>>,
+}
+
+impl DeformationGrid {
+    pub fn node(&self, i: i64, j: i64) -> f32 {
+        let (tile, k) = tile_of(i, j);
+        self.tiles.get(&tile).map_or(0.0, |t| t[k])
+    }
+
+    fn node_mut(&mut self, i: i64, j: i64) -> &mut f32 {
+        let (tile, k) = tile_of(i, j);
+        &mut self
+            .tiles
+            .entry(tile)
+            .or_insert_with(|| vec![0.0; (TILE * TILE) as usize])[k]
+    }
+
+    /// Плиток с узлами
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct DeformChunk {
+    stamps: Vec<Stamp>,
+    // От старых к новым
+    decals: VecDeque<Decal>,
+    grid: DeformationGrid,
+}
+
+impl DeformChunk {
+    fn is_empty(&self) -> bool {
+        self.stamps.is_empty() && self.decals.is_empty() && self.grid.is_empty()
+    }
+
+    fn dents(&self) -> impl Iterator<Item = Dent> + '_ {
+        self.stamps
+            .iter()
+            .map(Dent::from_stamp)
+            .chain(self.decals.iter().map(Dent::from_decal))
+    }
+}
+
+/// Следы и вмятины мира по чанкам
+#[derive(Debug, Clone)]
+pub struct DeformationLayer {
+    interaction: InteractionConfig,
+    resolution_m: f32,
+    chunk_size_m: f64,
+    chunks: HashMap<ChunkKey, DeformChunk>,
+    // Изменения до ближайшей рассылки
+    pending: HashMap<ChunkKey, DeformDelta>,
+    next_decal: u64,
+}
+
+impl DeformationLayer {
+    /// Пустой слой с чанками `chunk_size_m`×`chunk_size_m` метров
+    pub fn new(cfg: &WorldConfig, chunk_size_m: f64) -> Self {
+        let interaction = cfg.interaction.clone();
+        DeformationLayer {
+            resolution_m: interaction
+                .footprints
+                .deformation_resolution_meters
+                .max(MIN_RESOLUTION_M),
+            interaction,
+            chunk_size_m: chunk_size_m.max(1.0),
+            chunks: HashMap::new(),
+            pending: HashMap::new(),
+            next_decal: 0,
+        }
+    }
+
+    /// Шаг решётки, м
+    pub fn resolution_m(&self) -> f32 {
+        self.resolution_m
+    }
+
+    /// Чанк под точкой; `None` — точка левее или севернее карты
+    pub fn chunk_of(&self, x: f32, z: f32) -> Option<ChunkKey> {
+        if !(x.is_finite() && z.is_finite() && x >= 0.0 && z >= 0.0) {
+            return None;
+        }
+        Some((
+            (x as f64 / self.chunk_size_m) as u32,
+            (z as f64 / self.chunk_size_m) as u32,
+        ))
+    }
+
+    /// Оставляет след вида `kind` с направлением `heading`, радианы, на
+    /// `material` (`None` — материала в конфиге нет). Поставленный след
+    /// уходит в ближайшую рассылку
+    pub fn footprint(
+        &mut self,
+        kind: &str,
+        x: f32,
+        z: f32,
+        heading: f32,
+        material: Option<&MaterialConfig>,
+        now_ms: i64,
+    ) -> Result<Decal, DeformError> {
+        let footprints = &self.interaction.footprints;
+        let tracks = &self.interaction.object_interaction;
+        let allowed = if kind == FOOTPRINT {
+            footprints.enabled && footprints.max_decals_per_chunk > 0
+        } else {
+            tracks.leave_tracks && tracks.track_types.iter().any(|t| t == kind)
+        };
+        if !allowed {
+            return Err(DeformError::UnsupportedKind);
+        }
+        if !heading.is_finite() {
+            return Err(DeformError::InvalidEvent);
+        }
+        if kind == FOOTPRINT && material.is_some_and(|m| !m.supports_footprints) {
+            return Err(DeformError::UnsupportedSurface);
+        }
+        let (x, z) = (self.snap(x), self.snap(z));
+        let key = self.chunk_of(x, z).ok_or(DeformError::InvalidEvent)?;
+
+        let hardness = material.map_or(DEFAULT_HARDNESS, |m| m.hardness.clamp(0.0, 1.0));
+        let sinkage = FOOTPRINT_DEPTH_M * (1.0 - hardness).powi(2);
+        let room = self.max_depth_m() - self.depth_at(x, z);
+        let persistence = material
+            .and_then(|m| m.footprint_persistence)
+            .unwrap_or(1.0)
+            .max(0.0);
+        let lifetime_ms =
+            (footprints.fade_time_seconds as f64 * persistence as f64 * 1000.0) as i64;
+        let decal = Decal {
+            id: self.next_decal,
+            kind: kind.to_string(),
+            x,
+            z,
+            heading,
+            created_ms: now_ms,
+            depth: sinkage.min(room).max(0.0),
+            expires_ms: footprints.fade_over_time.then_some(now_ms + lifetime_ms),
+        };
+        self.next_decal += 1;
+        let max_decals = footprints.max_decals_per_chunk.max(1) as usize;
+
+        self.press(Dent::from_decal(&decal), None);
+        let chunk = self.chunks.entry(key).or_default();
+        chunk.decals.push_back(decal.clone());
+        let mut evicted = Vec::new();
+        while chunk.decals.len() > max_decals {
+            evicted.extend(chunk.decals.pop_front());
+        }
+        let delta = self.pending.entry(key).or_default();
+        delta.removed_decals.extend(evicted.iter().map(|d| d.id));
+        delta.decals.push(decal.clone());
+        self.rebuild(evicted.iter().map(Dent::from_decal));
+        Ok(decal)
+    }
+
+    /// Вмятина; глубина урезается так, чтобы в её центре не превысить
+    /// `maxDeformationDepthMeters`
+    pub fn stamp(&mut self, x: f32, z: f32, radius: f32, depth: f32) -> Result<Stamp, DeformError> {
+        if !self.interaction.surface_deformation.enabled {
+            return Err(DeformError::UnsupportedKind);
+        }
+        if !(radius > 0.0 && radius <= MAX_STAMP_RADIUS_M && depth > 0.0 && depth.is_finite()) {
+            return Err(DeformError::InvalidEvent);
+        }
+        let (x, z) = (self.snap(x), self.snap(z));
+        let key = self.chunk_of(x, z).ok_or(DeformError::InvalidEvent)?;
+        if self
+            .chunks
+            .get(&key)
+            .is_some_and(|c| c.stamps.len() >= MAX_STAMPS_PER_CHUNK)
+        {
+            return Err(DeformError::ChunkFull);
+        }
+        let depth = depth.min(self.max_depth_m() - self.depth_at(x, z));
+        if depth <= 0.0 {
+            return Err(DeformError::MaxDepthReached);
+        }
+        let stamp = Stamp {
+            x,
+            z,
+            radius,
+            depth,
+        };
+        self.press(Dent::from_stamp(&stamp), None);
+        self.chunks
+            .entry(key)
+            .or_default()
+            .stamps
+            .push(stamp.clone());
+        self.pending
+            .entry(key)
+            .or_default()
+            .stamps
+            .push(stamp.clone());
+        Ok(stamp)
+    }
+
+    /// Глубина продавленной поверхности в точке, м — билинейно по решётке
+    pub fn depth_at(&self, x: f32, z: f32) -> f32 {
+        if !(x.is_finite() && z.is_finite()) {
+            return 0.0;
+        }
+        let (u, v) = (x / self.resolution_m, z / self.resolution_m);
+        let (i, j) = (u.floor() as i64, v.floor() as i64);
+        let (fx, fz) = (u - i as f32, v - j as f32);
+        let top = self.node(i, j) * (1.0 - fx) + self.node(i + 1, j) * fx;
+        let bottom = self.node(i, j + 1) * (1.0 - fx) + self.node(i + 1, j + 1) * fx;
+        top * (1.0 - fz) + bottom * fz
+    }
+
+    /// Решётка чанка; `None` — чанк не тронут
+    pub fn grid(&self, key: ChunkKey) -> Option<&DeformationGrid> {
+        self.chunks.get(&key).map(|c| &c.grid)
+    }
+
+    /// Вмятины и следы чанка; `None` — их нет
+    pub fn chunk_state(&self, key: ChunkKey) -> Option<DeformChunkState> {
+        let chunk = self.chunks.get(&key)?;
+        if chunk.stamps.is_empty() && chunk.decals.is_empty() {
+            return None;
+        }
+        Some(DeformChunkState {
+            stamps: chunk.stamps.clone(),
+            decals: chunk.decals.iter().cloned().collect(),
+        })
+    }
+
+    /// Изменения с прошлого вызова по чанкам
+    pub fn take_deltas(&mut self) -> Vec<(ChunkKey, DeformDelta)> {
+        self.pending.drain().collect()
+    }
+
+    /// Убирает выцветшие к `now_ms` следы; возвращает, сколько их было
+    pub fn fade(&mut self, now_ms: i64) -> usize {
+        let mut faded = Vec::new();
+        for (key, chunk) in &mut self.chunks {
+            let before = faded.len();
+            chunk.decals.retain(|d| {
+                let alive = d.expires_ms.is_none_or(|t| t > now_ms);
+                if !alive {
+                    faded.push(d.clone());
+                }
+                alive
+            });
+            if faded.len() > before {
+                let delta = self.pending.entry(*key).or_default();
+                delta
+                    .removed_decals
+                    .extend(faded[before..].iter().map(|d| d.id));
+            }
+        }
+        self.rebuild(faded.iter().map(Dent::from_decal));
+        faded.len()
+    }
+
+    fn max_depth_m(&self) -> f32 {
+        self.interaction
+            .surface_deformation
+            .max_deformation_depth_meters
+            .max(0.0)
+    }
+
+    fn snap(&self, v: f32) -> f32 {
+        (v / self.resolution_m).round() * self.resolution_m
+    }
+
+    fn node(&self, i: i64, j: i64) -> f32 {
+        let key = self.node_chunk(i, j);
+        key.and_then(|k| self.chunks.get(&k))
+            .map_or(0.0, |c| c.grid.node(i, j))
+    }
+
+    /// Чанк, которому принадлежит узел
+    fn node_chunk(&self, i: i64, j: i64) -> Option<ChunkKey> {
+        let r = self.resolution_m as f64;
+        if i < 0 || j < 0 {
+            return None;
+        }
+        Some((
+            (i as f64 * r / self.chunk_size_m) as u32,
+            (j as f64 * r / self.chunk_size_m) as u32,
+        ))
+    }
+
+    /// Узлы, которые задевает вмятина
+    fn nodes(&self, dent: &Dent) -> NodeBox {
+        let r = self.resolution_m;
+        NodeBox {
+            i0: ((dent.x - dent.radius) / r).ceil().max(0.0) as i64,
+            i1: ((dent.x + dent.radius) / r).floor().max(-1.0) as i64,
+            j0: ((dent.z - dent.radius) / r).ceil().max(0.0) as i64,
+            j1: ((dent.z + dent.radius) / r).floor().max(-1.0) as i64,
+        }
+    }
+
+    /// Продавливает решётку вмятиной, только в узлах `clip`, если он задан
+    fn press(&mut self, dent: Dent, clip: Option<NodeBox>) {
+        let max_depth = self.max_depth_m();
+        let mut area = self.nodes(&dent);
+        if let Some(clip) = clip {
+            area = area.intersect(clip);
+        }
+        for j in area.j0..=area.j1 {
+            for i in area.i0..=area.i1 {
+                let (x, z) = (i as f32 * self.resolution_m, j as f32 * self.resolution_m);
+                let d = dent.depth_at(x, z);
+                if d <= 0.0 {
+                    continue;
+                }
+                let Some(key) = self.node_chunk(i, j) else {
+                    continue;
+                };
+                let node = self.chunks.entry(key).or_default().grid.node_mut(i, j);
+                *node = (*node + d).min(max_depth);
+            }
+        }
+    }
+
+    /// Пересчитывает решётку под ушедшими вмятинами по оставшимся
+    fn rebuild(&mut self, removed: impl Iterator<Item = Dent>) {
+        let areas: Vec<NodeBox> = removed.map(|d| self.nodes(&d)).collect();
+        if areas.is_empty() {
+            return;
+        }
+        let mut touched = HashSet::new();
+        for area in &areas {
+            for j in area.j0..=area.j1 {
+                for i in area.i0..=area.i1 {
+                    let Some(key) = self.node_chunk(i, j) else {
+                        continue;
+                    };
+                    touched.insert(key);
+                    if let Some(chunk) = self.chunks.get_mut(&key) {
+                        let (tile, k) = tile_of(i, j);
+                        if let Some(t) = chunk.grid.tiles.get_mut(&tile) {
+                            t[k] = 0.0;
+                        }
+                    }
+                }
+            }
+        }
+        // вмятины хранятся в чанке центра, а задевают и соседей
+        let sources: HashSet<ChunkKey> = touched
+            .iter()
+            .flat_map(|&(cx, cy)| {
+                (cy.saturating_sub(1)..=cy + 1)
+                    .flat_map(move |y| (cx.saturating_sub(1)..=cx + 1).map(move |x| (x, y)))
+            })
+            .collect();
+        let dents: Vec<Dent> = sources
+            .iter()
+            .filter_map(|k| self.chunks.get(k))
+            .flat_map(|c| c.dents())
+            .collect();
+        for dent in dents {
+            let nodes = self.nodes(&dent);
+            for area in &areas {
+                if nodes.overlaps(*area) {
+                    self.press(dent, Some(*area));
+                }
+            }
+        }
+        for key in touched {
+            if let Some(chunk) = self.chunks.get_mut(&key) {
+                chunk.grid.tiles.retain(|_, t| t.iter().any(|&d| d > 0.0));
+                if chunk.is_empty() {
+                    self.chunks.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Плитка узла и номер узла в ней
+fn tile_of(i: i64, j: i64) -> ((i64, i64), usize) {
+    let tile = (i.div_euclid(TILE), j.div_euclid(TILE));
+    let k = j.rem_euclid(TILE) * TILE + i.rem_euclid(TILE);
+    (tile, k as usize)
+}
+
+fn dent_depth(cx: f32, cz: f32, radius: f32, depth: f32, x: f32, z: f32) -> f32 {
+    let d2 = ((x - cx).powi(2) + (z - cz).powi(2)) / radius.powi(2);
+    depth * (1.0 - d2).max(0.0)
+}
+
+/// Вмятина или след как форма на решётке
+#[derive(Debug, Clone, Copy)]
+struct Dent {
+    x: f32,
+    z: f32,
+    radius: f32,
+    depth: f32,
+}
+
+impl Dent {
+    fn from_stamp(s: &Stamp) -> Self {
+        Dent {
+            x: s.x,
+            z: s.z,
+            radius: s.radius,
+            depth: s.depth,
+        }
+    }
+
+    fn from_decal(d: &Decal) -> Self {
+        Dent {
+            x: d.x,
+            z: d.z,
+            radius: d.radius(),
+            depth: d.depth,
+        }
+    }
+
+    fn depth_at(&self, x: f32, z: f32) -> f32 {
+        dent_depth(self.x, self.z, self.radius, self.depth, x, z)
+    }
+}
+
+/// Прямоугольник узлов, границы включительно
+#[derive(Debug, Clone, Copy)]
+struct NodeBox {
+    i0: i64,
+    i1: i64,
+    j0: i64,
+    j1: i64,
+}
+
+impl NodeBox {
+    fn intersect(self, other: NodeBox) -> NodeBox {
+        NodeBox {
+            i0: self.i0.max(other.i0),
+            i1: self.i1.min(other.i1),
+            j0: self.j0.max(other.j0),
+            j1: self.j1.min(other.j1),
+        }
+    }
+
+    fn overlaps(self, other: NodeBox) -> bool {
+        let b = self.intersect(other);
+        b.i0 <= b.i1 && b.j0 <= b.j1
+    }
+}
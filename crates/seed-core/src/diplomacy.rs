@@ -0,0 +1,269 @@
+//! Дипломатия: попарные отношения фракций, которые складываются из общих
+//! границ, торговли и войн. Матрица отношений сохраняется на каждый шаг
+//! истории, чтобы нарративный директор мог строить квесты из текущей
+//! геополитики.
+
+use crate::history::{History, HistoryEvent, HistoryEventKind, HISTORY_STEP_YEARS};
+use crate::war::at_war;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Порог отношений для торгового договора
+const TRADE_PACT_SCORE: f32 = 30.0;
+/// Порог отношений для союза
+const ALLIANCE_SCORE: f32 = 60.0;
+/// Ниже этого порога стороны — соперники
+const RIVALRY_SCORE: f32 = -40.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Treaty {
+    TradePact,
+    Alliance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stance {
+    Allied,
+    Friendly,
+    Neutral,
+    Rival,
+    AtWar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Relation {
+    /// -100 (заклятые враги) .. 100 (верные союзники)
+    pub score: f32,
+    pub stance: Stance,
+    pub treaty: Option<Treaty>,
+}
+
+impl Default for Relation {
+    fn default() -> Self {
+        Self {
+            score: 0.0,
+            stance: Stance::Neutral,
+            treaty: None,
+        }
+    }
+}
+
+/// Симметричная матрица отношений фракций на конкретный год
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationMatrix {
+    pub year: u32,
+    /// Порядок совпадает с `History::factions`
+    pub faction_ids: Vec<String>,
+    relations: Vec<Relation>,
+}
+
+impl RelationMatrix {
+    pub fn new(year: u32, faction_ids: Vec<String>) -> Self {
+        let n = faction_ids.len();
+        Self {
+            year,
+            faction_ids,
+            relations: vec![Relation::default(); n * n],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.faction_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faction_ids.is_empty()
+    }
+
+    /// Отношения фракций по индексам; фракция к самой себе — союзник
+    pub fn get(&self, a: usize, b: usize) -> Relation {
+        if a == b {
+            return Relation {
+                score: 100.0,
+                stance: Stance::Allied,
+                treaty: None,
+            };
+        }
+        self.relations[a * self.len() + b]
+    }
+
+    /// Отношения фракций по id
+    pub fn get_by_id(&self, a: &str, b: &str) -> Option<Relation> {
+        let ia = self.faction_ids.iter().position(|f| f == a)?;
+        let ib = self.faction_ids.iter().position(|f| f == b)?;
+        Some(self.get(ia, ib))
+    }
+
+    fn set(&mut self, a: usize, b: usize, r: Relation) {
+        let n = self.len();
+        self.relations[a * n + b] = r;
+        self.relations[b * n + a] = r;
+    }
+
+    /// Пары фракций (a < b) с заданным отношением
+    pub fn pairs_with_stance(&self, stance: Stance) -> Vec<(usize, usize)> {
+        let n = self.len();
+        (0..n)
+            .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+            .filter(|&(a, b)| self.get(a, b).stance == stance)
+            .collect()
+    }
+}
+
+/// История дипломатии: текущее состояние и срезы по годам
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diplomacy {
+    pub current: RelationMatrix,
+    pub snapshots: Vec<RelationMatrix>,
+}
+
+impl Diplomacy {
+    pub fn new(faction_ids: Vec<String>) -> Self {
+        Self {
+            current: RelationMatrix::new(0, faction_ids),
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// Матрица отношений на год `year` (последний срез не позже этого года)
+    pub fn matrix_at(&self, year: u32) -> Option<&RelationMatrix> {
+        let i = self.snapshots.partition_point(|m| m.year <= year);
+        i.checked_sub(1).map(|i| &self.snapshots[i])
+    }
+}
+
+/// Один шаг дипломатии: трения на границах, сближение через торговлю,
+/// вражда из-за войн, общие враги сближают. Заключение и разрыв договоров
+/// попадают в события истории.
+pub(crate) fn diplomacy_step(
+    history: &mut History,
+    contacts: &BTreeMap<(u16, u16), usize>,
+    year: u32,
+) {
+    let n = history.factions.len();
+    if history.diplomacy.current.len() != n {
+        return;
+    }
+    let dt = HISTORY_STEP_YEARS as f32;
+    let trade = history.trade.volume_between_factions(&history.cities);
+
+    let mut events = Vec::new();
+    for a in 0..n {
+        for b in a + 1..n {
+            let (fa, fb) = (&history.factions[a], &history.factions[b]);
+            let mut rel = history.diplomacy.current.get(a, b);
+            let old_treaty = rel.treaty;
+
+            // отношения постепенно забываются
+            let mut score = rel.score * 0.98;
+
+            let border = contacts.get(&(a as u16, b as u16)).copied().unwrap_or(0) as f32;
+            score -= (border / 20.0).min(1.0) * 0.4 * dt;
+
+            let key = (
+                fa.id.clone().min(fb.id.clone()),
+                fa.id.clone().max(fb.id.clone()),
+            );
+            let volume = trade.get(&key).copied().unwrap_or(0.0) as f32;
+            score += (volume / 5_000.0).min(1.0) * 0.8 * dt;
+
+            let war = at_war(history, &fa.id, &fb.id);
+            if war {
+                score -= 2.0 * dt;
+            }
+
+            // враг моего врага
+            let common_enemy = history.factions.iter().any(|f| {
+                f.id != fa.id
+                    && f.id != fb.id
+                    && at_war(history, &fa.id, &f.id)
+                    && at_war(history, &fb.id, &f.id)
+            });
+            if common_enemy {
+                score += 1.0 * dt;
+            }
+
+            // события этого шага
+            for e in history.events.iter().rev().take_while(|e| e.year == year) {
+                let involves = |x: &str, y: &str| {
+                    (e.faction_id == x)
+                        && matches!(&e.kind,
+                        HistoryEventKind::WarDeclared { enemy_id }
+                        | HistoryEventKind::PeaceMade { enemy_id } if enemy_id == y)
+                };
+                if involves(&fa.id, &fb.id) || involves(&fb.id, &fa.id) {
+                    match e.kind {
+                        HistoryEventKind::WarDeclared { .. } => score = score.min(-50.0),
+                        HistoryEventKind::PeaceMade { .. } => score += 20.0,
+                        _ => {}
+                    }
+                }
+            }
+
+            rel.score = score.clamp(-100.0, 100.0);
+            rel.treaty = if war {
+                None
+            } else if rel.score >= ALLIANCE_SCORE {
+                Some(Treaty::Alliance)
+            } else if rel.score >= TRADE_PACT_SCORE {
+                // союз распадается до торгового договора, если он был
+                Some(Treaty::TradePact)
+            } else {
+                None
+            };
+            rel.stance = if war {
+                Stance::AtWar
+            } else if rel.treaty == Some(Treaty::Alliance) {
+                Stance::Allied
+            } else if rel.score >= 20.0 {
+                Stance::Friendly
+            } else if rel.score <= RIVALRY_SCORE {
+                Stance::Rival
+            } else {
+                Stance::Neutral
+            };
+
+            // союз поверх торгового договора — повышение, а не разрыв
+            let rank = |t: Option<Treaty>| match t {
+                None => 0,
+                Some(Treaty::TradePact) => 1,
+                Some(Treaty::Alliance) => 2,
+            };
+            match (old_treaty, rel.treaty) {
+                (old, Some(t)) if rank(Some(t)) > rank(old) => {
+                    events.push((
+                        a,
+                        HistoryEventKind::TreatySigned {
+                            other_id: fb.id.clone(),
+                            treaty: t,
+                        },
+                    ));
+                }
+                (Some(t), new) if rank(new) < rank(Some(t)) => {
+                    events.push((
+                        a,
+                        HistoryEventKind::TreatyBroken {
+                            other_id: fb.id.clone(),
+                            treaty: t,
+                        },
+                    ));
+                }
+                _ => {}
+            }
+
+            history.diplomacy.current.set(a, b, rel);
+        }
+    }
+
+    for (a, kind) in events {
+        history.events.push(HistoryEvent {
+            year,
+            faction_id: history.factions[a].id.clone(),
+            kind,
+        });
+    }
+
+    history.diplomacy.current.year = year;
+    let snapshot = history.diplomacy.current.clone();
+    history.diplomacy.snapshots.push(snapshot);
+}
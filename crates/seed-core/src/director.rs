@@ -0,0 +1,1130 @@
+//! Нарративный директор: следит за состоянием мира (позиции игроков,
+//! свежие события из шины, отношения фракций) и выдаёт игрокам задания
+//! типов из `preferredQuestTypes` с целями в реальных местах карты —
+//! городах, руинах, названных объектах и местах катастроф. Шаблоны из
+//! `questTemplates` (см. `quest_template`) имеют приоритет над встроенными
+//! заданиями своего типа.
+//!
+//! Директор же задаёт темп: напряжение копится со скоростью `aggressiveness`,
+//! и на пике он публикует в шину набег, бурю или местную катастрофу. Событие,
+//! нарушающее `eventPolicies`, отклоняется и попадает в журнал отказов.
+//! Места для событий вдали от игроков директор ищет по карте опасности.
+
+use crate::calendar::Calendar;
+use crate::catastrophe::CatastropheType;
+use crate::danger::{compute_danger_map, DangerMap};
+use crate::diplomacy::{RelationMatrix, Stance};
+use crate::ecosystem::Ecosystem;
+use crate::events::{EventBus, WorldEvent, WorldEventKind};
+use crate::geography::{FeatureKind, GazetteerEntry};
+use crate::history::{History, HistoryEventKind};
+use crate::quest_template::{
+    QuestTemplate, SelectorKind, SelectorOrder, TargetSelector, TerritoryFilter,
+};
+use crate::rng::SplitMix64;
+use crate::seed_tree::SeedTree;
+use crate::{CoreError, Result};
+use seed_config::{NarrativeDirectorConfig, WorldConfig};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+/// Сколько суток планеты событие считается свежим
+const RECENT_EVENT_DAYS: f64 = 30.0;
+/// Сколько свежих событий директор держит в памяти
+const RECENT_EVENTS_LIMIT: usize = 64;
+/// Катастрофы истории не старше стольких лет до её конца — тоже места для заданий
+const HISTORY_CATASTROPHE_YEARS: u32 = 50;
+/// Рост напряжения в час при `aggressiveness` = 1: событие раз в игровые сутки
+const TENSION_PER_HOUR: f64 = 1.0 / 24.0;
+/// Событие с такой силой и выше разрушает накрытые им города
+const CITY_DESTRUCTION_INTENSITY: f32 = 0.7;
+/// Событие с радиусом больше такой доли ширины карты считается глобальным
+const GLOBAL_RADIUS_SHARE: f32 = 0.15;
+/// Сколько случайных клеток директор сравнивает по опасности, выбирая место события
+const PLACEMENT_SAMPLES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestType {
+    Exploration,
+    Rescue,
+    Defense,
+    ResourceStabilization,
+}
+
+impl QuestType {
+    pub fn from_id(id: &str) -> Option<QuestType> {
+        match id {
+            "exploration" => Some(QuestType::Exploration),
+            "rescue" => Some(QuestType::Rescue),
+            "defense" => Some(QuestType::Defense),
+            "resource_stabilization" => Some(QuestType::ResourceStabilization),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> &'static str {
+        match self {
+            QuestType::Exploration => "exploration",
+            QuestType::Rescue => "rescue",
+            QuestType::Defense => "defense",
+            QuestType::ResourceStabilization => "resource_stabilization",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorKind {
+    City,
+    Ruin,
+    /// Объект газеттира (хребет, река, лес...)
+    Feature(FeatureKind),
+    CatastropheSite,
+}
+
+/// Место на карте, к которому привязана цель задания
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestAnchor {
+    pub kind: AnchorKind,
+    pub id: String,
+    pub name: String,
+    pub faction_id: Option<String>,
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestStatus {
+    Active,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quest {
+    pub id: String,
+    pub player_id: String,
+    pub quest_type: QuestType,
+    pub title: String,
+    /// Цель первого шага
+    pub target: QuestAnchor,
+    pub objectives: Vec<QuestObjective>,
+    pub rewards: Vec<QuestReward>,
+    /// Шаблон, по которому построено задание; `None` — встроенное
+    pub template_id: Option<String>,
+    /// Фракция, на чьей земле игрок получил задание
+    pub giver_faction_id: Option<String>,
+    pub issued_at_minutes: u64,
+    pub status: QuestStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestObjective {
+    pub action: String,
+    pub description: String,
+    pub target: QuestAnchor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestReward {
+    pub item: String,
+    pub amount: u32,
+}
+
+/// Положение игрока в клетках карты
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerState {
+    pub id: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Почему директор отклонил событие
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyViolation {
+    /// `allowCityDestruction` = false, а событие разрушит город
+    CityDestruction { city_id: String },
+    /// `allowPermanentBiomeChanges` = false, а событие навсегда меняет ландшафт
+    PermanentBiomeChange,
+    /// Ожидаемая доля погибших игроков выше `maxPlayersKilledBySystemEvent`
+    PlayerDeaths { share: f32 },
+    /// `canTriggerGlobalCatastrophes` = false, а событие охватывает полкарты
+    GlobalCatastrophe,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyViolation::CityDestruction { city_id } => {
+                write!(f, "would destroy city {city_id}")
+            }
+            PolicyViolation::PermanentBiomeChange => write!(f, "would permanently change biomes"),
+            PolicyViolation::PlayerDeaths { share } => {
+                write!(f, "would kill {:.0}% of players", share * 100.0)
+            }
+            PolicyViolation::GlobalCatastrophe => write!(f, "global catastrophes are disabled"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RefusedEvent {
+    pub minutes: u64,
+    pub event: WorldEvent,
+    pub violation: PolicyViolation,
+}
+
+#[derive(Debug, Clone)]
+struct RecentEvent {
+    minutes: u64,
+    event: WorldEvent,
+}
+
+#[derive(Debug, Clone)]
+pub struct Director {
+    pub config: NarrativeDirectorConfig,
+    players: BTreeMap<String, PlayerState>,
+    /// Города, руины, объекты газеттира, места катастроф истории
+    anchors: Vec<QuestAnchor>,
+    faction_names: BTreeMap<String, String>,
+    /// id фракций в порядке истории — индексы `territory`
+    faction_ids: Vec<String>,
+    /// Хозяин каждой клетки карты
+    territory: Vec<Option<u16>>,
+    templates: Vec<QuestTemplate>,
+    /// Ошибки разбора `questTemplates`; такие шаблоны не используются
+    template_errors: Vec<String>,
+    relations: Option<RelationMatrix>,
+    recent: VecDeque<RecentEvent>,
+    quests: Vec<Quest>,
+    next_quest_id: u64,
+    event_cursor: usize,
+    now_minutes: u64,
+    /// `RECENT_EVENT_DAYS` в минутах
+    recent_event_minutes: u64,
+    /// Размер карты в клетках (из `set_world`)
+    width: u32,
+    height: u32,
+    /// Напряжение 0..1; на 1 директор выпускает событие
+    tension: f64,
+    last_paced_minutes: Option<u64>,
+    refused: Vec<RefusedEvent>,
+    danger: Option<DangerMap>,
+    rng: SplitMix64,
+}
+
+impl Director {
+    pub fn new(cfg: &WorldConfig, seed: u64) -> Self {
+        let mut templates = Vec::new();
+        let mut template_errors = Vec::new();
+        for t in cfg.narrative_director.quest_templates.iter().flatten() {
+            match QuestTemplate::from_config(t) {
+                Ok(t) => templates.push(t),
+                Err(CoreError::Config(msg)) => template_errors.push(msg),
+            }
+        }
+        Self {
+            config: cfg.narrative_director.clone(),
+            players: BTreeMap::new(),
+            anchors: Vec::new(),
+            faction_names: BTreeMap::new(),
+            faction_ids: Vec::new(),
+            territory: Vec::new(),
+            templates,
+            template_errors,
+            relations: None,
+            recent: VecDeque::new(),
+            quests: Vec::new(),
+            next_quest_id: 0,
+            event_cursor: 0,
+            now_minutes: 0,
+            recent_event_minutes: Calendar::from_config(cfg).minutes_in_days(RECENT_EVENT_DAYS),
+            width: 0,
+            height: 0,
+            tension: 0.0,
+            last_paced_minutes: None,
+            refused: Vec::new(),
+            danger: None,
+            rng: SeedTree::new(seed).derive("director").rng(),
+        }
+    }
+
+    /// Берёт места для заданий и отношения фракций из итогов истории и газеттира
+    pub fn set_world(&mut self, history: &History, gazetteer: &[GazetteerEntry]) {
+        self.width = history.territory.width;
+        self.height = history.territory.height;
+        self.anchors.clear();
+        for c in &history.cities {
+            self.anchors.push(QuestAnchor {
+                kind: AnchorKind::City,
+                id: c.id.clone(),
+                name: c.name.clone(),
+                faction_id: Some(c.faction_id.clone()),
+                x: c.x,
+                y: c.y,
+            });
+        }
+        for r in &history.ruins {
+            self.anchors.push(QuestAnchor {
+                kind: AnchorKind::Ruin,
+                id: r.id.clone(),
+                name: format!("the ruins of {}", r.name),
+                faction_id: None,
+                x: r.x,
+                y: r.y,
+            });
+        }
+        for g in gazetteer {
+            self.anchors.push(QuestAnchor {
+                kind: AnchorKind::Feature(g.kind),
+                id: format!("feature:{}", g.name),
+                name: g.name.clone(),
+                faction_id: None,
+                x: g.anchor.0,
+                y: g.anchor.1,
+            });
+        }
+        let since = history
+            .years_simulated
+            .saturating_sub(HISTORY_CATASTROPHE_YEARS);
+        for (i, e) in history.events.iter().enumerate() {
+            if let HistoryEventKind::CatastropheStruck {
+                catastrophe_type,
+                x,
+                y,
+                ..
+            } = &e.kind
+            {
+                if e.year >= since {
+                    self.anchors.push(QuestAnchor {
+                        kind: AnchorKind::CatastropheSite,
+                        id: format!("catastrophe:{i}"),
+                        name: format!("the {} site", catastrophe_noun(*catastrophe_type)),
+                        faction_id: None,
+                        x: *x,
+                        y: *y,
+                    });
+                }
+            }
+        }
+
+        self.faction_names = history
+            .factions
+            .iter()
+            .map(|f| (f.id.clone(), f.name.clone()))
+            .collect();
+        self.faction_ids = history.factions.iter().map(|f| f.id.clone()).collect();
+        self.territory = history.territory.owner.clone();
+        self.relations = Some(history.diplomacy.current.clone());
+    }
+
+    /// Ошибка конфига со списком всех негодных шаблонов заданий, если они есть
+    pub fn validate_templates(&self) -> Result<()> {
+        if self.template_errors.is_empty() {
+            return Ok(());
+        }
+        Err(CoreError::Config(format!(
+            "invalid quest templates: {}",
+            self.template_errors.join("; ")
+        )))
+    }
+
+    pub fn set_relations(&mut self, relations: RelationMatrix) {
+        self.relations = Some(relations);
+    }
+
+    /// Пересчитывает карту опасности по текущим отношениям и свежим событиям
+    pub fn refresh_danger_map(
+        &mut self,
+        history: &History,
+        ecosystem: Option<&Ecosystem>,
+    ) -> &DangerMap {
+        let events: Vec<WorldEvent> = self.recent.iter().map(|e| e.event.clone()).collect();
+        let relations = self
+            .relations
+            .as_ref()
+            .unwrap_or(&history.diplomacy.current);
+        let map = compute_danger_map(history, relations, ecosystem, &events);
+        self.danger.insert(map)
+    }
+
+    pub fn danger_map(&self) -> Option<&DangerMap> {
+        self.danger.as_ref()
+    }
+
+    pub fn update_player(&mut self, id: &str, x: f32, y: f32) {
+        self.players.insert(
+            id.to_string(),
+            PlayerState {
+                id: id.to_string(),
+                x,
+                y,
+            },
+        );
+    }
+
+    pub fn remove_player(&mut self, id: &str) {
+        self.players.remove(id);
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &PlayerState> {
+        self.players.values()
+    }
+
+    /// Запоминает новые события из шины
+    pub fn observe(&mut self, bus: &EventBus) {
+        let mut cursor = self.event_cursor;
+        for e in bus.read(&mut cursor) {
+            self.recent.push_back(RecentEvent {
+                minutes: self.now_minutes,
+                event: e.clone(),
+            });
+        }
+        self.event_cursor = cursor;
+        while self.recent.len() > RECENT_EVENTS_LIMIT {
+            self.recent.pop_front();
+        }
+    }
+
+    pub fn quests(&self) -> &[Quest] {
+        &self.quests
+    }
+
+    pub fn active_quests<'a>(&'a self, player_id: &'a str) -> impl Iterator<Item = &'a Quest> {
+        self.quests
+            .iter()
+            .filter(move |q| q.player_id == player_id && q.status == QuestStatus::Active)
+    }
+
+    pub fn complete_quest(&mut self, quest_id: &str) -> bool {
+        self.finish(quest_id, QuestStatus::Completed)
+    }
+
+    pub fn fail_quest(&mut self, quest_id: &str) -> bool {
+        self.finish(quest_id, QuestStatus::Failed)
+    }
+
+    fn finish(&mut self, quest_id: &str, status: QuestStatus) -> bool {
+        match self
+            .quests
+            .iter_mut()
+            .find(|q| q.id == quest_id && q.status == QuestStatus::Active)
+        {
+            Some(q) => {
+                q.status = status;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ход директора на момент `now_minutes`: каждому игроку, у которого
+    /// заданий меньше `maxActiveQuestsPerPlayer`, — не больше одного нового.
+    /// Возвращает выданные задания.
+    pub fn tick(&mut self, now_minutes: u64) -> Vec<Quest> {
+        self.now_minutes = now_minutes;
+        while self
+            .recent
+            .front()
+            .is_some_and(|e| now_minutes.saturating_sub(e.minutes) > self.recent_event_minutes)
+        {
+            self.recent.pop_front();
+        }
+
+        let gen = &self.config.quest_generation;
+        if !self.config.enabled || !gen.enabled {
+            return Vec::new();
+        }
+        let types: Vec<QuestType> = gen
+            .preferred_quest_types
+            .iter()
+            .filter_map(|t| QuestType::from_id(t))
+            .collect();
+        if types.is_empty() {
+            return Vec::new();
+        }
+
+        let max_active = gen.max_active_quests_per_player as usize;
+        let players: Vec<PlayerState> = self.players.values().cloned().collect();
+        let mut issued = Vec::new();
+        for player in &players {
+            if self.active_quests(&player.id).count() >= max_active {
+                continue;
+            }
+            // начинаем со случайного типа и берём первый, для которого нашлась цель
+            let start = self.rng.below(types.len());
+            let quest = (0..types.len())
+                .map(|k| types[(start + k) % types.len()])
+                .find_map(|t| {
+                    self.make_template_quest(player, t)
+                        .or_else(|| self.make_quest(player, t))
+                });
+            if let Some(q) = quest {
+                self.quests.push(q.clone());
+                issued.push(q);
+            }
+        }
+        issued
+    }
+
+    pub fn tension(&self) -> f64 {
+        self.tension
+    }
+
+    /// Отклонённые по `eventPolicies` события
+    pub fn refused_events(&self) -> &[RefusedEvent] {
+        &self.refused
+    }
+
+    /// Ход темпа на момент `now_minutes`: копит напряжение и на пике
+    /// выбирает событие. Допустимое событие публикуется в шину и
+    /// возвращается, недопустимое попадает в `refused_events`.
+    pub fn pace(&mut self, now_minutes: u64, bus: &mut EventBus) -> Option<WorldEvent> {
+        let last = self.last_paced_minutes.replace(now_minutes)?;
+        if !self.config.enabled || self.width == 0 {
+            return None;
+        }
+        let hours = now_minutes.saturating_sub(last) as f64 / 60.0;
+        let aggressiveness = self.config.aggressiveness.clamp(0.0, 1.0) as f64;
+        self.tension += hours * TENSION_PER_HOUR * aggressiveness;
+        if self.tension < 1.0 {
+            return None;
+        }
+
+        let event = self.propose_event();
+        match self.check_event(&event) {
+            Ok(()) => {
+                // после события — передышка
+                self.tension = 0.0;
+                bus.publish(event.clone());
+                Some(event)
+            }
+            Err(violation) => {
+                // следующая попытка раньше обычного
+                self.tension = 0.5;
+                self.refused.push(RefusedEvent {
+                    minutes: now_minutes,
+                    event,
+                    violation,
+                });
+                None
+            }
+        }
+    }
+
+    /// Проверка события на соответствие `eventPolicies` и `canTriggerGlobalCatastrophes`
+    pub fn check_event(&self, event: &WorldEvent) -> std::result::Result<(), PolicyViolation> {
+        let policies = &self.config.event_policies;
+        let global = event.radius > self.width as f32 * GLOBAL_RADIUS_SHARE;
+        if global && !self.config.can_trigger_global_catastrophes {
+            return Err(PolicyViolation::GlobalCatastrophe);
+        }
+        let permanent = matches!(
+            event.kind,
+            WorldEventKind::Catastrophe(
+                CatastropheType::VolcanicEruption | CatastropheType::MeteorImpact
+            )
+        );
+        if permanent && !policies.allow_permanent_biome_changes {
+            return Err(PolicyViolation::PermanentBiomeChange);
+        }
+        if !policies.allow_city_destruction && event.intensity >= CITY_DESTRUCTION_INTENSITY {
+            let hit = cities(&self.anchors).find(|c| {
+                (c.x as f32 - event.x as f32).hypot(c.y as f32 - event.y as f32) <= event.radius
+            });
+            if let Some(c) = hit {
+                return Err(PolicyViolation::CityDestruction {
+                    city_id: c.id.clone(),
+                });
+            }
+        }
+        if !self.players.is_empty() {
+            // гибель падает от центра к краю
+            let deaths: f32 = self
+                .players
+                .values()
+                .map(|p| {
+                    let d = (p.x - event.x as f32).hypot(p.y - event.y as f32);
+                    let falloff = (1.0 - d / event.radius.max(1.0)).max(0.0);
+                    player_lethality(event.kind) * event.intensity * falloff
+                })
+                .sum();
+            let share = deaths / self.players.len() as f32;
+            if share > policies.max_players_killed_by_system_event {
+                return Err(PolicyViolation::PlayerDeaths { share });
+            }
+        }
+        Ok(())
+    }
+
+    /// Событие на пике напряжения. `playerDangerBias` — вероятность ударить
+    /// рядом с игроком, `worldStabilityBias` гасит силу и реже выбирает катастрофы.
+    fn propose_event(&mut self) -> WorldEvent {
+        let aggressiveness = self.config.aggressiveness.clamp(0.0, 1.0) as f64;
+        let stability = self.config.world_stability_bias.clamp(0.0, 1.0) as f64;
+        let player_bias = self.config.player_danger_bias.clamp(0.0, 1.0) as f64;
+        let w = self.width as f32;
+
+        let (mut x, mut y) = if !self.players.is_empty() && self.rng.chance(player_bias) {
+            let i = self.rng.below(self.players.len());
+            let p = self.players.values().nth(i).expect("index below len");
+            let jitter = w * 0.02;
+            (
+                p.x + self.rng.range_f64(-1.0, 1.0) as f32 * jitter,
+                p.y + self.rng.range_f64(-1.0, 1.0) as f32 * jitter,
+            )
+        } else {
+            // из нескольких случайных клеток — самая опасная
+            let samples = if self.danger.is_some() {
+                PLACEMENT_SAMPLES
+            } else {
+                1
+            };
+            let mut best = (f32::MIN, 0, 0);
+            for _ in 0..samples {
+                let cx = self.rng.below(self.width as usize) as u32;
+                let cy = self.rng.below(self.height as usize) as u32;
+                let score = self.danger.as_ref().map_or(0.0, |m| m.danger_at(cx, cy));
+                if score > best.0 {
+                    best = (score, cx, cy);
+                }
+            }
+            (best.1 as f32, best.2 as f32)
+        };
+
+        let roll = self.rng.next_f64();
+        let catastrophe_chance = 0.5 * aggressiveness * (1.0 - stability);
+        let (kind, radius) = if roll < catastrophe_chance {
+            let global = self.config.can_trigger_global_catastrophes
+                && self.rng.chance(0.1 * aggressiveness);
+            if global {
+                let t = [
+                    CatastropheType::VolcanicEruption,
+                    CatastropheType::MeteorImpact,
+                ][self.rng.below(2)];
+                (
+                    WorldEventKind::Catastrophe(t),
+                    w * self.rng.range_f64(0.2, 0.35) as f32,
+                )
+            } else {
+                let local = [
+                    CatastropheType::Earthquake,
+                    CatastropheType::Tornado,
+                    CatastropheType::Tsunami,
+                    CatastropheType::Hurricane,
+                ];
+                let t = local[self.rng.below(local.len())];
+                (
+                    WorldEventKind::Catastrophe(t),
+                    w * self.rng.range_f64(0.02, 0.05) as f32,
+                )
+            }
+        } else if roll < catastrophe_chance + 0.35 && cities(&self.anchors).next().is_some() {
+            // набег идёт на ближайший к выбранной точке город
+            let target = cities(&self.anchors)
+                .min_by(|a, b| {
+                    let da = (a.x as f32 - x).hypot(a.y as f32 - y);
+                    let db = (b.x as f32 - x).hypot(b.y as f32 - y);
+                    da.total_cmp(&db)
+                })
+                .expect("checked non-empty");
+            x = target.x as f32;
+            y = target.y as f32;
+            (WorldEventKind::Raid, 2.0)
+        } else {
+            (
+                WorldEventKind::Storm,
+                w * self.rng.range_f64(0.04, 0.1) as f32,
+            )
+        };
+
+        let intensity =
+            self.rng.range_f64(0.3, 1.0) * (0.5 + 0.5 * aggressiveness) * (1.0 - 0.5 * stability);
+        WorldEvent {
+            kind,
+            x: x.clamp(0.0, (self.width - 1) as f32) as u32,
+            y: y.clamp(0.0, self.height.saturating_sub(1) as f32) as u32,
+            radius: radius.max(1.0),
+            intensity: intensity as f32,
+        }
+    }
+
+    fn make_quest(&mut self, player: &PlayerState, quest_type: QuestType) -> Option<Quest> {
+        let real = self.config.quest_generation.use_real_world_state;
+        // цели, по которым у игрока уже есть задание, не повторяем
+        let taken: Vec<&str> = self
+            .quests
+            .iter()
+            .filter(|q| q.player_id == player.id)
+            .map(|q| q.target.id.as_str())
+            .collect();
+        let free = |a: &&QuestAnchor| !taken.contains(&a.id.as_str());
+
+        let (target, title) = match quest_type {
+            QuestType::Exploration => {
+                let candidates: Vec<&QuestAnchor> = self
+                    .anchors
+                    .iter()
+                    .filter(|a| matches!(a.kind, AnchorKind::Ruin | AnchorKind::Feature(_)))
+                    .filter(free)
+                    .collect();
+                let t = pick(&mut self.rng, player, candidates, real)?;
+                let title = format!("Explore {}", t.name);
+                (t, title)
+            }
+            QuestType::Rescue => {
+                let (t, what) = if real {
+                    self.rescue_target(player, &taken)?
+                } else {
+                    let cities = cities(&self.anchors).filter(free).collect();
+                    (
+                        pick(&mut self.rng, player, cities, false)?,
+                        "disaster".to_string(),
+                    )
+                };
+                let title = match t.kind {
+                    AnchorKind::City => format!("Aid {} after the {what}", t.name),
+                    _ => format!("Search {} for survivors", t.name),
+                };
+                (t, title)
+            }
+            QuestType::Defense => {
+                let threatened: Vec<&QuestAnchor> = cities(&self.anchors)
+                    .filter(free)
+                    .filter(|a| !real || self.enemy_of(a).is_some())
+                    .collect();
+                let t = pick(&mut self.rng, player, threatened, real)?;
+                let title = match self.enemy_of(&t) {
+                    Some(enemy) => format!("Defend {} against {enemy}", t.name),
+                    None => format!("Defend {}", t.name),
+                };
+                (t, title)
+            }
+            QuestType::ResourceStabilization => {
+                // сначала земли, пострадавшие от пожаров, пепла и наводнений
+                let damaged = if real {
+                    self.recent
+                        .iter()
+                        .filter(|e| {
+                            !matches!(
+                                e.event.kind,
+                                WorldEventKind::Catastrophe(_) | WorldEventKind::Raid
+                            ) && !taken.contains(&event_anchor_id(e).as_str())
+                        })
+                        .min_by(|a, b| {
+                            dist2(player, a.event.x, a.event.y)
+                                .total_cmp(&dist2(player, b.event.x, b.event.y))
+                        })
+                        .map(|e| self.event_site(e))
+                } else {
+                    None
+                };
+                let t = match damaged {
+                    Some(t) => t,
+                    None => {
+                        let lands: Vec<&QuestAnchor> = self
+                            .anchors
+                            .iter()
+                            .filter(|a| {
+                                matches!(
+                                    a.kind,
+                                    AnchorKind::Feature(
+                                        FeatureKind::Forest
+                                            | FeatureKind::River
+                                            | FeatureKind::Lake
+                                            | FeatureKind::Desert
+                                    )
+                                )
+                            })
+                            .filter(free)
+                            .collect();
+                        pick(&mut self.rng, player, lands, real)?
+                    }
+                };
+                let title = format!("Restore the land around {}", t.name);
+                (t, title)
+            }
+        };
+
+        let objective = QuestObjective {
+            action: builtin_action(quest_type).to_string(),
+            description: title.clone(),
+            target: target.clone(),
+        };
+        Some(Quest {
+            id: self.next_id(),
+            player_id: player.id.clone(),
+            quest_type,
+            title,
+            target,
+            objectives: vec![objective],
+            rewards: Vec::new(),
+            template_id: None,
+            giver_faction_id: self.faction_at(player.x, player.y),
+            issued_at_minutes: self.now_minutes,
+            status: QuestStatus::Active,
+        })
+    }
+
+    fn next_id(&mut self) -> String {
+        let id = format!("quest_{}", self.next_quest_id);
+        self.next_quest_id += 1;
+        id
+    }
+
+    /// Задание по случайному (с учётом весов) шаблону типа `quest_type`;
+    /// `None`, если шаблонов нет или для какого-то шага не нашлось цели
+    fn make_template_quest(
+        &mut self,
+        player: &PlayerState,
+        quest_type: QuestType,
+    ) -> Option<Quest> {
+        let candidates: Vec<&QuestTemplate> = self
+            .templates
+            .iter()
+            .filter(|t| t.quest_type == quest_type && t.weight > 0.0)
+            .collect();
+        let total: f32 = candidates.iter().map(|t| t.weight).sum();
+        if candidates.is_empty() {
+            return None;
+        }
+        let mut roll = self.rng.next_f64() as f32 * total;
+        let template = candidates
+            .iter()
+            .find(|t| {
+                roll -= t.weight;
+                roll < 0.0
+            })
+            .unwrap_or(&candidates[candidates.len() - 1]);
+        let template = (*template).clone();
+
+        let giver = self.faction_at(player.x, player.y);
+        let taken: Vec<String> = self
+            .quests
+            .iter()
+            .filter(|q| q.player_id == player.id)
+            .map(|q| q.target.id.clone())
+            .collect();
+
+        let mut objectives: Vec<QuestObjective> = Vec::new();
+        for obj in &template.objectives {
+            let target = match obj.target {
+                TargetSelector::Same => objectives.last()?.target.clone(),
+                TargetSelector::Pick {
+                    order,
+                    kind,
+                    territory,
+                    near_player,
+                } => {
+                    let (ox, oy) = match objectives.last() {
+                        Some(prev) if !near_player => (prev.target.x as f32, prev.target.y as f32),
+                        _ => (player.x, player.y),
+                    };
+                    let mut found = self.select_targets(kind);
+                    // без учёта состояния мира территория не важна, а цель случайна
+                    let real = self.config.quest_generation.use_real_world_state;
+                    if let (Some(filter), true) = (territory, real) {
+                        found.retain(|a| self.territory_matches(a, giver.as_deref(), filter));
+                    }
+                    // первым шагом не ведём туда, куда игрока уже посылали
+                    if objectives.is_empty() {
+                        found.retain(|a| !taken.contains(&a.id));
+                    }
+                    let order = if real { order } else { SelectorOrder::Random };
+                    self.order_pick(found, order, ox, oy)?
+                }
+            };
+            let description = match &obj.description {
+                Some(d) => d.replace("{target}", &target.name),
+                None => capitalize(&format!("{} {}", obj.action, target.name)),
+            };
+            objectives.push(QuestObjective {
+                action: obj.action.clone(),
+                description,
+                target,
+            });
+        }
+
+        let mut rewards = Vec::new();
+        for r in &template.rewards {
+            if self.rng.chance(r.chance.unwrap_or(1.0) as f64) {
+                let amount = r.min + self.rng.below((r.max - r.min) as usize + 1) as u32;
+                rewards.push(QuestReward {
+                    item: r.item.clone(),
+                    amount,
+                });
+            }
+        }
+
+        let target = objectives[0].target.clone();
+        let faction_name = giver
+            .as_ref()
+            .and_then(|id| self.faction_names.get(id))
+            .map(String::as_str)
+            .unwrap_or("the locals");
+        let title = template
+            .title
+            .replace("{target}", &target.name)
+            .replace("{faction}", faction_name);
+        Some(Quest {
+            id: self.next_id(),
+            player_id: player.id.clone(),
+            quest_type,
+            title,
+            target,
+            objectives,
+            rewards,
+            template_id: Some(template.id.clone()),
+            giver_faction_id: giver,
+            issued_at_minutes: self.now_minutes,
+            status: QuestStatus::Active,
+        })
+    }
+
+    /// Все места, подходящие под вид селектора
+    fn select_targets(&self, kind: SelectorKind) -> Vec<QuestAnchor> {
+        if kind == SelectorKind::EventSite {
+            return self.recent.iter().map(|e| self.event_site(e)).collect();
+        }
+        self.anchors
+            .iter()
+            .filter(|a| match (kind, a.kind) {
+                (SelectorKind::Any, _) => true,
+                (SelectorKind::City, AnchorKind::City) => true,
+                (SelectorKind::Ruin, AnchorKind::Ruin) => true,
+                (SelectorKind::Feature(None), AnchorKind::Feature(_)) => true,
+                (SelectorKind::Feature(Some(want)), AnchorKind::Feature(f)) => want == f,
+                (SelectorKind::CatastropheSite, AnchorKind::CatastropheSite) => true,
+                _ => false,
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn order_pick(
+        &mut self,
+        found: Vec<QuestAnchor>,
+        order: SelectorOrder,
+        ox: f32,
+        oy: f32,
+    ) -> Option<QuestAnchor> {
+        let d = |a: &QuestAnchor| (a.x as f32 - ox).hypot(a.y as f32 - oy);
+        match order {
+            SelectorOrder::Nearest => found.into_iter().min_by(|a, b| d(a).total_cmp(&d(b))),
+            SelectorOrder::Farthest => found.into_iter().max_by(|a, b| d(a).total_cmp(&d(b))),
+            SelectorOrder::Random if found.is_empty() => None,
+            SelectorOrder::Random => {
+                let i = self.rng.below(found.len());
+                found.into_iter().nth(i)
+            }
+        }
+    }
+
+    /// id фракции — хозяина клетки
+    fn faction_at(&self, x: f32, y: f32) -> Option<String> {
+        if self.width == 0 || x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let (x, y) = (x as u32, y as u32);
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let owner = self
+            .territory
+            .get((y * self.width + x) as usize)
+            .copied()??;
+        self.faction_ids.get(owner as usize).cloned()
+    }
+
+    fn territory_matches(
+        &self,
+        anchor: &QuestAnchor,
+        giver: Option<&str>,
+        filter: TerritoryFilter,
+    ) -> bool {
+        let owner = self.faction_at(anchor.x as f32, anchor.y as f32);
+        let Some(owner) = owner else {
+            return filter == TerritoryFilter::Unclaimed;
+        };
+        let Some(giver) = giver else {
+            return false;
+        };
+        if owner == giver {
+            return filter == TerritoryFilter::Own;
+        }
+        let stance = self
+            .relations
+            .as_ref()
+            .and_then(|r| r.get_by_id(giver, &owner))
+            .map(|r| r.stance)
+            .unwrap_or(Stance::Neutral);
+        match filter {
+            TerritoryFilter::Friendly => matches!(stance, Stance::Allied | Stance::Friendly),
+            TerritoryFilter::Neutral => stance == Stance::Neutral,
+            TerritoryFilter::Hostile => matches!(stance, Stance::Rival | Stance::AtWar),
+            TerritoryFilter::Own | TerritoryFilter::Unclaimed => false,
+        }
+    }
+
+    /// Ближайший к игроку город у свежего бедствия, иначе само место бедствия
+    /// или место катастрофы из истории
+    fn rescue_target(&self, player: &PlayerState, taken: &[&str]) -> Option<(QuestAnchor, String)> {
+        let fresh = self
+            .recent
+            .iter()
+            .min_by(|a, b| {
+                dist2(player, a.event.x, a.event.y).total_cmp(&dist2(player, b.event.x, b.event.y))
+            })
+            .and_then(|e| {
+                let reach = (e.event.radius * 1.5).powi(2);
+                let city = cities(&self.anchors)
+                    .filter(|c| !taken.contains(&c.id.as_str()))
+                    .filter(|c| {
+                        let dx = c.x as f32 - e.event.x as f32;
+                        let dy = c.y as f32 - e.event.y as f32;
+                        dx * dx + dy * dy <= reach
+                    })
+                    .min_by(|a, b| dist2(player, a.x, a.y).total_cmp(&dist2(player, b.x, b.y)))
+                    .cloned();
+                let target = city.unwrap_or_else(|| self.event_site(e));
+                (!taken.contains(&target.id.as_str()))
+                    .then(|| (target, event_noun(e.event.kind).to_string()))
+            });
+        fresh.or_else(|| {
+            self.anchors
+                .iter()
+                .filter(|a| a.kind == AnchorKind::CatastropheSite)
+                .filter(|a| !taken.contains(&a.id.as_str()))
+                .min_by(|a, b| dist2(player, a.x, a.y).total_cmp(&dist2(player, b.x, b.y)))
+                .map(|a| (a.clone(), "disaster".to_string()))
+        })
+    }
+
+    fn event_site(&self, e: &RecentEvent) -> QuestAnchor {
+        let near = cities(&self.anchors)
+            .min_by(|a, b| {
+                let da = (a.x as f32 - e.event.x as f32).hypot(a.y as f32 - e.event.y as f32);
+                let db = (b.x as f32 - e.event.x as f32).hypot(b.y as f32 - e.event.y as f32);
+                da.total_cmp(&db)
+            })
+            .map(|c| format!(" near {}", c.name))
+            .unwrap_or_default();
+        QuestAnchor {
+            kind: AnchorKind::CatastropheSite,
+            id: event_anchor_id(e),
+            name: format!("the {} site{near}", event_noun(e.event.kind)),
+            faction_id: None,
+            x: e.event.x,
+            y: e.event.y,
+        }
+    }
+
+    /// Худший враг (война, затем соперничество) фракции города
+    fn enemy_of(&self, anchor: &QuestAnchor) -> Option<String> {
+        let rel = self.relations.as_ref()?;
+        let own = anchor.faction_id.as_deref()?;
+        rel.faction_ids
+            .iter()
+            .filter(|other| other.as_str() != own)
+            .filter_map(|other| {
+                let r = rel.get_by_id(own, other)?;
+                matches!(r.stance, Stance::AtWar | Stance::Rival).then_some((r.score, other))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, id)| self.faction_names.get(id).cloned().unwrap_or(id.clone()))
+    }
+}
+
+/// Ближайшая к игроку цель или, без учёта состояния мира, случайная
+fn pick(
+    rng: &mut SplitMix64,
+    player: &PlayerState,
+    candidates: Vec<&QuestAnchor>,
+    nearest: bool,
+) -> Option<QuestAnchor> {
+    if candidates.is_empty() {
+        return None;
+    }
+    if nearest {
+        candidates
+            .into_iter()
+            .min_by(|a, b| dist2(player, a.x, a.y).total_cmp(&dist2(player, b.x, b.y)))
+            .cloned()
+    } else {
+        let i = rng.below(candidates.len());
+        Some(candidates[i].clone())
+    }
+}
+
+fn cities(anchors: &[QuestAnchor]) -> impl Iterator<Item = &QuestAnchor> {
+    anchors.iter().filter(|a| a.kind == AnchorKind::City)
+}
+
+fn dist2(p: &PlayerState, x: u32, y: u32) -> f32 {
+    let dx = x as f32 - p.x;
+    let dy = y as f32 - p.y;
+    dx * dx + dy * dy
+}
+
+fn builtin_action(quest_type: QuestType) -> &'static str {
+    match quest_type {
+        QuestType::Exploration => "explore",
+        QuestType::Rescue => "rescue",
+        QuestType::Defense => "defend",
+        QuestType::ResourceStabilization => "restore",
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn event_anchor_id(e: &RecentEvent) -> String {
+    format!("event:{}:{}:{}", e.minutes, e.event.x, e.event.y)
+}
+
+fn event_noun(kind: WorldEventKind) -> &'static str {
+    match kind {
+        WorldEventKind::Wildfire => "wildfire",
+        WorldEventKind::AshWinter => "ash winter",
+        WorldEventKind::Flood => "flood",
+        WorldEventKind::Storm => "storm",
+        WorldEventKind::Raid => "raid",
+        WorldEventKind::Catastrophe(t) => catastrophe_noun(t),
+    }
+}
+
+fn catastrophe_noun(kind: CatastropheType) -> &'static str {
+    match kind {
+        CatastropheType::Earthquake => "earthquake",
+        CatastropheType::VolcanicEruption => "eruption",
+        CatastropheType::MeteorImpact => "meteor impact",
+        CatastropheType::Tsunami => "tsunami",
+        CatastropheType::Tornado => "tornado",
+        CatastropheType::Hurricane => "hurricane",
+    }
+}
+
+/// Доля игроков в центре события, гибнущих при полной силе
+fn player_lethality(kind: WorldEventKind) -> f32 {
+    match kind {
+        WorldEventKind::Raid => 0.5,
+        WorldEventKind::Storm => 0.2,
+        WorldEventKind::Wildfire => 0.6,
+        WorldEventKind::AshWinter => 0.3,
+        WorldEventKind::Flood => 0.4,
+        WorldEventKind::Catastrophe(_) => 0.9,
+    }
+}
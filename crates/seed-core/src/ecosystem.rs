@@ -0,0 +1,598 @@
+//! Экосистемы: популяции видов из `EcosystemsConfig` по регионам карты.
+//! Травоядные растут логистически до ёмкости, которую задаёт растительность
+//! биомов, хищники живут за счёт своей добычи по пищевой сети
+//! (модель Лотки — Вольтерры),
+//! мигрирующие виды расходятся в соседние регионы. Шаг модели —
+//! `time_step_minutes`. Пожары, пепельные зимы и наводнения из шины событий
+//! выбивают животных и растительность, охота игроков снимается через `harvest`.
+//! Травоядные сверх ёмкости вытаптывают растительность, а с ней падает и
+//! ёмкость — при долгом перевыпасе регион превращается в пыльную пустошь
+//! и отрастает годами.
+
+use crate::biome::{BiomeMap, ClimateMap};
+use crate::calendar::Calendar;
+use crate::events::{EventBus, WorldEvent, WorldEventKind};
+use crate::foodweb::{FoodWeb, TrophicRole};
+use seed_config::{SpeciesConfig, WorldConfig};
+
+/// Скорость роста травоядных, 1/год
+const HERBIVORE_GROWTH: f64 = 0.8;
+/// Смертность хищников без добычи, 1/год
+const PREDATOR_MORTALITY: f64 = 0.3;
+/// Доля ёмкости, на которой травоядные держатся при заданной плотности хищников
+const PREY_EQUILIBRIUM: f64 = 0.6;
+/// Качество местообитания вне предпочитаемых биомов
+const FOREIGN_BIOME_HABITAT: f32 = 0.2;
+/// Доля разницы плотностей с соседним регионом, уходящая мигрантами за год
+const MIGRATION_RATE: f64 = 0.05;
+
+/// Скорость восстановления повреждённой растительности, 1/год
+const VEGETATION_REGROWTH: f64 = 0.3;
+/// Доля растительности, теряемая за год на каждую единицу превышения ёмкости
+const OVERGRAZING_DAMAGE: f64 = 0.5;
+/// Остаток растительности на вытоптанной земле, от которого она отрастает
+const VEGETATION_FLOOR: f64 = 0.02;
+
+/// Вид считается присутствующим в клетке от этой доли своей плотности из конфига
+const PRESENCE_FRACTION: f32 = 0.05;
+/// За сколько градусов вне климата предпочитаемых биомов плотность падает в e раз
+const TEMPERATURE_TOLERANCE_C: f32 = 5.0;
+/// То же для влажности
+const HUMIDITY_TOLERANCE: f32 = 0.15;
+
+#[derive(Debug, Clone)]
+pub struct SpeciesState {
+    pub id: String,
+    pub role: TrophicRole,
+    pub migrates: bool,
+    /// Качество местообитания по клеткам карты (0..1)
+    habitat: Vec<f32>,
+    /// Среднее качество местообитания по регионам
+    suitability: Vec<f64>,
+    /// Ёмкость по растительности, особей/км² по регионам (для хищников — 0)
+    capacity: Vec<f64>,
+    /// Текущая плотность, особей/км² по регионам
+    pub density: Vec<f64>,
+    /// Интенсивность охоты и доля съеденного, идущая в прирост
+    attack: f64,
+    conversion: f64,
+}
+
+/// Численность всех видов на момент времени
+#[derive(Debug, Clone)]
+pub struct EcosystemSample {
+    pub minutes: u64,
+    /// Особей каждого вида на всей карте (порядок как в `Ecosystem::species`)
+    pub totals: Vec<f64>,
+    /// Среднее состояние растительности по регионам
+    pub mean_vegetation: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ecosystem {
+    pub width: u32,
+    pub height: u32,
+    /// Сторона региона, клеток
+    pub region_cells: u32,
+    pub regions_x: u32,
+    pub regions_y: u32,
+    pub species: Vec<SpeciesState>,
+    /// Кто кого ест (индексы как в `species`)
+    pub food_web: FoodWeb,
+    /// Прошедшее модельное время
+    pub minutes: u64,
+    pub step_minutes: u32,
+    /// Длина года планеты — скорости в конфиге заданы за год
+    minutes_per_year: f64,
+    /// Временной ряд численностей
+    pub series: Vec<EcosystemSample>,
+    /// Состояние растительности по регионам (1 — нетронута); ёмкость
+    /// травоядных пропорциональна ему
+    pub vegetation: Vec<f64>,
+    /// Плотность растительности биомов по клеткам карты
+    base_vegetation: Vec<f32>,
+    /// Площадь региона, км²
+    region_area_km2: Vec<f64>,
+    /// Сколько событий шины уже прочитано
+    event_cursor: usize,
+}
+
+impl Ecosystem {
+    /// Расселяет виды по регионам с плотностью из конфига, взвешенной
+    /// качеством местообитания
+    pub fn new(cfg: &WorldConfig, bm: &BiomeMap) -> Self {
+        let eco = &cfg.ecosystems;
+        let (w, h) = (bm.width, bm.height);
+        let region_cells = match eco.simulation_scale.as_str() {
+            "local" => 8,
+            "global" => w.max(h),
+            _ => 32,
+        }
+        .clamp(1, w.max(h).max(1));
+        let regions_x = w.div_ceil(region_cells);
+        let regions_y = h.div_ceil(region_cells);
+        let region_count = (regions_x * regions_y) as usize;
+
+        let km_per_cell = cfg.scale.region_size_km / w.max(1) as f64;
+        let cell_area = km_per_cell * km_per_cell;
+        let region_of = |i: usize| {
+            let (x, y) = (i as u32 % w, i as u32 / w);
+            ((y / region_cells) * regions_x + x / region_cells) as usize
+        };
+
+        let vegetation = vegetation_map(cfg, bm);
+
+        let mut region_cells_count = vec![0usize; region_count];
+        for i in 0..(w * h) as usize {
+            region_cells_count[region_of(i)] += 1;
+        }
+        let region_area_km2: Vec<f64> = region_cells_count
+            .iter()
+            .map(|&n| n as f64 * cell_area)
+            .collect();
+
+        let food_web = FoodWeb::from_config(eco);
+        let mut species: Vec<SpeciesState> = eco
+            .species_definitions
+            .iter()
+            .zip(&food_web.roles)
+            .map(|(def, &role)| {
+                let habitat = habitat_map(cfg, bm, def, &vegetation);
+
+                let mut suitability = vec![0.0; region_count];
+                for (i, &q) in habitat.iter().enumerate() {
+                    suitability[region_of(i)] += q as f64;
+                }
+                for (v, &n) in suitability.iter_mut().zip(&region_cells_count) {
+                    *v /= n.max(1) as f64;
+                }
+                // ёмкость региона: плотность вида на средней пригодности его клеток
+                let capacity: Vec<f64> = suitability
+                    .iter()
+                    .map(|q| q * role.grazes() * def.population_density_per_km2)
+                    .collect();
+
+                let density = suitability
+                    .iter()
+                    .map(|q| q * def.population_density_per_km2)
+                    .collect();
+
+                SpeciesState {
+                    id: def.id.clone(),
+                    role,
+                    migrates: def.migration_enabled,
+                    habitat,
+                    suitability,
+                    capacity,
+                    density,
+                    attack: 0.0,
+                    conversion: 0.0,
+                }
+            })
+            .collect();
+
+        // Калибровка по пищевой сети: в среднем по обитаемым регионам каждый
+        // охотник при стартовой плотности держит свою добычу на PREY_EQUILIBRIUM
+        // ёмкости и сам не растёт и не вымирает
+        let habitable: Vec<usize> = (0..region_count)
+            .filter(|&r| species.iter().any(|s| s.capacity[r] > 0.0))
+            .collect();
+        let mean_over_habitable = |f: &dyn Fn(usize) -> f64| {
+            habitable.iter().map(|&r| f(r)).sum::<f64>() / habitable.len().max(1) as f64
+        };
+        let mut calibration = vec![(0.0, 0.0); species.len()];
+        for (p, cal) in calibration.iter_mut().enumerate() {
+            if species[p].role.hunts() == 0.0 {
+                continue;
+            }
+            let prey: Vec<usize> = food_web.prey_of(p).collect();
+            // травоядная добыча — по ёмкости, охотники — по стартовой плотности
+            let prey_ref = mean_over_habitable(&|r| {
+                prey.iter()
+                    .map(|&q| match species[q].capacity[r] {
+                        k if k > 0.0 => k,
+                        _ => species[q].density[r],
+                    })
+                    .sum()
+            });
+            // все, кто охотится на ту же добычу (включая сам вид)
+            let rivals: Vec<usize> = (0..species.len())
+                .filter(|&o| food_web.prey_of(o).any(|q| prey.contains(&q)))
+                .collect();
+            let predator_ref = mean_over_habitable(&|r| {
+                rivals
+                    .iter()
+                    .map(|&o| species[o].role.hunts() * species[o].density[r])
+                    .sum()
+            });
+            if prey_ref > 0.0 && predator_ref > 0.0 {
+                let attack = HERBIVORE_GROWTH * (1.0 - PREY_EQUILIBRIUM) / predator_ref;
+                let conversion = PREDATOR_MORTALITY / (attack * PREY_EQUILIBRIUM * prey_ref);
+                *cal = (attack * species[p].role.hunts(), conversion);
+            }
+        }
+        for (s, (attack, conversion)) in species.iter_mut().zip(calibration) {
+            s.attack = attack;
+            s.conversion = conversion;
+        }
+
+        let mut eco = Self {
+            width: w,
+            height: h,
+            region_cells,
+            regions_x,
+            regions_y,
+            species,
+            food_web,
+            minutes: 0,
+            step_minutes: eco.time_step_minutes.max(1),
+            minutes_per_year: Calendar::from_config(cfg).minutes_per_year(),
+            series: Vec::new(),
+            vegetation: vec![1.0; region_count],
+            base_vegetation: vegetation,
+            region_area_km2,
+            event_cursor: 0,
+        };
+        eco.record();
+        eco
+    }
+
+    /// Один шаг модели длиной `time_step_minutes`
+    pub fn step(&mut self) {
+        let dt = self.step_minutes as f64 / self.minutes_per_year;
+        let regions = self.region_area_km2.len();
+
+        let mut dn = vec![0.0; self.species.len()];
+        for r in 0..regions {
+            for (d, s) in dn.iter_mut().zip(&self.species) {
+                let n = s.density[r];
+                *d = 0.0;
+                if s.role.grazes() > 0.0 {
+                    let k = s.capacity[r] * self.vegetation[r];
+                    *d += if k > 0.0 {
+                        HERBIVORE_GROWTH * s.role.grazes() * n * (1.0 - n / k)
+                    } else {
+                        -HERBIVORE_GROWTH * n
+                    };
+                }
+                if s.role == TrophicRole::Carnivore {
+                    *d -= PREDATOR_MORTALITY * n;
+                }
+            }
+            for link in &self.food_web.links {
+                let (p, q) = (&self.species[link.predator], &self.species[link.prey]);
+                let eaten = p.attack * p.density[r] * q.density[r];
+                dn[link.prey] -= eaten;
+                dn[link.predator] += p.conversion * eaten;
+            }
+            for (s, d) in self.species.iter_mut().zip(&dn) {
+                s.density[r] = (s.density[r] + d * dt).max(0.0);
+            }
+        }
+
+        self.graze(dt);
+
+        self.migrate(dt);
+        self.minutes += self.step_minutes as u64;
+    }
+
+    /// Выпас: нагрузка травоядных сверх ёмкости вытаптывает растительность,
+    /// без перевыпаса она отрастает (медленно, если почти уничтожена)
+    fn graze(&mut self, dt: f64) {
+        for (r, v) in self.vegetation.iter_mut().enumerate() {
+            let load: f64 = self
+                .species
+                .iter()
+                .filter(|s| s.capacity[r] > 0.0)
+                .map(|s| s.density[r] / (s.capacity[r] * *v).max(1e-9))
+                .sum();
+            let damage = OVERGRAZING_DAMAGE * (load - 1.0).max(0.0) * *v;
+            let regrowth = VEGETATION_REGROWTH * v.max(VEGETATION_FLOOR) * (1.0 - *v);
+            *v = (*v + (regrowth - damage) * dt).clamp(VEGETATION_FLOOR, 1.0);
+        }
+    }
+
+    /// Текущая плотность растительности по клеткам карты: растительность
+    /// биома с учётом выпаса и бедствий в регионе
+    pub fn vegetation_raster(&self) -> Vec<f32> {
+        self.base_vegetation
+            .iter()
+            .enumerate()
+            .map(|(i, &veg)| {
+                let (x, y) = (i as u32 % self.width, i as u32 / self.width);
+                let r = self.region_at(x, y).unwrap_or(0);
+                veg * self.vegetation[r] as f32
+            })
+            .collect()
+    }
+
+    /// Регион, в который попадает клетка карты
+    pub fn region_at(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let rc = self.region_cells;
+        Some(((y / rc) * self.regions_x + x / rc) as usize)
+    }
+
+    /// Применяет новые события из шины
+    pub fn sync_events(&mut self, bus: &EventBus) {
+        let mut cursor = self.event_cursor;
+        let events = bus.read(&mut cursor);
+        self.event_cursor = cursor;
+        for e in events {
+            self.apply_event(e);
+        }
+    }
+
+    /// Гибель животных и ущерб растительности в охвате события;
+    /// сила спадает от центра к краю
+    pub fn apply_event(&mut self, event: &WorldEvent) {
+        // доля гибнущих животных и выгорающей растительности при полной силе
+        let (lethality, scorch) = match event.kind {
+            WorldEventKind::Wildfire => (0.5, 0.8),
+            WorldEventKind::AshWinter => (0.2, 0.6),
+            WorldEventKind::Flood => (0.3, 0.3),
+            WorldEventKind::Storm => (0.05, 0.15),
+            WorldEventKind::Raid => (0.0, 0.0),
+            WorldEventKind::Catastrophe(_) => (0.1, 0.0),
+        };
+        let rc = self.region_cells as f32;
+        for ry in 0..self.regions_y {
+            for rx in 0..self.regions_x {
+                // расстояние от центра события до ближайшей точки региона
+                let x0 = rx as f32 * rc;
+                let y0 = ry as f32 * rc;
+                let dx = (x0 - event.x as f32)
+                    .max(event.x as f32 - (x0 + rc))
+                    .max(0.0);
+                let dy = (y0 - event.y as f32)
+                    .max(event.y as f32 - (y0 + rc))
+                    .max(0.0);
+                let falloff = 1.0 - dx.hypot(dy) / event.radius.max(1.0);
+                if falloff <= 0.0 {
+                    continue;
+                }
+                let hit = (event.intensity * falloff) as f64;
+                let r = (ry * self.regions_x + rx) as usize;
+                for s in &mut self.species {
+                    s.density[r] *= 1.0 - lethality * hit;
+                }
+                self.vegetation[r] =
+                    (self.vegetation[r] * (1.0 - scorch * hit)).max(VEGETATION_FLOOR);
+            }
+        }
+    }
+
+    /// Охота: снимает до `amount` особей вида в регионе. Возвращает, сколько
+    /// удалось добыть, или `None` для неизвестного вида или региона
+    pub fn harvest(&mut self, species: &str, region: usize, amount: f64) -> Option<f64> {
+        let area = *self.region_area_km2.get(region)?;
+        let s = self.species.iter_mut().find(|s| s.id == species)?;
+        let available = s.density[region] * area;
+        let taken = amount.clamp(0.0, available);
+        if area > 0.0 {
+            s.density[region] = (available - taken) / area;
+        }
+        Some(taken)
+    }
+
+    /// `steps` шагов с записью временного ряда каждые `sample_every` шагов
+    pub fn run(&mut self, steps: u32, sample_every: u32) {
+        for i in 1..=steps {
+            self.step();
+            if sample_every > 0 && i % sample_every == 0 {
+                self.record();
+            }
+        }
+    }
+
+    /// Численность вида на всей карте
+    pub fn total_population(&self, species: usize) -> f64 {
+        self.species[species]
+            .density
+            .iter()
+            .zip(&self.region_area_km2)
+            .map(|(d, a)| d * a)
+            .sum()
+    }
+
+    /// Временной ряд вида: (минуты, особей)
+    pub fn time_series(&self, species: usize) -> Vec<(u64, f64)> {
+        self.series
+            .iter()
+            .map(|s| (s.minutes, s.totals[species]))
+            .collect()
+    }
+
+    /// Текущая плотность вида по клеткам карты, особей/км²: плотность региона,
+    /// перераспределённая по качеству местообитания внутри него
+    pub fn population_raster(&self, species: usize) -> Vec<f32> {
+        let s = &self.species[species];
+        let (w, rc) = (self.width, self.region_cells);
+        let region_of = |i: usize| {
+            let (x, y) = (i as u32 % w, i as u32 / w);
+            ((y / rc) * self.regions_x + x / rc) as usize
+        };
+
+        let mut mean_habitat = vec![0.0f64; s.density.len()];
+        let mut counts = vec![0usize; s.density.len()];
+        for (i, &q) in s.habitat.iter().enumerate() {
+            mean_habitat[region_of(i)] += q as f64;
+            counts[region_of(i)] += 1;
+        }
+        for (m, &n) in mean_habitat.iter_mut().zip(&counts) {
+            *m /= n.max(1) as f64;
+        }
+
+        s.habitat
+            .iter()
+            .enumerate()
+            .map(|(i, &q)| {
+                let r = region_of(i);
+                if mean_habitat[r] > 0.0 {
+                    (s.density[r] * q as f64 / mean_habitat[r]) as f32
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    fn record(&mut self) {
+        let totals = (0..self.species.len())
+            .map(|s| self.total_population(s))
+            .collect();
+        let mean_vegetation =
+            self.vegetation.iter().sum::<f64>() / self.vegetation.len().max(1) as f64;
+        self.series.push(EcosystemSample {
+            minutes: self.minutes,
+            totals,
+            mean_vegetation,
+        });
+    }
+
+    /// Мигрирующие виды выравнивают плотность с соседними регионами,
+    /// где для них есть местообитание
+    fn migrate(&mut self, dt: f64) {
+        let (rx, ry) = (self.regions_x as usize, self.regions_y as usize);
+        let k = (MIGRATION_RATE * dt).min(0.25);
+        for s in self.species.iter_mut().filter(|s| s.migrates) {
+            let old = s.density.clone();
+            for y in 0..ry {
+                for x in 0..rx {
+                    let i = y * rx + x;
+                    for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+                        if nx >= rx || ny >= ry {
+                            continue;
+                        }
+                        let j = ny * rx + nx;
+                        if s.suitability[i] > 0.0 && s.suitability[j] > 0.0 {
+                            let flow = (old[j] - old[i]) * k;
+                            s.density[i] += flow;
+                            s.density[j] -= flow;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Ареал вида: ожидаемая плотность и присутствие по клеткам карты
+#[derive(Debug, Clone)]
+pub struct SpeciesDistribution {
+    pub species_id: String,
+    pub width: u32,
+    pub height: u32,
+    /// Особей/км²
+    pub density: Vec<f32>,
+    pub presence: Vec<bool>,
+}
+
+impl SpeciesDistribution {
+    pub fn density_at(&self, x: u32, y: u32) -> f32 {
+        self.density[(y * self.width + x) as usize]
+    }
+
+    /// Доля клеток карты, где вид встречается
+    pub fn range_share(&self) -> f32 {
+        let n = self.presence.iter().filter(|&&p| p).count();
+        n as f32 / self.presence.len().max(1) as f32
+    }
+}
+
+/// Ареалы видов из конфига: плотность `populationDensityPerKm2` в
+/// предпочитаемых биомах, ослабленная в чужих биомах, по скудной
+/// растительности и вне климата (температуры и влажности) предпочитаемых биомов
+pub fn generate_species_distribution(
+    cfg: &WorldConfig,
+    bm: &BiomeMap,
+    climate: &ClimateMap,
+) -> Vec<SpeciesDistribution> {
+    let vegetation = vegetation_map(cfg, bm);
+
+    cfg.ecosystems
+        .species_definitions
+        .iter()
+        .map(|def| {
+            let habitat = habitat_map(cfg, bm, def, &vegetation);
+
+            // климатическая ниша — объединение диапазонов предпочитаемых биомов
+            let niche = cfg
+                .biomes
+                .iter()
+                .filter(|b| def.preferred_biomes.contains(&b.id))
+                .map(|b| {
+                    let r = &b.climate_range;
+                    (
+                        r.temperature_c[0] as f32,
+                        r.temperature_c[1] as f32,
+                        r.humidity[0] as f32,
+                        r.humidity[1] as f32,
+                    )
+                })
+                .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)));
+
+            let density: Vec<f32> = habitat
+                .iter()
+                .enumerate()
+                .map(|(i, &q)| {
+                    let fit = niche.map_or(1.0, |(t0, t1, h0, h1)| {
+                        let t = climate.temperature_c.get(i).copied().unwrap_or(t0);
+                        let hu = climate.humidity.get(i).copied().unwrap_or(h0);
+                        let dt = (t0 - t).max(t - t1).max(0.0);
+                        let dh = (h0 - hu).max(hu - h1).max(0.0);
+                        (-dt / TEMPERATURE_TOLERANCE_C - dh / HUMIDITY_TOLERANCE).exp()
+                    });
+                    q * fit * def.population_density_per_km2 as f32
+                })
+                .collect();
+            let threshold = def.population_density_per_km2 as f32 * PRESENCE_FRACTION;
+            let presence = density.iter().map(|&d| d > 0.0 && d >= threshold).collect();
+
+            SpeciesDistribution {
+                species_id: def.id.clone(),
+                width: bm.width,
+                height: bm.height,
+                density,
+                presence,
+            }
+        })
+        .collect()
+}
+
+fn vegetation_map(cfg: &WorldConfig, bm: &BiomeMap) -> Vec<f32> {
+    bm.indices
+        .iter()
+        .map(|bi| {
+            bi.and_then(|bi| cfg.biomes.get(bi as usize))
+                .map_or(0.0, |b| b.vegetation_density.clamp(0.0, 1.0))
+        })
+        .collect()
+}
+
+/// Пригодность клеток для вида: растительность биома, полная в предпочитаемых
+/// биомах и ослабленная в остальных; море и неопознанные клетки непригодны
+fn habitat_map(
+    cfg: &WorldConfig,
+    bm: &BiomeMap,
+    def: &SpeciesConfig,
+    vegetation: &[f32],
+) -> Vec<f32> {
+    bm.indices
+        .iter()
+        .zip(vegetation)
+        .map(|(bi, &veg)| {
+            let Some(biome) = bi.and_then(|bi| cfg.biomes.get(bi as usize)) else {
+                return 0.0;
+            };
+            let preference = if def.preferred_biomes.contains(&biome.id) {
+                1.0
+            } else {
+                FOREIGN_BIOME_HABITAT
+            };
+            veg.max(0.1) * preference
+        })
+        .collect()
+}
@@ -0,0 +1,114 @@
+//! Шина событий мира. Источники (катастрофы, сервер игры, директор)
+//! публикуют события, потребители (экосистема и др.) читают новые события
+//! со своего курсора. Журнал только дополняется, поэтому порядок чтения
+//! одинаков у всех и детерминирован.
+
+use crate::catastrophe::{Catastrophe, CatastropheType};
+use crate::geo::MapGeo;
+use seed_config::WorldConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldEventKind {
+    Wildfire,
+    /// Пепел и похолодание после извержения
+    AshWinter,
+    Flood,
+    /// Буря: ветер и ливень без долгих последствий
+    Storm,
+    /// Набег разбойников или враждебной фракции на поселение
+    Raid,
+    /// Катастрофа без особых последствий для живой природы
+    Catastrophe(CatastropheType),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldEvent {
+    pub kind: WorldEventKind,
+    /// Центр в клетках карты
+    pub x: u32,
+    pub y: u32,
+    /// Охват, клеток
+    pub radius: f32,
+    /// Сила в центре, 0..1
+    pub intensity: f32,
+}
+
+impl WorldEvent {
+    /// События для живой природы из катастрофы на карте `width`×`height`:
+    /// извержение — пепельная зима, цунами и ураган — наводнение,
+    /// метеорит — пожар вокруг кратера
+    pub fn from_catastrophe(
+        cfg: &WorldConfig,
+        cat: &Catastrophe,
+        width: u32,
+        height: u32,
+    ) -> Vec<WorldEvent> {
+        let geo = MapGeo::from_config(cfg, width, height);
+        let (x, y) = geo.cell_at(cat.position.into());
+        // радиус в клетках по меридиану: по параллели клетка у полюсов уже
+        let radius = (cat.radius_km / geo.km_per_cell_y()).max(1.0) as f32;
+        let event = |kind, radius: f32, intensity: f64| WorldEvent {
+            kind,
+            x,
+            y,
+            radius,
+            intensity: intensity.clamp(0.0, 1.0) as f32,
+        };
+
+        match cat.catastrophe_type {
+            // пепел расходится шире самого извержения
+            CatastropheType::VolcanicEruption => vec![event(
+                WorldEventKind::AshWinter,
+                radius * 3.0,
+                cat.magnitude / 8.0,
+            )],
+            CatastropheType::Tsunami | CatastropheType::Hurricane => {
+                vec![event(WorldEventKind::Flood, radius, cat.magnitude / 10.0)]
+            }
+            CatastropheType::MeteorImpact => vec![
+                event(
+                    WorldEventKind::Catastrophe(cat.catastrophe_type),
+                    radius,
+                    1.0,
+                ),
+                event(WorldEventKind::Wildfire, radius * 2.0, 0.8),
+            ],
+            t => vec![event(
+                WorldEventKind::Catastrophe(t),
+                radius,
+                cat.magnitude / 10.0,
+            )],
+        }
+    }
+}
+
+/// Журнал событий с чтением по курсору
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    events: Vec<WorldEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&mut self, event: WorldEvent) {
+        self.events.push(event);
+    }
+
+    /// События, опубликованные после `cursor`; курсор сдвигается на конец журнала
+    pub fn read(&self, cursor: &mut usize) -> &[WorldEvent] {
+        let from = (*cursor).min(self.events.len());
+        *cursor = self.events.len();
+        &self.events[from..]
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
@@ -0,0 +1,200 @@
+//! Пищевая сеть экосистемы: кто кого ест. Связи строятся по `trophicLevel`
+//! (хищники едят травоядных и всеядных, всеядные — травоядных) или по явному
+//! списку `prey` вида. Проверка находит неизвестные уровни и виды, а также
+//! охотников, которым некого есть.
+
+use crate::{CoreError, Result};
+use seed_config::EcosystemsConfig;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrophicRole {
+    Herbivore,
+    Carnivore,
+    /// Кормится растительностью вдвое медленнее травоядных и охотится вдвое реже хищников
+    Omnivore,
+}
+
+impl TrophicRole {
+    pub fn from_trophic_level(level: &str) -> Option<TrophicRole> {
+        match level {
+            "herbivore" => Some(TrophicRole::Herbivore),
+            "carnivore" | "predator" => Some(TrophicRole::Carnivore),
+            "omnivore" => Some(TrophicRole::Omnivore),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn grazes(self) -> f64 {
+        match self {
+            TrophicRole::Herbivore => 1.0,
+            TrophicRole::Omnivore => 0.5,
+            TrophicRole::Carnivore => 0.0,
+        }
+    }
+
+    pub(crate) fn hunts(self) -> f64 {
+        match self {
+            TrophicRole::Herbivore => 0.0,
+            TrophicRole::Omnivore => 0.5,
+            TrophicRole::Carnivore => 1.0,
+        }
+    }
+
+    /// Кого вид этой роли ест, если список `prey` не задан
+    fn default_prey(self, other: TrophicRole) -> bool {
+        match self {
+            TrophicRole::Herbivore => false,
+            TrophicRole::Omnivore => other == TrophicRole::Herbivore,
+            TrophicRole::Carnivore => other != TrophicRole::Carnivore,
+        }
+    }
+}
+
+/// Связь "хищник ест добычу" (индексы видов в `FoodWeb::species`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedingLink {
+    pub predator: usize,
+    pub prey: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FoodWebIssue {
+    /// `trophicLevel` не распознан; вид считается травоядным
+    UnknownTrophicLevel { species: String, level: String },
+    /// В `prey` указан несуществующий вид
+    UnknownPrey { species: String, prey: String },
+    /// Вид указал себя в `prey`
+    SelfPrey { species: String },
+    /// Травоядному задан список `prey`; он не охотится
+    HerbivoreWithPrey { species: String },
+    /// Охотнику некого есть — без травоядных он вымрет
+    NoPrey { species: String },
+}
+
+impl fmt::Display for FoodWebIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FoodWebIssue::UnknownTrophicLevel { species, level } => {
+                write!(f, "{species}: unknown trophic level '{level}'")
+            }
+            FoodWebIssue::UnknownPrey { species, prey } => {
+                write!(f, "{species}: prey '{prey}' is not a defined species")
+            }
+            FoodWebIssue::SelfPrey { species } => write!(f, "{species}: lists itself as prey"),
+            FoodWebIssue::HerbivoreWithPrey { species } => {
+                write!(f, "{species}: herbivore cannot have prey")
+            }
+            FoodWebIssue::NoPrey { species } => write!(f, "{species}: hunter has nothing to eat"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FoodWeb {
+    /// id видов в порядке `speciesDefinitions`
+    pub species: Vec<String>,
+    pub roles: Vec<TrophicRole>,
+    pub links: Vec<FeedingLink>,
+    pub issues: Vec<FoodWebIssue>,
+}
+
+impl FoodWeb {
+    pub fn from_config(eco: &EcosystemsConfig) -> Self {
+        let defs = &eco.species_definitions;
+        let mut issues = Vec::new();
+
+        let roles: Vec<TrophicRole> = defs
+            .iter()
+            .map(|d| {
+                TrophicRole::from_trophic_level(&d.trophic_level).unwrap_or_else(|| {
+                    issues.push(FoodWebIssue::UnknownTrophicLevel {
+                        species: d.id.clone(),
+                        level: d.trophic_level.clone(),
+                    });
+                    TrophicRole::Herbivore
+                })
+            })
+            .collect();
+
+        let mut links = Vec::new();
+        for (pi, def) in defs.iter().enumerate() {
+            let role = roles[pi];
+            match &def.prey {
+                Some(prey) if role == TrophicRole::Herbivore => {
+                    if !prey.is_empty() {
+                        issues.push(FoodWebIssue::HerbivoreWithPrey {
+                            species: def.id.clone(),
+                        });
+                    }
+                }
+                Some(prey) => {
+                    for id in prey {
+                        match defs.iter().position(|d| &d.id == id) {
+                            Some(qi) if qi == pi => issues.push(FoodWebIssue::SelfPrey {
+                                species: def.id.clone(),
+                            }),
+                            Some(qi) => links.push(FeedingLink {
+                                predator: pi,
+                                prey: qi,
+                            }),
+                            None => issues.push(FoodWebIssue::UnknownPrey {
+                                species: def.id.clone(),
+                                prey: id.clone(),
+                            }),
+                        }
+                    }
+                }
+                None => {
+                    for (qi, &other) in roles.iter().enumerate() {
+                        if qi != pi && role.default_prey(other) {
+                            links.push(FeedingLink {
+                                predator: pi,
+                                prey: qi,
+                            });
+                        }
+                    }
+                }
+            }
+
+            if role == TrophicRole::Carnivore && !links.iter().any(|l| l.predator == pi) {
+                issues.push(FoodWebIssue::NoPrey {
+                    species: def.id.clone(),
+                });
+            }
+        }
+
+        Self {
+            species: defs.iter().map(|d| d.id.clone()).collect(),
+            roles,
+            links,
+            issues,
+        }
+    }
+
+    pub fn prey_of(&self, predator: usize) -> impl Iterator<Item = usize> + '_ {
+        self.links
+            .iter()
+            .filter(move |l| l.predator == predator)
+            .map(|l| l.prey)
+    }
+
+    pub fn predators_of(&self, prey: usize) -> impl Iterator<Item = usize> + '_ {
+        self.links
+            .iter()
+            .filter(move |l| l.prey == prey)
+            .map(|l| l.predator)
+    }
+
+    /// Ошибка конфига со списком всех проблем сети, если они есть
+    pub fn validate(&self) -> Result<()> {
+        if self.issues.is_empty() {
+            return Ok(());
+        }
+        let list: Vec<String> = self.issues.iter().map(|i| i.to_string()).collect();
+        Err(CoreError::Config(format!(
+            "invalid food web: {}",
+            list.join("; ")
+        )))
+    }
+}
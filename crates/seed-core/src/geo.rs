@@ -0,0 +1,262 @@
+//! Координаты на планете: клетки карты ↔ широта/долгота ↔ локальные метры,
+//! расстояния и направления по большому кругу. Карта — равнопромежуточная
+//! проекция всей планеты: столбцы — долгота от −180° до 180°, строки —
+//! широта от −90° (строка 0) до 90°; по долготе карта замкнута. Радиус —
+//! активной планеты, без неё — `scale.planetRadiusKm`.
+//!
+//! Охват события радиуса `R` км — не круг в клетках: к полюсам клетка по
+//! долготе сужается, и тот же охват занимает больше столбцов, а у полюса —
+//! всю строку. Поэтому клетки в охвате отбираются по расстоянию по
+//! поверхности (`MapGeo::cells_within`), а не по расстоянию в клетках.
+//!
+//! Метры игроков (`regionSizeKm` / ширина карты на клетку) — другая шкала:
+//! события на планете отбираются здесь в клетках, а в метры игроков клетки
+//! переводит уже потребитель.
+//!
+//! Углы — в градусах; азимут — по часовой стрелке от севера. Локальные метры
+//! (восток, север) от опорной точки — азимутальная равнопромежуточная
+//! проекция: расстояние и азимут от опорной точки сохраняются точно.
+
+use seed_config::WorldConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatLon {
+    pub lat_deg: f64,
+    pub lon_deg: f64,
+}
+
+impl LatLon {
+    pub fn new(lat_deg: f64, lon_deg: f64) -> Self {
+        LatLon { lat_deg, lon_deg }
+    }
+}
+
+impl From<(f64, f64)> for LatLon {
+    /// Из пары (широта, долгота), как `Catastrophe::position`
+    fn from((lat_deg, lon_deg): (f64, f64)) -> Self {
+        LatLon { lat_deg, lon_deg }
+    }
+}
+
+/// Расстояние по большому кругу на сфере радиуса `radius_km`, км
+pub fn haversine_km(radius_km: f64, a: LatLon, b: LatLon) -> f64 {
+    let (lat1, lat2) = (a.lat_deg.to_radians(), b.lat_deg.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.lon_deg - a.lon_deg).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * radius_km * h.sqrt().clamp(0.0, 1.0).asin()
+}
+
+/// Начальный азимут пути по большому кругу из `a` в `b`, 0..360°
+pub fn initial_bearing_deg(a: LatLon, b: LatLon) -> f64 {
+    let (lat1, lat2) = (a.lat_deg.to_radians(), b.lat_deg.to_radians());
+    let dlon = (b.lon_deg - a.lon_deg).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Точка в `distance_km` от `from` по большому кругу с начальным азимутом
+/// `bearing_deg`; долгота — в −180..180
+pub fn destination(radius_km: f64, from: LatLon, bearing_deg: f64, distance_km: f64) -> LatLon {
+    let (lat1, lon1) = (from.lat_deg.to_radians(), from.lon_deg.to_radians());
+    let bearing = bearing_deg.to_radians();
+    let d = distance_km / radius_km;
+    let lat2 = (lat1.sin() * d.cos() + lat1.cos() * d.sin() * bearing.cos())
+        .clamp(-1.0, 1.0)
+        .asin();
+    let lon2 =
+        lon1 + (bearing.sin() * d.sin() * lat1.cos()).atan2(d.cos() - lat1.sin() * lat2.sin());
+    LatLon::new(lat2.to_degrees(), wrap_lon(lon2.to_degrees()))
+}
+
+/// Клетка карты `width`×`height` под точкой (широта, долгота)
+pub fn latlon_to_pixel(lat_deg: f64, lon_deg: f64, width: u32, height: u32) -> (u32, u32) {
+    let norm_lat = ((lat_deg + 90.0) / 180.0).clamp(0.0, 1.0);
+    let norm_lon = ((lon_deg + 180.0) / 360.0).clamp(0.0, 1.0);
+    let x = ((norm_lon * width as f64) as u32).min(width.saturating_sub(1));
+    let y = ((norm_lat * height as f64) as u32).min(height.saturating_sub(1));
+    (x, y)
+}
+
+/// Обратное преобразование: центр клетки → (широта, долгота)
+pub fn pixel_to_latlon(x: u32, y: u32, width: u32, height: u32) -> (f64, f64) {
+    let lat = (y as f64 + 0.5) / height.max(1) as f64 * 180.0 - 90.0;
+    let lon = (x as f64 + 0.5) / width.max(1) as f64 * 360.0 - 180.0;
+    (lat, lon)
+}
+
+/// Карта `width`×`height` на планете радиуса `radius_km`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapGeo {
+    pub width: u32,
+    pub height: u32,
+    pub radius_km: f64,
+}
+
+impl MapGeo {
+    pub fn new(width: u32, height: u32, radius_km: f64) -> Self {
+        MapGeo {
+            width,
+            height,
+            radius_km,
+        }
+    }
+
+    /// Радиус активной планеты, без неё — `scale.planetRadiusKm`
+    pub fn from_config(cfg: &WorldConfig, width: u32, height: u32) -> Self {
+        let sys = &cfg.cosmos.star_system;
+        let radius_km = sys
+            .planets
+            .iter()
+            .find(|p| p.id == sys.active_planet_id)
+            .map(|p| p.radius_km)
+            .filter(|r| *r > 0.0)
+            .unwrap_or(cfg.scale.planet_radius_km)
+            .max(1.0);
+        MapGeo::new(width, height, radius_km)
+    }
+
+    /// Точка карты в клетках (дробных; центр клетки x — x + 0.5)
+    pub fn to_latlon(&self, x: f64, y: f64) -> LatLon {
+        LatLon::new(
+            y / self.height.max(1) as f64 * 180.0 - 90.0,
+            x / self.width.max(1) as f64 * 360.0 - 180.0,
+        )
+    }
+
+    pub fn cell_latlon(&self, x: u32, y: u32) -> LatLon {
+        pixel_to_latlon(x, y, self.width, self.height).into()
+    }
+
+    /// Точка в клетках, дробных
+    pub fn to_pixel(&self, p: LatLon) -> (f64, f64) {
+        (
+            (wrap_lon(p.lon_deg) + 180.0) / 360.0 * self.width as f64,
+            (p.lat_deg.clamp(-90.0, 90.0) + 90.0) / 180.0 * self.height as f64,
+        )
+    }
+
+    /// Клетка под точкой
+    pub fn cell_at(&self, p: LatLon) -> (u32, u32) {
+        latlon_to_pixel(p.lat_deg, wrap_lon(p.lon_deg), self.width, self.height)
+    }
+
+    pub fn distance_km(&self, a: LatLon, b: LatLon) -> f64 {
+        haversine_km(self.radius_km, a, b)
+    }
+
+    pub fn destination(&self, from: LatLon, bearing_deg: f64, distance_km: f64) -> LatLon {
+        destination(self.radius_km, from, bearing_deg, distance_km)
+    }
+
+    /// Километров в клетке по меридиану
+    pub fn km_per_cell_y(&self) -> f64 {
+        std::f64::consts::PI * self.radius_km / self.height.max(1) as f64
+    }
+
+    /// Километров в клетке по параллели на широте `lat_deg`
+    pub fn km_per_cell_x(&self, lat_deg: f64) -> f64 {
+        2.0 * std::f64::consts::PI * self.radius_km * lat_deg.to_radians().cos().max(0.0)
+            / self.width.max(1) as f64
+    }
+
+    /// Полуоси охвата радиуса `radius_km` вокруг `center` в клетках: по
+    /// столбцам — наибольшая полуширина охвата (не больше половины карты;
+    /// охват с полюсом — вся строка) и по строкам
+    pub fn radius_cells(&self, center: LatLon, radius_km: f64) -> (f64, f64) {
+        let ry = radius_km / self.km_per_cell_y();
+        let half = self.width as f64 / 2.0;
+        // наибольший разброс долготы в сферическом круге: asin(sin δ / cos φ)
+        let delta = (radius_km / self.radius_km).min(std::f64::consts::PI);
+        let ratio = delta.sin() / center.lat_deg.to_radians().cos();
+        let rx = if delta < std::f64::consts::FRAC_PI_2 && (0.0..1.0).contains(&ratio) {
+            (ratio.asin().to_degrees() / 360.0 * self.width as f64).min(half)
+        } else {
+            half
+        };
+        (rx, ry)
+    }
+
+    /// Клетки в `radius_km` от `center` по поверхности и расстояние до их
+    /// центров, км; клетка под самим `center` — всегда, даже если охват
+    /// меньше клетки. По долготе охват переходит через край карты
+    pub fn cells_within(&self, center: LatLon, radius_km: f64) -> Vec<((u32, u32), f64)> {
+        let (w, h) = (self.width as i64, self.height as i64);
+        if w == 0 || h == 0 {
+            return Vec::new();
+        }
+        let home = self.cell_at(center);
+        let mut out = vec![(
+            home,
+            self.distance_km(center, self.cell_latlon(home.0, home.1)),
+        )];
+        let radius_km = radius_km.max(0.0);
+        let (cx, cy) = self.to_pixel(center);
+        let ry = radius_km / self.km_per_cell_y();
+        let (y0, y1) = (
+            ((cy - ry).floor() as i64).max(0),
+            ((cy + ry).ceil() as i64).min(h - 1),
+        );
+        // полуширина охвата по долготе на широте строки — из сферического
+        // треугольника центр—полюс—край; у полюса охват берёт всю строку
+        let delta = radius_km / self.radius_km;
+        let center_lat = center.lat_deg.to_radians();
+        for y in y0..=y1 {
+            let lat = self.cell_latlon(0, y as u32).lat_deg.to_radians();
+            let denom = lat.cos() * center_lat.cos();
+            let cos_dlon = if denom.abs() < 1e-12 {
+                -1.0
+            } else {
+                (delta.cos() - lat.sin() * center_lat.sin()) / denom
+            };
+            if cos_dlon > 1.0 {
+                continue;
+            }
+            let half = if cos_dlon <= -1.0 {
+                w as f64
+            } else {
+                cos_dlon.acos().to_degrees() / 360.0 * w as f64
+            };
+            let (x0, x1) = if half * 2.0 + 1.0 >= w as f64 {
+                (0, w - 1)
+            } else {
+                (
+                    (cx - half).floor() as i64 - 1,
+                    (cx + half).ceil() as i64 + 1,
+                )
+            };
+            for x in x0..=x1 {
+                let cell = (x.rem_euclid(w) as u32, y as u32);
+                if cell == home {
+                    continue;
+                }
+                let d = self.distance_km(center, self.cell_latlon(cell.0, cell.1));
+                if d <= radius_km {
+                    out.push((cell, d));
+                }
+            }
+        }
+        out.sort_by_key(|&((x, y), _)| (y, x));
+        out.dedup_by_key(|&mut (cell, _)| cell);
+        out
+    }
+
+    /// Смещение `p` от опорной точки `origin` в метрах: (на восток, на север)
+    pub fn to_local_m(&self, origin: LatLon, p: LatLon) -> (f64, f64) {
+        let d = self.distance_km(origin, p) * 1000.0;
+        let bearing = initial_bearing_deg(origin, p).to_radians();
+        (d * bearing.sin(), d * bearing.cos())
+    }
+
+    /// Точка в `east_m` на восток и `north_m` на север от `origin`
+    pub fn from_local_m(&self, origin: LatLon, east_m: f64, north_m: f64) -> LatLon {
+        let bearing = east_m.atan2(north_m).to_degrees();
+        self.destination(origin, bearing, east_m.hypot(north_m) / 1000.0)
+    }
+}
+
+/// Долгота в −180..180
+fn wrap_lon(lon_deg: f64) -> f64 {
+    (lon_deg + 180.0).rem_euclid(360.0) - 180.0
+}
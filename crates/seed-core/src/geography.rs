@@ -0,0 +1,348 @@
+//! Газеттир: выделение и именование крупных географических объектов —
+//! континенты и острова, моря и озёра (по площади), горные хребты
+//! (кластеризация гребней), реки (по расходу), пустыни и леса
+//! (связные области биомов). Якорные точки служат для подписей на картах.
+
+use crate::biome::BiomeMap;
+use crate::geo::pixel_to_latlon;
+use crate::names::{NameGenerator, NameStyle};
+use crate::seed_tree::SeedTree;
+use crate::terrain::{compute_flow_accumulation, Heightmap};
+use seed_config::WorldConfig;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Доля максимального стока, начиная с которой клетка считается рекой
+pub const RIVER_MIN_FLOW: f32 = 0.05;
+
+/// Относительная высота над морем, начиная с которой гребень считается горным
+pub const MOUNTAIN_MIN_RELATIVE_HEIGHT: f32 = 0.45;
+
+/// Сколько объектов каждого вида попадает в газеттир (крупнейшие)
+pub const MAX_ENTRIES_PER_KIND: usize = 12;
+
+/// Гребни на расстоянии до стольких клеток объединяются в один хребет
+const RIDGE_LINK_DISTANCE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureKind {
+    Continent,
+    Island,
+    Sea,
+    Lake,
+    MountainRange,
+    River,
+    Desert,
+    Forest,
+}
+
+impl FeatureKind {
+    pub fn from_id(id: &str) -> Option<FeatureKind> {
+        match id {
+            "continent" => Some(FeatureKind::Continent),
+            "island" => Some(FeatureKind::Island),
+            "sea" => Some(FeatureKind::Sea),
+            "lake" => Some(FeatureKind::Lake),
+            "mountain_range" => Some(FeatureKind::MountainRange),
+            "river" => Some(FeatureKind::River),
+            "desert" => Some(FeatureKind::Desert),
+            "forest" => Some(FeatureKind::Forest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GazetteerEntry {
+    pub kind: FeatureKind,
+    pub name: String,
+    /// Место среди объектов своего вида (1 — крупнейший по площади,
+    /// протяжённости хребта или расходу реки)
+    pub rank: u32,
+    /// Точка для подписи: клетка объекта, ближайшая к его центру (для рек — середина течения)
+    pub anchor: (u32, u32),
+    pub lat_deg: f64,
+    pub lon_deg: f64,
+    /// Площадь (для рек и хребтов — длина) в клетках
+    pub cells: usize,
+    /// Мера величины: площадь для областей, высшая точка (0..1) для хребтов,
+    /// расход (доля максимального стока) для рек
+    pub magnitude: f32,
+}
+
+/// Объект до именования
+struct Candidate {
+    kind: FeatureKind,
+    cells: Vec<usize>,
+    magnitude: f32,
+    /// По чему ранжируются объекты одного вида
+    weight: f32,
+    anchor: usize,
+}
+
+/// Находит крупнейшие объекты каждого вида и даёт им имена в стиле "common"
+pub fn build_gazetteer(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    seed: u64,
+) -> Vec<GazetteerEntry> {
+    let w = hm.width as usize;
+    let h = hm.height as usize;
+    let total = w * h;
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let sea_level = cfg.sea_level as f32;
+    let flow = compute_flow_accumulation(hm, sea_level);
+    let is_land = |i: usize| hm.values[i] > sea_level;
+
+    let mut candidates = Vec::new();
+
+    // Суша
+    for comp in components(w, h, 1, false, is_land) {
+        if comp.len() < 16 {
+            continue;
+        }
+        let kind = if comp.len() >= total / 20 {
+            FeatureKind::Continent
+        } else {
+            FeatureKind::Island
+        };
+        candidates.push(area_candidate(kind, comp, w));
+    }
+
+    // Вода: касающаяся края карты — море, замкнутая — озеро
+    for comp in components(w, h, 1, false, |i| !is_land(i)) {
+        let touches_edge = comp.iter().any(|&i| {
+            let (x, y) = (i % w, i / w);
+            x == 0 || y == 0 || x == w - 1 || y == h - 1
+        });
+        let kind = match (touches_edge, comp.len()) {
+            (true, n) if n >= total / 50 => FeatureKind::Sea,
+            (false, n) if n >= 4 => FeatureKind::Lake,
+            _ => continue,
+        };
+        candidates.push(area_candidate(kind, comp, w));
+    }
+
+    // Горные хребты: гребни (локальные максимумы поперёк склона), близкие гребни —
+    // один хребет; крупнейший — самый протяжённый
+    let ridge = ridge_mask(hm, sea_level);
+    for comp in components(w, h, RIDGE_LINK_DISTANCE, true, |i| ridge[i]) {
+        if comp.len() < 6 {
+            continue;
+        }
+        let peak = comp.iter().map(|&i| hm.values[i]).fold(0.0, f32::max);
+        let anchor = centroid_cell(&comp, w);
+        candidates.push(Candidate {
+            kind: FeatureKind::MountainRange,
+            weight: comp.len() as f32,
+            cells: comp,
+            magnitude: peak,
+            anchor,
+        });
+    }
+
+    // Реки: величина — расход в устье, подпись — в середине течения
+    for comp in components(w, h, 1, true, |i| is_land(i) && flow[i] >= RIVER_MIN_FLOW) {
+        if comp.len() < 8 {
+            continue;
+        }
+        let discharge = comp.iter().map(|&i| flow[i]).fold(0.0, f32::max);
+        let anchor = comp
+            .iter()
+            .copied()
+            .min_by(|&a, &b| {
+                (flow[a] - discharge * 0.5)
+                    .abs()
+                    .total_cmp(&(flow[b] - discharge * 0.5).abs())
+            })
+            .unwrap_or(comp[0]);
+        candidates.push(Candidate {
+            kind: FeatureKind::River,
+            cells: comp,
+            magnitude: discharge,
+            weight: discharge,
+            anchor,
+        });
+    }
+
+    // Пустыни и леса: связные области соответствующих биомов
+    let category: Vec<Option<FeatureKind>> = (0..total)
+        .map(|i| {
+            let biome = bm.indices[i].and_then(|bi| cfg.biomes.get(bi as usize))?;
+            biome_feature(&biome.id)
+        })
+        .collect();
+    for kind in [FeatureKind::Desert, FeatureKind::Forest] {
+        for comp in components(w, h, 1, false, |i| category[i] == Some(kind)) {
+            if comp.len() >= (total / 200).max(8) {
+                candidates.push(area_candidate(kind, comp, w));
+            }
+        }
+    }
+
+    // Крупнейшие каждого вида; порядок стабилен — имена тоже
+    let mut namer = NameGenerator::new(
+        NameStyle::common(),
+        SeedTree::new(seed).derive("names").seed(),
+    );
+    let mut entries = Vec::new();
+    for kind in [
+        FeatureKind::Continent,
+        FeatureKind::Island,
+        FeatureKind::Sea,
+        FeatureKind::Lake,
+        FeatureKind::MountainRange,
+        FeatureKind::River,
+        FeatureKind::Desert,
+        FeatureKind::Forest,
+    ] {
+        let mut of_kind: Vec<&Candidate> = candidates.iter().filter(|c| c.kind == kind).collect();
+        of_kind.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+
+        for (rank, c) in of_kind.into_iter().take(MAX_ENTRIES_PER_KIND).enumerate() {
+            let (x, y) = ((c.anchor % w) as u32, (c.anchor / w) as u32);
+            let (lat, lon) = pixel_to_latlon(x, y, hm.width, hm.height);
+            entries.push(GazetteerEntry {
+                kind,
+                name: feature_name(kind, namer.next_name()),
+                rank: rank as u32 + 1,
+                anchor: (x, y),
+                lat_deg: lat,
+                lon_deg: lon,
+                cells: c.cells.len(),
+                magnitude: c.magnitude,
+            });
+        }
+    }
+
+    entries
+}
+
+fn feature_name(kind: FeatureKind, base: String) -> String {
+    match kind {
+        FeatureKind::Continent => base,
+        FeatureKind::Island => format!("{base} Isle"),
+        FeatureKind::Sea => format!("{base} Sea"),
+        FeatureKind::Lake => format!("Lake {base}"),
+        FeatureKind::MountainRange => format!("{base} Mountains"),
+        FeatureKind::River => format!("{base} River"),
+        FeatureKind::Desert => format!("{base} Desert"),
+        FeatureKind::Forest => format!("{base} Forest"),
+    }
+}
+
+/// Какие биомы образуют именуемые области
+fn biome_feature(biome_id: &str) -> Option<FeatureKind> {
+    if biome_id.contains("desert") {
+        Some(FeatureKind::Desert)
+    } else if biome_id.contains("forest") || biome_id.contains("taiga") {
+        Some(FeatureKind::Forest)
+    } else {
+        None
+    }
+}
+
+fn area_candidate(kind: FeatureKind, cells: Vec<usize>, w: usize) -> Candidate {
+    let anchor = centroid_cell(&cells, w);
+    Candidate {
+        kind,
+        magnitude: cells.len() as f32,
+        weight: cells.len() as f32,
+        cells,
+        anchor,
+    }
+}
+
+/// Клетка гребня: высокогорье, выше обоих соседей хотя бы в двух направлениях
+fn ridge_mask(hm: &Heightmap, sea_level: f32) -> Vec<bool> {
+    let w = hm.width as i32;
+    let h = hm.height as i32;
+    let mut mask = vec![false; (w * h) as usize];
+    let at = |x: i32, y: i32| hm.values[(y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize];
+
+    for y in 0..h {
+        for x in 0..w {
+            let hc = at(x, y);
+            let rel = (hc - sea_level) / (1.0 - sea_level).max(1e-6);
+            if rel < MOUNTAIN_MIN_RELATIVE_HEIGHT {
+                continue;
+            }
+            let crests = [(1, 0), (0, 1), (1, 1), (1, -1)]
+                .iter()
+                .filter(|&&(dx, dy)| hc > at(x - dx, y - dy) && hc > at(x + dx, y + dy))
+                .count();
+            mask[(y * w + x) as usize] = crests >= 2;
+        }
+    }
+    mask
+}
+
+/// Клетка компоненты, ближайшая к её центру масс
+fn centroid_cell(comp: &[usize], w: usize) -> usize {
+    let n = comp.len() as f64;
+    let cx = comp.iter().map(|&i| (i % w) as f64).sum::<f64>() / n;
+    let cy = comp.iter().map(|&i| (i / w) as f64).sum::<f64>() / n;
+    comp.iter()
+        .copied()
+        .min_by(|&a, &b| {
+            let da = ((a % w) as f64 - cx).powi(2) + ((a / w) as f64 - cy).powi(2);
+            let db = ((b % w) as f64 - cx).powi(2) + ((b / w) as f64 - cy).powi(2);
+            da.total_cmp(&db)
+        })
+        .unwrap_or(comp[0])
+}
+
+/// Связные компоненты клеток, удовлетворяющих `pred`. Соседи — клетки на
+/// расстоянии до `link` по каждой оси (без диагоналей при `diagonal == false`).
+pub(crate) fn components(
+    w: usize,
+    h: usize,
+    link: i32,
+    diagonal: bool,
+    pred: impl Fn(usize) -> bool,
+) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; w * h];
+    let mut out = Vec::new();
+    let mut queue = VecDeque::new();
+
+    for start in 0..w * h {
+        if seen[start] || !pred(start) {
+            continue;
+        }
+        seen[start] = true;
+        queue.push_back(start);
+        let mut comp = Vec::new();
+
+        while let Some(i) = queue.pop_front() {
+            comp.push(i);
+            let x = (i % w) as i32;
+            let y = (i / w) as i32;
+            for dy in -link..=link {
+                for dx in -link..=link {
+                    if (dx == 0 && dy == 0) || (!diagonal && dx != 0 && dy != 0) {
+                        continue;
+                    }
+                    let nx = x + dx;
+                    let ny = y + dy;
+                    if nx < 0 || ny < 0 || nx >= w as i32 || ny >= h as i32 {
+                        continue;
+                    }
+                    let n = ny as usize * w + nx as usize;
+                    if !seen[n] && pred(n) {
+                        seen[n] = true;
+                        queue.push_back(n);
+                    }
+                }
+            }
+        }
+        out.push(comp);
+    }
+
+    out
+}
@@ -0,0 +1,586 @@
+use crate::biome::BiomeMap;
+use crate::catastrophe::{generate_catastrophes, Catastrophe, CatastropheType};
+use crate::culture::{culture_step, CultureLayer};
+use crate::diplomacy::{diplomacy_step, Diplomacy, RelationMatrix, Treaty};
+use crate::geo::{latlon_to_pixel, MapGeo};
+use crate::infrastructure::{build_infrastructure, Infrastructure};
+use crate::names::NameStyle;
+use crate::population::{
+    catchment_capacities, compute_food_capacity, Demographics, PopulationSnapshot,
+};
+use crate::rng::SplitMix64;
+use crate::seed_tree::SeedTree;
+use crate::settlements::{
+    best_site_near, compute_risk_map, compute_suitability, risk_radius_km, City, Ruin, RuinCause,
+    SuitabilityMap,
+};
+use crate::tech::{tech_step, Era};
+use crate::terrain::Heightmap;
+use crate::territory::{grow_territories, TerritoryMap};
+use crate::trade::{build_trade_network, CostSurface, TradeNetwork};
+use crate::war::{war_step, Battle, War, WarState};
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+
+/// Шаг симуляции истории, лет
+pub const HISTORY_STEP_YEARS: u32 = 5;
+
+/// Предел "стоимости пути" влияния города средней величины при росте территорий
+pub const TERRITORY_MAX_COST: f32 = 60.0;
+
+/// Доля прокормленного угодьями населения, живущая в самом городе
+const URBAN_SHARE: f64 = 0.35;
+
+/// Доля стартового населения фракции, живущая в столице
+const CAPITAL_URBAN_SHARE: f64 = 0.2;
+
+/// Как часто пересчитываются торговая сеть и территории, лет
+pub const TRADE_REBUILD_YEARS: u32 = 50;
+
+/// Со сколькими ближайшими городами торгует каждый город
+const TRADE_LINKS_PER_CITY: usize = 2;
+
+/// Максимальная прибавка к ёмкости города за счёт торговли (доля)
+const TRADE_CAPACITY_BONUS: f64 = 0.5;
+
+/// Город и его угодья с меньшим населением жители покидают (кроме столиц)
+const ABANDON_POPULATION: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Faction {
+    pub id: String,
+    pub name: String,
+    pub tech_level: String,
+    pub preferred_biomes: Vec<String>,
+    pub capital_id: Option<String>,
+    /// Стиль имён фракции (см. `names::NAME_STYLES`)
+    pub name_style: String,
+    /// Прогресс к следующей эпохе (0..1)
+    pub tech_progress: f64,
+}
+
+impl Faction {
+    /// Текущая эпоха; неизвестный `tech_level` считается средневековьем
+    pub fn era(&self) -> Era {
+        Era::from_tech_level(&self.tech_level).unwrap_or(Era::Medieval)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HistoryEventKind {
+    CityFounded {
+        city_id: String,
+    },
+    WarDeclared {
+        enemy_id: String,
+    },
+    BattleFought {
+        city_id: String,
+        enemy_id: String,
+        won: bool,
+    },
+    CityCaptured {
+        city_id: String,
+        from_faction_id: String,
+    },
+    CityRazed {
+        city_id: String,
+    },
+    /// Жители покинули обезлюдевший город
+    CityAbandoned {
+        city_id: String,
+    },
+    PeaceMade {
+        enemy_id: String,
+    },
+    /// Фракция потеряла последний город
+    FactionDestroyed,
+    TreatySigned {
+        other_id: String,
+        treaty: Treaty,
+    },
+    TreatyBroken {
+        other_id: String,
+        treaty: Treaty,
+    },
+    ReligionFounded {
+        religion_id: String,
+        city_id: String,
+    },
+    /// Фракция вступила в новую эпоху
+    EraAdvanced {
+        era: Era,
+    },
+    /// Катастрофа задела города (`faction_id` события — владелец ближайшего)
+    CatastropheStruck {
+        catastrophe_type: CatastropheType,
+        x: u32,
+        y: u32,
+        city_ids: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEvent {
+    pub year: u32,
+    pub faction_id: String,
+    pub kind: HistoryEventKind,
+}
+
+/// Результат симуляции истории цивилизаций
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct History {
+    pub factions: Vec<Faction>,
+    pub cities: Vec<City>,
+    pub events: Vec<HistoryEvent>,
+    pub years_simulated: u32,
+    pub suitability: SuitabilityMap,
+    /// Территории фракций на конец симуляции
+    pub territory: TerritoryMap,
+    /// Торговые маршруты на конец симуляции
+    pub trade: TradeNetwork,
+    pub wars: Vec<War>,
+    pub battles: Vec<Battle>,
+    /// Разрушенные в войнах и покинутые города
+    pub ruins: Vec<Ruin>,
+    /// Население по годам
+    pub demographics: Demographics,
+    /// Отношения фракций по годам
+    pub diplomacy: Diplomacy,
+    /// Культуры и религии
+    pub culture: CultureLayer,
+    /// Дороги и сооружения на конец симуляции
+    pub infrastructure: Infrastructure,
+}
+
+impl History {
+    /// Население городов на год `year` (последний срез не позже этого года)
+    pub fn population_at(&self, year: u32) -> Option<&PopulationSnapshot> {
+        self.demographics.population_at(year)
+    }
+
+    /// Культура каждой клетки на год `year` (индексы `culture.cultures`)
+    pub fn culture_map_at(&self, year: u32) -> Option<Vec<Option<u16>>> {
+        self.culture.culture_map_at(year)
+    }
+
+    /// Матрица отношений фракций на год `year`
+    pub fn relations_at(&self, year: u32) -> Option<&RelationMatrix> {
+        self.diplomacy.matrix_at(year)
+    }
+}
+
+/// Симулирует историю: ставит столицы рядом с `capital_location_hint`
+/// и основывает новые города по мере роста фракций.
+pub fn simulate_history(cfg: &WorldConfig, hm: &Heightmap, bm: &BiomeMap, seed: u64) -> History {
+    let civ = &cfg.civilizations;
+    let hist_cfg = &civ.history_simulation;
+    let years = if civ.enabled && hist_cfg.enabled {
+        hist_cfg.years_to_simulate
+    } else {
+        0
+    };
+
+    let mut rng = SeedTree::new(seed).derive("history").rng();
+
+    // Риск: катастрофы за тот же период истории
+    let catastrophes = generate_catastrophes(cfg, years.max(1) as f64, seed);
+    let risk = compute_risk_map(cfg, hm, &catastrophes);
+    let suitability = compute_suitability(cfg, hm, bm, Some(&risk));
+
+    let mut history = History {
+        factions: Vec::new(),
+        cities: Vec::new(),
+        events: Vec::new(),
+        years_simulated: years,
+        suitability,
+        territory: TerritoryMap::empty(hm.width, hm.height),
+        trade: TradeNetwork::default(),
+        wars: Vec::new(),
+        battles: Vec::new(),
+        ruins: Vec::new(),
+        demographics: Demographics::new(compute_food_capacity(cfg, hm, bm)),
+        diplomacy: Diplomacy::new(Vec::new()),
+        culture: CultureLayer::new(hm.width, hm.height),
+        infrastructure: Infrastructure::empty(hm.width, hm.height),
+    };
+
+    if !civ.enabled {
+        return history;
+    }
+
+    let map_size = hm.width.max(hm.height);
+    let capital_search = (map_size / 8).max(4);
+    let expand_radius = (map_size / 10).max(4);
+    let min_spacing = (map_size as f64 / 24.0).max(3.0);
+
+    // У каждой фракции своя культура (индексы совпадают); имена — в её стиле
+    for (fi, preset) in civ.faction_presets.iter().enumerate() {
+        let seed = rng.next_u64() ^ fi as u64;
+        history
+            .culture
+            .found_culture(&preset.id, NameStyle::for_faction(preset), seed);
+    }
+
+    // --- Столицы ---
+    for (fi, preset) in civ.faction_presets.iter().enumerate() {
+        let hint = &preset.capital_location_hint;
+        let (hx, hy) = latlon_to_pixel(hint.lat_deg, hint.lon_deg, hm.width, hm.height);
+
+        let site = best_site_near(
+            &history.suitability,
+            hx,
+            hy,
+            capital_search,
+            &history.cities,
+            min_spacing,
+        )
+        // рядом ничего нет — ищем по всей карте
+        .or_else(|| {
+            best_site_near(
+                &history.suitability,
+                hx,
+                hy,
+                map_size,
+                &history.cities,
+                min_spacing,
+            )
+        });
+
+        let name = if preset.name.trim().is_empty() {
+            history.culture.next_name(fi)
+        } else {
+            preset.name.clone()
+        };
+        let mut faction = Faction {
+            id: preset.id.clone(),
+            name,
+            tech_level: preset.tech_level.clone(),
+            preferred_biomes: preset.preferred_biomes.clone(),
+            capital_id: None,
+            name_style: history.culture.cultures[fi].name_style.clone(),
+            tech_progress: 0.0,
+        };
+
+        if let Some((x, y, _)) = site {
+            let city_id = format!("{}_city_0", preset.id);
+            let start = preset.starting_population.max(0) as f64;
+            history.cities.push(City {
+                id: city_id.clone(),
+                name: history.culture.next_name(fi),
+                faction_id: preset.id.clone(),
+                x,
+                y,
+                founded_year: 0,
+                population: (start * CAPITAL_URBAN_SHARE) as u64,
+                rural_population: (start * (1.0 - CAPITAL_URBAN_SHARE)) as u64,
+                is_capital: true,
+            });
+            history.events.push(HistoryEvent {
+                year: 0,
+                faction_id: preset.id.clone(),
+                kind: HistoryEventKind::CityFounded {
+                    city_id: city_id.clone(),
+                },
+            });
+            history.culture.settle(&city_id, fi);
+            faction.capital_id = Some(city_id);
+        }
+
+        history.factions.push(faction);
+    }
+
+    history.diplomacy = Diplomacy::new(history.factions.iter().map(|f| f.id.clone()).collect());
+
+    // Стартовое население столиц не больше, чем могут прокормить их угодья
+    let caps = catchment_capacities(&history.demographics.food, &history.cities);
+    for (city, food) in history.cities.iter_mut().zip(caps) {
+        let rural_cap = (food * (1.0 - URBAN_SHARE)) as u64;
+        city.rural_population = city.rural_population.min(rural_cap);
+    }
+
+    // --- Ход истории ---
+    let surface = CostSurface::new(cfg, hm);
+    let mut wars = WarState::default();
+    history
+        .demographics
+        .snapshots
+        .push(PopulationSnapshot::capture(0, &history.cities));
+    let mut year = 0;
+    while year + HISTORY_STEP_YEARS <= years {
+        year += HISTORY_STEP_YEARS;
+        if year % TRADE_REBUILD_YEARS == 0 {
+            refresh_networks(&mut history, cfg, hm, bm, &surface);
+            wars.update_contacts(&history.territory);
+        }
+        apply_catastrophes(&mut history, cfg, &catastrophes, year);
+        grow_cities(&mut history, HISTORY_STEP_YEARS);
+        let abandoned = abandon_cities(&mut history, year);
+        found_cities(&mut history, year, expand_radius, min_spacing, &mut rng);
+        // города сменили хозяев — индексы маршрутов и границы устарели
+        if war_step(&mut history, &mut wars, cfg, hm, &surface, year, &mut rng) || abandoned {
+            refresh_networks(&mut history, cfg, hm, bm, &surface);
+        }
+        diplomacy_step(&mut history, wars.contacts(), year);
+        culture_step(&mut history, year, &mut rng);
+        tech_step(&mut history, year, HISTORY_STEP_YEARS);
+        history
+            .demographics
+            .snapshots
+            .push(PopulationSnapshot::capture(year, &history.cities));
+    }
+
+    refresh_networks(&mut history, cfg, hm, bm, &surface);
+    history.infrastructure = build_infrastructure(cfg, hm, &history);
+
+    history
+}
+
+/// Пересчёт торговой сети и территорий по текущим городам
+fn refresh_networks(
+    history: &mut History,
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    surface: &CostSurface,
+) {
+    history.trade =
+        build_trade_network(cfg, hm, bm, surface, &history.cities, TRADE_LINKS_PER_CITY);
+    history.territory = grow_territories(
+        cfg,
+        hm,
+        &history.cities,
+        &history.factions,
+        TERRITORY_MAX_COST,
+    );
+}
+
+/// Крупные катастрофы последнего шага: потери населения в задетых городах
+/// и запись в истории
+fn apply_catastrophes(
+    history: &mut History,
+    cfg: &WorldConfig,
+    catastrophes: &[Catastrophe],
+    year: u32,
+) {
+    let (w, h) = (history.territory.width, history.territory.height);
+    let geo = MapGeo::from_config(cfg, w, h);
+    let from = year.saturating_sub(HISTORY_STEP_YEARS) as f64;
+    let impact = cfg
+        .civilizations
+        .history_simulation
+        .catastrophe_impact_on_history
+        .clamp(0.0, 1.0) as f64;
+    // за шаг летопись помнит по одной катастрофе каждого типа
+    let mut seen: Vec<CatastropheType> = Vec::new();
+
+    for cat in catastrophes {
+        if !cat.is_major()
+            || cat.timestamp < from
+            || cat.timestamp >= year as f64
+            || seen.contains(&cat.catastrophe_type)
+        {
+            continue;
+        }
+        let center = cat.position.into();
+        let (cx, cy) = geo.cell_at(center);
+        // тот же охват, что и в карте риска; расстояние — по поверхности, км
+        let radius = risk_radius_km(&geo, cat);
+
+        let mut hit: Vec<(f64, usize)> = history
+            .cities
+            .iter()
+            .enumerate()
+            .map(|(ci, c)| (geo.distance_km(center, geo.cell_latlon(c.x, c.y)), ci))
+            .filter(|(d, _)| *d <= radius)
+            .collect();
+        if hit.is_empty() {
+            continue;
+        }
+        hit.sort_by(|a, b| a.0.total_cmp(&b.0));
+        seen.push(cat.catastrophe_type);
+
+        // у эпицентра гибнет до десятой части населения, к краю охвата потери сходят на нет
+        for &(d, ci) in &hit {
+            let keep = 1.0 - impact * 0.1 * (1.0 - d / radius);
+            let city = &mut history.cities[ci];
+            city.population = (city.population as f64 * keep) as u64;
+            city.rural_population = (city.rural_population as f64 * keep) as u64;
+        }
+
+        let event = HistoryEvent {
+            year,
+            faction_id: history.cities[hit[0].1].faction_id.clone(),
+            kind: HistoryEventKind::CatastropheStruck {
+                catastrophe_type: cat.catastrophe_type,
+                x: cx,
+                y: cy,
+                city_ids: hit
+                    .iter()
+                    .map(|&(_, ci)| history.cities[ci].id.clone())
+                    .collect(),
+            },
+        };
+        history.events.push(event);
+    }
+}
+
+/// Логистический рост к пределу, который задают угодья: сельские жители
+/// кормят город, торговля позволяет городу вырасти сверх местного урожая,
+/// излишек деревни уходит в город.
+fn grow_cities(history: &mut History, dt_years: u32) {
+    let dt = dt_years as f64;
+    let caps = catchment_capacities(&history.demographics.food, &history.cities);
+    let trade = history.trade.volume_by_city(history.cities.len());
+    let max_trade = trade.iter().cloned().fold(0.0, f64::max);
+
+    for (ci, city) in history.cities.iter_mut().enumerate() {
+        let food = caps[ci].max(1.0);
+        let mut urban_cap = food * URBAN_SHARE;
+        if city.is_capital {
+            urban_cap *= 1.5;
+        }
+        if max_trade > 0.0 {
+            urban_cap *= 1.0 + TRADE_CAPACITY_BONUS * trade[ci] / max_trade;
+        }
+        let rural_cap = food * (1.0 - URBAN_SHARE);
+
+        let rural = city.rural_population as f64;
+        let mut rural_next = logistic_step(rural.max(100.0), rural_cap, 0.03, dt);
+
+        let urban = city.population as f64;
+        let mut urban_next = logistic_step(urban, urban_cap, 0.01, dt);
+
+        // миграция из деревни, пока город не заполнен
+        if urban_next < urban_cap {
+            let migrants = (rural_next * 0.002 * dt).min(urban_cap - urban_next);
+            rural_next -= migrants;
+            urban_next += migrants;
+        }
+
+        city.population = urban_next.max(0.0) as u64;
+        city.rural_population = rural_next as u64;
+    }
+}
+
+/// Обезлюдевшие города покидают, на их месте остаются руины.
+/// Возвращает `true`, если хоть один город исчез.
+fn abandon_cities(history: &mut History, year: u32) -> bool {
+    let mut abandoned = false;
+    let mut ci = 0;
+    while ci < history.cities.len() {
+        let city = &history.cities[ci];
+        if city.is_capital || city.population + city.rural_population >= ABANDON_POPULATION {
+            ci += 1;
+            continue;
+        }
+        let city = history.cities.remove(ci);
+        history.culture.forget(&city.id);
+        history.events.push(HistoryEvent {
+            year,
+            faction_id: city.faction_id.clone(),
+            kind: HistoryEventKind::CityAbandoned {
+                city_id: city.id.clone(),
+            },
+        });
+        history.ruins.push(Ruin {
+            id: city.id,
+            name: city.name,
+            faction_id: city.faction_id,
+            x: city.x,
+            y: city.y,
+            founded_year: city.founded_year,
+            destroyed_year: year,
+            population_at_destruction: city.population,
+            cause: RuinCause::Abandoned,
+        });
+        abandoned = true;
+    }
+    abandoned
+}
+
+/// Логистический рост; сверх ёмкости — голод и отток (10% излишка в год)
+fn logistic_step(p: f64, capacity: f64, rate: f64, dt: f64) -> f64 {
+    let capacity = capacity.max(1.0);
+    if p > capacity {
+        p - (p - capacity) * (0.1 * dt).min(1.0)
+    } else {
+        p + rate * dt * p * (1.0 - p / capacity)
+    }
+}
+
+/// Крупные города отправляют переселенцев основывать новые поселения
+fn found_cities(
+    history: &mut History,
+    year: u32,
+    expand_radius: u32,
+    min_spacing: f64,
+    rng: &mut SplitMix64,
+) {
+    for fi in 0..history.factions.len() {
+        let faction_id = history.factions[fi].id.clone();
+        let own: Vec<usize> = history
+            .cities
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.faction_id == faction_id && c.population > 5_000)
+            .map(|(i, _)| i)
+            .collect();
+        if own.is_empty() {
+            continue;
+        }
+
+        // чем больше городов, тем медленнее экспансия
+        let chance = 0.35 / (1.0 + own.len() as f64 * 0.25);
+        if !rng.chance(chance) {
+            continue;
+        }
+
+        let parent = own[rng.below(own.len())];
+        let (px, py) = (history.cities[parent].x, history.cities[parent].y);
+        let Some((x, y, _)) = best_site_near(
+            &history.suitability,
+            px,
+            py,
+            expand_radius,
+            &history.cities,
+            min_spacing,
+        ) else {
+            continue;
+        };
+
+        let settlers = (history.cities[parent].population / 10).clamp(500, 20_000);
+        history.cities[parent].population -= settlers.min(history.cities[parent].population);
+
+        // считаем по событиям: захваченные и разрушенные города не дают повторить id
+        let n = history
+            .events
+            .iter()
+            .filter(|e| {
+                e.faction_id == faction_id && matches!(e.kind, HistoryEventKind::CityFounded { .. })
+            })
+            .count();
+        let city_id = format!("{faction_id}_city_{n}");
+        // новый город говорит на языке основателей, даже если страна другой культуры
+        let parent_id = history.cities[parent].id.clone();
+        let culture = history.culture.culture_of(&parent_id).unwrap_or(fi);
+        history.cities.push(City {
+            id: city_id.clone(),
+            name: history.culture.next_name(culture),
+            faction_id: faction_id.clone(),
+            x,
+            y,
+            founded_year: year,
+            // половина переселенцев распахивает окрестные земли
+            population: settlers - settlers / 2,
+            rural_population: settlers / 2,
+            is_capital: false,
+        });
+        history.culture.inherit(&parent_id, &city_id);
+        history.events.push(HistoryEvent {
+            year,
+            faction_id,
+            kind: HistoryEventKind::CityFounded { city_id },
+        });
+    }
+}
@@ -0,0 +1,165 @@
+//! Векторная гидрография: реки (полилинии по D8-стоку с расходом),
+//! озёра (полигоны по контуру замкнутых водоёмов) и береговая линия моря.
+//! Координаты — в клетках карты, как у `BorderLine`: (0, 0) — северо-западный
+//! угол, реки проходят через центры клеток, контуры — по углам.
+
+use crate::geography::{components, RIVER_MIN_FLOW};
+use crate::terrain::{compute_flow_accumulation, flow_directions, Heightmap};
+use crate::territory::{chain_segments, simplify_polyline, LatticeSegment};
+
+/// Меньшие замкнутые водоёмы в озёра не попадают (как и в газеттире)
+const MIN_LAKE_CELLS: usize = 4;
+
+/// Участок реки между истоком, слиянием и устьем
+#[derive(Debug, Clone)]
+pub struct RiverLine {
+    pub points: Vec<(f32, f32)>,
+    /// Расход в нижней точке участка, доля максимального стока карты
+    pub discharge: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct LakePolygon {
+    /// Первое кольцо — внешний контур, остальные — острова; кольца замкнуты
+    pub rings: Vec<Vec<(f32, f32)>>,
+    pub cells: usize,
+}
+
+/// Реки — клетки суши со стоком не меньше `RIVER_MIN_FLOW`; каждая
+/// полилиния доходит до следующего слияния или до воды
+pub fn extract_rivers(hm: &Heightmap, sea_level: f32, epsilon: f32) -> Vec<RiverLine> {
+    let w = hm.width as usize;
+    let flow = compute_flow_accumulation(hm, sea_level);
+    let down = flow_directions(hm, sea_level);
+    let is_river: Vec<bool> = (0..flow.len())
+        .map(|i| hm.values[i] > sea_level && flow[i] >= RIVER_MIN_FLOW)
+        .collect();
+
+    // сколько речных клеток впадает в клетку: 0 — исток, 2+ — слияние
+    let mut inflow = vec![0u8; flow.len()];
+    for (i, d) in down.iter().enumerate() {
+        if let (true, Some(j)) = (is_river[i], *d) {
+            inflow[j] = inflow[j].saturating_add(1);
+        }
+    }
+
+    let center = |i: usize| ((i % w) as f32 + 0.5, (i / w) as f32 + 0.5);
+    let mut rivers = Vec::new();
+    for start in (0..flow.len()).filter(|&i| is_river[i] && inflow[i] != 1) {
+        let mut cells = vec![start];
+        let mut current = start;
+        while let Some(next) = down[current] {
+            cells.push(next);
+            if !is_river[next] || inflow[next] != 1 {
+                break;
+            }
+            current = next;
+        }
+        if cells.len() < 2 {
+            continue;
+        }
+        let discharge = cells
+            .iter()
+            .filter(|&&i| is_river[i])
+            .map(|&i| flow[i])
+            .fold(0.0, f32::max);
+        let points: Vec<(f32, f32)> = cells.iter().map(|&i| center(i)).collect();
+        rivers.push(RiverLine {
+            points: simplify_polyline(&points, epsilon),
+            discharge,
+        });
+    }
+    rivers
+}
+
+/// Замкнутые (не касающиеся края карты) водоёмы
+pub fn extract_lakes(hm: &Heightmap, sea_level: f32, epsilon: f32) -> Vec<LakePolygon> {
+    let (w, h) = (hm.width as usize, hm.height as usize);
+    let mut lakes = Vec::new();
+    for comp in components(w, h, 1, false, |i| hm.values[i] <= sea_level) {
+        if comp.len() < MIN_LAKE_CELLS || comp.iter().any(|&i| on_edge(i, w, h)) {
+            continue;
+        }
+        let mut inside = vec![false; w * h];
+        for &i in &comp {
+            inside[i] = true;
+        }
+        let mut rings: Vec<Vec<(f32, f32)>> = chain_segments(&boundary(w, h, |i| inside[i]))
+            .into_iter()
+            .map(|chain| {
+                let pts: Vec<(f32, f32)> =
+                    chain.iter().map(|&(x, y)| (x as f32, y as f32)).collect();
+                simplify_polyline(&pts, epsilon)
+            })
+            .filter(|ring| ring.len() >= 4)
+            .collect();
+        // внешний контур охватывает наибольшую площадь
+        if let Some(outer) = (0..rings.len()).max_by(|&a, &b| {
+            ring_area(&rings[a])
+                .abs()
+                .total_cmp(&ring_area(&rings[b]).abs())
+        }) {
+            rings.swap(0, outer);
+        }
+        if !rings.is_empty() {
+            lakes.push(LakePolygon {
+                rings,
+                cells: comp.len(),
+            });
+        }
+    }
+    lakes
+}
+
+/// Граница суши и моря (вода, связанная с краем карты); по краю карты
+/// линия обрывается
+pub fn extract_coastlines(hm: &Heightmap, sea_level: f32, epsilon: f32) -> Vec<Vec<(f32, f32)>> {
+    let (w, h) = (hm.width as usize, hm.height as usize);
+    let mut sea = vec![false; w * h];
+    for comp in components(w, h, 1, false, |i| hm.values[i] <= sea_level) {
+        if comp.iter().any(|&i| on_edge(i, w, h)) {
+            for i in comp {
+                sea[i] = true;
+            }
+        }
+    }
+    chain_segments(&boundary(w, h, |i| sea[i]))
+        .into_iter()
+        .map(|chain| {
+            let pts: Vec<(f32, f32)> = chain.iter().map(|&(x, y)| (x as f32, y as f32)).collect();
+            simplify_polyline(&pts, epsilon)
+        })
+        .collect()
+}
+
+/// Отрезки решётки между клетками внутри и снаружи области (без края карты)
+fn boundary(w: usize, h: usize, inside: impl Fn(usize) -> bool) -> Vec<LatticeSegment> {
+    let mut segs = Vec::new();
+    for y in 0..h {
+        for x in 0..w {
+            let here = inside(y * w + x);
+            if x + 1 < w && here != inside(y * w + x + 1) {
+                let x = x as i32 + 1;
+                segs.push(((x, y as i32), (x, y as i32 + 1)));
+            }
+            if y + 1 < h && here != inside((y + 1) * w + x) {
+                let y = y as i32 + 1;
+                segs.push(((x as i32, y), (x as i32 + 1, y)));
+            }
+        }
+    }
+    segs
+}
+
+fn on_edge(i: usize, w: usize, h: usize) -> bool {
+    let (x, y) = (i % w, i / w);
+    x == 0 || y == 0 || x == w - 1 || y == h - 1
+}
+
+/// Ориентированная площадь кольца (формула шнурка)
+fn ring_area(ring: &[(f32, f32)]) -> f32 {
+    ring.windows(2)
+        .map(|p| p[0].0 * p[1].1 - p[1].0 * p[0].1)
+        .sum::<f32>()
+        * 0.5
+}
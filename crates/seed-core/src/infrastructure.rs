@@ -0,0 +1,335 @@
+//! Инфраструктура на конец истории: дороги, мосты, стены, гавани и рудники.
+//! Облик сооружения задаёт эпоха фракции, которая его держит; карта эпох
+//! показывает самый развитый уцелевший уровень в каждой клетке.
+
+use crate::geography::MOUNTAIN_MIN_RELATIVE_HEIGHT;
+use crate::history::History;
+use crate::population::CATCHMENT_RADIUS;
+use crate::tech::Era;
+use crate::terrain::Heightmap;
+use crate::trade::RouteKind;
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+
+/// Город меньше этого (горожане) обходится без стен, если он не столица
+const WALLED_CITY_POPULATION: u64 = 2_000;
+
+/// Гавань ставится, если переход суши в море не дальше стольких клеток от города
+const HARBOR_MAX_DISTANCE: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoadQuality {
+    /// Тропа (каменный и бронзовый век)
+    Track,
+    /// Мощёная дорога
+    Paved,
+    /// Шоссе и железные дороги
+    Highway,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BridgeKind {
+    Timber,
+    Stone,
+    Steel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WallKind {
+    Palisade,
+    StoneWall,
+    /// Бастионная крепость эпохи пороха
+    Bastion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HarborKind {
+    Jetty,
+    Harbor,
+    Port,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MineKind {
+    Pit,
+    Shaft,
+    Industrial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StructureKind {
+    Bridge(BridgeKind),
+    Wall(WallKind),
+    Harbor(HarborKind),
+    Mine(MineKind),
+}
+
+impl RoadQuality {
+    pub fn for_era(era: Era) -> RoadQuality {
+        match era {
+            Era::Stone | Era::Bronze => RoadQuality::Track,
+            Era::Iron | Era::Medieval | Era::Renaissance => RoadQuality::Paved,
+            _ => RoadQuality::Highway,
+        }
+    }
+}
+
+impl StructureKind {
+    /// Мост эпохи; в каменном веке реки переходят вброд
+    pub fn bridge(era: Era) -> Option<StructureKind> {
+        let kind = match era {
+            Era::Stone => return None,
+            Era::Bronze | Era::Iron => BridgeKind::Timber,
+            Era::Medieval | Era::Renaissance => BridgeKind::Stone,
+            _ => BridgeKind::Steel,
+        };
+        Some(StructureKind::Bridge(kind))
+    }
+
+    /// Городские стены эпохи; с индустриальной эпохи стены не строят
+    pub fn wall(era: Era) -> Option<StructureKind> {
+        let kind = match era {
+            Era::Stone | Era::Bronze => WallKind::Palisade,
+            Era::Iron | Era::Medieval => WallKind::StoneWall,
+            Era::Renaissance => WallKind::Bastion,
+            _ => return None,
+        };
+        Some(StructureKind::Wall(kind))
+    }
+
+    pub fn harbor(era: Era) -> StructureKind {
+        StructureKind::Harbor(match era {
+            Era::Stone | Era::Bronze | Era::Iron => HarborKind::Jetty,
+            Era::Medieval | Era::Renaissance => HarborKind::Harbor,
+            _ => HarborKind::Port,
+        })
+    }
+
+    /// Рудник эпохи; до бронзы руду не добывают
+    pub fn mine(era: Era) -> Option<StructureKind> {
+        let kind = match era {
+            Era::Stone => return None,
+            Era::Bronze | Era::Iron | Era::Medieval => MineKind::Pit,
+            Era::Renaissance => MineKind::Shaft,
+            _ => MineKind::Industrial,
+        };
+        Some(StructureKind::Mine(kind))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Road {
+    pub quality: RoadQuality,
+    pub era: Era,
+    pub points: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Structure {
+    pub kind: StructureKind,
+    pub era: Era,
+    pub x: u32,
+    pub y: u32,
+    /// Город, которому принадлежит сооружение
+    pub city_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Infrastructure {
+    pub width: u32,
+    pub height: u32,
+    pub roads: Vec<Road>,
+    pub structures: Vec<Structure>,
+    /// Самая развитая эпоха в клетке: хозяин территории и стоящие там сооружения
+    pub era_map: Vec<Option<Era>>,
+}
+
+impl Infrastructure {
+    pub fn empty(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            roads: Vec::new(),
+            structures: Vec::new(),
+            era_map: vec![None; (width * height) as usize],
+        }
+    }
+
+    pub fn era_at(&self, x: u32, y: u32) -> Option<Era> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.era_map[(y * self.width + x) as usize]
+    }
+}
+
+/// Строит инфраструктуру по итогам истории: торговые дороги и мосты,
+/// стены, гавани и рудники городов — всё в облике эпохи владельца.
+pub fn build_infrastructure(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    history: &History,
+) -> Infrastructure {
+    let (w, h) = (history.territory.width, history.territory.height);
+    let faction_era = |id: &str| {
+        history
+            .factions
+            .iter()
+            .find(|f| f.id == id)
+            .map(|f| f.era())
+            .unwrap_or(Era::Medieval)
+    };
+    let city_era: Vec<Era> = history
+        .cities
+        .iter()
+        .map(|c| faction_era(&c.faction_id))
+        .collect();
+
+    let mut roads = Vec::new();
+    let mut structures: Vec<Structure> = Vec::new();
+
+    // Дороги строит более развитый из торговых партнёров
+    for route in &history.trade.routes {
+        let (Some(&ea), Some(&eb)) = (city_era.get(route.from_city), city_era.get(route.to_city))
+        else {
+            continue;
+        };
+        let (era, owner) = if ea >= eb {
+            (ea, route.from_city)
+        } else {
+            (eb, route.to_city)
+        };
+        let owner_id = &history.cities[owner].id;
+
+        for (li, leg) in route.legs.iter().enumerate() {
+            match leg.kind {
+                RouteKind::Road => roads.push(Road {
+                    quality: RoadQuality::for_era(era),
+                    era,
+                    points: leg.points.clone(),
+                }),
+                // короткий речной участок между дорогами — переправа
+                RouteKind::River if leg.points.len() <= 2 => {
+                    let between_roads = li > 0
+                        && route.legs[li - 1].kind == RouteKind::Road
+                        && route
+                            .legs
+                            .get(li + 1)
+                            .is_some_and(|l| l.kind == RouteKind::Road);
+                    let (x, y) = leg.points[0];
+                    // по одному мосту на переправу, даже если по ней идут несколько маршрутов
+                    let built = structures.iter().any(|s| (s.x, s.y) == (x, y));
+                    if let (true, false, Some(kind)) =
+                        (between_roads, built, StructureKind::bridge(era))
+                    {
+                        structures.push(Structure {
+                            kind,
+                            era,
+                            x,
+                            y,
+                            city_id: owner_id.clone(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Гавани: место, где маршрут у города уходит в море
+    for (ci, city) in history.cities.iter().enumerate() {
+        let harbor = history
+            .trade
+            .routes
+            .iter()
+            .filter(|r| r.from_city == ci || r.to_city == ci)
+            .flat_map(|r| r.legs.windows(2))
+            .filter(|pair| pair[0].kind != RouteKind::SeaLane && pair[1].kind == RouteKind::SeaLane)
+            .filter_map(|pair| pair[0].points.last().copied())
+            .find(|&(x, y)| x.abs_diff(city.x).max(y.abs_diff(city.y)) <= HARBOR_MAX_DISTANCE);
+        if let Some((x, y)) = harbor {
+            structures.push(Structure {
+                kind: StructureKind::harbor(city_era[ci]),
+                era: city_era[ci],
+                x,
+                y,
+                city_id: city.id.clone(),
+            });
+        }
+    }
+
+    // Стены крупных городов и столиц
+    for (ci, city) in history.cities.iter().enumerate() {
+        if !city.is_capital && city.population < WALLED_CITY_POPULATION {
+            continue;
+        }
+        if let Some(kind) = StructureKind::wall(city_era[ci]) {
+            structures.push(Structure {
+                kind,
+                era: city_era[ci],
+                x: city.x,
+                y: city.y,
+                city_id: city.id.clone(),
+            });
+        }
+    }
+
+    // Рудник на самой высокой горной клетке в угодьях города
+    let sea_level = cfg.sea_level as f32;
+    for (ci, city) in history.cities.iter().enumerate() {
+        let Some(kind) = StructureKind::mine(city_era[ci]) else {
+            continue;
+        };
+        let r = CATCHMENT_RADIUS;
+        let mut best: Option<(f32, u32, u32)> = None;
+        for y in city.y.saturating_sub(r)..(city.y + r + 1).min(h) {
+            for x in city.x.saturating_sub(r)..(city.x + r + 1).min(w) {
+                let hv = hm.get(x, y);
+                let rel = (hv - sea_level) / (1.0 - sea_level).max(1e-6);
+                if rel >= MOUNTAIN_MIN_RELATIVE_HEIGHT && best.is_none_or(|b| hv > b.0) {
+                    best = Some((hv, x, y));
+                }
+            }
+        }
+        if let Some((_, x, y)) = best {
+            structures.push(Structure {
+                kind,
+                era: city_era[ci],
+                x,
+                y,
+                city_id: city.id.clone(),
+            });
+        }
+    }
+
+    // Карта эпох
+    let eras: Vec<Era> = history.factions.iter().map(|f| f.era()).collect();
+    let mut era_map: Vec<Option<Era>> = history
+        .territory
+        .owner
+        .iter()
+        .map(|o| o.and_then(|fi| eras.get(fi as usize).copied()))
+        .collect();
+    let mut raise = |x: u32, y: u32, era: Era| {
+        if x < w && y < h {
+            let cell = &mut era_map[(y * w + x) as usize];
+            *cell = (*cell).max(Some(era));
+        }
+    };
+    for road in &roads {
+        for &(x, y) in &road.points {
+            raise(x, y, road.era);
+        }
+    }
+    for s in &structures {
+        raise(s.x, s.y, s.era);
+    }
+
+    Infrastructure {
+        width: w,
+        height: h,
+        roads,
+        structures,
+        era_map,
+    }
+}
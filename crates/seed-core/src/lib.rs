@@ -1,17 +1,111 @@
-use seed_config::{CosmosConfig, WorldConfig};
 use thiserror::Error;
 
+pub mod astronomy;
 pub mod biome;
+pub mod calendar;
 pub mod catastrophe;
+pub mod chronicle;
+pub mod contours;
+pub mod culture;
+pub mod danger;
+pub mod deformation;
+pub mod diplomacy;
+pub mod director;
+pub mod ecosystem;
+pub mod events;
+pub mod foodweb;
+pub mod geo;
+pub mod geography;
+pub mod history;
+pub mod hydrography;
+pub mod infrastructure;
+pub mod mesh;
+pub mod names;
+pub mod navmesh;
 pub mod objects;
+pub mod pathfinding;
+pub mod planet;
+pub mod population;
+pub mod progress;
+pub mod quest_template;
+pub mod raster;
+pub mod raycast;
+pub mod relief;
+pub mod render;
+pub(crate) mod rng;
+pub mod seed_tree;
+pub mod settlements;
+pub mod surface;
+pub mod tech;
 pub mod terrain;
+pub mod territory;
+pub mod trade;
+pub mod volcano;
+pub mod war;
+pub mod world;
 
-pub use biome::{generate_biome_map_from_config, BiomeMap};
+pub use astronomy::{Astronomy, MoonPosition, SunPosition};
+pub use biome::{
+    compute_climate_map, generate_biome_map_from_config, generate_biome_map_window,
+    generate_biome_map_with_progress, BiomeMap, BiomeMapBuilder, ClimateMap,
+};
+pub use calendar::{Calendar, Season, SimulationContext, WorldClock, WorldDate};
 pub use catastrophe::{
-    apply_catastrophe_to_heightmap, generate_catastrophes, Catastrophe, CatastropheType,
+    apply_catastrophe_to_heightmap, apply_catastrophe_with_volcanoes, generate_catastrophes,
+    Catastrophe, CatastropheSchedule, CatastropheType,
+};
+pub use chronicle::{build_chronicle, Chronicle, ChronicleEntry};
+pub use contours::{extract_contours, ContourLine};
+pub use culture::{Architecture, Culture, CultureLayer, Religion};
+pub use danger::{compute_danger_map, DangerMap};
+pub use deformation::{
+    Decal, DeformChunkState, DeformDelta, DeformError, DeformationGrid, DeformationLayer, Stamp,
+};
+pub use diplomacy::{Diplomacy, Relation, RelationMatrix, Stance, Treaty};
+pub use director::{
+    AnchorKind, Director, PlayerState, PolicyViolation, Quest, QuestAnchor, QuestObjective,
+    QuestReward, QuestStatus, QuestType, RefusedEvent,
+};
+pub use ecosystem::{
+    generate_species_distribution, Ecosystem, EcosystemSample, SpeciesDistribution, SpeciesState,
+};
+pub use events::{EventBus, WorldEvent, WorldEventKind};
+pub use foodweb::{FeedingLink, FoodWeb, FoodWebIssue, TrophicRole};
+pub use geo::{destination, haversine_km, initial_bearing_deg, LatLon, MapGeo};
+pub use geography::{build_gazetteer, FeatureKind, GazetteerEntry};
+pub use history::{simulate_history, Faction, History, HistoryEvent, HistoryEventKind};
+pub use hydrography::{extract_coastlines, extract_lakes, extract_rivers, LakePolygon, RiverLine};
+pub use infrastructure::{build_infrastructure, Infrastructure, Road, Structure, StructureKind};
+pub use mesh::TerrainMesh;
+pub use names::{NameGenerator, NameStyle};
+pub use navmesh::{generate_navmesh, NavMesh, NavMeshOptions, NavPolygon};
+pub use objects::{
+    apply_cultural_architecture, generate_objects_for_chunk, generate_ruin_objects_for_chunk,
+    ObjectIndex, ObjectType, ProceduralObject,
 };
-pub use objects::{generate_objects_for_chunk, ObjectType, ProceduralObject};
-pub use terrain::{compute_flow_accumulation, generate_heightmap_from_config, Heightmap};
+pub use pathfinding::{find_path, PathError, PathOptions, TerrainPath};
+pub use planet::PlanetClimate;
+pub use population::{Demographics, PopulationSnapshot};
+pub use progress::{GenerationPhase, NoProgress, Progress};
+pub use quest_template::{QuestTemplate, TargetSelector};
+pub use raster::Mask;
+pub use raycast::{line_of_sight, raycast, RayHit, RayOptions};
+pub use seed_tree::SeedTree;
+pub use settlements::{compute_suitability, City, Ruin, RuinCause, SuitabilityMap};
+pub use surface::{compute_surface_map, SurfaceKind, SurfaceMap};
+pub use tech::Era;
+pub use terrain::{
+    compute_flow_accumulation, generate_heightmap_from_config, generate_heightmap_window,
+    generate_heightmap_with_progress, Heightmap, HeightmapBuilder, HeightmapWindows, MapWindow,
+    MAX_RELIEF_M,
+};
+pub use territory::{extract_borders, grow_territories, BorderLine, TerritoryMap};
+pub use trade::{generate_trade_network, RouteKind, TradeNetwork, TradeRoute};
+pub use volcano::{
+    VolcanicDeposits, VolcanicEvent, VolcanicEventKind, Volcano, VolcanoPhase, VolcanoSimulator,
+};
+pub use war::{Battle, War};
+pub use world::{Cosmos, Planet, World, CATASTROPHE_HORIZON_YEARS, DEFAULT_MAP_SIZE};
 
 #[derive(Debug, Error)]
 pub enum CoreError {
@@ -20,63 +114,3 @@ pub enum CoreError {
 }
 
 pub type Result<T> = std::result::Result<T, CoreError>;
-
-#[derive(Debug)]
-pub struct World {
-    pub id: String,
-    pub name: String,
-    pub cosmos: Cosmos,
-    // TODO: environment, geology, biomes, etc.
-}
-
-#[derive(Debug)]
-pub struct Cosmos {
-    pub active_planet: Planet,
-}
-
-#[derive(Debug)]
-pub struct Planet {
-    pub id: String,
-    pub name: String,
-    pub radius_km: f64,
-    pub gravity_ms2: f64,
-    pub day_length_hours: f64,
-    pub year_length_days: f64,
-}
-
-impl World {
-    pub fn from_config(cfg: &WorldConfig) -> Result<Self> {
-        let cosmos = Cosmos::from_config(&cfg.cosmos)?;
-
-        Ok(World {
-            id: cfg.world_id.clone(),
-            name: cfg.meta.name.clone(),
-            cosmos,
-        })
-    }
-}
-
-impl Cosmos {
-    pub fn from_config(cfg: &CosmosConfig) -> Result<Self> {
-        let active_id = &cfg.star_system.active_planet_id;
-        let planet_cfg = cfg
-            .star_system
-            .planets
-            .iter()
-            .find(|p| &p.id == active_id)
-            .ok_or_else(|| CoreError::Config(format!("Active planet '{active_id}' not found")))?;
-
-        let planet = Planet {
-            id: planet_cfg.id.clone(),
-            name: planet_cfg.name.clone(),
-            radius_km: planet_cfg.radius_km,
-            gravity_ms2: planet_cfg.gravity_ms2,
-            day_length_hours: planet_cfg.day_length_hours,
-            year_length_days: planet_cfg.year_length_days,
-        };
-
-        Ok(Cosmos {
-            active_planet: planet,
-        })
-    }
-}
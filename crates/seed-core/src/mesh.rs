@@ -0,0 +1,423 @@
+//! Треугольная сетка рельефа для экспорта в DCC-пакеты и движки.
+//! Вершина на каждую клетку карты; упрощение — стягивание рёбер по
+//! квадрикам ошибки (Garland–Heckbert) в одну из концевых вершин, так что
+//! уцелевшие вершины сохраняют свои UV и цвета. Край карты не двигается.
+
+use crate::render::Region;
+use crate::terrain::Heightmap;
+use seed_config::WorldConfig;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+#[derive(Debug, Clone)]
+pub struct TerrainMesh {
+    /// x — на восток, y — вверх, z — на юг, метры; начало — северо-западный угол
+    pub positions: Vec<[f32; 3]>,
+    /// Положение вершины на карте, 0..1 (u — по x, v — по y)
+    pub uvs: Vec<[f32; 2]>,
+    /// Треугольники против часовой стрелки при взгляде сверху
+    pub indices: Vec<u32>,
+}
+
+impl TerrainMesh {
+    /// Сетка по heightmap; высоты в метрах над уровнем моря, умноженные на
+    /// `vertical_scale`
+    pub fn from_heightmap(cfg: &WorldConfig, hm: &Heightmap, vertical_scale: f32) -> Self {
+        Self::from_region(cfg, hm, Region::full(hm), 1, vertical_scale)
+    }
+
+    /// Сетка участка `region` с вершиной через `step` клеток. Крайние
+    /// строка и столбец участка в сетке всегда, так что соседние участки с
+    /// общим краем стыкуются. Координаты и UV — как у сетки всей карты
+    pub fn from_region(
+        cfg: &WorldConfig,
+        hm: &Heightmap,
+        region: Region,
+        step: u32,
+        vertical_scale: f32,
+    ) -> Self {
+        let (w, h) = (hm.width, hm.height);
+        let cell_m = cfg.scale.region_size_km * 1000.0 / w.max(1) as f64;
+        let xs = grid_coords(region.x0, region.width, step);
+        let ys = grid_coords(region.y0, region.height, step);
+
+        let mut positions = Vec::with_capacity(xs.len() * ys.len());
+        let mut uvs = Vec::with_capacity(xs.len() * ys.len());
+        for &y in &ys {
+            for &x in &xs {
+                let elevation = hm.elevation_m(cfg.sea_level, x, y) as f32 * vertical_scale;
+                positions.push([
+                    (x as f64 * cell_m) as f32,
+                    elevation,
+                    (y as f64 * cell_m) as f32,
+                ]);
+                uvs.push([
+                    x as f32 / (w - 1).max(1) as f32,
+                    y as f32 / (h - 1).max(1) as f32,
+                ]);
+            }
+        }
+
+        let (cols, rows) = (xs.len() as u32, ys.len() as u32);
+        let mut indices =
+            Vec::with_capacity((cols.saturating_sub(1) * rows.saturating_sub(1) * 6) as usize);
+        for y in 0..rows.saturating_sub(1) {
+            for x in 0..cols.saturating_sub(1) {
+                let tl = y * cols + x;
+                let (tr, bl, br) = (tl + 1, tl + cols, tl + cols + 1);
+                indices.extend_from_slice(&[tl, bl, tr, tr, bl, br]);
+            }
+        }
+
+        Self {
+            positions,
+            uvs,
+            indices,
+        }
+    }
+
+    /// Нормали вершин `from_region` с теми же аргументами — по разностям
+    /// высот карты, а не по треугольникам, поэтому на стыке участков они
+    /// совпадают и швов в освещении нет
+    pub fn region_normals(
+        cfg: &WorldConfig,
+        hm: &Heightmap,
+        region: Region,
+        step: u32,
+        vertical_scale: f32,
+    ) -> Vec<[f32; 3]> {
+        let xs = grid_coords(region.x0, region.width, step);
+        grid_coords(region.y0, region.height, step)
+            .into_iter()
+            .flat_map(|y| xs.iter().map(move |&x| (x, y)))
+            .map(|cell| grid_normal(cfg, hm, cell, step, vertical_scale))
+            .collect()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Нормали вершин: сумма нормалей прилегающих треугольников (с весом площади)
+    pub fn normals(&self) -> Vec<[f32; 3]> {
+        let mut normals = vec![[0.0f32; 3]; self.positions.len()];
+        for t in self.indices.chunks_exact(3) {
+            let n = face_normal(
+                self.positions[t[0] as usize],
+                self.positions[t[1] as usize],
+                self.positions[t[2] as usize],
+            );
+            for &i in t {
+                for k in 0..3 {
+                    normals[i as usize][k] += n[k];
+                }
+            }
+        }
+        for n in &mut normals {
+            let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+            *n = if len > 0.0 {
+                [n[0] / len, n[1] / len, n[2] / len]
+            } else {
+                [0.0, 1.0, 0.0]
+            };
+        }
+        normals
+    }
+
+    /// Ограничивающий параллелепипед: (минимум, максимум)
+    pub fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        let mut lo = [f32::MAX; 3];
+        let mut hi = [f32::MIN; 3];
+        for p in &self.positions {
+            for k in 0..3 {
+                lo[k] = lo[k].min(p[k]);
+                hi[k] = hi[k].max(p[k]);
+            }
+        }
+        (lo, hi)
+    }
+
+    /// Оставляет примерно долю `keep` (0..1] треугольников, стягивая рёбра
+    /// с наименьшей квадрикой ошибки
+    pub fn simplify(&mut self, keep: f32) {
+        let keep = keep.clamp(0.0, 1.0);
+        let target = (self.triangle_count() as f32 * keep).ceil() as usize;
+        if target >= self.triangle_count() {
+            return;
+        }
+
+        let n = self.positions.len();
+        let pos: Vec<[f64; 3]> = self
+            .positions
+            .iter()
+            .map(|p| [p[0] as f64, p[1] as f64, p[2] as f64])
+            .collect();
+        let mut tris: Vec<[u32; 3]> = self
+            .indices
+            .chunks_exact(3)
+            .map(|t| [t[0], t[1], t[2]])
+            .collect();
+        let mut alive = vec![true; tris.len()];
+        let mut live = tris.len();
+
+        let mut vtris: Vec<Vec<u32>> = vec![Vec::new(); n];
+        let mut quadrics = vec![Quadric::default(); n];
+        for (ti, t) in tris.iter().enumerate() {
+            let q =
+                Quadric::from_triangle(pos[t[0] as usize], pos[t[1] as usize], pos[t[2] as usize]);
+            for &v in t {
+                vtris[v as usize].push(ti as u32);
+                quadrics[v as usize].add(&q);
+            }
+        }
+        // вершины края карты неподвижны, иначе сетка отойдёт от границ региона
+        let locked: Vec<bool> = self
+            .uvs
+            .iter()
+            .map(|uv| uv[0] <= 0.0 || uv[0] >= 1.0 || uv[1] <= 0.0 || uv[1] >= 1.0)
+            .collect();
+
+        let mut removed = vec![false; n];
+        let mut version = vec![0u32; n];
+        let mut heap = BinaryHeap::new();
+        let push =
+            |heap: &mut BinaryHeap<_>, quadrics: &[Quadric], version: &[u32], u: u32, v: u32| {
+                let mut q = quadrics[u as usize];
+                q.add(&quadrics[v as usize]);
+                let cost = q.error(pos[v as usize]).max(0.0);
+                heap.push(Reverse((
+                    cost.to_bits(),
+                    u,
+                    v,
+                    version[u as usize],
+                    version[v as usize],
+                )));
+            };
+        for t in &tris {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                if !locked[a as usize] {
+                    push(&mut heap, &quadrics, &version, a, b);
+                }
+                if !locked[b as usize] {
+                    push(&mut heap, &quadrics, &version, b, a);
+                }
+            }
+        }
+
+        while live > target {
+            let Some(Reverse((_, u, v, vu, vv))) = heap.pop() else {
+                break;
+            };
+            let (ui, vi) = (u as usize, v as usize);
+            if removed[ui] || removed[vi] || version[ui] != vu || version[vi] != vv {
+                continue;
+            }
+            vtris[ui].retain(|&t| alive[t as usize]);
+            vtris[vi].retain(|&t| alive[t as usize]);
+
+            // ребро u–v ещё существует?
+            let shared: Vec<u32> = vtris[ui]
+                .iter()
+                .copied()
+                .filter(|&t| tris[t as usize].contains(&v))
+                .collect();
+            if shared.is_empty() {
+                continue;
+            }
+            // условие связности: общих соседей ровно столько, сколько общих треугольников
+            let nu = neighbors(ui, &vtris, &tris);
+            let nv = neighbors(vi, &vtris, &tris);
+            let common = nu.iter().filter(|w| nv.binary_search(w).is_ok()).count();
+            if common != shared.len() {
+                continue;
+            }
+            // не выворачиваем треугольники
+            let flips = vtris[ui].iter().any(|&t| {
+                let tri = tris[t as usize];
+                if tri.contains(&v) {
+                    return false;
+                }
+                let before = face_normal64(
+                    pos[tri[0] as usize],
+                    pos[tri[1] as usize],
+                    pos[tri[2] as usize],
+                );
+                let moved = tri.map(|x| if x == u { pos[vi] } else { pos[x as usize] });
+                let after = face_normal64(moved[0], moved[1], moved[2]);
+                // сверху сетка должна оставаться однозначной: без складок в плане
+                dot(before, after) <= 0.0 || after[1] <= 0.0
+            });
+            if flips {
+                continue;
+            }
+
+            for t in std::mem::take(&mut vtris[ui]) {
+                let tri = &mut tris[t as usize];
+                if tri.contains(&v) {
+                    alive[t as usize] = false;
+                    live -= 1;
+                } else {
+                    for x in tri.iter_mut() {
+                        if *x == u {
+                            *x = v;
+                        }
+                    }
+                    vtris[vi].push(t);
+                }
+            }
+            removed[ui] = true;
+            let qu = quadrics[ui];
+            quadrics[vi].add(&qu);
+            version[vi] += 1;
+
+            for w in neighbors(vi, &vtris, &tris) {
+                if !locked[w as usize] {
+                    push(&mut heap, &quadrics, &version, w, v);
+                }
+                if !locked[vi] {
+                    push(&mut heap, &quadrics, &version, v, w);
+                }
+            }
+        }
+
+        // Уплотнение: только живые вершины и треугольники
+        let mut remap = vec![u32::MAX; n];
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::with_capacity(live * 3);
+        for (ti, t) in tris.iter().enumerate() {
+            if !alive[ti] {
+                continue;
+            }
+            for &x in t {
+                if remap[x as usize] == u32::MAX {
+                    remap[x as usize] = positions.len() as u32;
+                    positions.push(self.positions[x as usize]);
+                    uvs.push(self.uvs[x as usize]);
+                }
+                indices.push(remap[x as usize]);
+            }
+        }
+        self.positions = positions;
+        self.uvs = uvs;
+        self.indices = indices;
+    }
+}
+
+/// Клетки `start..start + len` через `step`; последняя — всегда
+fn grid_coords(start: u32, len: u32, step: u32) -> Vec<u32> {
+    let Some(last) = (start + len).checked_sub(1) else {
+        return Vec::new();
+    };
+    let mut coords: Vec<u32> = (start..=last).step_by(step.max(1) as usize).collect();
+    if coords.last() != Some(&last) {
+        coords.push(last);
+    }
+    coords
+}
+
+/// Нормаль рельефа в клетке (x, y) по разностям высот через `step` клеток
+fn grid_normal(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    (x, y): (u32, u32),
+    step: u32,
+    vertical_scale: f32,
+) -> [f32; 3] {
+    let cell_m = cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64;
+    let height = |x: u32, y: u32| hm.elevation_m(cfg.sea_level, x, y) * vertical_scale as f64;
+    let step = step.max(1);
+    let (xl, xr) = (x.saturating_sub(step), (x + step).min(hm.width - 1));
+    let (yu, yd) = (y.saturating_sub(step), (y + step).min(hm.height - 1));
+    let dx = (height(xr, y) - height(xl, y)) / ((xr - xl).max(1) as f64 * cell_m);
+    let dz = (height(x, yd) - height(x, yu)) / ((yd - yu).max(1) as f64 * cell_m);
+    let len = (dx * dx + 1.0 + dz * dz).sqrt();
+    [(-dx / len) as f32, (1.0 / len) as f32, (-dz / len) as f32]
+}
+
+/// Соседние вершины `x` по живым треугольникам, по возрастанию
+fn neighbors(x: usize, vtris: &[Vec<u32>], tris: &[[u32; 3]]) -> Vec<u32> {
+    let mut out: Vec<u32> = vtris[x]
+        .iter()
+        .flat_map(|&t| tris[t as usize])
+        .filter(|&w| w as usize != x)
+        .collect();
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+/// Симметричная матрица 4×4 квадрики ошибки (верхний треугольник)
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_triangle(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> Self {
+        let n = face_normal64(a, b, c);
+        let len = dot(n, n).sqrt();
+        if len == 0.0 {
+            return Self::default();
+        }
+        let [x, y, z] = [n[0] / len, n[1] / len, n[2] / len];
+        let d = -(x * a[0] + y * a[1] + z * a[2]);
+        Self([
+            x * x,
+            x * y,
+            x * z,
+            x * d,
+            y * y,
+            y * z,
+            y * d,
+            z * z,
+            z * d,
+            d * d,
+        ])
+    }
+
+    fn add(&mut self, o: &Quadric) {
+        for k in 0..10 {
+            self.0[k] += o.0[k];
+        }
+    }
+
+    /// vᵀ Q v для точки v = (x, y, z, 1)
+    fn error(&self, p: [f64; 3]) -> f64 {
+        let q = &self.0;
+        let [x, y, z] = p;
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+fn face_normal64(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> [f64; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
@@ -0,0 +1,275 @@
+//! Процедурные имена: цепь Маркова второго порядка по буквам,
+//! обученная на небольшом корпусе для каждого стиля.
+//!
+//! Один и тот же стиль и seed всегда дают одну и ту же последовательность имён.
+
+use crate::rng::SplitMix64;
+use seed_config::FactionPresetConfig;
+use std::collections::{HashMap, HashSet};
+
+/// Профиль стиля имён
+#[derive(Debug)]
+pub struct NameStyle {
+    pub id: &'static str,
+    corpus: &'static [&'static str],
+    min_len: usize,
+    max_len: usize,
+}
+
+pub const NAME_STYLES: &[NameStyle] = &[
+    NameStyle {
+        id: "common",
+        corpus: &[
+            "aldren", "berin", "caldor", "dunmere", "elwyn", "farrow", "galen", "harrow", "ilsen",
+            "korrin", "lorian", "marden", "norwyn", "orrin", "pellam", "rovan", "selden", "tarrow",
+            "varden", "wendel",
+        ],
+        min_len: 4,
+        max_len: 9,
+    },
+    NameStyle {
+        id: "nordic",
+        corpus: &[
+            "askeby",
+            "bjornvik",
+            "dalsgard",
+            "eskil",
+            "frosta",
+            "grimsholm",
+            "haldor",
+            "isfjord",
+            "jarnvik",
+            "kolby",
+            "lofthus",
+            "myrdal",
+            "nordheim",
+            "ormsund",
+            "ragnvald",
+            "skarheim",
+            "torvik",
+            "ulfsby",
+            "vindal",
+        ],
+        min_len: 4,
+        max_len: 10,
+    },
+    NameStyle {
+        id: "latin",
+        corpus: &[
+            "aurelia",
+            "brundisium",
+            "castellum",
+            "duraveum",
+            "emerita",
+            "florentia",
+            "genua",
+            "hispalis",
+            "italica",
+            "lucentum",
+            "mediolan",
+            "narbona",
+            "ostia",
+            "placentia",
+            "ravenna",
+            "saguntum",
+            "tarraco",
+            "valentia",
+            "verona",
+        ],
+        min_len: 5,
+        max_len: 10,
+    },
+    NameStyle {
+        id: "desert",
+        corpus: &[
+            "alqasir", "barrak", "dahrun", "esfahar", "farzan", "ghadir", "hamrah", "jazirah",
+            "kasrun", "marrakh", "nasrin", "qadesh", "rashan", "sahrim", "tabuk", "umran",
+            "wadhar", "zafar",
+        ],
+        min_len: 4,
+        max_len: 9,
+    },
+    NameStyle {
+        id: "sylvan",
+        corpus: &[
+            "aelindra",
+            "briallen",
+            "caelwyn",
+            "elarith",
+            "faelin",
+            "galathil",
+            "ilyndra",
+            "lysaria",
+            "miravel",
+            "naerys",
+            "orlindel",
+            "sylvara",
+            "thalion",
+            "valendris",
+            "yllara",
+        ],
+        min_len: 5,
+        max_len: 10,
+    },
+    NameStyle {
+        id: "tribal",
+        corpus: &[
+            "akuna", "bomani", "chaka", "dumaka", "enuka", "kibo", "limpa", "mbaru", "nkala",
+            "okapa", "tamba", "uzuri", "wanjo", "zamba",
+        ],
+        min_len: 4,
+        max_len: 8,
+    },
+];
+
+impl NameStyle {
+    pub fn by_id(id: &str) -> Option<&'static NameStyle> {
+        NAME_STYLES.iter().find(|s| s.id == id)
+    }
+
+    /// Стиль по умолчанию для мира и географии
+    pub fn common() -> &'static NameStyle {
+        &NAME_STYLES[0]
+    }
+
+    /// Стиль фракции: явный `nameStyle` из пресета, иначе — по предпочитаемым биомам
+    pub fn for_faction(preset: &FactionPresetConfig) -> &'static NameStyle {
+        if let Some(style) = preset.name_style.as_deref().and_then(NameStyle::by_id) {
+            return style;
+        }
+        for biome in &preset.preferred_biomes {
+            let id = match biome.as_str() {
+                b if b.contains("desert") || b.contains("savanna") => "desert",
+                b if b.contains("taiga") || b.contains("tundra") || b.contains("ice") => "nordic",
+                b if b.contains("tropical") => "tribal",
+                b if b.contains("forest") || b.contains("wetland") => "sylvan",
+                b if b.contains("mediterranean") => "latin",
+                _ => continue,
+            };
+            if let Some(style) = NameStyle::by_id(id) {
+                return style;
+            }
+        }
+        // ничего не подошло — детерминированно по id фракции
+        let h = preset
+            .id
+            .bytes()
+            .fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32));
+        &NAME_STYLES[h as usize % NAME_STYLES.len()]
+    }
+}
+
+const START: char = '^';
+const END: char = '$';
+
+/// Генератор уникальных имён одного стиля
+#[derive(Debug, Clone)]
+pub struct NameGenerator {
+    style: &'static NameStyle,
+    chain: HashMap<(char, char), Vec<char>>,
+    rng: SplitMix64,
+    used: HashSet<String>,
+}
+
+impl NameGenerator {
+    pub fn new(style: &'static NameStyle, seed: u64) -> Self {
+        let mut chain: HashMap<(char, char), Vec<char>> = HashMap::new();
+        for word in style.corpus {
+            let mut state = (START, START);
+            for c in word.chars().chain(std::iter::once(END)) {
+                chain.entry(state).or_default().push(c);
+                state = (state.1, c);
+            }
+        }
+        Self {
+            style,
+            chain,
+            rng: SplitMix64::new(seed),
+            used: HashSet::new(),
+        }
+    }
+
+    pub fn style(&self) -> &'static NameStyle {
+        self.style
+    }
+
+    /// Следующее имя, не повторяющее ранее выданные этим генератором
+    pub fn next_name(&mut self) -> String {
+        let mut fallback = None;
+        for attempt in 0..64 {
+            let Some(raw) = self.sample() else { continue };
+            // первые попытки отбрасывают слова, дословно взятые из корпуса
+            let from_corpus = self.style.corpus.contains(&raw.as_str());
+            let name = capitalize(&raw);
+            if self.used.contains(&name) || (from_corpus && attempt < 32) {
+                fallback.get_or_insert(name);
+                continue;
+            }
+            self.used.insert(name.clone());
+            return name;
+        }
+
+        // Пространство имён исчерпано — нумеруем
+        let base = fallback.unwrap_or_else(|| capitalize(self.style.corpus[0]));
+        let mut n = 2;
+        loop {
+            let name = format!("{base} {}", roman(n));
+            if self.used.insert(name.clone()) {
+                return name;
+            }
+            n += 1;
+        }
+    }
+
+    fn sample(&mut self) -> Option<String> {
+        let mut state = (START, START);
+        let mut out = String::new();
+        loop {
+            let next = self.chain.get(&state)?;
+            let c = next[self.rng.below(next.len())];
+            if c == END {
+                break;
+            }
+            out.push(c);
+            if out.len() > self.style.max_len {
+                return None;
+            }
+            state = (state.1, c);
+        }
+        (out.len() >= self.style.min_len).then_some(out)
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn roman(mut n: u32) -> String {
+    const TABLE: &[(u32, &str)] = &[
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut out = String::new();
+    for &(v, s) in TABLE {
+        while n >= v {
+            out.push_str(s);
+            n -= v;
+        }
+    }
+    out
+}
@@ -0,0 +1,335 @@
+//! Навигационная сетка для серверов и ИИ: проходимая поверхность мира
+//! полигонами с соседством по рёбрам, как у Recast/Detour. Пространство то
+//! же, что у `TerrainMesh`: метры, x — на восток, y — вверх (высота над
+//! уровнем моря), z — на юг, начало — северо-западный угол.
+//!
+//! Клетка карты делится на `subdivisions`² ячеек с билинейными высотами.
+//! Ячейка непроходима, если она круче `max_slope_deg` (по двум
+//! треугольникам, как в сетке рельефа), глубже `max_water_depth_m` под
+//! водой или её задевает препятствие — дерево, камень, дом — с запасом
+//! `agent_radius_m`. Проходимые ячейки жадно сливаются в прямоугольники
+//! не больше `max_polygon_cells` по стороне; вершины соседей на сторонах
+//! прямоугольника тоже входят в полигон, так что у каждого ребра не
+//! больше одного соседа. Полигон — плоское приближение рельефа под ним:
+//! чем меньше `max_polygon_cells`, тем точнее высоты.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::objects::{ObjectType, ProceduralObject};
+use crate::terrain::Heightmap;
+
+/// Больше ячеек на клетку карты не делим — сетка растёт квадратично
+pub const MAX_SUBDIVISIONS: u32 = 16;
+
+/// Настройки сетки; в JSON — camelCase, недостающие поля по умолчанию
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct NavMeshOptions {
+    /// Круче не пройти, градусы
+    pub max_slope_deg: f64,
+    /// Вброд можно пройти на такой глубине, м; 0 — вода непроходима
+    pub max_water_depth_m: f64,
+    /// Радиус агента, м: на столько он держится от препятствий
+    pub agent_radius_m: f64,
+    /// На сколько ячеек по каждой стороне делится клетка карты, 1..=16
+    pub subdivisions: u32,
+    /// Сторона самого большого полигона, ячейки
+    pub max_polygon_cells: u32,
+}
+
+impl Default for NavMeshOptions {
+    fn default() -> Self {
+        NavMeshOptions {
+            max_slope_deg: 45.0,
+            max_water_depth_m: 0.0,
+            agent_radius_m: 0.5,
+            subdivisions: 4,
+            max_polygon_cells: 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NavPolygon {
+    /// Номера вершин против часовой стрелки при взгляде сверху
+    pub vertices: Vec<u32>,
+    /// Сосед за ребром от `vertices[i]` к следующей вершине
+    pub neighbors: Vec<Option<u32>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NavMesh {
+    /// x — на восток, y — вверх, z — на юг, метры
+    pub vertices: Vec<[f32; 3]>,
+    pub polygons: Vec<NavPolygon>,
+}
+
+impl NavMesh {
+    /// Wavefront OBJ: вершины и грани-полигоны; соседство из граней
+    /// восстанавливается по общим рёбрам
+    pub fn to_obj(&self) -> String {
+        let mut out = String::from("# navmesh\n");
+        for [x, y, z] in &self.vertices {
+            let _ = writeln!(out, "v {x} {y} {z}");
+        }
+        for poly in &self.polygons {
+            out.push('f');
+            for v in &poly.vertices {
+                let _ = write!(out, " {}", v + 1);
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Площадь проходимой поверхности в плане, м²
+    pub fn area_m2(&self) -> f64 {
+        self.polygons
+            .iter()
+            .map(|p| {
+                let n = p.vertices.len();
+                let twice: f64 = (0..n)
+                    .map(|i| {
+                        let a = self.vertices[p.vertices[i] as usize];
+                        let b = self.vertices[p.vertices[(i + 1) % n] as usize];
+                        a[0] as f64 * b[2] as f64 - b[0] as f64 * a[2] as f64
+                    })
+                    .sum();
+                twice.abs() / 2.0
+            })
+            .sum()
+    }
+}
+
+/// Навигационная сетка по рельефу с препятствиями `obstacles` (объекты
+/// из `generate_objects_for_chunk`, координаты — клетки карты)
+pub fn generate_navmesh(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    obstacles: &[ProceduralObject],
+    options: &NavMeshOptions,
+) -> NavMesh {
+    if hm.width < 2 || hm.height < 2 {
+        return NavMesh::default();
+    }
+    let grid = Grid::new(cfg, hm, options.subdivisions);
+    let mut walkable = grid.walkable(options);
+    grid.carve(&mut walkable, obstacles, options.agent_radius_m.max(0.0));
+    let rects = merge_rects(&walkable, grid.cols, grid.rows, options.max_polygon_cells);
+    build_mesh(&grid, &rects)
+}
+
+/// Радиус, на который объект перекрывает проход, м; `None` — не мешает
+fn obstacle_radius_m(obj: &ProceduralObject) -> Option<f64> {
+    let base = match obj.object_type {
+        ObjectType::TreeConifer | ObjectType::TreeDeciduous | ObjectType::TreePalm => 0.4,
+        ObjectType::Cactus => 0.3,
+        ObjectType::RockMedium => 0.6,
+        ObjectType::RockLarge => 1.5,
+        ObjectType::BoulderCluster => 3.0,
+        ObjectType::CollapsedWall => 2.0,
+        ObjectType::HouseWood => 4.0,
+        ObjectType::HouseStone | ObjectType::HouseMedieval => 5.0,
+        ObjectType::RockSmall
+        | ObjectType::Bush
+        | ObjectType::Grass
+        | ObjectType::Ruins
+        | ObjectType::OvergrownFoundation => return None,
+    };
+    Some(base * obj.scale.max(0.0) as f64)
+}
+
+/// Решётка ячеек: `cols`×`rows`, по `sub` на клетку карты
+struct Grid<'a> {
+    hm: &'a Heightmap,
+    sea_level: f64,
+    sub: u32,
+    cols: usize,
+    rows: usize,
+    /// Сторона ячейки, м
+    cell_m: f64,
+}
+
+impl<'a> Grid<'a> {
+    fn new(cfg: &WorldConfig, hm: &'a Heightmap, subdivisions: u32) -> Self {
+        let sub = subdivisions.clamp(1, MAX_SUBDIVISIONS);
+        Grid {
+            hm,
+            sea_level: cfg.sea_level,
+            sub,
+            cols: ((hm.width - 1) * sub) as usize,
+            rows: ((hm.height - 1) * sub) as usize,
+            cell_m: cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64 / sub as f64,
+        }
+    }
+
+    /// Высота узла решётки (gx, gz), м — билинейно между клетками карты
+    fn elevation(&self, gx: usize, gz: usize) -> f64 {
+        let sub = self.sub as usize;
+        let (x0, z0) = ((gx / sub) as u32, (gz / sub) as u32);
+        let (fx, fz) = (
+            (gx % sub) as f64 / sub as f64,
+            (gz % sub) as f64 / sub as f64,
+        );
+        let x1 = (x0 + 1).min(self.hm.width - 1);
+        let z1 = (z0 + 1).min(self.hm.height - 1);
+        let e = |x, z| self.hm.elevation_m(self.sea_level, x, z);
+        let top = e(x0, z0) * (1.0 - fx) + e(x1, z0) * fx;
+        let bottom = e(x0, z1) * (1.0 - fx) + e(x1, z1) * fx;
+        top * (1.0 - fz) + bottom * fz
+    }
+
+    fn vertex(&self, gx: usize, gz: usize) -> [f32; 3] {
+        [
+            (gx as f64 * self.cell_m) as f32,
+            self.elevation(gx, gz) as f32,
+            (gz as f64 * self.cell_m) as f32,
+        ]
+    }
+
+    /// Проходимость ячеек по уклону и воде
+    fn walkable(&self, options: &NavMeshOptions) -> Vec<bool> {
+        let min_normal_y = options.max_slope_deg.clamp(0.0, 90.0).to_radians().cos();
+        let min_elevation = -options.max_water_depth_m.max(0.0);
+        let mut out = Vec::with_capacity(self.cols * self.rows);
+        for gz in 0..self.rows {
+            for gx in 0..self.cols {
+                let [tl, tr, bl, br] = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .map(|(dx, dz)| self.elevation(gx + dx, gz + dz));
+                let dry = tl.min(tr).min(bl).min(br) >= min_elevation;
+                // треугольники tl-bl-tr и tr-bl-br, как в `TerrainMesh`
+                let flat = [(tr - tl, bl - tl), (br - bl, br - tr)]
+                    .iter()
+                    .all(|&(dx, dz)| normal_y(dx, dz, self.cell_m) >= min_normal_y);
+                out.push(dry && flat);
+            }
+        }
+        out
+    }
+
+    /// Закрывает ячейки, которые задевают препятствия с запасом `margin_m`
+    fn carve(&self, walkable: &mut [bool], obstacles: &[ProceduralObject], margin_m: f64) {
+        let map_cell_m = self.cell_m * self.sub as f64;
+        for obj in obstacles {
+            let Some(radius) = obstacle_radius_m(obj) else {
+                continue;
+            };
+            let r = radius + margin_m;
+            let (cx, cz) = (obj.x as f64 * map_cell_m, obj.y as f64 * map_cell_m);
+            let range = |c: f64, n: usize| {
+                let lo = ((c - r) / self.cell_m).floor().max(0.0) as usize;
+                let hi = (((c + r) / self.cell_m).floor().max(0.0) as usize).min(n - 1);
+                lo..=hi
+            };
+            for gz in range(cz, self.rows) {
+                for gx in range(cx, self.cols) {
+                    // ближайшая к центру точка ячейки
+                    let nx = cx.clamp(gx as f64 * self.cell_m, (gx + 1) as f64 * self.cell_m);
+                    let nz = cz.clamp(gz as f64 * self.cell_m, (gz + 1) as f64 * self.cell_m);
+                    if (nx - cx).powi(2) + (nz - cz).powi(2) <= r * r {
+                        walkable[gz * self.cols + gx] = false;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Вертикальная составляющая единичной нормали плоскости с подъёмом `dx`
+/// вдоль x и `dz` вдоль z на `cell` метров
+fn normal_y(dx: f64, dz: f64, cell: f64) -> f64 {
+    let (gx, gz) = (dx / cell, dz / cell);
+    1.0 / (1.0 + gx * gx + gz * gz).sqrt()
+}
+
+/// Прямоугольник ячеек: столбцы x0..x1, строки z0..z1
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x0: usize,
+    z0: usize,
+    x1: usize,
+    z1: usize,
+}
+
+/// Жадно покрывает проходимые ячейки прямоугольниками: вправо, пока можно,
+/// затем вниз целыми строками
+fn merge_rects(walkable: &[bool], cols: usize, rows: usize, max_cells: u32) -> Vec<Rect> {
+    let max = max_cells.max(1) as usize;
+    let mut used = vec![false; walkable.len()];
+    let free = |used: &[bool], x: usize, z: usize| walkable[z * cols + x] && !used[z * cols + x];
+    let mut rects = Vec::new();
+    for z0 in 0..rows {
+        for x0 in 0..cols {
+            if !free(&used, x0, z0) {
+                continue;
+            }
+            let mut x1 = x0 + 1;
+            while x1 < cols && x1 - x0 < max && free(&used, x1, z0) {
+                x1 += 1;
+            }
+            let mut z1 = z0 + 1;
+            while z1 < rows && z1 - z0 < max && (x0..x1).all(|x| free(&used, x, z1)) {
+                z1 += 1;
+            }
+            for z in z0..z1 {
+                used[z * cols + x0..z * cols + x1].fill(true);
+            }
+            rects.push(Rect { x0, z0, x1, z1 });
+        }
+    }
+    rects
+}
+
+fn build_mesh(grid: &Grid, rects: &[Rect]) -> NavMesh {
+    let corners: HashSet<(usize, usize)> = rects
+        .iter()
+        .flat_map(|r| [(r.x0, r.z0), (r.x1, r.z0), (r.x0, r.z1), (r.x1, r.z1)])
+        .collect();
+
+    let mut mesh = NavMesh::default();
+    let mut index: HashMap<(usize, usize), u32> = HashMap::new();
+    let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+    for (p, r) in rects.iter().enumerate() {
+        // против часовой стрелки сверху: вниз по западной стороне, вправо
+        // по южной, вверх по восточной, влево по северной
+        let mut ring = Vec::new();
+        ring.extend((r.z0..r.z1).map(|z| (r.x0, z)));
+        ring.extend((r.x0..r.x1).map(|x| (x, r.z1)));
+        ring.extend((r.z0 + 1..=r.z1).rev().map(|z| (r.x1, z)));
+        ring.extend((r.x0 + 1..=r.x1).rev().map(|x| (x, r.z0)));
+        let vertices: Vec<u32> = ring
+            .into_iter()
+            .filter(|pt| corners.contains(pt))
+            .map(|(gx, gz)| {
+                *index.entry((gx, gz)).or_insert_with(|| {
+                    mesh.vertices.push(grid.vertex(gx, gz));
+                    mesh.vertices.len() as u32 - 1
+                })
+            })
+            .collect();
+        let n = vertices.len();
+        for i in 0..n {
+            edges.insert((vertices[i], vertices[(i + 1) % n]), p as u32);
+        }
+        mesh.polygons.push(NavPolygon {
+            vertices,
+            neighbors: Vec::new(),
+        });
+    }
+
+    for poly in &mut mesh.polygons {
+        let n = poly.vertices.len();
+        poly.neighbors = (0..n)
+            .map(|i| {
+                edges
+                    .get(&(poly.vertices[(i + 1) % n], poly.vertices[i]))
+                    .copied()
+            })
+            .collect();
+    }
+    mesh
+}
@@ -1,9 +1,16 @@
+use std::collections::HashMap;
+
 use crate::biome::BiomeMap;
+use crate::culture::CultureLayer;
+use crate::rng::SplitMix64;
+use crate::seed_tree::SeedTree;
+use crate::settlements::{Ruin, RuinCause};
 use crate::terrain::Heightmap;
-use noise::{NoiseFn, Perlin};
+use noise::NoiseFn;
 use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProceduralObject {
     pub x: f32,
     pub y: f32,
@@ -12,26 +19,80 @@ pub struct ProceduralObject {
     pub scale: f32,
     pub rotation_y: f32,
     pub variant: u8,
+    /// Возраст следов прошлого (руины), лет; `None` у живой природы и домов
+    pub age_years: Option<u32>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ObjectType {
-    TreeConifer,    // Хвойное дерево
-    TreeDeciduous,  // Лиственное дерево
-    TreePalm,       // Пальма
-    RockSmall,      // Маленький камень
-    RockMedium,     // Средний камень
-    RockLarge,      // Большой валун
-    BoulderCluster, // Группа камней
-    Bush,           // Куст
-    Grass,          // Трава (кластер)
-    Cactus,         // Кактус
-    HouseWood,      // Деревянный дом
-    HouseStone,     // Каменный дом
-    HouseMedieval,  // Средневековый дом
+    TreeConifer,         // Хвойное дерево
+    TreeDeciduous,       // Лиственное дерево
+    TreePalm,            // Пальма
+    RockSmall,           // Маленький камень
+    RockMedium,          // Средний камень
+    RockLarge,           // Большой валун
+    BoulderCluster,      // Группа камней
+    Bush,                // Куст
+    Grass,               // Трава (кластер)
+    Cactus,              // Кактус
+    HouseWood,           // Деревянный дом
+    HouseStone,          // Каменный дом
+    HouseMedieval,       // Средневековый дом
+    Ruins,               // Руины разрушенного города (POI)
+    CollapsedWall,       // Обрушенная стена
+    OvergrownFoundation, // Заросший фундамент
+}
+
+impl ObjectType {
+    /// Все типы; номер в списке — числовой id типа
+    pub const ALL: [ObjectType; 16] = [
+        ObjectType::TreeConifer,
+        ObjectType::TreeDeciduous,
+        ObjectType::TreePalm,
+        ObjectType::RockSmall,
+        ObjectType::RockMedium,
+        ObjectType::RockLarge,
+        ObjectType::BoulderCluster,
+        ObjectType::Bush,
+        ObjectType::Grass,
+        ObjectType::Cactus,
+        ObjectType::HouseWood,
+        ObjectType::HouseStone,
+        ObjectType::HouseMedieval,
+        ObjectType::Ruins,
+        ObjectType::CollapsedWall,
+        ObjectType::OvergrownFoundation,
+    ];
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Имя, как в JSON
+    pub fn name(self) -> &'static str {
+        match self {
+            ObjectType::TreeConifer => "TreeConifer",
+            ObjectType::TreeDeciduous => "TreeDeciduous",
+            ObjectType::TreePalm => "TreePalm",
+            ObjectType::RockSmall => "RockSmall",
+            ObjectType::RockMedium => "RockMedium",
+            ObjectType::RockLarge => "RockLarge",
+            ObjectType::BoulderCluster => "BoulderCluster",
+            ObjectType::Bush => "Bush",
+            ObjectType::Grass => "Grass",
+            ObjectType::Cactus => "Cactus",
+            ObjectType::HouseWood => "HouseWood",
+            ObjectType::HouseStone => "HouseStone",
+            ObjectType::HouseMedieval => "HouseMedieval",
+            ObjectType::Ruins => "Ruins",
+            ObjectType::CollapsedWall => "CollapsedWall",
+            ObjectType::OvergrownFoundation => "OvergrownFoundation",
+        }
+    }
 }
 
 /// Генерирует процедурные объекты для чанка мира
+#[allow(clippy::too_many_arguments)]
 pub fn generate_objects_for_chunk(
     cfg: &WorldConfig,
     hm: &Heightmap,
@@ -48,10 +109,11 @@ pub fn generate_objects_for_chunk(
     let sea_level = cfg.sea_level as f32;
 
     // Разные генераторы шума для разных типов объектов
-    let noise_trees = Perlin::new((base_seed ^ 0xAAAA) as u32);
-    let noise_rocks = Perlin::new((base_seed ^ 0xBBBB) as u32);
-    let noise_houses = Perlin::new((base_seed ^ 0xCCCC) as u32);
-    let noise_detail = Perlin::new((base_seed ^ 0xDDDD) as u32);
+    let seeds = SeedTree::new(base_seed).derive("objects");
+    let noise_trees = seeds.derive("trees").perlin();
+    let noise_rocks = seeds.derive("rocks").perlin();
+    let noise_houses = seeds.derive("houses").perlin();
+    let noise_detail = seeds.derive("detail").perlin();
 
     for y in chunk_y..(chunk_y + chunk_height).min(hm.height) {
         for x in chunk_x..(chunk_x + chunk_width).min(hm.width) {
@@ -136,6 +198,7 @@ pub fn generate_objects_for_chunk(
                         scale: scale as f32,
                         rotation_y,
                         variant,
+                        age_years: None,
                     });
                 }
             }
@@ -181,6 +244,7 @@ pub fn generate_objects_for_chunk(
                     scale: scale as f32,
                     rotation_y,
                     variant,
+                    age_years: None,
                 });
             }
 
@@ -209,6 +273,7 @@ pub fn generate_objects_for_chunk(
                         scale: 1.0,
                         rotation_y,
                         variant: 0,
+                        age_years: None,
                     });
                 }
             }
@@ -218,6 +283,180 @@ pub fn generate_objects_for_chunk(
     objects
 }
 
+/// Объекты карты по чанкам `chunk_cells`×`chunk_cells` клеток: чанк
+/// генерируется `generate_objects_for_chunk` при первом запросе и хранится
+/// до `invalidate`
+#[derive(Debug, Clone)]
+pub struct ObjectIndex {
+    chunk_cells: u32,
+    chunks: HashMap<(u32, u32), Vec<ProceduralObject>>,
+}
+
+impl ObjectIndex {
+    pub fn new(chunk_cells: u32) -> Self {
+        ObjectIndex {
+            chunk_cells: chunk_cells.max(1),
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn chunk_cells(&self) -> u32 {
+        self.chunk_cells
+    }
+
+    /// Объекты чанка (`chunk_x`, `chunk_y`) — номера чанков, не клеток
+    pub fn chunk(
+        &mut self,
+        cfg: &WorldConfig,
+        hm: &Heightmap,
+        bm: &BiomeMap,
+        chunk_x: u32,
+        chunk_y: u32,
+    ) -> &[ProceduralObject] {
+        let size = self.chunk_cells;
+        self.chunks.entry((chunk_x, chunk_y)).or_insert_with(|| {
+            generate_objects_for_chunk(
+                cfg,
+                hm,
+                bm,
+                chunk_x * size,
+                chunk_y * size,
+                size,
+                size,
+                cfg.world_seed,
+            )
+        })
+    }
+
+    /// Забывает чанки, чтобы они сгенерировались заново (рельеф изменился)
+    pub fn invalidate(&mut self, keys: &[(u32, u32)]) {
+        for key in keys {
+            self.chunks.remove(key);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+}
+
+/// Перекрашивает дома под архитектуру культуры, которой принадлежит местность
+/// на год `year`; дома вне культурных областей не меняются.
+pub fn apply_cultural_architecture(
+    objects: &mut [ProceduralObject],
+    culture: &CultureLayer,
+    year: u32,
+) {
+    for obj in objects.iter_mut() {
+        if !matches!(
+            obj.object_type,
+            ObjectType::HouseWood | ObjectType::HouseStone | ObjectType::HouseMedieval
+        ) {
+            continue;
+        }
+        if let Some(arch) = culture.architecture_at(year, obj.x as u32, obj.y as u32) {
+            let (object_type, variant) = arch.house();
+            obj.object_type = object_type;
+            obj.variant = variant;
+        }
+    }
+}
+
+/// Следы городов, разрушенных или покинутых в истории, попавшие в чанк:
+/// точка интереса в центре и обломки вокруг. `year` — текущий год мира
+/// (обычно `History::years_simulated`), от него считается `age_years`.
+/// Чем старше руины, тем меньше от них осталось и тем больше заросло.
+pub fn generate_ruin_objects_for_chunk(
+    hm: &Heightmap,
+    ruins: &[Ruin],
+    year: u32,
+    chunk_x: u32,
+    chunk_y: u32,
+    chunk_width: u32,
+    chunk_height: u32,
+) -> Vec<ProceduralObject> {
+    let in_chunk = |x: f32, y: f32| {
+        x >= chunk_x as f32
+            && x < (chunk_x + chunk_width).min(hm.width) as f32
+            && y >= chunk_y as f32
+            && y < (chunk_y + chunk_height).min(hm.height) as f32
+    };
+    let mut objects = Vec::new();
+
+    for r in ruins {
+        let age = year.saturating_sub(r.destroyed_year);
+        // доля уцелевшего: за несколько веков стены уходят в землю
+        let decay = 1.0 / (1.0 + age as f32 / 300.0);
+        // размер руин — по населению на момент разрушения
+        let size = (r.population_at_destruction as f32 / 50_000.0).sqrt();
+        let spread = 1.0 + size * 2.0;
+
+        // грубая отсечка: обломки не разлетаются дальше `spread`
+        if !in_chunk(r.x as f32, r.y as f32)
+            && (r.x as f32 + spread < chunk_x as f32
+                || r.x as f32 - spread >= (chunk_x + chunk_width) as f32
+                || r.y as f32 + spread < chunk_y as f32
+                || r.y as f32 - spread >= (chunk_y + chunk_height) as f32)
+        {
+            continue;
+        }
+
+        let mut rng = SplitMix64::new(ruin_seed(&r.id));
+        let push = |objects: &mut Vec<ProceduralObject>, x: f32, y: f32, t, scale, variant| {
+            if in_chunk(x, y) {
+                objects.push(ProceduralObject {
+                    x,
+                    y,
+                    z: hm.get(x as u32, y as u32),
+                    object_type: t,
+                    scale,
+                    rotation_y: 0.0,
+                    variant,
+                    age_years: Some(age),
+                });
+            }
+        };
+
+        push(
+            &mut objects,
+            r.x as f32,
+            r.y as f32,
+            ObjectType::Ruins,
+            (0.8 + size).min(3.0),
+            (r.destroyed_year % 4) as u8,
+        );
+
+        // разрушенный штурмом город оставляет больше обломков стен
+        let walls_share = match r.cause {
+            RuinCause::Razed => 0.6,
+            RuinCause::Abandoned => 0.3,
+        } * decay as f64;
+        let pieces = ((4.0 + size * 12.0) * (0.5 + 0.5 * decay)).round() as usize;
+        for _ in 0..pieces {
+            let angle = rng.range_f64(0.0, std::f64::consts::TAU);
+            let dist = rng.next_f64().sqrt() * spread as f64;
+            let x = r.x as f32 + (angle.cos() * dist) as f32;
+            let y = r.y as f32 + (angle.sin() * dist) as f32;
+            let t = if rng.chance(walls_share) {
+                ObjectType::CollapsedWall
+            } else {
+                ObjectType::OvergrownFoundation
+            };
+            let scale = (0.5 + rng.next_f64() as f32 * 0.5) * (0.4 + 0.6 * decay);
+            let variant = rng.below(4) as u8;
+            push(&mut objects, x, y, t, scale, variant);
+        }
+    }
+
+    objects
+}
+
+fn ruin_seed(id: &str) -> u64 {
+    id.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 /// Вычисляет наклон поверхности (0 = плоско, 1 = вертикально)
 fn calculate_slope(hm: &Heightmap, x: u32, y: u32) -> f32 {
     let _h_center = hm.get(x, y);
@@ -0,0 +1,277 @@
+//! Поиск пути по рельефу для персонажей и агентов — общий для seed-wasm и
+//! сервера. A* по 8-связной сетке клеток карты: шаг стоит своей длины в
+//! метрах, умноженной на штраф за уклон и на `movementCost` биома клетки
+//! (по умолчанию 1 + `vegetationDensity`: сквозь лес дольше, чем по степи).
+//! Вода непроходима, а с `allow_water` — втрое дороже ровной суши; круче
+//! `max_slope_deg` не пройти.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use seed_config::WorldConfig;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::biome::BiomeMap;
+use crate::terrain::Heightmap;
+
+/// Надбавка к шагу за каждый метр подъёма или спуска на метр пути
+const SLOPE_PENALTY: f64 = 4.0;
+/// Метр вплавь против метра по ровной суше
+const WATER_COST: f64 = 3.0;
+
+/// Настройки поиска; в JSON — camelCase, недостающие поля по умолчанию
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct PathOptions {
+    /// Можно плыть
+    pub allow_water: bool,
+    pub max_slope_deg: f32,
+    /// Учитывать `movementCost` биомов
+    pub biome_costs: bool,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        PathOptions {
+            allow_water: false,
+            max_slope_deg: 45.0,
+            biome_costs: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum PathError {
+    #[error("point is outside the map")]
+    OutOfBounds,
+    #[error("max slope must be within (0, 90] degrees")]
+    BadSlope,
+    #[error("no path between the points")]
+    NoPath,
+}
+
+impl PathError {
+    /// Код ошибки для API: `out_of_bounds`, `bad_slope`, `no_path`
+    pub fn code(self) -> &'static str {
+        match self {
+            PathError::OutOfBounds => "out_of_bounds",
+            PathError::BadSlope => "bad_slope",
+            PathError::NoPath => "no_path",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TerrainPath {
+    /// Все клетки пути от начальной до конечной
+    pub cells: Vec<(u32, u32)>,
+    pub cost: f64,
+}
+
+impl TerrainPath {
+    /// Клетки, где путь поворачивает, вместе с начальной и конечной
+    pub fn turns(&self) -> Vec<(u32, u32)> {
+        let dir = |a: (u32, u32), b: (u32, u32)| (b.0 as i64 - a.0 as i64, b.1 as i64 - a.1 as i64);
+        let mut out = vec![self.cells[0]];
+        for w in self.cells.windows(3) {
+            if dir(w[0], w[1]) != dir(w[1], w[2]) {
+                out.push(w[1]);
+            }
+        }
+        if self.cells.len() > 1 {
+            out.push(self.cells[self.cells.len() - 1]);
+        }
+        out
+    }
+}
+
+/// Высота поверхности клетки, м: над водой — её уровень (0)
+pub fn surface_m(cfg: &WorldConfig, hm: &Heightmap, x: u32, y: u32) -> f64 {
+    hm.elevation_m(cfg.sea_level, x, y).max(0.0)
+}
+
+/// Путь от клетки `from` до `to`; `bm` нужна для цен биомов
+pub fn find_path(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: Option<&BiomeMap>,
+    from: (u32, u32),
+    to: (u32, u32),
+    options: &PathOptions,
+) -> Result<TerrainPath, PathError> {
+    let max_slope_deg = options.max_slope_deg;
+    if !(max_slope_deg > 0.0 && max_slope_deg <= 90.0) {
+        return Err(PathError::BadSlope);
+    }
+    let inside = |(x, y): (u32, u32)| x < hm.width && y < hm.height;
+    if !inside(from) || !inside(to) {
+        return Err(PathError::OutOfBounds);
+    }
+    let biome_cost: Vec<f64> = match bm {
+        Some(_) if options.biome_costs => cfg
+            .biomes
+            .iter()
+            .map(|b| {
+                b.movement_cost
+                    .map_or(1.0 + b.vegetation_density as f64, |c| c as f64)
+                    .max(0.0)
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let grid = Grid {
+        cfg,
+        hm,
+        bm: bm.filter(|_| !biome_cost.is_empty()),
+        cell_m: cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64,
+        allow_water: options.allow_water,
+        max_slope: (max_slope_deg as f64).to_radians().tan(),
+        // шаг не дешевле своей длины, умноженной на самый дешёвый биом
+        min_factor: biome_cost.iter().copied().fold(1.0, f64::min),
+        biome_cost,
+    };
+    let w = hm.width as usize;
+    let index = |(x, y): (u32, u32)| y as usize * w + x as usize;
+    let (cells, cost) = grid
+        .search(index(from), index(to))
+        .ok_or(PathError::NoPath)?;
+    Ok(TerrainPath {
+        cells: cells
+            .into_iter()
+            .map(|i| ((i % w) as u32, (i / w) as u32))
+            .collect(),
+        cost,
+    })
+}
+
+struct Grid<'a> {
+    cfg: &'a WorldConfig,
+    hm: &'a Heightmap,
+    bm: Option<&'a BiomeMap>,
+    cell_m: f64,
+    allow_water: bool,
+    /// Тангенс наибольшего уклона
+    max_slope: f64,
+    /// Множитель шага по биому, по индексу в `cfg.biomes`
+    biome_cost: Vec<f64>,
+    min_factor: f64,
+}
+
+impl Grid<'_> {
+    fn water(&self, i: usize) -> bool {
+        self.hm.values[i] as f64 <= self.cfg.sea_level
+    }
+
+    fn surface_m(&self, i: usize) -> f64 {
+        let w = self.hm.width;
+        surface_m(self.cfg, self.hm, i as u32 % w, i as u32 / w)
+    }
+
+    fn biome_factor(&self, i: usize) -> f64 {
+        self.bm
+            .and_then(|bm| bm.indices[i])
+            .and_then(|b| self.biome_cost.get(b as usize))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Цена шага длиной `dist` м из `from` в соседнюю `to`; None — не пройти
+    fn step(&self, from: usize, to: usize, dist: f64) -> Option<f64> {
+        if self.water(to) {
+            return self.allow_water.then_some(dist * WATER_COST);
+        }
+        let slope = (self.surface_m(to) - self.surface_m(from)).abs() / dist;
+        (slope <= self.max_slope)
+            .then_some(dist * (1.0 + SLOPE_PENALTY * slope) * self.biome_factor(to))
+    }
+
+    /// A* от клетки `start` до `goal`: клетки пути и его цена
+    fn search(&self, start: usize, goal: usize) -> Option<(Vec<usize>, f64)> {
+        if self.water(goal) && !self.allow_water {
+            return None;
+        }
+        let (w, h) = (self.hm.width as usize, self.hm.height as usize);
+        let heuristic = |i: usize| {
+            let dx = (i % w) as f64 - (goal % w) as f64;
+            let dy = (i / w) as f64 - (goal / w) as f64;
+            (dx * dx + dy * dy).sqrt() * self.cell_m * self.min_factor
+        };
+
+        let mut g = vec![f64::INFINITY; w * h];
+        let mut came_from = vec![usize::MAX; w * h];
+        let mut heap = BinaryHeap::new();
+        g[start] = 0.0;
+        heap.push(Open {
+            f: heuristic(start),
+            idx: start,
+        });
+
+        while let Some(Open { f, idx }) = heap.pop() {
+            if idx == goal {
+                let mut path = vec![goal];
+                let mut cur = goal;
+                while cur != start {
+                    cur = came_from[cur];
+                    path.push(cur);
+                }
+                path.reverse();
+                return Some((path, g[goal]));
+            }
+            if f > g[idx] + heuristic(idx) + 1e-6 {
+                continue;
+            }
+            let (x, y) = ((idx % w) as i64, (idx / w) as i64);
+            for dy in -1i64..=1 {
+                for dx in -1i64..=1 {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if (dx == 0 && dy == 0) || nx < 0 || ny < 0 || nx >= w as i64 || ny >= h as i64
+                    {
+                        continue;
+                    }
+                    let n = ny as usize * w + nx as usize;
+                    let dist = if dx != 0 && dy != 0 {
+                        std::f64::consts::SQRT_2
+                    } else {
+                        1.0
+                    } * self.cell_m;
+                    let Some(c) = self.step(idx, n, dist) else {
+                        continue;
+                    };
+                    let tentative = g[idx] + c;
+                    if tentative < g[n] {
+                        g[n] = tentative;
+                        came_from[n] = idx;
+                        heap.push(Open {
+                            f: tentative + heuristic(n),
+                            idx: n,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Open {
+    f: f64,
+    idx: usize,
+}
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.f == other.f
+    }
+}
+impl Eq for Open {}
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.partial_cmp(&self.f).unwrap_or(Ordering::Equal)
+    }
+}
@@ -0,0 +1,104 @@
+//! Тепловой режим планеты для `sample_climate`: насколько она в среднем
+//! теплее опорной и насколько у неё холоднее полюса, чем экватор. Опорная
+//! планета — Земля из конфига по умолчанию (Солнце, 1 а. е., наклон 23.5°,
+//! 101.3 кПа, 0.04 % CO₂); для неё профиль совпадает с прежним, и
+//! `baseTemperatureC` по-прежнему задаёт, какой была бы такая планета.
+//!
+//! Средняя температура — равновесная (по светимости звезды и орбите) плюс
+//! парниковый эффект (давление и CO₂). Размах экватор–полюс растёт с
+//! контрастом годовой инсоляции, который задаёт наклон оси (за ~55° полюса
+//! получают больше экватора), и падает в плотной атмосфере, которая
+//! разносит тепло.
+
+use seed_config::WorldConfig;
+
+const REF_LUMINOSITY: f64 = 1.0;
+const REF_AXIS_AU: f64 = 1.0;
+const REF_ECCENTRICITY: f64 = 0.0167;
+const REF_TILT_DEG: f64 = 23.5;
+const REF_PRESSURE_KPA: f64 = 101.3;
+const REF_CO2: f64 = 0.0004;
+
+/// Равновесная температура Земли (альбедо 0.3), К
+const EARTH_EQUILIBRIUM_K: f64 = 255.0;
+/// Парниковый эффект Земли, К
+const EARTH_GREENHOUSE_K: f64 = 33.0;
+/// Потепление на каждое удвоение CO₂, К
+const CO2_DOUBLING_K: f64 = 3.0;
+/// Меньше 1/64 опорного CO₂ уже почти ничего не меняет
+const CO2_MIN_RATIO: f64 = 1.0 / 64.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlanetClimate {
+    /// Насколько средняя температура выше, чем у опорной планеты, °C
+    pub mean_shift_c: f64,
+    /// Размах экватор–полюс относительно опорной планеты; отрицательный —
+    /// полюса теплее экватора
+    pub latitude_contrast: f64,
+}
+
+impl PlanetClimate {
+    /// Профиль активной планеты и её звезды; чего нет в конфиге, берётся
+    /// от опорной планеты
+    pub fn from_config(cfg: &WorldConfig) -> Self {
+        let sys = &cfg.cosmos.star_system;
+        let planet = sys.planets.iter().find(|p| p.id == sys.active_planet_id);
+        let luminosity = planet
+            .and_then(|p| sys.stars.iter().find(|s| s.id == p.orbit.star_id))
+            .map_or(REF_LUMINOSITY, |s| s.luminosity.max(0.0));
+        let (axis_au, eccentricity, tilt_deg) =
+            planet.map_or((REF_AXIS_AU, REF_ECCENTRICITY, REF_TILT_DEG), |p| {
+                (
+                    p.orbit.semi_major_axis_au,
+                    p.orbit.eccentricity,
+                    p.axial_tilt_degrees,
+                )
+            });
+        let atm = &cfg.environment.atmosphere;
+        let co2 = atm.composition.get("CO2").copied().unwrap_or(0.0);
+
+        let t_eq = equilibrium_k(luminosity, axis_au, eccentricity);
+        let t_eq_ref = equilibrium_k(REF_LUMINOSITY, REF_AXIS_AU, REF_ECCENTRICITY);
+        let greenhouse = greenhouse_k(atm.pressure_k_pa, co2);
+        let greenhouse_ref = greenhouse_k(REF_PRESSURE_KPA, REF_CO2);
+
+        PlanetClimate {
+            mean_shift_c: (t_eq + greenhouse) - (t_eq_ref + greenhouse_ref),
+            latitude_contrast: (t_eq / t_eq_ref)
+                * (insolation_contrast(tilt_deg) / insolation_contrast(REF_TILT_DEG))
+                * (heat_retention(atm.pressure_k_pa) / heat_retention(REF_PRESSURE_KPA)),
+        }
+    }
+}
+
+/// Равновесная температура по среднему за год потоку от звезды, К
+fn equilibrium_k(luminosity: f64, axis_au: f64, eccentricity: f64) -> f64 {
+    if axis_au <= 0.0 {
+        return EARTH_EQUILIBRIUM_K;
+    }
+    let e = eccentricity.clamp(0.0, 0.99);
+    let flux = luminosity / (axis_au * axis_au * (1.0 - e * e).sqrt());
+    EARTH_EQUILIBRIUM_K * flux.powf(0.25)
+}
+
+/// Парниковый эффект, К: основной (водяной пар и прочее) растёт с давлением,
+/// CO₂ добавляет `CO2_DOUBLING_K` на каждое удвоение своего парциального
+/// давления
+fn greenhouse_k(pressure_kpa: f64, co2_fraction: f64) -> f64 {
+    let pressure = (pressure_kpa / REF_PRESSURE_KPA).max(0.0);
+    let co2 = (co2_fraction * pressure / REF_CO2).max(CO2_MIN_RATIO);
+    (EARTH_GREENHOUSE_K * pressure.powf(0.25) + CO2_DOUBLING_K * co2.log2()).max(0.0)
+}
+
+/// Разница среднегодовой инсоляции экватора и полюса (второй член
+/// разложения по Лежандру, North 1975)
+fn insolation_contrast(tilt_deg: f64) -> f64 {
+    let c = tilt_deg.to_radians().cos();
+    0.625 * (3.0 * c * c - 1.0) / 2.0
+}
+
+/// Какая доля контраста инсоляции остаётся в температуре: остальное
+/// атмосфера уносит к полюсам
+fn heat_retention(pressure_kpa: f64) -> f64 {
+    1.0 / (1.0 + pressure_kpa.max(0.0) / REF_PRESSURE_KPA)
+}
@@ -0,0 +1,252 @@
+//! Демография: население городов и окрестных деревень ограничено
+//! продовольствием с окружающих земель, а катастрофы и войны дают спады.
+//! История сохраняет срезы населения, по которым можно получить состояние
+//! и растр плотности на любой год.
+
+use crate::biome::BiomeMap;
+use crate::settlements::City;
+use crate::terrain::{compute_flow_accumulation, Heightmap};
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+
+/// Радиус (в клетках) земель, кормящих город
+pub const CATCHMENT_RADIUS: u32 = 6;
+
+/// Сколько людей кормит одна клетка идеальных угодий
+const FOOD_PER_CELL: f64 = 3_000.0;
+
+/// Поймы рек (доля от максимального стока) плодороднее
+const FLOODPLAIN_FLOW: f32 = 0.02;
+
+/// Продовольственная ёмкость земель: людей на клетку
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoodCapacityMap {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+impl FoodCapacityMap {
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        self.values[(y * self.width + x) as usize]
+    }
+}
+
+/// Ёмкость по биомам: растительность биома, поймы рек, рыба у побережья,
+/// склоны и высокогорья снижают урожай.
+pub fn compute_food_capacity(cfg: &WorldConfig, hm: &Heightmap, bm: &BiomeMap) -> FoodCapacityMap {
+    let w = hm.width;
+    let h = hm.height;
+    let sea_level = cfg.sea_level as f32;
+    let flow = compute_flow_accumulation(hm, sea_level);
+    let mut values = vec![0.0f32; (w * h) as usize];
+
+    for y in 0..h {
+        for x in 0..w {
+            let idx = (y * w + x) as usize;
+            let hc = hm.values[idx];
+            if hc <= sea_level {
+                continue;
+            }
+            let Some(biome) = bm.get_index(x, y).and_then(|bi| cfg.biomes.get(bi)) else {
+                continue;
+            };
+
+            // земледелие возможно там, где разрешены поселения; в остальных — охота и собирательство
+            let mut fertility = biome.vegetation_density.clamp(0.0, 1.0) as f64;
+            if !biome.allow_settlements {
+                fertility *= 0.2;
+            }
+            if flow[idx] >= FLOODPLAIN_FLOW {
+                fertility *= 1.5;
+            }
+
+            let xl = x.saturating_sub(1);
+            let xr = (x + 1).min(w - 1);
+            let yu = y.saturating_sub(1);
+            let yd = (y + 1).min(h - 1);
+            let coastal = [(xl, y), (xr, y), (x, yu), (x, yd)]
+                .iter()
+                .any(|&(nx, ny)| hm.get(nx, ny) <= sea_level);
+            let fish = if coastal { 0.3 } else { 0.0 };
+
+            let dx = hm.get(xr, y) - hm.get(xl, y);
+            let dy = hm.get(x, yd) - hm.get(x, yu);
+            let slope = (dx * dx + dy * dy).sqrt() * 0.5;
+            let flat = (1.0 - slope as f64 * 25.0).clamp(0.1, 1.0);
+
+            let rel = ((hc - sea_level) / (1.0 - sea_level).max(1e-6)).clamp(0.0, 1.0) as f64;
+            let highland = 1.0 - rel * 0.7;
+
+            values[idx] = (FOOD_PER_CELL * (fertility * flat * highland + fish)) as f32;
+        }
+    }
+
+    FoodCapacityMap {
+        width: w,
+        height: h,
+        values,
+    }
+}
+
+/// Владелец каждой клетки: ближайший город в радиусе `radius`
+pub(crate) fn catchment_owners(
+    width: u32,
+    height: u32,
+    positions: &[(u32, u32)],
+    radius: u32,
+) -> Vec<Option<usize>> {
+    let len = (width * height) as usize;
+    let mut owner = vec![None; len];
+    let mut best = vec![f64::INFINITY; len];
+    let r = radius as i64;
+
+    for (ci, &(cx, cy)) in positions.iter().enumerate() {
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let x = cx as i64 + dx;
+                let y = cy as i64 + dy;
+                if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                    continue;
+                }
+                let d = ((dx * dx + dy * dy) as f64).sqrt();
+                if d > r as f64 {
+                    continue;
+                }
+                let idx = (y as u32 * width + x as u32) as usize;
+                if d < best[idx] {
+                    best[idx] = d;
+                    owner[idx] = Some(ci);
+                }
+            }
+        }
+    }
+    owner
+}
+
+/// Сколько людей могут прокормить угодья каждого города (соседи делят землю)
+pub fn catchment_capacities(food: &FoodCapacityMap, cities: &[City]) -> Vec<f64> {
+    let positions: Vec<(u32, u32)> = cities.iter().map(|c| (c.x, c.y)).collect();
+    let owner = catchment_owners(food.width, food.height, &positions, CATCHMENT_RADIUS);
+    let mut caps = vec![0.0; cities.len()];
+    for (idx, o) in owner.iter().enumerate() {
+        if let Some(ci) = o {
+            caps[*ci] += food.values[idx] as f64;
+        }
+    }
+    caps
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CityPopulation {
+    pub city_id: String,
+    pub faction_id: String,
+    pub x: u32,
+    pub y: u32,
+    /// Горожане
+    pub urban: u64,
+    /// Сельское население угодий города
+    pub rural: u64,
+}
+
+/// Население всех городов на конкретный год
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PopulationSnapshot {
+    pub year: u32,
+    pub cities: Vec<CityPopulation>,
+}
+
+impl PopulationSnapshot {
+    pub fn capture(year: u32, cities: &[City]) -> Self {
+        Self {
+            year,
+            cities: cities
+                .iter()
+                .map(|c| CityPopulation {
+                    city_id: c.id.clone(),
+                    faction_id: c.faction_id.clone(),
+                    x: c.x,
+                    y: c.y,
+                    urban: c.population,
+                    rural: c.rural_population,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn urban_total(&self) -> u64 {
+        self.cities.iter().map(|c| c.urban).sum()
+    }
+
+    pub fn rural_total(&self) -> u64 {
+        self.cities.iter().map(|c| c.rural).sum()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.urban_total() + self.rural_total()
+    }
+
+    /// Население одной фракции
+    pub fn faction_total(&self, faction_id: &str) -> u64 {
+        self.cities
+            .iter()
+            .filter(|c| c.faction_id == faction_id)
+            .map(|c| c.urban + c.rural)
+            .sum()
+    }
+}
+
+/// Демографическая история мира
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Demographics {
+    pub food: FoodCapacityMap,
+    /// Срезы по годам в порядке возрастания
+    pub snapshots: Vec<PopulationSnapshot>,
+}
+
+impl Demographics {
+    pub fn new(food: FoodCapacityMap) -> Self {
+        Self {
+            food,
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// Последний срез не позже `year`
+    pub fn population_at(&self, year: u32) -> Option<&PopulationSnapshot> {
+        let i = self.snapshots.partition_point(|s| s.year <= year);
+        i.checked_sub(1).map(|i| &self.snapshots[i])
+    }
+
+    /// Растр плотности (людей на клетку) на год `year`: горожане — в клетке города,
+    /// сельские жители распределены по угодьям пропорционально их ёмкости.
+    pub fn density_raster(&self, year: u32) -> Option<Vec<f32>> {
+        let snap = self.population_at(year)?;
+        let (w, h) = (self.food.width, self.food.height);
+        let positions: Vec<(u32, u32)> = snap.cities.iter().map(|c| (c.x, c.y)).collect();
+        let owner = catchment_owners(w, h, &positions, CATCHMENT_RADIUS);
+
+        let mut weight = vec![0.0f64; snap.cities.len()];
+        for (idx, o) in owner.iter().enumerate() {
+            if let Some(ci) = o {
+                weight[*ci] += self.food.values[idx] as f64;
+            }
+        }
+
+        let mut raster = vec![0.0f32; (w * h) as usize];
+        for (idx, o) in owner.iter().enumerate() {
+            if let Some(ci) = *o {
+                if weight[ci] > 0.0 {
+                    raster[idx] += (snap.cities[ci].rural as f64 * self.food.values[idx] as f64
+                        / weight[ci]) as f32;
+                }
+            }
+        }
+        for c in &snap.cities {
+            if c.x < w && c.y < h {
+                raster[(c.y * w + c.x) as usize] += c.urban as f32;
+            }
+        }
+        Some(raster)
+    }
+}
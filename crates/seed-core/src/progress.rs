@@ -0,0 +1,71 @@
+//! Прогресс долгих этапов генерации. Генераторы сообщают этап и долю
+//! выполненного; построчные проходы идут параллельно (фича `parallel`,
+//! rayon), при этом результат не зависит от числа потоков.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GenerationPhase {
+    /// Шум heightmap
+    Noise,
+    /// Эрозия, озёра и сглаживание heightmap
+    Erosion,
+    /// Выбор биомов
+    Biomes,
+}
+
+impl GenerationPhase {
+    pub fn id(self) -> &'static str {
+        match self {
+            GenerationPhase::Noise => "noise",
+            GenerationPhase::Erosion => "erosion",
+            GenerationPhase::Biomes => "biomes",
+        }
+    }
+}
+
+/// Получатель прогресса: этап и доля выполненного (0..1). Может вызываться
+/// из нескольких потоков одновременно.
+pub trait Progress: Sync {
+    fn report(&self, phase: GenerationPhase, done: f32);
+}
+
+impl<F: Fn(GenerationPhase, f32) + Sync> Progress for F {
+    fn report(&self, phase: GenerationPhase, done: f32) {
+        self(phase, done)
+    }
+}
+
+/// Прогресс никому не нужен
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn report(&self, _phase: GenerationPhase, _done: f32) {}
+}
+
+/// Строки `0..height`, посчитанные `row` (параллельно, если есть rayon),
+/// склеенные по порядку
+pub(crate) fn map_rows<T: Send>(
+    height: u32,
+    phase: GenerationPhase,
+    progress: &dyn Progress,
+    row: impl Fn(u32) -> Vec<T> + Sync + Send,
+) -> Vec<T> {
+    let done = AtomicU32::new(0);
+    let run = |y: u32| {
+        let values = row(y);
+        let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+        progress.report(phase, n as f32 / height.max(1) as f32);
+        values
+    };
+
+    #[cfg(feature = "parallel")]
+    let rows: Vec<Vec<T>> = {
+        use rayon::prelude::*;
+        (0..height).into_par_iter().map(run).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let rows: Vec<Vec<T>> = (0..height).map(run).collect();
+
+    rows.into_iter().flatten().collect()
+}
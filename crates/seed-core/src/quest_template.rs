@@ -0,0 +1,185 @@
+//! Шаблоны заданий из `narrativeDirector.questTemplates`: цепочка шагов с
+//! селекторами целей и таблица наград. Селектор — короткая фраза:
+//!
+//! ```text
+//! [nearest|farthest|random] <kind> [in <own|friendly|neutral|hostile|unclaimed> territory] [near player]
+//! same
+//! ```
+//!
+//! `kind` — `city`, `ruin`, `feature`, вид объекта газеттира (`forest`,
+//! `river`, `mountain_range`...), `catastrophe_site` (катастрофа истории),
+//! `event_site` (свежее событие шины) или `any`. Первый шаг отсчитывается от
+//! игрока, следующие — от цели предыдущего шага, если не сказано `near player`;
+//! `same` повторяет цель предыдущего шага. Территория берётся относительно
+//! фракции-заказчика — хозяина клетки, где стоит игрок.
+
+use crate::director::QuestType;
+use crate::geography::FeatureKind;
+use crate::{CoreError, Result};
+use seed_config::{QuestRewardConfig, QuestTemplateConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectorOrder {
+    Nearest,
+    Farthest,
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectorKind {
+    Any,
+    City,
+    Ruin,
+    /// Объект газеттира; `None` — любой
+    Feature(Option<FeatureKind>),
+    CatastropheSite,
+    EventSite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerritoryFilter {
+    /// Земли заказчика
+    Own,
+    /// Союзники и друзья заказчика
+    Friendly,
+    Neutral,
+    /// Соперники и враги заказчика
+    Hostile,
+    /// Ничьи земли
+    Unclaimed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetSelector {
+    /// Цель предыдущего шага
+    Same,
+    Pick {
+        order: SelectorOrder,
+        kind: SelectorKind,
+        territory: Option<TerritoryFilter>,
+        /// Отсчитывать от игрока, даже если это не первый шаг
+        near_player: bool,
+    },
+}
+
+impl TargetSelector {
+    pub fn parse(text: &str) -> Result<TargetSelector> {
+        let err = |msg: &str| CoreError::Config(format!("target selector '{text}': {msg}"));
+        let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+        let mut words: &[String] = &words;
+
+        if words.len() == 1 && words[0] == "same" {
+            return Ok(TargetSelector::Same);
+        }
+
+        let order = match words.first().map(String::as_str) {
+            Some("nearest") => Some(SelectorOrder::Nearest),
+            Some("farthest") => Some(SelectorOrder::Farthest),
+            Some("random") => Some(SelectorOrder::Random),
+            _ => None,
+        };
+        if order.is_some() {
+            words = &words[1..];
+        }
+
+        let kind = match words.first().map(String::as_str) {
+            Some("any") => SelectorKind::Any,
+            Some("city") => SelectorKind::City,
+            Some("ruin") => SelectorKind::Ruin,
+            Some("feature") => SelectorKind::Feature(None),
+            Some("catastrophe_site") => SelectorKind::CatastropheSite,
+            Some("event_site") => SelectorKind::EventSite,
+            Some(other) => match FeatureKind::from_id(other) {
+                Some(f) => SelectorKind::Feature(Some(f)),
+                None => return Err(err(&format!("unknown target kind '{other}'"))),
+            },
+            None => return Err(err("missing target kind")),
+        };
+        words = &words[1..];
+
+        let mut territory = None;
+        if words.first().is_some_and(|w| w == "in") {
+            if words.len() < 3 || words[2] != "territory" {
+                return Err(err("expected 'in <relation> territory'"));
+            }
+            territory = Some(match words[1].as_str() {
+                "own" => TerritoryFilter::Own,
+                "friendly" => TerritoryFilter::Friendly,
+                "neutral" => TerritoryFilter::Neutral,
+                "hostile" => TerritoryFilter::Hostile,
+                "unclaimed" => TerritoryFilter::Unclaimed,
+                other => return Err(err(&format!("unknown territory '{other}'"))),
+            });
+            words = &words[3..];
+        }
+
+        let near_player = match words {
+            [] => false,
+            [a, b] if a == "near" && b == "player" => true,
+            _ => return Err(err(&format!("unexpected '{}'", words.join(" ")))),
+        };
+
+        Ok(TargetSelector::Pick {
+            order: order.unwrap_or(SelectorOrder::Nearest),
+            kind,
+            territory,
+            near_player,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectiveTemplate {
+    pub action: String,
+    pub target: TargetSelector,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuestTemplate {
+    pub id: String,
+    pub quest_type: QuestType,
+    pub title: String,
+    pub weight: f32,
+    pub objectives: Vec<ObjectiveTemplate>,
+    pub rewards: Vec<QuestRewardConfig>,
+}
+
+impl QuestTemplate {
+    pub fn from_config(cfg: &QuestTemplateConfig) -> Result<QuestTemplate> {
+        let err = |msg: String| CoreError::Config(format!("quest template '{}': {msg}", cfg.id));
+        let quest_type = QuestType::from_id(&cfg.quest_type)
+            .ok_or_else(|| err(format!("unknown quest type '{}'", cfg.quest_type)))?;
+        if cfg.objectives.is_empty() {
+            return Err(err("has no objectives".to_string()));
+        }
+
+        let mut objectives = Vec::with_capacity(cfg.objectives.len());
+        for (i, o) in cfg.objectives.iter().enumerate() {
+            let target =
+                TargetSelector::parse(&o.target).map_err(|CoreError::Config(msg)| err(msg))?;
+            if i == 0 && target == TargetSelector::Same {
+                return Err(err("first objective cannot target 'same'".to_string()));
+            }
+            objectives.push(ObjectiveTemplate {
+                action: o.action.clone(),
+                target,
+                description: o.description.clone(),
+            });
+        }
+
+        let rewards = cfg.rewards.clone().unwrap_or_default();
+        if let Some(r) = rewards.iter().find(|r| r.min > r.max) {
+            return Err(err(format!("reward '{}' has min > max", r.item)));
+        }
+
+        Ok(QuestTemplate {
+            id: cfg.id.clone(),
+            quest_type,
+            title: cfg.title.clone(),
+            weight: cfg.weight.unwrap_or(1.0).max(0.0),
+            objectives,
+            rewards,
+        })
+    }
+}
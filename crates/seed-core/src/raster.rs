@@ -0,0 +1,306 @@
+//! Растровые операции над `Heightmap` для своей постобработки без ручных
+//! циклов по клеткам: сложить, перемножить или смешать с другой картой,
+//! размыть, прижать, пропустить через кривую, выровнять участок под город.
+//! Операции берут карту и возвращают новую, так что их можно цепочкой:
+//!
+//! ```ignore
+//! let city = Mask::circle(hm.width, hm.height, 200.0, 140.0, 12.0, 6.0);
+//! let hm = hm
+//!     .lerp_map(&other, 0.3)?
+//!     .flatten(&city, 0.45)?
+//!     .blur(1)
+//!     .clamp(0.0, 1.0);
+//! ```
+//!
+//! Высоты после арифметики могут выйти за 0..1, которого ждут генераторы
+//! биомов и объектов, — цепочку стоит заканчивать `clamp(0.0, 1.0)` или
+//! `normalize()`. Маска — веса 0..1 по клеткам того же размера: 1 —
+//! операция действует в полную силу, 0 — клетка не тронута.
+
+use crate::terrain::Heightmap;
+use crate::{CoreError, Result};
+
+/// Веса 0..1 по клеткам карты, построчно с севера
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mask {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+impl Mask {
+    /// Маска с весом `value` во всех клетках
+    pub fn filled(width: u32, height: u32, value: f32) -> Self {
+        Mask {
+            width,
+            height,
+            values: vec![value.clamp(0.0, 1.0); (width * height) as usize],
+        }
+    }
+
+    /// Вес клетки — `f(x, y)`, прижатый к 0..1
+    pub fn from_fn(width: u32, height: u32, f: impl Fn(u32, u32) -> f32) -> Self {
+        let values = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y).clamp(0.0, 1.0))
+            .collect();
+        Mask {
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Круг с центром (`cx`, `cy`) и радиусом `radius` клеток; за ним вес
+    /// плавно спадает до нуля на ширине `falloff` клеток
+    pub fn circle(width: u32, height: u32, cx: f32, cy: f32, radius: f32, falloff: f32) -> Self {
+        Mask::from_fn(width, height, |x, y| {
+            let d = (x as f32 - cx).hypot(y as f32 - cy);
+            edge_weight(d - radius, falloff)
+        })
+    }
+
+    /// Прямоугольник клеток от (`x0`, `y0`) до (`x1`, `y1`) включительно с
+    /// краем шириной `falloff` клеток
+    pub fn rect(width: u32, height: u32, x0: u32, y0: u32, x1: u32, y1: u32, falloff: f32) -> Self {
+        let (x0, x1) = (x0.min(x1) as f32, x0.max(x1) as f32);
+        let (y0, y1) = (y0.min(y1) as f32, y0.max(y1) as f32);
+        Mask::from_fn(width, height, |x, y| {
+            let dx = (x0 - x as f32).max(x as f32 - x1).max(0.0);
+            let dy = (y0 - y as f32).max(y as f32 - y1).max(0.0);
+            edge_weight(dx.hypot(dy), falloff)
+        })
+    }
+
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        self.values[(y * self.width + x) as usize]
+    }
+
+    /// 1 − вес
+    pub fn invert(mut self) -> Self {
+        self.values.iter_mut().for_each(|v| *v = 1.0 - *v);
+        self
+    }
+
+    /// Наибольший из весов двух масок
+    pub fn union(self, other: &Mask) -> Result<Self> {
+        self.zip(other, f32::max)
+    }
+
+    /// Наименьший из весов двух масок
+    pub fn intersect(self, other: &Mask) -> Result<Self> {
+        self.zip(other, f32::min)
+    }
+
+    /// Размытие на `radius` клеток — мягкий край у жёсткой маски
+    pub fn blur(mut self, radius: u32) -> Self {
+        box_blur(&mut self.values, self.width, self.height, radius);
+        self
+    }
+
+    fn zip(mut self, other: &Mask, f: impl Fn(f32, f32) -> f32) -> Result<Self> {
+        check_size((self.width, self.height), (other.width, other.height))?;
+        for (a, &b) in self.values.iter_mut().zip(&other.values) {
+            *a = f(*a, b);
+        }
+        Ok(self)
+    }
+}
+
+impl Heightmap {
+    /// Карта с высотой `value` во всех клетках
+    pub fn filled(width: u32, height: u32, value: f32) -> Self {
+        Heightmap {
+            width,
+            height,
+            values: vec![value; (width * height) as usize],
+        }
+    }
+
+    /// Высота клетки — `f(высота)`
+    pub fn map(mut self, f: impl Fn(f32) -> f32) -> Self {
+        self.values.iter_mut().for_each(|v| *v = f(*v));
+        self
+    }
+
+    /// Высота клетки — `f(высота, высота в other)`; карты одного размера
+    pub fn combine(mut self, other: &Heightmap, f: impl Fn(f32, f32) -> f32) -> Result<Self> {
+        check_size((self.width, self.height), (other.width, other.height))?;
+        for (a, &b) in self.values.iter_mut().zip(&other.values) {
+            *a = f(*a, b);
+        }
+        Ok(self)
+    }
+
+    pub fn add_map(self, other: &Heightmap) -> Result<Self> {
+        self.combine(other, |a, b| a + b)
+    }
+
+    pub fn sub_map(self, other: &Heightmap) -> Result<Self> {
+        self.combine(other, |a, b| a - b)
+    }
+
+    pub fn mul_map(self, other: &Heightmap) -> Result<Self> {
+        self.combine(other, |a, b| a * b)
+    }
+
+    /// Смесь с `other`: 0 — эта карта, 1 — `other`
+    pub fn lerp_map(self, other: &Heightmap, t: f32) -> Result<Self> {
+        self.combine(other, |a, b| a + (b - a) * t)
+    }
+
+    /// Смесь с `other` по маске: где вес 1 — `other`, где 0 — эта карта
+    pub fn blend(mut self, other: &Heightmap, mask: &Mask) -> Result<Self> {
+        check_size((self.width, self.height), (other.width, other.height))?;
+        check_size((self.width, self.height), (mask.width, mask.height))?;
+        for ((a, &b), &t) in self.values.iter_mut().zip(&other.values).zip(&mask.values) {
+            *a += (b - *a) * t;
+        }
+        Ok(self)
+    }
+
+    /// Подтягивает высоты под маской к `level` — площадка под город или
+    /// дорогу; на краю маски рельеф плавно переходит в площадку
+    pub fn flatten(mut self, mask: &Mask, level: f32) -> Result<Self> {
+        check_size((self.width, self.height), (mask.width, mask.height))?;
+        for (a, &t) in self.values.iter_mut().zip(&mask.values) {
+            *a += (level - *a) * t;
+        }
+        Ok(self)
+    }
+
+    pub fn scale(self, factor: f32) -> Self {
+        self.map(|v| v * factor)
+    }
+
+    pub fn offset(self, delta: f32) -> Self {
+        self.map(|v| v + delta)
+    }
+
+    pub fn clamp(self, lo: f32, hi: f32) -> Self {
+        self.map(|v| v.clamp(lo, hi))
+    }
+
+    /// Растягивает высоты на 0..1; ровная карта становится нулевой
+    pub fn normalize(self) -> Self {
+        let (lo, hi) = self
+            .values
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        let range = hi - lo;
+        if range <= 0.0 || !range.is_finite() {
+            return self.map(|_| 0.0);
+        }
+        self.map(|v| (v - lo) / range)
+    }
+
+    /// Кривая высот: ломаная по точкам (вход, выход), отсортированным по
+    /// входу; за крайними точками — их выход. Пустая кривая карту не меняет
+    pub fn remap(self, curve: &[(f32, f32)]) -> Self {
+        if curve.is_empty() {
+            return self;
+        }
+        self.map(|v| {
+            let i = curve.partition_point(|&(x, _)| x < v);
+            if i == 0 {
+                return curve[0].1;
+            }
+            if i == curve.len() {
+                return curve[i - 1].1;
+            }
+            let ((x0, y0), (x1, y1)) = (curve[i - 1], curve[i]);
+            if x1 <= x0 {
+                return y1;
+            }
+            y0 + (y1 - y0) * (v - x0) / (x1 - x0)
+        })
+    }
+
+    /// Размытие на `radius` клеток (три прохода скользящего среднего —
+    /// почти гауссово); у краёв карты клетки за краем — как крайние
+    pub fn blur(mut self, radius: u32) -> Self {
+        box_blur(&mut self.values, self.width, self.height, radius);
+        self
+    }
+
+    /// Маска клеток выше `level`: вес 1 над ним, 0 под ним
+    pub fn threshold(&self, level: f32) -> Mask {
+        self.mask(|v| v > level)
+    }
+
+    /// Маска клеток, высота которых проходит `pred`
+    pub fn mask(&self, pred: impl Fn(f32) -> bool) -> Mask {
+        Mask {
+            width: self.width,
+            height: self.height,
+            values: self
+                .values
+                .iter()
+                .map(|&v| if pred(v) { 1.0 } else { 0.0 })
+                .collect(),
+        }
+    }
+}
+
+fn check_size(expected: (u32, u32), found: (u32, u32)) -> Result<()> {
+    if expected != found {
+        return Err(CoreError::Config(format!(
+            "map size mismatch: expected {}x{}, found {}x{}",
+            expected.0, expected.1, found.0, found.1
+        )));
+    }
+    Ok(())
+}
+
+/// Вес на расстоянии `d` клеток за краем фигуры (внутри `d` ≤ 0)
+fn edge_weight(d: f32, falloff: f32) -> f32 {
+    if d <= 0.0 {
+        1.0
+    } else if falloff <= 0.0 {
+        0.0
+    } else {
+        // smoothstep: без излома ни на краю фигуры, ни на краю спада
+        let t = (1.0 - d / falloff).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// Три прохода скользящего среднего шириной 2·`radius` + 1 по строкам и
+/// столбцам
+fn box_blur(values: &mut [f32], width: u32, height: u32, radius: u32) {
+    if radius == 0 || values.is_empty() {
+        return;
+    }
+    let (w, h) = (width as usize, height as usize);
+    let mut line = Vec::new();
+    for _ in 0..3 {
+        for y in 0..h {
+            blur_line(values, y * w, 1, w, radius as usize, &mut line);
+        }
+        for x in 0..w {
+            blur_line(values, x, w, h, radius as usize, &mut line);
+        }
+    }
+}
+
+/// Скользящее среднее `len` значений с шагом `stride` от `start`
+fn blur_line(
+    values: &mut [f32],
+    start: usize,
+    stride: usize,
+    len: usize,
+    radius: usize,
+    line: &mut Vec<f32>,
+) {
+    line.clear();
+    line.extend((0..len).map(|i| values[start + i * stride]));
+    let at = |i: isize| line[i.clamp(0, len as isize - 1) as usize] as f64;
+    let r = radius as isize;
+    let mut sum: f64 = (-r..=r).map(at).sum();
+    let n = (2 * radius + 1) as f64;
+    for i in 0..len as isize {
+        values[start + i as usize * stride] = (sum / n) as f32;
+        sum += at(i + r + 1) - at(i - r);
+    }
+}
@@ -0,0 +1,299 @@
+//! Лучи по рельефу — выбор точки камерой и проверка видимости. Пространство
+//! то же, что у `TerrainMesh`: метры, x — на восток, y — вверх (высота над
+//! уровнем моря, умноженная на `vertical_scale`), z — на юг, начало —
+//! северо-западный угол. Луч пересекается с теми же треугольниками, что
+//! в сетке всей карты с шагом 1, так что попадание лежит ровно на ней;
+//! клетки перебираются вдоль луча (DDA), а не все подряд.
+
+use seed_config::WorldConfig;
+use serde::Deserialize;
+
+use crate::terrain::Heightmap;
+
+/// Настройки луча; в JSON — camelCase, недостающие поля по умолчанию
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct RayOptions {
+    /// Как у сетки рельефа
+    pub vertical_scale: f64,
+    /// Дальше луч не идёт, м
+    pub max_distance: f64,
+    /// Луч останавливается и на поверхности моря (y = 0), а не только на дне
+    pub water: bool,
+}
+
+impl Default for RayOptions {
+    fn default() -> Self {
+        RayOptions {
+            vertical_scale: 1.0,
+            max_distance: f64::INFINITY,
+            water: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub point: [f64; 3],
+    /// Нормаль треугольника сетки (или воды), единичная, смотрит вверх
+    pub normal: [f64; 3],
+    /// От начала луча, м
+    pub distance: f64,
+    /// Попадание в поверхность моря
+    pub water: bool,
+}
+
+/// Первое пересечение луча из `origin` по `direction` с рельефом
+pub fn raycast(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    origin: [f64; 3],
+    direction: [f64; 3],
+    options: &RayOptions,
+) -> Option<RayHit> {
+    let len = (direction[0].powi(2) + direction[1].powi(2) + direction[2].powi(2)).sqrt();
+    if len.is_nan() || len <= 0.0 || hm.width < 2 || hm.height < 2 {
+        return None;
+    }
+    let dir = direction.map(|d| d / len);
+    let field = Field::new(cfg, hm, options.vertical_scale);
+    let (w, h) = (hm.width as usize, hm.height as usize);
+    let (max_x, max_z) = ((w - 1) as f64 * field.cell_m, (h - 1) as f64 * field.cell_m);
+
+    // отрезок луча над картой
+    let mut t_enter = 0.0f64;
+    let mut t_exit = options.max_distance;
+    for (o, d, max) in [(origin[0], dir[0], max_x), (origin[2], dir[2], max_z)] {
+        if d == 0.0 {
+            if o < 0.0 || o > max {
+                return None;
+            }
+        } else {
+            let (t0, t1) = ((0.0 - o) / d, (max - o) / d);
+            t_enter = t_enter.max(t0.min(t1));
+            t_exit = t_exit.min(t0.max(t1));
+        }
+    }
+    if t_enter > t_exit {
+        return None;
+    }
+
+    let water = options
+        .water
+        .then(|| water_hit(origin, dir, t_enter, t_exit))
+        .flatten();
+    let terrain = field.traverse(
+        origin,
+        dir,
+        t_enter,
+        t_exit.min(water.map_or(f64::INFINITY, |h| h.distance)),
+    );
+    terrain.or(water)
+}
+
+/// Видно ли `b` из `a`: рельеф (и вода с `options.water`) не заслоняет
+/// отрезок между ними. Точки на самой поверхности заслоняют себя — их
+/// стоит приподнять, например, на высоту глаз
+pub fn line_of_sight(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    a: [f64; 3],
+    b: [f64; 3],
+    options: &RayOptions,
+) -> bool {
+    let d = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let dist = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+    if dist == 0.0 {
+        return true;
+    }
+    let options = RayOptions {
+        max_distance: dist,
+        ..options.clone()
+    };
+    raycast(cfg, hm, a, d, &options).is_none()
+}
+
+/// Пересечение с поверхностью моря на отрезке луча `t0..t1`
+fn water_hit(origin: [f64; 3], dir: [f64; 3], t0: f64, t1: f64) -> Option<RayHit> {
+    if dir[1] >= 0.0 || origin[1] < 0.0 {
+        return None;
+    }
+    let t = -origin[1] / dir[1];
+    (t0..=t1).contains(&t).then(|| RayHit {
+        point: [origin[0] + dir[0] * t, 0.0, origin[2] + dir[2] * t],
+        normal: [0.0, 1.0, 0.0],
+        distance: t,
+        water: true,
+    })
+}
+
+struct Field<'a> {
+    hm: &'a Heightmap,
+    sea_level: f64,
+    cell_m: f64,
+    vertical_scale: f64,
+}
+
+impl<'a> Field<'a> {
+    fn new(cfg: &WorldConfig, hm: &'a Heightmap, vertical_scale: f64) -> Self {
+        Field {
+            hm,
+            sea_level: cfg.sea_level,
+            cell_m: cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64,
+            vertical_scale,
+        }
+    }
+
+    /// Вершина сетки в клетке (x, y)
+    fn vertex(&self, x: usize, y: usize) -> [f64; 3] {
+        let elevation =
+            self.hm.elevation_m(self.sea_level, x as u32, y as u32) * self.vertical_scale;
+        [x as f64 * self.cell_m, elevation, y as f64 * self.cell_m]
+    }
+
+    /// Клетки сетки вдоль луча на `t0..t1`; первая, где он задел треугольник
+    fn traverse(&self, origin: [f64; 3], dir: [f64; 3], t0: f64, t1: f64) -> Option<RayHit> {
+        let (qw, qh) = (self.hm.width as usize - 1, self.hm.height as usize - 1);
+        let cell = |v: f64, n: usize| ((v / self.cell_m).floor().max(0.0) as usize).min(n - 1);
+        let (mut qx, mut qz) = (
+            cell(at(origin, dir, t0, 0), qw),
+            cell(at(origin, dir, t0, 2), qh),
+        );
+
+        // DDA: t следующей границы по x и по z и шаг t между границами
+        let axis = |q: usize, k: usize| {
+            let d = dir[k];
+            if d > 0.0 {
+                (
+                    ((q + 1) as f64 * self.cell_m - origin[k]) / d,
+                    self.cell_m / d,
+                    1i64,
+                )
+            } else if d < 0.0 {
+                (
+                    (q as f64 * self.cell_m - origin[k]) / d,
+                    -self.cell_m / d,
+                    -1i64,
+                )
+            } else {
+                (f64::INFINITY, f64::INFINITY, 0)
+            }
+        };
+        let (mut next_x, dt_x, step_x) = axis(qx, 0);
+        let (mut next_z, dt_z, step_z) = axis(qz, 2);
+
+        loop {
+            if let Some(hit) = self.quad_hit(qx, qz, origin, dir, t0, t1) {
+                return Some(hit);
+            }
+            let t_next = next_x.min(next_z);
+            if !t_next.is_finite() || t_next > t1 {
+                return None;
+            }
+            if next_x < next_z {
+                let nx = qx as i64 + step_x;
+                if nx < 0 || nx >= qw as i64 {
+                    return None;
+                }
+                qx = nx as usize;
+                next_x += dt_x;
+            } else {
+                let nz = qz as i64 + step_z;
+                if nz < 0 || nz >= qh as i64 {
+                    return None;
+                }
+                qz = nz as usize;
+                next_z += dt_z;
+            }
+        }
+    }
+
+    /// Ближайшее пересечение с двумя треугольниками клетки сетки (qx, qz) —
+    /// как в `TerrainMesh`: tl-bl-tr и tr-bl-br
+    fn quad_hit(
+        &self,
+        qx: usize,
+        qz: usize,
+        origin: [f64; 3],
+        dir: [f64; 3],
+        t0: f64,
+        t1: f64,
+    ) -> Option<RayHit> {
+        let tl = self.vertex(qx, qz);
+        let tr = self.vertex(qx + 1, qz);
+        let bl = self.vertex(qx, qz + 1);
+        let br = self.vertex(qx + 1, qz + 1);
+        [(tl, bl, tr), (tr, bl, br)]
+            .into_iter()
+            .filter_map(|(a, b, c)| {
+                let t = intersect(origin, dir, a, b, c)?;
+                (t >= t0 && t <= t1).then_some((t, a, b, c))
+            })
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .map(|(t, a, b, c)| RayHit {
+                point: [0, 1, 2].map(|k| at(origin, dir, t, k)),
+                normal: face_normal(a, b, c),
+                distance: t,
+                water: false,
+            })
+    }
+}
+
+fn at(origin: [f64; 3], dir: [f64; 3], t: f64, k: usize) -> f64 {
+    origin[k] + dir[k] * t
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Möller–Trumbore: t пересечения луча с треугольником с обеих сторон
+fn intersect(
+    origin: [f64; 3],
+    dir: [f64; 3],
+    a: [f64; 3],
+    b: [f64; 3],
+    c: [f64; 3],
+) -> Option<f64> {
+    let (e1, e2) = (sub(b, a), sub(c, a));
+    let p = cross(dir, e2);
+    let det = dot(e1, p);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let s = sub(origin, a);
+    let u = dot(s, p) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = cross(s, e1);
+    let v = dot(dir, q) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some(dot(e2, q) / det)
+}
+
+/// Нормаль треугольника, повёрнутая вверх
+fn face_normal(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> [f64; 3] {
+    let n = cross(sub(b, a), sub(c, a));
+    let len = dot(n, n).sqrt();
+    let sign = if n[1] < 0.0 { -1.0 } else { 1.0 };
+    if len > 0.0 {
+        n.map(|v| v * sign / len)
+    } else {
+        [0.0, 1.0, 0.0]
+    }
+}
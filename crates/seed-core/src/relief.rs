@@ -0,0 +1,97 @@
+//! Освещение рельефа — карта нормалей (tangent space) и отмывка
+//! (hillshade), общие для seed-cli и seed-wasm. Наклон считается по
+//! высотам в метрах и размеру клетки на местности, так что картинка не
+//! зависит от разрешения карты. Растры — построчно с севера, пиксель —
+//! клетка.
+
+use seed_config::WorldConfig;
+
+use crate::terrain::Heightmap;
+
+/// Солнце для отмывки: азимут по часовой стрелке от севера и высота над
+/// горизонтом, градусы
+#[derive(Debug, Clone, Copy)]
+pub struct Sun {
+    pub azimuth_deg: f64,
+    pub altitude_deg: f64,
+}
+
+impl Sun {
+    /// Единичный вектор на солнце в осях (восток, север, вверх)
+    pub fn direction(self) -> (f64, f64, f64) {
+        let (az, alt) = (
+            self.azimuth_deg.to_radians(),
+            self.altitude_deg.to_radians(),
+        );
+        (az.sin() * alt.cos(), az.cos() * alt.cos(), alt.sin())
+    }
+}
+
+/// Отмывка: освещённость каждой клетки, 0..255
+pub fn hillshade(hm: &Heightmap, cfg: &WorldConfig, z_factor: f64, sun: Sun) -> Vec<u8> {
+    let light = sun.direction();
+    cells(hm)
+        .map(|(x, y)| {
+            let shade = shade(surface_normal(hm, cfg, z_factor, x, y), light);
+            (shade * 255.0).round() as u8
+        })
+        .collect()
+}
+
+/// Карта нормалей: RGB — восток, север (с `green_down` — юг, как в
+/// DirectX), вверх. v текстуры растёт на юг, поэтому в OpenGL зелёный —
+/// это север
+pub fn normal_map(
+    hm: &Heightmap,
+    cfg: &WorldConfig,
+    z_factor: f64,
+    green_down: bool,
+) -> Vec<[u8; 3]> {
+    cells(hm)
+        .map(|(x, y)| {
+            let (east, north, up) = surface_normal(hm, cfg, z_factor, x, y);
+            let green = if green_down { -north } else { north };
+            [encode_normal(east), encode_normal(green), encode_normal(up)]
+        })
+        .collect()
+}
+
+/// Освещённость по Ламберту, 0..1
+pub fn shade((east, north, up): (f64, f64, f64), light: (f64, f64, f64)) -> f64 {
+    (east * light.0 + north * light.1 + up * light.2).max(0.0)
+}
+
+/// Единичная нормаль в осях (восток, север, вверх); градиент — по Хорну,
+/// по окну 3×3 с клэмпом на краю
+pub fn surface_normal(
+    hm: &Heightmap,
+    cfg: &WorldConfig,
+    z_factor: f64,
+    x: u32,
+    y: u32,
+) -> (f64, f64, f64) {
+    let cell = cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64;
+    let z = |dx: i64, dy: i64| {
+        let xx = (x as i64 + dx).clamp(0, hm.width as i64 - 1) as u32;
+        let yy = (y as i64 + dy).clamp(0, hm.height as i64 - 1) as u32;
+        hm.elevation_m(cfg.sea_level, xx, yy)
+    };
+    let dz_east = ((z(1, -1) + 2.0 * z(1, 0) + z(1, 1)) - (z(-1, -1) + 2.0 * z(-1, 0) + z(-1, 1)))
+        / (8.0 * cell);
+    // строки карты идут с севера на юг
+    let dz_north = ((z(-1, -1) + 2.0 * z(0, -1) + z(1, -1)) - (z(-1, 1) + 2.0 * z(0, 1) + z(1, 1)))
+        / (8.0 * cell);
+    let (nx, ny, nz) = (-dz_east * z_factor, -dz_north * z_factor, 1.0);
+    let len = (nx * nx + ny * ny + nz * nz).sqrt();
+    (nx / len, ny / len, nz / len)
+}
+
+/// -1..1 → 0..255
+pub fn encode_normal(v: f64) -> u8 {
+    ((v * 0.5 + 0.5) * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn cells(hm: &Heightmap) -> impl Iterator<Item = (u32, u32)> {
+    let w = hm.width;
+    (0..hm.height).flat_map(move |y| (0..w).map(move |x| (x, y)))
+}
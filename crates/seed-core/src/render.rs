@@ -0,0 +1,319 @@
+//! Карта мира в RGBA — общая для веб-клиента (seed-wasm) и сервера:
+//! цвета биомов, вода с градиентом глубины, снег, пляжи, реки по стоку и
+//! освещение рельефа. Слои включаются по отдельности (`Layers`), без
+//! `biomes` суша раскрашивается по высоте. Свет, снеговая линия, порог рек
+//! и цвета настраиваются `RenderOptions`; по умолчанию — как всегда.
+//!
+//! Пиксель — клетка карты; участок `Region` рисуется так же, как его место
+//! на полной карте (снег по широте и сток считаются по всей карте).
+
+use std::collections::BTreeMap;
+
+use seed_config::WorldConfig;
+use serde::Deserialize;
+
+use crate::biome::BiomeMap;
+use crate::terrain::{compute_flow_accumulation, Heightmap};
+
+const SHALLOW: [u8; 3] = [70, 140, 200];
+const DEEP: [u8; 3] = [10, 30, 80];
+const RIVER: [u8; 3] = [30, 120, 220];
+const BEACH: [u8; 3] = [210, 190, 120];
+const SNOW: [u8; 3] = [255, 255, 255];
+const BEACH_WIDTH: f32 = 0.03;
+const SLOPE_SCALE: f32 = 40.0;
+const AMBIENT: f32 = 0.3;
+const SNOW_HEIGHT_START: f32 = 0.7;
+const SNOW_LAT_START: f32 = 0.5;
+/// Сток, с которого клетка рисуется рекой (доля максимального)
+const RIVER_FLOW_MIN: f32 = 0.1;
+
+/// Слои карты; в JSON недостающие слои включены
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layers {
+    pub biomes: bool,
+    /// Освещение рельефа
+    pub relief: bool,
+    pub rivers: bool,
+    pub snow: bool,
+    /// Пляжи у берега
+    pub coast: bool,
+}
+
+impl Layers {
+    pub const ALL: Layers = Layers {
+        biomes: true,
+        relief: true,
+        rivers: true,
+        snow: true,
+        coast: true,
+    };
+
+    pub const NONE: Layers = Layers {
+        biomes: false,
+        relief: false,
+        rivers: false,
+        snow: false,
+        coast: false,
+    };
+
+    /// Слои через запятую: `biomes,relief,rivers,snow,coast` или `all`
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut layers = Layers::NONE;
+        for name in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match name {
+                "all" => layers = Layers::ALL,
+                "biomes" => layers.biomes = true,
+                "relief" => layers.relief = true,
+                "rivers" => layers.rivers = true,
+                "snow" => layers.snow = true,
+                "coast" => layers.coast = true,
+                other => return Err(format!("unknown layer '{other}'")),
+            }
+        }
+        Ok(layers)
+    }
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Layers::ALL
+    }
+}
+
+/// Настройки карты. Из JSON (camelCase) берутся только заданные поля,
+/// например `{"layers": {"rivers": false}, "snowHeight": 0.6}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct RenderOptions {
+    pub layers: Layers,
+    /// Направление на источник света: x — на восток, y — на юг, z — вверх
+    pub light_dir: [f32; 3],
+    /// Освещённость склонов в тени, 0..1
+    pub ambient: f32,
+    /// Высота карты (0..1), с которой начинается снег
+    pub snow_height: f32,
+    /// Широта (0 — экватор, 1 — полюс), с которой начинается снег
+    pub snow_latitude: f32,
+    /// Сток (доля максимального), с которого клетка рисуется рекой
+    pub river_flow: f32,
+    /// Цвета вместо обычных: id биома из конфига или `shallow`, `deep`,
+    /// `river`, `beach`, `snow`
+    pub palette: BTreeMap<String, [u8; 3]>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            layers: Layers::ALL,
+            light_dir: [0.6, 0.6, 1.0],
+            ambient: AMBIENT,
+            snow_height: SNOW_HEIGHT_START,
+            snow_latitude: SNOW_LAT_START,
+            river_flow: RIVER_FLOW_MIN,
+            palette: BTreeMap::new(),
+        }
+    }
+}
+
+impl RenderOptions {
+    fn color(&self, key: &str, default: [u8; 3]) -> [u8; 3] {
+        self.palette.get(key).copied().unwrap_or(default)
+    }
+}
+
+/// Участок карты в клетках: угол (x0, y0), ширина и высота
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x0: u32,
+    pub y0: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn full(hm: &Heightmap) -> Self {
+        Region {
+            x0: 0,
+            y0: 0,
+            width: hm.width,
+            height: hm.height,
+        }
+    }
+
+    /// Участок целиком внутри карты `hm` и не пустой
+    pub fn fits(&self, hm: &Heightmap) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self
+                .x0
+                .checked_add(self.width)
+                .is_some_and(|x1| x1 <= hm.width)
+            && self
+                .y0
+                .checked_add(self.height)
+                .is_some_and(|y1| y1 <= hm.height)
+    }
+}
+
+/// Цвета биомов конфига по порядку; неизвестным — стабильный цвет по id
+pub fn biome_palette(cfg: &WorldConfig) -> Vec<[u8; 3]> {
+    cfg.biomes
+        .iter()
+        .map(|b| match b.id.as_str() {
+            "temperate_forest" => [45, 125, 45],
+            "hot_desert" => [218, 185, 110],
+            "cold_mountains" => [140, 145, 155],
+            "tundra" => [135, 165, 145],
+            "tropical_rainforest" => [20, 100, 35],
+            "savanna" => [185, 165, 95],
+            "taiga" => [55, 100, 65],
+            "ice_sheet" => [240, 248, 255],
+            "wetland" => [90, 120, 100],
+            "grassland" => [140, 170, 90],
+            "shrubland" => [160, 140, 100],
+            "mediterranean" => [170, 180, 110],
+            _ => {
+                // приглушённые цвета, без кислотных
+                let mut h = simple_hash(&b.id) as u64;
+                let r = 70 + ((h & 0xFF) as u8) / 2;
+                h >>= 8;
+                let g = 70 + ((h & 0xFF) as u8) / 2;
+                h >>= 8;
+                let bl = 70 + ((h & 0xFF) as u8) / 2;
+                [r, g, bl]
+            }
+        })
+        .collect()
+}
+
+fn simple_hash(s: &str) -> u32 {
+    let mut h = 0u32;
+    for b in s.bytes() {
+        h = h.wrapping_mul(31).wrapping_add(b as u32);
+    }
+    h
+}
+
+fn normalize3(x: f32, y: f32, z: f32) -> (f32, f32, f32) {
+    let len = (x * x + y * y + z * z).sqrt().max(1e-6);
+    (x / len, y / len, z / len)
+}
+
+fn mix(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    [
+        (a[0] as f32 * (1.0 - t) + b[0] as f32 * t) as u8,
+        (a[1] as f32 * (1.0 - t) + b[1] as f32 * t) as u8,
+        (a[2] as f32 * (1.0 - t) + b[2] as f32 * t) as u8,
+    ]
+}
+
+/// RGBA участка `region` (должен помещаться в карту), строки с севера на юг
+pub fn render_rgba(
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    cfg: &WorldConfig,
+    region: Region,
+    options: &RenderOptions,
+) -> Vec<u8> {
+    let mut buf = vec![0u8; (region.width * region.height * 4) as usize];
+    let layers = options.layers;
+    let palette: Vec<[u8; 3]> = biome_palette(cfg)
+        .into_iter()
+        .zip(&cfg.biomes)
+        .map(|(color, b)| options.color(&b.id, color))
+        .collect();
+    let (shallow, deep) = (
+        options.color("shallow", SHALLOW),
+        options.color("deep", DEEP),
+    );
+    let (river, beach, snow_color) = (
+        options.color("river", RIVER),
+        options.color("beach", BEACH),
+        options.color("snow", SNOW),
+    );
+    let sea_level = cfg.sea_level as f32;
+    let flow = if layers.rivers {
+        compute_flow_accumulation(hm, sea_level)
+    } else {
+        Vec::new()
+    };
+    let [lx, ly, lz] = options.light_dir;
+    let light_dir = normalize3(lx, ly, lz);
+    let ambient = options.ambient.clamp(0.0, 1.0);
+    // у 1.0 деление на ноль
+    let snow_height = options.snow_height.min(0.999);
+    let snow_latitude = options.snow_latitude.min(0.999);
+    let river_flow = options.river_flow.min(0.999);
+    let h_h = hm.height as f32;
+
+    for ry in 0..region.height {
+        for rx in 0..region.width {
+            let (x, y) = (region.x0 + rx, region.y0 + ry);
+            let hc = hm.get(x, y);
+
+            let shade = if layers.relief {
+                let xl = x.saturating_sub(1);
+                let xr = (x + 1).min(hm.width - 1);
+                let yu = y.saturating_sub(1);
+                let yd = (y + 1).min(hm.height - 1);
+                let dx = hm.get(xr, y) - hm.get(xl, y);
+                let dy = hm.get(x, yd) - hm.get(x, yu);
+                let normal = normalize3(-dx * SLOPE_SCALE, -dy * SLOPE_SCALE, 1.0);
+                let dot = normal.0 * light_dir.0 + normal.1 * light_dir.1 + normal.2 * light_dir.2;
+                (ambient + dot.max(0.0) * (1.0 - ambient)).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+
+            let biome = bm.get_index(x, y).filter(|i| *i < palette.len());
+            let mut color = match biome {
+                Some(bi) if layers.biomes => palette[bi],
+                // суша без слоя биомов — по высоте
+                _ if !layers.biomes && hc > sea_level => {
+                    let g = (90.0 + hc * 140.0).min(255.0) as u8;
+                    [g, g, g]
+                }
+                _ => {
+                    // вода: градиент по глубине
+                    let depth = (sea_level - hc).max(0.0);
+                    mix(shallow, deep, (depth / sea_level).clamp(0.0, 1.0))
+                }
+            };
+
+            if layers.snow {
+                let lat_abs = ((y as f32 / (h_h - 1.0)) * 2.0 - 1.0).abs();
+                let height_factor = ((hc - snow_height) / (1.0 - snow_height)).clamp(0.0, 1.0);
+                let lat_factor =
+                    ((lat_abs - snow_latitude) / (1.0 - snow_latitude)).clamp(0.0, 1.0);
+                let snow = (height_factor * lat_factor).clamp(0.0, 1.0);
+                if snow > 0.0 {
+                    color = mix(color, snow_color, snow);
+                }
+            }
+
+            if layers.coast && hc > sea_level {
+                let dh = hc - sea_level;
+                if dh < BEACH_WIDTH {
+                    color = mix(color, beach, 1.0 - (dh / BEACH_WIDTH).clamp(0.0, 1.0));
+                }
+            }
+
+            if layers.rivers && hc > sea_level {
+                let f = flow[(y * hm.width + x) as usize];
+                if f > river_flow {
+                    let t = ((f - river_flow) / (1.0 - river_flow)).clamp(0.0, 1.0);
+                    color = mix(color, river, t.powf(0.4));
+                }
+            }
+
+            let idx = ((ry * region.width + rx) * 4) as usize;
+            for c in 0..3 {
+                buf[idx + c] = (color[c] as f32 * shade).round().clamp(0.0, 255.0) as u8;
+            }
+            buf[idx + 3] = 255;
+        }
+    }
+    buf
+}
@@ -0,0 +1,80 @@
+//! Дерево сидов: у каждой подсистемы, а внутри неё у каждого чанка,
+//! события или слоя шума — свой сид, выведенный из сида мира хешем пути
+//! (`SeedTree::from_config(cfg).derive("objects").derive_chunk(x, y)`).
+//! Потоки разных веток не зависят друг от друга, а один и тот же путь на
+//! любой платформе даёт один и тот же сид — подсистему можно добавить или
+//! перенастроить, не сдвинув случайность остальных.
+//!
+//! Имена веток подсистем: `terrain` (от `geology.heightmap.baseSeed`),
+//! `biomes`, `objects`, `catastrophes`, `volcanoes`, `history`, `names`,
+//! `director`, `weather` (от `worldSeed`).
+
+use noise::Perlin;
+use seed_config::WorldConfig;
+
+use crate::rng::SplitMix64;
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Сид узла дерева; потомки выводятся из него и имени или номера ветки
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SeedTree {
+    seed: u64,
+}
+
+impl SeedTree {
+    pub fn new(seed: u64) -> Self {
+        SeedTree { seed }
+    }
+
+    /// Корень от `worldSeed`
+    pub fn from_config(cfg: &WorldConfig) -> Self {
+        SeedTree::new(cfg.world_seed)
+    }
+
+    /// Ветка с именем `label`
+    pub fn derive(&self, label: &str) -> Self {
+        let hash = label.bytes().fold(0xCBF2_9CE4_8422_2325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01B3)
+        });
+        self.child(hash)
+    }
+
+    /// Ветка с номером `index` — шаг, событие, объект
+    pub fn derive_index(&self, index: u64) -> Self {
+        self.child(mix(index.wrapping_add(GOLDEN_GAMMA)))
+    }
+
+    /// Ветка чанка (`x`, `y`)
+    pub fn derive_chunk(&self, x: u32, y: u32) -> Self {
+        self.derive_index(((x as u64) << 32) | y as u64)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Сид для генераторов с 32-битным сидом (Perlin)
+    pub fn seed_u32(&self) -> u32 {
+        (self.seed ^ self.seed >> 32) as u32
+    }
+
+    pub(crate) fn rng(&self) -> SplitMix64 {
+        SplitMix64::new(self.seed)
+    }
+
+    pub(crate) fn perlin(&self) -> Perlin {
+        Perlin::new(self.seed_u32())
+    }
+
+    fn child(&self, key: u64) -> Self {
+        SeedTree::new(mix(self.seed.wrapping_add(GOLDEN_GAMMA) ^ key))
+    }
+}
+
+/// Финализатор SplitMix64: близкие входы — далёкие выходы
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,264 @@
+use crate::biome::BiomeMap;
+use crate::catastrophe::Catastrophe;
+use crate::geo::MapGeo;
+use crate::terrain::{compute_flow_accumulation, Heightmap};
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Растр пригодности для поселений, значения в [0..1] (0 — жить нельзя)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuitabilityMap {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+impl SuitabilityMap {
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> f32 {
+        self.values[(y * self.width + x) as usize]
+    }
+}
+
+/// Город на карте мира
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct City {
+    pub id: String,
+    pub name: String,
+    pub faction_id: String,
+    pub x: u32,
+    pub y: u32,
+    /// Год основания (0 — стартовое состояние истории)
+    pub founded_year: u32,
+    /// Горожане
+    pub population: u64,
+    /// Сельское население угодий вокруг города
+    pub rural_population: u64,
+    pub is_capital: bool,
+}
+
+/// Почему город превратился в руины
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuinCause {
+    /// Разрушен в войне
+    Razed,
+    /// Покинут жителями (голод, катастрофы)
+    Abandoned,
+}
+
+/// Руины разрушенного или покинутого города — точка интереса на карте
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ruin {
+    /// id бывшего города
+    pub id: String,
+    pub name: String,
+    /// Последний владелец города
+    pub faction_id: String,
+    pub x: u32,
+    pub y: u32,
+    pub founded_year: u32,
+    pub destroyed_year: u32,
+    pub population_at_destruction: u64,
+    pub cause: RuinCause,
+}
+
+/// Поток, начиная с которого клетка считается рекой (доля от максимального стока)
+const RIVER_FLOW_THRESHOLD: f32 = 0.02;
+
+/// Относительная карта риска катастроф в [0..1]: сумма влияний событий с затуханием
+/// от эпицентра, нормированная на самую опасную клетку.
+pub fn compute_risk_map(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    catastrophes: &[Catastrophe],
+) -> Vec<f32> {
+    let w = hm.width as usize;
+    let h = hm.height as usize;
+    let mut risk = vec![0.0f32; w * h];
+    if w == 0 || h == 0 {
+        return risk;
+    }
+
+    let geo = MapGeo::from_config(cfg, hm.width, hm.height);
+    for cat in catastrophes {
+        let radius = risk_radius_km(&geo, cat);
+        for ((x, y), d) in geo.cells_within(cat.position.into(), radius) {
+            let falloff = (1.0 - d / radius).max(0.0) as f32;
+            risk[y as usize * w + x as usize] += falloff;
+        }
+    }
+
+    let max_risk = risk.iter().cloned().fold(0.0f32, f32::max);
+    if max_risk > 0.0 {
+        for r in &mut risk {
+            *r /= max_risk;
+        }
+    }
+    risk
+}
+
+/// Охват события для риска, км: радиус события, но не меньше пары клеток
+/// (иначе риск не виден) и не больше четверти карты
+pub(crate) fn risk_radius_km(geo: &MapGeo, cat: &Catastrophe) -> f64 {
+    let cell_km = geo.km_per_cell_y();
+    let max_cells = geo.width.max(geo.height) as f64 / 4.0;
+    cat.radius_km
+        .clamp(2.0 * cell_km, (max_cells * cell_km).max(2.0 * cell_km))
+}
+
+/// Пригодность клетки для города: ровная суша у воды, в биоме, где разрешены поселения,
+/// с умеренной высотой и низким риском катастроф.
+pub fn compute_suitability(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    risk: Option<&[f32]>,
+) -> SuitabilityMap {
+    let w = hm.width;
+    let h = hm.height;
+    let len = (w * h) as usize;
+    let sea_level = cfg.sea_level as f32;
+
+    // Пресная и морская вода: море + клетки с большим стоком
+    let flow = compute_flow_accumulation(hm, sea_level);
+    let water: Vec<bool> = (0..len)
+        .map(|i| hm.values[i] <= sea_level || flow[i] >= RIVER_FLOW_THRESHOLD)
+        .collect();
+    let water_dist = distance_to(&water, w, h);
+
+    let mut values = vec![0.0f32; len];
+    for y in 0..h {
+        for x in 0..w {
+            let idx = (y * w + x) as usize;
+            let hc = hm.values[idx];
+            if hc <= sea_level || water[idx] {
+                continue;
+            }
+
+            let biome = match bm.get_index(x, y).and_then(|bi| cfg.biomes.get(bi)) {
+                Some(b) => b,
+                None => continue,
+            };
+            if !biome.allow_settlements {
+                continue;
+            }
+
+            // равнинность
+            let slope = local_slope(hm, x, y);
+            let flat = (1.0 - slope * 25.0).clamp(0.0, 1.0);
+
+            // близость воды: лучше всего 1–3 клетки от берега/реки
+            let d = water_dist[idx] as f32;
+            let near_water = (1.0 - (d - 1.0).max(0.0) / 12.0).clamp(0.0, 1.0);
+
+            // высота: низины удобнее высокогорий
+            let rel = ((hc - sea_level) / (1.0 - sea_level).max(1e-6)).clamp(0.0, 1.0);
+            let lowland = 1.0 - rel.powf(0.8);
+
+            // плодородие по растительности биома
+            let fertility = 0.3 + 0.7 * biome.vegetation_density.clamp(0.0, 1.0);
+
+            // даже в самом опасном месте люди селятся, просто реже
+            let safety = 1.0 - 0.7 * risk.map(|r| r[idx]).unwrap_or(0.0);
+
+            values[idx] =
+                (flat * (0.3 + 0.7 * near_water) * lowland * fertility * safety).clamp(0.0, 1.0);
+        }
+    }
+
+    SuitabilityMap {
+        width: w,
+        height: h,
+        values,
+    }
+}
+
+/// Лучшая клетка в квадрате радиуса `radius` вокруг (cx, cy), которая не ближе
+/// `min_spacing` к уже существующим городам.
+pub fn best_site_near(
+    suit: &SuitabilityMap,
+    cx: u32,
+    cy: u32,
+    radius: u32,
+    cities: &[City],
+    min_spacing: f64,
+) -> Option<(u32, u32, f32)> {
+    let x0 = cx.saturating_sub(radius);
+    let y0 = cy.saturating_sub(radius);
+    let x1 = (cx + radius).min(suit.width.saturating_sub(1));
+    let y1 = (cy + radius).min(suit.height.saturating_sub(1));
+
+    let mut best: Option<(u32, u32, f32)> = None;
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            let s = suit.get(x, y);
+            if s <= 0.0 {
+                continue;
+            }
+            if best.map(|(_, _, bs)| s <= bs).unwrap_or(false) {
+                continue;
+            }
+            let crowded = cities.iter().any(|c| {
+                let dx = c.x as f64 - x as f64;
+                let dy = c.y as f64 - y as f64;
+                (dx * dx + dy * dy).sqrt() < min_spacing
+            });
+            if !crowded {
+                best = Some((x, y, s));
+            }
+        }
+    }
+    best
+}
+
+fn local_slope(hm: &Heightmap, x: u32, y: u32) -> f32 {
+    let xl = x.saturating_sub(1);
+    let xr = (x + 1).min(hm.width - 1);
+    let yu = y.saturating_sub(1);
+    let yd = (y + 1).min(hm.height - 1);
+    let dx = (hm.get(xr, y) - hm.get(xl, y)) * 0.5;
+    let dy = (hm.get(x, yd) - hm.get(x, yu)) * 0.5;
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Манхэттенское расстояние (в клетках) до ближайшей отмеченной клетки, BFS.
+fn distance_to(mask: &[bool], w: u32, h: u32) -> Vec<u32> {
+    let w = w as usize;
+    let h = h as usize;
+    let mut dist = vec![u32::MAX; w * h];
+    let mut queue = VecDeque::new();
+
+    for (i, &m) in mask.iter().enumerate() {
+        if m {
+            dist[i] = 0;
+            queue.push_back(i);
+        }
+    }
+
+    while let Some(i) = queue.pop_front() {
+        let x = i % w;
+        let y = i / w;
+        let d = dist[i] + 1;
+        let mut visit = |j: usize| {
+            if dist[j] > d {
+                dist[j] = d;
+                queue.push_back(j);
+            }
+        };
+        if x > 0 {
+            visit(i - 1);
+        }
+        if x + 1 < w {
+            visit(i + 1);
+        }
+        if y > 0 {
+            visit(i - w);
+        }
+        if y + 1 < h {
+            visit(i + w);
+        }
+    }
+
+    dist
+}
@@ -0,0 +1,232 @@
+//! Материал поверхности по клеткам — для splat-карт рендеров и для следов
+//! на сервере. Поверх материала биома (`baseMaterialId`, иначе первый из
+//! `dominantMaterials`): круче `ROCK_SLOPE_DEG` — скала, где среднегодовая
+//! температура ниже `SNOWLINE_TEMPERATURE_C` — снег, низкий берег у воды —
+//! песок. Пустыня с песчаным материалом биома остаётся песком, остальное —
+//! почва биома. Материал клетки — из `materials` конфига: для биома — по
+//! id, для скалы, песка и снега — первый материал этой категории;
+//! категория id без материала — часть до `_` (`soil_cold` → `soil`).
+
+use seed_config::{MaterialConfig, WorldConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::biome::{BiomeMap, ClimateMap};
+use crate::relief::surface_normal;
+use crate::terrain::Heightmap;
+
+/// Круче рыхлый грунт не держится — голая скала, градусы
+const ROCK_SLOPE_DEG: f64 = 45.0;
+/// Холоднее в среднем за год — снег круглый год, °C
+const SNOWLINE_TEMPERATURE_C: f32 = -2.0;
+/// Берег ниже этой высоты над морем — пляж, м
+const BEACH_HEIGHT_M: f64 = 8.0;
+/// Пляж — не дальше стольких клеток от воды
+const BEACH_REACH_CELLS: i64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SurfaceKind {
+    Water,
+    Rock,
+    Sand,
+    Snow,
+    Soil,
+}
+
+impl SurfaceKind {
+    /// Категория материала: `water`, `rock`, `sand`, `snow`, `soil`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SurfaceKind::Water => "water",
+            SurfaceKind::Rock => "rock",
+            SurfaceKind::Sand => "sand",
+            SurfaceKind::Snow => "snow",
+            SurfaceKind::Soil => "soil",
+        }
+    }
+
+    /// Канал splat-карты: R — скала, G — песок, B — снег, A — почва
+    fn splat_channel(self) -> Option<usize> {
+        match self {
+            SurfaceKind::Water => None,
+            SurfaceKind::Rock => Some(0),
+            SurfaceKind::Sand => Some(1),
+            SurfaceKind::Snow => Some(2),
+            SurfaceKind::Soil => Some(3),
+        }
+    }
+
+    fn from_category(category: &str) -> Self {
+        match category {
+            "rock" => SurfaceKind::Rock,
+            "sand" => SurfaceKind::Sand,
+            "snow" => SurfaceKind::Snow,
+            _ => SurfaceKind::Soil,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SurfaceMap {
+    pub width: u32,
+    pub height: u32,
+    pub kinds: Vec<SurfaceKind>,
+    /// Номер материала клетки в `materials` конфига; `None` — такого нет
+    pub materials: Vec<Option<u16>>,
+}
+
+impl SurfaceMap {
+    pub fn kind_at(&self, x: u32, y: u32) -> SurfaceKind {
+        self.kinds[(y * self.width + x) as usize]
+    }
+
+    pub fn material_at<'a>(
+        &self,
+        cfg: &'a WorldConfig,
+        x: u32,
+        y: u32,
+    ) -> Option<&'a MaterialConfig> {
+        let i = self.materials[(y * self.width + x) as usize]?;
+        cfg.materials.get(i as usize)
+    }
+
+    /// Категория материала клетки, без материала — категория её класса
+    pub fn category_at<'a>(&self, cfg: &'a WorldConfig, x: u32, y: u32) -> &'a str {
+        match self.material_at(cfg, x, y) {
+            Some(m) => &m.category,
+            None => self.kind_at(x, y).as_str(),
+        }
+    }
+
+    /// Остаются ли на клетке следы: `supportsFootprints` материала, без
+    /// материала — на рыхлом (песок, снег, почва)
+    pub fn supports_footprints(&self, cfg: &WorldConfig, x: u32, y: u32) -> bool {
+        match self.material_at(cfg, x, y) {
+            Some(m) => m.supports_footprints,
+            None => matches!(
+                self.kind_at(x, y),
+                SurfaceKind::Sand | SurfaceKind::Snow | SurfaceKind::Soil
+            ),
+        }
+    }
+
+    /// Splat-карта построчно с севера: RGBA — доли скалы, песка, снега и
+    /// почвы по окну 3×3 вокруг клетки, в сумме 255; на воде — нули
+    pub fn splat_map(&self) -> Vec<[u8; 4]> {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let mut out = Vec::with_capacity(self.kinds.len());
+        for y in 0..h {
+            for x in 0..w {
+                if self.kinds[(y * w + x) as usize] == SurfaceKind::Water {
+                    out.push([0; 4]);
+                    continue;
+                }
+                let mut counts = [0u32; 4];
+                for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+                    let (xx, yy) = ((x + dx).clamp(0, w - 1), (y + dy).clamp(0, h - 1));
+                    if let Some(c) = self.kinds[(yy * w + xx) as usize].splat_channel() {
+                        counts[c] += 1;
+                    }
+                }
+                let total = counts.iter().sum::<u32>().max(1);
+                out.push(counts.map(|c| (c * 255 / total) as u8));
+            }
+        }
+        out
+    }
+}
+
+/// Материалы поверхности по рельефу, биомам и климату той же карты
+pub fn compute_surface_map(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    climate: &ClimateMap,
+) -> SurfaceMap {
+    let (w, h) = (hm.width, hm.height);
+    let biomes: Vec<(SurfaceKind, Option<u16>)> = cfg
+        .biomes
+        .iter()
+        .map(|b| {
+            let id = b
+                .base_material_id
+                .as_deref()
+                .or(b.dominant_materials.first().map(String::as_str));
+            match id {
+                Some(id) => {
+                    let material = find_material(cfg, id);
+                    let category = match material {
+                        Some(i) => cfg.materials[i as usize].category.as_str(),
+                        None => category_of(id),
+                    };
+                    (SurfaceKind::from_category(category), material)
+                }
+                None => (SurfaceKind::Soil, find_material(cfg, "soil")),
+            }
+        })
+        .collect();
+    let by_kind = |kind: SurfaceKind| find_material(cfg, kind.as_str());
+    let (rock, sand, snow, soil) = (
+        by_kind(SurfaceKind::Rock),
+        by_kind(SurfaceKind::Sand),
+        by_kind(SurfaceKind::Snow),
+        by_kind(SurfaceKind::Soil),
+    );
+    let min_up = ROCK_SLOPE_DEG.to_radians().cos();
+    let water = |x: i64, y: i64| {
+        let (x, y) = (
+            x.clamp(0, w as i64 - 1) as u32,
+            y.clamp(0, h as i64 - 1) as u32,
+        );
+        hm.elevation_m(cfg.sea_level, x, y) < 0.0
+    };
+
+    let mut map = SurfaceMap {
+        width: w,
+        height: h,
+        kinds: Vec::with_capacity((w * h) as usize),
+        materials: Vec::with_capacity((w * h) as usize),
+    };
+    for y in 0..h {
+        for x in 0..w {
+            let elevation = hm.elevation_m(cfg.sea_level, x, y);
+            let (kind, material) = if elevation < 0.0 {
+                (SurfaceKind::Water, None)
+            } else if surface_normal(hm, cfg, 1.0, x, y).2 < min_up {
+                (SurfaceKind::Rock, rock)
+            } else if climate.temperature_at(x, y) < SNOWLINE_TEMPERATURE_C {
+                (SurfaceKind::Snow, snow)
+            } else if elevation < BEACH_HEIGHT_M
+                && (-BEACH_REACH_CELLS..=BEACH_REACH_CELLS).any(|dy| {
+                    (-BEACH_REACH_CELLS..=BEACH_REACH_CELLS)
+                        .any(|dx| water(x as i64 + dx, y as i64 + dy))
+                })
+            {
+                (SurfaceKind::Sand, sand)
+            } else {
+                match bm.get_index(x, y).and_then(|i| biomes.get(i)) {
+                    Some(&(kind, material)) => (kind, material),
+                    None => (SurfaceKind::Soil, soil),
+                }
+            };
+            map.kinds.push(kind);
+            map.materials.push(material);
+        }
+    }
+    map
+}
+
+/// Материал по id, иначе первый той же категории
+fn find_material(cfg: &WorldConfig, id: &str) -> Option<u16> {
+    let category = category_of(id);
+    cfg.materials
+        .iter()
+        .position(|m| m.id == id)
+        .or_else(|| cfg.materials.iter().position(|m| m.category == category))
+        .map(|i| i as u16)
+}
+
+/// `soil_cold` → `soil`
+fn category_of(id: &str) -> &str {
+    id.split('_').next().unwrap_or(id)
+}
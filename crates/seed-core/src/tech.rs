@@ -0,0 +1,177 @@
+//! Технологические эпохи фракций. Прогресс копится с годами: быстрее у
+//! многолюдных и торгующих держав, а соседи по торговле перенимают знания
+//! у более развитых партнёров.
+
+use crate::history::{History, HistoryEvent, HistoryEventKind};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Эпохи в порядке развития
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Era {
+    Stone,
+    Bronze,
+    Iron,
+    Medieval,
+    Renaissance,
+    Industrial,
+    Modern,
+    Futuristic,
+}
+
+impl Era {
+    pub const ALL: [Era; 8] = [
+        Era::Stone,
+        Era::Bronze,
+        Era::Iron,
+        Era::Medieval,
+        Era::Renaissance,
+        Era::Industrial,
+        Era::Modern,
+        Era::Futuristic,
+    ];
+
+    /// Эпоха по `techLevel` пресета фракции (синонимы: tribal, classical, space)
+    pub fn from_tech_level(tech_level: &str) -> Option<Era> {
+        Some(match tech_level {
+            "stone" | "tribal" => Era::Stone,
+            "bronze" => Era::Bronze,
+            "iron" | "classical" => Era::Iron,
+            "medieval" => Era::Medieval,
+            "renaissance" => Era::Renaissance,
+            "industrial" => Era::Industrial,
+            "modern" => Era::Modern,
+            "futuristic" | "space" => Era::Futuristic,
+            _ => return None,
+        })
+    }
+
+    /// Идентификатор эпохи — значение `techLevel`
+    pub fn id(self) -> &'static str {
+        match self {
+            Era::Stone => "stone",
+            Era::Bronze => "bronze",
+            Era::Iron => "iron",
+            Era::Medieval => "medieval",
+            Era::Renaissance => "renaissance",
+            Era::Industrial => "industrial",
+            Era::Modern => "modern",
+            Era::Futuristic => "futuristic",
+        }
+    }
+
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Era::Stone => "Stone Age",
+            Era::Bronze => "Bronze Age",
+            Era::Iron => "Iron Age",
+            Era::Medieval => "Middle Ages",
+            Era::Renaissance => "Renaissance",
+            Era::Industrial => "Industrial Age",
+            Era::Modern => "Modern Age",
+            Era::Futuristic => "Space Age",
+        }
+    }
+
+    pub fn next(self) -> Option<Era> {
+        Era::ALL.get(self as usize + 1).copied()
+    }
+
+    /// Военный множитель эпохи
+    pub fn strength(self) -> f64 {
+        match self {
+            Era::Stone => 0.6,
+            Era::Bronze => 0.8,
+            Era::Iron => 0.9,
+            Era::Medieval => 1.0,
+            Era::Renaissance => 1.3,
+            Era::Industrial => 1.8,
+            Era::Modern => 2.5,
+            Era::Futuristic => 3.5,
+        }
+    }
+
+    /// Сколько лет держава в миллион жителей без торговли идёт к следующей эпохе
+    fn research_years(self) -> f64 {
+        match self {
+            Era::Stone => 400.0,
+            Era::Bronze => 350.0,
+            Era::Iron => 350.0,
+            Era::Medieval => 400.0,
+            Era::Renaissance => 300.0,
+            Era::Industrial => 200.0,
+            Era::Modern => 300.0,
+            Era::Futuristic => f64::INFINITY,
+        }
+    }
+}
+
+/// Прибавка к скорости прогресса от знаний более развитого торгового партнёра
+const DIFFUSION_BONUS: f64 = 0.5;
+
+/// Продвижение фракций по эпохам за `dt_years` лет
+pub(crate) fn tech_step(history: &mut History, year: u32, dt_years: u32) {
+    let dt = dt_years as f64;
+    let trade = history.trade.volume_between_factions(&history.cities);
+
+    let mut population: BTreeMap<&str, u64> = BTreeMap::new();
+    let mut own_trade: BTreeMap<&str, f64> = BTreeMap::new();
+    for c in &history.cities {
+        *population.entry(&c.faction_id).or_insert(0) += c.population + c.rural_population;
+    }
+    for r in &history.trade.routes {
+        if let Some(c) = history.cities.get(r.from_city) {
+            *own_trade.entry(&c.faction_id).or_insert(0.0) += r.volume;
+        }
+    }
+    let eras: BTreeMap<&str, Era> = history
+        .factions
+        .iter()
+        .map(|f| (f.id.as_str(), f.era()))
+        .collect();
+
+    let mut advanced = Vec::new();
+    for (fi, f) in history.factions.iter().enumerate() {
+        let pop = population.get(f.id.as_str()).copied().unwrap_or(0);
+        if pop == 0 {
+            continue;
+        }
+        let era = f.era();
+        // большие державы думают быстрее, но не пропорционально размеру
+        let mut rate = (pop as f64 / 1e6).powf(0.3).clamp(0.3, 2.0);
+        rate *= 1.0 + (own_trade.get(f.id.as_str()).copied().unwrap_or(0.0) / 20_000.0).min(0.5);
+        let learns = trade.iter().any(|((a, b), v)| {
+            let other = if *a == f.id {
+                b
+            } else if *b == f.id {
+                a
+            } else {
+                return false;
+            };
+            *v > 0.0 && eras.get(other.as_str()).is_some_and(|&e| e > era)
+        });
+        if learns {
+            rate *= 1.0 + DIFFUSION_BONUS;
+        }
+        let progress = f.tech_progress + rate * dt / era.research_years();
+        advanced.push((fi, progress));
+    }
+
+    for (fi, progress) in advanced {
+        let faction = &mut history.factions[fi];
+        faction.tech_progress = progress;
+        if progress < 1.0 {
+            continue;
+        }
+        let Some(next) = faction.era().next() else {
+            continue;
+        };
+        faction.tech_level = next.id().to_string();
+        faction.tech_progress = 0.0;
+        history.events.push(HistoryEvent {
+            year,
+            faction_id: faction.id.clone(),
+            kind: HistoryEventKind::EraAdvanced { era: next },
+        });
+    }
+}
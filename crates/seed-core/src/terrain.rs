@@ -1,7 +1,12 @@
+use crate::progress::{map_rows, GenerationPhase, NoProgress, Progress};
+use crate::seed_tree::SeedTree;
 use noise::{NoiseFn, Perlin};
 use seed_config::{HeightmapConfig, WorldConfig};
 use std::f64::consts::PI;
 
+/// Перепад высот карты: heightmap 1.0 над уровнем моря — столько метров
+pub const MAX_RELIEF_M: f64 = 3500.0;
+
 #[derive(Debug, Clone)]
 pub struct Heightmap {
     pub width: u32,
@@ -30,6 +35,24 @@ impl Heightmap {
     pub fn get(&self, x: u32, y: u32) -> f32 {
         self.values[self.index(x, y)]
     }
+
+    /// Высота клетки над уровнем моря `sea_level` (0..1), м; дно моря — ниже нуля
+    pub fn elevation_m(&self, sea_level: f64, x: u32, y: u32) -> f64 {
+        (self.get(x, y) as f64 - sea_level) / (1.0 - sea_level).max(1e-6) * MAX_RELIEF_M
+    }
+
+    /// Высота (0..1) в точке (x, y) в клетках, билинейно между центрами
+    /// клеток; точки за краем карты прижимаются к нему
+    pub fn sample(&self, x: f64, y: f64) -> f32 {
+        let x = x.clamp(0.0, self.width.saturating_sub(1) as f64);
+        let y = y.clamp(0.0, self.height.saturating_sub(1) as f64);
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = ((x - x0 as f64) as f32, (y - y0 as f64) as f32);
+        let top = self.get(x0, y0) * (1.0 - tx) + self.get(x1, y0) * tx;
+        let bottom = self.get(x0, y1) * (1.0 - tx) + self.get(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
 }
 
 impl Heightmap {
@@ -63,208 +86,551 @@ impl Heightmap {
     }
 }
 
-/// Континенты + горные хребты (анизотропные) + детали.
-pub fn generate_heightmap_from_config(cfg: &WorldConfig, width: u32, height: u32) -> Heightmap {
-    let hcfg: &HeightmapConfig = &cfg.geology.heightmap;
+/// D8: соседняя клетка с наибольшим перепадом вниз (None — локальный минимум).
+pub fn d8_downslope(hm: &Heightmap, x: u32, y: u32) -> Option<(u32, u32)> {
+    let h_here = hm.get(x, y);
+    let mut best_diff = 0.0f32;
+    let mut best: Option<(u32, u32)> = None;
 
-    let base_seed = hcfg.base_seed as u32;
+    for dy in -1i32..=1 {
+        for dx in -1i32..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx < 0 || ny < 0 || nx >= hm.width as i32 || ny >= hm.height as i32 {
+                continue;
+            }
+            // по диагонали расстояние больше — нормируем перепад
+            let dist = if dx != 0 && dy != 0 {
+                std::f32::consts::SQRT_2
+            } else {
+                1.0
+            };
+            let diff = (h_here - hm.get(nx as u32, ny as u32)) / dist;
+            if diff > best_diff {
+                best_diff = diff;
+                best = Some((nx as u32, ny as u32));
+            }
+        }
+    }
 
-    // Разные генераторы с разными seed'ами
-    let perlin_cont = Perlin::new(base_seed);
-    let perlin_detail = Perlin::new(base_seed ^ 0x1234_5678);
-    let perlin_ridge1 = Perlin::new(base_seed ^ 0x8765_4321);
-    let perlin_ridge2 = Perlin::new(base_seed.wrapping_add(7777));
-    let perlin_warp = Perlin::new(base_seed.wrapping_add(999));
+    best
+}
 
-    let mut raw_values = Vec::with_capacity((width * height) as usize);
+/// Прямоугольник полной карты в долях её размера (0..1 по каждой оси; 0 и 1 —
+/// центры крайних клеток, как у `fx`/`fy` генераторов). `FULL` — вся карта.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapWindow {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+}
 
-    // Масштаб континентов (в "условных км") - УВЕЛИЧЕН для более плавного рельефа
-    let continental_scale = hcfg.continental_scale_km.max(10.0) * 1.5;
+impl MapWindow {
+    pub const FULL: MapWindow = MapWindow {
+        x0: 0.0,
+        y0: 0.0,
+        x1: 1.0,
+        y1: 1.0,
+    };
+
+    /// Окно по клеткам карты `width`×`height`: от (x0, y0) до (x1, y1) включительно
+    pub fn from_cells(width: u32, height: u32, x0: u32, y0: u32, x1: u32, y1: u32) -> Self {
+        let w1 = (width.saturating_sub(1).max(1)) as f64;
+        let h1 = (height.saturating_sub(1).max(1)) as f64;
+        MapWindow {
+            x0: x0 as f64 / w1,
+            y0: y0 as f64 / h1,
+            x1: x1 as f64 / w1,
+            y1: y1 as f64 / h1,
+        }
+    }
 
-    let freq_cont = 0.5 / continental_scale; // очень низкая частота
-    let freq_detail_base = 3.0 * freq_cont; // детали - уменьшено для плавности
-    let freq_ridge = 1.8 * freq_cont; // горные цепи - мягче
-    let freq_warp = 0.8 * freq_cont; // warp - меньше искажений
-    let warp_strength = 0.35; // уменьшена интенсивность warp
+    /// Доля полной карты по x для столбца `x` из `w1 + 1`
+    #[inline]
+    pub(crate) fn fx(&self, x: u32, w1: f64) -> f64 {
+        self.x0 + x as f64 / w1 * (self.x1 - self.x0)
+    }
 
-    // Смещения от seed, чтобы карта не была привязана к (0,0)
-    let offset_x = (base_seed as f64 * 12_345.6789).sin() * 1000.0;
-    let offset_y = (base_seed as f64 * 98_765.4321).cos() * 1000.0;
+    /// Доля полной карты по y для строки `y` из `h1 + 1`
+    #[inline]
+    pub(crate) fn fy(&self, y: u32, h1: f64) -> f64 {
+        self.y0 + y as f64 / h1 * (self.y1 - self.y0)
+    }
+}
 
-    // Направления горных хребтов (в градусах)
-    let theta1 = 25.0_f64 / 180.0 * PI; // первый “магистральный” хребет
-    let theta2 = -40.0_f64 / 180.0 * PI; // второй, пересекающий
+/// Шум рельефа до эрозии: высота в точке (fx, fy) полной карты не зависит от
+/// её разрешения
+#[derive(Clone)]
+struct HeightNoise {
+    cont: Perlin,
+    detail: Perlin,
+    ridge1: Perlin,
+    ridge2: Perlin,
+    warp: Perlin,
+    continental_scale: f64,
+    freq_cont: f64,
+    freq_detail_base: f64,
+    freq_ridge: f64,
+    freq_warp: f64,
+    warp_strength: f64,
+    offset_x: f64,
+    offset_y: f64,
+    axis1: (f64, f64),
+    ortho1: (f64, f64),
+    axis2: (f64, f64),
+    ortho2: (f64, f64),
+}
 
-    let axis1 = (theta1.cos(), theta1.sin());
-    let ortho1 = (-theta1.sin(), theta1.cos());
-    let axis2 = (theta2.cos(), theta2.sin());
-    let ortho2 = (-theta2.sin(), theta2.cos());
+impl HeightNoise {
+    fn new(hcfg: &HeightmapConfig) -> Self {
+        let seeds = SeedTree::new(hcfg.base_seed).derive("terrain");
+        let mut offsets = seeds.derive("offset").rng();
+
+        // Масштаб континентов (в "условных км") - УВЕЛИЧЕН для более плавного рельефа
+        let continental_scale = hcfg.continental_scale_km.max(10.0) * 1.5;
+        let freq_cont = 0.5 / continental_scale; // очень низкая частота
+
+        // Направления горных хребтов (в градусах)
+        let theta1 = 25.0_f64 / 180.0 * PI; // первый “магистральный” хребет
+        let theta2 = -40.0_f64 / 180.0 * PI; // второй, пересекающий
+
+        HeightNoise {
+            // Разные генераторы с разными seed'ами
+            cont: seeds.derive("continents").perlin(),
+            detail: seeds.derive("detail").perlin(),
+            ridge1: seeds.derive("ridge1").perlin(),
+            ridge2: seeds.derive("ridge2").perlin(),
+            warp: seeds.derive("warp").perlin(),
+            continental_scale,
+            freq_cont,
+            freq_detail_base: 3.0 * freq_cont, // детали - уменьшено для плавности
+            freq_ridge: 1.8 * freq_cont,       // горные цепи - мягче
+            freq_warp: 0.8 * freq_cont,        // warp - меньше искажений
+            warp_strength: 0.35,               // уменьшена интенсивность warp
+            // Смещения от seed, чтобы карта не была привязана к (0,0)
+            offset_x: offsets.range_f64(-1000.0, 1000.0),
+            offset_y: offsets.range_f64(-1000.0, 1000.0),
+            axis1: (theta1.cos(), theta1.sin()),
+            ortho1: (-theta1.sin(), theta1.cos()),
+            axis2: (theta2.cos(), theta2.sin()),
+            ortho2: (-theta2.sin(), theta2.cos()),
+        }
+    }
 
-    let mut min_v = f64::MAX;
-    let mut max_v = f64::MIN;
+    /// Высота до эрозии в точке (fx, fy) ∈ [0..1]² полной карты
+    fn sample(&self, fx: f64, fy: f64) -> f64 {
+        // Базовые координаты в "мировом" пространстве
+        let px = fx * self.continental_scale + self.offset_x;
+        let py = fy * self.continental_scale + self.offset_y;
+
+        // Domain warp
+        let wx = self.warp.get([px * self.freq_warp, py * self.freq_warp]);
+        let wy = self
+            .warp
+            .get([(px + 100.0) * self.freq_warp, (py - 50.0) * self.freq_warp]);
+        let xw = px + wx * self.warp_strength * self.continental_scale;
+        let yw = py + wy * self.warp_strength * self.continental_scale;
+
+        // --- Континенты ---
+        let cont_raw = self.cont.get([xw * self.freq_cont, yw * self.freq_cont]);
+
+        // Порог "уровня моря": чем выше bias, тем больше океанов
+        let sea_bias = 0.1;
+        let cont = cont_raw - sea_bias;
+
+        let land = cont.max(0.0); // суша (0.. ~1)
+
+        // --- Градиент континентального шума (для размещения хребтов) ---
+        let eps = 0.5 * self.continental_scale; // шаг для оценки градиента
+        let cont_x1 = self
+            .cont
+            .get([(xw + eps) * self.freq_cont, yw * self.freq_cont]);
+        let cont_x0 = cont_raw;
+        let cont_y1 = self
+            .cont
+            .get([xw * self.freq_cont, (yw + eps) * self.freq_cont]);
+
+        let dx = cont_x1 - cont_x0;
+        let dy = cont_y1 - cont_x0;
+        let grad_mag = (dx * dx + dy * dy).sqrt(); // чем больше, тем резче переход
+        let grad_factor = (grad_mag * 2.0).clamp(0.0, 1.5); // поджимаем сверху
+
+        // --- Детали рельефа ---
+        let mut detail = 0.0;
+        let mut amp = 1.0;
+        let mut f = self.freq_detail_base;
+        for _ in 0..3 {
+            let d = self.detail.get([xw * f, yw * f]);
+            detail += amp * d;
+            amp *= 0.5;
+            f *= 2.0;
+        }
+        // Чуть меньше амплитуда мелких деталей,
+        // чтобы рельеф был более плавным.
+        detail *= 0.18;
 
-    let w1 = (width.saturating_sub(1).max(1)) as f64;
-    let h1 = (height.saturating_sub(1).max(1)) as f64;
-
-    for y in 0..height {
-        for x in 0..width {
-            // Нормированные координаты [0..1]
-            let fx = x as f64 / w1;
-            let fy = y as f64 / h1;
-
-            // Базовые координаты в "мировом" пространстве
-            let px = fx * continental_scale + offset_x;
-            let py = fy * continental_scale + offset_y;
-
-            // Domain warp
-            let wx = perlin_warp.get([px * freq_warp, py * freq_warp]);
-            let wy = perlin_warp.get([(px + 100.0) * freq_warp, (py - 50.0) * freq_warp]);
-            let xw = px + wx * warp_strength * continental_scale;
-            let yw = py + wy * warp_strength * continental_scale;
-
-            // --- Континенты ---
-            let cont_raw = perlin_cont.get([xw * freq_cont, yw * freq_cont]);
-
-            // Порог "уровня моря": чем выше bias, тем больше океанов
-            let sea_bias = 0.1;
-            let cont = cont_raw - sea_bias;
-
-            let land = cont.max(0.0); // суша (0.. ~1)
-
-            // --- Градиент континентального шума (для размещения хребтов) ---
-            let eps = 0.5 * continental_scale; // шаг для оценки градиента
-            let cont_x1 = perlin_cont.get([(xw + eps) * freq_cont, yw * freq_cont]);
-            let cont_x0 = cont_raw;
-            let cont_y1 = perlin_cont.get([xw * freq_cont, (yw + eps) * freq_cont]);
-
-            let dx = cont_x1 - cont_x0;
-            let dy = cont_y1 - cont_x0;
-            let grad_mag = (dx * dx + dy * dy).sqrt(); // чем больше, тем резче переход
-            let grad_factor = (grad_mag * 2.0).clamp(0.0, 1.5); // поджимаем сверху
-
-            // --- Детали рельефа ---
-            let mut detail = 0.0;
-            let mut amp = 1.0;
-            let mut f = freq_detail_base;
-            for _ in 0..3 {
-                let d = perlin_detail.get([xw * f, yw * f]);
-                detail += amp * d;
-                amp *= 0.5;
-                f *= 2.0;
-            }
-            // Чуть меньше амплитуда мелких деталей,
-            // чтобы рельеф был более плавным.
-            detail *= 0.18;
+        // --- Анизотропные горные хребты ---
 
-            // --- Анизотропные горные хребты ---
+        // Проекция точки на ось и перпендикуляр (для хребта 1)
+        let u1 = (xw * self.axis1.0 + yw * self.axis1.1) * self.freq_ridge;
+        let v1 = (xw * self.ortho1.0 + yw * self.ortho1.1) * self.freq_ridge * 0.35; // 0.35 => вытянутые
 
-            // Проекция точки на ось и перпендикуляр (для хребта 1)
-            let u1 = (xw * axis1.0 + yw * axis1.1) * freq_ridge;
-            let v1 = (xw * ortho1.0 + yw * ortho1.1) * freq_ridge * 0.35; // 0.35 => вытянутые
+        // Хребет 1
+        let r1_src = self.ridge1.get([u1, v1]);
+        let ridge1 = (1.0 - r1_src.abs()).max(0.0).powf(1.7); // пики
 
-            // Хребет 1
-            let r1_src = perlin_ridge1.get([u1, v1]);
-            let ridge1 = (1.0 - r1_src.abs()).max(0.0).powf(1.7); // пики
+        // Хребет 2 (пересекающийся)
+        let u2 = (xw * self.axis2.0 + yw * self.axis2.1) * self.freq_ridge * 0.9;
+        let v2 = (xw * self.ortho2.0 + yw * self.ortho2.1) * self.freq_ridge * 0.4;
 
-            // Хребет 2 (пересекающийся)
-            let u2 = (xw * axis2.0 + yw * axis2.1) * freq_ridge * 0.9;
-            let v2 = (xw * ortho2.0 + yw * ortho2.1) * freq_ridge * 0.4;
+        let r2_src = self.ridge2.get([u2, v2]);
+        let ridge2 = (1.0 - r2_src.abs()).max(0.0).powf(1.7);
 
-            let r2_src = perlin_ridge2.get([u2, v2]);
-            let ridge2 = (1.0 - r2_src.abs()).max(0.0).powf(1.7);
+        let ridge_sum = 0.6 * ridge1 + 0.4 * ridge2; // смесь двух направлений
 
-            let ridge_sum = 0.6 * ridge1 + 0.4 * ridge2; // смесь двух направлений
+        // Хребты только на суше + усиление там, где сильный градиент континента
+        let mountain_raw = ridge_sum * land * grad_factor;
 
-            // Хребты только на суше + усиление там, где сильный градиент континента
-            let mountain_raw = ridge_sum * land * grad_factor;
+        // Нормируем горы в [0..~2]
+        let mountain = mountain_raw.clamp(0.0, 2.0);
 
-            // Нормируем горы в [0..~2]
-            let mountain = mountain_raw.max(0.0).min(2.0);
+        // --- Итоговая высота ---
 
-            // --- Итоговая высота ---
+        // 1) базовый "каркас" суши
+        let base_land = land.powf(1.2);
 
-            // 1) базовый "каркас" суши
-            let base_land = land.powf(1.2);
+        // 2) прибрежная зона — сглаживаем детали и горы около берега
+        let coastal_width = 0.18;
+        let coastal = (land / coastal_width).clamp(0.0, 1.0);
 
-            // 2) прибрежная зона — сглаживаем детали и горы около берега
-            let coastal_width = 0.18;
-            let coastal = (land / coastal_width).clamp(0.0, 1.0);
+        // Смягчаем вклад гор, чтобы пики были менее резкими.
+        let mountain_inland = mountain * (0.4 + 0.5 * coastal); // 0.4..0.9
+        let detail_inland = detail * coastal;
 
-            // Смягчаем вклад гор, чтобы пики были менее резкими.
-            let mountain_inland = mountain * (0.4 + 0.5 * coastal); // 0.4..0.9
-            let detail_inland = detail * coastal;
+        let mut elevation = base_land + detail_inland + mountain_inland;
 
-            let mut elevation = base_land + detail_inland + mountain_inland;
+        if elevation < 0.0 {
+            elevation = 0.0;
+        }
 
-            if elevation < 0.0 {
-                elevation = 0.0;
-            }
+        elevation
+    }
+
+    /// Строки шума карты `width`×`height`, покрывающей окно `window`
+    fn rows(
+        &self,
+        width: u32,
+        height: u32,
+        window: MapWindow,
+        progress: &dyn Progress,
+    ) -> Vec<f64> {
+        map_rows(height, GenerationPhase::Noise, progress, |y| {
+            self.row(width, height, window, y)
+        })
+    }
+
+    /// Строка `y` шума карты `width`×`height`, покрывающей окно `window`
+    fn row(&self, width: u32, height: u32, window: MapWindow, y: u32) -> Vec<f64> {
+        let w1 = (width.saturating_sub(1).max(1)) as f64;
+        let h1 = (height.saturating_sub(1).max(1)) as f64;
+        let fy = window.fy(y, h1);
+        (0..width)
+            .map(|x| self.sample(window.fx(x, w1), fy))
+            .collect()
+    }
+}
+
+/// Континенты + горные хребты (анизотропные) + детали.
+pub fn generate_heightmap_from_config(cfg: &WorldConfig, width: u32, height: u32) -> Heightmap {
+    generate_heightmap_with_progress(cfg, width, height, &NoProgress)
+}
+
+/// То же, что `generate_heightmap_from_config`, с отчётом о прогрессе шума и эрозии
+pub fn generate_heightmap_with_progress(
+    cfg: &WorldConfig,
+    width: u32,
+    height: u32,
+    progress: &dyn Progress,
+) -> Heightmap {
+    let noise = HeightNoise::new(&cfg.geology.heightmap);
+    let raw_values = eroded_values(&noise, width, height, progress);
+    normalized(width, height, &raw_values)
+}
 
-            raw_values.push(elevation);
+/// Карта из шума после эрозии
+fn normalized(width: u32, height: u32, raw_values: &[f64]) -> Heightmap {
+    // После эрозии min/max поменялись — пересчитаем
+    let (min_v, range) = value_range(raw_values);
+    Heightmap {
+        width,
+        height,
+        values: raw_values
+            .iter()
+            .map(|&v| normalize_height(v, min_v, range))
+            .collect(),
+    }
+}
 
-            if elevation < min_v {
-                min_v = elevation;
+/// Heightmap по кускам, для однопоточных сред вроде браузера: строки шума,
+/// затем по проходу эрозии за `step`. Карта та же, что у
+/// `generate_heightmap_from_config`.
+#[derive(Clone)]
+pub struct HeightmapBuilder {
+    noise: HeightNoise,
+    width: u32,
+    height: u32,
+    raw_values: Vec<f64>,
+    rows_done: u32,
+    passes_done: u32,
+}
+
+impl HeightmapBuilder {
+    pub fn new(cfg: &WorldConfig, width: u32, height: u32) -> Self {
+        Self {
+            noise: HeightNoise::new(&cfg.geology.heightmap),
+            width,
+            height,
+            raw_values: Vec::with_capacity(width as usize * height as usize),
+            rows_done: 0,
+            passes_done: 0,
+        }
+    }
+
+    /// Следующий кусок: до `rows` строк шума или один проход эрозии (он
+    /// идёт по всей карте). true — карта готова
+    pub fn step(&mut self, rows: u32) -> bool {
+        if self.rows_done < self.height {
+            let end = self.rows_done.saturating_add(rows.max(1)).min(self.height);
+            for y in self.rows_done..end {
+                let row = self.noise.row(self.width, self.height, MapWindow::FULL, y);
+                self.raw_values.extend(row);
             }
-            if elevation > max_v {
-                max_v = elevation;
+            self.rows_done = end;
+        } else if self.passes_done < EROSION_PASSES {
+            erosion_pass(
+                &self.noise,
+                self.width,
+                self.height,
+                &mut self.raw_values,
+                self.passes_done,
+            );
+            self.passes_done += 1;
+        }
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.passes_done == EROSION_PASSES
+    }
+
+    /// Текущий этап и доля выполненного в нём
+    pub fn progress(&self) -> (GenerationPhase, f32) {
+        if self.rows_done < self.height {
+            (
+                GenerationPhase::Noise,
+                self.rows_done as f32 / self.height as f32,
+            )
+        } else {
+            (
+                GenerationPhase::Erosion,
+                self.passes_done as f32 / EROSION_PASSES as f32,
+            )
+        }
+    }
+
+    /// Готовая карта; None, пока `step` не вернул true
+    pub fn finish(self) -> Option<Heightmap> {
+        self.is_done()
+            .then(|| normalized(self.width, self.height, &self.raw_values))
+    }
+}
+
+/// Участок `window` полной карты `map_width`×`map_height`, заново посчитанный
+/// в разрешении `width`×`height`. Эрозия, озёра и нормировка высот берутся с
+/// полной карты (иначе береговая линия и русла у краёв участка разошлись бы с
+/// картой), а шум, который полная карта не различает, досчитывается в новом
+/// разрешении поверх них.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_heightmap_window(
+    cfg: &WorldConfig,
+    map_width: u32,
+    map_height: u32,
+    window: MapWindow,
+    width: u32,
+    height: u32,
+    progress: &dyn Progress,
+) -> Heightmap {
+    HeightmapWindows::new(cfg, map_width, map_height, progress)
+        .window(window, width, height, progress)
+}
+
+/// Полная карта после эрозии для `generate_heightmap_window`: считается один
+/// раз, а участки из неё — сколько угодно, без повторной эрозии
+pub struct HeightmapWindows {
+    noise: HeightNoise,
+    eroded: Vec<f64>,
+    map_width: u32,
+    map_height: u32,
+    min_v: f64,
+    range: f64,
+}
+
+impl HeightmapWindows {
+    pub fn new(
+        cfg: &WorldConfig,
+        map_width: u32,
+        map_height: u32,
+        progress: &dyn Progress,
+    ) -> Self {
+        let noise = HeightNoise::new(&cfg.geology.heightmap);
+        let eroded = eroded_values(&noise, map_width, map_height, progress);
+        let (min_v, range) = value_range(&eroded);
+        HeightmapWindows {
+            noise,
+            eroded,
+            map_width,
+            map_height,
+            min_v,
+            range,
+        }
+    }
+
+    /// Участок `window` в разрешении `width`×`height`
+    pub fn window(
+        &self,
+        window: MapWindow,
+        width: u32,
+        height: u32,
+        progress: &dyn Progress,
+    ) -> Heightmap {
+        let mw1 = (self.map_width.saturating_sub(1).max(1)) as f64;
+        let mh1 = (self.map_height.saturating_sub(1).max(1)) as f64;
+
+        // Шум в узлах полной карты, на которые ложится окно: из него вычитается
+        // то, что карта уже знает, остаётся только мелкая деталь
+        let cx0 = (window.x0.min(window.x1) * mw1).floor().max(0.0) as u32;
+        let cy0 = (window.y0.min(window.y1) * mh1).floor().max(0.0) as u32;
+        let cx1 = ((window.x0.max(window.x1) * mw1).ceil() as u32).min(self.map_width - 1);
+        let cy1 = ((window.y0.max(window.y1) * mh1).ceil() as u32).min(self.map_height - 1);
+        let cache_w = (cx1 - cx0 + 1) as usize;
+        let coarse: Vec<f64> = (cy0..=cy1)
+            .flat_map(|y| (cx0..=cx1).map(move |x| (x, y)))
+            .map(|(x, y)| self.noise.sample(x as f64 / mw1, y as f64 / mh1))
+            .collect();
+
+        let fine = self.noise.rows(width, height, window, progress);
+        let w1 = (width.saturating_sub(1).max(1)) as f64;
+        let h1 = (height.saturating_sub(1).max(1)) as f64;
+
+        let mut values = Vec::with_capacity(fine.len());
+        for y in 0..height {
+            let gy = (window.fy(y, h1) * mh1).clamp(cy0 as f64, cy1 as f64);
+            for x in 0..width {
+                let gx = (window.fx(x, w1) * mw1).clamp(cx0 as f64, cx1 as f64);
+                let base = bilinear(&self.eroded, self.map_width as usize, gx, gy, 0, 0);
+                let coarse_noise = bilinear(&coarse, cache_w, gx, gy, cx0, cy0);
+                let detail = fine[(y * width + x) as usize] - coarse_noise;
+                let v = (base + detail).clamp(self.min_v, self.min_v + self.range);
+                values.push(normalize_height(v, self.min_v, self.range));
             }
         }
+
+        Heightmap {
+            width,
+            height,
+            values,
+        }
     }
+}
 
-    // --- МЯГКАЯ ЭРОЗИЯ: СНАЧАЛА ТЕРМИЧЕСКАЯ, ПОТОМ ГИДРО ---
+/// Шум полной карты после эрозии, озёр и сглаживания (ещё не нормирован)
+fn eroded_values(
+    noise: &HeightNoise,
+    width: u32,
+    height: u32,
+    progress: &dyn Progress,
+) -> Vec<f64> {
+    let mut raw_values = noise.rows(width, height, MapWindow::FULL, progress);
+
+    progress.report(GenerationPhase::Erosion, 0.0);
+    for pass in 0..EROSION_PASSES {
+        erosion_pass(noise, width, height, &mut raw_values, pass);
+        progress.report(
+            GenerationPhase::Erosion,
+            (pass + 1) as f32 / EROSION_PASSES as f32,
+        );
+    }
 
-    // 1. Термическая (осыпание склонов) - УСИЛЕНО для сглаживания
-    apply_thermal_erosion(
-        width,
-        height,
-        &mut raw_values,
-        16,    // iterations: больше итераций для более плавного рельефа
-        0.020, // talus: ниже порог для активной эрозии
-        0.22,  // amount: увеличено для более интенсивного сглаживания
-    );
-
-    // 2. Гидро-эрозия (формирование мягких русел) - СМЯГЧЕНО
-    apply_flow_erosion(
-        width,
-        height,
-        &mut raw_values,
-        0.22,  // water_level_fraction: реалистичный уровень моря
-        120.0, // flow_threshold: выше порог = меньше мелких русел
-        0.010, // carve_strength: ещё меньше глубина = более мелкие русла
-    );
-
-    // 3. Генерация озёр в низинах - УВЕЛИЧЕНО количество
-    apply_lake_formation(
-        width,
-        height,
-        &mut raw_values,
-        &perlin_detail,
-        0.12,  // min_depth: меньший минимум для большего количества озёр
-        0.012, // formation_chance: выше вероятность
-    );
-
-    // 4. Формирование каньонов в засушливых регионах - СМЯГЧЕНО
-    apply_canyon_erosion(
-        width,
-        height,
-        &mut raw_values,
-        &perlin_ridge1,
-        0.010, // carve_intensity: ещё меньше интенсивность = неглубокие каньоны
-    );
+    raw_values
+}
 
-    // 5. Финальное сглаживание для устранения артефактов - УСИЛЕНО
-    apply_gaussian_smooth(
-        width,
-        height,
-        &mut raw_values,
-        4,   // iterations: ещё чуть больше сглаживания
-        0.9, // sigma: немного шире фильтр
-    );
+/// Проходов в `erosion_pass`
+const EROSION_PASSES: u32 = 5;
 
-    // После эрозии min/max поменялись — пересчитаем
-    min_v = f64::MAX;
-    max_v = f64::MIN;
-    for &v in &raw_values {
+/// Проход `pass` (0..EROSION_PASSES) эрозии, озёр и сглаживания
+fn erosion_pass(noise: &HeightNoise, width: u32, height: u32, raw_values: &mut [f64], pass: u32) {
+    // --- МЯГКАЯ ЭРОЗИЯ: СНАЧАЛА ТЕРМИЧЕСКАЯ, ПОТОМ ГИДРО ---
+    match pass {
+        0 => {
+            // 1. Термическая (осыпание склонов) - УСИЛЕНО для сглаживания
+            apply_thermal_erosion(
+                width, height, raw_values,
+                16,    // iterations: больше итераций для более плавного рельефа
+                0.020, // talus: ниже порог для активной эрозии
+                0.22,  // amount: увеличено для более интенсивного сглаживания
+            );
+        }
+        1 => {
+            // 2. Гидро-эрозия (формирование мягких русел) - СМЯГЧЕНО
+            apply_flow_erosion(
+                width, height, raw_values,
+                0.22,  // water_level_fraction: реалистичный уровень моря
+                120.0, // flow_threshold: выше порог = меньше мелких русел
+                0.010, // carve_strength: ещё меньше глубина = более мелкие русла
+            );
+        }
+        2 => {
+            // 3. Генерация озёр в низинах - УВЕЛИЧЕНО количество
+            apply_lake_formation(
+                width,
+                height,
+                raw_values,
+                &noise.detail,
+                0.12,  // min_depth: меньший минимум для большего количества озёр
+                0.012, // formation_chance: выше вероятность
+            );
+        }
+        3 => {
+            // 4. Формирование каньонов в засушливых регионах - СМЯГЧЕНО
+            apply_canyon_erosion(
+                width,
+                height,
+                raw_values,
+                &noise.ridge1,
+                0.010, // carve_intensity: ещё меньше интенсивность = неглубокие каньоны
+            );
+        }
+        4 => {
+            // 5. Финальное сглаживание для устранения артефактов - УСИЛЕНО
+            apply_gaussian_smooth(
+                width, height, raw_values,
+                4,   // iterations: ещё чуть больше сглаживания
+                0.9, // sigma: немного шире фильтр
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Минимум и размах значений (размах не меньше 1e-6)
+fn value_range(values: &[f64]) -> (f64, f64) {
+    let mut min_v = f64::MAX;
+    let mut max_v = f64::MIN;
+    for &v in values {
         if v < min_v {
             min_v = v;
         }
@@ -272,34 +638,41 @@ pub fn generate_heightmap_from_config(cfg: &WorldConfig, width: u32, height: u32
             max_v = v;
         }
     }
+    (min_v, (max_v - min_v).max(1e-6))
+}
 
-    // Нормализация в [0..1]
-    let range = (max_v - min_v).max(1e-6);
-    let mut norm = Vec::with_capacity(raw_values.len());
-    for v in raw_values {
-        let mut x = (v - min_v) / range;
-        // Небольшое сглаживание: степени < 1 сглаживают контраст высот.
-        x = x.powf(0.9);
-        norm.push(x as f32);
-    }
+/// Нормализация в [0..1]
+fn normalize_height(v: f64, min_v: f64, range: f64) -> f32 {
+    let mut x = (v - min_v) / range;
+    // Небольшое сглаживание: степени < 1 сглаживают контраст высот.
+    x = x.powf(0.9);
+    x as f32
+}
 
-    Heightmap {
-        width,
-        height,
-        values: norm,
-    }
+/// Билинейная выборка сетки `values` шириной `stride`, чьи узлы начинаются с
+/// (`ox`, `oy`) в координатах (gx, gy)
+fn bilinear(values: &[f64], stride: usize, gx: f64, gy: f64, ox: u32, oy: u32) -> f64 {
+    let rows = values.len() / stride.max(1);
+    let lx = gx - ox as f64;
+    let ly = gy - oy as f64;
+    let x0 = (lx.floor().max(0.0) as usize).min(stride - 1);
+    let y0 = (ly.floor().max(0.0) as usize).min(rows - 1);
+    let x1 = (x0 + 1).min(stride - 1);
+    let y1 = (y0 + 1).min(rows - 1);
+    let tx = lx - x0 as f64;
+    let ty = ly - y0 as f64;
+    let at = |x: usize, y: usize| values[y * stride + x];
+    let top = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+    let bottom = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+    top * (1.0 - ty) + bottom * ty
 }
 
-/// D8-сток: для каждой клетки считаем, сколько "воды" через неё проходит.
-/// Возвращает вектор длиной width*height, значения нормированы в [0..1].
-pub fn compute_flow_accumulation(hm: &Heightmap, sea_level_norm: f32) -> Vec<f32> {
+/// D8-направления стока: для каждой клетки суши — самый низкий сосед ниже неё
+/// (None — море или бессточная впадина)
+pub(crate) fn flow_directions(hm: &Heightmap, sea_level_norm: f32) -> Vec<Option<usize>> {
     let w = hm.width as usize;
     let h = hm.height as usize;
     let len = w * h;
-    if len == 0 {
-        return Vec::new();
-    }
-
     let vals = &hm.values;
     let mut downslope: Vec<Option<usize>> = vec![None; len];
 
@@ -307,7 +680,7 @@ pub fn compute_flow_accumulation(hm: &Heightmap, sea_level_norm: f32) -> Vec<f32
     for y in 0..h {
         for x in 0..w {
             let idx = y * w + x;
-            let h_here = vals[idx] as f32;
+            let h_here = vals[idx];
             if h_here <= sea_level_norm {
                 continue; // море — сток не считаем
             }
@@ -326,7 +699,7 @@ pub fn compute_flow_accumulation(hm: &Heightmap, sea_level_norm: f32) -> Vec<f32
                         continue;
                     }
                     let nidx = ny as usize * w + nx as usize;
-                    let h_nei = vals[nidx] as f32;
+                    let h_nei = vals[nidx];
                     let diff = h_here - h_nei;
                     if diff > best_diff {
                         best_diff = diff;
@@ -341,6 +714,20 @@ pub fn compute_flow_accumulation(hm: &Heightmap, sea_level_norm: f32) -> Vec<f32
         }
     }
 
+    downslope
+}
+
+/// D8-сток: для каждой клетки считаем, сколько "воды" через неё проходит.
+/// Возвращает вектор длиной width*height, значения нормированы в [0..1].
+pub fn compute_flow_accumulation(hm: &Heightmap, sea_level_norm: f32) -> Vec<f32> {
+    let len = hm.width as usize * hm.height as usize;
+    if len == 0 {
+        return Vec::new();
+    }
+
+    let vals = &hm.values;
+    let downslope = flow_directions(hm, sea_level_norm);
+
     // Порядок обхода: сверху вниз по высоте
     let mut order: Vec<usize> = (0..len).collect();
     order.sort_unstable_by(|&a, &b| {
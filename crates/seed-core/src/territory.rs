@@ -0,0 +1,349 @@
+use crate::history::Faction;
+use crate::settlements::City;
+use crate::terrain::Heightmap;
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Растр владения: для каждой клетки индекс фракции в `History::factions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerritoryMap {
+    pub width: u32,
+    pub height: u32,
+    pub owner: Vec<Option<u16>>,
+    /// Для каждой клетки — индекс города, к которому она "тянется"
+    pub city: Vec<Option<u32>>,
+}
+
+impl TerritoryMap {
+    pub fn empty(width: u32, height: u32) -> Self {
+        let len = (width * height) as usize;
+        Self {
+            width,
+            height,
+            owner: vec![None; len],
+            city: vec![None; len],
+        }
+    }
+
+    #[inline]
+    pub fn owner_at(&self, x: u32, y: u32) -> Option<u16> {
+        self.owner[(y * self.width + x) as usize]
+    }
+
+    /// Число клеток каждой фракции
+    pub fn area_by_faction(&self, faction_count: usize) -> Vec<usize> {
+        let mut area = vec![0usize; faction_count];
+        for o in self.owner.iter().flatten() {
+            if let Some(a) = area.get_mut(*o as usize) {
+                *a += 1;
+            }
+        }
+        area
+    }
+}
+
+/// Граница между двумя фракциями (None — ничья земля) в координатах углов клеток
+#[derive(Debug, Clone)]
+pub struct BorderLine {
+    pub faction_a: Option<u16>,
+    pub faction_b: Option<u16>,
+    pub points: Vec<(f32, f32)>,
+}
+
+/// Отрезок между двумя углами клеток
+pub(crate) type LatticeSegment = ((i32, i32), (i32, i32));
+
+/// Стоимость шага по клетке: склоны и высокогорья дороже, море почти непроходимо.
+pub fn terrain_step_cost(cfg: &WorldConfig, hm: &Heightmap, x: u32, y: u32) -> f32 {
+    let sea_level = cfg.sea_level as f32;
+    let h = hm.get(x, y);
+    if h <= sea_level {
+        return f32::INFINITY;
+    }
+    let xl = x.saturating_sub(1);
+    let xr = (x + 1).min(hm.width - 1);
+    let yu = y.saturating_sub(1);
+    let yd = (y + 1).min(hm.height - 1);
+    let dx = hm.get(xr, y) - hm.get(xl, y);
+    let dy = hm.get(x, yd) - hm.get(x, yu);
+    let slope = (dx * dx + dy * dy).sqrt() * 0.5;
+
+    let rel = ((h - sea_level) / (1.0 - sea_level).max(1e-6)).clamp(0.0, 1.0);
+    // горы выше ~60% относительной высоты — естественный барьер
+    let mountain = ((rel - 0.6) / 0.4).max(0.0) * 8.0;
+
+    1.0 + slope * 60.0 + mountain
+}
+
+#[derive(Copy, Clone)]
+struct Node {
+    cost: f32,
+    idx: usize,
+    city: u32,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+impl Eq for Node {}
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // min-heap по стоимости
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Растит территории от городов взвешенным по рельефу заливом (Дейкстра от всех городов
+/// сразу). Крупные города "дотягиваются" дальше; `max_cost` — предел влияния
+/// города с населением ~10 тыс.
+pub fn grow_territories(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    cities: &[City],
+    factions: &[Faction],
+    max_cost: f32,
+) -> TerritoryMap {
+    let w = hm.width as usize;
+    let h = hm.height as usize;
+    let mut map = TerritoryMap::empty(hm.width, hm.height);
+    if w == 0 || h == 0 {
+        return map;
+    }
+
+    let faction_index: HashMap<&str, u16> = factions
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.id.as_str(), i as u16))
+        .collect();
+
+    let step_cost: Vec<f32> = (0..w * h)
+        .map(|i| terrain_step_cost(cfg, hm, (i % w) as u32, (i / w) as u32))
+        .collect();
+
+    // "Дальнобойность" города растёт с населением медленнее линейного
+    let reach: Vec<f32> = cities
+        .iter()
+        .map(|c| ((c.population.max(1) as f32 / 10_000.0).powf(0.3)).clamp(0.3, 4.0))
+        .collect();
+
+    let mut best = vec![f32::INFINITY; w * h];
+    let mut heap = BinaryHeap::new();
+
+    for (ci, c) in cities.iter().enumerate() {
+        if !faction_index.contains_key(c.faction_id.as_str()) {
+            continue;
+        }
+        let idx = c.y as usize * w + c.x as usize;
+        if idx < best.len() {
+            best[idx] = 0.0;
+            heap.push(Node {
+                cost: 0.0,
+                idx,
+                city: ci as u32,
+            });
+        }
+    }
+
+    while let Some(Node { cost, idx, city }) = heap.pop() {
+        if cost > best[idx] || map.city[idx].is_some() {
+            continue;
+        }
+        map.city[idx] = Some(city);
+        map.owner[idx] = faction_index
+            .get(cities[city as usize].faction_id.as_str())
+            .copied();
+
+        let x = idx % w;
+        let y = idx / w;
+        let r = reach[city as usize];
+
+        for (dx, dy) in [(-1i32, 0i32), (1, 0), (0, -1), (0, 1)] {
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx < 0 || ny < 0 || nx >= w as i32 || ny >= h as i32 {
+                continue;
+            }
+            let nidx = ny as usize * w + nx as usize;
+            let sc = step_cost[nidx];
+            if !sc.is_finite() {
+                continue;
+            }
+            let next = cost + sc / r;
+            if next > max_cost || next >= best[nidx] {
+                continue;
+            }
+            best[nidx] = next;
+            heap.push(Node {
+                cost: next,
+                idx: nidx,
+                city,
+            });
+        }
+    }
+
+    map
+}
+
+/// Вытягивает границы между фракциями в полилинии и упрощает их
+/// (Douglas–Peucker с допуском `epsilon` клеток).
+pub fn extract_borders(map: &TerritoryMap, epsilon: f32) -> Vec<BorderLine> {
+    let w = map.width as i32;
+    let h = map.height as i32;
+    type Key = (Option<u16>, Option<u16>);
+    let mut segments: HashMap<Key, Vec<LatticeSegment>> = HashMap::new();
+
+    let owner = |x: i32, y: i32| -> Option<u16> {
+        if x < 0 || y < 0 || x >= w || y >= h {
+            None
+        } else {
+            map.owner[(y * w + x) as usize]
+        }
+    };
+    let key = |a: Option<u16>, b: Option<u16>| -> Key {
+        if a <= b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    };
+
+    for y in 0..h {
+        for x in 0..w {
+            let here = owner(x, y);
+            // правый сосед → вертикальный отрезок
+            let right = owner(x + 1, y);
+            if x + 1 < w && here != right {
+                segments
+                    .entry(key(here, right))
+                    .or_default()
+                    .push(((x + 1, y), (x + 1, y + 1)));
+            }
+            // нижний сосед → горизонтальный отрезок
+            let down = owner(x, y + 1);
+            if y + 1 < h && here != down {
+                segments
+                    .entry(key(here, down))
+                    .or_default()
+                    .push(((x, y + 1), (x + 1, y + 1)));
+            }
+        }
+    }
+
+    let mut keys: Vec<Key> = segments.keys().copied().collect();
+    keys.sort();
+
+    let mut lines = Vec::new();
+    for k in keys {
+        for chain in chain_segments(&segments[&k]) {
+            let pts: Vec<(f32, f32)> = chain.iter().map(|&(x, y)| (x as f32, y as f32)).collect();
+            lines.push(BorderLine {
+                faction_a: k.0,
+                faction_b: k.1,
+                points: simplify_polyline(&pts, epsilon),
+            });
+        }
+    }
+    lines
+}
+
+/// Склеивает отрезки решётки в непрерывные цепочки
+pub(crate) fn chain_segments(segs: &[LatticeSegment]) -> Vec<Vec<(i32, i32)>> {
+    let mut adjacency: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (i, (a, b)) in segs.iter().enumerate() {
+        adjacency.entry(*a).or_default().push(i);
+        adjacency.entry(*b).or_default().push(i);
+    }
+    let mut used = vec![false; segs.len()];
+    let mut chains = Vec::new();
+
+    // сначала начинаем с концов (степень != 2), потом добираем циклы
+    let mut starts: Vec<(i32, i32)> = adjacency
+        .iter()
+        .filter(|(_, v)| v.len() != 2)
+        .map(|(p, _)| *p)
+        .collect();
+    starts.sort();
+    let mut all: Vec<(i32, i32)> = adjacency.keys().copied().collect();
+    all.sort();
+    starts.extend(all);
+
+    for start in starts {
+        while let Some(&first) = adjacency[&start].iter().find(|&&s| !used[s]) {
+            let mut chain = vec![start];
+            let mut current = start;
+            let mut seg = first;
+            loop {
+                used[seg] = true;
+                let (a, b) = segs[seg];
+                let next = if a == current { b } else { a };
+                chain.push(next);
+                current = next;
+                match adjacency[&current].iter().find(|&&s| !used[s]) {
+                    Some(&s) => seg = s,
+                    None => break,
+                }
+            }
+            chains.push(chain);
+        }
+    }
+    chains
+}
+
+/// Упрощение полилинии Douglas–Peucker
+pub fn simplify_polyline(points: &[(f32, f32)], epsilon: f32) -> Vec<(f32, f32)> {
+    if points.len() < 3 || epsilon <= 0.0 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0usize, points.len() - 1)];
+
+    while let Some((s, e)) = stack.pop() {
+        if e <= s + 1 {
+            continue;
+        }
+        let (ax, ay) = points[s];
+        let (bx, by) = points[e];
+        let (vx, vy) = (bx - ax, by - ay);
+        let len = (vx * vx + vy * vy).sqrt();
+
+        let mut max_d = 0.0;
+        let mut max_i = s;
+        for (i, &(px, py)) in points.iter().enumerate().take(e).skip(s + 1) {
+            let d = if len < 1e-6 {
+                ((px - ax).powi(2) + (py - ay).powi(2)).sqrt()
+            } else {
+                ((px - ax) * vy - (py - ay) * vx).abs() / len
+            };
+            if d > max_d {
+                max_d = d;
+                max_i = i;
+            }
+        }
+        if max_d > epsilon {
+            keep[max_i] = true;
+            stack.push((s, max_i));
+            stack.push((max_i, e));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, k)| *k)
+        .map(|(p, _)| *p)
+        .collect()
+}
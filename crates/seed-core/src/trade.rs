@@ -0,0 +1,394 @@
+use crate::biome::BiomeMap;
+use crate::settlements::City;
+use crate::terrain::{compute_flow_accumulation, Heightmap};
+use crate::territory::terrain_step_cost;
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+/// Способ перевозки на участке маршрута
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RouteKind {
+    Road,
+    SeaLane,
+    River,
+}
+
+/// Непрерывный участок маршрута одного типа
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLeg {
+    pub kind: RouteKind,
+    pub points: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeRoute {
+    /// Индексы городов в `History::cities`
+    pub from_city: usize,
+    pub to_city: usize,
+    pub legs: Vec<RouteLeg>,
+    /// Суммарная стоимость пути (условные "дни пути")
+    pub cost: f32,
+    /// Товары, которыми обмениваются концы маршрута
+    pub goods: Vec<String>,
+    /// Условный объём торговли
+    pub volume: f64,
+}
+
+impl TradeRoute {
+    /// Преобладающий способ перевозки (по числу клеток)
+    pub fn dominant_kind(&self) -> RouteKind {
+        let mut counts = [0usize; 3];
+        for leg in &self.legs {
+            let i = match leg.kind {
+                RouteKind::Road => 0,
+                RouteKind::SeaLane => 1,
+                RouteKind::River => 2,
+            };
+            counts[i] += leg.points.len();
+        }
+        match counts.iter().enumerate().max_by_key(|(_, c)| **c) {
+            Some((1, _)) => RouteKind::SeaLane,
+            Some((2, _)) => RouteKind::River,
+            _ => RouteKind::Road,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeNetwork {
+    pub routes: Vec<TradeRoute>,
+}
+
+impl TradeNetwork {
+    /// Растровый слой маршрутов (для карт): тип перевозки в каждой клетке
+    pub fn rasterize(&self, width: u32, height: u32) -> Vec<Option<RouteKind>> {
+        let mut layer = vec![None; (width * height) as usize];
+        for route in &self.routes {
+            for leg in &route.legs {
+                for &(x, y) in &leg.points {
+                    if x < width && y < height {
+                        layer[(y * width + x) as usize] = Some(leg.kind);
+                    }
+                }
+            }
+        }
+        layer
+    }
+
+    /// Суммарный объём торговли каждого города (индексы как в `cities`)
+    pub fn volume_by_city(&self, city_count: usize) -> Vec<f64> {
+        let mut v = vec![0.0; city_count];
+        for r in &self.routes {
+            if r.from_city < city_count {
+                v[r.from_city] += r.volume;
+            }
+            if r.to_city < city_count {
+                v[r.to_city] += r.volume;
+            }
+        }
+        v
+    }
+
+    /// Объём торговли между парами фракций; ключ — упорядоченная пара id
+    pub fn volume_between_factions(&self, cities: &[City]) -> BTreeMap<(String, String), f64> {
+        let mut out = BTreeMap::new();
+        for r in &self.routes {
+            let (Some(a), Some(b)) = (cities.get(r.from_city), cities.get(r.to_city)) else {
+                continue;
+            };
+            let (fa, fb) = (&a.faction_id, &b.faction_id);
+            if fa != fb {
+                let key = (fa.min(fb).clone(), fa.max(fb).clone());
+                *out.entry(key).or_insert(0.0) += r.volume;
+            }
+        }
+        out
+    }
+}
+
+/// Доля максимального стока, начиная с которой река судоходна
+const NAVIGABLE_FLOW: f32 = 0.05;
+/// Стоимость клетки морского пути и речного сплава
+const SEA_COST: f32 = 0.6;
+const RIVER_COST: f32 = 0.5;
+/// Штраф за погрузку/разгрузку в порту при смене суши и моря
+const PORT_PENALTY: f32 = 8.0;
+
+/// Слой стоимости перемещения и типа перевозки по клеткам
+pub(crate) struct CostSurface {
+    pub w: usize,
+    pub h: usize,
+    cost: Vec<f32>,
+    pub kind: Vec<RouteKind>,
+}
+
+impl CostSurface {
+    pub fn new(cfg: &WorldConfig, hm: &Heightmap) -> Self {
+        let w = hm.width as usize;
+        let h = hm.height as usize;
+        let sea_level = cfg.sea_level as f32;
+        let flow = compute_flow_accumulation(hm, sea_level);
+
+        let mut cost = Vec::with_capacity(w * h);
+        let mut kind = Vec::with_capacity(w * h);
+        for (i, (&hv, &fv)) in hm.values.iter().zip(&flow).enumerate() {
+            let (x, y) = ((i % w) as u32, (i / w) as u32);
+            if hv <= sea_level {
+                cost.push(SEA_COST);
+                kind.push(RouteKind::SeaLane);
+            } else if fv >= NAVIGABLE_FLOW {
+                cost.push(RIVER_COST);
+                kind.push(RouteKind::River);
+            } else {
+                cost.push(terrain_step_cost(cfg, hm, x, y));
+                kind.push(RouteKind::Road);
+            }
+        }
+        Self { w, h, cost, kind }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Open {
+    f: f32,
+    idx: usize,
+}
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.f == other.f
+    }
+}
+impl Eq for Open {}
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.partial_cmp(&self.f).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// A* по 8-связной сетке между клетками `start` и `goal`
+pub(crate) fn least_cost_path(
+    surface: &CostSurface,
+    start: usize,
+    goal: usize,
+) -> Option<(Vec<usize>, f32)> {
+    let (w, h) = (surface.w, surface.h);
+    let min_cost = RIVER_COST.min(SEA_COST);
+    let heuristic = |i: usize| {
+        let dx = (i % w) as f32 - (goal % w) as f32;
+        let dy = (i / w) as f32 - (goal / w) as f32;
+        (dx * dx + dy * dy).sqrt() * min_cost
+    };
+
+    let mut g = vec![f32::INFINITY; w * h];
+    let mut came_from = vec![usize::MAX; w * h];
+    let mut heap = BinaryHeap::new();
+    g[start] = 0.0;
+    heap.push(Open {
+        f: heuristic(start),
+        idx: start,
+    });
+
+    while let Some(Open { f, idx }) = heap.pop() {
+        if idx == goal {
+            let mut path = vec![goal];
+            let mut cur = goal;
+            while cur != start {
+                cur = came_from[cur];
+                path.push(cur);
+            }
+            path.reverse();
+            return Some((path, g[goal]));
+        }
+        if f > g[idx] + heuristic(idx) + 1e-4 {
+            continue;
+        }
+        let x = (idx % w) as i32;
+        let y = (idx / w) as i32;
+        for dy in -1i32..=1 {
+            for dx in -1i32..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let nx = x + dx;
+                let ny = y + dy;
+                if nx < 0 || ny < 0 || nx >= w as i32 || ny >= h as i32 {
+                    continue;
+                }
+                let n = ny as usize * w + nx as usize;
+                let step = if dx != 0 && dy != 0 {
+                    std::f32::consts::SQRT_2
+                } else {
+                    1.0
+                };
+                let mut c = surface.cost[n] * step;
+                if (surface.kind[n] == RouteKind::SeaLane)
+                    != (surface.kind[idx] == RouteKind::SeaLane)
+                {
+                    c += PORT_PENALTY;
+                }
+                let tentative = g[idx] + c;
+                if tentative < g[n] {
+                    g[n] = tentative;
+                    came_from[n] = idx;
+                    heap.push(Open {
+                        f: tentative + heuristic(n),
+                        idx: n,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Товары, которые производит окрестность города (по биомам и близости моря)
+pub fn local_goods(cfg: &WorldConfig, hm: &Heightmap, bm: &BiomeMap, city: &City) -> Vec<String> {
+    let mut goods = BTreeSet::new();
+    let sea_level = cfg.sea_level as f32;
+    let r = 4i32;
+
+    for dy in -r..=r {
+        for dx in -r..=r {
+            let x = city.x as i32 + dx;
+            let y = city.y as i32 + dy;
+            if x < 0 || y < 0 || x >= hm.width as i32 || y >= hm.height as i32 {
+                continue;
+            }
+            let (x, y) = (x as u32, y as u32);
+            if hm.get(x, y) <= sea_level {
+                goods.insert("fish");
+                continue;
+            }
+            let Some(biome) = bm.get_index(x, y).and_then(|bi| cfg.biomes.get(bi)) else {
+                continue;
+            };
+            let id = biome.id.as_str();
+            if id.contains("forest") || id.contains("taiga") {
+                goods.insert("timber");
+                goods.insert("furs");
+            } else if id.contains("desert") {
+                goods.insert("salt");
+                goods.insert("spices");
+            } else if id.contains("mountain") {
+                goods.insert("ore");
+                goods.insert("stone");
+            } else if id.contains("tundra") || id.contains("ice") {
+                goods.insert("furs");
+            } else if id.contains("grass") || id.contains("savanna") || id.contains("steppe") {
+                goods.insert("grain");
+                goods.insert("livestock");
+            } else {
+                goods.insert("grain");
+            }
+        }
+    }
+
+    goods.into_iter().map(String::from).collect()
+}
+
+/// Строит торговую сеть: каждый город связывается с `links_per_city` ближайшими
+/// соседями кратчайшим по стоимости путём (дороги, реки, морские пути).
+pub fn generate_trade_network(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    cities: &[City],
+    links_per_city: usize,
+) -> TradeNetwork {
+    if cities.len() < 2 || hm.width == 0 || hm.height == 0 {
+        return TradeNetwork::default();
+    }
+    let surface = CostSurface::new(cfg, hm);
+    build_trade_network(cfg, hm, bm, &surface, cities, links_per_city)
+}
+
+/// То же, что `generate_trade_network`, но на готовом слое стоимости
+pub(crate) fn build_trade_network(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    bm: &BiomeMap,
+    surface: &CostSurface,
+    cities: &[City],
+    links_per_city: usize,
+) -> TradeNetwork {
+    let mut network = TradeNetwork::default();
+    if cities.len() < 2 {
+        return network;
+    }
+
+    let w = surface.w;
+    let max_link = hm.width.max(hm.height) as f64 / 3.0;
+    let importance = cfg.civilizations.history_simulation.trade_importance as f64;
+
+    let goods: Vec<Vec<String>> = cities.iter().map(|c| local_goods(cfg, hm, bm, c)).collect();
+
+    // Пары городов: k ближайших соседей, без повторов
+    let mut pairs = BTreeSet::new();
+    for (i, a) in cities.iter().enumerate() {
+        let mut near: Vec<(f64, usize)> = cities
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(j, b)| {
+                let dx = a.x as f64 - b.x as f64;
+                let dy = a.y as f64 - b.y as f64;
+                ((dx * dx + dy * dy).sqrt(), j)
+            })
+            .filter(|(d, _)| *d <= max_link)
+            .collect();
+        near.sort_by(|p, q| p.partial_cmp(q).unwrap_or(Ordering::Equal));
+        for &(_, j) in near.iter().take(links_per_city) {
+            pairs.insert((i.min(j), i.max(j)));
+        }
+    }
+
+    for (i, j) in pairs {
+        let (a, b) = (&cities[i], &cities[j]);
+        let start = a.y as usize * w + a.x as usize;
+        let goal = b.y as usize * w + b.x as usize;
+        let Some((path, cost)) = least_cost_path(surface, start, goal) else {
+            continue;
+        };
+
+        // Разбиваем путь на участки одного типа
+        let mut legs: Vec<RouteLeg> = Vec::new();
+        for idx in path {
+            let kind = surface.kind[idx];
+            let p = ((idx % w) as u32, (idx / w) as u32);
+            match legs.last_mut() {
+                Some(leg) if leg.kind == kind => leg.points.push(p),
+                _ => legs.push(RouteLeg {
+                    kind,
+                    points: vec![p],
+                }),
+            }
+        }
+
+        // Обмениваются тем, чего нет у партнёра
+        let mut traded: BTreeSet<String> = BTreeSet::new();
+        traded.extend(goods[i].iter().filter(|g| !goods[j].contains(g)).cloned());
+        traded.extend(goods[j].iter().filter(|g| !goods[i].contains(g)).cloned());
+
+        let volume = (a.population as f64 * b.population as f64).sqrt() / (1.0 + cost as f64)
+            * importance
+            * (1.0 + traded.len() as f64 * 0.25);
+
+        network.routes.push(TradeRoute {
+            from_city: i,
+            to_city: j,
+            legs,
+            cost,
+            goods: traded.into_iter().collect(),
+            volume,
+        });
+    }
+
+    network
+}
@@ -0,0 +1,554 @@
+use crate::biome::prevailing_wind;
+use crate::catastrophe::{Catastrophe, CatastropheType};
+use crate::geo::MapGeo;
+use crate::rng::SplitMix64;
+use crate::seed_tree::SeedTree;
+use crate::terrain::{d8_downslope, Heightmap};
+use seed_config::WorldConfig;
+
+/// Шаг `VolcanoSimulator::advance_to`, игровые годы
+const ADVANCE_STEP_YEARS: f64 = 0.05;
+/// В скольких клетках от места извержения по расписанию ищется вулкан
+const TRIGGER_REACH_CELLS: f64 = 8.0;
+
+/// Фаза жизненного цикла вулкана
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolcanoPhase {
+    /// Копит давление магмы между извержениями
+    Dormant,
+    /// Идёт извержение
+    Erupting,
+    /// Исчерпал себя, больше не извергается
+    Extinct,
+}
+
+#[derive(Debug, Clone)]
+pub struct Volcano {
+    pub id: String,
+    /// Жерло в координатах heightmap
+    pub x: u32,
+    pub y: u32,
+    pub phase: VolcanoPhase,
+    /// Среднее время покоя между извержениями, лет
+    pub repose_years: f64,
+    /// Накопленное давление магмы: извержение начинается при 1.0
+    pub magma_pressure: f64,
+    pub eruption_count: u32,
+    /// После стольких извержений вулкан потухает
+    pub max_eruptions: u32,
+    /// Суммарный прирост постройки за все извержения (в единицах нормированной высоты)
+    pub edifice_height: f32,
+    pub last_eruption_year: Option<f64>,
+    /// Сколько лет ещё продлится текущее извержение
+    eruption_years_left: f64,
+    /// VEI текущего (или последнего) извержения
+    current_vei: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolcanicEventKind {
+    EruptionStarted,
+    EruptionEnded,
+    WentExtinct,
+}
+
+/// Запись в хронологии вулканической активности
+#[derive(Debug, Clone)]
+pub struct VolcanicEvent {
+    pub volcano_id: String,
+    pub year: f64,
+    pub kind: VolcanicEventKind,
+    pub vei: f64,
+}
+
+/// Что сделало с рельефом одно извержение
+#[derive(Debug, Clone, Default)]
+pub struct EruptionFootprint {
+    pub edifice_growth: f32,
+    pub lava_cells: usize,
+    pub ash_cells: usize,
+}
+
+/// Слои вулканических отложений (та же сетка, что heightmap)
+#[derive(Debug, Clone)]
+pub struct VolcanicDeposits {
+    pub width: u32,
+    pub height: u32,
+    /// Толщина застывшей лавы (нормированная высота)
+    pub lava: Vec<f32>,
+    /// Толщина пепла (нормированная высота)
+    pub ash: Vec<f32>,
+}
+
+impl VolcanicDeposits {
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = (width * height) as usize;
+        Self {
+            width,
+            height,
+            lava: vec![0.0; len],
+            ash: vec![0.0; len],
+        }
+    }
+}
+
+/// Симулятор вулканов на временной шкале мира
+#[derive(Debug, Clone)]
+pub struct VolcanoSimulator {
+    pub volcanoes: Vec<Volcano>,
+    pub deposits: VolcanicDeposits,
+    pub events: Vec<VolcanicEvent>,
+    pub year: f64,
+    rng: SplitMix64,
+    cfg: WorldConfig,
+}
+
+impl VolcanoSimulator {
+    /// Расставляет вулканы по карте; их число зависит от частоты
+    /// `volcanic_eruption` в конфиге катастроф.
+    pub fn new(cfg: &WorldConfig, hm: &Heightmap, seed: u64) -> Self {
+        let mut rng = SeedTree::new(seed).derive("volcanoes").rng();
+
+        let frequency = cfg
+            .catastrophes
+            .event_types
+            .iter()
+            .find(|e| e.id == "volcanic_eruption")
+            .map(|e| e.base_frequency_per_year)
+            .unwrap_or(0.0);
+
+        let count = if cfg.catastrophes.global_controls.enabled {
+            ((frequency * 2.0).round() as usize).clamp(0, 24)
+        } else {
+            0
+        };
+
+        let volcanoes = place_volcanoes(cfg, hm, count, &mut rng);
+
+        Self {
+            volcanoes,
+            deposits: VolcanicDeposits::new(hm.width, hm.height),
+            events: Vec::new(),
+            year: 0.0,
+            rng,
+            cfg: cfg.clone(),
+        }
+    }
+
+    /// Продвигает симуляцию на `dt_years`, изменяя рельеф
+    pub fn step(&mut self, hm: &mut Heightmap, dt_years: f64) {
+        if dt_years <= 0.0 {
+            return;
+        }
+        self.year += dt_years;
+
+        for vi in 0..self.volcanoes.len() {
+            let v = &mut self.volcanoes[vi];
+            match v.phase {
+                VolcanoPhase::Extinct => {}
+                VolcanoPhase::Dormant => {
+                    // давление растёт неравномерно: то быстрее, то медленнее
+                    let jitter = 0.5 + self.rng.next_f64();
+                    v.magma_pressure += dt_years / v.repose_years * jitter;
+
+                    if v.magma_pressure >= 1.0 {
+                        // чем дольше копилось давление, тем сильнее извержение
+                        let dormancy = v
+                            .last_eruption_year
+                            .map(|ly| self.year - ly)
+                            .unwrap_or(v.repose_years);
+                        let base = (dormancy / v.repose_years).sqrt().clamp(0.5, 2.0);
+                        let vei = (base * self.rng.range_f64(1.5, 4.5)).clamp(0.0, 8.0);
+                        let seed = self.rng.next_u64();
+                        self.start_eruption(hm, vi, vei, seed);
+                    }
+                }
+                VolcanoPhase::Erupting => {
+                    v.eruption_years_left -= dt_years;
+                    if v.eruption_years_left <= 0.0 {
+                        v.phase = VolcanoPhase::Dormant;
+                        self.events.push(VolcanicEvent {
+                            volcano_id: v.id.clone(),
+                            year: self.year,
+                            kind: VolcanicEventKind::EruptionEnded,
+                            vei: v.current_vei,
+                        });
+
+                        if v.eruption_count >= v.max_eruptions {
+                            v.phase = VolcanoPhase::Extinct;
+                            self.events.push(VolcanicEvent {
+                                volcano_id: v.id.clone(),
+                                year: self.year,
+                                kind: VolcanicEventKind::WentExtinct,
+                                vei: 0.0,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Ведёт симуляцию до года `year`; начатые за это время извержения —
+    /// событиями `volcanic_eruption` в жерле вулкана, с радиусом и
+    /// длительностью как у `Catastrophe::at`
+    pub fn advance_to(&mut self, hm: &mut Heightmap, year: f64) -> Vec<Catastrophe> {
+        let seen = self.events.len();
+        self.run(hm, year - self.year, ADVANCE_STEP_YEARS);
+        let geo = MapGeo::from_config(&self.cfg, hm.width, hm.height);
+        self.events[seen..]
+            .iter()
+            .filter(|e| e.kind == VolcanicEventKind::EruptionStarted)
+            .filter_map(|e| {
+                let v = self.volcanoes.iter().find(|v| v.id == e.volcano_id)?;
+                let p = geo.cell_latlon(v.x, v.y);
+                let mut cat = Catastrophe::at(
+                    &self.cfg,
+                    CatastropheType::VolcanicEruption,
+                    (p.lat_deg, p.lon_deg),
+                    e.vei,
+                );
+                cat.id = format!("{}_eruption_{:.3}", v.id, e.year);
+                cat.timestamp = e.year;
+                Some(cat)
+            })
+            .collect()
+    }
+
+    /// Извержение по внешнему расписанию (катастрофа `volcanic_eruption`)
+    /// силы `vei` в клетке (`x`, `y`): извергается ближайший непотухший
+    /// вулкан в `TRIGGER_REACH_CELLS` клетках, а без него на месте события
+    /// открывается одноразовый конус, который потухнет после этого
+    /// извержения. Фазы, счёт извержений и отложения — как у извержений
+    /// самого симулятора
+    pub fn trigger(
+        &mut self,
+        hm: &mut Heightmap,
+        x: u32,
+        y: u32,
+        vei: f64,
+        seed: u64,
+    ) -> EruptionFootprint {
+        let near = self
+            .volcanoes
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.phase != VolcanoPhase::Extinct)
+            .map(|(i, v)| (i, (v.x as f64 - x as f64).hypot(v.y as f64 - y as f64)))
+            .filter(|&(_, d)| d <= TRIGGER_REACH_CELLS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+        let vi = match near {
+            Some(vi) => vi,
+            None => {
+                let id = format!("volcano_{}", self.volcanoes.len());
+                let mut volcano = new_volcano(id, x, y, &mut self.rng);
+                volcano.max_eruptions = 1;
+                self.volcanoes.push(volcano);
+                self.volcanoes.len() - 1
+            }
+        };
+        self.start_eruption(hm, vi, vei.clamp(0.0, 8.0), seed)
+    }
+
+    /// Начинает извержение вулкана `vi`: фаза, событие и само извержение
+    fn start_eruption(
+        &mut self,
+        hm: &mut Heightmap,
+        vi: usize,
+        vei: f64,
+        seed: u64,
+    ) -> EruptionFootprint {
+        let v = &mut self.volcanoes[vi];
+        v.phase = VolcanoPhase::Erupting;
+        v.current_vei = vei;
+        v.eruption_years_left = 0.05 + vei * 0.1;
+        v.magma_pressure = 0.0;
+        v.eruption_count += 1;
+        v.last_eruption_year = Some(self.year);
+
+        self.events.push(VolcanicEvent {
+            volcano_id: v.id.clone(),
+            year: self.year,
+            kind: VolcanicEventKind::EruptionStarted,
+            vei,
+        });
+
+        let (x, y) = (v.x, v.y);
+        let footprint = erupt(&self.cfg, hm, &mut self.deposits, x, y, vei, seed);
+        self.volcanoes[vi].edifice_height += footprint.edifice_growth;
+        footprint
+    }
+
+    /// Прогоняет `years` лет с шагом `dt_years`
+    pub fn run(&mut self, hm: &mut Heightmap, years: f64, dt_years: f64) {
+        let dt = dt_years.max(1e-3);
+        let mut t = 0.0;
+        while t < years {
+            let step = dt.min(years - t);
+            self.step(hm, step);
+            t += step;
+        }
+    }
+}
+
+/// Выбирает места для вулканов: возвышенности суши, не слишком близко друг к другу
+fn place_volcanoes(
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    count: usize,
+    rng: &mut SplitMix64,
+) -> Vec<Volcano> {
+    let mut volcanoes = Vec::with_capacity(count);
+    if count == 0 || hm.width < 3 || hm.height < 3 {
+        return volcanoes;
+    }
+
+    let sea_level = cfg.sea_level as f32;
+    let min_spacing = (hm.width.min(hm.height) as f64 / 12.0).max(2.0);
+
+    // Несколько попыток на каждый вулкан, берём самую высокую точку суши
+    for i in 0..count {
+        let mut best: Option<(u32, u32, f32)> = None;
+
+        for _ in 0..32 {
+            let x = 1 + rng.below(hm.width as usize - 2) as u32;
+            let y = 1 + rng.below(hm.height as usize - 2) as u32;
+            let h = hm.get(x, y);
+            if h <= sea_level + 0.02 {
+                continue;
+            }
+            let too_close = volcanoes.iter().any(|v: &Volcano| {
+                let dx = v.x as f64 - x as f64;
+                let dy = v.y as f64 - y as f64;
+                (dx * dx + dy * dy).sqrt() < min_spacing
+            });
+            if too_close {
+                continue;
+            }
+            if best.map(|(_, _, bh)| h > bh).unwrap_or(true) {
+                best = Some((x, y, h));
+            }
+        }
+
+        if let Some((x, y, _)) = best {
+            volcanoes.push(new_volcano(format!("volcano_{i}"), x, y, rng));
+        }
+    }
+
+    volcanoes
+}
+
+/// Спящий вулкан со случайными периодом покоя и запасом извержений
+fn new_volcano(id: String, x: u32, y: u32, rng: &mut SplitMix64) -> Volcano {
+    Volcano {
+        id,
+        x,
+        y,
+        phase: VolcanoPhase::Dormant,
+        repose_years: rng.range_f64(20.0, 200.0),
+        magma_pressure: rng.next_f64(),
+        eruption_count: 0,
+        max_eruptions: 3 + rng.below(10) as u32,
+        edifice_height: 0.0,
+        last_eruption_year: None,
+        eruption_years_left: 0.0,
+        current_vei: 0.0,
+    }
+}
+
+/// Одно извержение в точке (x, y): рост постройки, лавовые потоки
+/// вниз по склону и пепел по ветру. `seed` задаёт разброс лавовых потоков.
+pub fn erupt(
+    cfg: &WorldConfig,
+    hm: &mut Heightmap,
+    deposits: &mut VolcanicDeposits,
+    x: u32,
+    y: u32,
+    vei: f64,
+    seed: u64,
+) -> EruptionFootprint {
+    let mut rng = SplitMix64::new(seed);
+    let rng = &mut rng;
+    let vei = vei.clamp(0.0, 10.0);
+    let mut footprint = EruptionFootprint::default();
+
+    // 1) Постройка: каждое извержение надстраивает конус
+    let radius = 2.0 + vei * 1.5;
+    let growth = (0.004 + 0.003 * vei) as f32;
+    footprint.edifice_growth = grow_edifice(hm, x, y, radius, growth);
+
+    // 2) Лава: несколько потоков по D8-пути вниз
+    let flows = 1 + (vei / 2.0) as usize;
+    let max_len = (10.0 + vei * 12.0) as usize;
+    let thickness = (0.002 + 0.0015 * vei) as f32;
+    let sea_level = cfg.sea_level as f32;
+    for _ in 0..flows {
+        footprint.lava_cells += lava_flow(hm, deposits, x, y, max_len, thickness, sea_level, rng);
+    }
+
+    // 3) Пепел: вытянутый шлейф по преобладающему ветру
+    let lat_deg = (y as f64 / hm.height.max(1) as f64) * 180.0 - 90.0;
+    let wind = prevailing_wind(cfg, lat_deg);
+    footprint.ash_cells = deposit_ash(hm, deposits, x, y, vei, wind);
+
+    footprint
+}
+
+/// Наращивает конус вокруг жерла, возвращает фактический прирост в центре
+fn grow_edifice(hm: &mut Heightmap, cx: u32, cy: u32, radius: f64, growth: f32) -> f32 {
+    let r = radius.ceil() as i32;
+    let before = hm.get(cx, cy);
+
+    for dy in -r..=r {
+        for dx in -r..=r {
+            let x = cx as i32 + dx;
+            let y = cy as i32 + dy;
+            if x < 0 || y < 0 || x >= hm.width as i32 || y >= hm.height as i32 {
+                continue;
+            }
+            let dist = ((dx * dx + dy * dy) as f64).sqrt();
+            if dist > radius {
+                continue;
+            }
+            let profile = (1.0 - (dist / radius).powf(1.5)) as f32;
+            let idx = hm.index(x as u32, y as u32);
+            hm.values[idx] = (hm.values[idx] + growth * profile).min(1.0);
+        }
+    }
+
+    hm.get(cx, cy) - before
+}
+
+/// Лавовый поток: идёт по самому крутому спуску, иногда уклоняясь в сторону,
+/// и застывает слоем убывающей толщины. Возвращает число затронутых клеток.
+#[allow(clippy::too_many_arguments)]
+fn lava_flow(
+    hm: &mut Heightmap,
+    deposits: &mut VolcanicDeposits,
+    start_x: u32,
+    start_y: u32,
+    max_len: usize,
+    thickness: f32,
+    sea_level: f32,
+    rng: &mut SplitMix64,
+) -> usize {
+    let (mut x, mut y) = (start_x, start_y);
+    let mut cells = 0;
+
+    for step in 0..max_len {
+        // случайный сдвиг, чтобы потоки расходились веером
+        let next = if rng.chance(0.25) {
+            random_lower_neighbor(hm, x, y, rng)
+        } else {
+            d8_downslope(hm, x, y)
+        };
+
+        let Some((nx, ny)) = next else {
+            // локальный минимум — лава скапливается в озеро
+            let idx = hm.index(x, y);
+            hm.values[idx] = (hm.values[idx] + thickness).min(1.0);
+            deposits.lava[idx] += thickness;
+            cells += 1;
+            break;
+        };
+
+        x = nx;
+        y = ny;
+        let t = thickness * (1.0 - step as f32 / max_len as f32);
+        let idx = hm.index(x, y);
+        hm.values[idx] = (hm.values[idx] + t).min(1.0);
+        deposits.lava[idx] += t;
+        cells += 1;
+
+        // в море лава остывает
+        if hm.values[idx] <= sea_level {
+            break;
+        }
+    }
+
+    cells
+}
+
+fn random_lower_neighbor(
+    hm: &Heightmap,
+    x: u32,
+    y: u32,
+    rng: &mut SplitMix64,
+) -> Option<(u32, u32)> {
+    let h_here = hm.get(x, y);
+    let mut candidates: Vec<(u32, u32)> = Vec::with_capacity(8);
+    for dy in -1i32..=1 {
+        for dx in -1i32..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx < 0 || ny < 0 || nx >= hm.width as i32 || ny >= hm.height as i32 {
+                continue;
+            }
+            if hm.get(nx as u32, ny as u32) < h_here {
+                candidates.push((nx as u32, ny as u32));
+            }
+        }
+    }
+    if candidates.is_empty() {
+        None
+    } else {
+        Some(candidates[rng.below(candidates.len())])
+    }
+}
+
+/// Пепел: шлейф вдоль ветра, расширяется и истончается с расстоянием
+fn deposit_ash(
+    hm: &mut Heightmap,
+    deposits: &mut VolcanicDeposits,
+    cx: u32,
+    cy: u32,
+    vei: f64,
+    wind: (f64, f64),
+) -> usize {
+    let plume_len = 6.0 + vei * 10.0;
+    let base = 0.0004 * vei;
+    if base <= 0.0 {
+        return 0;
+    }
+
+    let reach = plume_len.ceil() as i32;
+    let mut cells = 0;
+
+    for dy in -reach..=reach {
+        for dx in -reach..=reach {
+            let x = cx as i32 + dx;
+            let y = cy as i32 + dy;
+            if x < 0 || y < 0 || x >= hm.width as i32 || y >= hm.height as i32 {
+                continue;
+            }
+            // координаты вдоль/поперёк ветра
+            let along = dx as f64 * wind.0 + dy as f64 * wind.1;
+            let across = -(dx as f64) * wind.1 + dy as f64 * wind.0;
+            if along < -2.0 || along > plume_len {
+                continue;
+            }
+            let half_width = 1.5 + along.max(0.0) * 0.3;
+            if across.abs() > half_width {
+                continue;
+            }
+
+            let t = base
+                * (-along.max(0.0) / (plume_len * 0.4)).exp()
+                * (1.0 - across.abs() / half_width);
+            if t <= 0.0 {
+                continue;
+            }
+
+            let idx = hm.index(x as u32, y as u32);
+            hm.values[idx] = (hm.values[idx] + t as f32).min(1.0);
+            deposits.ash[idx] += t as f32;
+            cells += 1;
+        }
+    }
+
+    cells
+}
@@ -0,0 +1,410 @@
+//! Войны в симуляции истории: споры на общих границах перерастают в войны,
+//! армии идут между городами по наименее затратному пути, а исход битв
+//! решают население и уровень технологий. Захваченные города меняют
+//! владельца, разрушенные остаются на карте руинами.
+
+use crate::diplomacy::Treaty;
+use crate::history::{History, HistoryEvent, HistoryEventKind, HISTORY_STEP_YEARS};
+use crate::rng::SplitMix64;
+use crate::settlements::{Ruin, RuinCause};
+use crate::tech::Era;
+use crate::terrain::Heightmap;
+use crate::territory::{terrain_step_cost, TerritoryMap};
+use crate::trade::{least_cost_path, CostSurface};
+use seed_config::WorldConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Максимальная стоимость пути армии; дальше походы не ведутся
+pub const MAX_CAMPAIGN_COST: f32 = 120.0;
+
+/// Длина общей границы (в клетках), при которой спор достигает полной силы
+const FULL_DISPUTE_BORDER: f64 = 20.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct War {
+    pub attacker_id: String,
+    pub defender_id: String,
+    pub start_year: u32,
+    /// `None` — война продолжалась на конец симуляции
+    pub end_year: Option<u32>,
+    pub battles: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Battle {
+    pub year: u32,
+    pub attacker_id: String,
+    pub defender_id: String,
+    /// Осаждённый город
+    pub city_id: String,
+    pub x: u32,
+    pub y: u32,
+    /// Путь армии от её города до цели
+    pub army_path: Vec<(u32, u32)>,
+    pub attacker_strength: f64,
+    pub defender_strength: f64,
+    pub attacker_won: bool,
+    pub razed: bool,
+}
+
+/// Военный множитель уровня технологий
+pub fn tech_strength(tech_level: &str) -> f64 {
+    Era::from_tech_level(tech_level).map_or(1.0, Era::strength)
+}
+
+/// Длина общих границ между парами фракций (индексы, a < b)
+pub fn border_contacts(map: &TerritoryMap) -> BTreeMap<(u16, u16), usize> {
+    let mut contacts = BTreeMap::new();
+    let w = map.width as usize;
+    let h = map.height as usize;
+    for y in 0..h {
+        for x in 0..w {
+            let Some(a) = map.owner[y * w + x] else {
+                continue;
+            };
+            let right = (x + 1 < w).then(|| map.owner[y * w + x + 1]).flatten();
+            let down = (y + 1 < h).then(|| map.owner[(y + 1) * w + x]).flatten();
+            for b in [right, down].into_iter().flatten() {
+                if a != b {
+                    *contacts.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    contacts
+}
+
+/// Напряжённость на границах между шагами симуляции
+#[derive(Debug, Default)]
+pub(crate) struct WarState {
+    tensions: BTreeMap<(usize, usize), f64>,
+    contacts: BTreeMap<(u16, u16), usize>,
+}
+
+impl WarState {
+    pub fn update_contacts(&mut self, territory: &TerritoryMap) {
+        self.contacts = border_contacts(territory);
+    }
+
+    pub fn contacts(&self) -> &BTreeMap<(u16, u16), usize> {
+        &self.contacts
+    }
+}
+
+/// Один шаг войн: эскалация споров, походы и битвы, заключение мира.
+/// Возвращает `true`, если города сменили владельцев или были разрушены.
+pub(crate) fn war_step(
+    history: &mut History,
+    state: &mut WarState,
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    surface: &CostSurface,
+    year: u32,
+    rng: &mut SplitMix64,
+) -> bool {
+    let likelihood = cfg.civilizations.history_simulation.war_likelihood.max(0.0) as f64;
+    let dt = HISTORY_STEP_YEARS as f64;
+
+    escalate_disputes(history, state, likelihood, dt, year, rng);
+
+    let mut changed = false;
+    for wi in 0..history.wars.len() {
+        if history.wars[wi].end_year.is_some() {
+            continue;
+        }
+        // походы идут не каждый шаг
+        if rng.chance(0.5) {
+            changed |= fight_battle(history, wi, cfg, hm, surface, year, rng);
+        }
+
+        let war = &history.wars[wi];
+        if war.end_year.is_some() {
+            continue;
+        }
+        let weariness = 0.1 + war.battles as f64 * 0.05;
+        if rng.chance(weariness.min(0.8)) {
+            let (a, d) = (war.attacker_id.clone(), war.defender_id.clone());
+            history.wars[wi].end_year = Some(year);
+            history.events.push(HistoryEvent {
+                year,
+                faction_id: a,
+                kind: HistoryEventKind::PeaceMade { enemy_id: d },
+            });
+        }
+    }
+
+    changed
+}
+
+fn faction_population(history: &History, faction_id: &str) -> u64 {
+    history
+        .cities
+        .iter()
+        .filter(|c| c.faction_id == faction_id)
+        .map(|c| c.population)
+        .sum()
+}
+
+pub(crate) fn at_war(history: &History, a: &str, b: &str) -> bool {
+    history.wars.iter().any(|w| {
+        w.end_year.is_none()
+            && ((w.attacker_id == a && w.defender_id == b)
+                || (w.attacker_id == b && w.defender_id == a))
+    })
+}
+
+/// Споры на общих границах копят напряжённость; торговля её гасит
+fn escalate_disputes(
+    history: &mut History,
+    state: &mut WarState,
+    likelihood: f64,
+    dt: f64,
+    year: u32,
+    rng: &mut SplitMix64,
+) {
+    let shared_trade = history.trade.volume_between_factions(&history.cities);
+
+    let mut declared = Vec::new();
+    for (&(a, b), &contact) in &state.contacts {
+        let (a, b) = (a as usize, b as usize);
+        let (Some(fa), Some(fb)) = (history.factions.get(a), history.factions.get(b)) else {
+            continue;
+        };
+        if fa.capital_id.is_none() || fb.capital_id.is_none() || at_war(history, &fa.id, &fb.id) {
+            continue;
+        }
+
+        // союзники друг на друга не нападают
+        let relation = history.diplomacy.current.get(a, b);
+        if relation.treaty == Some(Treaty::Alliance) {
+            continue;
+        }
+
+        let border = (contact as f64 / FULL_DISPUTE_BORDER).min(1.0);
+        let key = (
+            fa.id.clone().min(fb.id.clone()),
+            fa.id.clone().max(fb.id.clone()),
+        );
+        let trade = shared_trade.get(&key).copied().unwrap_or(0.0);
+        let calm = 1.0 / (1.0 + trade / 10_000.0);
+        // соперники ссорятся вдвое быстрее, друзья — медленнее
+        let hostility = 1.0 - relation.score as f64 / 100.0;
+
+        let t = state.tensions.entry((a, b)).or_insert(0.0);
+        *t = *t * 0.98 + likelihood * border * calm * hostility * dt * 0.02;
+
+        if *t >= 1.0 && rng.chance(likelihood.min(1.0)) {
+            *t = 0.0;
+            // нападает, как правило, более многочисленная сторона
+            let pa = faction_population(history, &fa.id) as f64;
+            let pb = faction_population(history, &fb.id) as f64;
+            let a_attacks = rng.next_f64() * (pa + pb) < pa;
+            declared.push(if a_attacks { (a, b) } else { (b, a) });
+        }
+    }
+
+    for (att, def) in declared {
+        let attacker_id = history.factions[att].id.clone();
+        let defender_id = history.factions[def].id.clone();
+        history.events.push(HistoryEvent {
+            year,
+            faction_id: attacker_id.clone(),
+            kind: HistoryEventKind::WarDeclared {
+                enemy_id: defender_id.clone(),
+            },
+        });
+        history.wars.push(War {
+            attacker_id,
+            defender_id,
+            start_year: year,
+            end_year: None,
+            battles: 0,
+        });
+    }
+}
+
+/// Поход и битва в рамках войны `wi`. Возвращает `true`, если город сменил владельца или разрушен.
+fn fight_battle(
+    history: &mut History,
+    wi: usize,
+    cfg: &WorldConfig,
+    hm: &Heightmap,
+    surface: &CostSurface,
+    year: u32,
+    rng: &mut SplitMix64,
+) -> bool {
+    let war = &history.wars[wi];
+    // чаще наступает объявивший войну, но бывают и контрудары
+    let (att_id, def_id) = if rng.chance(0.75) {
+        (war.attacker_id.clone(), war.defender_id.clone())
+    } else {
+        (war.defender_id.clone(), war.attacker_id.clone())
+    };
+
+    // Ближайшая пара "свой город — вражеский город"
+    let mut best: Option<(usize, usize, f64)> = None;
+    for (si, s) in history.cities.iter().enumerate() {
+        if s.faction_id != att_id {
+            continue;
+        }
+        for (ti, t) in history.cities.iter().enumerate() {
+            if t.faction_id != def_id {
+                continue;
+            }
+            let d = (s.x as f64 - t.x as f64).hypot(s.y as f64 - t.y as f64);
+            if best.is_none_or(|(_, _, bd)| d < bd) {
+                best = Some((si, ti, d));
+            }
+        }
+    }
+    let Some((si, ti, _)) = best else {
+        return false;
+    };
+
+    let w = surface.w;
+    let (src, dst) = (&history.cities[si], &history.cities[ti]);
+    let start = src.y as usize * w + src.x as usize;
+    let goal = dst.y as usize * w + dst.x as usize;
+    let Some((path, cost)) = least_cost_path(surface, start, goal) else {
+        return false;
+    };
+    if cost > MAX_CAMPAIGN_COST {
+        return false;
+    }
+
+    let tech_of = |id: &str| {
+        history
+            .factions
+            .iter()
+            .find(|f| f.id == id)
+            .map(|f| tech_strength(&f.tech_level))
+            .unwrap_or(1.0)
+    };
+
+    // Армия — десятая часть города, тает в долгом походе
+    let army = src.population as f64 * 0.1;
+    let attacker_strength = army * tech_of(&att_id) * (-(cost as f64) / 200.0).exp();
+    let fortification = if dst.is_capital { 1.5 } else { 1.0 };
+    let terrain = 1.0 + 0.05 * (terrain_step_cost(cfg, hm, dst.x, dst.y) - 1.0).min(10.0) as f64;
+    let defender_strength =
+        dst.population as f64 * 0.15 * tech_of(&def_id) * fortification * terrain;
+
+    let p_win = attacker_strength / (attacker_strength + defender_strength).max(1.0);
+    let attacker_won = rng.chance(p_win);
+
+    // Потери
+    let (att_loss, def_loss) = if attacker_won { (0.2, 0.3) } else { (0.5, 0.1) };
+    let src_pop = history.cities[si].population;
+    history.cities[si].population = src_pop.saturating_sub((army * att_loss) as u64);
+    let dst_pop = history.cities[ti].population;
+    history.cities[ti].population = dst_pop.saturating_sub((dst_pop as f64 * def_loss) as u64);
+    // армия разоряет окрестные деревни
+    let rural = history.cities[ti].rural_population;
+    let pillage = if attacker_won { 0.25 } else { 0.05 };
+    history.cities[ti].rural_population = rural.saturating_sub((rural as f64 * pillage) as u64);
+
+    let target = history.cities[ti].clone();
+    let razed = attacker_won && rng.chance(if target.population < 5_000 { 0.6 } else { 0.25 });
+
+    history.wars[wi].battles += 1;
+    history.events.push(HistoryEvent {
+        year,
+        faction_id: att_id.clone(),
+        kind: HistoryEventKind::BattleFought {
+            city_id: target.id.clone(),
+            enemy_id: def_id.clone(),
+            won: attacker_won,
+        },
+    });
+    history.battles.push(Battle {
+        year,
+        attacker_id: att_id.clone(),
+        defender_id: def_id.clone(),
+        city_id: target.id.clone(),
+        x: target.x,
+        y: target.y,
+        army_path: path
+            .iter()
+            .map(|&i| ((i % w) as u32, (i / w) as u32))
+            .collect(),
+        attacker_strength,
+        defender_strength,
+        attacker_won,
+        razed,
+    });
+
+    if !attacker_won {
+        return false;
+    }
+
+    if razed {
+        history.cities.remove(ti);
+        history.culture.forget(&target.id);
+        history.ruins.push(Ruin {
+            id: target.id.clone(),
+            name: target.name.clone(),
+            faction_id: def_id.clone(),
+            x: target.x,
+            y: target.y,
+            founded_year: target.founded_year,
+            destroyed_year: year,
+            population_at_destruction: target.population,
+            cause: RuinCause::Razed,
+        });
+        history.events.push(HistoryEvent {
+            year,
+            faction_id: att_id.clone(),
+            kind: HistoryEventKind::CityRazed {
+                city_id: target.id.clone(),
+            },
+        });
+    } else {
+        let city = &mut history.cities[ti];
+        city.faction_id = att_id.clone();
+        city.is_capital = false;
+        history.events.push(HistoryEvent {
+            year,
+            faction_id: att_id.clone(),
+            kind: HistoryEventKind::CityCaptured {
+                city_id: target.id.clone(),
+                from_faction_id: def_id.clone(),
+            },
+        });
+    }
+
+    if target.is_capital {
+        relocate_capital(history, wi, &def_id, year);
+    }
+
+    true
+}
+
+/// Потеряв столицу, фракция переносит её в крупнейший из оставшихся городов;
+/// если городов не осталось, фракция исчезает и война заканчивается.
+fn relocate_capital(history: &mut History, wi: usize, faction_id: &str, year: u32) {
+    let new_capital = history
+        .cities
+        .iter_mut()
+        .filter(|c| c.faction_id == faction_id)
+        .max_by_key(|c| c.population);
+
+    let capital_id = new_capital.map(|c| {
+        c.is_capital = true;
+        c.id.clone()
+    });
+    let destroyed = capital_id.is_none();
+
+    if let Some(f) = history.factions.iter_mut().find(|f| f.id == faction_id) {
+        f.capital_id = capital_id;
+    }
+
+    if destroyed {
+        history.wars[wi].end_year = Some(year);
+        history.events.push(HistoryEvent {
+            year,
+            faction_id: faction_id.to_string(),
+            kind: HistoryEventKind::FactionDestroyed,
+        });
+    }
+}
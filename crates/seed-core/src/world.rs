@@ -0,0 +1,306 @@
+//! Мир целиком — общая точка входа для seed-cli, сервера и seed-wasm:
+//! конфиг, активная планета, календарь и часы и всё, что из них выводится.
+//! Карты высот, биомов, климата и материалов поверхности, объекты по
+//! чанкам, расписание катастроф и вулканы строятся при первом обращении и
+//! дальше хранятся в мире. Изменение рельефа (`heightmap_mut`, удар
+//! катастрофы или извержение в `advance`) сбрасывает всё, что от него
+//! зависит.
+//!
+//! Извержения по расписанию катастроф проходят через `VolcanoSimulator`
+//! мира: извергается ближайший вулкан, и отложения копятся от извержения к
+//! извержению. Между ними симулятор идёт по часам мира и может разбудить
+//! вулкан сам.
+
+use std::sync::OnceLock;
+
+use seed_config::{CosmosConfig, WorldConfig};
+
+use crate::biome::{compute_climate_map, generate_biome_map_with_progress, BiomeMap, ClimateMap};
+use crate::calendar::{Calendar, SimulationContext, WorldClock};
+use crate::catastrophe::{apply_catastrophe_with_volcanoes, Catastrophe, CatastropheSchedule};
+use crate::names::{NameGenerator, NameStyle};
+use crate::navmesh::{generate_navmesh, NavMesh, NavMeshOptions};
+use crate::objects::{ObjectIndex, ProceduralObject};
+use crate::progress::{NoProgress, Progress};
+use crate::surface::{compute_surface_map, SurfaceMap};
+use crate::terrain::{generate_heightmap_with_progress, Heightmap};
+use crate::volcano::VolcanoSimulator;
+use crate::{CoreError, Result};
+
+/// Сторона карты по умолчанию, клетки
+pub const DEFAULT_MAP_SIZE: u32 = 512;
+/// Сторона чанка объектов, клетки — как у чанков сервера
+const OBJECT_CHUNK_CELLS: u32 = 32;
+/// На сколько игровых лет вперёд строится расписание катастроф
+pub const CATASTROPHE_HORIZON_YEARS: f64 = 1000.0;
+
+#[derive(Debug)]
+pub struct World {
+    pub id: String,
+    pub name: String,
+    pub cosmos: Cosmos,
+    pub clock: WorldClock,
+    config: WorldConfig,
+    calendar: Calendar,
+    map_width: u32,
+    map_height: u32,
+    heightmap: OnceLock<Heightmap>,
+    biomes: OnceLock<BiomeMap>,
+    climate: OnceLock<ClimateMap>,
+    surface: OnceLock<SurfaceMap>,
+    objects: ObjectIndex,
+    catastrophes: OnceLock<CatastropheSchedule>,
+    volcanoes: OnceLock<VolcanoSimulator>,
+}
+
+#[derive(Debug)]
+pub struct Cosmos {
+    pub active_planet: Planet,
+}
+
+#[derive(Debug)]
+pub struct Planet {
+    pub id: String,
+    pub name: String,
+    pub radius_km: f64,
+    pub gravity_ms2: f64,
+    pub day_length_hours: f64,
+    pub year_length_days: f64,
+}
+
+impl World {
+    /// Мир с картой `DEFAULT_MAP_SIZE`×`DEFAULT_MAP_SIZE`
+    pub fn from_config(cfg: &WorldConfig) -> Result<Self> {
+        Self::with_map_size(cfg, DEFAULT_MAP_SIZE, DEFAULT_MAP_SIZE)
+    }
+
+    /// Мир с картой `width`×`height` клеток; карты ещё не построены
+    pub fn with_map_size(cfg: &WorldConfig, width: u32, height: u32) -> Result<Self> {
+        let cosmos = Cosmos::from_config(&cfg.cosmos)?;
+
+        Ok(World {
+            id: cfg.world_id.clone(),
+            name: cfg.meta.name.clone(),
+            cosmos,
+            clock: WorldClock::default(),
+            config: cfg.clone(),
+            calendar: Calendar::from_config(cfg),
+            map_width: width,
+            map_height: height,
+            heightmap: OnceLock::new(),
+            biomes: OnceLock::new(),
+            climate: OnceLock::new(),
+            surface: OnceLock::new(),
+            objects: ObjectIndex::new(OBJECT_CHUNK_CELLS),
+            catastrophes: OnceLock::new(),
+            volcanoes: OnceLock::new(),
+        })
+    }
+
+    /// Мир с уже готовыми картами — например, загруженными из сохранения
+    pub fn from_maps(cfg: &WorldConfig, hm: Heightmap, bm: BiomeMap) -> Result<Self> {
+        let world = Self::with_map_size(cfg, hm.width, hm.height)?;
+        let _ = world.heightmap.set(hm);
+        let _ = world.biomes.set(bm);
+        Ok(world)
+    }
+
+    pub fn config(&self) -> &WorldConfig {
+        &self.config
+    }
+
+    pub fn calendar(&self) -> &Calendar {
+        &self.calendar
+    }
+
+    /// Ширина и высота карты, клетки
+    pub fn map_size(&self) -> (u32, u32) {
+        (self.map_width, self.map_height)
+    }
+
+    pub fn heightmap(&self) -> &Heightmap {
+        self.heightmap_with_progress(&NoProgress)
+    }
+
+    /// Как `heightmap`, с отчётом о ходе, если карту приходится строить
+    pub fn heightmap_with_progress(&self, progress: &dyn Progress) -> &Heightmap {
+        self.heightmap.get_or_init(|| {
+            generate_heightmap_with_progress(
+                &self.config,
+                self.map_width,
+                self.map_height,
+                progress,
+            )
+        })
+    }
+
+    /// Рельеф для правки; всё, что от него зависит, будет построено заново
+    pub fn heightmap_mut(&mut self) -> &mut Heightmap {
+        self.heightmap();
+        self.invalidate_derived();
+        self.heightmap.get_mut().expect("heightmap is initialized")
+    }
+
+    pub fn biomes(&self) -> &BiomeMap {
+        self.biomes_with_progress(&NoProgress)
+    }
+
+    /// Как `biomes`, с отчётом о ходе; рельеф строится с тем же отчётом
+    pub fn biomes_with_progress(&self, progress: &dyn Progress) -> &BiomeMap {
+        let hm = self.heightmap_with_progress(progress);
+        self.biomes
+            .get_or_init(|| generate_biome_map_with_progress(&self.config, hm, progress))
+    }
+
+    /// Температура и осадки по клеткам
+    pub fn climate(&self) -> &ClimateMap {
+        self.climate
+            .get_or_init(|| compute_climate_map(&self.config, self.heightmap()))
+    }
+
+    /// Материал поверхности по клеткам
+    pub fn surface(&self) -> &SurfaceMap {
+        self.surface.get_or_init(|| {
+            compute_surface_map(
+                &self.config,
+                self.heightmap(),
+                self.biomes(),
+                self.climate(),
+            )
+        })
+    }
+
+    /// Объекты чанка (`chunk_x`, `chunk_y`) по `ObjectIndex` мира
+    pub fn objects_in_chunk(&mut self, chunk_x: u32, chunk_y: u32) -> &[ProceduralObject] {
+        self.biomes();
+        let hm = self.heightmap.get().expect("heightmap is initialized");
+        let bm = self.biomes.get().expect("biomes are initialized");
+        self.objects.chunk(&self.config, hm, bm, chunk_x, chunk_y)
+    }
+
+    /// Сторона чанка объектов, клетки
+    pub fn object_chunk_cells(&self) -> u32 {
+        self.objects.chunk_cells()
+    }
+
+    /// Навигационная сетка всей карты; препятствия — объекты всех чанков
+    pub fn navmesh(&mut self, options: &NavMeshOptions) -> NavMesh {
+        let (w, h) = self.map_size();
+        let cells = self.object_chunk_cells();
+        let mut obstacles = Vec::new();
+        for cy in 0..h.div_ceil(cells) {
+            for cx in 0..w.div_ceil(cells) {
+                obstacles.extend_from_slice(self.objects_in_chunk(cx, cy));
+            }
+        }
+        generate_navmesh(&self.config, self.heightmap(), &obstacles, options)
+    }
+
+    /// Расписание катастроф по сиду мира; при первом обращении прошедшие по
+    /// часам события считаются случившимися
+    pub fn catastrophes(&self) -> &CatastropheSchedule {
+        self.catastrophes.get_or_init(|| {
+            CatastropheSchedule::new(
+                &self.config,
+                CATASTROPHE_HORIZON_YEARS,
+                self.config.world_seed,
+                self.clock.years(&self.calendar),
+            )
+        })
+    }
+
+    /// Вулканы мира с их фазами и отложениями; расставляются по рельефу
+    /// при первом обращении, время симулятора — часы мира
+    pub fn volcanoes(&self) -> &VolcanoSimulator {
+        self.volcanoes.get_or_init(|| {
+            let mut sim =
+                VolcanoSimulator::new(&self.config, self.heightmap(), self.config.world_seed);
+            sim.year = self.clock.years(&self.calendar);
+            sim
+        })
+    }
+
+    /// Двигает часы на `real_s` реальных секунд и применяет к рельефу
+    /// наступившие катастрофы, затем ведёт вулканы до нового времени.
+    /// Возвращает случившееся: катастрофы по расписанию и извержения,
+    /// начатые самими вулканами
+    pub fn advance(&mut self, real_s: f64, context: SimulationContext) -> Vec<Catastrophe> {
+        self.catastrophes();
+        self.volcanoes();
+        self.clock.advance(&self.calendar, real_s, context);
+        let now = self.clock.years(&self.calendar);
+        let mut struck = self
+            .catastrophes
+            .get_mut()
+            .expect("schedule is initialized")
+            .due(now);
+        let hm = self.heightmap.get_mut().expect("heightmap is initialized");
+        let volcanoes = self.volcanoes.get_mut().expect("volcanoes are initialized");
+        for cat in &struck {
+            apply_catastrophe_with_volcanoes(hm, cat, &self.config, volcanoes);
+        }
+        struck.extend(volcanoes.advance_to(hm, now));
+        if !struck.is_empty() {
+            self.invalidate_derived();
+        }
+        struck
+    }
+
+    /// Рельеф и биомы, построенные при необходимости
+    pub fn into_maps(self) -> (Heightmap, BiomeMap) {
+        self.biomes();
+        let World {
+            heightmap, biomes, ..
+        } = self;
+        (
+            heightmap.into_inner().expect("heightmap is initialized"),
+            biomes.into_inner().expect("biomes are initialized"),
+        )
+    }
+
+    /// Забывает всё, что построено по рельефу
+    fn invalidate_derived(&mut self) {
+        self.biomes.take();
+        self.climate.take();
+        self.surface.take();
+        self.objects.clear();
+    }
+}
+
+impl Cosmos {
+    pub fn from_config(cfg: &CosmosConfig) -> Result<Self> {
+        let active_id = &cfg.star_system.active_planet_id;
+        let planet_cfg = cfg
+            .star_system
+            .planets
+            .iter()
+            .find(|p| &p.id == active_id)
+            .ok_or_else(|| CoreError::Config(format!("Active planet '{active_id}' not found")))?;
+
+        // безымянная планета получает процедурное имя, стабильное для её id
+        let name = if planet_cfg.name.trim().is_empty() {
+            let seed = planet_cfg
+                .id
+                .bytes()
+                .fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+                    (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+                });
+            NameGenerator::new(NameStyle::common(), seed).next_name()
+        } else {
+            planet_cfg.name.clone()
+        };
+
+        let planet = Planet {
+            id: planet_cfg.id.clone(),
+            name,
+            radius_km: planet_cfg.radius_km,
+            gravity_ms2: planet_cfg.gravity_ms2,
+            day_length_hours: planet_cfg.day_length_hours,
+            year_length_days: planet_cfg.year_length_days,
+        };
+
+        Ok(Cosmos {
+            active_planet: planet,
+        })
+    }
+}
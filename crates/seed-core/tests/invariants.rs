@@ -0,0 +1,167 @@
+//! Инварианты генератора на случайных конфигах и сидах: высоты в 0..1 и без
+//! NaN после эрозии, индексы биомов из конфига, один сид — одна карта,
+//! карта по кускам и участок полной карты — та же карта, одна история
+//! фракций на один сид. Конфиги — образец
+//! `world-config.json` со случайными сидом, масштабом континентов и уровнем
+//! моря; карты маленькие, чтобы прогонов хватало на разные сиды.
+//!
+//! Снимок `snapshots/invariants__fixed_seed_map.snap` ловит любое изменение
+//! карты образца; если оно намеренное — `cargo insta review`.
+
+use proptest::prelude::*;
+use seed_config::WorldConfig;
+use seed_core::{
+    generate_biome_map_from_config, generate_heightmap_from_config, generate_heightmap_window,
+    simulate_history, BiomeMapBuilder, HeightmapBuilder, MapWindow, NoProgress,
+};
+
+fn sample_config() -> WorldConfig {
+    include_str!("../../../world-config.json")
+        .parse()
+        .expect("world-config.json parses")
+}
+
+prop_compose! {
+    fn arb_config()(
+        seed in any::<u32>(),
+        continental_scale_km in 10.0f64..5000.0,
+        sea_level in 0.2f64..0.7,
+    ) -> WorldConfig {
+        let mut cfg = sample_config();
+        cfg.geology.heightmap.base_seed = seed as u64;
+        cfg.geology.heightmap.continental_scale_km = continental_scale_km;
+        cfg.sea_level = sea_level;
+        cfg
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    #[test]
+    fn heights_are_finite_and_normalized(cfg in arb_config(), w in 8u32..48, h in 8u32..48) {
+        let hm = generate_heightmap_from_config(&cfg, w, h);
+        prop_assert_eq!(hm.values.len(), (w * h) as usize);
+        for (i, &v) in hm.values.iter().enumerate() {
+            prop_assert!(v.is_finite(), "cell {} is {}", i, v);
+            prop_assert!((0.0..=1.0).contains(&v), "cell {} is {}", i, v);
+        }
+    }
+
+    #[test]
+    fn biome_indices_are_valid(cfg in arb_config(), w in 8u32..48, h in 8u32..48) {
+        let hm = generate_heightmap_from_config(&cfg, w, h);
+        let bm = generate_biome_map_from_config(&cfg, &hm);
+        prop_assert_eq!((bm.width, bm.height), (w, h));
+        for y in 0..h {
+            for x in 0..w {
+                if let Some(i) = bm.get_index(x, y) {
+                    prop_assert!(i < cfg.biomes.len(), "biome {} at ({}, {})", i, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_world(cfg in arb_config(), size in 8u32..40) {
+        let a = generate_heightmap_from_config(&cfg, size, size);
+        let b = generate_heightmap_from_config(&cfg, size, size);
+        prop_assert_eq!(&a.values, &b.values);
+        let (ba, bb) = (
+            generate_biome_map_from_config(&cfg, &a),
+            generate_biome_map_from_config(&cfg, &b),
+        );
+        prop_assert_eq!(ba.indices, bb.indices);
+    }
+
+    #[test]
+    fn chunked_generation_matches_full(cfg in arb_config(), size in 8u32..40, rows in 1u32..12) {
+        let full = generate_heightmap_from_config(&cfg, size, size);
+        let mut builder = HeightmapBuilder::new(&cfg, size, size);
+        while !builder.step(rows) {}
+        let chunked = builder.finish().expect("builder is done");
+        prop_assert_eq!(&chunked.values, &full.values);
+
+        let full_biomes = generate_biome_map_from_config(&cfg, &full);
+        let mut builder = BiomeMapBuilder::new(&full);
+        while !builder.step(&cfg, &full, rows) {}
+        let chunked_biomes = builder.finish(&cfg).expect("builder is done");
+        prop_assert_eq!(chunked_biomes.indices, full_biomes.indices);
+    }
+
+    #[test]
+    fn window_at_map_resolution_matches_full(
+        cfg in arb_config(),
+        size in 8u32..40,
+        x0 in 0u32..8,
+        y0 in 0u32..8,
+        side in 1u32..8,
+    ) {
+        let full = generate_heightmap_from_config(&cfg, size, size);
+        let (x1, y1) = ((x0 + side).min(size - 1), (y0 + side).min(size - 1));
+        let window = MapWindow::from_cells(size, size, x0, y0, x1, y1);
+        let (w, h) = (x1 - x0 + 1, y1 - y0 + 1);
+        let part = generate_heightmap_window(&cfg, size, size, window, w, h, &NoProgress);
+        for y in 0..h {
+            for x in 0..w {
+                let (a, b) = (part.get(x, y), full.get(x0 + x, y0 + y));
+                prop_assert!((a - b).abs() < 1e-5, "({}, {}): {} != {}", x0 + x, y0 + y, a, b);
+            }
+        }
+    }
+}
+
+#[test]
+fn fixed_seed_map() {
+    let cfg = sample_config();
+    let hm = generate_heightmap_from_config(&cfg, 24, 24);
+    let bm = generate_biome_map_from_config(&cfg, &hm);
+    // высота цифрой 0..9 и номер биома в base36 (`~` — без биома)
+    let mut out = String::new();
+    for y in 0..hm.height {
+        for x in 0..hm.width {
+            let digit = (hm.get(x, y) * 9.999).floor() as u32;
+            let biome = bm
+                .get_index(x, y)
+                .and_then(|i| char::from_digit(i as u32, 36))
+                .unwrap_or('~');
+            out.push_str(&format!("{digit}{biome} "));
+        }
+        out.pop();
+        out.push('\n');
+    }
+    insta::assert_snapshot!(out);
+}
+
+#[test]
+fn same_seed_same_history() {
+    let cfg = sample_config();
+    // на картах помельче событий в истории нет — сравнивать нечего
+    let hm = generate_heightmap_from_config(&cfg, 128, 128);
+    let bm = generate_biome_map_from_config(&cfg, &hm);
+    let a = simulate_history(&cfg, &hm, &bm, cfg.world_seed);
+    let b = simulate_history(&cfg, &hm, &bm, cfg.world_seed);
+    assert!(!a.factions.is_empty() && !a.events.is_empty());
+
+    // по фракциям — чтобы расхождение сразу показало, чья история разошлась
+    for (fa, fb) in a.factions.iter().zip(&b.factions) {
+        assert_eq!(fa.id, fb.id);
+        let events = |h: &seed_core::History| {
+            h.events
+                .iter()
+                .filter(|e| e.faction_id == fa.id)
+                .map(|e| (e.year, e.kind.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(events(&a), events(&b), "faction {}", fa.id);
+        assert_eq!(
+            (&fa.tech_level, &fa.capital_id, fa.tech_progress),
+            (&fb.tech_level, &fb.capital_id, fb.tech_progress),
+            "faction {}",
+            fa.id
+        );
+    }
+    // и целиком: города, войны, дипломатия, культура, инфраструктура
+    let json = |h| serde_json::to_value(h).expect("history serializes");
+    assert_eq!(json(&a), json(&b));
+}
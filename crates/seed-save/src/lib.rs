@@ -0,0 +1,422 @@
+//! Сохранённый мир — бандл `.seedworld`: конфиг, heightmap, карта биомов и
+//! (по желанию) объекты, история и лента катастроф в одном файле, чтобы
+//! дорогую генерацию можно было посчитать один раз и открыть в CLI, сервере
+//! или wasm.
+//!
+//! Устройство файла:
+//!
+//! ```text
+//! "SEEDWRLD" | u32 LE версия | u32 LE длина индекса | индекс (JSON) | секции
+//! ```
+//!
+//! Индекс описывает мир и секции: имя, смещение от конца индекса, сжатая и
+//! исходная длина, CRC32 исходных данных. Каждая секция сжата отдельно, так
+//! что одну секцию можно прочитать, не распаковывая остальные. Чем сжаты
+//! секции, записано в индексе (`codec`): новые бандлы пишутся zstd, бандлы
+//! с `deflate` по-прежнему читаются. Heightmap — f32 LE по строкам, биомы — байт на
+//! клетку (255 — без биома), остальное — JSON.
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use ruzstd::decoding::StreamingDecoder;
+use ruzstd::encoding::CompressionLevel;
+use seed_config::WorldConfig;
+use seed_core::{BiomeMap, Catastrophe, Heightmap, History, ProceduralObject};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+use thiserror::Error;
+
+/// Расширение файла бандла
+pub const EXTENSION: &str = "seedworld";
+
+const MAGIC: &[u8; 8] = b"SEEDWRLD";
+const FORMAT_VERSION: u32 = 1;
+/// Клетка без биома в секции `biomes`
+const NO_BIOME: u8 = u8::MAX;
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("not a .seedworld bundle")]
+    NotABundle,
+
+    #[error("unsupported bundle version {0} (expected {FORMAT_VERSION})")]
+    UnsupportedVersion(u32),
+
+    #[error("unsupported section codec '{0}'")]
+    UnsupportedCodec(String),
+
+    #[error("bundle has no '{0}' section")]
+    MissingSection(String),
+
+    #[error("corrupt bundle: {0}")]
+    Corrupt(String),
+}
+
+pub type Result<T> = std::result::Result<T, SaveError>;
+
+/// Заголовок бандла: что за мир и где лежат секции
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleIndex {
+    pub codec: String,
+    pub world_id: String,
+    pub world_name: String,
+    pub world_seed: u64,
+    pub seed_version: String,
+    pub width: u32,
+    pub height: u32,
+    pub sections: Vec<SectionEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionEntry {
+    pub name: String,
+    /// От конца индекса
+    pub offset: u64,
+    pub length: u64,
+    pub raw_length: u64,
+    pub crc32: u32,
+}
+
+impl BundleIndex {
+    pub fn section(&self, name: &str) -> Option<&SectionEntry> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Отпечаток конфига, с которым сохранён мир (`config_hash`)
+    pub fn config_hash(&self) -> Option<u32> {
+        self.section("config").map(|s| s.crc32)
+    }
+}
+
+/// CRC32 конфига в том виде, в каком он ложится в секцию `config`: по
+/// нему видно, что сохранённый мир посчитан с тем же конфигом
+pub fn config_hash(cfg: &WorldConfig) -> Result<u32> {
+    Ok(crc32fast::hash(&config_json(cfg)?))
+}
+
+/// JSON конфига с ключами по порядку: в конфиге есть HashMap (состав
+/// атмосферы), и без сортировки один и тот же конфиг давал бы разные байты
+fn config_json(cfg: &WorldConfig) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&serde_json::to_value(cfg)?)?)
+}
+
+/// Чем сжаты секции; id пишется в индекс (`codec`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    /// Первые бандлы; читается, но больше не пишется по умолчанию
+    Deflate,
+    #[default]
+    Zstd,
+}
+
+impl Codec {
+    pub fn id(self) -> &'static str {
+        match self {
+            Codec::Deflate => "deflate",
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        [Codec::Deflate, Codec::Zstd]
+            .into_iter()
+            .find(|c| c.id() == id)
+    }
+
+    fn compress(self, raw: &[u8]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Codec::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(raw)?;
+                encoder.finish()?
+            }
+            Codec::Zstd => ruzstd::encoding::compress_to_vec(raw, CompressionLevel::Fastest),
+        })
+    }
+
+    fn decompress(self, packed: &[u8], raw: &mut Vec<u8>) -> Result<()> {
+        match self {
+            Codec::Deflate => {
+                DeflateDecoder::new(packed).read_to_end(raw)?;
+            }
+            Codec::Zstd => {
+                StreamingDecoder::new(packed)
+                    .map_err(|e| SaveError::Corrupt(format!("zstd frame: {e}")))?
+                    .read_to_end(raw)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Мир целиком; необязательные части — то, что посчитали перед сохранением
+#[derive(Debug, Clone)]
+pub struct WorldBundle {
+    pub config: WorldConfig,
+    pub heightmap: Heightmap,
+    pub biomes: BiomeMap,
+    pub objects: Option<Vec<ProceduralObject>>,
+    pub history: Option<History>,
+    pub catastrophes: Option<Vec<Catastrophe>>,
+}
+
+impl WorldBundle {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.to_bytes_with(Codec::default())
+    }
+
+    /// Бандл с секциями, сжатыми `codec`
+    pub fn to_bytes_with(&self, codec: Codec) -> Result<Vec<u8>> {
+        let mut sections: Vec<(&str, Vec<u8>)> = vec![
+            ("config", config_json(&self.config)?),
+            (
+                "heightmap",
+                self.heightmap
+                    .values
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect(),
+            ),
+            (
+                "biomes",
+                self.biomes
+                    .indices
+                    .iter()
+                    .map(|i| i.unwrap_or(NO_BIOME))
+                    .collect(),
+            ),
+        ];
+        if let Some(objects) = &self.objects {
+            sections.push(("objects", serde_json::to_vec(objects)?));
+        }
+        if let Some(history) = &self.history {
+            sections.push(("history", serde_json::to_vec(history)?));
+        }
+        if let Some(catastrophes) = &self.catastrophes {
+            sections.push(("catastrophes", serde_json::to_vec(catastrophes)?));
+        }
+
+        let mut index = BundleIndex {
+            codec: codec.id().to_string(),
+            world_id: self.config.world_id.clone(),
+            world_name: self.config.meta.name.clone(),
+            world_seed: self.config.world_seed,
+            seed_version: self.config.seed_version.clone(),
+            width: self.heightmap.width,
+            height: self.heightmap.height,
+            sections: Vec::new(),
+        };
+        let mut body = Vec::new();
+        for (name, raw) in sections {
+            let packed = codec.compress(&raw)?;
+            index.sections.push(SectionEntry {
+                name: name.to_string(),
+                offset: body.len() as u64,
+                length: packed.len() as u64,
+                raw_length: raw.len() as u64,
+                crc32: crc32fast::hash(&raw),
+            });
+            body.extend_from_slice(&packed);
+        }
+
+        let index_json = serde_json::to_vec(&index)?;
+        let mut out = Vec::with_capacity(16 + index_json.len() + body.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&(index_json.len() as u32).to_le_bytes());
+        out.extend_from_slice(&index_json);
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let reader = BundleReader::new(bytes)?;
+        let (width, height) = (reader.index.width, reader.index.height);
+        let cells = width as usize * height as usize;
+
+        let heights = reader.section("heightmap")?;
+        if heights.len() != cells * 4 {
+            return Err(SaveError::Corrupt(format!(
+                "heightmap has {} bytes, expected {}",
+                heights.len(),
+                cells * 4
+            )));
+        }
+        let biomes = reader.section("biomes")?;
+        if biomes.len() != cells {
+            return Err(SaveError::Corrupt(format!(
+                "biome map has {} cells, expected {cells}",
+                biomes.len()
+            )));
+        }
+
+        Ok(WorldBundle {
+            config: serde_json::from_slice(&reader.section("config")?)?,
+            heightmap: Heightmap {
+                width,
+                height,
+                values: heights
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            },
+            biomes: BiomeMap {
+                width,
+                height,
+                indices: biomes
+                    .iter()
+                    .map(|&b| (b != NO_BIOME).then_some(b))
+                    .collect(),
+            },
+            objects: reader.optional_json("objects")?,
+            history: reader.optional_json("history")?,
+            catastrophes: reader.optional_json("catastrophes")?,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+/// Чтение бандла по секциям, без распаковки ненужных
+pub struct BundleReader<'a> {
+    pub index: BundleIndex,
+    codec: Codec,
+    body: &'a [u8],
+}
+
+impl<'a> BundleReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < 16 || &bytes[..8] != MAGIC {
+            return Err(SaveError::NotABundle);
+        }
+        let version = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        if version != FORMAT_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+        let index_len = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]) as usize;
+        let index_end = 16usize
+            .checked_add(index_len)
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| SaveError::Corrupt("index is truncated".to_string()))?;
+        let index: BundleIndex = serde_json::from_slice(&bytes[16..index_end])?;
+        let codec = Codec::from_id(&index.codec)
+            .ok_or_else(|| SaveError::UnsupportedCodec(index.codec.clone()))?;
+        Ok(BundleReader {
+            index,
+            codec,
+            body: &bytes[index_end..],
+        })
+    }
+
+    pub fn has_section(&self, name: &str) -> bool {
+        self.index.section(name).is_some()
+    }
+
+    /// Распакованная секция `name` с проверкой длины и CRC
+    pub fn section(&self, name: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .index
+            .section(name)
+            .ok_or_else(|| SaveError::MissingSection(name.to_string()))?;
+        let start = entry.offset as usize;
+        let packed = start
+            .checked_add(entry.length as usize)
+            .and_then(|end| self.body.get(start..end))
+            .ok_or_else(|| SaveError::Corrupt(format!("section '{name}' is truncated")))?;
+        let mut raw = Vec::with_capacity(entry.raw_length as usize);
+        self.codec.decompress(packed, &mut raw)?;
+        if raw.len() as u64 != entry.raw_length || crc32fast::hash(&raw) != entry.crc32 {
+            return Err(SaveError::Corrupt(format!(
+                "section '{name}' failed its checksum"
+            )));
+        }
+        Ok(raw)
+    }
+
+    fn optional_json<T: for<'de> Deserialize<'de>>(&self, name: &str) -> Result<Option<T>> {
+        if !self.has_section(name) {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&self.section(name)?)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> WorldBundle {
+        let config: WorldConfig = include_str!("../../../world-config.json").parse().unwrap();
+        let (width, height) = (4, 3);
+        WorldBundle {
+            config,
+            heightmap: Heightmap {
+                width,
+                height,
+                values: (0..12).map(|i| i as f32 * 0.1).collect(),
+            },
+            biomes: BiomeMap {
+                width,
+                height,
+                indices: (0..12).map(|i| (i % 3 != 0).then_some(i as u8)).collect(),
+            },
+            objects: None,
+            history: None,
+            catastrophes: None,
+        }
+    }
+
+    fn assert_same(a: &WorldBundle, b: &WorldBundle) {
+        assert_eq!(a.heightmap.values, b.heightmap.values);
+        assert_eq!(a.biomes.indices, b.biomes.indices);
+        assert_eq!(
+            config_hash(&a.config).unwrap(),
+            config_hash(&b.config).unwrap()
+        );
+    }
+
+    #[test]
+    fn new_bundles_are_zstd() {
+        let bundle = bundle();
+        let bytes = bundle.to_bytes().unwrap();
+        assert_eq!(BundleReader::new(&bytes).unwrap().index.codec, "zstd");
+        assert_same(&bundle, &WorldBundle::from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn old_deflate_bundles_still_load() {
+        let bundle = bundle();
+        let bytes = bundle.to_bytes_with(Codec::Deflate).unwrap();
+        assert_eq!(BundleReader::new(&bytes).unwrap().index.codec, "deflate");
+        assert_same(&bundle, &WorldBundle::from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn unknown_codec_is_an_error() {
+        // то же число байт, так что длина индекса не меняется
+        let mut bytes = bundle().to_bytes().unwrap();
+        let at = bytes.windows(6).position(|w| w == b"\"zstd\"").unwrap();
+        bytes[at..at + 6].copy_from_slice(b"\"lzma\"");
+        assert!(matches!(
+            WorldBundle::from_bytes(&bytes),
+            Err(SaveError::UnsupportedCodec(c)) if c == "lzma"
+        ));
+    }
+}
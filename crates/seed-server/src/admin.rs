@@ -0,0 +1,141 @@
+//! API администратора: `GET /api/admin/players` — кто в мире, и
+//! `POST /api/admin/players/{id}/role` с `{"role": "spectator"}` — сделать
+//! игрока зрителем или вернуть в игру (`pc`, `vr`).
+//!
+//! Включается `--admin-token`; запросы несут его в заголовке
+//! `Authorization: Bearer <token>`. Без токена API выключено. Мир
+//! выбирается `?world=`, как у остальных `/api`.
+//!
+//! Клиент игрока получает `role_changed`. Вернуть зрителя в игру можно,
+//! только если в мире есть место.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::api::WorldQuery;
+use crate::worlds::{self, api_error, ApiError};
+use crate::{broadcast_roster, AppState, PlayerRole, ServerMessage, WorldState};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminPlayer {
+    id: String,
+    role: PlayerRole,
+    /// Соединение открыто; иначе игрок ждёт возобновления сессии
+    online: bool,
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleRequest {
+    role: PlayerRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleChange {
+    id: String,
+    role: PlayerRole,
+    previous: PlayerRole,
+}
+
+fn authorize(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(token) = state.admin_token.as_deref() else {
+        return Err(api_error(
+            StatusCode::NOT_FOUND,
+            "admin API is disabled (--admin-token)",
+        ));
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if given != Some(token) {
+        return Err(api_error(StatusCode::UNAUTHORIZED, "invalid admin token"));
+    }
+    Ok(())
+}
+
+/// `GET /api/admin/players`
+pub async fn list_players(
+    State(state): State<AppState>,
+    Query(query): Query<WorldQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<AdminPlayer>>, ApiError> {
+    authorize(&state, &headers)?;
+    let world = worlds::resolve(&state, query.world.as_deref())
+        .await
+        .map_err(|(status, message)| api_error(status, message))?;
+    let world = world.lock().await;
+    let mut players: Vec<AdminPlayer> = world
+        .players
+        .values()
+        .map(|p| AdminPlayer {
+            id: p.id.clone(),
+            role: p.role.clone(),
+            online: world.clients.contains_key(&p.id),
+            x: p.x,
+            y: p.y,
+            z: p.z,
+        })
+        .collect();
+    players.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(Json(players))
+}
+
+/// `POST /api/admin/players/{id}/role`
+pub async fn set_player_role(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<WorldQuery>,
+    headers: HeaderMap,
+    Json(req): Json<RoleRequest>,
+) -> Result<Json<RoleChange>, ApiError> {
+    authorize(&state, &headers)?;
+    let world = worlds::resolve(&state, query.world.as_deref())
+        .await
+        .map_err(|(status, message)| api_error(status, message))?;
+    let mut world = world.lock().await;
+    let previous = set_role(&mut world, &id, req.role.clone())?;
+    Ok(Json(RoleChange {
+        id,
+        role: req.role,
+        previous,
+    }))
+}
+
+/// Меняет роль игрока в мире; возвращает прежнюю
+fn set_role(world: &mut WorldState, id: &str, role: PlayerRole) -> Result<PlayerRole, ApiError> {
+    let playing = worlds::playing(&world.players);
+    let Some(p) = world.players.get_mut(id) else {
+        return Err(api_error(
+            StatusCode::NOT_FOUND,
+            format!("player '{id}' is not in the world"),
+        ));
+    };
+    if p.spectator() && role != PlayerRole::Spectator && playing >= worlds::MAX_PLAYERS_PER_WORLD {
+        return Err(api_error(StatusCode::CONFLICT, "world_full"));
+    }
+    let previous = std::mem::replace(&mut p.role, role.clone());
+    if previous == role {
+        return Ok(previous);
+    }
+    world.dirty = true;
+    info!(
+        "[{}] {} is now {:?} (was {:?})",
+        world.name, id, role, previous
+    );
+    if let Some(msg) = (ServerMessage::RoleChanged { role }).to_text() {
+        if let Some(c) = world.clients.get(id) {
+            let _ = c.sender.send(msg);
+        }
+    }
+    broadcast_roster(world);
+    Ok(previous)
+}
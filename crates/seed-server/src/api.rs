@@ -0,0 +1,119 @@
+//! Описание мира для клиентов: `GET /api/world` и `GET /api/config`.
+//! Клиенту хватает этого, чтобы настроить отрисовку и запросить тайлы,
+//! не имея своей копии world-config.json.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use seed_config::{MaterialConfig, WorldConfig};
+use seed_core::{render, MAX_RELIEF_M};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::lod;
+use crate::tiles;
+use crate::worlds;
+use crate::AppState;
+
+/// `?world=` — мир; без него — мир по умолчанию
+#[derive(Debug, Deserialize)]
+pub struct WorldQuery {
+    pub(crate) world: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldInfo {
+    world_id: String,
+    name: String,
+    seed_version: String,
+    world_seed: u64,
+    /// Размер карты сервера в клетках
+    width: u32,
+    height: u32,
+    region_size_km: f64,
+    cell_size_m: f64,
+    /// Уровень моря в нормализованных высотах карты (0..1)
+    sea_level: f64,
+    max_relief_m: f64,
+    max_tile_zoom: u32,
+    /// Глубина квадродерева рельефа и отсчётов по стороне узла (`terrain_nodes`)
+    max_lod_level: u32,
+    lod_node_samples: u32,
+    /// Порядок совпадает с индексами в тайлах биомов
+    biomes: Vec<BiomeInfo>,
+    /// Индекс воды в тайлах биомов
+    water_index: u8,
+    materials: Vec<MaterialConfig>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BiomeInfo {
+    id: String,
+    display_name: String,
+    color: [u8; 3],
+}
+
+pub async fn world_info(
+    State(state): State<AppState>,
+    Query(query): Query<WorldQuery>,
+) -> Result<Json<WorldInfo>, (StatusCode, String)> {
+    let world = worlds::resolve(&state, query.world.as_deref()).await?;
+    let world = world.lock().await;
+    let cfg = &world.config;
+    let hm = &world.heightmap;
+    let biomes = cfg
+        .biomes
+        .iter()
+        // палитра та же, что у веб-клиента, чтобы карты совпадали
+        .zip(render::biome_palette(cfg))
+        .map(|(b, color)| BiomeInfo {
+            id: b.id.clone(),
+            display_name: b.display_name.clone(),
+            color,
+        })
+        .collect();
+    Ok(Json(WorldInfo {
+        world_id: cfg.world_id.clone(),
+        name: cfg.meta.name.clone(),
+        seed_version: cfg.seed_version.clone(),
+        world_seed: cfg.world_seed,
+        width: hm.width,
+        height: hm.height,
+        region_size_km: cfg.scale.region_size_km,
+        cell_size_m: cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64,
+        sea_level: cfg.sea_level,
+        max_relief_m: MAX_RELIEF_M,
+        max_tile_zoom: tiles::MAX_ZOOM,
+        max_lod_level: lod::max_level(hm),
+        lod_node_samples: lod::NODE_SAMPLES,
+        biomes,
+        water_index: tiles::NO_BIOME,
+        materials: cfg.materials.clone(),
+    }))
+}
+
+/// Конфиг без серверной части: директор сюжета (шаблоны заданий и политика
+/// событий — спойлеры) и сетевые настройки симуляции
+pub async fn world_config(
+    State(state): State<AppState>,
+    Query(query): Query<WorldQuery>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let world = worlds::resolve(&state, query.world.as_deref()).await?;
+    let world = world.lock().await;
+    Ok(Json(sanitized_config(&world.config)))
+}
+
+fn sanitized_config(cfg: &WorldConfig) -> Value {
+    let mut value = serde_json::to_value(cfg).unwrap_or(Value::Null);
+    if let Some(root) = value.as_object_mut() {
+        root.remove("narrativeDirector");
+        if let Some(sim) = root.get_mut("simulation").and_then(Value::as_object_mut) {
+            sim.remove("network");
+        }
+    }
+    value
+}
@@ -0,0 +1,441 @@
+//! Катастрофы по расписанию. Расписание — `CatastropheSchedule` из
+//! seed-core на `CATASTROPHE_HORIZON_YEARS` вперёд по сиду мира, время события — годы
+//! часов мира, так что после перезапуска мир продолжает то же расписание,
+//! а прошедшие события не повторяются.
+//!
+//! За `WARNING_LEAD` реального времени до удара событие проверяет директор
+//! сюжета (`eventPolicies`, `canTriggerGlobalCatastrophes`, доля гибнущих
+//! игроков) и лимит `maxConcurrentEvents`; отклонённое событие не
+//! случается. Принятое проходит три фазы, о каждой всем клиентам мира
+//! приходит `catastrophe`:
+//! - `warning` — где и когда ударит;
+//! - `impact` — рельеф изменён (`apply_catastrophe_with_volcanoes`), событие
+//!   опубликовано в шину мира; клиентам, у которых загружены изменённые
+//!   чанки, досылаются `terrain_chunk` и перегенерированные `objects_chunk`;
+//! - `aftermath` — через `durationHours` игрового времени.
+//!
+//! Извержение по расписанию будит ближайший вулкан `VolcanoSimulator` мира,
+//! и отложения копятся от извержения к извержению. Раз в `VOLCANO_STEP`
+//! симулятор идёт по часам мира; извержение, начатое самим вулканом, не
+//! проходит директора и предупреждение — клиенты сразу получают `impact`.
+//! Фазы вулканов живут в памяти до перезапуска, их след — в рельефе.
+//!
+//! Охват события считается на планете (`MapGeo`): `radiusKm` — по
+//! поверхности сферы радиуса планеты, отсюда клетки карты. В `catastrophe`
+//! центр и охват из клеток переводятся в метры игроков тем же размером
+//! клетки, что и везде на сервере (`regionSizeKm` / ширина карты), так что
+//! круг в уведомлении совпадает с задетыми чанками.
+//!
+//! Отдельного инкрементального API перегенерации в seed-core нет: изменённые
+//! чанки находятся сравнением карты высот до и после удара. Изменённый
+//! рельеф сохраняется в бандл мира периодическим сохранением.
+
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use seed_config::WorldConfig;
+use seed_core::{
+    apply_catastrophe_with_volcanoes, build_gazetteer, simulate_history, BiomeMap, Catastrophe,
+    CatastropheSchedule, CatastropheType, Director, Heightmap, History, MapGeo, VolcanoPhase,
+    VolcanoSimulator, WorldEvent, CATASTROPHE_HORIZON_YEARS,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::clock::{self, WorldClock};
+use crate::deform;
+use crate::lod;
+use crate::objects::{self, ChunkKey, CHUNK_CELLS};
+use crate::persist::TerrainDelta;
+use crate::protocol::Feature;
+use crate::{ServerMessage, WorldState};
+
+/// За сколько реального времени до удара предупреждать клиентов
+pub const WARNING_LEAD: Duration = Duration::from_secs(30);
+/// Как часто вулканы догоняют часы мира, реального времени
+const VOLCANO_STEP: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Warning,
+    Impact,
+    Aftermath,
+}
+
+/// Сообщение `catastrophe`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatastropheNotice {
+    pub id: String,
+    pub phase: Phase,
+    pub kind: CatastropheType,
+    /// Центр, м в системе игроков (см. `objects`)
+    pub x: f32,
+    pub z: f32,
+    /// Охват по z (север — юг), м: `radiusKm` события на планете,
+    /// переведённый в клетки карты и оттуда в метры игроков
+    pub radius_m: f32,
+    /// Охват по x (запад — восток), м; к полюсам шире `radius_m`, клетка
+    /// карты там уже по долготе
+    pub radius_x_m: f32,
+    pub magnitude: f64,
+    /// Реальных секунд до удара; 0 — уже ударило
+    pub impact_in_s: f32,
+    /// Реальных секунд от удара до последствий
+    pub duration_s: f32,
+    /// Чанки с изменённым рельефом — только в `impact`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<[u32; 2]>,
+}
+
+#[derive(Debug)]
+struct Active {
+    cat: Catastrophe,
+    // Игровые секунды с начала мира
+    impact_s: f64,
+    struck: bool,
+}
+
+#[derive(Debug)]
+pub struct Catastrophes {
+    schedule: CatastropheSchedule,
+    active: Vec<Active>,
+    director: Director,
+    history: History,
+    volcanoes: VolcanoSimulator,
+    last_volcano_step: Option<Instant>,
+    // Чанки, чей рельеф изменился после последнего сохранения
+    unsaved: BTreeSet<ChunkKey>,
+}
+
+impl Catastrophes {
+    /// Расписание и директор мира. Без сохранённой истории она
+    /// симулируется заново — это долго, звать из блокирующего потока
+    pub fn new(
+        cfg: &WorldConfig,
+        hm: &Heightmap,
+        bm: &BiomeMap,
+        history: Option<History>,
+        clock: &WorldClock,
+    ) -> Self {
+        let history = history.unwrap_or_else(|| simulate_history(cfg, hm, bm, cfg.world_seed));
+        let mut director = Director::new(cfg, cfg.world_seed);
+        director.set_world(&history, &build_gazetteer(cfg, hm, bm, cfg.world_seed));
+
+        // прошедшие до старта события уже случились или пропущены
+        let schedule = CatastropheSchedule::new(
+            cfg,
+            CATASTROPHE_HORIZON_YEARS,
+            cfg.world_seed,
+            clock.years(cfg),
+        );
+        let mut volcanoes = VolcanoSimulator::new(cfg, hm, cfg.world_seed);
+        volcanoes.year = clock.years(cfg);
+        Self {
+            schedule,
+            active: Vec::new(),
+            director,
+            history,
+            volcanoes,
+            last_volcano_step: None,
+            unsaved: BTreeSet::new(),
+        }
+    }
+
+    /// История мира — сохраняется в бандл, чтобы не симулировать её заново
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Чанки, чей рельеф менялся с прошлого вызова, — их пора сохранить
+    pub fn take_unsaved(&mut self) -> BTreeSet<ChunkKey> {
+        std::mem::take(&mut self.unsaved)
+    }
+}
+
+/// Центр и полуоси охвата катастрофы в клетках карты — как в
+/// `apply_catastrophe_to_heightmap`: (x, y, по столбцам, по строкам)
+fn footprint_cells(cat: &Catastrophe, cfg: &WorldConfig, hm: &Heightmap) -> [f64; 4] {
+    let geo = MapGeo::from_config(cfg, hm.width, hm.height);
+    let center = cat.position.into();
+    let (x, y) = geo.to_pixel(center);
+    let (rx, ry) = geo.radius_cells(center, cat.radius_km);
+    [x, y, rx, ry]
+}
+
+fn notice(world: &WorldState, a: &Active, phase: Phase, chunks: &[ChunkKey]) -> ServerMessage {
+    let cfg = &world.config;
+    let scale = clock::time_scale(world).max(f64::EPSILON);
+    let cell_m = cfg.scale.region_size_km * 1000.0 / world.heightmap.width.max(1) as f64;
+    let [cx, cy, rx, ry] = footprint_cells(&a.cat, cfg, &world.heightmap);
+    ServerMessage::Catastrophe(CatastropheNotice {
+        id: a.cat.id.clone(),
+        phase,
+        kind: a.cat.catastrophe_type,
+        x: (cx * cell_m) as f32,
+        z: (cy * cell_m) as f32,
+        radius_m: (ry * cell_m) as f32,
+        radius_x_m: (rx * cell_m) as f32,
+        magnitude: a.cat.magnitude,
+        impact_in_s: ((a.impact_s - world.clock.time.elapsed_s) / scale).max(0.0) as f32,
+        duration_s: (a.cat.duration_hours * 3600.0 / scale) as f32,
+        chunks: chunks.iter().map(|&(x, y)| [x, y]).collect(),
+    })
+}
+
+fn broadcast(world: &WorldState, msg: ServerMessage) {
+    let Some(msg) = msg.to_text() else {
+        return;
+    };
+    for c in world.clients.values() {
+        if c.features.has(Feature::Catastrophes) {
+            c.sender.send(msg.clone());
+        }
+    }
+}
+
+/// Идущие катастрофы — клиенту при входе
+pub fn messages(world: &WorldState) -> Vec<ServerMessage> {
+    world
+        .catastrophes
+        .active
+        .iter()
+        .map(|a| {
+            let phase = if a.struck {
+                Phase::Impact
+            } else {
+                Phase::Warning
+            };
+            notice(world, a, phase, &[])
+        })
+        .collect()
+}
+
+/// Проверка директором и лимитом одновременных событий
+fn admit(world: &mut WorldState, cat: &Catastrophe) -> Result<(), String> {
+    let max = world
+        .config
+        .catastrophes
+        .global_controls
+        .max_concurrent_events as usize;
+    if world.catastrophes.active.len() >= max {
+        return Err(format!("{max} events already in progress"));
+    }
+    let WorldState {
+        config,
+        heightmap,
+        players,
+        catastrophes,
+        ..
+    } = world;
+    // директор считает игроков в клетках карты
+    let cell_m = config.scale.region_size_km * 1000.0 / heightmap.width.max(1) as f64;
+    let director = &mut catastrophes.director;
+    let gone: Vec<String> = director
+        .players()
+        .filter(|p| players.get(&p.id).is_none_or(|q| q.spectator()))
+        .map(|p| p.id.clone())
+        .collect();
+    for id in gone {
+        director.remove_player(&id);
+    }
+    for (id, p) in players.iter().filter(|(_, p)| !p.spectator()) {
+        director.update_player(
+            id,
+            (p.x as f64 / cell_m) as f32,
+            (p.z as f64 / cell_m) as f32,
+        );
+    }
+    for event in WorldEvent::from_catastrophe(config, cat, heightmap.width, heightmap.height) {
+        director.check_event(&event).map_err(|v| v.to_string())?;
+    }
+    Ok(())
+}
+
+/// Применяет катастрофу к рельефу; возвращает изменённые чанки
+fn strike(world: &mut WorldState, cat: &Catastrophe) -> Vec<ChunkKey> {
+    let before = world.heightmap.values.clone();
+    apply_catastrophe_with_volcanoes(
+        &mut world.heightmap,
+        cat,
+        &world.config,
+        &mut world.catastrophes.volcanoes,
+    );
+    let changed = refresh_terrain(world, &before);
+    if !changed.is_empty() {
+        publish(world, cat);
+    }
+    changed
+}
+
+/// Событие катастрофы — в шину мира
+fn publish(world: &mut WorldState, cat: &Catastrophe) {
+    let hm = &world.heightmap;
+    for event in WorldEvent::from_catastrophe(&world.config, cat, hm.width, hm.height) {
+        world.events.publish(event);
+    }
+}
+
+/// Вулканы догоняют часы мира; начатые ими извержения сразу ударяют
+fn step_volcanoes(world: &mut WorldState) {
+    let volcanoes = &world.catastrophes.volcanoes.volcanoes;
+    if volcanoes.iter().all(|v| v.phase == VolcanoPhase::Extinct) {
+        return;
+    }
+    let before = world.heightmap.values.clone();
+    let now = world.clock.years(&world.config);
+    let eruptions = world
+        .catastrophes
+        .volcanoes
+        .advance_to(&mut world.heightmap, now);
+    if eruptions.is_empty() {
+        return;
+    }
+    let chunks = refresh_terrain(world, &before);
+    let now_s = world.clock.time.elapsed_s;
+    for cat in eruptions {
+        info!(
+            "[{}] Volcano erupted: {} (VEI {:.1})",
+            world.name, cat.id, cat.magnitude
+        );
+        publish(world, &cat);
+        let active = Active {
+            cat,
+            impact_s: now_s,
+            struck: true,
+        };
+        broadcast(world, notice(world, &active, Phase::Impact, &chunks));
+        world.catastrophes.active.push(active);
+    }
+}
+
+/// Рельеф изменился относительно `before`: изменённые чанки — клиентам,
+/// в сохранение, объектам и LOD; возвращает их
+fn refresh_terrain(world: &mut WorldState, before: &[f32]) -> Vec<ChunkKey> {
+    let w = world.heightmap.width;
+    let changed: BTreeSet<ChunkKey> = before
+        .iter()
+        .zip(&world.heightmap.values)
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(i, _)| {
+            let i = i as u32;
+            ((i % w) / CHUNK_CELLS, (i / w) / CHUNK_CELLS)
+        })
+        .collect();
+    let changed: Vec<ChunkKey> = changed.into_iter().collect();
+    if changed.is_empty() {
+        return changed;
+    }
+    world.catastrophes.unsaved.extend(&changed);
+
+    for &key in &changed {
+        if !world.clients.values().any(|c| c.chunks.has(key)) {
+            continue;
+        }
+        let Some(msg) = terrain_chunk(&world.heightmap, key).to_text() else {
+            continue;
+        };
+        let clients = world.clients.values();
+        for c in clients.filter(|c| c.chunks.has(key) && c.features.has(Feature::TerrainChunks)) {
+            c.sender.send(msg.clone());
+        }
+    }
+    world.surface = deform::surface_map(&world.config, &world.heightmap, &world.biomemap);
+    // объекты стоят на рельефе — их чанки генерируются заново
+    objects::refresh(world, &changed);
+    lod::invalidate(world, &changed);
+    changed
+}
+
+/// Высоты чанка построчно, нормализованные как в карте высот
+fn terrain_chunk(hm: &Heightmap, key: ChunkKey) -> ServerMessage {
+    let d = TerrainDelta::capture(hm, key);
+    ServerMessage::TerrainChunk {
+        chunk_x: d.chunk_x,
+        chunk_y: d.chunk_y,
+        width: d.width,
+        height: d.height,
+        heights: d.heights,
+    }
+}
+
+/// Предупреждает о близких событиях, применяет наступившие и объявляет
+/// последствия закончившихся
+pub fn tick(world: &mut WorldState) {
+    let scale = clock::time_scale(world);
+    if scale <= 0.0 || world.heightmap.width == 0 {
+        return;
+    }
+    let now = world.clock.time.elapsed_s;
+    let year_s = clock::year_length_s(&world.config);
+    let lead_s = WARNING_LEAD.as_secs_f64() * scale;
+
+    let step_due = world
+        .catastrophes
+        .last_volcano_step
+        .is_none_or(|t| t.elapsed() >= VOLCANO_STEP);
+    if step_due {
+        world.catastrophes.last_volcano_step = Some(Instant::now());
+        step_volcanoes(world);
+    }
+
+    while let Some(cat) = world.catastrophes.schedule.peek() {
+        let impact_s = cat.timestamp * year_s;
+        if impact_s - now > lead_s {
+            break;
+        }
+        let Some(cat) = world.catastrophes.schedule.pop() else {
+            break;
+        };
+        if let Err(reason) = admit(world, &cat) {
+            info!(
+                "[{}] Catastrophe {} refused: {}",
+                world.name, cat.id, reason
+            );
+            continue;
+        }
+        info!(
+            "[{}] Catastrophe {} ({:?}, magnitude {:.1}) in {:.0} s",
+            world.name,
+            cat.id,
+            cat.catastrophe_type,
+            cat.magnitude,
+            ((impact_s - now) / scale).max(0.0)
+        );
+        let active = Active {
+            cat,
+            impact_s,
+            struck: false,
+        };
+        broadcast(world, notice(world, &active, Phase::Warning, &[]));
+        world.catastrophes.active.push(active);
+    }
+
+    let mut i = 0;
+    while i < world.catastrophes.active.len() {
+        let a = &world.catastrophes.active[i];
+        if !a.struck && now >= a.impact_s {
+            let cat = a.cat.clone();
+            let chunks = strike(world, &cat);
+            info!(
+                "[{}] Catastrophe {} struck, {} chunks changed",
+                world.name,
+                cat.id,
+                chunks.len()
+            );
+            let a = &mut world.catastrophes.active[i];
+            a.struck = true;
+            let a = &world.catastrophes.active[i];
+            broadcast(world, notice(world, a, Phase::Impact, &chunks));
+        }
+        let a = &world.catastrophes.active[i];
+        if a.struck && now >= a.impact_s + a.cat.duration_hours * 3600.0 {
+            let a = world.catastrophes.active.remove(i);
+            info!("[{}] Catastrophe {} is over", world.name, a.cat.id);
+            broadcast(world, notice(world, &a, Phase::Aftermath, &[]));
+            continue;
+        }
+        i += 1;
+    }
+}
@@ -0,0 +1,162 @@
+//! Часы мира: время суток и года активной планеты (`seed_core::calendar`).
+//! Идут на тиках сервера со скоростью `simulation.time.timeScale` игровых
+//! секунд за реальную — пока в мире есть игроки, не быстрее
+//! `maxTimeScaleInHub`, без них — `maxTimeScaleInBackgroundSim`; длина суток
+//! и года и наклон оси — из `cosmos.starSystem` активной планеты. Раз в `BROADCAST_INTERVAL` и при входе клиент получает `clock`
+//! с направлением на солнце и луны и сезоном, так что освещение у всех
+//! одно, а экосистема и директор сюжета могут опираться на общее время.
+//!
+//! Солнце и луны — `seed_core::astronomy`: год начинается с весеннего
+//! равноденствия, мир — в `START_HOUR` первого дня. Небо считается для
+//! центра карты (равномерная широтно-долготная сетка, как в seed-core); в
+//! режиме `planet` клиент пересчитывает солнце для своей широты по
+//! `sun_declination_deg` и `hour_angle_deg`. Сезон — для северного
+//! полушария.
+
+use std::time::Duration;
+
+use seed_config::WorldConfig;
+use seed_core::{Astronomy, Calendar, Season, SimulationContext};
+use serde::{Deserialize, Serialize};
+
+use crate::{ServerMessage, WorldState};
+
+pub const BROADCAST_INTERVAL: Duration = Duration::from_secs(1);
+/// Час, с которого начинается время нового мира
+const START_HOUR: f64 = 8.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockState {
+    /// Игровых секунд с начала мира
+    pub elapsed_s: f64,
+    /// Номер суток с начала мира
+    pub day: u64,
+    /// Доля суток: 0 — полночь, 0.5 — полдень
+    pub time_of_day: f64,
+    /// Доля года: 0 — весеннее равноденствие
+    pub year_fraction: f64,
+    pub season: Season,
+    /// Игровых секунд за реальную — клиент может интерполировать
+    pub time_scale: f64,
+    pub day_length_s: f64,
+    pub year_length_days: f64,
+    pub sun_declination_deg: f64,
+    /// Часовой угол солнца на долготе 0: 0 — полдень
+    pub hour_angle_deg: f64,
+    /// Высота солнца над горизонтом в центре карты: ниже 0 — ночь
+    pub sun_elevation_deg: f64,
+    /// Единичный вектор на солнце: x — восток, y — вверх, z — север
+    pub sun_dir: [f32; 3],
+    pub moons: Vec<MoonState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoonState {
+    pub id: String,
+    /// 0 — новолуние, 0.5 — полнолуние
+    pub phase: f64,
+    /// Освещённая доля диска
+    pub illumination: f64,
+    /// Единичный вектор на луну, как `sun_dir`
+    pub dir: [f32; 3],
+}
+
+/// Сохраняется между перезапусками
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldClock {
+    #[serde(flatten)]
+    pub time: seed_core::WorldClock,
+    #[serde(skip)]
+    since_broadcast: Duration,
+}
+
+impl WorldClock {
+    pub fn new(cfg: &WorldConfig) -> Self {
+        Self {
+            time: seed_core::WorldClock::at_hour(&Calendar::from_config(cfg), START_HOUR),
+            since_broadcast: Duration::ZERO,
+        }
+    }
+
+    pub fn state(&self, cfg: &WorldConfig, context: SimulationContext) -> ClockState {
+        let calendar = Calendar::from_config(cfg);
+        let astro = Astronomy::from_config(cfg);
+        let t = self.time.elapsed_s;
+        let date = self.time.date(&calendar);
+        // центр карты — экватор на нулевой долготе
+        let sun = astro.sun(0.0, 0.0, t);
+        ClockState {
+            elapsed_s: t,
+            day: date.day,
+            time_of_day: date.time_of_day,
+            year_fraction: date.year_fraction,
+            season: date.season,
+            time_scale: calendar.time_scale(context),
+            day_length_s: calendar.day_length_s,
+            year_length_days: calendar.year_length_days,
+            sun_declination_deg: sun.declination_deg,
+            hour_angle_deg: sun.hour_angle_deg,
+            sun_elevation_deg: sun.elevation_deg,
+            sun_dir: sun.direction().map(|v| v as f32),
+            moons: astro
+                .moons(0.0, 0.0, t)
+                .into_iter()
+                .map(|m| MoonState {
+                    dir: m.direction().map(|v| v as f32),
+                    id: m.id,
+                    phase: m.phase,
+                    illumination: m.illumination,
+                })
+                .collect(),
+        }
+    }
+
+    /// Игровых лет с начала мира
+    pub fn years(&self, cfg: &WorldConfig) -> f64 {
+        self.time.years(&Calendar::from_config(cfg))
+    }
+}
+
+/// Длина игрового года, с
+pub fn year_length_s(cfg: &WorldConfig) -> f64 {
+    Calendar::from_config(cfg).year_length_s()
+}
+
+/// С игроками мир — хаб, без них — фоновая симуляция
+pub fn context(world: &WorldState) -> SimulationContext {
+    if world.clients.is_empty() {
+        SimulationContext::Background
+    } else {
+        SimulationContext::Hub
+    }
+}
+
+/// Игровых секунд за реальную сейчас
+pub fn time_scale(world: &WorldState) -> f64 {
+    Calendar::from_config(&world.config).time_scale(context(world))
+}
+
+pub fn message(world: &WorldState) -> ServerMessage {
+    ServerMessage::Clock(world.clock.state(&world.config, context(world)))
+}
+
+/// Двигает часы на `dt` реального времени и при необходимости рассылает их
+pub fn tick(world: &mut WorldState, dt: Duration) {
+    let calendar = Calendar::from_config(&world.config);
+    let context = context(world);
+    world
+        .clock
+        .time
+        .advance(&calendar, dt.as_secs_f64(), context);
+    world.clock.since_broadcast += dt;
+    if world.clock.since_broadcast < BROADCAST_INTERVAL || world.clients.is_empty() {
+        return;
+    }
+    world.clock.since_broadcast = Duration::ZERO;
+    let Some(msg) = message(world).to_text() else {
+        return;
+    };
+    for c in world.clients.values() {
+        c.sender.send(msg.clone());
+    }
+}
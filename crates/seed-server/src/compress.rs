@@ -0,0 +1,190 @@
+//! Сжатие крупных сообщений сервера. Клиент перечисляет в `join` кодеки,
+//! которые умеет распаковывать, в порядке предпочтения
+//! (`"compression": ["zstd", "deflate"]`), сервер выбирает первый знакомый
+//! и называет его в `joined`. С этого момента сообщения
+//! (JSON и двоичные снапшоты) от `--compress-threshold` байт могут прийти
+//! сжатыми — двоичным кадром:
+//!
+//! ```text
+//! u8 KIND_COMPRESSED | u8 кодек (CODEC_*) | u8 что внутри (INNER_*) | сжатые данные
+//! ```
+//!
+//! `deflate` — сырой DEFLATE без заголовков, в браузере это
+//! `DecompressionStream("deflate-raw")`. `zstd` — кадр Zstandard; сжимает
+//! быстрее, но браузер его сам не распаковывает, клиенту нужен свой
+//! декодер (например, fzstd). Сообщение, которое не стало
+//! меньше, уходит как есть. Сжимает очередь соединения при отправке, так
+//! что общая рассылка не сжимает одно сообщение под мировой блокировкой.
+//!
+//! Расширение permessage-deflate сервер согласует только в сборке с фичей
+//! `permessage-deflate` (см. `deflate`): tungstenite, на котором стоят
+//! WebSocket'ы axum, его не поддерживает. Без фичи сжатие здесь его
+//! заменяет, причём только для крупных сообщений.
+
+use std::io::Write;
+
+use axum::extract::ws::Message;
+use flate2::write::DeflateEncoder;
+use ruzstd::encoding::CompressionLevel;
+use serde::{Deserialize, Serialize};
+
+/// Первый байт сжатого кадра; у снапшотов — `snapshot::KIND_*`
+pub const KIND_COMPRESSED: u8 = 3;
+pub const CODEC_DEFLATE: u8 = 1;
+pub const CODEC_ZSTD: u8 = 2;
+/// Внутри JSON-сообщение
+pub const INNER_TEXT: u8 = 0;
+/// Внутри двоичный кадр
+pub const INNER_BINARY: u8 = 1;
+pub const DEFAULT_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    Deflate,
+    Zstd,
+}
+
+impl Codec {
+    /// Первый знакомый серверу кодек из предложенных клиентом
+    pub fn negotiate(offered: &[String]) -> Option<Self> {
+        offered.iter().find_map(|c| Self::parse(c))
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "deflate" => Some(Self::Deflate),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Байт кодека в сжатом кадре
+    fn id(self) -> u8 {
+        match self {
+            Codec::Deflate => CODEC_DEFLATE,
+            Codec::Zstd => CODEC_ZSTD,
+        }
+    }
+}
+
+/// Сжатие исходящих сообщений одного клиента
+#[derive(Debug, Clone, Copy)]
+pub struct Compression {
+    pub codec: Codec,
+    /// Сообщения короче не сжимаются
+    pub threshold: usize,
+}
+
+impl Compression {
+    pub fn apply(&self, msg: Message) -> Message {
+        let (inner, data) = match &msg {
+            Message::Text(t) if t.len() >= self.threshold => (INNER_TEXT, t.as_bytes()),
+            Message::Binary(b) if b.len() >= self.threshold => (INNER_BINARY, b.as_slice()),
+            _ => return msg,
+        };
+        let header = vec![KIND_COMPRESSED, self.codec.id(), inner];
+        let packed = match self.codec {
+            Codec::Deflate => {
+                let mut out = DeflateEncoder::new(header, flate2::Compression::fast());
+                match out.write_all(data).and_then(|()| out.finish()) {
+                    Ok(packed) => packed,
+                    Err(_) => return msg,
+                }
+            }
+            Codec::Zstd => {
+                let mut packed = header;
+                ruzstd::encoding::compress(data, &mut packed, CompressionLevel::Fastest);
+                packed
+            }
+        };
+        if packed.len() < data.len() {
+            Message::Binary(packed)
+        } else {
+            msg
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::DeflateDecoder;
+
+    use super::*;
+
+    /// Распаковывает сжатый кадр обратно в сообщение
+    fn unpack(data: &[u8]) -> Message {
+        assert_eq!(data[0], KIND_COMPRESSED);
+        let mut raw = Vec::new();
+        match data[1] {
+            CODEC_DEFLATE => DeflateDecoder::new(&data[3..]).read_to_end(&mut raw),
+            CODEC_ZSTD => ruzstd::decoding::StreamingDecoder::new(&data[3..])
+                .unwrap()
+                .read_to_end(&mut raw),
+            codec => panic!("unknown codec {codec}"),
+        }
+        .unwrap();
+        match data[2] {
+            INNER_TEXT => Message::Text(String::from_utf8(raw).unwrap()),
+            INNER_BINARY => Message::Binary(raw),
+            inner => panic!("unknown inner kind {inner}"),
+        }
+    }
+
+    #[test]
+    fn compressed_frames_round_trip() {
+        let text = r#"{"type":"snapshot","players":[]}"#.repeat(100);
+        let binary: Vec<u8> = (0..4096u32).map(|i| (i % 7) as u8).collect();
+        for codec in [Codec::Deflate, Codec::Zstd] {
+            let c = Compression {
+                codec,
+                threshold: DEFAULT_THRESHOLD,
+            };
+            for msg in [Message::Text(text.clone()), Message::Binary(binary.clone())] {
+                let Message::Binary(packed) = c.apply(msg.clone()) else {
+                    panic!("{codec:?} left a large message uncompressed");
+                };
+                assert_eq!(packed[1], codec.id());
+                assert_eq!(unpack(&packed), msg);
+            }
+        }
+    }
+
+    #[test]
+    fn small_and_incompressible_messages_pass_through() {
+        let c = Compression {
+            codec: Codec::Zstd,
+            threshold: DEFAULT_THRESHOLD,
+        };
+        let small = Message::Text("{}".into());
+        assert_eq!(c.apply(small.clone()), small);
+        // псевдослучайные байты не сжимаются
+        let mut x = 0x9e37_79b9_7f4a_7c15u64;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x as u8
+            })
+            .collect();
+        let noise = Message::Binary(noise);
+        assert_eq!(c.apply(noise.clone()), noise);
+    }
+
+    #[test]
+    fn negotiate_picks_the_first_known_codec() {
+        let offered = |c: &[&str]| c.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            Codec::negotiate(&offered(&["brotli", "zstd", "deflate"])),
+            Some(Codec::Zstd)
+        );
+        assert_eq!(
+            Codec::negotiate(&offered(&["deflate"])),
+            Some(Codec::Deflate)
+        );
+        assert_eq!(Codec::negotiate(&offered(&["brotli"])), None);
+    }
+}
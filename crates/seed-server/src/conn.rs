@@ -0,0 +1,259 @@
+//! Обслуживание WebSocket-соединений: ограниченная очередь исходящих
+//! сообщений и keepalive.
+//!
+//! Очередь держит не больше `OUTBOX_CAPACITY` сообщений; при переполнении
+//! выбрасывается самое старое — медленному клиенту свежий снапшот нужнее
+//! пропущенного. Если клиент не забрал ни одного сообщения, пока очередь
+//! обернулась целиком, он считается зависшим и отключается.
+//!
+//! Сервер пингует клиента каждые `ping_interval`; соединение, от которого
+//! за `timeout` не пришло ничего (включая pong), закрывается.
+//!
+//! Крупные сообщения сжимаются при отправке, если клиент это согласовал
+//! (см. `compress`).
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::ws::{CloseFrame, Message};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::info;
+
+use crate::compress::Compression;
+
+/// Сколько исходящих сообщений ждёт отправки у одного клиента
+pub const OUTBOX_CAPACITY: usize = 256;
+/// Сколько ждать отправки очереди перед закрытием с причиной
+const CLOSE_FLUSH: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Heartbeat {
+    pub ping_interval: Duration,
+    pub timeout: Duration,
+}
+
+/// Очередь исходящих сообщений клиента; клоны пишут в одну очередь
+#[derive(Debug, Clone, Default)]
+pub struct Outbox {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    queue: Mutex<Queue>,
+    // Появилось сообщение или очередь закрыта
+    ready: Notify,
+    closed: AtomicBool,
+    closed_notify: Notify,
+    compression: Mutex<Option<Compression>>,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    messages: VecDeque<Message>,
+    // Выброшено подряд без единого `recv`
+    dropped: usize,
+}
+
+impl Outbox {
+    /// Ставит сообщение в очередь; false — соединение закрыто
+    pub fn send(&self, msg: Message) -> bool {
+        if self.is_closed() {
+            return false;
+        }
+        let stalled = {
+            let mut q = self.inner.queue.lock().unwrap();
+            q.messages.push_back(msg);
+            if q.messages.len() > OUTBOX_CAPACITY {
+                q.messages.pop_front();
+                q.dropped += 1;
+            }
+            q.dropped >= OUTBOX_CAPACITY
+        };
+        if stalled {
+            info!("Client outbox stalled, disconnecting");
+            self.close();
+            return false;
+        }
+        self.inner.ready.notify_one();
+        true
+    }
+
+    /// Следующее сообщение; None — очередь закрыта
+    async fn recv(&self) -> Option<Message> {
+        loop {
+            if self.is_closed() {
+                return None;
+            }
+            {
+                let mut q = self.inner.queue.lock().unwrap();
+                if let Some(msg) = q.messages.pop_front() {
+                    q.dropped = 0;
+                    return Some(msg);
+                }
+            }
+            self.inner.ready.notified().await;
+        }
+    }
+
+    pub fn close(&self) {
+        self.inner.closed.store(true, Ordering::Release);
+        self.inner.ready.notify_one();
+        self.inner.closed_notify.notify_waiters();
+    }
+
+    /// Сжимать ли крупные сообщения, начиная с ещё не отправленных
+    pub fn set_compression(&self, compression: Option<Compression>) {
+        *self.inner.compression.lock().unwrap() = compression;
+    }
+
+    fn compress(&self, msg: Message) -> Message {
+        let compression = *self.inner.compression.lock().unwrap();
+        match compression {
+            Some(c) => c.apply(msg),
+            None => msg,
+        }
+    }
+
+    /// Та же очередь, а не другая с теми же сообщениями
+    pub fn same(&self, other: &Outbox) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::Acquire)
+    }
+
+    /// Забирает всё, что ждёт отправки
+    #[cfg(test)]
+    pub fn drain(&self) -> Vec<Message> {
+        let mut q = self.inner.queue.lock().unwrap();
+        q.dropped = 0;
+        q.messages.drain(..).collect()
+    }
+
+    /// Дожидается закрытия очереди
+    async fn closed(&self) {
+        loop {
+            let notified = self.inner.closed_notify.notified();
+            if self.is_closed() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Пишет очередь в сокет и пингует клиента, пока очередь не закрыта
+pub async fn pump<S>(mut sink: S, outbox: Outbox, heartbeat: Heartbeat)
+where
+    S: Sink<Message> + Unpin,
+{
+    let mut ping = tokio::time::interval(heartbeat.ping_interval);
+    ping.tick().await;
+    loop {
+        let msg = tokio::select! {
+            msg = outbox.recv() => match msg {
+                Some(msg) => outbox.compress(msg),
+                None => break,
+            },
+            _ = ping.tick() => Message::Ping(Vec::new()),
+        };
+        let closing = matches!(msg, Message::Close(_));
+        if sink.send(msg).await.is_err() || closing {
+            break;
+        }
+    }
+    outbox.close();
+    let _ = sink.close().await;
+}
+
+/// Завершает отправку. С `close` очередь сперва дописывается в сокет вместе
+/// с кадром закрытия (не дольше `CLOSE_FLUSH`), чтобы клиент узнал причину
+pub async fn finish(outbox: &Outbox, pump: JoinHandle<()>, close: Option<CloseFrame<'static>>) {
+    let Some(frame) = close else {
+        pump.abort();
+        return;
+    };
+    outbox.send(Message::Close(Some(frame)));
+    let abort = pump.abort_handle();
+    if tokio::time::timeout(CLOSE_FLUSH, pump).await.is_err() {
+        abort.abort();
+    }
+}
+
+/// Следующее сообщение клиента; None — клиент отключился, молчит дольше
+/// `timeout` или его очередь зависла
+pub async fn next_message<S, E>(
+    stream: &mut S,
+    outbox: &Outbox,
+    heartbeat: Heartbeat,
+) -> Option<Message>
+where
+    S: Stream<Item = Result<Message, E>> + Unpin,
+{
+    tokio::select! {
+        msg = tokio::time::timeout(heartbeat.timeout, stream.next()) => match msg {
+            Ok(Some(Ok(msg))) => Some(msg),
+            Ok(_) => None,
+            Err(_) => {
+                info!("Client timed out after {:?}", heartbeat.timeout);
+                None
+            }
+        },
+        _ = outbox.closed() => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(i: usize) -> Message {
+        Message::Text(i.to_string())
+    }
+
+    #[test]
+    fn overflow_drops_the_oldest_message() {
+        let outbox = Outbox::default();
+        for i in 0..OUTBOX_CAPACITY + 3 {
+            assert!(outbox.send(text(i)));
+        }
+        let queued = outbox.drain();
+        assert_eq!(queued.len(), OUTBOX_CAPACITY);
+        assert_eq!(queued[0], text(3));
+        assert_eq!(queued[OUTBOX_CAPACITY - 1], text(OUTBOX_CAPACITY + 2));
+        assert!(!outbox.is_closed());
+    }
+
+    #[test]
+    fn client_that_reads_nothing_is_disconnected() {
+        let outbox = Outbox::default();
+        // очередь полна, и ещё столько же выброшено без единого чтения
+        for i in 0..2 * OUTBOX_CAPACITY - 1 {
+            assert!(outbox.send(text(i)));
+        }
+        assert!(!outbox.send(text(0)));
+        assert!(outbox.is_closed());
+        assert!(!outbox.send(text(1)));
+    }
+
+    #[tokio::test]
+    async fn reading_resets_the_stall_count() {
+        let outbox = Outbox::default();
+        for i in 0..2 * OUTBOX_CAPACITY - 1 {
+            outbox.send(text(i));
+        }
+        assert_eq!(outbox.recv().await, Some(text(OUTBOX_CAPACITY - 1)));
+        for i in 0..OUTBOX_CAPACITY {
+            assert!(outbox.send(text(i)));
+        }
+        assert!(!outbox.is_closed());
+        outbox.close();
+        assert_eq!(outbox.recv().await, None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Расширение permessage-deflate (RFC 7692) у `/ws`, фича
+//! `permessage-deflate`. tungstenite, на котором стоят WebSocket'ы axum, его
+//! не поддерживает, поэтому с фичей соединения `/ws` поднимает yawc, а
+//! `DeflateSocket` переводит его кадры в `Message` axum: очередь, keepalive
+//! и разбор сообщений не знают, какой стек под ними.
+//!
+//! Расширение согласуется, если клиент предложил его в
+//! `Sec-WebSocket-Extensions` (браузеры предлагают всегда), иначе соединение
+//! обычное. Так сжимается каждое сообщение в обе стороны, и сжатие крупных
+//! сообщений из `compress` такому клиенту не нужно — кодеки в `join` можно
+//! не перечислять.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use axum::body::Body;
+use axum::extract::ws::{CloseFrame, Message};
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::response::Response;
+use futures_util::{Sink, Stream};
+use tracing::info;
+use yawc::close::CloseCode;
+use yawc::{Frame, HttpWebSocket, OpCode, Options, WebSocket};
+
+/// Ответ `101` на запрос апгрейда и соединение, которое поднимется после
+/// него; None — апгрейд не удался
+pub fn upgrade(
+    req: Request,
+) -> Result<(Response, impl Future<Output = Option<DeflateSocket>>), (StatusCode, String)> {
+    let options = Options::default().with_balanced_compression().with_utf8();
+    let (res, upgrade) = WebSocket::upgrade_with_options(req, options)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let socket = async move {
+        match upgrade.await {
+            Ok(ws) => Some(DeflateSocket(ws)),
+            Err(e) => {
+                info!("WebSocket upgrade failed: {}", e);
+                None
+            }
+        }
+    };
+    Ok((res.map(Body::new), socket))
+}
+
+/// Соединение yawc как поток и приёмник `Message` axum
+pub struct DeflateSocket(HttpWebSocket);
+
+impl Stream for DeflateSocket {
+    type Item = Result<Message, axum::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // куски сообщения yawc собирает сам; ошибка кадра кончает поток
+        let frame = ready!(Pin::new(&mut self.0).poll_next(cx));
+        Poll::Ready(frame.map(|f| Ok(to_message(f))))
+    }
+}
+
+impl Sink<Message> for DeflateSocket {
+    type Error = axum::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.0)
+            .poll_ready(cx)
+            .map_err(axum::Error::new)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, msg: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut self.0)
+            .start_send(to_frame(msg))
+            .map_err(axum::Error::new)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.0)
+            .poll_flush(cx)
+            .map_err(axum::Error::new)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.0)
+            .poll_close(cx)
+            .map_err(axum::Error::new)
+    }
+}
+
+fn to_message(frame: Frame) -> Message {
+    match frame.opcode() {
+        // UTF-8 проверяет yawc (`with_utf8`)
+        OpCode::Text => Message::Text(String::from_utf8_lossy(frame.payload()).into_owned()),
+        OpCode::Ping => Message::Ping(frame.into_payload().to_vec()),
+        OpCode::Pong => Message::Pong(frame.into_payload().to_vec()),
+        OpCode::Close => Message::Close(frame.close_code().map(|code| {
+            CloseFrame {
+                code: code.into(),
+                reason: frame
+                    .close_reason()
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+                    .to_owned()
+                    .into(),
+            }
+        })),
+        OpCode::Binary | OpCode::Continuation => Message::Binary(frame.into_payload().to_vec()),
+    }
+}
+
+fn to_frame(msg: Message) -> Frame {
+    match msg {
+        Message::Text(text) => Frame::text(text),
+        Message::Binary(data) => Frame::binary(data),
+        Message::Ping(data) => Frame::ping(data),
+        Message::Pong(data) => Frame::pong(data),
+        Message::Close(Some(close)) => {
+            Frame::close(CloseCode::from(close.code), close.reason.as_bytes())
+        }
+        Message::Close(None) => Frame::close(CloseCode::Normal, []),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum::Router;
+    use flate2::{Decompress, FlushDecompress};
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    /// Отвечает каждым текстовым сообщением обратно
+    async fn echo(req: Request) -> Result<Response, (StatusCode, String)> {
+        let (res, socket) = upgrade(req)?;
+        tokio::spawn(async move {
+            let mut socket = socket.await.expect("upgraded");
+            while let Some(Ok(msg)) = socket.next().await {
+                if let Message::Text(_) = msg {
+                    socket.send(msg).await.unwrap();
+                }
+            }
+        });
+        Ok(res)
+    }
+
+    async fn read_head(stream: &mut TcpStream) -> String {
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+        String::from_utf8(head).unwrap()
+    }
+
+    #[tokio::test]
+    async fn negotiates_and_compresses_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/ws", get(echo));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "GET /ws HTTP/1.1\r\nHost: {addr}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+             Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+             Sec-WebSocket-Extensions: permessage-deflate\r\n\r\n"
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let head = read_head(&mut stream).await.to_ascii_lowercase();
+        assert!(head.starts_with("http/1.1 101"), "{head}");
+        assert!(
+            head.contains("sec-websocket-extensions: permessage-deflate"),
+            "{head}"
+        );
+
+        // клиент шлёт несжатый кадр с маской, это разрешено и с расширением
+        let text = r#"{"type":"snapshot","players":[]}"#.repeat(64);
+        let mask = [1u8, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | 126];
+        frame.extend_from_slice(&(text.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&mask);
+        frame.extend(text.bytes().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        stream.write_all(&frame).await.unwrap();
+
+        // ответ сервера: FIN, RSV1 (сжат) и текст
+        let first = stream.read_u8().await.unwrap();
+        assert_eq!(first, 0x80 | 0x40 | 0x1);
+        let len = match stream.read_u8().await.unwrap() {
+            126 => stream.read_u16().await.unwrap() as usize,
+            len => len as usize,
+        };
+        assert!(len < text.len() / 4, "{len} bytes is barely compressed");
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).await.unwrap();
+        payload.extend_from_slice(&[0x00, 0x00, 0xff, 0xff]);
+        let mut raw = Vec::with_capacity(text.len() * 2);
+        Decompress::new(false)
+            .decompress_vec(&payload, &mut raw, FlushDecompress::Sync)
+            .unwrap();
+        assert_eq!(String::from_utf8(raw).unwrap(), text);
+    }
+}
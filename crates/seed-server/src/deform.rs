@@ -0,0 +1,196 @@
+//! Следы и деформация поверхности (`interaction` в конфиге). Клиенты шлют
+//! `footprint` (след ноги или волока/колеса из `trackTypes`) и `deform`
+//! (вмятина радиуса `radius` и глубины `depth`); сервер проверяет их,
+//! складывает в слой деформации по чанкам объектов и на ближайшем тике
+//! рассылает `deform_delta` тем, у кого чанк загружен. При загрузке чанка
+//! клиент получает его слой целиком (`deform_chunk`).
+//!
+//! Проверки: поверхность под точкой — материал из `supportMaterials`
+//! (категория материала клетки из `SurfaceMap`: скала на крутых склонах,
+//! снег выше снеговой линии, песок на пляжах, иначе материал биома), точка
+//! рядом с игроком. Сам слой — `seed_core::DeformationLayer`: он решает,
+//! держит ли материал след, насколько тот глубок и когда выцветет, урезает
+//! вмятины по `maxDeformationDepthMeters` и вытесняет старые следы сверх
+//! `maxDecalsPerChunk`. Выцветшие следы сервер убирает раз в секунду и
+//! рассылает их id; клиент гасит следы по `expires_ms` и сам. Слой живёт в
+//! памяти мира до перезапуска.
+//!
+//! Событие — от игрока, вошедшего через это соединение; до `join` оно
+//! отклоняется с `not_joined`. Координаты — метры в системе игроков (см.
+//! `objects`).
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use seed_config::WorldConfig;
+use seed_core::{
+    compute_climate_map, compute_surface_map, BiomeMap, DeformError, DeformationLayer, Heightmap,
+    SurfaceMap,
+};
+
+use crate::objects::{ChunkKey, CHUNK_CELLS};
+use crate::protocol::Feature;
+use crate::{ServerMessage, WorldState};
+
+/// Как далеко от своего игрока клиент может оставить след, м
+const MAX_REACH_M: f32 = 8.0;
+/// Событий от одного игрока за тик; остальные отбрасываются
+const MAX_EVENTS_PER_TICK: usize = 4;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct DeformLayer {
+    layer: DeformationLayer,
+    events: HashMap<String, usize>,
+    last_prune: Option<Instant>,
+}
+
+impl DeformLayer {
+    /// Пустой слой с чанками объектов карты `map_width` клеток
+    pub fn new(cfg: &WorldConfig, map_width: u32) -> Self {
+        let cell_m = cfg.scale.region_size_km * 1000.0 / map_width.max(1) as f64;
+        DeformLayer {
+            layer: DeformationLayer::new(cfg, cell_m * CHUNK_CELLS as f64),
+            events: HashMap::new(),
+            last_prune: None,
+        }
+    }
+
+    /// Слой чанка целиком; None — в чанке ничего нет
+    pub fn chunk_message(&self, (cx, cy): ChunkKey) -> Option<ServerMessage> {
+        let state = self.layer.chunk_state((cx, cy))?;
+        Some(ServerMessage::DeformChunk {
+            chunk_x: cx,
+            chunk_y: cy,
+            stamps: state.stamps,
+            decals: state.decals,
+        })
+    }
+
+    fn count_event(&mut self, client_id: &str) -> Result<(), &'static str> {
+        let count = self.events.entry(client_id.to_string()).or_default();
+        *count += 1;
+        if *count > MAX_EVENTS_PER_TICK {
+            return Err("rate_limited");
+        }
+        Ok(())
+    }
+}
+
+/// Клетка карты под точкой; None — вне карты
+fn locate(world: &WorldState, x: f32, z: f32) -> Option<(u32, u32)> {
+    let hm = &world.heightmap;
+    if hm.width == 0 || !x.is_finite() || !z.is_finite() || x < 0.0 || z < 0.0 {
+        return None;
+    }
+    let cell_m = world.config.scale.region_size_km * 1000.0 / hm.width as f64;
+    let (cx, cy) = (
+        (x as f64 / cell_m).floor() as u32,
+        (z as f64 / cell_m).floor() as u32,
+    );
+    if cx >= hm.width || cy >= hm.height {
+        return None;
+    }
+    Some((cx, cy))
+}
+
+/// Материалы поверхности мира; пересчитываются, когда меняется рельеф
+pub fn surface_map(cfg: &WorldConfig, hm: &Heightmap, bm: &BiomeMap) -> SurfaceMap {
+    compute_surface_map(cfg, hm, bm, &compute_climate_map(cfg, hm))
+}
+
+/// Общие проверки события: игрок есть, точка рядом с ним, на карте и на
+/// поддерживаемой поверхности; возвращает клетку под точкой
+fn check_event(
+    world: &WorldState,
+    client_id: &str,
+    x: f32,
+    z: f32,
+) -> Result<(u32, u32), &'static str> {
+    let p = world.players.get(client_id).ok_or("not_joined")?;
+    if p.spectator() {
+        return Err("spectator");
+    }
+    let (cx, cy) = locate(world, x, z).ok_or("out_of_bounds")?;
+    if (x - p.x).hypot(z - p.z) > MAX_REACH_M {
+        return Err("out_of_reach");
+    }
+    let cfg = &world.config;
+    let category = world.surface.category_at(cfg, cx, cy);
+    let supported = &cfg.interaction.surface_deformation.support_materials;
+    if !supported.iter().any(|m| m == category) {
+        return Err("unsupported_surface");
+    }
+    Ok((cx, cy))
+}
+
+/// След игрока `client_id`
+pub fn footprint(
+    world: &mut WorldState,
+    client_id: &str,
+    kind: &str,
+    x: f32,
+    z: f32,
+    heading: f32,
+) -> Result<(), &'static str> {
+    let (cx, cy) = check_event(world, client_id, x, z)?;
+    world.deform.count_event(client_id)?;
+    let material = world.surface.material_at(&world.config, cx, cy);
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    world
+        .deform
+        .layer
+        .footprint(kind, x, z, heading, material, now_ms)
+        .map_err(DeformError::code)?;
+    Ok(())
+}
+
+/// Вмятина; глубина урезается до `maxDeformationDepthMeters` в её центре
+pub fn deform(
+    world: &mut WorldState,
+    client_id: &str,
+    x: f32,
+    z: f32,
+    radius: f32,
+    depth: f32,
+) -> Result<(), &'static str> {
+    check_event(world, client_id, x, z)?;
+    world.deform.count_event(client_id)?;
+    world
+        .deform
+        .layer
+        .stamp(x, z, radius, depth)
+        .map_err(DeformError::code)?;
+    Ok(())
+}
+
+/// Рассылает накопленные изменения и убирает выцветшие следы
+pub fn flush(world: &mut WorldState) {
+    let WorldState {
+        clients, deform, ..
+    } = world;
+    deform.events.clear();
+
+    let now = Instant::now();
+    if deform.last_prune.is_none_or(|t| now - t >= PRUNE_INTERVAL) {
+        deform.last_prune = Some(now);
+        deform.layer.fade(chrono::Utc::now().timestamp_millis());
+    }
+
+    for ((cx, cy), delta) in deform.layer.take_deltas() {
+        let Some(msg) = (ServerMessage::DeformDelta {
+            chunk_x: cx,
+            chunk_y: cy,
+            stamps: delta.stamps,
+            decals: delta.decals,
+            removed_decals: delta.removed_decals,
+        })
+        .to_text() else {
+            continue;
+        };
+        let clients = clients.values();
+        for c in clients.filter(|c| c.chunks.has((cx, cy)) && c.features.has(Feature::Deform)) {
+            c.sender.send(msg.clone());
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Зона интереса клиента (`simulation.network.regionRadiusKmActive`):
+//! в снапшот клиента попадают только игроки в радиусе от его игрока.
+//! Расстояние считается по горизонтали (x, z; y — вверх), позиции — в метрах.
+//!
+//! Чтобы игрок на границе не мигал, у зоны есть гистерезис: войти можно
+//! ближе `enter_m`, а выпадает игрок только дальше `exit_m`. Кандидатов
+//! ищем по сетке с клеткой `exit_m` — соседние 3×3 клетки покрывают радиус,
+//! поэтому рассылка стоит O(игроки × соседи), а не O(n²).
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+use crate::PlayerState;
+
+/// Насколько радиус выхода больше радиуса входа
+const HYSTERESIS: f64 = 0.1;
+/// Сколько последних видимых множеств помнить — как история снапшотов
+const VIEW_HISTORY_LEN: usize = 64;
+
+pub type Visible = Arc<HashSet<String>>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Interest {
+    pub enter_m: f64,
+    pub exit_m: f64,
+}
+
+impl Interest {
+    /// `None` при нулевом радиусе — клиенты видят всех
+    pub fn from_radius_km(radius_km: f64) -> Option<Self> {
+        (radius_km > 0.0).then_some(Self {
+            enter_m: radius_km * 1000.0,
+            exit_m: radius_km * 1000.0 * (1.0 + HYSTERESIS),
+        })
+    }
+}
+
+/// Игроки по клеткам горизонтальной сетки
+pub struct SpatialGrid<'a> {
+    cell_m: f64,
+    cells: HashMap<(i64, i64), Vec<&'a PlayerState>>,
+}
+
+impl<'a> SpatialGrid<'a> {
+    pub fn build(players: &'a HashMap<String, PlayerState>, cell_m: f64) -> Self {
+        let mut cells: HashMap<(i64, i64), Vec<&PlayerState>> = HashMap::new();
+        for p in players.values() {
+            cells.entry(cell_of(p, cell_m)).or_default().push(p);
+        }
+        Self { cell_m, cells }
+    }
+
+    /// Игроки в клетке `p` и восьми соседних
+    fn near(&self, p: &PlayerState) -> impl Iterator<Item = &'a PlayerState> + '_ {
+        let (cx, cz) = cell_of(p, self.cell_m);
+        (-1..=1)
+            .flat_map(move |dx| (-1..=1).map(move |dz| (cx + dx, cz + dz)))
+            .filter_map(|c| self.cells.get(&c))
+            .flatten()
+            .copied()
+    }
+}
+
+fn cell_of(p: &PlayerState, cell_m: f64) -> (i64, i64) {
+    (
+        (p.x as f64 / cell_m).floor() as i64,
+        (p.z as f64 / cell_m).floor() as i64,
+    )
+}
+
+/// Кого видит клиент и кого видел в недавних снапшотах (для базы дельты)
+#[derive(Debug, Default)]
+pub struct ClientView {
+    visible: HashSet<String>,
+    sent: VecDeque<(u32, Visible)>,
+}
+
+impl ClientView {
+    /// Видимые игроки в снапшоте `seq` для клиента с игроком `me`
+    pub fn update(
+        &mut self,
+        seq: u32,
+        me: &PlayerState,
+        grid: &SpatialGrid,
+        interest: Interest,
+    ) -> Visible {
+        let mut visible: HashSet<String> = grid
+            .near(me)
+            .filter(|p| {
+                let d = (p.x as f64 - me.x as f64).hypot(p.z as f64 - me.z as f64);
+                d <= interest.enter_m || (d <= interest.exit_m && self.visible.contains(&p.id))
+            })
+            .map(|p| p.id.clone())
+            .collect();
+        visible.insert(me.id.clone());
+        self.visible = visible;
+
+        let visible = Arc::new(self.visible.clone());
+        if self.sent.len() == VIEW_HISTORY_LEN {
+            self.sent.pop_front();
+        }
+        self.sent.push_back((seq, visible.clone()));
+        visible
+    }
+
+    /// Кого клиент видит в последнем снапшоте
+    pub fn visible(&self) -> &HashSet<String> {
+        &self.visible
+    }
+
+    /// Кого клиент видел в снапшоте `seq`
+    pub fn at(&self, seq: u32) -> Option<Visible> {
+        self.sent
+            .iter()
+            .find(|(s, _)| *s == seq)
+            .map(|(_, v)| v.clone())
+    }
+}
+
+/// Только видимые игроки
+pub fn filter(
+    players: &HashMap<String, PlayerState>,
+    visible: &HashSet<String>,
+) -> HashMap<String, PlayerState> {
+    players
+        .iter()
+        .filter(|(id, _)| visible.contains(*id))
+        .map(|(id, p)| (id.clone(), p.clone()))
+        .collect()
+}
@@ -0,0 +1,253 @@
+//! Защита от злонамеренных и сломанных клиентов: лимит одновременных
+//! WebSocket-соединений (игровых и relay), комнат relay и входящего
+//! трафика каждого соединения. Ноль в любом лимите — без ограничения.
+//!
+//! Входящий трафик считается корзиной токенов по сообщениям и по байтам с
+//! запасом на секунду лимита. Первое превышение — предупреждение (`error`
+//! с `rate_limited`), сообщение отбрасывается; ещё `WARN_GRACE` лишнее
+//! просто отбрасывается, чтобы клиент успел притормозить. Превышение после
+//! этого, пока не прошло `WARN_COOLDOWN` с предупреждения, — отключение.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::ws::Message;
+
+const WARN_GRACE: Duration = Duration::from_secs(1);
+const WARN_COOLDOWN: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_rooms: usize,
+    pub messages_per_s: u32,
+    pub bytes_per_s: u32,
+    /// Наибольший кадр relay, байт
+    pub max_relay_frame: usize,
+}
+
+/// Счётчик открытых соединений
+#[derive(Debug)]
+pub struct Connections {
+    open: AtomicUsize,
+    max: usize,
+}
+
+/// Место под соединение; освобождается при удалении
+#[derive(Debug)]
+pub struct ConnectionGuard(Arc<Connections>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.open.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl Connections {
+    pub fn new(max: usize) -> Arc<Self> {
+        Arc::new(Self {
+            open: AtomicUsize::new(0),
+            max,
+        })
+    }
+
+    /// Открытые соединения
+    pub fn open(&self) -> usize {
+        self.open.load(Ordering::Acquire)
+    }
+
+    /// None — соединений уже `max`
+    pub fn acquire(self: &Arc<Self>) -> Option<ConnectionGuard> {
+        let max = if self.max == 0 { usize::MAX } else { self.max };
+        self.open
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .ok()?;
+        Some(ConnectionGuard(self.clone()))
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+}
+
+impl Bucket {
+    fn new(rate: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+        }
+    }
+
+    fn refill(&mut self, dt: f64) {
+        self.tokens = (self.tokens + self.rate * dt).min(self.rate);
+    }
+
+    fn fits(&self, n: f64) -> bool {
+        self.rate <= 0.0 || self.tokens >= n
+    }
+
+    fn take(&mut self, n: f64) {
+        self.tokens -= n;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    /// Первое превышение: сообщение отброшено, клиента надо предупредить
+    Warn,
+    /// Отброшено молча — клиент уже предупреждён
+    Drop,
+    Disconnect,
+}
+
+/// Лимит входящего трафика одного соединения
+#[derive(Debug)]
+pub struct RateLimiter {
+    messages: Bucket,
+    bytes: Bucket,
+    last: Instant,
+    warned: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: &Limits) -> Self {
+        Self {
+            messages: Bucket::new(limits.messages_per_s),
+            bytes: Bucket::new(limits.bytes_per_s),
+            last: Instant::now(),
+            warned: None,
+        }
+    }
+
+    /// Учитывает входящее сообщение; служебные кадры (ping, close) не считаются
+    pub fn check(&mut self, msg: &Message) -> Verdict {
+        let len = match msg {
+            Message::Text(t) => t.len(),
+            Message::Binary(b) => b.len(),
+            _ => return Verdict::Pass,
+        };
+        let now = Instant::now();
+        let dt = (now - self.last).as_secs_f64();
+        self.last = now;
+        self.messages.refill(dt);
+        self.bytes.refill(dt);
+        if self.warned.is_some_and(|t| now - t >= WARN_COOLDOWN) {
+            self.warned = None;
+        }
+        // сообщение крупнее секундного лимита проходит только при полной корзине
+        let len = (len as f64).min(self.bytes.rate);
+        if self.messages.fits(1.0) && self.bytes.fits(len) {
+            self.messages.take(1.0);
+            self.bytes.take(len);
+            return Verdict::Pass;
+        }
+        match self.warned {
+            None => {
+                self.warned = Some(now);
+                Verdict::Warn
+            }
+            Some(t) if now - t < WARN_GRACE => Verdict::Drop,
+            Some(_) => Verdict::Disconnect,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(messages_per_s: u32, bytes_per_s: u32) -> RateLimiter {
+        RateLimiter::new(&Limits {
+            max_rooms: 0,
+            messages_per_s,
+            bytes_per_s,
+            max_relay_frame: 0,
+        })
+    }
+
+    fn text(len: usize) -> Message {
+        Message::Text("x".repeat(len))
+    }
+
+    /// Сдвигает часы лимитера на `by` назад
+    fn rewind(r: &mut RateLimiter, by: Duration) {
+        r.last -= by;
+        if let Some(t) = &mut r.warned {
+            *t -= by;
+        }
+    }
+
+    #[test]
+    fn burst_over_the_limit_warns_then_drops_then_disconnects() {
+        let mut r = limiter(5, 0);
+        for _ in 0..5 {
+            assert_eq!(r.check(&text(1)), Verdict::Pass);
+        }
+        assert_eq!(r.check(&text(1)), Verdict::Warn);
+        assert_eq!(r.check(&text(1)), Verdict::Drop);
+        // служебные кадры не считаются
+        assert_eq!(r.check(&Message::Ping(vec![])), Verdict::Pass);
+
+        // корзина не успела наполниться, а отсрочка вышла
+        r.warned = r.warned.map(|t| t - WARN_GRACE);
+        assert_eq!(r.check(&text(1)), Verdict::Disconnect);
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let mut r = limiter(2, 0);
+        r.check(&text(1));
+        r.check(&text(1));
+        assert_eq!(r.check(&text(1)), Verdict::Warn);
+        rewind(&mut r, Duration::from_millis(500));
+        assert_eq!(r.check(&text(1)), Verdict::Pass);
+        assert_eq!(r.check(&text(1)), Verdict::Drop);
+
+        // давнее предупреждение забыто: снова сначала предупреждение
+        rewind(&mut r, WARN_COOLDOWN);
+        for _ in 0..2 {
+            assert_eq!(r.check(&text(1)), Verdict::Pass);
+        }
+        assert_eq!(r.check(&text(1)), Verdict::Warn);
+    }
+
+    #[test]
+    fn bytes_are_limited_separately() {
+        let mut r = limiter(0, 100);
+        assert_eq!(r.check(&text(60)), Verdict::Pass);
+        assert_eq!(r.check(&text(60)), Verdict::Warn);
+
+        // сообщение крупнее секундного лимита проходит при полной корзине
+        let mut r = limiter(0, 100);
+        assert_eq!(r.check(&text(500)), Verdict::Pass);
+        assert_eq!(r.check(&text(1)), Verdict::Warn);
+    }
+
+    #[test]
+    fn zero_means_unlimited() {
+        let mut r = limiter(0, 0);
+        for _ in 0..10_000 {
+            assert_eq!(r.check(&text(1_000)), Verdict::Pass);
+        }
+        let connections = Connections::new(0);
+        let guards: Vec<_> = (0..1_000).filter_map(|_| connections.acquire()).collect();
+        assert_eq!(guards.len(), 1_000);
+    }
+
+    #[test]
+    fn connections_are_capped_until_one_closes() {
+        let connections = Connections::new(2);
+        let a = connections.acquire().unwrap();
+        let _b = connections.acquire().unwrap();
+        assert!(connections.acquire().is_none());
+        assert_eq!(connections.open(), 2);
+        drop(a);
+        assert_eq!(connections.open(), 1);
+        assert!(connections.acquire().is_some());
+    }
+}
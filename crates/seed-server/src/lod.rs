@@ -0,0 +1,281 @@
+//! Рельеф квадродеревом уровней детализации — для плавного приближения от
+//! всей планеты к земле. Узел `(level, x, y)`: на уровне `level` карта
+//! делится на 2^level × 2^level узлов, `x` растёт на восток, `y` — на юг.
+//! Уровень 0 — вся карта, на `max_level` узел примерно равен карте в
+//! полном разрешении.
+//!
+//! Клиент сам выбирает узлы по расстоянию до камеры и запрашивает их
+//! `{"type": "terrain_nodes", "nodes": [[level, x, y], ...]}`; сервер
+//! отвечает `terrain_node` на каждый. В узле `NODE_SAMPLES`×`NODE_SAMPLES`
+//! нормализованных высот построчно с севера; крайние отсчёты соседей одного
+//! уровня совпадают. Отсчёт крупного узла — среднее клеток вокруг него, а
+//! не одна клетка, чтобы издалека рельеф не рябил. `error_m` — наибольшее
+//! отклонение узла от полной карты в метрах: по нему и расстоянию клиент
+//! решает, делить ли узел дальше.
+//!
+//! Сшивка: `edges` (с уровня 1) — края узла так, как их видит сосед на
+//! уровень крупнее: отсчёты родительского уровня, между ними — линейно.
+//! Если сосед со стороны `north`/`east`/`south`/`west` крупнее, клиент
+//! ставит на этот край `edges` вместо своих высот, и трещин нет. Для этого
+//! соседние узлы должны отличаться не больше чем на уровень. `neighbors` —
+//! соседи того же уровня, null — край карты.
+//!
+//! После катастрофы кэш узлов над изменёнными чанками сбрасывается; клиент
+//! перезапрашивает узлы над `chunks` из её `catastrophe`.
+
+use std::collections::{HashMap, VecDeque};
+
+use axum::extract::ws::Message;
+use seed_core::{Heightmap, MAX_RELIEF_M};
+use serde::{Deserialize, Serialize};
+
+use crate::objects::{ChunkKey, CHUNK_CELLS};
+use crate::tiles;
+use crate::{ServerMessage, WorldState};
+
+/// Отсчётов по стороне узла; чётное число интервалов, чтобы отсчёты
+/// родителя попадали на каждый второй
+pub const NODE_SAMPLES: u32 = 33;
+const NODE_INTERVALS: u32 = NODE_SAMPLES - 1;
+/// Больше узлов в одном запросе не отдаём
+pub const MAX_NODES_PER_REQUEST: usize = 64;
+const MAX_CACHED_NODES: usize = 1024;
+
+/// `(level, x, y)`
+pub type NodeKey = (u32, u32, u32);
+
+/// Края узла с уровня крупнее, по `NODE_SAMPLES` высот: север и юг — с
+/// запада на восток, запад и восток — с севера на юг
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeEdges {
+    pub north: Vec<f32>,
+    pub east: Vec<f32>,
+    pub south: Vec<f32>,
+    pub west: Vec<f32>,
+}
+
+/// Соседи того же уровня, `[level, x, y]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeNeighbors {
+    pub north: Option<[u32; 3]>,
+    pub east: Option<[u32; 3]>,
+    pub south: Option<[u32; 3]>,
+    pub west: Option<[u32; 3]>,
+}
+
+/// Уровень, на котором шаг отсчётов не больше клетки карты
+pub fn max_level(hm: &Heightmap) -> u32 {
+    let cells = hm.width.max(hm.height).saturating_sub(1);
+    let mut level = 0;
+    while (cells >> level) > NODE_INTERVALS {
+        level += 1;
+    }
+    level
+}
+
+pub fn valid(hm: &Heightmap, (level, x, y): NodeKey) -> bool {
+    level <= max_level(hm) && x < (1 << level) && y < (1 << level)
+}
+
+/// Готовые узлы мира; самые старые вытесняются
+#[derive(Debug, Default)]
+pub struct NodeCache {
+    nodes: HashMap<NodeKey, Message>,
+    order: VecDeque<NodeKey>,
+}
+
+impl NodeCache {
+    fn get_or_build(&mut self, key: NodeKey, hm: &Heightmap, sea_level: f64) -> Option<Message> {
+        if let Some(msg) = self.nodes.get(&key) {
+            return Some(msg.clone());
+        }
+        let msg = build(hm, sea_level, key).to_text()?;
+        if self.order.len() == MAX_CACHED_NODES {
+            if let Some(old) = self.order.pop_front() {
+                self.nodes.remove(&old);
+            }
+        }
+        self.order.push_back(key);
+        self.nodes.insert(key, msg.clone());
+        Some(msg)
+    }
+}
+
+/// Сообщения `terrain_node` на запрос; Err — запрос отклонён целиком
+pub fn request(world: &mut WorldState, keys: &[[u32; 3]]) -> Result<Vec<Message>, &'static str> {
+    if keys.len() > MAX_NODES_PER_REQUEST {
+        return Err("too_many_nodes");
+    }
+    let hm = &world.heightmap;
+    if !keys.iter().all(|&[l, x, y]| valid(hm, (l, x, y))) {
+        return Err("unknown_node");
+    }
+    let sea_level = world.config.sea_level;
+    Ok(keys
+        .iter()
+        .filter_map(|&[l, x, y]| world.lod_nodes.get_or_build((l, x, y), hm, sea_level))
+        .collect())
+}
+
+/// Забывает узлы над изменёнными чанками (рельеф изменился)
+pub fn invalidate(world: &mut WorldState, chunks: &[ChunkKey]) {
+    let hm = &world.heightmap;
+    let cache = &mut world.lod_nodes;
+    let stale: Vec<NodeKey> = cache
+        .nodes
+        .keys()
+        .copied()
+        .filter(|&key| {
+            let (x0, y0, x1, y1) = footprint(hm, key);
+            chunks.iter().any(|&(cx, cy)| {
+                let (cx0, cy0) = ((cx * CHUNK_CELLS) as f64, (cy * CHUNK_CELLS) as f64);
+                let (cx1, cy1) = (cx0 + CHUNK_CELLS as f64, cy0 + CHUNK_CELLS as f64);
+                // отсчёт на краю узла усредняет клетки по обе стороны
+                x0 <= cx1 && cx0 <= x1 + 1.0 && y0 <= cy1 && cy0 <= y1 + 1.0
+            })
+        })
+        .collect();
+    cache.order.retain(|k| !stale.contains(k));
+    for key in stale {
+        cache.nodes.remove(&key);
+    }
+}
+
+/// Координата `i`-го отсчёта уровня `level` в клетках; через целый номер
+/// отсчёта, чтобы у соседей край совпадал до бита
+fn coord(level: u32, i: u32, cells: u32) -> f64 {
+    let total = (NODE_INTERVALS << level) as f64;
+    i as f64 / total * cells.saturating_sub(1) as f64
+}
+
+/// Шаг отсчётов уровня в клетках по x и y
+fn step(hm: &Heightmap, level: u32) -> (f64, f64) {
+    (coord(level, 1, hm.width), coord(level, 1, hm.height))
+}
+
+/// Прямоугольник узла в клетках: x0, y0, x1, y1
+fn footprint(hm: &Heightmap, (level, x, y): NodeKey) -> (f64, f64, f64, f64) {
+    let (sx, sy) = step(hm, level);
+    let radius = (sx.max(sy) / 2.0).max(1.0);
+    (
+        coord(level, x * NODE_INTERVALS, hm.width) - radius,
+        coord(level, y * NODE_INTERVALS, hm.height) - radius,
+        coord(level, (x + 1) * NODE_INTERVALS, hm.width) + radius,
+        coord(level, (y + 1) * NODE_INTERVALS, hm.height) + radius,
+    )
+}
+
+/// Высота отсчёта уровня `level` с номером (i, j) во всей карте
+fn sample(hm: &Heightmap, level: u32, i: u32, j: u32) -> f32 {
+    let (px, py) = (coord(level, i, hm.width), coord(level, j, hm.height));
+    let (sx, sy) = step(hm, level);
+    if sx <= 1.0 && sy <= 1.0 {
+        return tiles::bilinear(hm, px, py) as f32;
+    }
+    // среднее клеток в окне шага вокруг отсчёта
+    let span = |p: f64, s: f64, n: u32| {
+        let lo = (p - s / 2.0).ceil().max(0.0) as u32;
+        let hi = ((p + s / 2.0).floor() as u32).min(n - 1);
+        (lo, hi.max(lo))
+    };
+    let (x0, x1) = span(px, sx, hm.width);
+    let (y0, y1) = span(py, sy, hm.height);
+    let mut sum = 0.0f64;
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            sum += hm.get(x, y) as f64;
+        }
+    }
+    (sum / ((x1 - x0 + 1) * (y1 - y0 + 1)) as f64) as f32
+}
+
+/// Высоты края с уровня крупнее: `at(k)` — номер `k`-го отсчёта края во
+/// всей карте уровня `level`. Узлы начинаются с чётного отсчёта, так что
+/// чётные `k` — отсчёты родителя, нечётные — между ними
+fn coarse_edge(hm: &Heightmap, level: u32, at: impl Fn(u32) -> (u32, u32)) -> Vec<f32> {
+    let parent = |k: u32| {
+        let (i, j) = at(k);
+        sample(hm, level - 1, i / 2, j / 2)
+    };
+    (0..NODE_SAMPLES)
+        .map(|k| {
+            if k % 2 == 1 {
+                (parent(k - 1) + parent(k + 1)) / 2.0
+            } else {
+                parent(k)
+            }
+        })
+        .collect()
+}
+
+fn build(hm: &Heightmap, sea_level: f64, (level, x, y): NodeKey) -> ServerMessage {
+    let (i0, j0) = (x * NODE_INTERVALS, y * NODE_INTERVALS);
+    let mut heights = Vec::with_capacity((NODE_SAMPLES * NODE_SAMPLES) as usize);
+    for j in 0..NODE_SAMPLES {
+        for i in 0..NODE_SAMPLES {
+            heights.push(sample(hm, level, i0 + i, j0 + j));
+        }
+    }
+
+    let last = NODE_INTERVALS;
+    let edges = (level > 0).then(|| NodeEdges {
+        north: coarse_edge(hm, level, |k| (i0 + k, j0)),
+        south: coarse_edge(hm, level, |k| (i0 + k, j0 + last)),
+        west: coarse_edge(hm, level, |k| (i0, j0 + k)),
+        east: coarse_edge(hm, level, |k| (i0 + last, j0 + k)),
+    });
+
+    let side = 1u32 << level;
+    let node = |dx: i64, dy: i64| {
+        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+        ((0..side as i64).contains(&nx) && (0..side as i64).contains(&ny))
+            .then_some([level, nx as u32, ny as u32])
+    };
+
+    ServerMessage::TerrainNode {
+        level,
+        x,
+        y,
+        leaf: level == max_level(hm),
+        samples: NODE_SAMPLES,
+        error_m: error_m(hm, sea_level, (level, x, y), &heights),
+        heights,
+        edges,
+        neighbors: NodeNeighbors {
+            north: node(0, -1),
+            east: node(1, 0),
+            south: node(0, 1),
+            west: node(-1, 0),
+        },
+    }
+}
+
+/// Наибольшее отклонение клеток узла от его билинейной сетки, м
+fn error_m(hm: &Heightmap, sea_level: f64, (level, x, y): NodeKey, heights: &[f32]) -> f32 {
+    let (sx, sy) = step(hm, level);
+    let (ox, oy) = (
+        coord(level, x * NODE_INTERVALS, hm.width),
+        coord(level, y * NODE_INTERVALS, hm.height),
+    );
+    let (x1, y1) = (
+        ox + sx * NODE_INTERVALS as f64,
+        oy + sy * NODE_INTERVALS as f64,
+    );
+    let mut worst = 0.0f64;
+    for cy in (oy.ceil() as u32)..=(y1.floor() as u32).min(hm.height - 1) {
+        for cx in (ox.ceil() as u32)..=(x1.floor() as u32).min(hm.width - 1) {
+            let (u, v) = ((cx as f64 - ox) / sx, (cy as f64 - oy) / sy);
+            let (i, j) = (
+                (u.floor() as u32).min(NODE_INTERVALS - 1),
+                (v.floor() as u32).min(NODE_INTERVALS - 1),
+            );
+            let (tx, ty) = (u - i as f64, v - j as f64);
+            let at = |i: u32, j: u32| heights[(j * NODE_SAMPLES + i) as usize] as f64;
+            let top = at(i, j) * (1.0 - tx) + at(i + 1, j) * tx;
+            let bottom = at(i, j + 1) * (1.0 - tx) + at(i + 1, j + 1) * tx;
+            let approx = top * (1.0 - ty) + bottom * ty;
+            worst = worst.max((hm.get(cx, cy) as f64 - approx).abs());
+        }
+    }
+    (worst / (1.0 - sea_level).max(1e-6) * MAX_RELIEF_M) as f32
+}
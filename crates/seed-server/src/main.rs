@@ -1,271 +1,1288 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use axum::{
     body::Body,
-    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::ws::{close_code, CloseFrame, Message},
     extract::{Query, State},
     http::{Request, StatusCode},
     response::Response,
-    routing::get,
+    routing::{get, post},
     Router,
 };
-use futures_util::{SinkExt, StreamExt};
-use seed_config::WorldConfig;
-use seed_core::{
-    generate_biome_map_from_config, generate_heightmap_from_config, BiomeMap, Heightmap,
-};
+use clap::Parser;
+use futures_util::{Sink, Stream, StreamExt};
+use seed_config::{Severity, WorldConfig};
+use seed_core::{BiomeMap, Decal, EventBus, Heightmap, ProceduralObject, Stamp, SurfaceMap};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::Mutex;
 use tower::util::ServiceExt;
 use tower_http::services::ServeDir;
 use tracing::{error, info};
 
+mod admin;
+mod api;
+mod catastrophes;
+mod clock;
+mod compress;
+mod conn;
+#[cfg(feature = "permessage-deflate")]
+mod deflate;
+mod deform;
+mod interest;
+mod limits;
+mod lod;
+mod messaging;
+mod objects;
+mod path;
+mod persist;
+mod protocol;
+mod record;
+mod relay;
+mod render;
+mod session;
+mod shutdown;
+mod snapshot;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod sync;
+mod tick;
+mod tiles;
+#[cfg(feature = "tls")]
+mod tls;
+mod weather;
+mod worlds;
+
+use compress::Codec;
+use protocol::Feature;
+use snapshot::SnapshotEncoding;
+use worlds::WorldHandle;
+
+/// Сервер мультиплеера: мир из конфига, WebSocket, REST API и статика
+#[derive(Parser, Debug)]
+#[command(name = "seed-server", version)]
+struct Args {
+    /// Адрес, на котором слушать
+    #[arg(long, env = "SEED_SERVER_HOST", default_value = "0.0.0.0")]
+    host: std::net::IpAddr,
+
+    /// Порт HTTP и WebSocket
+    #[arg(long, env = "SEED_SERVER_PORT", default_value_t = 9000)]
+    port: u16,
+
+    /// PEM-сертификат: слушать HTTPS и wss:// вместо HTTP (сборка с фичей
+    /// `tls`)
+    #[arg(long, env = "SEED_SERVER_TLS_CERT", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM-ключ сертификата `--tls-cert`
+    #[arg(long, env = "SEED_SERVER_TLS_KEY", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Конфиг мира по умолчанию
+    #[arg(long, env = "SEED_SERVER_CONFIG", default_value = "world-config.json")]
+    config: PathBuf,
+
+    /// Сторона карты мира по умолчанию в клетках
+    #[arg(long, env = "SEED_SERVER_MAP_SIZE", default_value_t = worlds::DEFAULT_MAP_SIZE)]
+    map_size: u32,
+
+    /// Каталог статики веб-клиента
+    #[arg(long, env = "SEED_SERVER_WEB_DIR", default_value = "web")]
+    web_dir: PathBuf,
+
+    /// Отдавать статику с COOP/COEP (`same-origin`/`require-corp`), чтобы
+    /// страница получила SharedArrayBuffer и потоки seed-wasm (`parallel`).
+    /// Страницы со скриптами с CDN без CORP при этом не загрузятся
+    #[arg(long, env = "SEED_SERVER_CROSS_ORIGIN_ISOLATION")]
+    cross_origin_isolation: bool,
+
+    /// Куда сохранять миры и игроков
+    #[arg(long, env = "SEED_SERVER_DATA_DIR", default_value = "server-data")]
+    data_dir: PathBuf,
+
+    /// Хранилище миров: `file` (JSON и bincode в data_dir), `sqlite`
+    /// (`world.sqlite` в каталоге мира, сборка с фичей `sqlite`) или
+    /// `memory` (ничего не переживает перезапуск)
+    #[arg(long, env = "SEED_SERVER_STORE", value_enum, default_value = "file")]
+    store: persist::StoreKind,
+
+    /// Как часто пинговать клиентов, секунды
+    #[arg(long, env = "SEED_SERVER_PING_INTERVAL", default_value_t = 15)]
+    ping_interval: u64,
+
+    /// Через сколько секунд тишины отключать клиента
+    #[arg(long, env = "SEED_SERVER_CLIENT_TIMEOUT", default_value_t = 45)]
+    client_timeout: u64,
+
+    /// Сколько секунд держать игрока в мире после обрыва связи, ожидая
+    /// возобновления сессии; 0 — убирать сразу
+    #[arg(long, env = "SEED_SERVER_RESUME_GRACE", default_value_t = session::DEFAULT_RESUME_GRACE_S)]
+    resume_grace: u64,
+
+    /// Одновременных WebSocket-соединений (игровых и relay); 0 — без лимита
+    #[arg(long, env = "SEED_SERVER_MAX_CONNECTIONS", default_value_t = 512)]
+    max_connections: usize,
+
+    /// Комнат relay; 0 — без лимита
+    #[arg(long, env = "SEED_SERVER_MAX_ROOMS", default_value_t = 64)]
+    max_rooms: usize,
+
+    /// Входящих сообщений в секунду на соединение; 0 — без лимита
+    #[arg(long, env = "SEED_SERVER_MAX_MESSAGES_PER_SEC", default_value_t = 120)]
+    max_messages_per_sec: u32,
+
+    /// Входящих байт в секунду на соединение; 0 — без лимита
+    #[arg(long, env = "SEED_SERVER_MAX_BYTES_PER_SEC", default_value_t = 1 << 20)]
+    max_bytes_per_sec: u32,
+
+    /// Наибольший кадр relay в байтах; больший закрывает соединение
+    #[arg(long, env = "SEED_SERVER_MAX_RELAY_FRAME", default_value_t = 1 << 20)]
+    max_relay_frame: usize,
+
+    /// Сколько секунд комната relay ждёт ушедшего host, прежде чем закрыться
+    #[arg(long, env = "SEED_SERVER_RELAY_ROOM_TTL", default_value_t = relay::DEFAULT_ROOM_TTL_S)]
+    relay_room_ttl: u64,
+
+    /// Сжимать сообщения от этого размера в байтах клиентам, согласовавшим
+    /// сжатие; 0 — не сжимать
+    #[arg(long, env = "SEED_SERVER_COMPRESS_THRESHOLD", default_value_t = compress::DEFAULT_THRESHOLD)]
+    compress_threshold: usize,
+
+    /// Записывать сессию мира по умолчанию в файл (см. `--replay`)
+    #[arg(long, env = "SEED_SERVER_RECORD")]
+    record: Option<PathBuf>,
+
+    /// Воспроизвести запись сессии, сверить её с записанной и выйти
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+
+    /// Сколько секунд предупреждать клиентов перед остановкой
+    #[arg(long, env = "SEED_SERVER_SHUTDOWN_GRACE", default_value_t = shutdown::DEFAULT_GRACE_S)]
+    shutdown_grace: u64,
+
+    /// Токен API администратора (`/api/admin`); без него API выключено
+    #[arg(long, env = "SEED_SERVER_ADMIN_TOKEN")]
+    admin_token: Option<String>,
+
+    /// Проверить конфиг и настройки и выйти, не запуская сервер
+    #[arg(long)]
+    check: bool,
+}
+
 #[derive(Clone)]
 struct AppState {
-    world: Arc<Mutex<WorldState>>,
-    relay: Arc<Mutex<RelayState>>,
+    worlds: Arc<worlds::Worlds>,
+    web_dir: Arc<PathBuf>,
+    cross_origin_isolation: bool,
+    heartbeat: conn::Heartbeat,
+    resume_grace: Duration,
+    compress_threshold: usize,
+    limits: limits::Limits,
+    connections: Arc<limits::Connections>,
+    relay: Arc<Mutex<relay::RelayState>>,
+    admin_token: Option<Arc<str>>,
+    // Идёт остановка: новые соединения не принимаются
+    shutting_down: Arc<AtomicBool>,
 }
 
 #[derive(Debug)]
 struct WorldState {
+    // Имя в `?world=`
+    name: String,
     config: WorldConfig,
     heightmap: Heightmap,
     biomemap: BiomeMap,
+    // Материал поверхности по клеткам — для следов и вмятин
+    surface: SurfaceMap,
     players: HashMap<String, PlayerState>,
+    // Отключившиеся игроки: при входе продолжают с сохранённой позиции
+    offline_players: HashMap<String, PlayerState>,
     // Каналы для рассылки снапшотов всем подключённым клиентам
-    clients: HashMap<String, mpsc::UnboundedSender<ServerMessage>>,
-}
-
-#[derive(Debug, Default)]
-struct RelayState {
-    rooms: HashMap<String, RelayRoom>,
+    clients: HashMap<String, ClientChannel>,
+    // stateSyncStrategy == "delta_compressed": подтвердившим клиентам — дельты
+    delta_sync: bool,
+    history: sync::SnapshotHistory,
+    // Зона интереса; None — каждый клиент видит всех
+    interest: Option<interest::Interest>,
+    // Ввод игроков до ближайшего тика
+    inputs: Vec<tick::QueuedInput>,
+    // Мир изменился с прошлого тика — нужен снапшот
+    dirty: bool,
+    // Номер текущего тика, растёт с запуска мира
+    tick: u64,
+    // Отключившиеся клиенты, ждущие возобновления сессии
+    sessions: session::Sessions,
+    // Сгенерированные чанки объектов
+    object_chunks: objects::ChunkCache,
+    // Готовые узлы квадродерева рельефа
+    lod_nodes: lod::NodeCache,
+    // Следы и вмятины по чанкам
+    deform: deform::DeformLayer,
+    clock: clock::WorldClock,
+    weather: weather::Weather,
+    // События мира (бури, наводнения) для экосистемы и директора
+    events: EventBus,
+    // Расписание катастроф и директор, который их допускает
+    catastrophes: catastrophes::Catastrophes,
+    // Запись сессии (`--record`)
+    recorder: Option<record::Recorder>,
 }
 
 #[derive(Debug)]
-struct RelayRoom {
-    host: Option<RelayPeer>,
-    clients: HashMap<String, RelayPeer>,
-}
-
-#[derive(Debug)]
-struct RelayPeer {
-    sender: mpsc::UnboundedSender<Message>,
+struct ClientChannel {
+    sender: conn::Outbox,
+    encoding: SnapshotEncoding,
+    // Последний подтверждённый клиентом снапшот — база для дельты
+    acked: Option<u32>,
+    view: interest::ClientView,
+    chunks: objects::ClientChunks,
+    resume_token: String,
+    // Зрителю — снапшоты всей карты, а не зоны интереса
+    whole_map: bool,
+    // Согласованное в `hello`
+    features: protocol::Features,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PlayerState {
     id: String,
     role: PlayerRole,
+    // Номер игрока в двоичных снапшотах
+    #[serde(skip)]
+    slot: u16,
     x: f32,
     y: f32,
     z: f32,
     // Для VR-клиентов
     head_pos: Option<[f32; 3]>,
     head_quat: Option<[f32; 4]>,
+    // Скорость за последний тик, м/с — для интерполяции на клиентах
+    #[serde(default)]
+    velocity: [f32; 3],
+    // Id последнего обработанного ввода игрока (`input_id`), 0 — не было;
+    // клиент по нему сверяет предсказание
+    #[serde(default)]
+    last_input_id: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 enum PlayerRole {
     Pc,
     Vr,
+    // Только смотрит: не шлёт ввод, не виден в снапшотах и не считается
+    // в игроках мира
+    Spectator,
+}
+
+impl PlayerState {
+    fn spectator(&self) -> bool {
+        self.role == PlayerRole::Spectator
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 enum ClientMessage {
+    // Первое сообщение: версия протокола и возможности клиента (`protocol`)
+    #[serde(rename = "hello")]
+    Hello {
+        protocol: u32,
+        #[serde(default)]
+        encodings: Vec<String>,
+        #[serde(default)]
+        compression: Vec<String>,
+        #[serde(default)]
+        features: Option<Vec<String>>,
+    },
     #[serde(rename = "join")]
     Join {
         client_id: String,
         role: Option<PlayerRole>,
+        #[serde(default)]
+        encoding: SnapshotEncoding,
+        // Токен из прошлого `joined` — вернуться к своему игроку
+        #[serde(default)]
+        resume_token: Option<String>,
+        // Кодеки сжатия, которые клиент умеет распаковывать
+        #[serde(default)]
+        compression: Vec<String>,
+        // Зрителю — вся карта вместо зоны интереса
+        #[serde(default)]
+        whole_map: bool,
     },
+    // Ввод и поза — всегда игрока этого соединения; `client_id` в теле,
+    // который шлют старые клиенты, не читается
     #[serde(rename = "input")]
     Input {
-        client_id: String,
         dx: f32,
         dy: f32,
         dz: f32,
+        // Возрастающий номер ввода, с 1
+        #[serde(default)]
+        input_id: Option<u32>,
     },
     #[serde(rename = "vr_pose")]
     VrPose {
-        client_id: String,
         head_pos: [f32; 3],
         head_quat: [f32; 4],
     },
+    // Снапшот `seq` принят — база для следующих дельт
+    #[serde(rename = "ack")]
+    Ack { seq: u32 },
+    // Базы дельты у клиента нет — прислать полный снапшот
+    #[serde(rename = "resync")]
+    Resync,
+    // След: `kind` — "footprint" или тип из interaction.objectInteraction.trackTypes
+    #[serde(rename = "footprint")]
+    Footprint {
+        #[serde(default = "default_footprint_kind")]
+        kind: String,
+        x: f32,
+        z: f32,
+        #[serde(default)]
+        heading: f32,
+    },
+    // Вмятина в поверхности
+    #[serde(rename = "deform")]
+    Deform {
+        x: f32,
+        z: f32,
+        radius: f32,
+        depth: f32,
+    },
+    // Узлы квадродерева рельефа `[level, x, y]` (`lod`)
+    #[serde(rename = "terrain_nodes")]
+    TerrainNodes { nodes: Vec<[u32; 3]> },
+    // Путь по рельефу (`path`); `id` вернётся в ответе
+    #[serde(rename = "find_path")]
+    FindPath {
+        #[serde(default)]
+        id: Option<u32>,
+        #[serde(flatten)]
+        request: path::PathRequest,
+    },
+    // Чат; `route` по умолчанию — всем в мире
+    #[serde(rename = "chat")]
+    Chat {
+        channel: String,
+        text: String,
+        #[serde(default)]
+        route: messaging::Route,
+    },
+    // Сообщение игры: сервер доставляет `payload`, не разбирая его
+    #[serde(rename = "custom")]
+    Custom {
+        kind: String,
+        #[serde(default)]
+        payload: serde_json::Value,
+        #[serde(default)]
+        route: messaging::Route,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 enum ServerMessage {
+    #[serde(rename = "hello")]
+    Hello(protocol::ServerHello),
     #[serde(rename = "world_snapshot")]
-    WorldSnapshot { players: Vec<PlayerState> },
+    WorldSnapshot {
+        seq: u32,
+        tick: u64,
+        server_time_ms: i64,
+        players: Vec<PlayerState>,
+    },
+    #[serde(rename = "world_delta")]
+    WorldDelta {
+        seq: u32,
+        tick: u64,
+        server_time_ms: i64,
+        base_seq: u32,
+        players: Vec<sync::PlayerDelta>,
+        removed: Vec<String>,
+    },
     #[serde(rename = "joined")]
-    Joined { client_id: String, role: PlayerRole },
+    Joined {
+        client_id: String,
+        role: PlayerRole,
+        slot: u16,
+        encoding: SnapshotEncoding,
+        // Для возобновления сессии после обрыва
+        resume_token: String,
+        // Сессия возобновлена: игрок, зона интереса и чанки прежние
+        resumed: bool,
+        // Выбранный кодек; дальше крупные сообщения могут приходить сжатыми
+        #[serde(skip_serializing_if = "Option::is_none")]
+        compression: Option<compress::Codec>,
+    },
+    // Сервер остановится через `seconds` секунд; 0 — сейчас, следом
+    // закрытие соединения
+    #[serde(rename = "server_shutdown")]
+    ServerShutdown { seconds: u64 },
+    // Администратор сменил роль игрока
+    #[serde(rename = "role_changed")]
+    RoleChanged { role: PlayerRole },
+    // Игрок потерял связь, вернулся или ушёл совсем
+    #[serde(rename = "player_status")]
+    PlayerStatus {
+        client_id: String,
+        status: session::PresenceStatus,
+    },
+    // Слоты игроков для двоичных снапшотов
+    #[serde(rename = "roster")]
+    Roster { players: Vec<RosterEntry> },
+    // Объекты чанка карты
+    #[serde(rename = "objects_chunk")]
+    ObjectsChunk {
+        chunk_x: u32,
+        chunk_y: u32,
+        chunk_size: u32,
+        objects: Vec<ProceduralObject>,
+    },
+    // Чанки вне зоны интереса — клиент может их выгрузить
+    #[serde(rename = "objects_evict")]
+    ObjectsEvict { chunks: Vec<[u32; 2]> },
+    // Слой деформации чанка целиком — при его загрузке
+    #[serde(rename = "deform_chunk")]
+    DeformChunk {
+        chunk_x: u32,
+        chunk_y: u32,
+        stamps: Vec<Stamp>,
+        decals: Vec<Decal>,
+    },
+    #[serde(rename = "deform_delta")]
+    DeformDelta {
+        chunk_x: u32,
+        chunk_y: u32,
+        stamps: Vec<Stamp>,
+        decals: Vec<Decal>,
+        removed_decals: Vec<u64>,
+    },
+    // Время суток и года, направление на солнце
+    #[serde(rename = "clock")]
+    Clock(clock::ClockState),
+    // Погода региона игрока
+    #[serde(rename = "weather")]
+    Weather(weather::RegionWeather),
+    // Предупреждение, удар и последствия катастрофы
+    #[serde(rename = "catastrophe")]
+    Catastrophe(catastrophes::CatastropheNotice),
+    // Высоты чанка после изменения рельефа, строками по `width`
+    #[serde(rename = "terrain_chunk")]
+    TerrainChunk {
+        chunk_x: u32,
+        chunk_y: u32,
+        width: u32,
+        height: u32,
+        heights: Vec<f32>,
+    },
+    // Узел квадродерева рельефа: `samples`×`samples` высот строками с севера
+    #[serde(rename = "terrain_node")]
+    TerrainNode {
+        level: u32,
+        x: u32,
+        y: u32,
+        // Глубже уровней нет
+        leaf: bool,
+        samples: u32,
+        // Отклонение от полной карты, м
+        error_m: f32,
+        heights: Vec<f32>,
+        // Края для сшивки с соседом на уровень крупнее; у корня нет
+        #[serde(skip_serializing_if = "Option::is_none")]
+        edges: Option<lod::NodeEdges>,
+        neighbors: lod::NodeNeighbors,
+    },
+    // Ответ на `find_path`
+    #[serde(rename = "path")]
+    Path {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<u32>,
+        waypoints: Vec<path::Waypoint>,
+        length_m: f32,
+        cost: f32,
+    },
+    #[serde(rename = "path_error")]
+    PathError {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<u32>,
+        message: String,
+    },
+    #[serde(rename = "chat")]
+    Chat {
+        from: String,
+        channel: String,
+        text: String,
+        route: messaging::Route,
+    },
+    #[serde(rename = "custom")]
+    Custom {
+        from: String,
+        kind: String,
+        payload: serde_json::Value,
+        route: messaging::Route,
+    },
     #[serde(rename = "error")]
     Error { message: String },
 }
 
+fn default_footprint_kind() -> String {
+    "footprint".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RosterEntry {
+    client_id: String,
+    slot: u16,
+    role: PlayerRole,
+}
+
+impl ServerMessage {
+    fn to_text(&self) -> Option<Message> {
+        match serde_json::to_string(self) {
+            Ok(t) => Some(Message::Text(t)),
+            Err(e) => {
+                error!("Failed to serialize ServerMessage: {}", e);
+                None
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.check {
+        let ok = check(&args);
+        std::process::exit(if ok { 0 } else { 1 });
+    }
     tracing_subscriber::fmt().with_env_filter("info").init();
+    if let Some(path) = &args.replay {
+        let ok = record::replay(path).await?;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
 
-    // Загружаем конфиг мира из стандартного JSON в корне репозитория
-    let cfg = WorldConfig::from_file("world-config.json")?;
-    let width = 512;
-    let height = 512;
-    let hm = generate_heightmap_from_config(&cfg, width, height);
-    let bm = generate_biome_map_from_config(&cfg, &hm);
-
-    let world = WorldState {
-        config: cfg,
-        heightmap: hm,
-        biomemap: bm,
-        players: HashMap::new(),
-        clients: HashMap::new(),
-    };
+    let cfg = WorldConfig::from_file(&args.config)?;
+    worlds::check_map_size(args.map_size).map_err(anyhow::Error::msg)?;
+    if args.tls_cert.is_some() && !cfg!(feature = "tls") {
+        anyhow::bail!("--tls-cert: seed-server is built without the `tls` feature");
+    }
+
+    // Мир по умолчанию хранится в корне data_dir, созданные — в worlds/
+    let store = persist::open_store(args.store, &args.data_dir)?;
+    let saved = store.load_world()?;
+    let default = worlds::open(
+        worlds::DEFAULT_WORLD,
+        cfg,
+        args.map_size,
+        saved,
+        store,
+        args.record.as_deref(),
+    )
+    .await?;
 
     let state = AppState {
-        world: Arc::new(Mutex::new(world)),
-        relay: Arc::new(Mutex::new(RelayState::default())),
+        worlds: Arc::new(worlds::Worlds::new(args.data_dir.clone(), args.store)),
+        web_dir: Arc::new(args.web_dir.clone()),
+        cross_origin_isolation: args.cross_origin_isolation,
+        heartbeat: conn::Heartbeat {
+            ping_interval: Duration::from_secs(args.ping_interval.max(1)),
+            timeout: Duration::from_secs(args.client_timeout.max(1)),
+        },
+        resume_grace: Duration::from_secs(args.resume_grace),
+        compress_threshold: args.compress_threshold,
+        limits: limits::Limits {
+            max_rooms: args.max_rooms,
+            messages_per_s: args.max_messages_per_sec,
+            bytes_per_s: args.max_bytes_per_sec,
+            max_relay_frame: args.max_relay_frame.max(1),
+        },
+        connections: limits::Connections::new(args.max_connections),
+        relay: Arc::new(Mutex::new(relay::RelayState::default())),
+        admin_token: args.admin_token.as_deref().map(Arc::from),
+        shutting_down: Arc::new(AtomicBool::new(false)),
     };
+    state
+        .worlds
+        .insert(worlds::DEFAULT_WORLD.to_string(), default)
+        .await;
+    worlds::restore_created(&state.worlds).await;
+    tokio::spawn(relay::expire_rooms(
+        state.clone(),
+        Duration::from_secs(args.relay_room_ttl),
+    ));
 
     // HTTP + WebSocket:
-    // - /ws  -> WebSocket для мультиплеера
+    // - /ws?world=...  -> WebSocket для мультиплеера
     // - /relay -> WebSocket-ретранслятор видео/JSON между host (ПК) и client (телефон)
+    // - /api/relay/rooms -> комнаты relay
+    // - /api/worlds -> список миров (GET) и создание нового (POST)
+    // - /api/world, /api/config -> описание мира и конфиг без серверной части
+    // - /api/heightmap/{z}/{x}/{y}, /api/biomes/{z}/{x}/{y} -> тайлы рельефа и биомов
+    // - /api/render/worldview.png -> карта мира (или её участок) в PNG
+    // - /api/admin/players -> игроки мира и смена роли (с --admin-token)
     // - всё остальное → статика из каталога web/ (index3d-enhanced.html, vr_client_enhanced.html и т.п.)
     let app = Router::new()
         .route("/ws", get(ws_handler))
-        .route("/relay", get(relay_ws_handler))
-        .fallback(static_handler)
-        .with_state(state);
+        .route("/relay", get(relay::relay_ws_handler))
+        .route("/api/relay/rooms", get(relay::list_rooms))
+        .route(
+            "/api/worlds",
+            get(worlds::list_worlds).post(worlds::create_world),
+        )
+        .route("/api/world", get(api::world_info))
+        .route("/api/config", get(api::world_config))
+        .route("/api/heightmap/:z/:x/:y", get(tiles::heightmap_tile))
+        .route("/api/biomes/:z/:x/:y", get(tiles::biome_tile))
+        .route("/api/render/worldview.png", get(render::worldview_png))
+        .route("/api/path", post(path::find_path))
+        .route("/api/admin/players", get(admin::list_players))
+        .route("/api/admin/players/:id/role", post(admin::set_player_role))
+        .fallback(static_handler);
 
-    let addr: SocketAddr = "0.0.0.0:9000".parse()?;
-    info!("Starting seed-server on {}", addr);
-    axum::serve(tokio::net::TcpListener::bind(addr).await?, app).await?;
+    let addr = SocketAddr::new(args.host, args.port);
+    let app = app.with_state(state.clone());
+    let server = async {
+        match (&args.tls_cert, &args.tls_key) {
+            #[cfg(feature = "tls")]
+            (Some(cert), Some(key)) => {
+                info!("Starting seed-server on https://{}", addr);
+                tls::serve(addr, app, cert, key).await
+            }
+            _ => {
+                info!("Starting seed-server on {}", addr);
+                let listener = tokio::net::TcpListener::bind(addr).await?;
+                axum::serve(listener, app).await?;
+                anyhow::Ok(())
+            }
+        }
+    };
+    // Сервер работает, пока идёт отсчёт остановки: клиенты дослушивают
+    // предупреждения, а HTTP отвечает
+    let grace = Duration::from_secs(args.shutdown_grace);
+    tokio::select! {
+        res = server => res?,
+        _ = async {
+            shutdown::signal().await;
+            shutdown::run(&state, grace).await;
+        } => {}
+    }
+    state.worlds.save_all().await;
+    // запись может идти у любого мира, не только у мира по умолчанию
+    for world in state.worlds.handles().await {
+        record::stop(&mut *world.lock().await);
+    }
 
     Ok(())
 }
 
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> Response {
-    ws.on_upgrade(move |socket| handle_socket(socket, state))
+/// `--check`: конфиг загружается и проходит проверку, настройки допустимы
+/// (ошибки и предупреждения — в stderr, итог — в stdout)
+fn check(args: &Args) -> bool {
+    let mut errors = 0;
+    match WorldConfig::from_file(&args.config) {
+        Ok(cfg) => {
+            for d in cfg.validate() {
+                if d.severity == Severity::Error {
+                    errors += 1;
+                }
+                eprintln!("{d}");
+            }
+        }
+        Err(e) => {
+            errors += 1;
+            eprintln!("error: {}: {e}", args.config.display());
+        }
+    }
+    if let Err(e) = worlds::check_map_size(args.map_size) {
+        errors += 1;
+        eprintln!("error: --map-size: {e}");
+    }
+    if args.tls_cert.is_some() && !cfg!(feature = "tls") {
+        errors += 1;
+        eprintln!("error: --tls-cert: built without the `tls` feature");
+    }
+    if args.store == persist::StoreKind::Sqlite && !cfg!(feature = "sqlite") {
+        errors += 1;
+        eprintln!("error: --store sqlite: built without the `sqlite` feature");
+    }
+    if !args.web_dir.is_dir() {
+        eprintln!(
+            "warning: --web-dir {} is not a directory, static files will 404",
+            args.web_dir.display()
+        );
+    }
+    println!(
+        "{}: {}",
+        args.config.display(),
+        if errors == 0 { "ok" } else { "invalid" }
+    );
+    errors == 0
 }
 
 #[derive(Debug, Deserialize)]
-struct RelayQuery {
-    role: String,
-    #[serde(default)]
-    room: Option<String>,
+struct WsQuery {
+    world: Option<String>,
 }
 
-async fn relay_ws_handler(
-    ws: WebSocketUpgrade,
+#[cfg(not(feature = "permessage-deflate"))]
+async fn ws_handler(
+    ws: axum::extract::ws::WebSocketUpgrade,
     State(state): State<AppState>,
-    Query(params): Query<RelayQuery>,
-) -> Response {
-    ws.on_upgrade(move |socket| handle_relay_socket(socket, state, params))
+    Query(params): Query<WsQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let world = worlds::resolve(&state, params.world.as_deref()).await?;
+    let guard = acquire_connection(&state)?;
+    Ok(ws.on_upgrade(move |socket| async move {
+        handle_socket(socket, world, state).await;
+        drop(guard);
+    }))
 }
 
-async fn handle_socket(socket: WebSocket, state: AppState) {
-    let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
-
-    let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            let text = match serde_json::to_string(&msg) {
-                Ok(t) => t,
-                Err(e) => {
-                    error!("Failed to serialize ServerMessage: {}", e);
-                    continue;
-                }
-            };
-            if sender.send(Message::Text(text)).await.is_err() {
-                break;
-            }
+/// С фичей `permessage-deflate` соединение поднимает yawc (см. `deflate`)
+#[cfg(feature = "permessage-deflate")]
+async fn ws_handler(
+    State(state): State<AppState>,
+    Query(params): Query<WsQuery>,
+    req: Request<Body>,
+) -> Result<Response, (StatusCode, String)> {
+    let world = worlds::resolve(&state, params.world.as_deref()).await?;
+    let guard = acquire_connection(&state)?;
+    let (res, socket) = deflate::upgrade(req)?;
+    tokio::spawn(async move {
+        if let Some(socket) = socket.await {
+            handle_socket(socket, world, state).await;
         }
+        drop(guard);
     });
+    Ok(res)
+}
+
+/// Место под соединение или 503
+fn acquire_connection(state: &AppState) -> Result<limits::ConnectionGuard, (StatusCode, String)> {
+    if state.shutting_down.load(Ordering::Acquire) {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "server is shutting down".to_string(),
+        ));
+    }
+    state.connections.acquire().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too many connections".to_string(),
+        )
+    })
+}
+
+async fn handle_socket<S>(socket: S, world_state: WorldHandle, state: AppState)
+where
+    S: Stream<Item = Result<Message, axum::Error>> + Sink<Message> + Send + 'static,
+{
+    let (heartbeat, resume_grace) = (state.heartbeat, state.resume_grace);
+    let mut limiter = limits::RateLimiter::new(&state.limits);
+    let (sender, mut receiver) = socket.split();
+    let tx = conn::Outbox::default();
+    let send_task = tokio::spawn(conn::pump(sender, tx.clone(), heartbeat));
+    let reply = |msg: ServerMessage| {
+        if let Some(m) = msg.to_text() {
+            let _ = tx.send(m);
+        }
+    };
 
     let mut client_id: Option<String> = None;
+    // None — клиент не прислал `hello`
+    let mut hello: Option<protocol::Negotiated> = None;
+    let mut features = protocol::Features::ALL;
+    // Причина, с которой сервер закрывает соединение сам
+    let mut close: Option<CloseFrame<'static>> = None;
 
-    while let Some(Ok(msg)) = receiver.next().await {
+    while let Some(msg) = conn::next_message(&mut receiver, &tx, heartbeat).await {
+        match limiter.check(&msg) {
+            limits::Verdict::Pass => {}
+            limits::Verdict::Warn => {
+                reply(ServerMessage::Error {
+                    message: "rate_limited".into(),
+                });
+                continue;
+            }
+            limits::Verdict::Drop => continue,
+            limits::Verdict::Disconnect => {
+                info!(
+                    "client {:?} exceeded the rate limit, disconnecting",
+                    client_id
+                );
+                break;
+            }
+        }
         match msg {
             Message::Text(text) => {
                 match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::Hello {
+                        protocol: version,
+                        encodings,
+                        compression,
+                        features: offered,
+                    }) => {
+                        let (server, negotiated) = protocol::negotiate(
+                            version,
+                            &encodings,
+                            &compression,
+                            offered.as_deref(),
+                        );
+                        reply(ServerMessage::Hello(server));
+                        let Some(negotiated) = negotiated else {
+                            info!("client speaks unsupported protocol {}", version);
+                            reply(ServerMessage::Error {
+                                message: "unsupported_protocol".into(),
+                            });
+                            close = Some(CloseFrame {
+                                code: close_code::POLICY,
+                                reason: "unsupported_protocol".into(),
+                            });
+                            break;
+                        };
+                        features = negotiated.features;
+                        hello = Some(negotiated);
+                    }
                     Ok(ClientMessage::Join {
                         client_id: cid,
                         role,
+                        encoding,
+                        resume_token,
+                        compression,
+                        whole_map,
                     }) => {
-                        let role = role.unwrap_or(PlayerRole::Pc);
-                        info!("client {} joined as {:?}", cid, role);
-                        client_id = Some(cid.clone());
-                        {
-                            let mut world = state.world.lock().await;
-                            world.players.entry(cid.clone()).or_insert(PlayerState {
-                                id: cid.clone(),
-                                role: role.clone(),
-                                x: 0.0,
-                                y: 0.0,
-                                z: 0.0,
-                                head_pos: None,
-                                head_quat: None,
+                        // соединение входит один раз: иначе прежняя
+                        // регистрация осталась бы без хозяина
+                        if client_id.is_some() {
+                            reply(ServerMessage::Error {
+                                message: "already_joined".into(),
                             });
-                            // Запоминаем канал для рассылки снапшотов этому клиенту
-                            world.clients.insert(cid.clone(), tx.clone());
+                            continue;
                         }
-                        let _ = tx.send(ServerMessage::Joined {
-                            client_id: cid,
+                        let role = role.unwrap_or(PlayerRole::Pc);
+                        let (encoding, codec) = match &hello {
+                            Some(h) => (h.encoding(encoding), h.codec(&compression)),
+                            None => (encoding, Codec::negotiate(&compression)),
+                        };
+                        let version = hello
+                            .as_ref()
+                            .map_or(protocol::LEGACY_PROTOCOL_VERSION, |h| h.protocol);
+                        info!(
+                            "client {} joined as {:?} ({:?}, protocol {})",
+                            cid, role, encoding, version
+                        );
+                        let token = session::new_token();
+                        let mut resumed = false;
+                        let slot = {
+                            let mut world = world_state.lock().await;
+                            let world = &mut *world;
+                            let resumed_session = match &resume_token {
+                                Some(t)
+                                    if features.has(Feature::Resume)
+                                        && world.players.contains_key(&cid) =>
+                                {
+                                    session::resume(world, &cid, t)
+                                }
+                                _ => None,
+                            };
+                            // зрители мест не занимают
+                            let takes_place = role != PlayerRole::Spectator
+                                && world.players.get(&cid).is_none_or(|p| p.spectator());
+                            let connected_elsewhere =
+                                world.clients.get(&cid).is_some_and(|c| !c.sender.same(&tx));
+                            if resumed_session.is_none() && connected_elsewhere {
+                                // открытую сессию забирают только по её токену
+                                Err("session_in_use")
+                            } else if takes_place
+                                && worlds::playing(&world.players) >= worlds::MAX_PLAYERS_PER_WORLD
+                            {
+                                Err("world_full")
+                            } else if let Some(r) = resumed_session {
+                                resumed = true;
+                                let player = world.players.get_mut(&cid);
+                                let joined = player.map(|p| {
+                                    p.role = role.clone();
+                                    p.clone()
+                                });
+                                let slot = joined.as_ref().map_or(0, |p| p.slot);
+                                if let Some(player) = joined {
+                                    record::note(world, record::Event::Join { player, slot });
+                                }
+                                world.dirty = true;
+                                world.clients.insert(
+                                    cid.clone(),
+                                    ClientChannel {
+                                        sender: tx.clone(),
+                                        encoding,
+                                        acked: None,
+                                        view: r.view,
+                                        chunks: r.chunks,
+                                        resume_token: token.clone(),
+                                        whole_map,
+                                        features,
+                                    },
+                                );
+                                Ok(slot)
+                            } else {
+                                session::forget(world, &cid);
+                                let slot = free_slot(&world.players);
+                                let saved = world.offline_players.remove(&cid);
+                                let player =
+                                    world.players.entry(cid.clone()).or_insert_with(|| {
+                                        let mut player = saved.unwrap_or_else(|| PlayerState {
+                                            id: cid.clone(),
+                                            role: role.clone(),
+                                            slot,
+                                            x: 0.0,
+                                            y: 0.0,
+                                            z: 0.0,
+                                            head_pos: None,
+                                            head_quat: None,
+                                            velocity: [0.0; 3],
+                                            last_input_id: 0,
+                                        });
+                                        player.role = role.clone();
+                                        player.slot = slot;
+                                        // новое соединение нумерует ввод заново
+                                        player.velocity = [0.0; 3];
+                                        player.last_input_id = 0;
+                                        player
+                                    });
+                                let (player, slot) = (player.clone(), player.slot);
+                                record::note(world, record::Event::Join { player, slot });
+                                world.dirty = true;
+                                // Запоминаем канал для рассылки снапшотов этому клиенту
+                                world.clients.insert(
+                                    cid.clone(),
+                                    ClientChannel {
+                                        sender: tx.clone(),
+                                        encoding,
+                                        acked: None,
+                                        view: interest::ClientView::default(),
+                                        chunks: objects::ClientChunks::default(),
+                                        resume_token: token.clone(),
+                                        whole_map,
+                                        features,
+                                    },
+                                );
+                                Ok(slot)
+                            }
+                        };
+                        let slot = match slot {
+                            Ok(slot) => slot,
+                            Err("session_in_use") => {
+                                info!("client {} is connected elsewhere, join refused", cid);
+                                reply(ServerMessage::Error {
+                                    message: "session_in_use".into(),
+                                });
+                                continue;
+                            }
+                            Err(reason) => {
+                                // мир заполнен: отказываем и закрываем соединение
+                                reply(ServerMessage::Error {
+                                    message: reason.into(),
+                                });
+                                close = Some(CloseFrame {
+                                    code: close_code::AGAIN,
+                                    reason: reason.into(),
+                                });
+                                break;
+                            }
+                        };
+                        client_id = Some(cid.clone());
+                        let codec = codec.filter(|_| state.compress_threshold > 0);
+                        reply(ServerMessage::Joined {
+                            client_id: cid.clone(),
                             role,
+                            slot,
+                            encoding,
+                            resume_token: token,
+                            resumed,
+                            compression: codec,
                         });
-                        // сразу отправляем снапшот
-                        send_world_snapshot(&state).await;
+                        tx.set_compression(codec.map(|codec| compress::Compression {
+                            codec,
+                            threshold: state.compress_threshold,
+                        }));
+                        {
+                            let world = world_state.lock().await;
+                            reply(clock::message(&world));
+                            if features.has(Feature::Weather) {
+                                if let Some(msg) = weather::message(&world, &cid) {
+                                    reply(msg);
+                                }
+                            }
+                            if features.has(Feature::Catastrophes) {
+                                for msg in catastrophes::messages(&world) {
+                                    reply(msg);
+                                }
+                            }
+                        }
+                        send_roster(&world_state).await;
                     }
                     Ok(ClientMessage::Input {
-                        client_id: cid,
                         dx,
                         dy,
                         dz,
+                        input_id,
                     }) => {
-                        let mut world = state.world.lock().await;
-                        if let Some(p) = world.players.get_mut(&cid) {
-                            p.x += dx;
-                            p.y += dy;
-                            p.z += dz;
-
-                            if matches!(p.role, PlayerRole::Vr) {
-                                info!(
-                                    "VR input from {}: dx={:.3}, dy={:.3}, dz={:.3}",
-                                    cid, dx, dy, dz
-                                );
-                            }
+                        let mut world = world_state.lock().await;
+                        let input_id = input_id.filter(|_| features.has(Feature::InputIds));
+                        let queued =
+                            queue_input(&mut world, &client_id, &tx, [dx, dy, dz], input_id);
+                        if let Err(message) = queued {
+                            reply(ServerMessage::Error {
+                                message: message.into(),
+                            });
                         }
-                        drop(world);
-                        send_world_snapshot(&state).await;
                     }
                     Ok(ClientMessage::VrPose {
-                        client_id: cid,
                         head_pos,
                         head_quat,
                     }) => {
-                        let mut world = state.world.lock().await;
-                        if let Some(p) = world.players.get_mut(&cid) {
-                            p.head_pos = Some(head_pos);
-                            p.head_quat = Some(head_quat);
-
-                            if matches!(p.role, PlayerRole::Vr) {
-                                info!(
-                                    "VR pose from {}: head_pos={:?}, head_quat={:?}",
-                                    cid, p.head_pos, p.head_quat
-                                );
+                        let mut world = world_state.lock().await;
+                        let world = &mut *world;
+                        let p = match own_player(world, &client_id, &tx) {
+                            Ok(p) => p,
+                            Err(message) => {
+                                reply(ServerMessage::Error {
+                                    message: message.into(),
+                                });
+                                continue;
                             }
+                        };
+                        p.head_pos = Some(head_pos);
+                        p.head_quat = Some(head_quat);
+                        if matches!(p.role, PlayerRole::Vr) {
+                            info!(
+                                "VR pose from {}: head_pos={:?}, head_quat={:?}",
+                                p.id, p.head_pos, p.head_quat
+                            );
+                        }
+                        let cid = p.id.clone();
+                        world.dirty = true;
+                        record::note(
+                            world,
+                            record::Event::VrPose {
+                                client_id: cid,
+                                head_pos,
+                                head_quat,
+                            },
+                        );
+                        // поза — абсолютная: берётся последняя, в снапшот попадёт на тике
+                    }
+                    Ok(ClientMessage::Ack { seq }) => {
+                        let mut world = world_state.lock().await;
+                        if let Some(c) = own_channel(&mut world, &client_id, &tx) {
+                            // опоздавшее подтверждение не откатывает базу назад
+                            if c.acked.is_none_or(|a| seq.wrapping_sub(a) as i32 > 0) {
+                                c.acked = Some(seq);
+                            }
+                        }
+                    }
+                    Ok(ClientMessage::Resync) => {
+                        let mut world = world_state.lock().await;
+                        if let Some(c) = own_channel(&mut world, &client_id, &tx) {
+                            c.acked = None;
+                        }
+                    }
+                    Ok(ClientMessage::Footprint {
+                        kind,
+                        x,
+                        z,
+                        heading,
+                    }) => {
+                        // след оставляет игрок этого соединения
+                        let Some(cid) = client_id.clone() else {
+                            reply(ServerMessage::Error {
+                                message: "not_joined".into(),
+                            });
+                            continue;
+                        };
+                        if !features.has(Feature::Deform) {
+                            reply(ServerMessage::Error {
+                                message: "feature_disabled".into(),
+                            });
+                            continue;
+                        }
+                        let mut world = world_state.lock().await;
+                        match deform::footprint(&mut world, &cid, &kind, x, z, heading) {
+                            Ok(()) => record::note(
+                                &mut world,
+                                record::Event::Footprint {
+                                    client_id: cid,
+                                    kind,
+                                    x,
+                                    z,
+                                    heading,
+                                },
+                            ),
+                            Err(message) => reply(ServerMessage::Error {
+                                message: message.into(),
+                            }),
+                        }
+                    }
+                    Ok(ClientMessage::Deform {
+                        x,
+                        z,
+                        radius,
+                        depth,
+                    }) => {
+                        let Some(cid) = client_id.clone() else {
+                            reply(ServerMessage::Error {
+                                message: "not_joined".into(),
+                            });
+                            continue;
+                        };
+                        if !features.has(Feature::Deform) {
+                            reply(ServerMessage::Error {
+                                message: "feature_disabled".into(),
+                            });
+                            continue;
+                        }
+                        let mut world = world_state.lock().await;
+                        match deform::deform(&mut world, &cid, x, z, radius, depth) {
+                            Ok(()) => record::note(
+                                &mut world,
+                                record::Event::Deform {
+                                    client_id: cid,
+                                    x,
+                                    z,
+                                    radius,
+                                    depth,
+                                },
+                            ),
+                            Err(message) => reply(ServerMessage::Error {
+                                message: message.into(),
+                            }),
+                        }
+                    }
+                    Ok(ClientMessage::TerrainNodes { nodes }) => {
+                        if !features.has(Feature::TerrainLod) {
+                            reply(ServerMessage::Error {
+                                message: "feature_disabled".into(),
+                            });
+                            continue;
+                        }
+                        let mut world = world_state.lock().await;
+                        match lod::request(&mut world, &nodes) {
+                            Ok(msgs) => {
+                                for msg in msgs {
+                                    let _ = tx.send(msg);
+                                }
+                            }
+                            Err(message) => reply(ServerMessage::Error {
+                                message: message.into(),
+                            }),
+                        }
+                    }
+                    Ok(ClientMessage::FindPath { id, request }) => {
+                        if !features.has(Feature::Pathfinding) {
+                            reply(ServerMessage::PathError {
+                                id,
+                                message: "feature_disabled".into(),
+                            });
+                            continue;
+                        }
+                        // поиск долгий — не держим им приём сообщений
+                        let (world, tx) = (world_state.clone(), tx.clone());
+                        tokio::spawn(async move {
+                            let msg = match path::find(&world, request).await {
+                                Ok(found) => ServerMessage::Path {
+                                    id,
+                                    waypoints: found.waypoints,
+                                    length_m: found.length_m,
+                                    cost: found.cost,
+                                },
+                                Err(message) => ServerMessage::PathError {
+                                    id,
+                                    message: message.into(),
+                                },
+                            };
+                            if let Some(m) = msg.to_text() {
+                                let _ = tx.send(m);
+                            }
+                        });
+                    }
+                    Ok(ClientMessage::Chat {
+                        channel,
+                        text,
+                        route,
+                    }) => {
+                        // отправитель — игрок этого соединения
+                        let Some(cid) = client_id.clone() else {
+                            reply(ServerMessage::Error {
+                                message: "not_joined".into(),
+                            });
+                            continue;
+                        };
+                        if !features.has(Feature::Messaging) {
+                            reply(ServerMessage::Error {
+                                message: "feature_disabled".into(),
+                            });
+                            continue;
+                        }
+                        let sent = match messaging::check_chat(&channel, &text) {
+                            Ok(()) => {
+                                let msg = ServerMessage::Chat {
+                                    from: cid.clone(),
+                                    channel,
+                                    text,
+                                    route: route.clone(),
+                                };
+                                messaging::deliver(&world_state, &cid, &route, msg).await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        if let Err(message) = sent {
+                            reply(ServerMessage::Error {
+                                message: message.into(),
+                            });
+                        }
+                    }
+                    Ok(ClientMessage::Custom {
+                        kind,
+                        payload,
+                        route,
+                    }) => {
+                        // отправитель — игрок этого соединения
+                        let Some(cid) = client_id.clone() else {
+                            reply(ServerMessage::Error {
+                                message: "not_joined".into(),
+                            });
+                            continue;
+                        };
+                        if !features.has(Feature::Messaging) {
+                            reply(ServerMessage::Error {
+                                message: "feature_disabled".into(),
+                            });
+                            continue;
+                        }
+                        let sent = match messaging::check_custom(&kind, &payload) {
+                            Ok(()) => {
+                                let msg = ServerMessage::Custom {
+                                    from: cid.clone(),
+                                    kind,
+                                    payload,
+                                    route: route.clone(),
+                                };
+                                messaging::deliver(&world_state, &cid, &route, msg).await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        if let Err(message) = sent {
+                            reply(ServerMessage::Error {
+                                message: message.into(),
+                            });
                         }
-                        drop(world);
-                        // VR-позы обычно часто, поэтому можно не отправлять полный снапшот каждый раз,
-                        // но для простоты сейчас отправляем
-                        send_world_snapshot(&state).await;
                     }
                     Err(e) => {
                         error!("Failed to parse ClientMessage: {}", e);
-                        let _ = tx.send(ServerMessage::Error {
+                        reply(ServerMessage::Error {
                             message: "invalid_message".into(),
                         });
                     }
@@ -280,244 +1297,432 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
 
     // Cleanup on disconnect
     if let Some(cid) = client_id {
-        let mut world = state.world.lock().await;
-        world.players.remove(&cid);
-        world.clients.remove(&cid);
+        let mut world = world_state.lock().await;
+        // сессию могло забрать новое соединение того же клиента
+        if world.clients.get(&cid).is_some_and(|c| c.sender.same(&tx)) {
+            // без возобновления игрок уходит сразу
+            let grace = if features.has(Feature::Resume) {
+                resume_grace
+            } else {
+                Duration::ZERO
+            };
+            session::suspend(&mut world, &cid, grace);
+        }
+    }
+
+    conn::finish(&tx, send_task, close).await;
+}
+
+/// Канал клиента, вошедшего через это соединение; ack и resync чужих
+/// клиентов не трогают
+fn own_channel<'a>(
+    world: &'a mut WorldState,
+    client_id: &Option<String>,
+    tx: &conn::Outbox,
+) -> Option<&'a mut ClientChannel> {
+    let c = world.clients.get_mut(client_id.as_deref()?)?;
+    c.sender.same(tx).then_some(c)
+}
+
+/// Игрок этого соединения: `not_joined`, пока соединение не вошло или
+/// если его сессию забрало другое, `spectator` — у зрителя
+fn own_player<'a>(
+    world: &'a mut WorldState,
+    client_id: &Option<String>,
+    tx: &conn::Outbox,
+) -> Result<&'a mut PlayerState, &'static str> {
+    let cid = client_id.as_deref().ok_or("not_joined")?;
+    own_channel(world, client_id, tx).ok_or("not_joined")?;
+    let p = world.players.get_mut(cid).ok_or("not_joined")?;
+    if p.spectator() {
+        return Err("spectator");
     }
+    Ok(p)
+}
 
-    send_task.abort();
+/// Ставит ввод игрока этого соединения в очередь; применится на ближайшем
+/// тике
+fn queue_input(
+    world: &mut WorldState,
+    client_id: &Option<String>,
+    tx: &conn::Outbox,
+    delta: [f32; 3],
+    input_id: Option<u32>,
+) -> Result<(), &'static str> {
+    let p = own_player(world, client_id, tx)?;
+    if matches!(p.role, PlayerRole::Vr) {
+        let [dx, dy, dz] = delta;
+        info!(
+            "VR input from {}: dx={:.3}, dy={:.3}, dz={:.3}",
+            p.id, dx, dy, dz
+        );
+    }
+    let cid = p.id.clone();
+    record::note(
+        world,
+        record::Event::Input {
+            client_id: cid.clone(),
+            delta,
+            input_id,
+        },
+    );
+    world.inputs.push(tick::QueuedInput {
+        client_id: cid,
+        delta,
+        input_id,
+        sender: Some(tx.clone()),
+    });
+    Ok(())
 }
 
-async fn send_world_snapshot(state: &AppState) {
+async fn send_world_snapshot(world_state: &WorldHandle) {
+    let (header, players, clients) = {
+        let mut world = world_state.lock().await;
+        let world = &mut *world;
+        // зрителей в снапшотах нет
+        let players: sync::Players = Arc::new(
+            world
+                .players
+                .iter()
+                .filter(|(_, p)| !p.spectator())
+                .map(|(id, p)| (id.clone(), p.clone()))
+                .collect(),
+        );
+        let seq = world.history.push(players.clone());
+        let header = snapshot::Header {
+            seq,
+            tick: world.tick,
+            server_time_ms: chrono::Utc::now().timestamp_millis(),
+        };
+        let grid = world
+            .interest
+            .map(|i| interest::SpatialGrid::build(&players, i.exit_m));
+        let clients: Vec<_> = world
+            .clients
+            .iter_mut()
+            .map(|(cid, c)| {
+                let me = world.players.get(cid);
+                let view = match (world.interest, &grid, me) {
+                    (_, _, Some(me)) if me.spectator() && c.whole_map => None,
+                    // клиент без зоны интереса видит всех
+                    _ if !c.features.has(Feature::Interest) => None,
+                    (Some(i), Some(grid), Some(me)) => Some(c.view.update(seq, me, grid, i)),
+                    _ => None,
+                };
+                // база выпала из истории — клиенту полный снапшот
+                let delta = world.delta_sync && c.features.has(Feature::DeltaSync);
+                let base = c.acked.filter(|_| delta).and_then(|a| {
+                    let base = world.history.get(a)?;
+                    if view.is_none() {
+                        return Some((a, base));
+                    }
+                    // база — то, что клиент видел в подтверждённом снапшоте
+                    let seen = c.view.at(a)?;
+                    Some((a, Arc::new(interest::filter(&base, &seen))))
+                });
+                (c.sender.clone(), c.encoding, base, view)
+            })
+            .collect();
+        (header, players, clients)
+    };
+
+    // Без зоны интереса каждое сообщение собираем один раз на всех клиентов
+    // с той же кодировкой и базой
+    let mut cache: HashMap<(SnapshotEncoding, Option<u32>), Option<Message>> = HashMap::new();
+    for (tx, encoding, base, view) in clients {
+        let msg = match view {
+            Some(visible) => snapshot_message(
+                header,
+                encoding,
+                base,
+                &interest::filter(&players, &visible),
+            ),
+            None => cache
+                .entry((encoding, base.as_ref().map(|(b, _)| *b)))
+                .or_insert_with(|| snapshot_message(header, encoding, base, &players))
+                .clone(),
+        };
+        if let Some(msg) = msg {
+            let _ = tx.send(msg);
+        }
+    }
+}
+
+/// Снапшот `current` в кодировке клиента: дельта от базы или полный
+fn snapshot_message(
+    header: snapshot::Header,
+    encoding: SnapshotEncoding,
+    base: Option<(u32, sync::Players)>,
+    current: &HashMap<String, PlayerState>,
+) -> Option<Message> {
+    match base {
+        Some((base_seq, base)) => {
+            let delta = sync::diff(&base, current);
+            match encoding {
+                SnapshotEncoding::Json => ServerMessage::WorldDelta {
+                    seq: header.seq,
+                    tick: header.tick,
+                    server_time_ms: header.server_time_ms,
+                    base_seq,
+                    removed: delta.removed.into_iter().map(|(id, _)| id).collect(),
+                    players: delta.changed,
+                }
+                .to_text(),
+                SnapshotEncoding::Binary => Some(Message::Binary(snapshot::encode_binary_delta(
+                    header, base_seq, &delta,
+                ))),
+            }
+        }
+        None => match encoding {
+            SnapshotEncoding::Json => ServerMessage::WorldSnapshot {
+                seq: header.seq,
+                tick: header.tick,
+                server_time_ms: header.server_time_ms,
+                players: current.values().cloned().collect(),
+            }
+            .to_text(),
+            SnapshotEncoding::Binary => Some(Message::Binary(snapshot::encode_binary(
+                header,
+                current.values(),
+            ))),
+        },
+    }
+}
+
+/// Рассылает слоты игроков клиентам с двоичными снапшотами
+async fn send_roster(world_state: &WorldHandle) {
+    broadcast_roster(&*world_state.lock().await);
+}
+
+fn broadcast_roster(world: &WorldState) {
     let (players, clients) = {
-        let world = state.world.lock().await;
-        let players: Vec<PlayerState> = world.players.values().cloned().collect();
-        let clients: Vec<mpsc::UnboundedSender<ServerMessage>> =
-            world.clients.values().cloned().collect();
+        let players: Vec<RosterEntry> = world
+            .players
+            .values()
+            .filter(|p| !p.spectator())
+            .map(|p| RosterEntry {
+                client_id: p.id.clone(),
+                slot: p.slot,
+                role: p.role.clone(),
+            })
+            .collect();
+        let clients: Vec<conn::Outbox> = world
+            .clients
+            .values()
+            .filter(|c| c.encoding == SnapshotEncoding::Binary)
+            .map(|c| c.sender.clone())
+            .collect();
         (players, clients)
     };
-
-    let msg = ServerMessage::WorldSnapshot { players };
+    if clients.is_empty() {
+        return;
+    }
+    let Some(msg) = (ServerMessage::Roster { players }).to_text() else {
+        return;
+    };
     for tx in clients {
         let _ = tx.send(msg.clone());
     }
 }
 
+/// Наименьший слот, не занятый другим игроком
+fn free_slot(players: &HashMap<String, PlayerState>) -> u16 {
+    let used: std::collections::HashSet<u16> = players.values().map(|p| p.slot).collect();
+    (0..=u16::MAX)
+        .find(|s| !used.contains(s))
+        .unwrap_or(u16::MAX)
+}
+
 async fn static_handler(
+    State(state): State<AppState>,
     req: Request<Body>,
 ) -> Result<impl axum::response::IntoResponse, (StatusCode, String)> {
-    let res = ServeDir::new("web").oneshot(req).await.map_err(|err| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Internal server error: {}", err),
-        )
-    })?;
+    let mut res = ServeDir::new(&*state.web_dir)
+        .oneshot(req)
+        .await
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Internal server error: {}", err),
+            )
+        })?;
 
+    if state.cross_origin_isolation {
+        let headers = res.headers_mut();
+        headers.insert(
+            "cross-origin-opener-policy",
+            axum::http::HeaderValue::from_static("same-origin"),
+        );
+        headers.insert(
+            "cross-origin-embedder-policy",
+            axum::http::HeaderValue::from_static("require-corp"),
+        );
+    }
     Ok(res)
 }
 
-async fn handle_relay_socket(socket: WebSocket, state: AppState, params: RelayQuery) {
-    // Разделяем WebSocket на приёмник и отправитель
-    let (mut ws_sender, mut ws_receiver) = socket.split();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Создаём отдельный канал для отправки сообщений этому пиру
-    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+    async fn test_world() -> WorldState {
+        let config: WorldConfig = include_str!("../../../world-config.json")
+            .parse()
+            .expect("world-config.json parses");
+        let store = persist::MemoryStore::default();
+        let (world, _) = worlds::build("test", config, 64, None, &store)
+            .await
+            .expect("world builds");
+        world
+    }
 
-    // Фоновой таск, который шлёт в сокет всё, что приходит в tx
-    let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if ws_sender.send(msg).await.is_err() {
-                break;
-            }
+    /// Игрок `id` с ролью `role`, вошедший через новое соединение
+    fn join(world: &mut WorldState, id: &str, role: PlayerRole) -> conn::Outbox {
+        let tx = conn::Outbox::default();
+        let player = PlayerState {
+            id: id.into(),
+            role,
+            slot: free_slot(&world.players),
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            head_pos: None,
+            head_quat: None,
+            velocity: [0.0; 3],
+            last_input_id: 0,
+        };
+        world.players.insert(id.into(), player);
+        world.clients.insert(
+            id.into(),
+            ClientChannel {
+                sender: tx.clone(),
+                encoding: SnapshotEncoding::default(),
+                acked: None,
+                view: interest::ClientView::default(),
+                chunks: objects::ClientChunks::default(),
+                resume_token: session::new_token(),
+                whole_map: false,
+                features: protocol::Features::ALL,
+            },
+        );
+        tx
+    }
+
+    fn spoofed_input() -> (f32, f32, f32, Option<u32>) {
+        let text = r#"{"type":"input","client_id":"bob","dx":1.0,"dy":0.0,"dz":0.0}"#;
+        match serde_json::from_str(text).expect("input parses") {
+            ClientMessage::Input {
+                dx,
+                dy,
+                dz,
+                input_id,
+            } => (dx, dy, dz, input_id),
+            other => panic!("expected input, got {other:?}"),
         }
-    });
+    }
 
-    let role = params.role.to_lowercase();
-    let mut room_code = params.room.clone();
-    let mut player_id: Option<String> = None;
-
-    // Регистрация в состоянии
-    {
-        let mut relay = state.relay.lock().await;
-
-        if role == "host" {
-            // Создаём или берём комнату
-            let code = room_code.take().unwrap_or_else(|| generate_room_code());
-            room_code = Some(code.clone());
-
-            let room = relay
-                .rooms
-                .entry(code.clone())
-                .or_insert_with(|| RelayRoom {
-                    host: None,
-                    clients: HashMap::new(),
-                });
+    #[tokio::test]
+    async fn input_naming_another_client_is_rejected() {
+        let mut world = test_world().await;
+        let alice = join(&mut world, "alice", PlayerRole::Pc);
+        join(&mut world, "bob", PlayerRole::Pc);
+        let watcher = join(&mut world, "carol", PlayerRole::Spectator);
+        let (dx, dy, dz, input_id) = spoofed_input();
 
-            room.host = Some(RelayPeer { sender: tx.clone() });
-
-            // Сообщаем хосту код комнаты
-            let msg = serde_json::json!({
-                "type": "room_created",
-                "roomCode": code,
-            });
-            let _ = tx.send(Message::Text(msg.to_string()));
-        } else {
-            // client
-            let code = match room_code.clone() {
-                Some(c) => c,
-                None => {
-                    let err = serde_json::json!({
-                        "type": "error",
-                        "message": "Room code required",
-                    });
-                    let _ = tx.send(Message::Text(err.to_string()));
-                    return;
-                }
-            };
+        // соединение без join, чужое соединение и зритель не двигают bob
+        let stranger = conn::Outbox::default();
+        let none = None;
+        let bob = Some("bob".to_string());
+        let carol = Some("carol".to_string());
+        for (cid, tx, error) in [
+            (&none, &stranger, "not_joined"),
+            (&bob, &alice, "not_joined"),
+            (&carol, &watcher, "spectator"),
+        ] {
+            let queued = queue_input(&mut world, cid, tx, [dx, dy, dz], input_id);
+            assert_eq!(queued, Err(error));
+        }
+        assert!(world.inputs.is_empty());
 
-            let room = match relay.rooms.get_mut(&code) {
-                Some(r) => r,
-                None => {
-                    let err = serde_json::json!({
-                        "type": "error",
-                        "message": "Room not found or host offline",
-                    });
-                    let _ = tx.send(Message::Text(err.to_string()));
-                    return;
-                }
-            };
+        // `client_id` в теле ни на что не влияет: ввод alice — её игроку
+        let cid = Some("alice".to_string());
+        queue_input(&mut world, &cid, &alice, [dx, dy, dz], input_id).unwrap();
+        let ids: Vec<&str> = world.inputs.iter().map(|i| i.client_id.as_str()).collect();
+        assert_eq!(ids, ["alice"]);
+    }
 
-            if room.host.is_none() {
-                let err = serde_json::json!({
-                    "type": "error",
-                    "message": "Room not found or host offline",
-                });
-                let _ = tx.send(Message::Text(err.to_string()));
-                return;
-            }
+    #[tokio::test]
+    async fn input_from_a_replaced_connection_is_dropped() {
+        let mut world = test_world().await;
+        let old = join(&mut world, "alice", PlayerRole::Pc);
+        let cid = Some("alice".to_string());
+        queue_input(&mut world, &cid, &old, [1.0, 0.0, 0.0], Some(1)).unwrap();
 
-            let pid = format!(
-                "player_{}_{}",
-                chrono::Utc::now().timestamp_millis(),
-                rand::random::<u32>()
-            );
-            player_id = Some(pid.clone());
-
-            room.clients
-                .insert(pid.clone(), RelayPeer { sender: tx.clone() });
-
-            // Уведомляем клиента, что он подключился
-            let joined = serde_json::json!({
-                "type": "joined_room",
-                "roomCode": code,
-                "playerId": pid,
-            });
-            let _ = tx.send(Message::Text(joined.to_string()));
-
-            // Уведомляем хоста о новом игроке
-            if let Some(host) = &room.host {
-                let info = serde_json::json!({
-                    "type": "player_joined",
-                    "playerId": player_id,
-                    "totalPlayers": room.clients.len(),
-                });
-                let _ = host.sender.send(Message::Text(info.to_string()));
-            }
-        }
-    }
+        // до тика сессию забрало новое соединение alice
+        let new = conn::Outbox::default();
+        world.clients.get_mut("alice").unwrap().sender = new.clone();
+        queue_input(&mut world, &cid, &new, [0.0, 0.0, 2.0], Some(1)).unwrap();
+        tick::advance(&mut world, Duration::from_millis(50));
 
-    // Основной цикл приёма сообщений от этого пира и маршрутизация
-    let room_code_final = room_code.clone();
-    while let Some(Ok(msg)) = ws_receiver.next().await {
-        match msg {
-            Message::Binary(data) => {
-                // Бинарные кадры от host → всем клиентам в комнате
-                if role == "host" {
-                    if let Some(code) = &room_code_final {
-                        let mut relay = state.relay.lock().await;
-                        if let Some(room) = relay.rooms.get_mut(code) {
-                            let frame = data.clone();
-                            for client in room.clients.values() {
-                                let _ = client.sender.send(Message::Binary(frame.clone()));
-                            }
-                        }
-                    }
-                }
-            }
-            Message::Text(text) => {
-                // Текстовые сообщения пробрасываем: host→клиенты, client→host
-                if role == "host" {
-                    if let Some(code) = &room_code_final {
-                        let mut relay = state.relay.lock().await;
-                        if let Some(room) = relay.rooms.get_mut(code) {
-                            for client in room.clients.values() {
-                                let _ = client.sender.send(Message::Text(text.clone()));
-                            }
-                        }
-                    }
-                } else {
-                    if let Some(code) = &room_code_final {
-                        let mut relay = state.relay.lock().await;
-                        if let Some(room) = relay.rooms.get_mut(code) {
-                            if let Some(host) = &room.host {
-                                let _ = host.sender.send(Message::Text(text.clone()));
-                            }
-                        }
-                    }
-                }
-            }
-            Message::Close(_) => {
-                break;
-            }
-            _ => {}
-        }
+        let p = &world.players["alice"];
+        assert_eq!((p.x, p.z), (0.0, 2.0));
     }
 
-    // Очистка при отключении
-    {
-        let mut relay = state.relay.lock().await;
-        if let Some(code) = room_code {
-            if let Some(room) = relay.rooms.get_mut(&code) {
-                if role == "host" {
-                    // Уведомляем всех клиентов, что хост ушёл
-                    let msg = serde_json::json!({
-                        "type": "host_disconnected",
-                    });
-                    for client in room.clients.values() {
-                        let _ = client.sender.send(Message::Text(msg.to_string()));
-                    }
-                    room.host = None;
-                } else if let Some(pid) = player_id {
-                    room.clients.remove(&pid);
-                    // Опционально уведомляем хоста
-                    if let Some(host) = &room.host {
-                        let info = serde_json::json!({
-                            "type": "player_left",
-                            "playerId": pid,
-                            "totalPlayers": room.clients.len(),
-                        });
-                        let _ = host.sender.send(Message::Text(info.to_string()));
-                    }
-                }
+    /// Тип и номер снапшотов в очереди клиента, с базой у дельт
+    fn snapshots(tx: &conn::Outbox) -> Vec<(String, u64, Option<u64>)> {
+        tx.drain()
+            .into_iter()
+            .map(|msg| {
+                let Message::Text(text) = msg else {
+                    panic!("expected a JSON snapshot, got {msg:?}");
+                };
+                let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+                (
+                    v["type"].as_str().unwrap().to_string(),
+                    v["seq"].as_u64().unwrap(),
+                    v["base_seq"].as_u64(),
+                )
+            })
+            .collect()
+    }
 
-                if room.host.is_none() && room.clients.is_empty() {
-                    relay.rooms.remove(&code);
-                }
+    #[tokio::test]
+    async fn snapshots_are_deltas_from_the_acked_base() {
+        let mut world = test_world().await;
+        world.delta_sync = true;
+        world.interest = None;
+        let tx = join(&mut world, "alice", PlayerRole::Pc);
+        let world: WorldHandle = Arc::new(Mutex::new(world));
+        let set_acked = |acked| {
+            let world = world.clone();
+            async move {
+                world.lock().await.clients.get_mut("alice").unwrap().acked = acked;
             }
-        }
-    }
+        };
 
-    send_task.abort();
-}
+        // подтверждений ещё нет — полный снапшот
+        send_world_snapshot(&world).await;
+        let [(kind, first, _)] = snapshots(&tx).try_into().unwrap();
+        assert_eq!(kind, "world_snapshot");
 
-fn generate_room_code() -> String {
-    use rand::Rng;
-    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
-    let mut rng = rand::thread_rng();
-    (0..6)
-        .map(|_| {
-            let idx = rng.gen_range(0..ALPHABET.len());
-            ALPHABET[idx] as char
-        })
-        .collect()
+        set_acked(Some(first as u32)).await;
+        send_world_snapshot(&world).await;
+        assert_eq!(
+            snapshots(&tx),
+            [("world_delta".to_string(), first + 1, Some(first))]
+        );
+
+        // resync сбрасывает базу
+        set_acked(None).await;
+        send_world_snapshot(&world).await;
+        assert_eq!(snapshots(&tx)[0].0, "world_snapshot");
+
+        // подтверждённый снапшот выпал из истории — снова полный
+        set_acked(Some(first as u32)).await;
+        for _ in 0..sync::HISTORY_LEN {
+            world.lock().await.history.push(Arc::default());
+        }
+        send_world_snapshot(&world).await;
+        assert_eq!(snapshots(&tx)[0].0, "world_snapshot");
+    }
 }
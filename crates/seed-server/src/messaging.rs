@@ -0,0 +1,110 @@
+//! Прикладные сообщения поверх сокета мультиплеера: чат и произвольные
+//! `custom`-сообщения игры. Сервер их не разбирает, только проверяет размер
+//! и доставляет по маршруту: всем в мире, в зоне интереса отправителя или
+//! одному игроку. Отправитель всегда получает свою копию — так клиент
+//! знает, что сообщение прошло. Отправитель — игрок, вошедший через это
+//! соединение; до `join` сообщения отклоняются с `not_joined`.
+
+use crate::conn::Outbox;
+use crate::protocol::Feature;
+use crate::worlds::WorldHandle;
+use crate::ServerMessage;
+use serde::{Deserialize, Serialize};
+
+/// Длина текста чата в символах
+pub const MAX_CHAT_LEN: usize = 500;
+pub const MAX_CHANNEL_LEN: usize = 32;
+pub const MAX_KIND_LEN: usize = 64;
+/// Размер `payload` в JSON
+pub const MAX_PAYLOAD_BYTES: usize = 16 * 1024;
+
+/// Кому доставить сообщение
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "lowercase")]
+pub enum Route {
+    /// Всем клиентам мира
+    #[default]
+    Global,
+    /// Тем, кого видит отправитель (без зоны интереса — всем)
+    Area,
+    /// Одному игроку
+    Direct { to: String },
+}
+
+/// Проверяет лимиты чата; ошибка — код для `ServerMessage::Error`
+pub fn check_chat(channel: &str, text: &str) -> Result<(), &'static str> {
+    if channel.is_empty() || channel.len() > MAX_CHANNEL_LEN {
+        return Err("invalid_channel");
+    }
+    if text.trim().is_empty() {
+        return Err("empty_message");
+    }
+    if text.chars().count() > MAX_CHAT_LEN {
+        return Err("message_too_long");
+    }
+    Ok(())
+}
+
+pub fn check_custom(kind: &str, payload: &serde_json::Value) -> Result<(), &'static str> {
+    if kind.is_empty() || kind.len() > MAX_KIND_LEN {
+        return Err("invalid_kind");
+    }
+    let size = serde_json::to_vec(payload).map_or(usize::MAX, |v| v.len());
+    if size > MAX_PAYLOAD_BYTES {
+        return Err("payload_too_large");
+    }
+    Ok(())
+}
+
+/// Доставляет `msg` от игрока `from` по маршруту `route`
+pub async fn deliver(
+    world_state: &WorldHandle,
+    from: &str,
+    route: &Route,
+    msg: ServerMessage,
+) -> Result<(), &'static str> {
+    let recipients: Vec<Outbox> = {
+        let world = world_state.lock().await;
+        let Some(sender) = world.clients.get(from) else {
+            return Err("not_joined");
+        };
+        // сообщения получают только согласовавшие `messaging`
+        let clients = world
+            .clients
+            .iter()
+            .filter(|(_, c)| c.features.has(Feature::Messaging));
+        match route {
+            Route::Global => clients.map(|(_, c)| c.sender.clone()).collect(),
+            Route::Area if world.interest.is_none() => {
+                clients.map(|(_, c)| c.sender.clone()).collect()
+            }
+            Route::Area => {
+                let visible = sender.view.visible();
+                clients
+                    .filter(|(cid, _)| *cid == from || visible.contains(*cid))
+                    .map(|(_, c)| c.sender.clone())
+                    .collect()
+            }
+            Route::Direct { to } => {
+                let Some(target) = world.clients.get(to) else {
+                    return Err("unknown_recipient");
+                };
+                if !target.features.has(Feature::Messaging) {
+                    return Err("feature_disabled");
+                }
+                if to == from {
+                    vec![target.sender.clone()]
+                } else {
+                    vec![target.sender.clone(), sender.sender.clone()]
+                }
+            }
+        }
+    };
+    let Some(msg) = msg.to_text() else {
+        return Ok(());
+    };
+    for tx in recipients {
+        let _ = tx.send(msg.clone());
+    }
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Потоковая отдача процедурных объектов (деревья, камни, дома) по чанкам.
+//! Карта делится на чанки по `CHUNK_CELLS` клеток; клиенту приходят
+//! `objects_chunk` для чанков в зоне интереса его игрока и `objects_evict`,
+//! когда чанки из неё выпадают. Чанки генерируются при первом запросе и
+//! кэшируются в мире вместе с готовым сообщением. Вместе с чанком приходит
+//! его слой следов и вмятин (`deform`).
+//!
+//! Позиция игрока — метры от угла карты с клеткой (0, 0): x — вдоль
+//! столбцов, z — вдоль строк. Объекты в сообщениях — как в seed-core:
+//! x, y — клетки карты, z — нормализованная высота.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use axum::extract::ws::Message;
+use seed_config::WorldConfig;
+use seed_core::{generate_objects_for_chunk, BiomeMap, Heightmap};
+
+use crate::protocol::Feature;
+use crate::{ServerMessage, WorldState};
+
+/// Сторона чанка в клетках карты
+pub const CHUNK_CELLS: u32 = 32;
+/// Радиус в чанках, если зона интереса не задана
+const DEFAULT_RADIUS_CHUNKS: f64 = 1.0;
+/// Больше чанков вокруг игрока не отдаём, даже при большом радиусе
+const MAX_RADIUS_CHUNKS: f64 = 6.0;
+const HYSTERESIS: f64 = 0.1;
+const MAX_CACHED_CHUNKS: usize = 1024;
+
+pub type ChunkKey = (u32, u32);
+
+/// Сгенерированные чанки мира; самые старые вытесняются
+#[derive(Debug, Default)]
+pub struct ChunkCache {
+    chunks: HashMap<ChunkKey, Message>,
+    order: VecDeque<ChunkKey>,
+}
+
+impl ChunkCache {
+    fn get_or_generate(
+        &mut self,
+        key: ChunkKey,
+        cfg: &WorldConfig,
+        hm: &Heightmap,
+        bm: &BiomeMap,
+    ) -> Option<Message> {
+        if let Some(msg) = self.chunks.get(&key) {
+            return Some(msg.clone());
+        }
+        let (cx, cy) = key;
+        let objects = generate_objects_for_chunk(
+            cfg,
+            hm,
+            bm,
+            cx * CHUNK_CELLS,
+            cy * CHUNK_CELLS,
+            CHUNK_CELLS,
+            CHUNK_CELLS,
+            cfg.world_seed,
+        );
+        let msg = ServerMessage::ObjectsChunk {
+            chunk_x: cx,
+            chunk_y: cy,
+            chunk_size: CHUNK_CELLS,
+            objects,
+        }
+        .to_text()?;
+        if self.order.len() == MAX_CACHED_CHUNKS {
+            if let Some(old) = self.order.pop_front() {
+                self.chunks.remove(&old);
+            }
+        }
+        self.order.push_back(key);
+        self.chunks.insert(key, msg.clone());
+        Some(msg)
+    }
+
+    /// Забывает чанки, чтобы они сгенерировались заново (рельеф изменился)
+    fn invalidate(&mut self, keys: &[ChunkKey]) {
+        for key in keys {
+            self.chunks.remove(key);
+        }
+        self.order.retain(|k| !keys.contains(k));
+    }
+}
+
+/// Какие чанки уже есть у клиента
+#[derive(Debug, Default)]
+pub struct ClientChunks {
+    loaded: HashSet<ChunkKey>,
+    // Клетка игрока при последнем пересчёте
+    cell: Option<(i64, i64)>,
+}
+
+impl ClientChunks {
+    pub fn has(&self, key: ChunkKey) -> bool {
+        self.loaded.contains(&key)
+    }
+}
+
+/// Перегенерирует чанки `keys` и досылает их клиентам, у которых они
+/// загружены
+pub fn refresh(world: &mut WorldState, keys: &[ChunkKey]) {
+    let WorldState {
+        config,
+        heightmap,
+        biomemap,
+        clients,
+        object_chunks,
+        ..
+    } = world;
+    object_chunks.invalidate(keys);
+    for &key in keys {
+        if !clients.values().any(|c| c.chunks.has(key)) {
+            continue;
+        }
+        let Some(msg) = object_chunks.get_or_generate(key, config, heightmap, biomemap) else {
+            continue;
+        };
+        for c in clients.values().filter(|c| c.chunks.has(key)) {
+            c.sender.send(msg.clone());
+        }
+    }
+}
+
+/// Досылает клиентам чанки вокруг их игроков и снимает дальние
+pub fn stream(world: &mut WorldState) {
+    let WorldState {
+        config,
+        heightmap,
+        biomemap,
+        players,
+        clients,
+        interest,
+        object_chunks,
+        deform,
+        ..
+    } = world;
+    if heightmap.width == 0 || heightmap.height == 0 {
+        return;
+    }
+    let cell_m = config.scale.region_size_km * 1000.0 / heightmap.width as f64;
+    let chunk_m = cell_m * CHUNK_CELLS as f64;
+    let enter_m = interest
+        .map_or(DEFAULT_RADIUS_CHUNKS * chunk_m, |i| i.enter_m)
+        .min(MAX_RADIUS_CHUNKS * chunk_m);
+    let exit_m = enter_m * (1.0 + HYSTERESIS);
+    let chunks_x = heightmap.width.div_ceil(CHUNK_CELLS);
+    let chunks_y = heightmap.height.div_ceil(CHUNK_CELLS);
+
+    for (cid, client) in clients.iter_mut() {
+        let Some(p) = players.get(cid) else {
+            continue;
+        };
+        let (px, pz) = (p.x as f64, p.z as f64);
+        let cell = ((px / cell_m).floor() as i64, (pz / cell_m).floor() as i64);
+        if client.chunks.cell == Some(cell) {
+            continue;
+        }
+        client.chunks.cell = Some(cell);
+
+        // Расстояние от игрока до ближайшей точки чанка
+        let dist = |(cx, cy): ChunkKey| {
+            let x0 = cx as f64 * chunk_m;
+            let z0 = cy as f64 * chunk_m;
+            let dx = (x0 - px).max(px - (x0 + chunk_m)).max(0.0);
+            let dz = (z0 - pz).max(pz - (z0 + chunk_m)).max(0.0);
+            dx.hypot(dz)
+        };
+
+        let evicted: Vec<ChunkKey> = client
+            .chunks
+            .loaded
+            .iter()
+            .copied()
+            .filter(|&key| dist(key) > exit_m)
+            .collect();
+        for key in &evicted {
+            client.chunks.loaded.remove(key);
+        }
+        if !evicted.is_empty() {
+            if let Some(msg) = (ServerMessage::ObjectsEvict {
+                chunks: evicted.iter().map(|&(x, y)| [x, y]).collect(),
+            })
+            .to_text()
+            {
+                client.sender.send(msg);
+            }
+        }
+
+        let reach = (enter_m / chunk_m).ceil() as i64 + 1;
+        let (ccx, ccy) = ((px / chunk_m).floor() as i64, (pz / chunk_m).floor() as i64);
+        for cy in (ccy - reach).max(0)..=(ccy + reach).min(chunks_y as i64 - 1) {
+            for cx in (ccx - reach).max(0)..=(ccx + reach).min(chunks_x as i64 - 1) {
+                let key = (cx as u32, cy as u32);
+                if client.chunks.loaded.contains(&key) || dist(key) > enter_m {
+                    continue;
+                }
+                if let Some(msg) = object_chunks.get_or_generate(key, config, heightmap, biomemap) {
+                    client.sender.send(msg);
+                    client.chunks.loaded.insert(key);
+                    let deform = deform
+                        .chunk_message(key)
+                        .filter(|_| client.features.has(Feature::Deform));
+                    if let Some(msg) = deform.and_then(|m| m.to_text()) {
+                        client.sender.send(msg);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Поиск пути по рельефу для ИИ-агентов и меток квестов: `POST /api/path`
+//! и `{"type": "find_path", ...}` по WebSocket, чтобы клиентам не писать
+//! свой поиск.
+//!
+//! Запрос: `from` и `to` — точки `[x, z]` в метрах мира, как позиции
+//! игроков (`x` на восток, `z` на юг); `allow_water` — можно плыть;
+//! `max_slope_deg` — круче не пройти, по умолчанию 45°.
+//!
+//! Ищет `seed_core::pathfinding` без цен биомов. Ответ — путевые точки от
+//! `from` до `to` только в поворотах пути (`y` — высота поверхности земли
+//! или воды, м), длина пути по горизонтали и его стоимость. Ошибки:
+//! `out_of_bounds`, `bad_slope`, `no_path`, `busy` — все поиски заняты.
+//!
+//! По WebSocket ответ — `path` или `path_error` с `id` из запроса.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use seed_core::pathfinding::{self, PathOptions};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use crate::api::WorldQuery;
+use crate::worlds::{self, api_error, ApiError};
+use crate::{AppState, WorldHandle};
+
+/// Поиск на большой карте — сотни миллисекунд; больше разом не считаем
+const MAX_SEARCHES: usize = 4;
+
+static SEARCHES: Semaphore = Semaphore::const_new(MAX_SEARCHES);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathRequest {
+    from: [f32; 2],
+    to: [f32; 2],
+    #[serde(default)]
+    allow_water: bool,
+    #[serde(default)]
+    max_slope_deg: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Found {
+    pub waypoints: Vec<Waypoint>,
+    pub length_m: f32,
+    pub cost: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathResponse {
+    waypoints: Vec<Waypoint>,
+    length_m: f32,
+    cost: f32,
+}
+
+/// `POST /api/path`
+pub async fn find_path(
+    State(state): State<AppState>,
+    Query(query): Query<WorldQuery>,
+    Json(req): Json<PathRequest>,
+) -> Result<Json<PathResponse>, ApiError> {
+    let world = worlds::resolve(&state, query.world.as_deref())
+        .await
+        .map_err(|(status, message)| api_error(status, message))?;
+    let found = find(&world, req).await.map_err(|e| {
+        let status = match e {
+            "no_path" => StatusCode::UNPROCESSABLE_ENTITY,
+            "busy" => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        api_error(status, e)
+    })?;
+    Ok(Json(PathResponse {
+        waypoints: found.waypoints,
+        length_m: found.length_m,
+        cost: found.cost,
+    }))
+}
+
+/// Путь в мире; считается вне блокировки мира на копии карты
+pub async fn find(world: &WorldHandle, req: PathRequest) -> Result<Found, &'static str> {
+    let options = PathOptions {
+        allow_water: req.allow_water,
+        max_slope_deg: req
+            .max_slope_deg
+            .unwrap_or(PathOptions::default().max_slope_deg),
+        biome_costs: false,
+    };
+    if !(options.max_slope_deg > 0.0 && options.max_slope_deg <= 90.0) {
+        return Err("bad_slope");
+    }
+    let Ok(_permit) = SEARCHES.try_acquire() else {
+        return Err("busy");
+    };
+    let (hm, cfg) = {
+        let world = world.lock().await;
+        (world.heightmap.clone(), world.config.clone())
+    };
+    let cell_m = cfg.scale.region_size_km * 1000.0 / hm.width.max(1) as f64;
+    // клетка точки `[x, z]` или None за краем карты
+    let cell = |[x, z]: [f32; 2]| {
+        let (cx, cy) = (x as f64 / cell_m, z as f64 / cell_m);
+        let inside = cx >= 0.0 && cy >= 0.0 && cx < hm.width as f64 && cy < hm.height as f64;
+        inside.then_some((cx as u32, cy as u32))
+    };
+    let (Some(start), Some(goal)) = (cell(req.from), cell(req.to)) else {
+        return Err("out_of_bounds");
+    };
+    tokio::task::spawn_blocking(move || {
+        let path =
+            pathfinding::find_path(&cfg, &hm, None, start, goal, &options).map_err(|e| e.code())?;
+        let surface = |(x, y): (u32, u32)| pathfinding::surface_m(&cfg, &hm, x, y) as f32;
+        let centre = |v: u32| ((v as f64 + 0.5) * cell_m) as f32;
+
+        // точки запроса на концах, центры клеток в поворотах между ними
+        let turns = path.turns();
+        let inner = if turns.len() > 2 {
+            &turns[1..turns.len() - 1]
+        } else {
+            &[]
+        };
+        let mut waypoints = vec![Waypoint {
+            x: req.from[0],
+            y: surface(start),
+            z: req.from[1],
+        }];
+        waypoints.extend(inner.iter().map(|&c| Waypoint {
+            x: centre(c.0),
+            y: surface(c),
+            z: centre(c.1),
+        }));
+        waypoints.push(Waypoint {
+            x: req.to[0],
+            y: surface(goal),
+            z: req.to[1],
+        });
+        let length_m = waypoints
+            .windows(2)
+            .map(|p| (p[1].x - p[0].x).hypot(p[1].z - p[0].z))
+            .sum();
+        Ok(Found {
+            waypoints,
+            length_m,
+            cost: path.cost as f32,
+        })
+    })
+    .await
+    .map_err(|_| "no_path")?
+}
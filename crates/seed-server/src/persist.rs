@@ -0,0 +1,475 @@
+//! Сохранение мира между перезапусками сервера. Хранилище скрыто за
+//! `WorldStore` и выбирается `--store`: файлы (`FileStore`), SQLite
+//! (`sqlite::SqliteStore`, фича `sqlite`) или память (`MemoryStore` — ничего не
+//! переживает перезапуск, для временных серверов и проверок).
+//!
+//! Мир хранится бандлом `.seedworld` (seed-save) с текущей heightmap и
+//! историей мира. Изменённые катастрофами чанки рельефа сохраняются
+//! отдельно (`TerrainDelta`), без перезаписи всего бандла, и накладываются
+//! на него при загрузке; при остановке бандл пишется целиком, и дельты
+//! больше не нужны. Игроки — все известные серверу, включая
+//! отключившихся: при повторном входе игрок появляется там, где вышел.
+//! Часы мира — отдельно, чтобы время суток не сбрасывалось при перезапуске.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use seed_core::Heightmap;
+use seed_save::WorldBundle;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::clock::WorldClock;
+use crate::objects::{ChunkKey, CHUNK_CELLS};
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteStore;
+use crate::worlds::WorldHandle;
+use crate::{PlayerState, WorldState};
+
+/// Как часто сохранять игроков и часы; мир — при создании и при остановке
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+pub trait WorldStore: Send + Sync {
+    fn load_world(&self) -> Result<Option<WorldBundle>>;
+    fn save_world(&self, bundle: &WorldBundle) -> Result<()>;
+    fn load_players(&self) -> Result<Vec<PlayerState>>;
+    /// Все известные игроки; прежний список заменяется
+    fn save_players(&self, players: &[PlayerState]) -> Result<()>;
+    fn load_clock(&self) -> Result<Option<WorldClock>>;
+    fn save_clock(&self, clock: &WorldClock) -> Result<()>;
+    /// Чанки рельефа, изменённые после сохранения бандла
+    fn load_terrain(&self) -> Result<Vec<TerrainDelta>>;
+    /// Добавляет чанки; сохранённый раньше тот же чанк заменяется
+    fn save_terrain(&self, deltas: &[TerrainDelta]) -> Result<()>;
+    /// Бандл сохранён с текущим рельефом — дельты больше не нужны
+    fn clear_terrain(&self) -> Result<()>;
+}
+
+/// Хранилище миров
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StoreKind {
+    File,
+    Sqlite,
+    Memory,
+}
+
+/// Хранилище мира с каталогом `dir`
+pub fn open_store(kind: StoreKind, dir: &Path) -> Result<Arc<dyn WorldStore>> {
+    Ok(match kind {
+        StoreKind::File => Arc::new(FileStore::new(dir)),
+        #[cfg(feature = "sqlite")]
+        StoreKind::Sqlite => Arc::new(SqliteStore::open(dir)?),
+        #[cfg(not(feature = "sqlite"))]
+        StoreKind::Sqlite => {
+            anyhow::bail!("--store sqlite: seed-server is built without the `sqlite` feature")
+        }
+        StoreKind::Memory => Arc::new(MemoryStore::default()),
+    })
+}
+
+/// Высоты чанка после изменения рельефа, строками по `width`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerrainDelta {
+    pub chunk_x: u32,
+    pub chunk_y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub heights: Vec<f32>,
+}
+
+impl TerrainDelta {
+    /// Текущие высоты чанка `key`
+    pub fn capture(hm: &Heightmap, (cx, cy): ChunkKey) -> Self {
+        let (x0, y0) = (cx * CHUNK_CELLS, cy * CHUNK_CELLS);
+        let width = CHUNK_CELLS.min(hm.width - x0);
+        let height = CHUNK_CELLS.min(hm.height - y0);
+        let mut heights = Vec::with_capacity((width * height) as usize);
+        for y in y0..y0 + height {
+            for x in x0..x0 + width {
+                heights.push(hm.get(x, y));
+            }
+        }
+        Self {
+            chunk_x: cx,
+            chunk_y: cy,
+            width,
+            height,
+            heights,
+        }
+    }
+
+    /// Кладёт высоты на карту; false — чанк не с этой карты
+    pub fn apply(&self, hm: &mut Heightmap) -> bool {
+        let (x0, y0) = (self.chunk_x * CHUNK_CELLS, self.chunk_y * CHUNK_CELLS);
+        let fits = x0.checked_add(self.width).is_some_and(|x1| x1 <= hm.width)
+            && y0
+                .checked_add(self.height)
+                .is_some_and(|y1| y1 <= hm.height)
+            && self.heights.len() as u64 == self.width as u64 * self.height as u64;
+        if !fits {
+            return false;
+        }
+        for (i, &h) in self.heights.iter().enumerate() {
+            let (x, y) = (i as u32 % self.width, i as u32 / self.width);
+            hm.values[((y0 + y) * hm.width + x0 + x) as usize] = h;
+        }
+        true
+    }
+}
+
+/// Файлы в каталоге: `world.seedworld`, `players.json`, `clock.json` и
+/// `terrain.json`
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn world_path(&self) -> PathBuf {
+        self.dir.join(format!("world.{}", seed_save::EXTENSION))
+    }
+
+    fn players_path(&self) -> PathBuf {
+        self.dir.join("players.json")
+    }
+
+    fn clock_path(&self) -> PathBuf {
+        self.dir.join("clock.json")
+    }
+
+    fn terrain_path(&self) -> PathBuf {
+        self.dir.join("terrain.json")
+    }
+}
+
+impl WorldStore for FileStore {
+    fn load_world(&self) -> Result<Option<WorldBundle>> {
+        let path = self.world_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bundle =
+            WorldBundle::load(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(Some(bundle))
+    }
+
+    fn save_world(&self, bundle: &WorldBundle) -> Result<()> {
+        write_atomic(&self.world_path(), &bundle.to_bytes()?)
+    }
+
+    fn load_players(&self) -> Result<Vec<PlayerState>> {
+        let path = self.players_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    fn save_players(&self, players: &[PlayerState]) -> Result<()> {
+        write_atomic(&self.players_path(), &serde_json::to_vec_pretty(players)?)
+    }
+
+    fn load_clock(&self) -> Result<Option<WorldClock>> {
+        let path = self.clock_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(Some(serde_json::from_slice(&data)?))
+    }
+
+    fn save_clock(&self, clock: &WorldClock) -> Result<()> {
+        write_atomic(&self.clock_path(), &serde_json::to_vec_pretty(clock)?)
+    }
+
+    fn load_terrain(&self) -> Result<Vec<TerrainDelta>> {
+        let path = self.terrain_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    fn save_terrain(&self, deltas: &[TerrainDelta]) -> Result<()> {
+        let mut all: BTreeMap<ChunkKey, TerrainDelta> = self
+            .load_terrain()?
+            .into_iter()
+            .map(|d| ((d.chunk_x, d.chunk_y), d))
+            .collect();
+        for d in deltas {
+            all.insert((d.chunk_x, d.chunk_y), d.clone());
+        }
+        let all: Vec<&TerrainDelta> = all.values().collect();
+        write_atomic(&self.terrain_path(), &serde_json::to_vec(&all)?)
+    }
+
+    fn clear_terrain(&self) -> Result<()> {
+        match fs::remove_file(self.terrain_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Всё в памяти процесса
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    world: Mutex<Option<WorldBundle>>,
+    players: Mutex<Vec<PlayerState>>,
+    clock: Mutex<Option<WorldClock>>,
+    terrain: Mutex<BTreeMap<ChunkKey, TerrainDelta>>,
+}
+
+impl MemoryStore {
+    /// Хранилище с уже сохранёнными игроками и часами
+    pub fn with(players: Vec<PlayerState>, clock: Option<WorldClock>) -> Self {
+        Self {
+            players: Mutex::new(players),
+            clock: Mutex::new(clock),
+            ..Self::default()
+        }
+    }
+}
+
+fn locked<T>(m: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl WorldStore for MemoryStore {
+    fn load_world(&self) -> Result<Option<WorldBundle>> {
+        Ok(locked(&self.world).clone())
+    }
+
+    fn save_world(&self, bundle: &WorldBundle) -> Result<()> {
+        *locked(&self.world) = Some(bundle.clone());
+        Ok(())
+    }
+
+    fn load_players(&self) -> Result<Vec<PlayerState>> {
+        Ok(locked(&self.players).clone())
+    }
+
+    fn save_players(&self, players: &[PlayerState]) -> Result<()> {
+        *locked(&self.players) = players.to_vec();
+        Ok(())
+    }
+
+    fn load_clock(&self) -> Result<Option<WorldClock>> {
+        Ok(locked(&self.clock).clone())
+    }
+
+    fn save_clock(&self, clock: &WorldClock) -> Result<()> {
+        *locked(&self.clock) = Some(clock.clone());
+        Ok(())
+    }
+
+    fn load_terrain(&self) -> Result<Vec<TerrainDelta>> {
+        Ok(locked(&self.terrain).values().cloned().collect())
+    }
+
+    fn save_terrain(&self, deltas: &[TerrainDelta]) -> Result<()> {
+        let mut terrain = locked(&self.terrain);
+        for d in deltas {
+            terrain.insert((d.chunk_x, d.chunk_y), d.clone());
+        }
+        Ok(())
+    }
+
+    fn clear_terrain(&self) -> Result<()> {
+        locked(&self.terrain).clear();
+        Ok(())
+    }
+}
+
+/// Пишет во временный файл и переименовывает: прерванная запись не портит
+/// прошлое сохранение
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data).with_context(|| format!("writing {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
+
+/// Все известные игроки: в сети и отключившиеся
+pub async fn snapshot_players(world: &WorldHandle) -> Vec<PlayerState> {
+    let world = world.lock().await;
+    let mut players: HashMap<&String, &PlayerState> = world.offline_players.iter().collect();
+    players.extend(world.players.iter());
+    players.into_values().cloned().collect()
+}
+
+pub async fn save_players(world: &WorldHandle, store: &dyn WorldStore) {
+    let players = snapshot_players(world).await;
+    let name = world.lock().await.name.clone();
+    match store.save_players(&players) {
+        Ok(()) => info!("[{}] Saved {} players", name, players.len()),
+        Err(e) => error!("[{}] Failed to save players: {:#}", name, e),
+    }
+}
+
+pub async fn save_clock(world: &WorldHandle, store: &dyn WorldStore) {
+    let (name, clock) = {
+        let world = world.lock().await;
+        (world.name.clone(), world.clock.clone())
+    };
+    if let Err(e) = store.save_clock(&clock) {
+        error!("[{}] Failed to save clock: {:#}", name, e);
+    }
+}
+
+/// Бандл с текущим рельефом и историей мира
+pub fn bundle(world: &WorldState) -> WorldBundle {
+    WorldBundle {
+        config: world.config.clone(),
+        heightmap: world.heightmap.clone(),
+        biomes: world.biomemap.clone(),
+        objects: None,
+        history: Some(world.catastrophes.history().clone()),
+        catastrophes: None,
+    }
+}
+
+pub async fn save_world(world: &WorldHandle, store: &dyn WorldStore) {
+    let (name, bundle) = {
+        let world = world.lock().await;
+        (world.name.clone(), bundle(&world))
+    };
+    // в бандле текущий рельеф — дельты поверх него больше не нужны
+    match store
+        .save_world(&bundle)
+        .and_then(|()| store.clear_terrain())
+    {
+        Ok(()) => info!("[{}] Saved world bundle", name),
+        Err(e) => error!("[{}] Failed to save world: {:#}", name, e),
+    }
+}
+
+/// Сохраняет чанки рельефа, изменённые с прошлого сохранения
+pub async fn save_terrain(world: &WorldHandle, store: &dyn WorldStore) {
+    let (name, deltas) = {
+        let mut world = world.lock().await;
+        let chunks = world.catastrophes.take_unsaved();
+        let deltas: Vec<TerrainDelta> = chunks
+            .into_iter()
+            .map(|key| TerrainDelta::capture(&world.heightmap, key))
+            .collect();
+        (world.name.clone(), deltas)
+    };
+    if deltas.is_empty() {
+        return;
+    }
+    match store.save_terrain(&deltas) {
+        Ok(()) => info!("[{}] Saved {} changed terrain chunks", name, deltas.len()),
+        Err(e) => error!("[{}] Failed to save terrain: {:#}", name, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use seed_config::WorldConfig;
+
+    use crate::PlayerRole;
+
+    fn sample_world() -> (WorldBundle, Vec<PlayerState>, WorldClock, TerrainDelta) {
+        let config: WorldConfig = include_str!("../../../world-config.json")
+            .parse()
+            .expect("world-config.json parses");
+        let heightmap = seed_core::generate_heightmap_from_config(&config, 48, 40);
+        let biomes = seed_core::generate_biome_map_from_config(&config, &heightmap);
+        let terrain = TerrainDelta::capture(&heightmap, (0, 0));
+        let player: PlayerState = serde_json::from_value(serde_json::json!({
+            "id": "alice", "role": "pc", "x": 1.5, "y": 2.0, "z": -3.25,
+            "head_pos": null, "head_quat": null, "last_input_id": 7,
+        }))
+        .expect("player parses");
+        let mut clock = WorldClock::new(&config);
+        clock.time.elapsed_s += 1234.5;
+        let bundle = WorldBundle {
+            config,
+            heightmap,
+            biomes,
+            objects: None,
+            history: None,
+            catastrophes: None,
+        };
+        (bundle, vec![player], clock, terrain)
+    }
+
+    /// Сохраняет мир, игроков, часы и чанк рельефа и читает их обратно
+    fn round_trip(store: &dyn WorldStore) {
+        let (bundle, players, clock, terrain) = sample_world();
+        assert!(store.load_world().unwrap().is_none());
+
+        store.save_world(&bundle).unwrap();
+        store.save_players(&players).unwrap();
+        store.save_clock(&clock).unwrap();
+        store.save_terrain(std::slice::from_ref(&terrain)).unwrap();
+
+        let world = store.load_world().unwrap().expect("world saved");
+        assert_eq!(world.heightmap.width, bundle.heightmap.width);
+        assert_eq!(world.heightmap.height, bundle.heightmap.height);
+        assert_eq!(world.heightmap.values, bundle.heightmap.values);
+        assert_eq!(world.biomes.indices, bundle.biomes.indices);
+        assert_eq!(
+            seed_save::config_hash(&world.config).unwrap(),
+            seed_save::config_hash(&bundle.config).unwrap()
+        );
+
+        let loaded = store.load_players().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].id, "alice");
+        assert_eq!(loaded[0].role, PlayerRole::Pc);
+        assert_eq!(
+            (loaded[0].x, loaded[0].y, loaded[0].z),
+            (players[0].x, players[0].y, players[0].z)
+        );
+        assert_eq!(loaded[0].last_input_id, 7);
+
+        assert_eq!(store.load_clock().unwrap().unwrap().time, clock.time);
+
+        let deltas = store.load_terrain().unwrap();
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].heights, terrain.heights);
+        store.clear_terrain().unwrap();
+        assert!(store.load_terrain().unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_store_round_trips_a_world() {
+        round_trip(&MemoryStore::default());
+    }
+
+    #[test]
+    fn file_store_round_trips_a_world() {
+        let dir = std::env::temp_dir().join(format!("seed-persist-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        round_trip(&FileStore::new(&dir));
+        // в новом FileStore на том же каталоге — то же самое
+        let store = FileStore::new(&dir);
+        assert!(store.load_world().unwrap().is_some());
+        assert_eq!(store.load_players().unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store_round_trips_a_world() {
+        let dir = std::env::temp_dir().join(format!("seed-sqlite-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        round_trip(&SqliteStore::open(&dir).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
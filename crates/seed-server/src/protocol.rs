@@ -0,0 +1,318 @@
+//! Версия протокола и обмен возможностями. Первым сообщением клиент шлёт
+//! `hello` со своей версией протокола, кодировками снапшотов, кодеками
+//! сжатия и флагами возможностей; сервер отвечает своим `hello` с
+//! согласованным: версия — меньшая из двух, списки — пересечение (кодеки —
+//! в порядке предпочтения клиента, первый из них — `preferred_compression`).
+//!
+//! Флаги возможностей (`Feature`) включают поведение сервера для этого
+//! соединения: без флага сервер не шлёт клиенту сообщений этой возможности
+//! (погоду, катастрофы, дельты снапшотов...), а запросы к ней отклоняет с
+//! `feature_disabled`. Клиент без `hello` или без списка флагов получает всё.
+//!
+//! Клиент новее сервера работает на версии сервера. Клиент старше
+//! `MIN_PROTOCOL_VERSION` получает `hello` сервера (чтобы знать, какие
+//! версии поддерживаются), `error` с `unsupported_protocol`, и соединение
+//! закрывается. Клиент без `hello` считается клиентом версии
+//! `LEGACY_PROTOCOL_VERSION` и работает как раньше: всё решает `join`.
+//!
+//! После `hello` `join` не выходит за согласованное: двоичная кодировка,
+//! которую клиент не назвал, заменяется JSON, а без своего списка кодеков
+//! `join` берёт названные в `hello`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::compress::Codec;
+use crate::snapshot::SnapshotEncoding;
+
+pub const PROTOCOL_VERSION: u32 = 2;
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Версия клиентов, которые не шлют `hello`
+pub const LEGACY_PROTOCOL_VERSION: u32 = 1;
+
+const ENCODINGS: &[SnapshotEncoding] = &[SnapshotEncoding::Json, SnapshotEncoding::Binary];
+const CODECS: &[Codec] = &[Codec::Zstd, Codec::Deflate];
+
+/// Возможность, которую клиент может не брать
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// Дельты снапшотов после `ack`; без неё — только полные снапшоты
+    DeltaSync,
+    /// Зона интереса; без неё в снапшотах вся карта
+    Interest,
+    /// Возобновление сессии; без неё игрок уходит сразу при обрыве
+    Resume,
+    /// `input_id` во вводе и `last_input_id` в ответ
+    InputIds,
+    /// `chat` и `custom`
+    Messaging,
+    /// Следы и вмятины: запросы и `deform_delta`
+    Deform,
+    Weather,
+    Catastrophes,
+    /// `terrain_chunk` после изменений рельефа
+    TerrainChunks,
+    /// `terrain_nodes`
+    TerrainLod,
+    /// `find_path`
+    Pathfinding,
+}
+
+impl Feature {
+    const ALL: [Feature; 11] = [
+        Feature::DeltaSync,
+        Feature::Interest,
+        Feature::Resume,
+        Feature::InputIds,
+        Feature::Messaging,
+        Feature::Deform,
+        Feature::Weather,
+        Feature::Catastrophes,
+        Feature::TerrainChunks,
+        Feature::TerrainLod,
+        Feature::Pathfinding,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Feature::DeltaSync => "delta_sync",
+            Feature::Interest => "interest",
+            Feature::Resume => "resume",
+            Feature::InputIds => "input_ids",
+            Feature::Messaging => "messaging",
+            Feature::Deform => "deform",
+            Feature::Weather => "weather",
+            Feature::Catastrophes => "catastrophes",
+            Feature::TerrainChunks => "terrain_chunks",
+            Feature::TerrainLod => "terrain_lod",
+            Feature::Pathfinding => "pathfinding",
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Согласованные возможности соединения
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features(u32);
+
+impl Features {
+    pub const ALL: Features = Features((1 << Feature::ALL.len()) - 1);
+
+    pub fn has(self, f: Feature) -> bool {
+        self.0 & f.bit() != 0
+    }
+
+    /// Названные клиентом; незнакомые пропускаются
+    fn from_names(names: &[String]) -> Self {
+        let bits = Feature::ALL
+            .iter()
+            .filter(|f| names.iter().any(|n| n == f.name()))
+            .fold(0, |bits, f| bits | f.bit());
+        Features(bits)
+    }
+
+    fn names(self) -> Vec<String> {
+        Feature::ALL
+            .iter()
+            .filter(|f| self.has(**f))
+            .map(|f| f.name().to_string())
+            .collect()
+    }
+}
+
+/// Ответный `hello`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerHello {
+    /// Согласованная версия
+    pub protocol: u32,
+    pub min_protocol: u32,
+    pub max_protocol: u32,
+    pub server_version: String,
+    pub encodings: Vec<SnapshotEncoding>,
+    /// Кодеки, которые знают обе стороны, в порядке предпочтения клиента
+    pub compression: Vec<Codec>,
+    /// Первый из `compression`: его возьмёт `join` без своего списка
+    pub preferred_compression: Option<Codec>,
+    pub features: Vec<String>,
+}
+
+/// Итог `hello` для соединения
+#[derive(Debug, Clone)]
+pub struct Negotiated {
+    pub protocol: u32,
+    pub features: Features,
+    encodings: Vec<SnapshotEncoding>,
+    compression: Vec<Codec>,
+}
+
+impl Negotiated {
+    /// Кодировка для `join` в пределах согласованного
+    pub fn encoding(&self, requested: SnapshotEncoding) -> SnapshotEncoding {
+        if self.encodings.contains(&requested) {
+            requested
+        } else {
+            SnapshotEncoding::Json
+        }
+    }
+
+    /// Кодек для `join`: первый из его списка, согласованный в `hello`;
+    /// без списка — предпочтительный из `hello`
+    pub fn codec(&self, offered: &[String]) -> Option<Codec> {
+        if offered.is_empty() {
+            return self.compression.first().copied();
+        }
+        offered
+            .iter()
+            .filter_map(|c| Codec::parse(c))
+            .find(|c| self.compression.contains(c))
+    }
+}
+
+fn encoding_name(e: SnapshotEncoding) -> &'static str {
+    match e {
+        SnapshotEncoding::Json => "json",
+        SnapshotEncoding::Binary => "binary",
+    }
+}
+
+/// `hello` сервера в ответ клиенту; None вторым — версия клиента не
+/// поддерживается
+pub fn negotiate(
+    protocol: u32,
+    encodings: &[String],
+    compression: &[String],
+    features: Option<&[String]>,
+) -> (ServerHello, Option<Negotiated>) {
+    // без списка кодировок клиент умеет только JSON
+    let encodings: Vec<SnapshotEncoding> = ENCODINGS
+        .iter()
+        .copied()
+        .filter(|e| {
+            *e == SnapshotEncoding::Json || encodings.iter().any(|o| o == encoding_name(*e))
+        })
+        .collect();
+    let mut codecs: Vec<Codec> = Vec::new();
+    for c in compression.iter().filter_map(|c| Codec::parse(c)) {
+        if CODECS.contains(&c) && !codecs.contains(&c) {
+            codecs.push(c);
+        }
+    }
+    // без флагов клиент ничего не ограничивает
+    let features = features.map_or(Features::ALL, Features::from_names);
+    let supported = (MIN_PROTOCOL_VERSION..).contains(&protocol);
+    let hello = ServerHello {
+        protocol: if supported {
+            protocol.min(PROTOCOL_VERSION)
+        } else {
+            PROTOCOL_VERSION
+        },
+        min_protocol: MIN_PROTOCOL_VERSION,
+        max_protocol: PROTOCOL_VERSION,
+        server_version: env!("CARGO_PKG_VERSION").to_string(),
+        encodings: encodings.clone(),
+        preferred_compression: codecs.first().copied(),
+        compression: codecs.clone(),
+        features: features.names(),
+    };
+    if !supported {
+        return (hello, None);
+    }
+    let negotiated = Negotiated {
+        protocol: hello.protocol,
+        features,
+        encodings,
+        compression: codecs,
+    };
+    (hello, Some(negotiated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn version_is_the_lower_of_the_two() {
+        let (hello, negotiated) = negotiate(PROTOCOL_VERSION + 5, &[], &[], None);
+        assert_eq!(hello.protocol, PROTOCOL_VERSION);
+        assert_eq!(negotiated.unwrap().protocol, PROTOCOL_VERSION);
+
+        let (hello, negotiated) = negotiate(MIN_PROTOCOL_VERSION, &[], &[], None);
+        assert_eq!(hello.protocol, MIN_PROTOCOL_VERSION);
+        assert_eq!(negotiated.unwrap().protocol, MIN_PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn too_old_client_is_refused_with_the_supported_range() {
+        let (hello, negotiated) = negotiate(MIN_PROTOCOL_VERSION - 1, &[], &[], None);
+        assert!(negotiated.is_none());
+        assert_eq!(hello.protocol, PROTOCOL_VERSION);
+        assert_eq!(
+            (hello.min_protocol, hello.max_protocol),
+            (MIN_PROTOCOL_VERSION, PROTOCOL_VERSION)
+        );
+    }
+
+    #[test]
+    fn codecs_are_the_intersection_in_client_order() {
+        let offered = names(&["brotli", "deflate", "zstd", "deflate"]);
+        let (hello, negotiated) = negotiate(PROTOCOL_VERSION, &[], &offered, None);
+        assert_eq!(hello.compression, [Codec::Deflate, Codec::Zstd]);
+        assert_eq!(hello.preferred_compression, Some(Codec::Deflate));
+
+        let negotiated = negotiated.unwrap();
+        // `join` без списка берёт предпочтительный, со списком — первый
+        // согласованный из своего
+        assert_eq!(negotiated.codec(&[]), Some(Codec::Deflate));
+        assert_eq!(negotiated.codec(&names(&["zstd"])), Some(Codec::Zstd));
+        assert_eq!(negotiated.codec(&names(&["brotli"])), None);
+
+        let (hello, negotiated) = negotiate(PROTOCOL_VERSION, &[], &names(&["brotli"]), None);
+        assert!(hello.compression.is_empty());
+        assert_eq!(hello.preferred_compression, None);
+        assert_eq!(negotiated.unwrap().codec(&names(&["zstd"])), None);
+    }
+
+    #[test]
+    fn encodings_always_include_json() {
+        let (hello, negotiated) = negotiate(PROTOCOL_VERSION, &[], &[], None);
+        assert_eq!(hello.encodings, [SnapshotEncoding::Json]);
+        let negotiated = negotiated.unwrap();
+        assert_eq!(
+            negotiated.encoding(SnapshotEncoding::Binary),
+            SnapshotEncoding::Json
+        );
+
+        let (hello, negotiated) = negotiate(PROTOCOL_VERSION, &names(&["binary"]), &[], None);
+        assert_eq!(
+            hello.encodings,
+            [SnapshotEncoding::Json, SnapshotEncoding::Binary]
+        );
+        assert_eq!(
+            negotiated.unwrap().encoding(SnapshotEncoding::Binary),
+            SnapshotEncoding::Binary
+        );
+    }
+
+    #[test]
+    fn features_are_only_those_both_sides_know() {
+        let offered = names(&["weather", "teleport", "delta_sync"]);
+        let (hello, negotiated) = negotiate(PROTOCOL_VERSION, &[], &[], Some(&offered));
+        assert_eq!(hello.features, names(&["delta_sync", "weather"]));
+        let features = negotiated.unwrap().features;
+        assert!(features.has(Feature::Weather));
+        assert!(features.has(Feature::DeltaSync));
+        assert!(!features.has(Feature::Catastrophes));
+        assert!(!features.has(Feature::Messaging));
+
+        // без списка флагов — всё
+        let (hello, negotiated) = negotiate(PROTOCOL_VERSION, &[], &[], None);
+        assert_eq!(hello.features.len(), Feature::ALL.len());
+        assert_eq!(negotiated.unwrap().features, Features::ALL);
+    }
+}
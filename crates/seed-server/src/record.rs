@@ -0,0 +1,388 @@
+//! Запись сессии и её воспроизведение. С `--record <файл>` сервер пишет
+//! мир по умолчанию с момента открытия: всё, что меняет симуляцию (входы и
+//! уходы игроков, ввод, позы VR, следы и вмятины), по тикам вместе с `dt`
+//! тика и контрольной суммой мира после него. `--replay <файл>` поднимает
+//! мир из записи без сети, прогоняет те же тики с теми же `dt` и сверяет
+//! суммы: первое расхождение — тик, с которого симуляция стала
+//! недетерминированной.
+//!
+//! Устройство файла:
+//!
+//! ```text
+//! "SEEDSESS" | u32 LE версия | DEFLATE( запись заголовка | запись бандла | записи тиков )
+//! ```
+//!
+//! Запись — u32 LE длина и данные. Заголовок и тики — JSON, бандл —
+//! `.seedworld` мира на момент старта (seed-save). Поток сбрасывается на
+//! диск каждые `FLUSH_FRAMES` тиков, так что запись, оборванная падением
+//! сервера, читается до последнего сброса.
+//!
+//! Контрольная сумма покрывает номер тика, часы мира и игроков (позиция,
+//! скорость, последний ввод). Следы и вмятины воспроизводятся, но в сумму
+//! не входят: их выцветание идёт по настенным часам.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use seed_save::WorldBundle;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::clock::WorldClock;
+use crate::persist::{self, MemoryStore};
+use crate::{deform, tick, worlds, PlayerState, WorldState};
+
+const MAGIC: &[u8; 8] = b"SEEDSESS";
+const FORMAT_VERSION: u32 = 1;
+const FLUSH_FRAMES: u64 = 256;
+
+/// Что было с миром до первого тика записи
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    world: String,
+    map_size: u32,
+    tick: u64,
+    clock: WorldClock,
+    /// Известные миру игроки не в сети
+    players: Vec<PlayerState>,
+}
+
+/// Изменение мира между тиками
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Игрок вошёл или вернулся в мир — состояние сразу после входа
+    Join { player: PlayerState, slot: u16 },
+    /// Соединение оборвалось, игрок ждёт возобновления сессии
+    Disconnect { client_id: String },
+    /// Игрок ушёл из мира
+    Leave { client_id: String },
+    Input {
+        client_id: String,
+        delta: [f32; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        input_id: Option<u32>,
+    },
+    VrPose {
+        client_id: String,
+        head_pos: [f32; 3],
+        head_quat: [f32; 4],
+    },
+    Footprint {
+        client_id: String,
+        kind: String,
+        x: f32,
+        z: f32,
+        heading: f32,
+    },
+    Deform {
+        client_id: String,
+        x: f32,
+        z: f32,
+        radius: f32,
+        depth: f32,
+    },
+}
+
+/// Тик записи: события перед ним, его `dt` и сумма мира после
+#[derive(Debug, Serialize, Deserialize)]
+struct Frame {
+    tick: u64,
+    dt_ns: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    events: Vec<Event>,
+    checksum: u64,
+}
+
+#[derive(Debug)]
+pub struct Recorder {
+    out: DeflateEncoder<BufWriter<File>>,
+    // События с прошлого тика
+    pending: Vec<Event>,
+    frames: u64,
+}
+
+impl Recorder {
+    /// Начинает запись мира, который ещё не тикал
+    pub fn create(path: &Path, world: &WorldState) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+        file.write_all(MAGIC)?;
+        file.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        let mut out = DeflateEncoder::new(file, Compression::default());
+        let header = Header {
+            world: world.name.clone(),
+            map_size: world.heightmap.width,
+            tick: world.tick,
+            clock: world.clock.clone(),
+            players: world.offline_players.values().cloned().collect(),
+        };
+        write_record(&mut out, &serde_json::to_vec(&header)?)?;
+        write_record(&mut out, &persist::bundle(world).to_bytes()?)?;
+        info!("[{}] Recording session to {}", world.name, path.display());
+        Ok(Self {
+            out,
+            pending: Vec::new(),
+            frames: 0,
+        })
+    }
+}
+
+fn write_record(out: &mut impl Write, data: &[u8]) -> std::io::Result<()> {
+    out.write_all(&(data.len() as u32).to_le_bytes())?;
+    out.write_all(data)
+}
+
+/// None — поток кончился
+fn read_record(input: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match input.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut data = vec![0u8; u32::from_le_bytes(len) as usize];
+    input.read_exact(&mut data)?;
+    Ok(Some(data))
+}
+
+/// Запоминает событие до ближайшего тика, если мир записывается
+pub fn note(world: &mut WorldState, event: Event) {
+    if let Some(r) = &mut world.recorder {
+        r.pending.push(event);
+    }
+}
+
+/// Записывает прошедший тик; при ошибке записи запись останавливается
+pub fn frame(world: &mut WorldState, dt: Duration) {
+    let checksum = checksum(world);
+    let Some(r) = &mut world.recorder else {
+        return;
+    };
+    let frame = Frame {
+        tick: world.tick,
+        dt_ns: dt.as_nanos() as u64,
+        events: std::mem::take(&mut r.pending),
+        checksum,
+    };
+    let written = serde_json::to_vec(&frame)
+        .map_err(std::io::Error::from)
+        .and_then(|data| write_record(&mut r.out, &data))
+        .and_then(|()| {
+            r.frames += 1;
+            if r.frames % FLUSH_FRAMES == 0 {
+                r.out.flush()
+            } else {
+                Ok(())
+            }
+        });
+    if let Err(e) = written {
+        error!("[{}] Session recording stopped: {}", world.name, e);
+        world.recorder = None;
+    }
+}
+
+/// Дописывает и закрывает запись (при остановке сервера)
+pub fn stop(world: &mut WorldState) {
+    let Some(r) = world.recorder.take() else {
+        return;
+    };
+    let frames = r.frames;
+    match r.out.finish().and_then(|mut file| file.flush()) {
+        Ok(()) => info!("[{}] Recorded {} ticks", world.name, frames),
+        Err(e) => error!("[{}] Failed to finish the recording: {}", world.name, e),
+    }
+}
+
+/// FNV-1a: одинакова на любой платформе и в любой сборке
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+fn checksum(world: &WorldState) -> u64 {
+    let mut h = Fnv(0xcbf2_9ce4_8422_2325);
+    h.write(&world.tick.to_le_bytes());
+    h.write(&world.clock.time.elapsed_s.to_le_bytes());
+    let mut players: Vec<&PlayerState> = world.players.values().collect();
+    players.sort_by(|a, b| a.id.cmp(&b.id));
+    for p in players {
+        h.write(p.id.as_bytes());
+        for v in [p.x, p.y, p.z].iter().chain(&p.velocity) {
+            h.write(&v.to_le_bytes());
+        }
+        h.write(&p.last_input_id.to_le_bytes());
+    }
+    h.0
+}
+
+/// Повторяет событие так же, как его применил сервер
+fn apply(world: &mut WorldState, event: Event) {
+    match event {
+        Event::Join { mut player, slot } => {
+            player.slot = slot;
+            world.offline_players.remove(&player.id);
+            world.players.insert(player.id.clone(), player);
+            world.dirty = true;
+        }
+        Event::Disconnect { client_id } => {
+            if let Some(p) = world.players.get_mut(&client_id) {
+                if p.velocity != [0.0; 3] {
+                    p.velocity = [0.0; 3];
+                    world.dirty = true;
+                }
+            }
+        }
+        Event::Leave { client_id } => {
+            if let Some(player) = world.players.remove(&client_id) {
+                world.offline_players.insert(client_id, player);
+            }
+            world.dirty = true;
+        }
+        Event::Input {
+            client_id,
+            delta,
+            input_id,
+        } => world.inputs.push(tick::QueuedInput {
+            client_id,
+            delta,
+            input_id,
+            sender: None,
+        }),
+        Event::VrPose {
+            client_id,
+            head_pos,
+            head_quat,
+        } => {
+            if let Some(p) = world.players.get_mut(&client_id) {
+                p.head_pos = Some(head_pos);
+                p.head_quat = Some(head_quat);
+                world.dirty = true;
+            }
+        }
+        Event::Footprint {
+            client_id,
+            kind,
+            x,
+            z,
+            heading,
+        } => {
+            let _ = deform::footprint(world, &client_id, &kind, x, z, heading);
+        }
+        Event::Deform {
+            client_id,
+            x,
+            z,
+            radius,
+            depth,
+        } => {
+            let _ = deform::deform(world, &client_id, x, z, radius, depth);
+        }
+    }
+}
+
+/// `--replay`: прогоняет запись и сверяет суммы; true — совпали все тики
+pub async fn replay(path: &Path) -> Result<bool> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut file = BufReader::new(file);
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)
+        .context("reading the recording header")?;
+    if &magic != MAGIC {
+        bail!("{} is not a session recording", path.display());
+    }
+    let mut version = [0u8; 4];
+    file.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != FORMAT_VERSION {
+        bail!("unsupported recording version {version} (expected {FORMAT_VERSION})");
+    }
+    let mut input = DeflateDecoder::new(file);
+    let header: Header = match read_record(&mut input)? {
+        Some(data) => serde_json::from_slice(&data)?,
+        None => bail!("recording has no header"),
+    };
+    let bundle = match read_record(&mut input)? {
+        Some(data) => WorldBundle::from_bytes(&data)?,
+        None => bail!("recording has no world"),
+    };
+
+    // игроки и часы из заголовка записи
+    let store = MemoryStore::with(header.players, Some(header.clock));
+    let cfg = bundle.config.clone();
+    let (mut world, _) =
+        worlds::build(&header.world, cfg, header.map_size, Some(bundle), &store).await?;
+    world.tick = header.tick;
+    info!("[{}] Replaying {}", world.name, path.display());
+
+    let (mut ticks, mut played, mut joins, mut inputs) = (0u64, Duration::ZERO, 0usize, 0usize);
+    loop {
+        let data = match read_record(&mut input) {
+            Ok(Some(data)) => data,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Recording ends abruptly after {} ticks: {}", ticks, e);
+                break;
+            }
+        };
+        let frame: Frame = serde_json::from_slice(&data)?;
+        for event in frame.events {
+            match &event {
+                Event::Join { .. } => joins += 1,
+                Event::Input { .. } => inputs += 1,
+                _ => {}
+            }
+            apply(&mut world, event);
+        }
+        let dt = Duration::from_nanos(frame.dt_ns);
+        tick::advance(&mut world, dt);
+        ticks += 1;
+        played += dt;
+        if world.tick != frame.tick || checksum(&world) != frame.checksum {
+            println!(
+                "desync at tick {} (replayed tick {}): checksum {:016x}, recorded {:016x}",
+                frame.tick,
+                world.tick,
+                checksum(&world),
+                frame.checksum
+            );
+            let mut players: Vec<&PlayerState> = world.players.values().collect();
+            players.sort_by(|a, b| a.id.cmp(&b.id));
+            for p in players {
+                println!(
+                    "  {}: pos [{}, {}, {}] velocity {:?} last input {}",
+                    p.id, p.x, p.y, p.z, p.velocity, p.last_input_id
+                );
+            }
+            return Ok(false);
+        }
+    }
+    let players: HashSet<&String> = world
+        .players
+        .keys()
+        .chain(world.offline_players.keys())
+        .collect();
+    println!(
+        "{}: replayed {} ticks ({:.1} s), {} joins, {} inputs, {} players: deterministic",
+        path.display(),
+        ticks,
+        played.as_secs_f64(),
+        joins,
+        inputs,
+        players.len()
+    );
+    Ok(true)
+}
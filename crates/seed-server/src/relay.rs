@@ -0,0 +1,439 @@
+//! WebSocket-ретранслятор `/relay` между host (ПК) и client (телефон):
+//! host создаёт комнату с кодом, клиенты входят по коду; бинарные кадры и
+//! текст host уходят всем клиентам комнаты, текст клиента — host.
+//!
+//! JSON-сообщение с полем `to` (`playerId` или `"host"`) уходит только
+//! адресату, с добавленным `from` отправителя; неизвестный адресат — `error`.
+//! Бинарные кадры клиента (камера, датчики) уходят host с префиксом
+//! отправителя: байт длины и `playerId` в UTF-8, затем сам кадр.
+//!
+//! Комната может быть закрыта паролем (`?password=` у host при создании и
+//! у клиентов при входе). Комната без host живёт `--relay-room-ttl`, ожидая его
+//! возвращения; потом клиенты получают `room_closed` и отключаются.
+//! `GET /api/relay/rooms` — список комнат для интерфейса host.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    extract::{Query, State},
+    http::StatusCode,
+    response::Response,
+    Json,
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{acquire_connection, conn, limits, shutdown, AppState};
+
+pub const DEFAULT_ROOM_TTL_S: u64 = 120;
+/// Адрес host в `to` и `from`
+const HOST_ID: &str = "host";
+/// Как часто искать комнаты, чей host не вернулся
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+pub struct RelayState {
+    rooms: HashMap<String, RelayRoom>,
+}
+
+#[derive(Debug)]
+struct RelayRoom {
+    host: Option<RelayPeer>,
+    clients: HashMap<String, RelayPeer>,
+    password: Option<String>,
+    created: Instant,
+    // Когда ушёл host; None — host на месте
+    host_left: Option<Instant>,
+}
+
+impl RelayRoom {
+    /// Участник по `playerId`; host — `"host"`
+    fn peer(&self, id: &str) -> Option<&RelayPeer> {
+        if id == HOST_ID {
+            self.host.as_ref()
+        } else {
+            self.clients.get(id)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RelayPeer {
+    sender: conn::Outbox,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelayQuery {
+    role: String,
+    #[serde(default)]
+    room: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomInfo {
+    room_code: String,
+    players: usize,
+    uptime_seconds: u64,
+    host_online: bool,
+    password_protected: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoomList {
+    count: usize,
+    rooms: Vec<RoomInfo>,
+}
+
+fn error(tx: &conn::Outbox, message: &str) {
+    let err = serde_json::json!({
+        "type": "error",
+        "message": message,
+    });
+    let _ = tx.send(Message::Text(err.to_string()));
+}
+
+pub async fn relay_ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(params): Query<RelayQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let guard = acquire_connection(&state)?;
+    let max_frame = state.limits.max_relay_frame;
+    Ok(ws
+        .max_frame_size(max_frame)
+        .max_message_size(max_frame)
+        .on_upgrade(move |socket| async move {
+            handle_relay_socket(socket, state, params).await;
+            drop(guard);
+        }))
+}
+
+/// `GET /api/relay/rooms`
+pub async fn list_rooms(State(state): State<AppState>) -> Json<RoomList> {
+    let relay = state.relay.lock().await;
+    let now = Instant::now();
+    let mut rooms: Vec<RoomInfo> = relay
+        .rooms
+        .iter()
+        .map(|(code, room)| RoomInfo {
+            room_code: code.clone(),
+            players: room.clients.len(),
+            uptime_seconds: (now - room.created).as_secs(),
+            host_online: room.host.is_some(),
+            password_protected: room.password.is_some(),
+        })
+        .collect();
+    rooms.sort_by(|a, b| a.room_code.cmp(&b.room_code));
+    Json(RoomList {
+        count: rooms.len(),
+        rooms,
+    })
+}
+
+/// Закрывает комнаты, чей host не вернулся за `ttl`
+pub async fn expire_rooms(state: AppState, ttl: Duration) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let mut relay = state.relay.lock().await;
+        let now = Instant::now();
+        relay.rooms.retain(|code, room| {
+            let expired = room.host.is_none() && room.host_left.is_some_and(|t| now - t >= ttl);
+            if expired {
+                info!("Relay room {} closed: host did not return", code);
+                let msg = serde_json::json!({
+                    "type": "room_closed",
+                    "reason": "host_timeout",
+                });
+                // Close идёт через очередь, чтобы room_closed успел уйти
+                for client in room.clients.values() {
+                    let _ = client.sender.send(Message::Text(msg.to_string()));
+                    let _ = client.sender.send(Message::Close(Some(CloseFrame {
+                        code: close_code::AWAY,
+                        reason: "host_timeout".into(),
+                    })));
+                }
+            }
+            !expired
+        });
+    }
+}
+
+/// `server_shutdown` всем участникам комнат; с `close` — и закрытие
+pub fn announce_shutdown(relay: &RelayState, seconds: u64, close: bool) {
+    let msg = serde_json::json!({
+        "type": "server_shutdown",
+        "seconds": seconds,
+    });
+    let peers = relay
+        .rooms
+        .values()
+        .flat_map(|room| room.host.iter().chain(room.clients.values()));
+    for peer in peers {
+        let _ = peer.sender.send(Message::Text(msg.to_string()));
+        if close {
+            let _ = peer.sender.send(shutdown::close_frame());
+        }
+    }
+}
+
+async fn handle_relay_socket(socket: WebSocket, state: AppState, params: RelayQuery) {
+    // Разделяем WebSocket на приёмник и отправитель
+    let (ws_sender, mut ws_receiver) = socket.split();
+
+    // Очередь исходящих этого пира; фоновой таск пишет её в сокет и пингует
+    let tx = conn::Outbox::default();
+    let send_task = tokio::spawn(conn::pump(ws_sender, tx.clone(), state.heartbeat));
+
+    let role = params.role.to_lowercase();
+    let mut room_code = params.room.clone();
+    let mut player_id: Option<String> = None;
+    // пустой пароль — комната открыта
+    let password = params.password.filter(|p| !p.is_empty());
+
+    // Регистрация в состоянии
+    {
+        let mut relay = state.relay.lock().await;
+
+        if role == "host" {
+            // Создаём или берём комнату
+            let code = room_code.take().unwrap_or_else(generate_room_code);
+            let max_rooms = state.limits.max_rooms;
+            if max_rooms > 0 && !relay.rooms.contains_key(&code) && relay.rooms.len() >= max_rooms {
+                error(&tx, "Too many rooms");
+                return;
+            }
+            // чужую закрытую комнату не перехватить
+            if relay
+                .rooms
+                .get(&code)
+                .is_some_and(|r| r.password.is_some() && r.password != password)
+            {
+                error(&tx, "Wrong room password");
+                return;
+            }
+            room_code = Some(code.clone());
+
+            let room = relay
+                .rooms
+                .entry(code.clone())
+                .or_insert_with(|| RelayRoom {
+                    host: None,
+                    clients: HashMap::new(),
+                    password,
+                    created: Instant::now(),
+                    host_left: None,
+                });
+
+            room.host = Some(RelayPeer { sender: tx.clone() });
+            room.host_left = None;
+
+            // Сообщаем хосту код комнаты
+            let msg = serde_json::json!({
+                "type": "room_created",
+                "roomCode": code,
+            });
+            let _ = tx.send(Message::Text(msg.to_string()));
+        } else {
+            // client
+            let code = match room_code.clone() {
+                Some(c) => c,
+                None => {
+                    error(&tx, "Room code required");
+                    return;
+                }
+            };
+
+            let room = match relay.rooms.get_mut(&code) {
+                Some(r) if r.host.is_some() => r,
+                _ => {
+                    error(&tx, "Room not found or host offline");
+                    return;
+                }
+            };
+
+            if room.password.is_some() && room.password != password {
+                error(&tx, "Wrong room password");
+                return;
+            }
+
+            let pid = format!(
+                "player_{}_{}",
+                chrono::Utc::now().timestamp_millis(),
+                rand::random::<u32>()
+            );
+            player_id = Some(pid.clone());
+
+            room.clients
+                .insert(pid.clone(), RelayPeer { sender: tx.clone() });
+
+            // Уведомляем клиента, что он подключился
+            let joined = serde_json::json!({
+                "type": "joined_room",
+                "roomCode": code,
+                "playerId": pid,
+            });
+            let _ = tx.send(Message::Text(joined.to_string()));
+
+            // Уведомляем хоста о новом игроке
+            if let Some(host) = &room.host {
+                let info = serde_json::json!({
+                    "type": "player_joined",
+                    "playerId": player_id,
+                    "totalPlayers": room.clients.len(),
+                });
+                let _ = host.sender.send(Message::Text(info.to_string()));
+            }
+        }
+    }
+
+    // Основной цикл приёма сообщений от этого пира и маршрутизация
+    let room_code_final = room_code.clone();
+    let mut limiter = limits::RateLimiter::new(&state.limits);
+    while let Some(msg) = conn::next_message(&mut ws_receiver, &tx, state.heartbeat).await {
+        match limiter.check(&msg) {
+            limits::Verdict::Pass => {}
+            limits::Verdict::Warn => {
+                error(&tx, "Rate limit exceeded");
+                continue;
+            }
+            limits::Verdict::Drop => continue,
+            limits::Verdict::Disconnect => {
+                info!("relay {} peer exceeded the rate limit, disconnecting", role);
+                break;
+            }
+        }
+        let Some(code) = &room_code_final else {
+            continue;
+        };
+        match msg {
+            // Бинарные кадры от host → всем клиентам в комнате
+            Message::Binary(data) if role == "host" => {
+                let relay = state.relay.lock().await;
+                if let Some(room) = relay.rooms.get(code) {
+                    for client in room.clients.values() {
+                        let _ = client.sender.send(Message::Binary(data.clone()));
+                    }
+                }
+            }
+            // Бинарные кадры клиента → host, с префиксом отправителя
+            Message::Binary(data) => {
+                let relay = state.relay.lock().await;
+                if let (Some(host), Some(pid)) = (
+                    relay.rooms.get(code).and_then(|r| r.host.as_ref()),
+                    &player_id,
+                ) {
+                    let _ = host.sender.send(Message::Binary(tag_frame(pid, &data)));
+                }
+            }
+            Message::Text(text) => {
+                let relay = state.relay.lock().await;
+                let Some(room) = relay.rooms.get(code) else {
+                    continue;
+                };
+                let from = player_id.as_deref().unwrap_or(HOST_ID);
+                if let Some((to, text)) = addressed(&text, from) {
+                    match room.peer(&to) {
+                        Some(peer) => {
+                            let _ = peer.sender.send(Message::Text(text));
+                        }
+                        None => error(&tx, "Unknown player"),
+                    }
+                } else if role == "host" {
+                    // Текстовые сообщения без адресата: host→клиенты, client→host
+                    for client in room.clients.values() {
+                        let _ = client.sender.send(Message::Text(text.clone()));
+                    }
+                } else if let Some(host) = &room.host {
+                    let _ = host.sender.send(Message::Text(text));
+                }
+            }
+            Message::Close(_) => {
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    // Очистка при отключении
+    {
+        let mut relay = state.relay.lock().await;
+        if let Some(code) = room_code {
+            if let Some(room) = relay.rooms.get_mut(&code) {
+                if role == "host" {
+                    // комнату мог уже забрать новый host
+                    if room.host.as_ref().is_some_and(|h| h.sender.same(&tx)) {
+                        // Уведомляем всех клиентов, что хост ушёл
+                        let msg = serde_json::json!({
+                            "type": "host_disconnected",
+                        });
+                        for client in room.clients.values() {
+                            let _ = client.sender.send(Message::Text(msg.to_string()));
+                        }
+                        room.host = None;
+                        room.host_left = Some(Instant::now());
+                    }
+                } else if let Some(pid) = player_id {
+                    room.clients.remove(&pid);
+                    // Опционально уведомляем хоста
+                    if let Some(host) = &room.host {
+                        let info = serde_json::json!({
+                            "type": "player_left",
+                            "playerId": pid,
+                            "totalPlayers": room.clients.len(),
+                        });
+                        let _ = host.sender.send(Message::Text(info.to_string()));
+                    }
+                }
+
+                if room.host.is_none() && room.clients.is_empty() {
+                    relay.rooms.remove(&code);
+                }
+            }
+        }
+    }
+
+    send_task.abort();
+}
+
+/// Адресат и текст для пересылки, если сообщение адресное
+fn addressed(text: &str, from: &str) -> Option<(String, String)> {
+    // без `"to"` сообщение точно не адресное — не разбираем
+    if !text.contains("\"to\"") {
+        return None;
+    }
+    let mut msg: serde_json::Value = serde_json::from_str(text).ok()?;
+    let obj = msg.as_object_mut()?;
+    let to = obj.get("to")?.as_str()?.to_string();
+    obj.insert("from".into(), from.into());
+    Some((to, msg.to_string()))
+}
+
+/// Кадр клиента для host: длина `playerId`, `playerId`, кадр
+fn tag_frame(player_id: &str, data: &[u8]) -> Vec<u8> {
+    let id = player_id.as_bytes();
+    let id = &id[..id.len().min(u8::MAX as usize)];
+    let mut frame = Vec::with_capacity(1 + id.len() + data.len());
+    frame.push(id.len() as u8);
+    frame.extend_from_slice(id);
+    frame.extend_from_slice(data);
+    frame
+}
+
+fn generate_room_code() -> String {
+    use rand::Rng;
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let mut rng = rand::thread_rng();
+    (0..6)
+        .map(|_| {
+            let idx = rng.gen_range(0..ALPHABET.len());
+            ALPHABET[idx] as char
+        })
+        .collect()
+}
@@ -0,0 +1,107 @@
+//! Карта мира картинкой: `GET /api/render/worldview.png`. Рисует тот же
+//! модуль, что и веб-клиент (`seed_core::render`), так что картинка
+//! совпадает с картой в браузере.
+//!
+//! Параметры:
+//! - `x0`, `y0`, `w`, `h` — участок в клетках карты, по умолчанию вся карта;
+//! - `layers` — слои через запятую (`biomes,relief,rivers,snow,coast` или
+//!   `all`, по умолчанию все);
+//! - `world` — мир, как у остальных `/api`.
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::Response,
+};
+use seed_core::render::{render_rgba, Layers, Region, RenderOptions};
+use serde::Deserialize;
+
+use crate::worlds;
+use crate::AppState;
+
+/// Больше — картинка на сотни мегабайт
+const MAX_PIXELS: u64 = 4096 * 4096;
+
+#[derive(Debug, Deserialize)]
+pub struct RenderQuery {
+    x0: Option<u32>,
+    y0: Option<u32>,
+    w: Option<u32>,
+    h: Option<u32>,
+    layers: Option<String>,
+    world: Option<String>,
+}
+
+type RenderError = (StatusCode, String);
+
+pub async fn worldview_png(
+    State(state): State<AppState>,
+    Query(query): Query<RenderQuery>,
+) -> Result<Response, RenderError> {
+    let layers = match query.layers.as_deref() {
+        Some(list) => Layers::parse(list).map_err(|e| (StatusCode::BAD_REQUEST, e))?,
+        None => Layers::ALL,
+    };
+    let world = worlds::resolve(&state, query.world.as_deref()).await?;
+    // рисуем без блокировки мира: копия карт дешевле кадра симуляции
+    let (hm, bm, cfg) = {
+        let world = world.lock().await;
+        (
+            world.heightmap.clone(),
+            world.biomemap.clone(),
+            world.config.clone(),
+        )
+    };
+    let x0 = query.x0.unwrap_or(0);
+    let y0 = query.y0.unwrap_or(0);
+    let region = Region {
+        x0,
+        y0,
+        width: query.w.unwrap_or(hm.width.saturating_sub(x0)),
+        height: query.h.unwrap_or(hm.height.saturating_sub(y0)),
+    };
+    if !region.fits(&hm) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "region {}x{} at {},{} is outside the {}x{} map",
+                region.width, region.height, region.x0, region.y0, hm.width, hm.height
+            ),
+        ));
+    }
+    if region.width as u64 * region.height as u64 > MAX_PIXELS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("region is larger than {MAX_PIXELS} pixels"),
+        ));
+    }
+
+    let png = tokio::task::spawn_blocking(move || {
+        let options = RenderOptions {
+            layers,
+            ..RenderOptions::default()
+        };
+        let rgba = render_rgba(&hm, &bm, &cfg, region, &options);
+        encode_png(&rgba, region.width, region.height)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "image/png")
+        .body(Body::from(png))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+fn encode_png(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>, png::EncodingError> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+    writer.finish()?;
+    Ok(out)
+}
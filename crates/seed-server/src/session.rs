@@ -0,0 +1,183 @@
+//! Возобновление сессии после обрыва связи. В `joined` клиент получает
+//! `resume_token`; если соединение оборвалось, игрок ещё `resume_grace`
+//! остаётся в мире на своём месте и в своём слоте, а остальные клиенты
+//! получают `player_status` с `disconnected`. Повторный `join` с тем же
+//! `client_id` и токеном в этот срок возвращает клиенту того же игрока
+//! вместе с зоной интереса и загруженными чанками (`resumed` в `joined`),
+//! остальным уходит `resumed`. По истечении срока игрок уходит из мира, как
+//! при обычном выходе, и остальные получают `left`.
+//!
+//! Токен годится и пока сервер ещё не заметил обрыва: новое соединение
+//! забирает сессию у старого, старое закрывается. Без токена или с чужим
+//! `join` с `client_id` открытой сессии отклоняется (`session_in_use`).
+//! Токен меняется при каждом входе. Второй `join` в уже вошедшем соединении
+//! отклоняется (`already_joined`).
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{broadcast_roster, interest, objects, record, ServerMessage, WorldState};
+
+pub const DEFAULT_RESUME_GRACE_S: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceStatus {
+    Disconnected,
+    Resumed,
+    Left,
+}
+
+/// Отключившиеся клиенты, которые ещё могут вернуться
+#[derive(Debug, Default)]
+pub struct Sessions {
+    suspended: HashMap<String, Suspended>,
+}
+
+#[derive(Debug)]
+struct Suspended {
+    token: String,
+    until: Instant,
+    view: interest::ClientView,
+    chunks: objects::ClientChunks,
+}
+
+/// Что переносится в новое соединение
+pub struct Resumed {
+    pub view: interest::ClientView,
+    pub chunks: objects::ClientChunks,
+}
+
+pub fn new_token() -> String {
+    format!("{:032x}", rand::thread_rng().gen::<u128>())
+}
+
+/// `player_status` всем клиентам, кроме самого игрока
+fn notify(world: &WorldState, client_id: &str, status: PresenceStatus) {
+    let Some(msg) = (ServerMessage::PlayerStatus {
+        client_id: client_id.to_string(),
+        status,
+    })
+    .to_text() else {
+        return;
+    };
+    for (cid, c) in &world.clients {
+        if cid != client_id {
+            c.sender.send(msg.clone());
+        }
+    }
+}
+
+/// Соединение клиента закрылось: игрок ждёт его `grace`, с нулевым сроком
+/// уходит сразу
+pub fn suspend(world: &mut WorldState, client_id: &str, grace: Duration) {
+    let Some(channel) = world.clients.remove(client_id) else {
+        return;
+    };
+    if grace.is_zero() {
+        leave(world, client_id);
+        return;
+    }
+    record::note(
+        world,
+        record::Event::Disconnect {
+            client_id: client_id.to_string(),
+        },
+    );
+    if let Some(p) = world.players.get_mut(client_id) {
+        if p.velocity != [0.0; 3] {
+            p.velocity = [0.0; 3];
+            world.dirty = true;
+        }
+    }
+    world.sessions.suspended.insert(
+        client_id.to_string(),
+        Suspended {
+            token: channel.resume_token,
+            until: Instant::now() + grace,
+            view: channel.view,
+            chunks: channel.chunks,
+        },
+    );
+    info!(
+        "[{}] {} disconnected, waiting {:?}",
+        world.name, client_id, grace
+    );
+    notify(world, client_id, PresenceStatus::Disconnected);
+}
+
+/// Сессия клиента по токену: отключившаяся или ещё открытая в другом
+/// соединении (его закрываем). None — токен не подошёл или срок вышел
+pub fn resume(world: &mut WorldState, client_id: &str, token: &str) -> Option<Resumed> {
+    let resumed = match world.sessions.suspended.get(client_id) {
+        Some(s) if s.token == token => {
+            let s = world.sessions.suspended.remove(client_id)?;
+            notify(world, client_id, PresenceStatus::Resumed);
+            Resumed {
+                view: s.view,
+                chunks: s.chunks,
+            }
+        }
+        Some(_) => return None,
+        None => {
+            let active = world.clients.get(client_id)?;
+            if active.resume_token != token {
+                return None;
+            }
+            let old = world.clients.remove(client_id)?;
+            old.sender.close();
+            Resumed {
+                view: old.view,
+                chunks: old.chunks,
+            }
+        }
+    };
+    info!("[{}] {} resumed its session", world.name, client_id);
+    Some(resumed)
+}
+
+/// Игрок вошёл заново без токена: прежнюю сессию не ждём, но для
+/// остальных он вернулся
+pub fn forget(world: &mut WorldState, client_id: &str) {
+    if world.sessions.suspended.remove(client_id).is_some() {
+        notify(world, client_id, PresenceStatus::Resumed);
+    }
+}
+
+/// Убирает из мира игроков, не вернувшихся в срок
+pub fn expire(world: &mut WorldState) {
+    let now = Instant::now();
+    let expired: Vec<String> = world
+        .sessions
+        .suspended
+        .iter()
+        .filter(|(_, s)| s.until <= now)
+        .map(|(cid, _)| cid.clone())
+        .collect();
+    for cid in expired {
+        world.sessions.suspended.remove(&cid);
+        info!("[{}] {} did not come back", world.name, cid);
+        leave(world, &cid);
+    }
+}
+
+/// Игрок уходит из мира; позиция запоминается до следующего входа
+fn leave(world: &mut WorldState, client_id: &str) {
+    record::note(
+        world,
+        record::Event::Leave {
+            client_id: client_id.to_string(),
+        },
+    );
+    if let Some(player) = world.players.remove(client_id) {
+        world.offline_players.insert(client_id.to_string(), player);
+    }
+    // остальные узнают об уходе и из следующего снапшота
+    world.dirty = true;
+    notify(world, client_id, PresenceStatus::Left);
+    broadcast_roster(world);
+}
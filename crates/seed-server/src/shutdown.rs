@@ -0,0 +1,96 @@
+//! Плавная остановка по SIGTERM или Ctrl+C. Новые WebSocket-соединения
+//! получают 503, а открытым раз в секунду приходит `server_shutdown` с
+//! числом секунд до остановки (`--shutdown-grace`); повторный сигнал
+//! обрывает отсчёт. Затем всем — `server_shutdown` с нулём и закрытие с
+//! кодом 1001 (going away); relay получает то же. Когда соединения
+//! закрыты, циклы тиков доделывают текущий тик с накопленным вводом и
+//! останавливаются, после чего `main` сохраняет миры и игроков.
+
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use axum::extract::ws::{close_code, CloseFrame, Message};
+use tracing::info;
+
+use crate::{relay, AppState, ServerMessage};
+
+pub const DEFAULT_GRACE_S: u64 = 5;
+/// Сколько ждать, пока соединения допишут очереди и закроются
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+const DRAIN_POLL: Duration = Duration::from_millis(50);
+pub const CLOSE_REASON: &str = "server_shutdown";
+
+/// Ctrl+C или SIGTERM
+pub async fn signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Отсчёт, закрытие соединений и остановка циклов тиков
+pub async fn run(state: &AppState, grace: Duration) {
+    state.shutting_down.store(true, Ordering::Release);
+    let mut left = grace.as_secs();
+    info!("Shutting down in {} s", left);
+    while left > 0 {
+        announce(state, left, false).await;
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(1)) => left -= 1,
+            _ = signal() => {
+                info!("Second signal, shutting down now");
+                break;
+            }
+        }
+    }
+    announce(state, 0, true).await;
+
+    let deadline = Instant::now() + DRAIN_TIMEOUT;
+    while state.connections.open() > 0 && Instant::now() < deadline {
+        tokio::time::sleep(DRAIN_POLL).await;
+    }
+    let left = state.connections.open();
+    if left > 0 {
+        info!("{} connections did not close in time", left);
+    }
+    state.worlds.stop().await;
+}
+
+/// `server_shutdown` всем клиентам миров и relay; с `close` — и закрытие
+async fn announce(state: &AppState, seconds: u64, close: bool) {
+    let Some(msg) = (ServerMessage::ServerShutdown { seconds }).to_text() else {
+        return;
+    };
+    for world in state.worlds.handles().await {
+        let world = world.lock().await;
+        for c in world.clients.values() {
+            let _ = c.sender.send(msg.clone());
+            if close {
+                // Close идёт через очередь, чтобы сообщение успело уйти
+                let _ = c.sender.send(close_frame());
+            }
+        }
+    }
+    relay::announce_shutdown(&*state.relay.lock().await, seconds, close);
+}
+
+pub fn close_frame() -> Message {
+    Message::Close(Some(CloseFrame {
+        code: close_code::AWAY,
+        reason: CLOSE_REASON.into(),
+    }))
+}
@@ -0,0 +1,152 @@
+//! Двоичные снапшоты мультиплеера. Клиент выбирает кодировку в `join`
+//! (`"encoding": "binary"`), по умолчанию остаётся JSON. Служебные сообщения
+//! (`joined`, `roster`, `error`) всегда идут текстом, двоичными — только
+//! снапшоты и дельты. Клиенту, согласовавшему сжатие, крупный кадр может
+//! прийти обёрнутым в `compress::KIND_COMPRESSED`.
+//!
+//! Игрок в снапшоте — не строка id, а номер слота (u16): соответствие
+//! слотов и id клиент получает из `joined` (свой слот) и `roster`
+//! (рассылается при входе и выходе игроков).
+//!
+//! Раскладка, little-endian. Позиции — i32 в миллиметрах, скорость — 3 ×
+//! i32 в мм/с, кватернион — 4 × i16 (компонента × 32767). Заголовок `Header`
+//! — u32 seq, u64 tick, i64 время сервера в мс.
+//! - Снапшот: u8 `KIND_SNAPSHOT`, u8 `VERSION`, заголовок, u16 число
+//!   игроков; на игрока u16 слот, u8 флаги (`FLAG_HEAD` — есть поза
+//!   головы), позиция, скорость, u32 id последнего ввода; с `FLAG_HEAD`
+//!   дальше позиция и кватернион головы.
+//! - Дельта (см. `sync`): u8 `KIND_DELTA`, u8 `VERSION`, заголовок, u32 seq
+//!   базы, u16 число ушедших и их слоты (u16), u16 число изменившихся; на
+//!   игрока u16 слот, u8 маска `DELTA_*` и только отмеченные поля в порядке
+//!   x, y, z (i32), позиция головы, кватернион головы, скорость, id ввода.
+
+use serde::{Deserialize, Serialize};
+
+use crate::sync::Delta;
+use crate::PlayerState;
+
+pub const KIND_SNAPSHOT: u8 = 1;
+pub const KIND_DELTA: u8 = 2;
+pub const VERSION: u8 = 3;
+pub const FLAG_HEAD: u8 = 1;
+
+pub const DELTA_X: u8 = 1;
+pub const DELTA_Y: u8 = 2;
+pub const DELTA_Z: u8 = 4;
+pub const DELTA_HEAD_POS: u8 = 8;
+pub const DELTA_HEAD_QUAT: u8 = 16;
+pub const DELTA_VELOCITY: u8 = 32;
+pub const DELTA_LAST_INPUT: u8 = 64;
+
+/// Общее у снапшота и дельты: по `seq` клиент замечает пропущенные
+/// снапшоты, по `tick` и `server_time_ms` — интерполирует между ними
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub seq: u32,
+    /// Номер тика мира, на котором снят снапшот
+    pub tick: u64,
+    pub server_time_ms: i64,
+}
+
+impl Header {
+    fn put(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.seq.to_le_bytes());
+        out.extend_from_slice(&self.tick.to_le_bytes());
+        out.extend_from_slice(&self.server_time_ms.to_le_bytes());
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotEncoding {
+    #[default]
+    Json,
+    Binary,
+}
+
+pub fn encode_binary<'a>(
+    header: Header,
+    players: impl ExactSizeIterator<Item = &'a PlayerState>,
+) -> Vec<u8> {
+    let count = players.len().min(u16::MAX as usize);
+    let mut out = Vec::with_capacity(24 + count * 51);
+    out.push(KIND_SNAPSHOT);
+    out.push(VERSION);
+    header.put(&mut out);
+    out.extend_from_slice(&(count as u16).to_le_bytes());
+    for p in players.take(count) {
+        out.extend_from_slice(&p.slot.to_le_bytes());
+        let head = p.head_pos.zip(p.head_quat);
+        out.push(if head.is_some() { FLAG_HEAD } else { 0 });
+        for v in [p.x, p.y, p.z] {
+            put_mm(&mut out, v);
+        }
+        p.velocity.into_iter().for_each(|v| put_mm(&mut out, v));
+        out.extend_from_slice(&p.last_input_id.to_le_bytes());
+        if let Some((pos, quat)) = head {
+            pos.into_iter().for_each(|v| put_mm(&mut out, v));
+            put_quat(&mut out, quat);
+        }
+    }
+    out
+}
+
+pub fn encode_binary_delta(header: Header, base_seq: u32, delta: &Delta) -> Vec<u8> {
+    let removed = delta.removed.len().min(u16::MAX as usize);
+    let changed = delta.changed.len().min(u16::MAX as usize);
+    let mut out = Vec::with_capacity(30 + removed * 2 + changed * 15);
+    out.push(KIND_DELTA);
+    out.push(VERSION);
+    header.put(&mut out);
+    out.extend_from_slice(&base_seq.to_le_bytes());
+    out.extend_from_slice(&(removed as u16).to_le_bytes());
+    for (_, slot) in delta.removed.iter().take(removed) {
+        out.extend_from_slice(&slot.to_le_bytes());
+    }
+    out.extend_from_slice(&(changed as u16).to_le_bytes());
+    for p in delta.changed.iter().take(changed) {
+        out.extend_from_slice(&p.slot.to_le_bytes());
+        let mask = [
+            (p.x.is_some(), DELTA_X),
+            (p.y.is_some(), DELTA_Y),
+            (p.z.is_some(), DELTA_Z),
+            (p.head_pos.is_some(), DELTA_HEAD_POS),
+            (p.head_quat.is_some(), DELTA_HEAD_QUAT),
+            (p.velocity.is_some(), DELTA_VELOCITY),
+            (p.last_input_id.is_some(), DELTA_LAST_INPUT),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |m, (_, bit)| m | bit);
+        out.push(mask);
+        for v in [p.x, p.y, p.z].into_iter().flatten() {
+            put_mm(&mut out, v);
+        }
+        if let Some(pos) = p.head_pos {
+            pos.into_iter().for_each(|v| put_mm(&mut out, v));
+        }
+        if let Some(quat) = p.head_quat {
+            put_quat(&mut out, quat);
+        }
+        if let Some(vel) = p.velocity {
+            vel.into_iter().for_each(|v| put_mm(&mut out, v));
+        }
+        if let Some(id) = p.last_input_id {
+            out.extend_from_slice(&id.to_le_bytes());
+        }
+    }
+    out
+}
+
+fn put_mm(out: &mut Vec<u8>, v: f32) {
+    // `as` насыщает: за пределами ±2147 км позиция упирается в край
+    let mm = (v as f64 * 1000.0).round() as i32;
+    out.extend_from_slice(&mm.to_le_bytes());
+}
+
+fn put_quat(out: &mut Vec<u8>, quat: [f32; 4]) {
+    for v in quat {
+        let q = (v.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        out.extend_from_slice(&q.to_le_bytes());
+    }
+}
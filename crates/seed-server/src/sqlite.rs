@@ -0,0 +1,171 @@
+//! Хранилище мира в одном файле SQLite (`--store sqlite`): бандл мира,
+//! игроки, часы и изменённые чанки рельефа — по таблице. Удобно, когда
+//! миров много или данные бэкапятся одним файлом.
+//!
+//! SQLite собирается в бинарник (rusqlite с `bundled`), системная
+//! libsqlite3 не нужна. Соединение одно: rusqlite не даёт делить его между
+//! потоками, и `SqliteStore` держит его под мьютексом.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use seed_save::WorldBundle;
+
+use crate::clock::WorldClock;
+use crate::persist::{TerrainDelta, WorldStore};
+use crate::PlayerState;
+
+/// Имя файла базы в каталоге мира
+pub const FILE_NAME: &str = "world.sqlite";
+/// Сколько ждать, если база занята другим процессом
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS world (id INTEGER PRIMARY KEY CHECK (id = 0), bundle BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS players (id TEXT PRIMARY KEY, data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS clock (id INTEGER PRIMARY KEY CHECK (id = 0), data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS terrain (
+        chunk_x INTEGER NOT NULL,
+        chunk_y INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (chunk_x, chunk_y)
+    );
+";
+
+/// База `world.sqlite` в каталоге мира
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        let path = dir.join(FILE_NAME);
+        let conn =
+            Connection::open(&path).with_context(|| format!("opening {}", path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("creating tables in {}", path.display()))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Первый столбец (BLOB или TEXT) первой строки запроса
+    fn one(&self, sql: &str) -> Result<Option<Vec<u8>>> {
+        let value = self
+            .conn()
+            .query_row(sql, [], |row| Ok(row.get_ref(0)?.as_bytes()?.to_vec()))
+            .optional()?;
+        Ok(value)
+    }
+}
+
+impl WorldStore for SqliteStore {
+    fn load_world(&self) -> Result<Option<WorldBundle>> {
+        let Some(data) = self.one("SELECT bundle FROM world WHERE id = 0")? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            WorldBundle::from_bytes(&data).context("reading the world bundle")?,
+        ))
+    }
+
+    fn save_world(&self, bundle: &WorldBundle) -> Result<()> {
+        let data = bundle.to_bytes()?;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO world (id, bundle) VALUES (0, ?1)",
+            [data],
+        )?;
+        Ok(())
+    }
+
+    fn load_players(&self) -> Result<Vec<PlayerState>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT data FROM players")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.map(|data| serde_json::from_str(&data?).context("reading a player"))
+            .collect()
+    }
+
+    fn save_players(&self, players: &[PlayerState]) -> Result<()> {
+        let rows = players
+            .iter()
+            .map(|p| Ok((p.id.as_str(), serde_json::to_string(p)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM players", [])?;
+        for (id, data) in &rows {
+            tx.execute(
+                "INSERT INTO players (id, data) VALUES (?1, ?2)",
+                params![id, data],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_clock(&self) -> Result<Option<WorldClock>> {
+        self.one("SELECT data FROM clock WHERE id = 0")?
+            .map(|data| serde_json::from_slice(&data).context("reading the clock"))
+            .transpose()
+    }
+
+    fn save_clock(&self, clock: &WorldClock) -> Result<()> {
+        let data = serde_json::to_string(clock)?;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO clock (id, data) VALUES (0, ?1)",
+            [data],
+        )?;
+        Ok(())
+    }
+
+    fn load_terrain(&self) -> Result<Vec<TerrainDelta>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT chunk_x, chunk_y, data FROM terrain ORDER BY chunk_y, chunk_x")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        rows.map(|row| {
+            let (x, y, data) = row?;
+            serde_json::from_str(&data).with_context(|| format!("reading terrain chunk {x},{y}"))
+        })
+        .collect()
+    }
+
+    fn save_terrain(&self, deltas: &[TerrainDelta]) -> Result<()> {
+        let rows = deltas
+            .iter()
+            .map(|d| Ok((d.chunk_x, d.chunk_y, serde_json::to_string(d)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for (x, y, data) in &rows {
+            tx.execute(
+                "INSERT OR REPLACE INTO terrain (chunk_x, chunk_y, data) VALUES (?1, ?2, ?3)",
+                params![x, y, data],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn clear_terrain(&self) -> Result<()> {
+        self.conn().execute("DELETE FROM terrain", [])?;
+        Ok(())
+    }
+}
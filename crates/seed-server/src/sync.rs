@@ -0,0 +1,252 @@
+//! Дельта-синхронизация (`simulation.network.stateSyncStrategy:
+//! "delta_compressed"`). Каждый снапшот получает номер `seq`, клиент
+//! подтверждает принятые (`{"type": "ack", "seq": N}`), и следующий снапшот
+//! ему уходит разницей с последним подтверждённым: только изменившиеся
+//! игроки и поля плюс список ушедших.
+//!
+//! Полный снапшот уходит, пока подтверждений нет, когда подтверждённый
+//! снапшот выпал из истории (пропуск пакетов, долгая задержка) и по запросу
+//! клиента (`{"type": "resync"}`) — например, если у него нет базы дельты.
+//!
+//! Номера идут подряд и каждый снапшот уходит всем клиентам мира, так что
+//! пропуск в `seq` значит, что снапшот потерялся по дороге (например,
+//! вытеснен из очереди медленного клиента).
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PlayerRole, PlayerState};
+
+/// Сколько последних снапшотов годятся в базу дельты
+pub const HISTORY_LEN: usize = 64;
+
+pub type Players = Arc<HashMap<String, PlayerState>>;
+
+/// Последние разосланные снапшоты по номерам
+#[derive(Debug, Default)]
+pub struct SnapshotHistory {
+    next_seq: u32,
+    entries: VecDeque<(u32, Players)>,
+}
+
+impl SnapshotHistory {
+    /// Запоминает снапшот и возвращает его номер
+    pub fn push(&mut self, players: Players) -> u32 {
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        if self.entries.len() == HISTORY_LEN {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((seq, players));
+        seq
+    }
+
+    pub fn get(&self, seq: u32) -> Option<Players> {
+        self.entries
+            .iter()
+            .find(|(s, _)| *s == seq)
+            .map(|(_, p)| p.clone())
+    }
+}
+
+/// Изменившиеся поля игрока; у нового игрока заполнены все
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerDelta {
+    pub id: String,
+    #[serde(skip)]
+    pub slot: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<PlayerRole>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub z: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_pos: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_quat: Option<[f32; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_input_id: Option<u32>,
+}
+
+impl PlayerDelta {
+    fn is_empty(&self) -> bool {
+        self.role.is_none()
+            && self.x.is_none()
+            && self.y.is_none()
+            && self.z.is_none()
+            && self.head_pos.is_none()
+            && self.head_quat.is_none()
+            && self.velocity.is_none()
+            && self.last_input_id.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Delta {
+    pub changed: Vec<PlayerDelta>,
+    /// Ушедшие игроки
+    pub removed: Vec<(String, u16)>,
+}
+
+pub fn diff(base: &HashMap<String, PlayerState>, current: &HashMap<String, PlayerState>) -> Delta {
+    let changed = current
+        .values()
+        .filter_map(|p| {
+            // игрок, перезашедший в другой слот, для клиента — новый
+            let old = base.get(&p.id).filter(|o| o.slot == p.slot);
+            let pick = |new, old: Option<_>| (old != Some(new)).then_some(new);
+            let delta = PlayerDelta {
+                id: p.id.clone(),
+                slot: p.slot,
+                role: match old {
+                    Some(o) if o.role == p.role => None,
+                    _ => Some(p.role.clone()),
+                },
+                x: pick(p.x, old.map(|o| o.x)),
+                y: pick(p.y, old.map(|o| o.y)),
+                z: pick(p.z, old.map(|o| o.z)),
+                head_pos: p
+                    .head_pos
+                    .filter(|h| old.is_none_or(|o| o.head_pos != Some(*h))),
+                head_quat: p
+                    .head_quat
+                    .filter(|q| old.is_none_or(|o| o.head_quat != Some(*q))),
+                velocity: (old.map(|o| o.velocity) != Some(p.velocity)).then_some(p.velocity),
+                last_input_id: (old.map(|o| o.last_input_id) != Some(p.last_input_id))
+                    .then_some(p.last_input_id),
+            };
+            (!delta.is_empty()).then_some(delta)
+        })
+        .collect();
+    let removed = base
+        .values()
+        .filter(|o| current.get(&o.id).is_none_or(|p| p.slot != o.slot))
+        .map(|o| (o.id.clone(), o.slot))
+        .collect();
+    Delta { changed, removed }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn player(id: &str, slot: u16, x: f32) -> PlayerState {
+        PlayerState {
+            id: id.into(),
+            role: PlayerRole::Pc,
+            slot,
+            x,
+            y: 0.0,
+            z: 0.0,
+            head_pos: None,
+            head_quat: None,
+            velocity: [0.0; 3],
+            last_input_id: 0,
+        }
+    }
+
+    fn players(list: &[PlayerState]) -> HashMap<String, PlayerState> {
+        list.iter().map(|p| (p.id.clone(), p.clone())).collect()
+    }
+
+    /// Дельта поверх базы — так её применяет клиент
+    fn apply(base: &HashMap<String, PlayerState>, delta: &Delta) -> HashMap<String, PlayerState> {
+        let mut out = base.clone();
+        for (id, slot) in &delta.removed {
+            if out.get(id).is_some_and(|p| p.slot == *slot) {
+                out.remove(id);
+            }
+        }
+        for d in &delta.changed {
+            let p = out.entry(d.id.clone()).or_insert_with(|| {
+                let mut p = player(&d.id, d.slot, 0.0);
+                p.role = d.role.clone().expect("a new player carries its role");
+                p
+            });
+            p.slot = d.slot;
+            p.role = d.role.clone().unwrap_or(p.role.clone());
+            p.x = d.x.unwrap_or(p.x);
+            p.y = d.y.unwrap_or(p.y);
+            p.z = d.z.unwrap_or(p.z);
+            p.head_pos = d.head_pos.or(p.head_pos);
+            p.head_quat = d.head_quat.or(p.head_quat);
+            p.velocity = d.velocity.unwrap_or(p.velocity);
+            p.last_input_id = d.last_input_id.unwrap_or(p.last_input_id);
+        }
+        out
+    }
+
+    fn normalized(players: &HashMap<String, PlayerState>) -> BTreeMap<String, (u16, String)> {
+        players
+            .iter()
+            .map(|(id, p)| (id.clone(), (p.slot, serde_json::to_string(p).unwrap())))
+            .collect()
+    }
+
+    #[test]
+    fn delta_carries_only_changes() {
+        let base = players(&[player("a", 0, 1.0), player("b", 1, 2.0)]);
+        let mut moved = player("a", 0, 5.0);
+        moved.head_pos = Some([0.0, 1.7, 0.0]);
+        let current = players(&[moved, player("b", 1, 2.0)]);
+
+        let delta = diff(&base, &current);
+        assert!(delta.removed.is_empty());
+        let [a] = delta.changed.as_slice() else {
+            panic!("expected one change: {delta:?}");
+        };
+        assert_eq!(a.id, "a");
+        assert_eq!(a.x, Some(5.0));
+        assert_eq!(a.head_pos, Some([0.0, 1.7, 0.0]));
+        assert!(a.role.is_none() && a.y.is_none() && a.velocity.is_none());
+    }
+
+    #[test]
+    fn applied_delta_reproduces_the_snapshot() {
+        let base = players(&[
+            player("a", 0, 1.0),
+            player("b", 1, 2.0),
+            player("c", 2, 3.0),
+        ]);
+        let mut a = player("a", 0, 1.5);
+        a.velocity = [0.5, 0.0, 0.0];
+        a.last_input_id = 7;
+        // b ушёл, c перезашёл в другой слот, d пришёл
+        let current = players(&[a, player("c", 3, 3.0), player("d", 1, 9.0)]);
+
+        let delta = diff(&base, &current);
+        let mut removed = delta.removed.clone();
+        removed.sort();
+        assert_eq!(removed, [("b".to_string(), 1), ("c".to_string(), 2)]);
+        assert_eq!(normalized(&apply(&base, &delta)), normalized(&current));
+    }
+
+    #[test]
+    fn unchanged_world_gives_an_empty_delta() {
+        let base = players(&[player("a", 0, 1.0)]);
+        let delta = diff(&base, &base);
+        assert!(delta.changed.is_empty() && delta.removed.is_empty());
+    }
+
+    #[test]
+    fn old_snapshots_fall_out_of_history() {
+        let mut history = SnapshotHistory::default();
+        let first = history.push(Arc::default());
+        for _ in 0..HISTORY_LEN {
+            history.push(Arc::default());
+        }
+        // базы нет — клиенту уйдёт полный снапшот
+        assert!(history.get(first).is_none());
+        assert!(history.get(first + 1).is_some());
+        assert!(history.get(first + HISTORY_LEN as u32 + 1).is_none());
+    }
+}
@@ -0,0 +1,140 @@
+//! Авторитетный цикл сервера: с частотой `simulation.network.tickRateHz`
+//! применяет накопленный ввод игроков и рассылает снапшот, если мир
+//! изменился. Сообщения клиентов только ставят ввод в очередь, поэтому
+//! частота рассылки не зависит от того, как часто шлёт ввод самый
+//! активный клиент.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+use tracing::info;
+
+use crate::worlds::WorldHandle;
+use crate::{
+    catastrophes, clock, conn, deform, objects, record, send_world_snapshot, session, weather,
+    WorldState,
+};
+
+/// Сколько сообщений ввода одного игрока учитывается за тик; остальные
+/// отбрасываются, чтобы флуд не ускорял игрока
+const MAX_INPUTS_PER_TICK: usize = 8;
+const MIN_TICK_RATE_HZ: u32 = 1;
+const MAX_TICK_RATE_HZ: u32 = 240;
+
+/// Ввод игрока, ожидающий ближайшего тика
+#[derive(Debug, Clone)]
+pub struct QueuedInput {
+    pub client_id: String,
+    pub delta: [f32; 3],
+    pub input_id: Option<u32>,
+    // Соединение, приславшее ввод; None — ввод из записи сессии
+    pub sender: Option<conn::Outbox>,
+}
+
+/// Цикл тиков; когда `stop` станет true, доделывает ещё один тик, чтобы
+/// применить накопленный ввод, и выходит
+pub async fn run(world: WorldHandle, mut stop: watch::Receiver<bool>) {
+    let (name, rate) = {
+        let world = world.lock().await;
+        let rate = world
+            .config
+            .simulation
+            .network
+            .tick_rate_hz
+            .clamp(MIN_TICK_RATE_HZ, MAX_TICK_RATE_HZ);
+        (world.name.clone(), rate)
+    };
+    info!("[{}] Tick loop at {} Hz", name, rate);
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rate as f64));
+    // после долгой паузы не догоняем пропущенные тики пачкой
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut last = Instant::now();
+    let mut stopping = false;
+    while !stopping {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = stop.changed() => stopping = true,
+        }
+        let now = Instant::now();
+        let dt = now - last;
+        last = now;
+        let changed = {
+            let mut world = world.lock().await;
+            // уход не вернувшихся игроков записывается событием этого тика
+            session::expire(&mut world);
+            let changed = advance(&mut world, dt);
+            record::frame(&mut world, dt);
+            changed
+        };
+        if changed {
+            send_world_snapshot(&world).await;
+        }
+    }
+    info!("[{}] Tick loop stopped", name);
+}
+
+/// Один тик мира без сети — общий для сервера и `--replay`;
+/// true — мир изменился с прошлого тика
+pub fn advance(world: &mut WorldState, dt: Duration) -> bool {
+    world.tick += 1;
+    clock::tick(world, dt);
+    weather::tick(world, dt);
+    catastrophes::tick(world);
+    let changed = step(world, dt);
+    // сначала изменения загруженным чанкам, потом новые чанки целиком
+    deform::flush(world);
+    objects::stream(world);
+    changed
+}
+
+/// Применяет очередь ввода и пересчитывает скорости игроков за `dt`;
+/// true — мир изменился с прошлого тика
+fn step(world: &mut WorldState, dt: Duration) -> bool {
+    let mut per_player: HashMap<String, usize> = HashMap::new();
+    let mut moved: HashMap<String, [f32; 3]> = HashMap::new();
+    for input in std::mem::take(&mut world.inputs) {
+        // ввод соединения, чью сессию уже забрало другое, не применяется
+        if let Some(sender) = &input.sender {
+            let owner = world.clients.get(&input.client_id);
+            if owner.is_none_or(|c| !c.sender.same(sender)) {
+                continue;
+            }
+        }
+        let Some(p) = world.players.get_mut(&input.client_id) else {
+            continue;
+        };
+        // отброшенный ввод тоже считается обработанным: повторять его
+        // клиенту не нужно
+        if let Some(id) = input.input_id.filter(|id| *id > p.last_input_id) {
+            p.last_input_id = id;
+            world.dirty = true;
+        }
+        let count = per_player.entry(input.client_id.clone()).or_default();
+        *count += 1;
+        if *count > MAX_INPUTS_PER_TICK {
+            continue;
+        }
+        let [dx, dy, dz] = input.delta;
+        p.x += dx;
+        p.y += dy;
+        p.z += dz;
+        let m = moved.entry(input.client_id).or_default();
+        m[0] += dx;
+        m[1] += dy;
+        m[2] += dz;
+        world.dirty = true;
+    }
+    let dt = dt.as_secs_f32();
+    if dt > 0.0 {
+        for (id, p) in world.players.iter_mut() {
+            let velocity = moved.get(id).map_or([0.0; 3], |m| m.map(|v| v / dt));
+            if p.velocity != velocity {
+                p.velocity = velocity;
+                world.dirty = true;
+            }
+        }
+    }
+    std::mem::take(&mut world.dirty)
+}
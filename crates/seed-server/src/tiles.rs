@@ -0,0 +1,207 @@
+//! HTTP-тайлы рельефа и биомов для игровых клиентов и веб-карт:
+//! `GET /api/heightmap/{z}/{x}/{y}` и `GET /api/biomes/{z}/{x}/{y}`.
+//!
+//! На уровне `z` карта делится на 2^z × 2^z тайлов, `x` растёт на восток,
+//! `y` — на юг (как у веб-карт). Тайл — сетка `size`×`size` отсчётов
+//! (`?size=`, по умолчанию 256), строки с севера на юг. Крайние отсчёты
+//! соседних тайлов совпадают, поэтому меши стыкуются без швов. Высоты
+//! берутся из карты сервера билинейно, биомы — по ближайшей клетке.
+//!
+//! Мир выбирается `?world=`, как у остальных `/api`.
+//!
+//! Тело ответа — сырые little-endian числа (`application/octet-stream`):
+//! - `format=f32` — высота над уровнем моря, м;
+//! - `format=u16` — высота, квантованная в диапазон карты из заголовков
+//!   `x-elevation-min` / `x-elevation-max` (одинаковый для всех тайлов);
+//! - биомы — u8 индекс в `biomes` конфига, 255 — вода.
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::Response,
+};
+use seed_core::{BiomeMap, Heightmap, MAX_RELIEF_M};
+use serde::Deserialize;
+
+use crate::worlds;
+use crate::AppState;
+
+/// Глубже — только интерполяция карты сервера, новых деталей не появится
+pub(crate) const MAX_ZOOM: u32 = 12;
+const DEFAULT_TILE_SIZE: u32 = 256;
+const MAX_TILE_SIZE: u32 = 1024;
+/// Клетка без биома
+pub(crate) const NO_BIOME: u8 = u8::MAX;
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum HeightFormat {
+    #[default]
+    F32,
+    U16,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TileQuery {
+    #[serde(default)]
+    format: HeightFormat,
+    size: Option<u32>,
+    world: Option<String>,
+}
+
+type TileError = (StatusCode, String);
+
+/// Тайл в долях карты: угол (x0, y0), сторона span и число отсчётов по стороне
+struct TileRect {
+    x0: f64,
+    y0: f64,
+    span: f64,
+    size: u32,
+}
+
+impl TileRect {
+    fn new(z: u32, x: u32, y: u32, size: Option<u32>) -> Result<Self, TileError> {
+        if z > MAX_ZOOM {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("zoom {z} is above the maximum {MAX_ZOOM}"),
+            ));
+        }
+        let tiles = 1u32 << z;
+        if x >= tiles || y >= tiles {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("tile {x},{y} is outside the {tiles}x{tiles} grid at zoom {z}"),
+            ));
+        }
+        let size = size.unwrap_or(DEFAULT_TILE_SIZE);
+        if !(2..=MAX_TILE_SIZE).contains(&size) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("size must be within 2..={MAX_TILE_SIZE}"),
+            ));
+        }
+        let span = 1.0 / tiles as f64;
+        Ok(Self {
+            x0: x as f64 * span,
+            y0: y as f64 * span,
+            span,
+            size,
+        })
+    }
+
+    /// Отсчёты тайла построчно: координаты в клетках карты `w`×`h`
+    fn samples(&self, w: u32, h: u32) -> impl Iterator<Item = (f64, f64)> + '_ {
+        let step = self.span / (self.size - 1) as f64;
+        let (max_x, max_y) = ((w.max(1) - 1) as f64, (h.max(1) - 1) as f64);
+        (0..self.size).flat_map(move |j| {
+            (0..self.size).map(move |i| {
+                (
+                    (self.x0 + i as f64 * step) * max_x,
+                    (self.y0 + j as f64 * step) * max_y,
+                )
+            })
+        })
+    }
+}
+
+pub async fn heightmap_tile(
+    State(state): State<AppState>,
+    Path((z, x, y)): Path<(u32, u32, u32)>,
+    Query(query): Query<TileQuery>,
+) -> Result<Response, TileError> {
+    let rect = TileRect::new(z, x, y, query.size)?;
+    let world = worlds::resolve(&state, query.world.as_deref()).await?;
+    let world = world.lock().await;
+    let (hm, sea) = (&world.heightmap, world.config.sea_level);
+    let elevations = rect
+        .samples(hm.width, hm.height)
+        .map(|(px, py)| elevation_m(bilinear(hm, px, py), sea));
+
+    let mut body = Vec::with_capacity((rect.size * rect.size * 4) as usize);
+    let mut response = tile_response(rect.size);
+    match query.format {
+        HeightFormat::F32 => {
+            for e in elevations {
+                body.extend_from_slice(&(e as f32).to_le_bytes());
+            }
+        }
+        HeightFormat::U16 => {
+            let (lo, hi) = hm
+                .values
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+            let (lo, hi) = (elevation_m(lo as f64, sea), elevation_m(hi as f64, sea));
+            let range = (hi - lo).max(1e-6);
+            for e in elevations {
+                let q = ((e - lo) / range * u16::MAX as f64).round();
+                body.extend_from_slice(&(q.clamp(0.0, u16::MAX as f64) as u16).to_le_bytes());
+            }
+            response = response
+                .header("x-elevation-min", lo.to_string())
+                .header("x-elevation-max", hi.to_string());
+        }
+    }
+    finish(
+        response.header("x-tile-format", format_name(query.format)),
+        body,
+    )
+}
+
+pub async fn biome_tile(
+    State(state): State<AppState>,
+    Path((z, x, y)): Path<(u32, u32, u32)>,
+    Query(query): Query<TileQuery>,
+) -> Result<Response, TileError> {
+    let rect = TileRect::new(z, x, y, query.size)?;
+    let world = worlds::resolve(&state, query.world.as_deref()).await?;
+    let world = world.lock().await;
+    let bm = &world.biomemap;
+    let body: Vec<u8> = rect
+        .samples(bm.width, bm.height)
+        .map(|(px, py)| nearest_biome(bm, px, py))
+        .collect();
+    finish(tile_response(rect.size).header("x-tile-format", "u8"), body)
+}
+
+fn tile_response(size: u32) -> axum::http::response::Builder {
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header("x-tile-size", size.to_string())
+}
+
+fn finish(builder: axum::http::response::Builder, body: Vec<u8>) -> Result<Response, TileError> {
+    builder
+        .body(Body::from(body))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+fn format_name(format: HeightFormat) -> &'static str {
+    match format {
+        HeightFormat::F32 => "f32",
+        HeightFormat::U16 => "u16",
+    }
+}
+
+/// Высота над уровнем моря, м — как `Heightmap::elevation_m`, но для
+/// произвольного значения карты
+fn elevation_m(v: f64, sea_level: f64) -> f64 {
+    (v - sea_level) / (1.0 - sea_level).max(1e-6) * MAX_RELIEF_M
+}
+
+pub(crate) fn bilinear(hm: &Heightmap, px: f64, py: f64) -> f64 {
+    let (x0, y0) = (px.floor() as u32, py.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(hm.width - 1), (y0 + 1).min(hm.height - 1));
+    let (tx, ty) = (px - x0 as f64, py - y0 as f64);
+    let at = |x: u32, y: u32| hm.get(x, y) as f64;
+    let top = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+    let bottom = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+fn nearest_biome(bm: &BiomeMap, px: f64, py: f64) -> u8 {
+    let x = (px.round() as u32).min(bm.width - 1);
+    let y = (py.round() as u32).min(bm.height - 1);
+    bm.indices[(y * bm.width + x) as usize].unwrap_or(NO_BIOME)
+}
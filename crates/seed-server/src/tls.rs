@@ -0,0 +1,24 @@
+//! HTTPS и wss:// прямо из сервера (фича `tls`): `--tls-cert` и `--tls-key`
+//! — PEM-файлы сертификата (с цепочкой) и его ключа. Без них сервер
+//! слушает обычный HTTP, и TLS можно снять на обратном прокси.
+
+use std::net::SocketAddr;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+
+/// Слушает `addr` по HTTPS, пока сервер не остановят
+pub async fn serve(addr: SocketAddr, app: Router, cert: &Path, key: &Path) -> Result<()> {
+    // rustls собран без поставщика криптографии по умолчанию — ставим ring;
+    // ошибка значит, что поставщик уже есть
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let config = RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| format!("loading {} and {}", cert.display(), key.display()))?;
+    axum_server::bind_rustls(addr, config)
+        .serve(app.into_make_service())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,319 @@
+//! Погода в реальном времени. Карта делится на регионы по `REGION_CELLS`
+//! клеток; у каждого региона есть облачность, осадки и ветер. Фон берётся из
+//! климата seed-core (влажность и годовые осадки на широте и высоте центра
+//! региона, преобладающий ветер), поверх него ходят бури: появляются с
+//! частотой `environment.climateModel.stormFrequency`, сила — около
+//! `stormIntensityMean`, дрейфуют по преобладающему ветру.
+//!
+//! Погода идёт в игровом времени (часы мира) и пересчитывается раз в
+//! `STEP_INTERVAL`; клиент раз в `BROADCAST_INTERVAL` и при входе получает
+//! `weather` своего региона. Ливень копится по регионам: набравший
+//! `FLOOD_RAIN_MM` регион публикует в шину событий мира `Flood`, новая буря —
+//! `Storm`; их читают экосистема и директор сюжета.
+
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use seed_config::WorldConfig;
+use seed_core::biome::{prevailing_wind, sample_climate};
+use seed_core::{Heightmap, SeedTree, WorldEvent, WorldEventKind, MAX_RELIEF_M};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::protocol::Feature;
+use crate::{clock, ServerMessage, WorldState};
+
+/// Сторона региона погоды в клетках карты
+pub const REGION_CELLS: u32 = 64;
+const STEP_INTERVAL: Duration = Duration::from_secs(1);
+const BROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+/// Бурь за игровые сутки при `stormFrequency` = 1
+const STORMS_PER_DAY: f64 = 4.0;
+const STORM_HOURS: (f64, f64) = (3.0, 9.0);
+/// Радиус бури в регионах
+const STORM_RADIUS: (f64, f64) = (0.8, 2.0);
+/// Скорость дрейфа бури, регионов в игровой час
+const STORM_DRIFT: f64 = 0.3;
+/// Ливень в центре бури силы 1, мм/ч
+const STORM_RAIN_MM_H: f64 = 30.0;
+const STORM_GUST_MS: f64 = 20.0;
+const BASE_WIND_MS: f64 = 5.0;
+/// Осадки, с которых дождь считается ливнем, мм/ч
+const HEAVY_RAIN_MM_H: f64 = 10.0;
+/// Сколько ливня регион набирает до наводнения, мм
+const FLOOD_RAIN_MM: f64 = 60.0;
+
+/// Погода региона в сообщении `weather`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionWeather {
+    /// Регион: столбец и строка
+    pub region: [u32; 2],
+    /// 0..1
+    pub cloud_cover: f32,
+    pub precipitation_mm_h: f32,
+    /// Ветер, м/с: x — восток, z — север
+    pub wind: [f32; 2],
+    /// Сила бури над регионом, 0 — бури нет
+    pub storm: f32,
+    pub temperature_c: f32,
+}
+
+#[derive(Debug, Clone)]
+struct Region {
+    // Климатический фон
+    humidity: f64,
+    base_rain_mm_h: f64,
+    wind_dir: (f64, f64),
+    temperature_c: f64,
+    phase: f64,
+    // Текущее
+    cloud_cover: f64,
+    rain_mm_h: f64,
+    storm: f64,
+    // Ливень с прошлого наводнения
+    heavy_rain_mm: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Storm {
+    // Центр в регионах
+    x: f64,
+    y: f64,
+    radius: f64,
+    intensity: f64,
+    hours_left: f64,
+}
+
+#[derive(Debug)]
+pub struct Weather {
+    cols: u32,
+    rows: u32,
+    regions: Vec<Region>,
+    storms: Vec<Storm>,
+    rng: StdRng,
+    // Игровые часы с начала мира
+    hours: f64,
+    since_step: Duration,
+    since_broadcast: Duration,
+}
+
+impl Weather {
+    pub fn new(cfg: &WorldConfig, hm: &Heightmap) -> Self {
+        let cols = hm.width.div_ceil(REGION_CELLS).max(1);
+        let rows = hm.height.div_ceil(REGION_CELLS).max(1);
+        let h1 = hm.height.saturating_sub(1).max(1) as f64;
+        let mut rng = StdRng::seed_from_u64(SeedTree::from_config(cfg).derive("weather").seed());
+        let mut regions = Vec::with_capacity((cols * rows) as usize);
+        for ry in 0..rows {
+            for rx in 0..cols {
+                let (cx, cy) = (
+                    (rx * REGION_CELLS + REGION_CELLS / 2).min(hm.width.saturating_sub(1)),
+                    (ry * REGION_CELLS + REGION_CELLS / 2).min(hm.height.saturating_sub(1)),
+                );
+                // широта и высота — как в compute_climate_map
+                let lat = (cy as f64 / h1) * 2.0 - 1.0;
+                let h01 = if hm.width > 0 {
+                    hm.get(cx, cy) as f64
+                } else {
+                    0.0
+                };
+                let rel = ((h01 - cfg.sea_level) / (1.0 - cfg.sea_level)).clamp(0.0, 1.0);
+                let climate = sample_climate(cfg, lat, rel * MAX_RELIEF_M);
+                regions.push(Region {
+                    humidity: climate.humidity,
+                    base_rain_mm_h: climate.precipitation_mm_per_year / (365.0 * 24.0),
+                    wind_dir: prevailing_wind(cfg, lat * 90.0),
+                    temperature_c: climate.temperature_c,
+                    phase: rng.gen_range(0.0..TAU),
+                    cloud_cover: 0.0,
+                    rain_mm_h: 0.0,
+                    storm: 0.0,
+                    heavy_rain_mm: 0.0,
+                });
+            }
+        }
+        Self {
+            cols,
+            rows,
+            regions,
+            storms: Vec::new(),
+            rng,
+            hours: 0.0,
+            since_step: STEP_INTERVAL,
+            since_broadcast: Duration::ZERO,
+        }
+    }
+
+    fn region_of(&self, cell_x: f64, cell_y: f64) -> usize {
+        let rx = ((cell_x / REGION_CELLS as f64).floor().max(0.0) as u32).min(self.cols - 1);
+        let ry = ((cell_y / REGION_CELLS as f64).floor().max(0.0) as u32).min(self.rows - 1);
+        (ry * self.cols + rx) as usize
+    }
+
+    fn region_weather(&self, idx: usize) -> RegionWeather {
+        let r = &self.regions[idx];
+        let gust = STORM_GUST_MS * r.storm;
+        let speed = BASE_WIND_MS * (0.6 + 0.8 * r.cloud_cover) + gust;
+        RegionWeather {
+            region: [idx as u32 % self.cols, idx as u32 / self.cols],
+            cloud_cover: r.cloud_cover as f32,
+            precipitation_mm_h: r.rain_mm_h as f32,
+            wind: [(r.wind_dir.0 * speed) as f32, (r.wind_dir.1 * speed) as f32],
+            storm: r.storm as f32,
+            temperature_c: r.temperature_c as f32,
+        }
+    }
+
+    /// Продвигает погоду на `hours` игровых часов; события — в `events`
+    fn step(&mut self, cfg: &WorldConfig, hours: f64, events: &mut Vec<WorldEvent>) {
+        self.hours += hours;
+        let clim = &cfg.environment.climate_model;
+
+        // новые бури: пуассоновский поток по всей карте
+        let rate = clim.storm_frequency.max(0.0) as f64 * STORMS_PER_DAY / 24.0;
+        if self.rng.gen_bool((rate * hours).clamp(0.0, 1.0)) {
+            let intensity =
+                (clim.storm_intensity_mean as f64 * self.rng.gen_range(0.5..1.5)).clamp(0.05, 1.0);
+            let storm = Storm {
+                x: self.rng.gen_range(0.0..self.cols as f64),
+                y: self.rng.gen_range(0.0..self.rows as f64),
+                radius: self.rng.gen_range(STORM_RADIUS.0..STORM_RADIUS.1),
+                intensity,
+                hours_left: self.rng.gen_range(STORM_HOURS.0..STORM_HOURS.1),
+            };
+            events.push(self.event(
+                WorldEventKind::Storm,
+                storm.x,
+                storm.y,
+                storm.radius,
+                intensity,
+            ));
+            self.storms.push(storm);
+        }
+
+        // бури дрейфуют по ветру под собой и выдыхаются
+        for storm in &mut self.storms {
+            let rx = (storm.x.floor().max(0.0) as u32).min(self.cols - 1);
+            let ry = (storm.y.floor().max(0.0) as u32).min(self.rows - 1);
+            let (wx, wy) = self.regions[(ry * self.cols + rx) as usize].wind_dir;
+            storm.x += wx * STORM_DRIFT * hours;
+            storm.y += wy * STORM_DRIFT * hours;
+            storm.hours_left -= hours;
+        }
+        let (cols, rows) = (self.cols as f64, self.rows as f64);
+        self.storms.retain(|s| {
+            s.hours_left > 0.0
+                && s.x > -s.radius
+                && s.y > -s.radius
+                && s.x < cols + s.radius
+                && s.y < rows + s.radius
+        });
+
+        let day_phase = TAU * self.hours / 24.0;
+        let mut floods = Vec::new();
+        for (idx, r) in self.regions.iter_mut().enumerate() {
+            let (x, y) = (
+                (idx as u32 % self.cols) as f64 + 0.5,
+                (idx as u32 / self.cols) as f64 + 0.5,
+            );
+            r.storm = self
+                .storms
+                .iter()
+                .map(|s| {
+                    let d = (x - s.x).hypot(y - s.y) / s.radius;
+                    s.intensity * (1.0 - d * d).max(0.0)
+                })
+                .fold(0.0, f64::max);
+            // фон медленно колышется вокруг климатической влажности
+            let drift = 0.2 * (day_phase / 3.0 + r.phase).sin();
+            r.cloud_cover = (r.humidity * 0.8 + drift + r.storm).clamp(0.0, 1.0);
+            let background = if r.cloud_cover > 0.6 {
+                // годовая норма выпадает в пасмурные часы
+                r.base_rain_mm_h * 3.0 * (r.cloud_cover - 0.6) / 0.4
+            } else {
+                0.0
+            };
+            r.rain_mm_h = background + STORM_RAIN_MM_H * r.storm;
+
+            if r.rain_mm_h >= HEAVY_RAIN_MM_H {
+                r.heavy_rain_mm += r.rain_mm_h * hours;
+                if r.heavy_rain_mm >= FLOOD_RAIN_MM {
+                    r.heavy_rain_mm = 0.0;
+                    floods.push((x, y, (r.rain_mm_h / STORM_RAIN_MM_H).min(1.0)));
+                }
+            } else {
+                // земля подсыхает
+                r.heavy_rain_mm = (r.heavy_rain_mm - HEAVY_RAIN_MM_H * hours).max(0.0);
+            }
+        }
+        for (x, y, intensity) in floods {
+            events.push(self.event(WorldEventKind::Flood, x, y, 0.7, intensity));
+        }
+    }
+
+    /// Событие в клетках карты по центру и радиусу в регионах
+    fn event(
+        &self,
+        kind: WorldEventKind,
+        x: f64,
+        y: f64,
+        radius: f64,
+        intensity: f64,
+    ) -> WorldEvent {
+        let cell = REGION_CELLS as f64;
+        WorldEvent {
+            kind,
+            x: (x * cell).max(0.0) as u32,
+            y: (y * cell).max(0.0) as u32,
+            radius: (radius * cell) as f32,
+            intensity: intensity.clamp(0.0, 1.0) as f32,
+        }
+    }
+}
+
+/// Погода региона игрока `client_id`
+pub fn message(world: &WorldState, client_id: &str) -> Option<ServerMessage> {
+    let p = world.players.get(client_id)?;
+    let cell_m = world.config.scale.region_size_km * 1000.0 / world.heightmap.width.max(1) as f64;
+    let idx = world
+        .weather
+        .region_of(p.x as f64 / cell_m, p.z as f64 / cell_m);
+    Some(ServerMessage::Weather(world.weather.region_weather(idx)))
+}
+
+/// Продвигает погоду на `dt` реального времени, публикует события и
+/// рассылает клиентам погоду их регионов
+pub fn tick(world: &mut WorldState, dt: Duration) {
+    let scale = clock::time_scale(world);
+    let weather = &mut world.weather;
+    weather.since_step += dt;
+    weather.since_broadcast += dt;
+    if weather.since_step >= STEP_INTERVAL {
+        let hours = weather.since_step.as_secs_f64() * scale / 3600.0;
+        weather.since_step = Duration::ZERO;
+        let mut events = Vec::new();
+        weather.step(&world.config, hours, &mut events);
+        for e in events {
+            info!(
+                "[{}] {:?} at ({}, {}), intensity {:.2}",
+                world.name, e.kind, e.x, e.y, e.intensity
+            );
+            world.events.publish(e);
+        }
+    }
+
+    if world.weather.since_broadcast < BROADCAST_INTERVAL {
+        return;
+    }
+    world.weather.since_broadcast = Duration::ZERO;
+    for (cid, c) in &world.clients {
+        if !c.features.has(Feature::Weather) {
+            continue;
+        }
+        if let Some(msg) = message(world, cid).and_then(|m| m.to_text()) {
+            c.sender.send(msg);
+        }
+    }
+}
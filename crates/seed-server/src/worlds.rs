@@ -0,0 +1,442 @@
+//! Несколько миров в одном процессе. Мир по умолчанию строится из
+//! world-config.json, остальные создаёт `POST /api/worlds`; клиенты выбирают
+//! мир параметром `?world=` (`/ws`, `/api/world`, тайлы). У каждого мира своё
+//! состояние, свой цикл тиков и своё хранилище в `<data-dir>/worlds/<имя>`,
+//! поэтому созданные миры переживают перезапуск.
+//!
+//! Лимиты ресурсов: число миров, размер карты и число игроков в мире.
+//! Генерация идёт по одной за раз в блокирующем потоке и не тормозит
+//! уже запущенные миры.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::{extract::State, http::StatusCode, Json};
+use seed_config::{Severity, WorldConfig};
+use seed_core::{EventBus, World};
+use seed_save::WorldBundle;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::persist::{self, StoreKind, WorldStore};
+use crate::{
+    catastrophes, clock, deform, interest, lod, objects, record, session, sync, tick, weather,
+    AppState, PlayerState, WorldState,
+};
+
+/// Имя мира из world-config.json
+pub const DEFAULT_WORLD: &str = "default";
+pub const MAX_WORLDS: usize = 8;
+pub const DEFAULT_MAP_SIZE: u32 = 512;
+pub const MIN_MAP_SIZE: u32 = 64;
+pub const MAX_MAP_SIZE: u32 = 1024;
+pub const MAX_PLAYERS_PER_WORLD: usize = 64;
+const MAX_NAME_LEN: usize = 32;
+/// Сколько ждать остановки цикла тиков или сохранения
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub type WorldHandle = Arc<Mutex<WorldState>>;
+
+pub struct WorldEntry {
+    pub state: WorldHandle,
+    pub store: Arc<dyn WorldStore>,
+    // true — остановить цикл тиков и периодическое сохранение
+    stop: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+pub struct Worlds {
+    map: RwLock<HashMap<String, WorldEntry>>,
+    // Каталог хранилищ; созданные миры — в `worlds/<имя>`
+    data_dir: PathBuf,
+    store: StoreKind,
+    // Генерация тяжёлая — по одному миру за раз
+    creating: Mutex<()>,
+}
+
+impl Worlds {
+    pub fn new(data_dir: PathBuf, store: StoreKind) -> Self {
+        Self {
+            map: RwLock::new(HashMap::new()),
+            data_dir,
+            store,
+            creating: Mutex::new(()),
+        }
+    }
+
+    /// Хранилище созданного мира
+    fn open_store(&self, name: &str) -> Result<Arc<dyn WorldStore>> {
+        persist::open_store(self.store, &self.world_dir(name))
+    }
+
+    /// Каталог хранилища созданного мира
+    fn world_dir(&self, name: &str) -> PathBuf {
+        self.data_dir.join("worlds").join(name)
+    }
+
+    /// Мир по имени; без имени — мир по умолчанию
+    pub async fn get(&self, name: Option<&str>) -> Option<WorldHandle> {
+        let map = self.map.read().await;
+        map.get(name.unwrap_or(DEFAULT_WORLD))
+            .map(|e| e.state.clone())
+    }
+
+    pub async fn insert(&self, name: String, entry: WorldEntry) {
+        self.map.write().await.insert(name, entry);
+    }
+
+    pub async fn handles(&self) -> Vec<WorldHandle> {
+        let map = self.map.read().await;
+        map.values().map(|e| e.state.clone()).collect()
+    }
+
+    /// Останавливает циклы тиков (каждый доделывает тик с накопленным
+    /// вводом) и периодическое сохранение всех миров
+    pub async fn stop(&self) {
+        let mut map = self.map.write().await;
+        for entry in map.values_mut() {
+            let _ = entry.stop.send(true);
+        }
+        for entry in map.values_mut() {
+            for task in &mut entry.tasks {
+                let abort = task.abort_handle();
+                if tokio::time::timeout(STOP_TIMEOUT, task).await.is_err() {
+                    abort.abort();
+                }
+            }
+        }
+    }
+
+    /// Сохраняет все миры и их игроков (при остановке)
+    pub async fn save_all(&self) {
+        let map = self.map.read().await;
+        for entry in map.values() {
+            persist::save_world(&entry.state, &*entry.store).await;
+            persist::save_players(&entry.state, &*entry.store).await;
+            persist::save_clock(&entry.state, &*entry.store).await;
+        }
+    }
+}
+
+/// Мир по имени из `?world=` или 404
+pub async fn resolve(
+    state: &AppState,
+    name: Option<&str>,
+) -> Result<WorldHandle, (StatusCode, String)> {
+    state.worlds.get(name).await.ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("world '{}' not found", name.unwrap_or(DEFAULT_WORLD)),
+        )
+    })
+}
+
+/// Поднимает мир (см. `build`), запускает цикл тиков и периодическое
+/// сохранение игроков; с `record` мир пишется в запись сессии
+pub async fn open(
+    name: &str,
+    cfg: WorldConfig,
+    size: u32,
+    saved: Option<WorldBundle>,
+    store: Arc<dyn WorldStore>,
+    record: Option<&Path>,
+) -> Result<WorldEntry> {
+    let (mut state, restored) = build(name, cfg, size, saved, &*store).await?;
+    if let Some(path) = record {
+        state.recorder = Some(record::Recorder::create(path, &state)?);
+    }
+    let world: WorldHandle = Arc::new(Mutex::new(state));
+
+    let (stop, stopped) = watch::channel(false);
+    let ticker = tokio::spawn(tick::run(world.clone(), stopped.clone()));
+    if !restored {
+        persist::save_world(&world, &*store).await;
+    }
+    let saver = {
+        let (world, store, mut stopped) = (world.clone(), store.clone(), stopped);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(persist::SAVE_INTERVAL);
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    // последнее сохранение — при остановке
+                    _ = stopped.changed() => break,
+                }
+                persist::save_players(&world, &*store).await;
+                persist::save_clock(&world, &*store).await;
+                // рельеф меняют катастрофы
+                persist::save_terrain(&world, &*store).await;
+            }
+        })
+    };
+    Ok(WorldEntry {
+        state: world,
+        store,
+        stop,
+        tasks: vec![ticker, saver],
+    })
+}
+
+/// Мир без сети и цикла тиков: сохранённый, если он построен из того же
+/// конфига и в том же размере, иначе сгенерированный; true — сохранённый
+pub async fn build(
+    name: &str,
+    cfg: WorldConfig,
+    size: u32,
+    saved: Option<WorldBundle>,
+    store: &dyn WorldStore,
+) -> Result<(WorldState, bool)> {
+    let same_config = match &saved {
+        Some(s) => serde_json::to_value(&s.config)? == serde_json::to_value(&cfg)?,
+        None => false,
+    };
+    let (hm, bm, history, restored) = match saved {
+        Some(saved)
+            if same_config && saved.heightmap.width == size && saved.heightmap.height == size =>
+        {
+            info!("[{}] Restored world from save", name);
+            let mut hm = saved.heightmap;
+            let applied = store
+                .load_terrain()?
+                .iter()
+                .filter(|d| d.apply(&mut hm))
+                .count();
+            if applied > 0 {
+                info!("[{}] Applied {} changed terrain chunks", name, applied);
+            }
+            (hm, saved.biomes, saved.history, true)
+        }
+        saved => {
+            if saved.is_some() {
+                info!(
+                    "[{}] Config changed since the last save, regenerating",
+                    name
+                );
+            }
+            let world = World::with_map_size(&cfg, size, size)?;
+            let (hm, bm) = tokio::task::spawn_blocking(move || world.into_maps()).await?;
+            (hm, bm, None, false)
+        }
+    };
+    let offline_players: HashMap<String, PlayerState> = store
+        .load_players()?
+        .into_iter()
+        .map(|p| (p.id.clone(), p))
+        .collect();
+    info!("[{}] Loaded {} saved players", name, offline_players.len());
+
+    // часы идут дальше только у того же мира
+    let clock = match store.load_clock()? {
+        Some(clock) if restored => clock,
+        _ => clock::WorldClock::new(&cfg),
+    };
+
+    let weather = weather::Weather::new(&cfg, &hm);
+    // история мира нужна директору; без сохранённой — симуляция в фоне
+    let (catastrophes, surface, hm, bm) = {
+        let (gen_cfg, gen_clock) = (cfg.clone(), clock.clone());
+        tokio::task::spawn_blocking(move || {
+            let c = catastrophes::Catastrophes::new(&gen_cfg, &hm, &bm, history, &gen_clock);
+            let surface = deform::surface_map(&gen_cfg, &hm, &bm);
+            (c, surface, hm, bm)
+        })
+        .await?
+    };
+
+    let delta_sync = cfg.simulation.network.state_sync_strategy == "delta_compressed";
+    let interest =
+        interest::Interest::from_radius_km(cfg.simulation.network.region_radius_km_active);
+    let deform = deform::DeformLayer::new(&cfg, hm.width);
+    let state = WorldState {
+        name: name.to_string(),
+        config: cfg,
+        heightmap: hm,
+        biomemap: bm,
+        surface,
+        players: HashMap::new(),
+        offline_players,
+        clients: HashMap::new(),
+        delta_sync,
+        history: sync::SnapshotHistory::default(),
+        interest,
+        inputs: Vec::new(),
+        dirty: false,
+        tick: 0,
+        sessions: session::Sessions::default(),
+        object_chunks: objects::ChunkCache::default(),
+        lod_nodes: lod::NodeCache::default(),
+        deform,
+        clock,
+        weather,
+        events: EventBus::new(),
+        catastrophes,
+        recorder: None,
+    };
+    Ok((state, restored))
+}
+
+/// Поднимает миры, созданные через API до перезапуска
+pub async fn restore_created(worlds: &Worlds) {
+    let Ok(dirs) = std::fs::read_dir(worlds.data_dir.join("worlds")) else {
+        return;
+    };
+    for dir in dirs.flatten() {
+        let name = dir.file_name().to_string_lossy().into_owned();
+        if !valid_name(&name) || name == DEFAULT_WORLD {
+            continue;
+        }
+        let (saved, store) = match worlds
+            .open_store(&name)
+            .and_then(|s| Ok((s.load_world()?, s)))
+        {
+            Ok((Some(saved), store)) => (saved, store),
+            Ok((None, _)) => continue,
+            Err(e) => {
+                error!("[{}] Failed to restore world: {:#}", name, e);
+                continue;
+            }
+        };
+        let (cfg, size) = (saved.config.clone(), saved.heightmap.width);
+        match open(&name, cfg, size, Some(saved), store, None).await {
+            Ok(entry) => worlds.insert(name, entry).await,
+            Err(e) => error!("[{}] Failed to restore world: {:#}", name, e),
+        }
+    }
+}
+
+/// Игроки без зрителей — они и занимают места в мире
+pub fn playing(players: &HashMap<String, PlayerState>) -> usize {
+    players.values().filter(|p| !p.spectator()).count()
+}
+
+/// Сторона карты в допустимых пределах
+pub fn check_map_size(size: u32) -> Result<(), String> {
+    if (MIN_MAP_SIZE..=MAX_MAP_SIZE).contains(&size) {
+        Ok(())
+    } else {
+        Err(format!(
+            "map size must be within {MIN_MAP_SIZE}..={MAX_MAP_SIZE}, got {size}"
+        ))
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWorld {
+    name: String,
+    config: WorldConfig,
+    /// Сторона карты в клетках
+    map_size: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldSummary {
+    name: String,
+    world_id: String,
+    world_seed: u64,
+    map_size: u32,
+    players: usize,
+    spectators: usize,
+    max_players: usize,
+}
+
+async fn summary(world: &WorldHandle) -> WorldSummary {
+    let world = world.lock().await;
+    WorldSummary {
+        name: world.name.clone(),
+        world_id: world.config.world_id.clone(),
+        world_seed: world.config.world_seed,
+        map_size: world.heightmap.width,
+        players: playing(&world.players),
+        spectators: world.players.len() - playing(&world.players),
+        max_players: MAX_PLAYERS_PER_WORLD,
+    }
+}
+
+pub(crate) type ApiError = (StatusCode, Json<serde_json::Value>);
+
+pub(crate) fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(serde_json::json!({ "error": message.into() })))
+}
+
+/// `GET /api/worlds`
+pub async fn list_worlds(State(state): State<AppState>) -> Json<Vec<WorldSummary>> {
+    let handles = state.worlds.handles().await;
+    let mut out = Vec::with_capacity(handles.len());
+    for h in &handles {
+        out.push(summary(h).await);
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(out)
+}
+
+/// `POST /api/worlds`: `{ "name", "config", "mapSize"? }`
+pub async fn create_world(
+    State(state): State<AppState>,
+    Json(req): Json<CreateWorld>,
+) -> Result<(StatusCode, Json<WorldSummary>), ApiError> {
+    if !valid_name(&req.name) {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("world name must be 1..={MAX_NAME_LEN} characters of a-z, 0-9, '-', '_'"),
+        ));
+    }
+    let size = req.map_size.unwrap_or(DEFAULT_MAP_SIZE);
+    check_map_size(size).map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+    let errors: Vec<_> = req
+        .config
+        .validate()
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect();
+    if !errors.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": "invalid config", "diagnostics": errors })),
+        ));
+    }
+
+    let _creating = state.worlds.creating.lock().await;
+    {
+        let map = state.worlds.map.read().await;
+        if map.contains_key(&req.name) {
+            return Err(api_error(
+                StatusCode::CONFLICT,
+                format!("world '{}' already exists", req.name),
+            ));
+        }
+        if map.len() >= MAX_WORLDS {
+            return Err(api_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("server already hosts the maximum of {MAX_WORLDS} worlds"),
+            ));
+        }
+    }
+
+    info!("[{}] Creating world {}x{}", req.name, size, size);
+    let store = state
+        .worlds
+        .open_store(&req.name)
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+    let entry = open(&req.name, req.config, size, None, store, None)
+        .await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+    let world = entry.state.clone();
+    state.worlds.insert(req.name, entry).await;
+    Ok((StatusCode::CREATED, Json(summary(&world).await)))
+}
@@ -0,0 +1,11 @@
+#!/bin/sh
+# Сборка seed-wasm с потоками: фича `parallel`, атомики и std, пересобранная
+# с ними (build-std). Нужны nightly с rust-src и wasm-pack; результат — в
+# web/pkg, как у обычной сборки, но с `initThreadPool`. Страницу отдавать с
+# COOP/COEP: `seed-server --cross-origin-isolation`.
+set -e
+cd "$(dirname "$0")/../.."
+RUSTFLAGS='-C target-feature=+atomics,+bulk-memory,+mutable-globals' \
+    rustup run "${SEED_WASM_NIGHTLY:-nightly}" \
+    wasm-pack build --target web --out-dir ../../web/pkg crates/seed-wasm \
+    -- --features parallel -Z build-std=panic_abort,std
@@ -1,35 +1,263 @@
-use seed_config::WorldConfig;
+use seed_config::{BiomeConfig, HeightmapConfig, WorldConfig};
+use seed_core::biome::sample_climate;
+use seed_core::pathfinding::{self, PathOptions};
+use seed_core::raycast::{self, RayOptions};
+use seed_core::relief::{self, Sun};
+use seed_core::render::{render_rgba, Region, RenderOptions};
 use seed_core::{
-    compute_flow_accumulation, generate_biome_map_from_config, generate_heightmap_from_config,
-    BiomeMap, Heightmap,
+    apply_catastrophe_to_heightmap, compute_climate_map, compute_surface_map, extract_coastlines,
+    extract_rivers, generate_biome_map_from_config, generate_catastrophes,
+    generate_heightmap_from_config, generate_objects_for_chunk, Astronomy, BiomeMap,
+    BiomeMapBuilder, Catastrophe, CatastropheType, GenerationPhase, Heightmap, HeightmapBuilder,
+    HeightmapWindows, MapWindow, NoProgress, ObjectType, TerrainMesh, World, MAX_RELIEF_M,
 };
+use seed_save::{BundleReader, WorldBundle};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+/// `initThreadPool(n)` в JS: поднимает пул rayon из n Worker'ов. Есть только
+/// в сборке `parallel` с атомиками (`crates/seed-wasm/build-threads.sh`);
+/// вызывать после `init()` и до генерации.
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
 #[wasm_bindgen]
 pub struct SeedWorld {
     cfg: WorldConfig,
     heightmap: Heightmap,
     biomemap: BiomeMap,
+    /// Эрозия карты для `height_tile`, считается при первом тайле
+    tiles: Option<HeightmapWindows>,
+}
+
+/// Глубже тайлы уже мельче миллиметра даже на самой большой карте
+const MAX_TILE_LEVEL: u32 = 24;
+const MAX_TILE_SIZE: u32 = 4096;
+
+/// Ошибка, которую получает JS: `code` — для ветвления в коде страницы,
+/// `message` — для человека, `path` — JSON-путь к полю вроде
+/// `$.biomes[2].climateRange`, если ошибка в JSON конфига или настроек
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SeedError {
+    code: &'static str,
+    message: String,
+    path: Option<String>,
+}
+
+#[wasm_bindgen]
+impl SeedError {
+    /// `invalid_config`, `invalid_options`, `invalid_argument`,
+    /// `out_of_bounds`, `bad_slope`, `no_path`, `save_failed`,
+    /// `load_failed`, `stale_snapshot` или `not_ready`
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> String {
+        self.code.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn path(&self) -> Option<String> {
+        self.path.clone()
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        match &self.path {
+            Some(path) => format!("{} (at {path})", self.message),
+            None => self.message.clone(),
+        }
+    }
+}
+
+impl SeedError {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        SeedError {
+            code,
+            message: message.into(),
+            path: None,
+        }
+    }
+}
+
+impl From<pathfinding::PathError> for SeedError {
+    fn from(e: pathfinding::PathError) -> Self {
+        SeedError::new(e.code(), e.to_string())
+    }
+}
+
+/// JSON в `T`; ошибка — с путём к полю, на котором разбор споткнулся
+fn parse_json<T: DeserializeOwned>(
+    json: &str,
+    code: &'static str,
+    what: &str,
+) -> Result<T, SeedError> {
+    let error = |e: &serde_json::Error, path: Option<String>| SeedError {
+        code,
+        message: format!("{what} error: {e}"),
+        path,
+    };
+    let mut de = serde_json::Deserializer::from_str(json);
+    let value = serde_path_to_error::deserialize(&mut de).map_err(|e| {
+        let path = match e.path().to_string().as_str() {
+            "." => "$".to_string(),
+            p if p.starts_with('[') => format!("${p}"),
+            p => format!("$.{p}"),
+        };
+        error(e.inner(), Some(path))
+    })?;
+    de.end().map_err(|e| error(&e, None))?;
+    Ok(value)
+}
+
+/// Настройки из необязательного JSON, без него — по умолчанию
+fn parse_options<T: DeserializeOwned + Default>(
+    options_json: Option<&str>,
+    what: &str,
+) -> Result<T, SeedError> {
+    match options_json {
+        Some(json) => parse_json(json, "invalid_options", what),
+        None => Ok(T::default()),
+    }
+}
+
+fn parse_config(config_json: &str) -> Result<WorldConfig, SeedError> {
+    parse_json(config_json, "invalid_config", "Config parse")
+}
+
+/// Правка конфига для `SeedWorld::regenerate`: заменяемые части
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigPatch {
+    seed: Option<u64>,
+    heightmap: Option<HeightmapConfig>,
+    biomes: Option<Vec<BiomeConfig>>,
+}
+
+/// Части конфига одинаковы (у типов конфига нет `PartialEq`)
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
 }
 
 #[wasm_bindgen]
 impl SeedWorld {
-    /// Создаёт мир из JSON-строки конфигурации
+    /// Создаёт мир из JSON-строки конфигурации. На больших картах
+    /// блокирует страницу на секунды — тогда лучше `SeedWorldBuilder`
     #[wasm_bindgen(constructor)]
-    pub fn new(config_json: &str, width: u32, height: u32) -> Result<SeedWorld, JsValue> {
-        let cfg: WorldConfig = serde_json::from_str(config_json)
-            .map_err(|e| JsValue::from_str(&format!("Config parse error: {e}")))?;
-
-        let hm = generate_heightmap_from_config(&cfg, width, height);
-        let bm = generate_biome_map_from_config(&cfg, &hm);
+    pub fn new(config_json: &str, width: u32, height: u32) -> Result<SeedWorld, SeedError> {
+        let cfg = parse_config(config_json)?;
+        let (hm, bm) = World::with_map_size(&cfg, width, height)
+            .map_err(|e| SeedError::new("invalid_config", e.to_string()))?
+            .into_maps();
 
         Ok(SeedWorld {
             cfg,
             heightmap: hm,
             biomemap: bm,
+            tiles: None,
         })
     }
 
+    /// Меняет часть конфига и пересчитывает только то, что от неё зависит.
+    /// `patch_json` — `{"seed": 42, "heightmap": {...}, "biomes": [...]}`,
+    /// любые из полей: `heightmap` — как `geology.heightmap` конфига,
+    /// `biomes` — как `biomes`; `seed` задаёт и `world_seed`, и сид рельефа
+    /// (поверх `heightmap.baseSeed`). Новый сид или рельеф — заново
+    /// heightmap и биомы, только биомы — карта биомов на прежнем рельефе.
+    /// Возвращает пересчитанные этапы: `["heightmap", "biomes"]`, `["biomes"]`
+    /// или пусто, если ничего не изменилось
+    #[wasm_bindgen]
+    pub fn regenerate(&mut self, patch_json: &str) -> Result<Vec<String>, SeedError> {
+        let patch: ConfigPatch = parse_json(patch_json, "invalid_options", "Regenerate options")?;
+        let mut cfg = self.cfg.clone();
+        if let Some(heightmap) = patch.heightmap {
+            cfg.geology.heightmap = heightmap;
+        }
+        if let Some(seed) = patch.seed {
+            cfg.world_seed = seed;
+            cfg.geology.heightmap.base_seed = seed;
+        }
+        if let Some(biomes) = patch.biomes {
+            cfg.biomes = biomes;
+        }
+
+        let relief = !same(&cfg.geology.heightmap, &self.cfg.geology.heightmap);
+        // сид мира входит и в выбор биомов
+        let biomes =
+            relief || cfg.world_seed != self.cfg.world_seed || !same(&cfg.biomes, &self.cfg.biomes);
+        self.cfg = cfg;
+
+        let mut stages = Vec::new();
+        if relief {
+            let (w, h) = (self.heightmap.width, self.heightmap.height);
+            self.heightmap = generate_heightmap_from_config(&self.cfg, w, h);
+            self.tiles = None;
+            stages.push("heightmap".to_string());
+        }
+        if biomes {
+            self.biomemap = generate_biome_map_from_config(&self.cfg, &self.heightmap);
+            stages.push("biomes".to_string());
+        }
+        Ok(stages)
+    }
+
+    /// Снимок мира — бандл `.seedworld` с конфигом, heightmap и картой
+    /// биомов, сжатыми zstd (`from_bytes` читает и старые, с DEFLATE), —
+    /// например, для кэша в IndexedDB
+    #[wasm_bindgen]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SeedError> {
+        WorldBundle {
+            config: self.cfg.clone(),
+            heightmap: self.heightmap.clone(),
+            biomes: self.biomemap.clone(),
+            objects: None,
+            history: None,
+            catastrophes: None,
+        }
+        .to_bytes()
+        .map_err(|e| SeedError::new("save_failed", format!("Save error: {e}")))
+    }
+
+    /// Мир из снимка `to_bytes` без генерации. С `config_json` снимок
+    /// принимается, только если сделан с тем же конфигом, — иначе ошибка
+    /// и мир надо сгенерировать заново
+    #[wasm_bindgen]
+    pub fn from_bytes(bytes: &[u8], config_json: Option<String>) -> Result<SeedWorld, SeedError> {
+        let load_error =
+            |e: seed_save::SaveError| SeedError::new("load_failed", format!("Load error: {e}"));
+        if let Some(json) = config_json {
+            let expected = seed_save::config_hash(&parse_config(&json)?).map_err(load_error)?;
+            let saved = BundleReader::new(bytes)
+                .map_err(load_error)?
+                .index
+                .config_hash();
+            if saved != Some(expected) {
+                return Err(SeedError::new(
+                    "stale_snapshot",
+                    "Saved world was generated with a different config",
+                ));
+            }
+        }
+        let bundle = WorldBundle::from_bytes(bytes).map_err(load_error)?;
+        Ok(SeedWorld {
+            cfg: bundle.config,
+            heightmap: bundle.heightmap,
+            biomemap: bundle.biomes,
+            tiles: None,
+        })
+    }
+
+    /// Отпечаток конфига мира (CRC32), как в снимке `to_bytes`
+    #[wasm_bindgen(getter)]
+    pub fn config_hash(&self) -> Result<u32, SeedError> {
+        seed_save::config_hash(&self.cfg).map_err(|e| SeedError::new("save_failed", e.to_string()))
+    }
+
     /// Ширина карты
     #[wasm_bindgen(getter)]
     pub fn width(&self) -> u32 {
@@ -48,10 +276,138 @@ impl SeedWorld {
         self.heightmap.values.clone()
     }
 
-    /// Возвращает RGBA-буфер "worldview" (биомы + освещение рельефа)
+    /// Возвращает RGBA-буфер "worldview" (биомы + освещение рельефа).
+    /// `options_json` — `RenderOptions` из seed-core, например
+    /// `{"layers": {"rivers": false}, "lightDir": [-1, -1, 1]}`; без него —
+    /// все слои и обычные цвета
     #[wasm_bindgen]
-    pub fn worldview_rgba(&self) -> Vec<u8> {
-        build_worldview_rgba(&self.heightmap, &self.biomemap, &self.cfg)
+    pub fn worldview_rgba(&self, options_json: Option<String>) -> Result<Vec<u8>, SeedError> {
+        let options: RenderOptions = parse_options(options_json.as_deref(), "Render options")?;
+        Ok(render_rgba(
+            &self.heightmap,
+            &self.biomemap,
+            &self.cfg,
+            Region::full(&self.heightmap),
+            &options,
+        ))
+    }
+
+    /// `worldview_rgba`, уменьшенный для миникарты усреднением по площади:
+    /// ширина — `min(max_width, width)`, высота — в той же пропорции (не
+    /// меньше 1), то есть длина буфера / 4 / ширина
+    #[wasm_bindgen]
+    pub fn worldview_rgba_scaled(
+        &self,
+        max_width: u32,
+        options_json: Option<String>,
+    ) -> Result<Vec<u8>, SeedError> {
+        let (w, h) = (self.heightmap.width, self.heightmap.height);
+        let out_w = max_width.clamp(1, w.max(1));
+        let out_h = ((h as u64 * out_w as u64 / w.max(1) as u64) as u32).max(1);
+        let rgba: Vec<f32> = self
+            .worldview_rgba(options_json)?
+            .into_iter()
+            .map(f32::from)
+            .collect();
+        Ok(area_average(&rgba, w, h, 4, out_w, out_h)
+            .into_iter()
+            .map(|v| v.round() as u8)
+            .collect())
+    }
+
+    /// Высоты, уменьшенные в `factor` раз по каждой оси усреднением по
+    /// площади: `ceil(width / factor)` × `ceil(height / factor)` построчно
+    #[wasm_bindgen]
+    pub fn heightmap_downsampled(&self, factor: u32) -> Result<Vec<f32>, SeedError> {
+        if factor == 0 {
+            return Err(SeedError::new(
+                "invalid_argument",
+                "factor must be at least 1",
+            ));
+        }
+        let (w, h) = (self.heightmap.width, self.heightmap.height);
+        Ok(area_average(
+            &self.heightmap.values,
+            w,
+            h,
+            1,
+            w.div_ceil(factor),
+            h.div_ceil(factor),
+        ))
+    }
+
+    /// Тайл высот `tile_size`×`tile_size` для просмотра мира крупнее карты:
+    /// на уровне `level` карта делится на 2^level × 2^level тайлов, тайл
+    /// (`tile_x`, `tile_y`) считается заново в своём разрешении (крупный
+    /// рельеф — с карты, шум — досчитывается), соседние тайлы сходятся на
+    /// общем краю. Полная карта в таком разрешении нигде не хранится. Тайлы
+    /// следуют сгенерированному рельефу, без катастроф и прочих правок
+    #[wasm_bindgen]
+    pub fn height_tile(
+        &mut self,
+        level: u32,
+        tile_x: u32,
+        tile_y: u32,
+        tile_size: u32,
+    ) -> Result<Vec<f32>, SeedError> {
+        if level > MAX_TILE_LEVEL {
+            return Err(SeedError::new(
+                "invalid_argument",
+                format!("level must be at most {MAX_TILE_LEVEL}"),
+            ));
+        }
+        if !(2..=MAX_TILE_SIZE).contains(&tile_size) {
+            return Err(SeedError::new(
+                "invalid_argument",
+                format!("tile size must be within 2..={MAX_TILE_SIZE}"),
+            ));
+        }
+        let tiles = 1u32 << level;
+        if tile_x >= tiles || tile_y >= tiles {
+            return Err(SeedError::new("out_of_bounds", "tile is outside the map"));
+        }
+        let (w, h) = (self.heightmap.width, self.heightmap.height);
+        let cfg = &self.cfg;
+        let windows = self
+            .tiles
+            .get_or_insert_with(|| HeightmapWindows::new(cfg, w, h, &NoProgress));
+        let n = tiles as f64;
+        let window = MapWindow {
+            x0: tile_x as f64 / n,
+            y0: tile_y as f64 / n,
+            x1: (tile_x + 1) as f64 / n,
+            y1: (tile_y + 1) as f64 / n,
+        };
+        Ok(windows
+            .window(window, tile_size, tile_size, &NoProgress)
+            .values)
+    }
+
+    /// Карта нормалей для освещения рельефа в three.js (`normalMap`):
+    /// RGBA, построчно с севера, как `worldview_rgba`; RGB — восток, север,
+    /// вверх (tangent space, зелёный — как в OpenGL). `z_factor` — во
+    /// сколько раз преувеличить рельеф, по умолчанию 1
+    #[wasm_bindgen]
+    pub fn normalmap_rgba(&self, z_factor: Option<f64>) -> Vec<u8> {
+        relief::normal_map(&self.heightmap, &self.cfg, z_factor.unwrap_or(1.0), false)
+            .into_iter()
+            .flat_map(|[r, g, b]| [r, g, b, 255])
+            .collect()
+    }
+
+    /// Отмывка рельефа серым RGBA: солнце с азимута `azimuth` (градусы по
+    /// часовой стрелке от севера) на высоте `altitude` градусов над
+    /// горизонтом; `z_factor` — как у `normalmap_rgba`
+    #[wasm_bindgen]
+    pub fn hillshade_rgba(&self, azimuth: f64, altitude: f64, z_factor: Option<f64>) -> Vec<u8> {
+        let sun = Sun {
+            azimuth_deg: azimuth,
+            altitude_deg: altitude,
+        };
+        relief::hillshade(&self.heightmap, &self.cfg, z_factor.unwrap_or(1.0), sun)
+            .into_iter()
+            .flat_map(|v| [v, v, v, 255])
+            .collect()
     }
 
     /// Индексы биомов (та же сетка, что heightmap): 0..N-1 или 255 для воды/отсутствия
@@ -63,186 +419,729 @@ impl SeedWorld {
             .map(|opt| opt.unwrap_or(255)) // 255 = "нет биома / вода"
             .collect()
     }
-}
 
-// ---- Ниже — логика рендеринга worldview в RGBA ----
+    /// Splat-карта материалов поверхности (та же сетка, что heightmap):
+    /// RGBA — доли скалы, песка, снега и почвы, в сумме 255; на воде — нули
+    #[wasm_bindgen]
+    pub fn surface_splat(&self) -> Vec<u8> {
+        let climate = compute_climate_map(&self.cfg, &self.heightmap);
+        compute_surface_map(&self.cfg, &self.heightmap, &self.biomemap, &climate)
+            .splat_map()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
 
-fn build_worldview_rgba(hm: &Heightmap, bm: &BiomeMap, cfg: &WorldConfig) -> Vec<u8> {
-    let mut buf = vec![0u8; (hm.width * hm.height * 4) as usize];
+    /// Высота (0..1) в точке (x, y) в клетках карты, билинейно; точки за
+    /// краем карты прижимаются к нему
+    #[wasm_bindgen]
+    pub fn height_at(&self, x: f64, y: f64) -> f32 {
+        self.heightmap.sample(x, y)
+    }
 
-    let palette = build_biome_palette(cfg);
+    /// id биома из конфига в ближайшей к (x, y) клетке; undefined — вода
+    #[wasm_bindgen]
+    pub fn biome_id_at(&self, x: f64, y: f64) -> Option<String> {
+        let (cx, cy) = self.nearest_cell(x, y);
+        let bi = self.biomemap.get_index(cx, cy)?;
+        self.cfg.biomes.get(bi).map(|b| b.id.clone())
+    }
 
-    let shallow = [70u8, 140u8, 200u8];
-    let deep = [10u8, 30u8, 80u8];
-    let sea_level_norm = cfg.sea_level as f32;
+    /// Климат в точке (x, y): по широте и билинейной высоте, как при выборе
+    /// биомов; над морем — на высоте 0 м
+    #[wasm_bindgen]
+    pub fn climate_at(&self, x: f64, y: f64) -> Climate {
+        let h1 = self.heightmap.height.saturating_sub(1).max(1) as f64;
+        let lat = (y / h1).clamp(0.0, 1.0) * 2.0 - 1.0;
+        let sea_level = self.cfg.sea_level;
+        let rel =
+            ((self.heightmap.sample(x, y) as f64 - sea_level) / (1.0 - sea_level)).clamp(0.0, 1.0);
+        let elevation_m = rel * MAX_RELIEF_M;
+        let c = sample_climate(&self.cfg, lat, elevation_m);
+        Climate {
+            temperature_c: c.temperature_c,
+            humidity: c.humidity,
+            precipitation_mm_per_year: c.precipitation_mm_per_year,
+            elevation_m,
+        }
+    }
+}
 
-    let flow = compute_flow_accumulation(hm, sea_level_norm);
+#[wasm_bindgen]
+impl SeedWorld {
+    /// Сетка рельефа участка (x0, y0, width, height) в клетках для three.js:
+    /// вершина через 2^`lod` клеток, x — на восток, y — вверх (м над морем,
+    /// умноженные на `vertical_scale`), z — на юг, начало — северо-западный
+    /// угол карты
+    #[wasm_bindgen]
+    pub fn terrain_mesh(
+        &self,
+        lod: u32,
+        x0: u32,
+        y0: u32,
+        width: u32,
+        height: u32,
+        vertical_scale: f32,
+    ) -> Result<MeshBuffers, SeedError> {
+        let region = Region {
+            x0,
+            y0,
+            width,
+            height,
+        };
+        if !region.fits(&self.heightmap) {
+            return Err(SeedError::new("out_of_bounds", "region is outside the map"));
+        }
+        Ok(self.mesh_buffers(region, lod_step(lod)?, vertical_scale))
+    }
 
-    let river_color = [30u8, 120u8, 220u8];
-    let beach_color = [210u8, 190u8, 120u8];
-    let beach_width = 0.03_f32;
+    /// Вся карта сетками по `tile_cells`×`tile_cells` клеток, построчно с
+    /// северо-запада; соседние тайлы делят крайние вершины
+    #[wasm_bindgen]
+    pub fn terrain_mesh_tiles(
+        &self,
+        lod: u32,
+        tile_cells: u32,
+        vertical_scale: f32,
+    ) -> Result<Vec<MeshBuffers>, SeedError> {
+        let step = lod_step(lod)?;
+        if tile_cells == 0 {
+            return Err(SeedError::new(
+                "invalid_argument",
+                "tile_cells must be positive",
+            ));
+        }
+        let (w, h) = (self.heightmap.width, self.heightmap.height);
+        let mut tiles = Vec::new();
+        for y0 in (0..h.saturating_sub(1)).step_by(tile_cells as usize) {
+            for x0 in (0..w.saturating_sub(1)).step_by(tile_cells as usize) {
+                let region = Region {
+                    x0,
+                    y0,
+                    width: (tile_cells + 1).min(w - x0),
+                    height: (tile_cells + 1).min(h - y0),
+                };
+                tiles.push(self.mesh_buffers(region, step, vertical_scale));
+            }
+        }
+        Ok(tiles)
+    }
+}
 
-    let light_dir = normalize3(0.6, 0.6, 1.0);
-    let slope_scale = 40.0_f32;
+/// Уменьшает растр `w`×`h` по `channels` чисел на клетку до `out_w`×`out_h`:
+/// каждая клетка результата — среднее своей доли исходных клеток
+fn area_average(src: &[f32], w: u32, h: u32, channels: usize, out_w: u32, out_h: u32) -> Vec<f32> {
+    // доля исходной оси `size` для клетки `i` из `out` — хотя бы одна клетка
+    let span = |i: u32, out: u32, size: u32| {
+        let start = (i as u64 * size as u64 / out as u64) as usize;
+        let end = ((i as u64 + 1) * size as u64 / out as u64) as usize;
+        start..end.max(start + 1).min(size as usize)
+    };
+    let mut out = Vec::with_capacity(out_w as usize * out_h as usize * channels);
+    let mut sum = vec![0.0f64; channels];
+    for oy in 0..out_h {
+        let rows = span(oy, out_h, h);
+        for ox in 0..out_w {
+            let cols = span(ox, out_w, w);
+            sum.iter_mut().for_each(|s| *s = 0.0);
+            for y in rows.clone() {
+                for x in cols.clone() {
+                    let i = (y * w as usize + x) * channels;
+                    for (s, &v) in sum.iter_mut().zip(&src[i..i + channels]) {
+                        *s += v as f64;
+                    }
+                }
+            }
+            let n = (rows.len() * cols.len()) as f64;
+            out.extend(sum.iter().map(|s| (s / n) as f32));
+        }
+    }
+    out
+}
 
-    let h_h = hm.height as f32;
+/// Сторона чанка в клетках для списков изменённых участков
+const CHUNK_CELLS: u32 = 32;
+/// Чисел на объект в `objects_for_chunk`
+const OBJECT_STRIDE: usize = 7;
 
-    for y in 0..hm.height {
-        for x in 0..hm.width {
-            let hc = hm.get(x, y) as f32;
-            let idx1 = (y * hm.width + x) as usize;
+/// Имена типов объектов по их id из `objects_for_chunk`
+#[wasm_bindgen]
+pub fn object_type_names() -> Vec<String> {
+    ObjectType::ALL
+        .iter()
+        .map(|t| t.name().to_string())
+        .collect()
+}
 
-            // --- высота и соседи ---
-            let xl = x.saturating_sub(1);
-            let xr = (x + 1).min(hm.width - 1);
-            let yu = y.saturating_sub(1);
-            let yd = (y + 1).min(hm.height - 1);
+#[wasm_bindgen]
+impl SeedWorld {
+    /// Деревья, камни и дома участка (x, y, w, h) в клетках — те же, что у
+    /// сервера и seed-cli. Плоский массив по 7 чисел на объект: x, y
+    /// (клетки), z (высота 0..1), id типа (`object_type_names`), масштаб,
+    /// поворот вокруг вертикали (рад), вариант модели
+    #[wasm_bindgen]
+    pub fn objects_for_chunk(&self, x: u32, y: u32, w: u32, h: u32) -> Vec<f32> {
+        let objects = generate_objects_for_chunk(
+            &self.cfg,
+            &self.heightmap,
+            &self.biomemap,
+            x,
+            y,
+            w,
+            h,
+            self.cfg.world_seed,
+        );
+        let mut out = Vec::with_capacity(objects.len() * OBJECT_STRIDE);
+        for o in objects {
+            out.extend([
+                o.x,
+                o.y,
+                o.z,
+                o.object_type.id() as f32,
+                o.scale,
+                o.rotation_y,
+                o.variant as f32,
+            ]);
+        }
+        out
+    }
 
-            let hl = hm.get(xl, y) as f32;
-            let hr = hm.get(xr, y) as f32;
-            let hu = hm.get(x, yu) as f32;
-            let hd = hm.get(x, yd) as f32;
+    /// Путь по рельефу от (x0, y0) до (x1, y1) в клетках: плоский массив
+    /// по 3 числа на точку — x, y (клетки) и высота поверхности, м. Точки —
+    /// концы пути и центры клеток в его поворотах. `options_json` —
+    /// `PathOptions` из seed-core: `{"allowWater": true, "maxSlopeDeg": 30,
+    /// "biomeCosts": false}`; ошибка, если пути нет
+    #[wasm_bindgen]
+    pub fn find_path(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+        options_json: Option<String>,
+    ) -> Result<Vec<f32>, SeedError> {
+        let options: PathOptions = parse_options(options_json.as_deref(), "Path options")?;
+        let (from, to) = (self.nearest_cell(x0, y0), self.nearest_cell(x1, y1));
+        let path = pathfinding::find_path(
+            &self.cfg,
+            &self.heightmap,
+            Some(&self.biomemap),
+            from,
+            to,
+            &options,
+        )?;
 
-            // --- нормаль и освещение ---
-            let dx = hr - hl;
-            let dy = hd - hu;
+        let turns = path.turns();
+        let inner = if turns.len() > 2 {
+            &turns[1..turns.len() - 1]
+        } else {
+            &[]
+        };
+        let points = std::iter::once((x0, y0, from))
+            .chain(inner.iter().map(|&c| (c.0 as f64, c.1 as f64, c)))
+            .chain(std::iter::once((x1, y1, to)));
+        let mut out = Vec::new();
+        for (x, y, (cx, cy)) in points {
+            let surface = pathfinding::surface_m(&self.cfg, &self.heightmap, cx, cy);
+            out.extend([x as f32, y as f32, surface as f32]);
+        }
+        Ok(out)
+    }
 
-            let nx = -dx * slope_scale;
-            let ny = -dy * slope_scale;
-            let nz = 1.0;
-            let normal = normalize3(nx, ny, nz);
+    /// Первое пересечение луча с рельефом в пространстве `terrain_mesh`
+    /// (метры, y — вверх) — для выбора точки камерой; undefined — мимо.
+    /// `options_json` — `RayOptions` из seed-core: `{"verticalScale": 2,
+    /// "maxDistance": 5000, "water": true}`
+    #[wasm_bindgen]
+    #[allow(clippy::too_many_arguments)]
+    pub fn raycast(
+        &self,
+        ox: f64,
+        oy: f64,
+        oz: f64,
+        dx: f64,
+        dy: f64,
+        dz: f64,
+        options_json: Option<String>,
+    ) -> Result<Option<RayHit>, SeedError> {
+        let options: RayOptions = parse_options(options_json.as_deref(), "Ray options")?;
+        let hit = raycast::raycast(
+            &self.cfg,
+            &self.heightmap,
+            [ox, oy, oz],
+            [dx, dy, dz],
+            &options,
+        );
+        Ok(hit.map(|h| RayHit {
+            x: h.point[0],
+            y: h.point[1],
+            z: h.point[2],
+            nx: h.normal[0],
+            ny: h.normal[1],
+            nz: h.normal[2],
+            distance: h.distance,
+            water: h.water,
+        }))
+    }
 
-            let dot = normal.0 * light_dir.0 + normal.1 * light_dir.1 + normal.2 * light_dir.2;
-            let mut shade = dot.max(0.0);
-            let ambient = 0.3;
-            shade = ambient + shade * (1.0 - ambient);
-            shade = shade.clamp(0.0, 1.0);
+    /// Видна ли точка b из точки a (пространство и `options_json` — как у
+    /// `raycast`). Точки на самой земле стоит приподнять на высоту глаз
+    #[wasm_bindgen]
+    #[allow(clippy::too_many_arguments)]
+    pub fn line_of_sight(
+        &self,
+        ax: f64,
+        ay: f64,
+        az: f64,
+        bx: f64,
+        by: f64,
+        bz: f64,
+        options_json: Option<String>,
+    ) -> Result<bool, SeedError> {
+        let options: RayOptions = parse_options(options_json.as_deref(), "Ray options")?;
+        Ok(raycast::line_of_sight(
+            &self.cfg,
+            &self.heightmap,
+            [ax, ay, az],
+            [bx, by, bz],
+            &options,
+        ))
+    }
 
-            // --- базовый цвет: биом или вода ---
-            let mut base_color = match bm.get_index(x, y) {
-                Some(bi) if bi < palette.len() => palette[bi],
-                _ => {
-                    // вода: градиент по глубине
-                    let depth = (sea_level_norm - hc).max(0.0);
-                    let depth_norm = (depth / sea_level_norm).clamp(0.0, 1.0);
-                    let t = depth_norm;
-                    [
-                        (shallow[0] as f32 * (1.0 - t) + deep[0] as f32 * t) as u8,
-                        (shallow[1] as f32 * (1.0 - t) + deep[1] as f32 * t) as u8,
-                        (shallow[2] as f32 * (1.0 - t) + deep[2] as f32 * t) as u8,
-                    ]
-                }
-            };
+    /// Реки полилиниями через центры клеток (координаты в клетках);
+    /// `epsilon` — допуск упрощения в клетках, по умолчанию 0 — без него
+    #[wasm_bindgen]
+    pub fn river_polylines(&self, epsilon: Option<f32>) -> Polylines {
+        let rivers = extract_rivers(
+            &self.heightmap,
+            self.cfg.sea_level as f32,
+            epsilon.unwrap_or(0.0),
+        );
+        let discharge = rivers.iter().map(|r| r.discharge).collect();
+        let mut lines = Polylines::from_lines(rivers.into_iter().map(|r| r.points));
+        lines.discharge = discharge;
+        lines
+    }
 
-            // --- снеговые шапки ---
-            let lat = (y as f32 / (h_h - 1.0)) * 2.0 - 1.0;
-            let lat_abs = lat.abs();
+    /// Береговая линия моря по углам клеток: кольца замкнуты (первая точка
+    /// повторена в конце), у края карты линия обрывается
+    #[wasm_bindgen]
+    pub fn coastline_polygons(&self, epsilon: Option<f32>) -> Polylines {
+        Polylines::from_lines(extract_coastlines(
+            &self.heightmap,
+            self.cfg.sea_level as f32,
+            epsilon.unwrap_or(0.0),
+        ))
+    }
 
-            let snow_height_start = 0.7;
-            let snow_lat_start = 0.5;
+    /// Катастрофа `kind` (`earthquake`, `volcanic_eruption`, `meteor_impact`)
+    /// силы `magnitude` в точке (lat, lon) в градусах. Меняет рельеф и
+    /// пересчитывает биомы; возвращает изменённые чанки плоским массивом
+    /// `[x0, y0, w, h, ...]` в клетках
+    #[wasm_bindgen]
+    pub fn apply_catastrophe(
+        &mut self,
+        kind: &str,
+        lat: f64,
+        lon: f64,
+        magnitude: f64,
+    ) -> Result<Vec<u32>, SeedError> {
+        let kind = CatastropheType::from_id(kind)
+            .filter(|k| k.shapes_terrain())
+            .ok_or_else(|| {
+                SeedError::new(
+                    "invalid_argument",
+                    format!("Unsupported catastrophe type: {kind}"),
+                )
+            })?;
+        let cat = Catastrophe::at(&self.cfg, kind, (lat, lon), magnitude);
+        Ok(self.apply_events(&[cat]))
+    }
 
-            let height_factor =
-                ((hc - snow_height_start) / (1.0 - snow_height_start)).clamp(0.0, 1.0);
-            let lat_factor = ((lat_abs - snow_lat_start) / (1.0 - snow_lat_start)).clamp(0.0, 1.0);
+    /// Катастрофы за `years` лет из сида мира, как `seed-cli catastrophes
+    /// --apply`; возвращает изменённые чанки, как `apply_catastrophe`
+    #[wasm_bindgen]
+    pub fn generate_catastrophes(&mut self, years: f64) -> Vec<u32> {
+        let mut events = generate_catastrophes(&self.cfg, years, self.cfg.world_seed);
+        events.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+        self.apply_events(&events)
+    }
 
-            let snow_mask = (height_factor * lat_factor).clamp(0.0, 1.0);
+    /// Солнце над точкой `lat`, `lon` (градусы, север и восток
+    /// положительны) через `time_s` игровых секунд от начала мира — как в
+    /// часах сервера: год с весеннего равноденствия, полдень на долготе 0 —
+    /// середина суток
+    #[wasm_bindgen]
+    pub fn sun_position(&self, lat: f64, lon: f64, time_s: f64) -> SunPosition {
+        let astro = Astronomy::from_config(&self.cfg);
+        let sun = astro.sun(lat, lon, time_s);
+        let [x, y, z] = sun.direction();
+        SunPosition {
+            elevation_deg: sun.elevation_deg,
+            azimuth_deg: sun.azimuth_deg,
+            x,
+            y,
+            z,
+            insolation_w_m2: astro.insolation(lat, lon, time_s),
+        }
+    }
 
-            if snow_mask > 0.0 {
-                let s = snow_mask;
-                base_color[0] = (base_color[0] as f32 * (1.0 - s) + 255.0 * s) as u8;
-                base_color[1] = (base_color[1] as f32 * (1.0 - s) + 255.0 * s) as u8;
-                base_color[2] = (base_color[2] as f32 * (1.0 - s) + 255.0 * s) as u8;
-            }
+    /// Луны планеты над точкой; аргументы — как у `sun_position`
+    #[wasm_bindgen]
+    pub fn moons(&self, lat: f64, lon: f64, time_s: f64) -> Vec<MoonPosition> {
+        Astronomy::from_config(&self.cfg)
+            .moons(lat, lon, time_s)
+            .into_iter()
+            .map(|m| {
+                let [x, y, z] = m.direction();
+                MoonPosition {
+                    name: m.name,
+                    phase: m.phase,
+                    illumination: m.illumination,
+                    elevation_deg: m.elevation_deg,
+                    azimuth_deg: m.azimuth_deg,
+                    x,
+                    y,
+                    z,
+                }
+            })
+            .collect()
+    }
+}
 
-            let flow_val = flow[idx1];
-
-            // пляжи
-            if hc > sea_level_norm {
-                let dh = hc - sea_level_norm;
-                if dh > 0.0 && dh < beach_width {
-                    let t = (dh / beach_width).clamp(0.0, 1.0);
-                    let s = 1.0 - t;
-                    base_color[0] =
-                        (base_color[0] as f32 * (1.0 - s) + beach_color[0] as f32 * s) as u8;
-                    base_color[1] =
-                        (base_color[1] as f32 * (1.0 - s) + beach_color[1] as f32 * s) as u8;
-                    base_color[2] =
-                        (base_color[2] as f32 * (1.0 - s) + beach_color[2] as f32 * s) as u8;
+impl SeedWorld {
+    fn apply_events(&mut self, events: &[Catastrophe]) -> Vec<u32> {
+        let before_heights = self.heightmap.values.clone();
+        let before_biomes = self.biomemap.indices.clone();
+        for cat in events {
+            apply_catastrophe_to_heightmap(&mut self.heightmap, cat, &self.cfg);
+        }
+        self.biomemap = generate_biome_map_from_config(&self.cfg, &self.heightmap);
+
+        let (w, h) = (self.heightmap.width, self.heightmap.height);
+        let mut chunks = Vec::new();
+        for y0 in (0..h).step_by(CHUNK_CELLS as usize) {
+            for x0 in (0..w).step_by(CHUNK_CELLS as usize) {
+                let (cw, ch) = (CHUNK_CELLS.min(w - x0), CHUNK_CELLS.min(h - y0));
+                let changed = (y0..y0 + ch).any(|y| {
+                    let row = (y * w + x0) as usize..(y * w + x0 + cw) as usize;
+                    self.heightmap.values[row.clone()] != before_heights[row.clone()]
+                        || self.biomemap.indices[row.clone()] != before_biomes[row]
+                });
+                if changed {
+                    chunks.extend([x0, y0, cw, ch]);
                 }
             }
+        }
+        chunks
+    }
 
-            // реки
-            if hc > sea_level_norm && flow_val > 0.1 {
-                let t = ((flow_val - 0.1) / 0.9).clamp(0.0, 1.0);
-                let intensity = t.powf(0.4);
-
-                base_color[0] = (base_color[0] as f32 * (1.0 - intensity)
-                    + river_color[0] as f32 * intensity) as u8;
-                base_color[1] = (base_color[1] as f32 * (1.0 - intensity)
-                    + river_color[1] as f32 * intensity) as u8;
-                base_color[2] = (base_color[2] as f32 * (1.0 - intensity)
-                    + river_color[2] as f32 * intensity) as u8;
-            }
+    fn mesh_buffers(&self, region: Region, step: u32, vertical_scale: f32) -> MeshBuffers {
+        let (cfg, hm) = (&self.cfg, &self.heightmap);
+        let mesh = TerrainMesh::from_region(cfg, hm, region, step, vertical_scale);
+        let normals = TerrainMesh::region_normals(cfg, hm, region, step, vertical_scale);
+        MeshBuffers {
+            x0: region.x0,
+            y0: region.y0,
+            positions: mesh.positions.into_iter().flatten().collect(),
+            normals: normals.into_iter().flatten().collect(),
+            uvs: mesh.uvs.into_iter().flatten().collect(),
+            indices: mesh.indices,
+        }
+    }
+
+    fn nearest_cell(&self, x: f64, y: f64) -> (u32, u32) {
+        let clamp = |v: f64, size: u32| v.round().clamp(0.0, size.saturating_sub(1) as f64) as u32;
+        (
+            clamp(x, self.heightmap.width),
+            clamp(y, self.heightmap.height),
+        )
+    }
+}
 
-            // --- применяем освещение ---
-            let r = (base_color[0] as f32 * shade).round().clamp(0.0, 255.0) as u8;
-            let g = (base_color[1] as f32 * shade).round().clamp(0.0, 255.0) as u8;
-            let b = (base_color[2] as f32 * shade).round().clamp(0.0, 255.0) as u8;
+/// Шаг сетки уровня `lod`
+fn lod_step(lod: u32) -> Result<u32, SeedError> {
+    if lod >= 16 {
+        return Err(SeedError::new("invalid_argument", "lod must be below 16"));
+    }
+    Ok(1 << lod)
+}
+
+/// Буферы сетки рельефа для `THREE.BufferGeometry`: по 3 числа на вершину
+/// в `positions` и `normals`, по 2 — в `uvs` (0..1 по всей карте)
+#[wasm_bindgen]
+pub struct MeshBuffers {
+    x0: u32,
+    y0: u32,
+    positions: Vec<f32>,
+    normals: Vec<f32>,
+    uvs: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl MeshBuffers {
+    /// Северо-западная клетка участка
+    #[wasm_bindgen(getter)]
+    pub fn x0(&self) -> u32 {
+        self.x0
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn y0(&self) -> u32 {
+        self.y0
+    }
 
-            let idx = ((y * hm.width + x) * 4) as usize;
-            buf[idx] = r;
-            buf[idx + 1] = g;
-            buf[idx + 2] = b;
-            buf[idx + 3] = 255;
+    #[wasm_bindgen(getter)]
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn normals(&self) -> Vec<f32> {
+        self.normals.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn uvs(&self) -> Vec<f32> {
+        self.uvs.clone()
+    }
+
+    /// Треугольники против часовой стрелки при взгляде сверху
+    #[wasm_bindgen(getter)]
+    pub fn indices(&self) -> Vec<u32> {
+        self.indices.clone()
+    }
+}
+
+/// Набор линий одним буфером: точки линии `i` — пары (x, y) в `coords`
+/// с `offsets[i]` по `offsets[i + 1]` (номера точек, не чисел)
+#[wasm_bindgen]
+pub struct Polylines {
+    coords: Vec<f32>,
+    offsets: Vec<u32>,
+    discharge: Vec<f32>,
+}
+
+impl Polylines {
+    fn from_lines(lines: impl IntoIterator<Item = Vec<(f32, f32)>>) -> Self {
+        let mut coords = Vec::new();
+        let mut offsets = vec![0];
+        for line in lines {
+            coords.extend(line.into_iter().flat_map(|(x, y)| [x, y]));
+            offsets.push((coords.len() / 2) as u32);
         }
+        Polylines {
+            coords,
+            offsets,
+            discharge: Vec::new(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Polylines {
+    /// Число линий
+    #[wasm_bindgen(getter)]
+    pub fn count(&self) -> u32 {
+        self.offsets.len() as u32 - 1
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn coords(&self) -> Vec<f32> {
+        self.coords.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
     }
 
-    buf
+    /// Расход рек в устье участка, доля наибольшего стока; у берегов пуст
+    #[wasm_bindgen(getter)]
+    pub fn discharge(&self) -> Vec<f32> {
+        self.discharge.clone()
+    }
 }
 
-// --- палитра биомов ---
+/// Попадание луча (`SeedWorld::raycast`)
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// Нормаль поверхности, единичная
+    pub nx: f64,
+    pub ny: f64,
+    pub nz: f64,
+    /// От начала луча, м
+    pub distance: f64,
+    /// Попали в поверхность моря
+    pub water: bool,
+}
 
-pub fn build_biome_palette(cfg: &WorldConfig) -> Vec<[u8; 3]> {
-    cfg.biomes
-        .iter()
-        .map(|b| match b.id.as_str() {
-            "temperate_forest" => [45, 125, 45],    // Насыщенный зелёный лес
-            "hot_desert" => [218, 185, 110],        // Песчаный жёлто-коричневый
-            "cold_mountains" => [140, 145, 155],    // Серый камень с холодным оттенком
-            "tundra" => [135, 165, 145],            // Приглушенный сине-зелёный
-            "tropical_rainforest" => [20, 100, 35], // Тёмно-зелёный джунгли
-            "savanna" => [185, 165, 95],            // Сухая трава
-            "taiga" => [55, 100, 65],               // Хвойный лес
-            "ice_sheet" => [240, 248, 255],         // Белый лёд
-            "wetland" => [90, 120, 100],            // Болотистый зелёно-серый
-            "grassland" => [140, 170, 90],          // Светло-зелёная трава
-            "shrubland" => [160, 140, 100],         // Кустарник коричнево-зелёный
-            "mediterranean" => [170, 180, 110],     // Средиземноморский
-            _ => {
-                // Генерация уникального цвета на основе имени биома
-                let mut h = simple_hash(&b.id) as u64;
-
-                // Более натуральные цвета (избегаем слишком яркие/кислотные)
-                let r = 70 + ((h & 0xFF) as u8) / 2; // 70..197
-                h >>= 8;
-                let g = 70 + ((h & 0xFF) as u8) / 2; // 70..197
-                h >>= 8;
-                let bl = 70 + ((h & 0xFF) as u8) / 2; // 70..197
-                [r, g, bl]
-            }
-        })
-        .collect()
+/// Солнце над точкой (`SeedWorld::sun_position`)
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct SunPosition {
+    /// Над горизонтом, градусы; ниже 0 — ночь
+    pub elevation_deg: f64,
+    /// По часовой стрелке от севера, градусы
+    pub azimuth_deg: f64,
+    /// Единичный вектор на солнце: x — восток, y — вверх, z — север
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// Поток от звезды на горизонтальную площадку над атмосферой, Вт/м²
+    pub insolation_w_m2: f64,
+}
+
+/// Луна над точкой (`SeedWorld::moons`)
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct MoonPosition {
+    name: String,
+    /// 0 — новолуние, 0.5 — полнолуние
+    pub phase: f64,
+    /// Освещённая доля диска
+    pub illumination: f64,
+    pub elevation_deg: f64,
+    pub azimuth_deg: f64,
+    /// Единичный вектор на луну, как у `SunPosition`
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
 }
 
-fn simple_hash(s: &str) -> u32 {
-    let mut h = 0u32;
-    for b in s.bytes() {
-        h = h.wrapping_mul(31).wrapping_add(b as u32);
+#[wasm_bindgen]
+impl MoonPosition {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
     }
-    h
 }
 
-fn normalize3(x: f32, y: f32, z: f32) -> (f32, f32, f32) {
-    let len = (x * x + y * y + z * z).sqrt().max(1e-6);
-    (x / len, y / len, z / len)
+/// Климат в точке карты (`SeedWorld::climate_at`)
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct Climate {
+    pub temperature_c: f64,
+    /// 0..1
+    pub humidity: f64,
+    pub precipitation_mm_per_year: f64,
+    /// Высота над уровнем моря, м; над морем 0
+    pub elevation_m: f64,
+}
+
+/// Доли этапов в общем прогрессе `SeedWorldBuilder`
+const NOISE_SHARE: f32 = 0.5;
+const EROSION_SHARE: f32 = 0.3;
+
+enum Stage {
+    // шум внутри строителя большой — держим в куче
+    Heightmap(Box<HeightmapBuilder>),
+    Biomes(Heightmap, BiomeMapBuilder),
+    Done(Heightmap, BiomeMap),
+}
+
+/// Тот же мир, что у `new SeedWorld(...)`, но по кускам, чтобы страница не
+/// замирала: JS вызывает `step` между кадрами, показывает `progress` и,
+/// когда `step` вернул true, забирает мир `finish`. Ни он, ни `SeedWorld`
+/// не трогают DOM, так что генерацию можно целиком увести в Worker
+/// (`web/worker.js`).
+#[wasm_bindgen]
+pub struct SeedWorldBuilder {
+    cfg: WorldConfig,
+    stage: Option<Stage>,
+}
+
+#[wasm_bindgen]
+impl SeedWorldBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new(config_json: &str, width: u32, height: u32) -> Result<SeedWorldBuilder, SeedError> {
+        let cfg = parse_config(config_json)?;
+        let stage = Stage::Heightmap(Box::new(HeightmapBuilder::new(&cfg, width, height)));
+        Ok(SeedWorldBuilder {
+            cfg,
+            stage: Some(stage),
+        })
+    }
+
+    /// Следующий кусок работы: до `rows` строк шума или биомов либо один
+    /// проход эрозии по всей карте. true — мир готов
+    #[wasm_bindgen]
+    pub fn step(&mut self, rows: u32) -> bool {
+        let stage = match self.stage.take() {
+            Some(Stage::Heightmap(mut hb)) => {
+                if hb.step(rows) {
+                    let hm = hb.finish().expect("heightmap is done");
+                    let bb = BiomeMapBuilder::new(&hm);
+                    Stage::Biomes(hm, bb)
+                } else {
+                    Stage::Heightmap(hb)
+                }
+            }
+            Some(Stage::Biomes(hm, mut bb)) => {
+                if bb.step(&self.cfg, &hm, rows) {
+                    let bm = bb.finish(&self.cfg).expect("biomes are done");
+                    Stage::Done(hm, bm)
+                } else {
+                    Stage::Biomes(hm, bb)
+                }
+            }
+            stage => {
+                self.stage = stage;
+                return self.done();
+            }
+        };
+        self.stage = Some(stage);
+        self.done()
+    }
+
+    /// Мир готов
+    #[wasm_bindgen(getter)]
+    pub fn done(&self) -> bool {
+        matches!(self.stage, Some(Stage::Done(..)))
+    }
+
+    /// Этап: "noise", "erosion", "biomes" или "done"
+    #[wasm_bindgen(getter)]
+    pub fn phase(&self) -> String {
+        match &self.stage {
+            Some(Stage::Heightmap(hb)) => hb.progress().0.id().to_string(),
+            Some(Stage::Biomes(..)) => GenerationPhase::Biomes.id().to_string(),
+            _ => "done".to_string(),
+        }
+    }
+
+    /// Доля всей работы, 0..1
+    #[wasm_bindgen(getter)]
+    pub fn progress(&self) -> f32 {
+        match &self.stage {
+            Some(Stage::Heightmap(hb)) => match hb.progress() {
+                (GenerationPhase::Noise, done) => done * NOISE_SHARE,
+                (_, done) => NOISE_SHARE + done * EROSION_SHARE,
+            },
+            Some(Stage::Biomes(_, bb)) => {
+                let biomes_share = 1.0 - NOISE_SHARE - EROSION_SHARE;
+                NOISE_SHARE + EROSION_SHARE + bb.progress() * biomes_share
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Готовый мир; ошибка, если `step` ещё не закончил
+    #[wasm_bindgen]
+    pub fn finish(self) -> Result<SeedWorld, SeedError> {
+        match self.stage {
+            Some(Stage::Done(heightmap, biomemap)) => Ok(SeedWorld {
+                cfg: self.cfg,
+                heightmap,
+                biomemap,
+                tiles: None,
+            }),
+            _ => Err(SeedError::new("not_ready", "world is not generated yet")),
+        }
+    }
 }
@@ -1,4 +1,22 @@
-import init, { SeedWorld } from "./pkg/seed_wasm.js";
+import init, { SeedWorld, SeedWorldBuilder } from "./pkg/seed_wasm.js";
+
+// Снимки мира в IndexedDB: ключ — размер карты
+function openCache() {
+    return new Promise((resolve, reject) => {
+        const req = indexedDB.open("seed-worlds", 1);
+        req.onupgradeneeded = () => req.result.createObjectStore("worlds");
+        req.onsuccess = () => resolve(req.result);
+        req.onerror = () => reject(req.error);
+    });
+}
+
+function cacheRequest(db, mode, fn) {
+    return new Promise((resolve, reject) => {
+        const req = fn(db.transaction("worlds", mode).objectStore("worlds"));
+        req.onsuccess = () => resolve(req.result);
+        req.onerror = () => reject(req.error);
+    });
+}
 
 async function run() {
     // 1. Загружаем конфиг
@@ -8,29 +26,96 @@ async function run() {
     // 2. Инициализируем wasm-модуль
     await init();
 
-    // 3. Создаём мир (размер — какой хочешь)
+    // 3. Создаём мир (размер — какой хочешь) по кускам между кадрами,
+    //    чтобы страница не замирала
     const width = 1024;
     const height = 512;
-    const world = new SeedWorld(configText, width, height);
+    //    Мир с тем же конфигом берём из кэша, если он там есть
+    const bar = document.getElementById("progress");
+    const key = `${width}x${height}`;
+    const db = await openCache().catch(() => null);
+    let world = null;
+    const cached = db && (await cacheRequest(db, "readonly", (s) => s.get(key)).catch(() => null));
+    if (cached) {
+        try {
+            world = SeedWorld.from_bytes(new Uint8Array(cached), configText);
+        } catch (err) {
+            console.log(`cached world is not used (${err.code}): ${err}`);
+        }
+    }
+    if (!world) {
+        const builder = new SeedWorldBuilder(configText, width, height);
+        while (!builder.step(32)) {
+            bar.value = builder.progress;
+            await new Promise(requestAnimationFrame);
+        }
+        world = builder.finish();
+        if (db) {
+            const bytes = world.to_bytes();
+            await cacheRequest(db, "readwrite", (s) => s.put(bytes, key)).catch(() => {});
+        }
+    }
+    bar.remove();
 
-    // 4. Получаем RGBA буфер
-    const rgba = world.worldview_rgba();
+    // 4. Рисуем RGBA буфер в canvas с выбранными слоями
     const w = world.width;
     const h = world.height;
-
-    // 5. Рисуем в canvas
     const canvas = document.getElementById("world");
     canvas.width = w;
     canvas.height = h;
-
     const ctx = canvas.getContext("2d");
-    const imgData = new ImageData(new Uint8ClampedArray(rgba), w, h);
-    ctx.putImageData(imgData, 0, 0);
+
+    const layers = { biomes: true, relief: true, rivers: true, snow: true, coast: true };
+    const draw = () => {
+        const rgba = world.worldview_rgba(JSON.stringify({ layers }));
+        const imgData = new ImageData(new Uint8ClampedArray(rgba), w, h);
+        ctx.putImageData(imgData, 0, 0);
+    };
+
+    // 5. Галочки слоёв
+    const toggles = document.getElementById("layers");
+    for (const name of Object.keys(layers)) {
+        const label = document.createElement("label");
+        const box = document.createElement("input");
+        box.type = "checkbox";
+        box.checked = layers[name];
+        box.addEventListener("change", () => {
+            layers[name] = box.checked;
+            draw();
+        });
+        label.append(box, ` ${name} `);
+        toggles.appendChild(label);
+    }
+    draw();
+
+    // 6. Подсказка под курсором: биом и климат точки
+    canvas.addEventListener("mousemove", (e) => {
+        const x = (e.offsetX * w) / canvas.clientWidth;
+        const y = (e.offsetY * h) / canvas.clientHeight;
+        const c = world.climate_at(x, y);
+        const biome = world.biome_id_at(x, y) ?? "water";
+        canvas.title =
+            `${biome}, ${c.elevation_m.toFixed(0)} m\n` +
+            `${c.temperature_c.toFixed(1)} °C, humidity ${c.humidity.toFixed(2)}, ` +
+            `${c.precipitation_mm_per_year.toFixed(0)} mm/year`;
+        c.free();
+    });
+
+    // 7. Shift+щелчок — метеорит в точке щелчка
+    canvas.addEventListener("click", (e) => {
+        if (!e.shiftKey) return;
+        const lat = (e.offsetY / canvas.clientHeight) * 180 - 90;
+        const lon = (e.offsetX / canvas.clientWidth) * 360 - 180;
+        const chunks = world.apply_catastrophe("meteor_impact", lat, lon, 80);
+        console.log(`meteor_impact: ${chunks.length / 4} chunks changed`);
+        draw();
+    });
 }
 
 run().catch((err) => {
     console.error(err);
+    // SeedError из seed-wasm: path указывает на поле конфига с ошибкой
     const pre = document.createElement("pre");
-    pre.textContent = String(err);
+    pre.textContent = err.path ? `${err.path}: ${err.message}` : String(err);
     document.body.appendChild(pre);
 });
@@ -0,0 +1,47 @@
+// Генерация мира в Worker, чтобы главный поток не ждал ни шага. Модульный
+// Worker: new Worker("./worker.js", { type: "module" }).
+//
+// Вход: { configText, width, height, rows? }.
+// Выход: { type: "progress", phase, progress } по ходу генерации, затем
+// { type: "done", width, height, rgba, snapshot } — картинка worldview и
+// снимок мира (SeedWorld.from_bytes на главном потоке) — или
+// { type: "error", code, message, path } — поля SeedError. Буферы передаются без копирования.
+//
+// Если pkg собран с потоками (crates/seed-wasm/build-threads.sh), а страница
+// изолирована (COOP/COEP, `seed-server --cross-origin-isolation`), Worker
+// поднимает пул rayon на все ядра; иначе генерация идёт в одном потоке.
+import init, * as wasm from "./pkg/seed_wasm.js";
+
+const { SeedWorldBuilder } = wasm;
+
+const ready = init().then(() =>
+    wasm.initThreadPool && self.crossOriginIsolated
+        ? wasm.initThreadPool(navigator.hardwareConcurrency)
+        : undefined,
+);
+
+self.onmessage = async (e) => {
+    const { configText, width, height, rows = 64 } = e.data;
+    try {
+        await ready;
+        const builder = new SeedWorldBuilder(configText, width, height);
+        while (!builder.step(rows)) {
+            self.postMessage({ type: "progress", phase: builder.phase, progress: builder.progress });
+        }
+        const world = builder.finish();
+        const rgba = world.worldview_rgba();
+        const snapshot = world.to_bytes();
+        self.postMessage(
+            { type: "done", width: world.width, height: world.height, rgba, snapshot },
+            [rgba.buffer, snapshot.buffer],
+        );
+        world.free();
+    } catch (err) {
+        self.postMessage({
+            type: "error",
+            code: err.code ?? "internal",
+            message: err.message ?? String(err),
+            path: err.path ?? null,
+        });
+    }
+};